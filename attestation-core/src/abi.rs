@@ -0,0 +1,206 @@
+//! Solidity ABI encodings for the on-chain registry.
+//!
+//! Produces calldata and hashes in exactly the layout expected by
+//! `smart-contracts/contracts/RobotAttestationRegistry.sol` and
+//! `VeribotMerkle.sol`, so a proof that verifies off-chain verifies on-chain.
+//! Typed contract bindings live in the `veribot-bindings` crate, whose tests
+//! decode this module's output to keep both sides in lockstep.
+//!
+//! ## Layout
+//! - `Entry` is the static tuple `(uint64 timestampUs, uint64 nonce, bytes32 dataHash)`
+//! - Leaf preimage is `abi.encodePacked(timestampUs, nonce, dataHash)` (48 bytes),
+//!   identical to the preimage of [`Entry::hash`]
+//! - An anchored checkpoint's `merkleRoot` is the checkpoint's `entries_root`
+
+use crate::checkpoint::Checkpoint;
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::merkle::{Entry, MerkleProof};
+use crate::types::Hash256;
+use sha3::{Digest, Keccak256};
+use thiserror::Error;
+
+/// `RobotAttestationRegistry.verifyEntry`
+pub const VERIFY_ENTRY_SIGNATURE: &str =
+    "verifyEntry(bytes32,(uint64,uint64,bytes32),uint256,bytes32[])";
+
+/// `RobotAttestationRegistry.anchorCheckpoint`
+pub const ANCHOR_CHECKPOINT_SIGNATURE: &str = "anchorCheckpoint(bytes32,bytes32,string,bytes)";
+
+#[derive(Debug, Error)]
+pub enum AbiError {
+    #[error("Enclave measurement must be 32 bytes for on-chain anchoring, got {0}")]
+    MeasurementLength(usize),
+}
+
+impl ErrorCoded for AbiError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            AbiError::MeasurementLength(_) => ErrorCode::AbiEncode,
+        }
+    }
+}
+
+/// Keccak-256, as used by Solidity.
+pub fn keccak256(data: &[u8]) -> Hash256 {
+    Keccak256::digest(data).into()
+}
+
+/// 4-byte function selector for a canonical signature.
+pub fn selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// `abi.encodePacked(timestampUs, nonce, dataHash)`: the leaf hash preimage.
+pub fn encode_packed_entry(entry: &Entry) -> [u8; 48] {
+    let mut out = [0u8; 48];
+    out[..8].copy_from_slice(&entry.timestamp_us.to_be_bytes());
+    out[8..16].copy_from_slice(&entry.nonce.to_be_bytes());
+    out[16..].copy_from_slice(&entry.data_hash);
+    out
+}
+
+/// `abi.encode(entry)` for the static `Entry` tuple.
+pub fn encode_entry(entry: &Entry) -> [u8; 96] {
+    let mut out = [0u8; 96];
+    out[..32].copy_from_slice(&uint_word(entry.timestamp_us));
+    out[32..64].copy_from_slice(&uint_word(entry.nonce));
+    out[64..].copy_from_slice(&entry.data_hash);
+    out
+}
+
+/// Calldata for `verifyEntry(checkpointId, entry, leafIndex, siblings)`.
+pub fn verify_entry_calldata(checkpoint_id: &Hash256, proof: &MerkleProof) -> Vec<u8> {
+    // Head: checkpointId, entry (3 words, static), leafIndex, offset of siblings
+    const HEAD_WORDS: u64 = 6;
+
+    let mut out = selector(VERIFY_ENTRY_SIGNATURE).to_vec();
+    out.extend_from_slice(checkpoint_id);
+    out.extend_from_slice(&encode_entry(&proof.leaf));
+    out.extend_from_slice(&uint_word(proof.leaf_index as u64));
+    out.extend_from_slice(&uint_word(HEAD_WORDS * 32));
+    out.extend_from_slice(&uint_word(proof.siblings.len() as u64));
+    for sibling in &proof.siblings {
+        out.extend_from_slice(sibling);
+    }
+    out
+}
+
+/// Calldata for `anchorCheckpoint(merkleRoot, enclaveMeasurement, vendor, gatewaySignature)`.
+pub fn anchor_checkpoint_calldata(
+    checkpoint: &Checkpoint,
+    vendor: &str,
+    gateway_signature: &[u8],
+) -> Result<Vec<u8>, AbiError> {
+    // Head: merkleRoot, enclaveMeasurement, offset of vendor, offset of signature
+    const HEAD_WORDS: u64 = 4;
+
+    let measurement = measurement_word(&checkpoint.enclave_measurement)?;
+    let vendor_tail = encode_bytes(vendor.as_bytes());
+    let signature_offset = HEAD_WORDS * 32 + vendor_tail.len() as u64;
+
+    let mut out = selector(ANCHOR_CHECKPOINT_SIGNATURE).to_vec();
+    out.extend_from_slice(&checkpoint.entries_root);
+    out.extend_from_slice(&measurement);
+    out.extend_from_slice(&uint_word(HEAD_WORDS * 32));
+    out.extend_from_slice(&uint_word(signature_offset));
+    out.extend_from_slice(&vendor_tail);
+    out.extend_from_slice(&encode_bytes(gateway_signature));
+    Ok(out)
+}
+
+/// The `checkpointId` the registry assigns in `anchorCheckpoint`:
+/// `keccak256(abi.encodePacked(merkleRoot, enclaveMeasurement, gateway, block.timestamp, counter))`.
+pub fn checkpoint_id(
+    merkle_root: &Hash256,
+    enclave_measurement: &Hash256,
+    gateway: &[u8; 20],
+    block_timestamp: u64,
+    counter: u64,
+) -> Hash256 {
+    let mut packed = Vec::with_capacity(32 + 32 + 20 + 32 + 32);
+    packed.extend_from_slice(merkle_root);
+    packed.extend_from_slice(enclave_measurement);
+    packed.extend_from_slice(gateway);
+    packed.extend_from_slice(&uint_word(block_timestamp));
+    packed.extend_from_slice(&uint_word(counter));
+    keccak256(&packed)
+}
+
+/// A checkpoint's enclave measurement as `bytes32`.
+pub fn measurement_word(measurement: &[u8]) -> Result<Hash256, AbiError> {
+    measurement
+        .try_into()
+        .map_err(|_| AbiError::MeasurementLength(measurement.len()))
+}
+
+/// Left-padded big-endian `uint256` word.
+fn uint_word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Length word followed by the data, right-padded to a whole word.
+fn encode_bytes(data: &[u8]) -> Vec<u8> {
+    let mut out = uint_word(data.len() as u64).to_vec();
+    out.extend_from_slice(data);
+    out.resize(32 + data.len().div_ceil(32) * 32, 0);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::MerkleTree;
+
+    #[test]
+    fn test_selectors() {
+        // Well-known selector, as a check on the Keccak variant
+        assert_eq!(
+            selector("transfer(address,uint256)"),
+            [0xa9, 0x05, 0x9c, 0xbb]
+        );
+        assert_eq!(
+            selector(VERIFY_ENTRY_SIGNATURE),
+            selector("verifyEntry(bytes32,(uint64,uint64,bytes32),uint256,bytes32[])")
+        );
+    }
+
+    #[test]
+    fn test_packed_entry_is_leaf_preimage() {
+        let entry = Entry::new(1_700_000_000_000_000, 7, b"data");
+        assert_eq!(
+            crate::crypto::sha256(&encode_packed_entry(&entry)),
+            entry.hash()
+        );
+    }
+
+    #[test]
+    fn test_verify_entry_calldata_layout() {
+        let mut tree = MerkleTree::new();
+        for i in 0..3u64 {
+            tree.insert(Entry::new(1000 * (i + 1), i, &[i as u8]));
+        }
+        let proof = tree.generate_proof(3000, 2).unwrap();
+        let calldata = verify_entry_calldata(&[0xAA; 32], &proof);
+
+        assert_eq!(calldata.len(), 4 + 32 * (6 + 1 + proof.siblings.len()));
+        assert_eq!(&calldata[4..36], &[0xAA; 32]);
+        assert_eq!(calldata[4 + 32 * 4 + 31], 2); // leafIndex
+        assert_eq!(calldata[4 + 32 * 5 + 31], 6 * 32); // siblings offset
+        assert_eq!(calldata[4 + 32 * 6 + 31], proof.siblings.len() as u8);
+    }
+
+    #[test]
+    fn test_encode_bytes_padding() {
+        assert_eq!(encode_bytes(b"").len(), 32);
+        assert_eq!(encode_bytes(b"intel-sgx").len(), 64);
+        assert_eq!(encode_bytes(&[1u8; 32]).len(), 64);
+        assert_eq!(encode_bytes(&[1u8; 33]).len(), 96);
+        assert!(matches!(
+            measurement_word(&[0u8; 48]),
+            Err(AbiError::MeasurementLength(48))
+        ));
+    }
+}
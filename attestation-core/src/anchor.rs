@@ -0,0 +1,583 @@
+//! Anchor transaction tracking.
+//!
+//! Submitting `anchorCheckpoint` is not evidence: the transaction can be
+//! dropped from the mempool, revert, or land in a block that is later
+//! reorganized away. An [`AnchorTracker`] follows every anchor transaction
+//! through an [`AnchorChain`] client until its block is buried under the
+//! configured confirmation depth, and only then records an
+//! [`AnchorReceipt`]. Transactions that revert or are not mined within
+//! [`AnchorConfig::drop_after_blocks`] are marked failed and can be retried
+//! up to [`AnchorConfig::max_attempts`] times.
+//!
+//! Confirmation depth makes reorganizations unlikely, not impossible. Every
+//! poll re-checks the blocks of confirmed anchors; when one is no longer on
+//! the canonical chain the record is marked [`AnchorStatus::Superseded`]
+//! with the orphaned block, and the root is re-anchored under a new record
+//! that links back to it (reusing the original transaction if the new
+//! chain mined it too). [`AnchorTracker::lineage`] walks that linkage, so a
+//! receipt issued before the reorg resolves to what replaced it.
+//!
+//! The tracker holds no chain state of its own beyond its records, which
+//! serialize so a gateway can persist them across restarts.
+
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::forensic::AnchorReceipt;
+use crate::types::Hash256;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Client for the chain hosting the `RobotAttestationRegistry`.
+pub trait AnchorChain {
+    /// Number of the latest block.
+    fn head(&self) -> Result<u64, AnchorError>;
+
+    /// Where `transaction_hash` was mined, if it was.
+    fn inclusion(&self, transaction_hash: &Hash256) -> Result<Option<TxInclusion>, AnchorError>;
+
+    /// Hash of the canonical block at `number`, if the chain reached it.
+    fn block_hash(&self, number: u64) -> Result<Option<Hash256>, AnchorError>;
+
+    /// Send a registry call; returns the transaction hash.
+    fn submit(&mut self, calldata: &[u8]) -> Result<Hash256, AnchorError>;
+}
+
+/// A mined transaction as reported by the chain client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxInclusion {
+    pub block_number: u64,
+    pub block_hash: Hash256,
+    pub block_timestamp: u64,
+    /// Whether the call succeeded (a reverted call is still mined)
+    pub succeeded: bool,
+    /// `checkpointId` from the `CheckpointAnchored` event, if emitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint_id: Option<Hash256>,
+}
+
+/// Where and how deeply anchors must land.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnchorConfig {
+    pub chain_id: u64,
+    /// Address of the `RobotAttestationRegistry` contract
+    pub registry: [u8; 20],
+    /// Blocks (including the inclusion block) before an anchor counts
+    pub confirmation_depth: u64,
+    /// Blocks after submission before an unmined transaction counts as dropped
+    pub drop_after_blocks: u64,
+    /// Submissions allowed per root, the first one included
+    pub max_attempts: u32,
+}
+
+impl AnchorConfig {
+    pub fn new(chain_id: u64, registry: [u8; 20]) -> Self {
+        Self {
+            chain_id,
+            registry,
+            confirmation_depth: 12,
+            drop_after_blocks: 50,
+            max_attempts: 3,
+        }
+    }
+}
+
+/// Lifecycle of an anchor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnchorStatus {
+    /// Submitted, not yet mined or not yet deep enough
+    Pending,
+    /// Mined and buried under the confirmation depth
+    Confirmed,
+    /// Reverted or dropped; may be retried
+    Failed,
+    /// Was confirmed, then its block was reorganized away
+    Superseded,
+}
+
+/// A confirmed anchor's block that left the canonical chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Orphaned {
+    pub block_number: u64,
+    pub block_hash: Hash256,
+    /// Canonical block at that height when the reorg was detected, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_hash: Option<Hash256>,
+    /// Chain head when the reorg was detected
+    pub detected_at_block: u64,
+    /// Position in [`AnchorTracker::records`] of the record re-anchoring the root
+    pub superseded_by: usize,
+}
+
+/// Tracking state of one anchored root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnchorRecord {
+    /// Anchored Merkle root (a checkpoint's `entries_root`)
+    pub merkle_root: Hash256,
+    /// `anchorCheckpoint` calldata, kept for retries
+    pub calldata: Vec<u8>,
+    /// Latest submission
+    pub transaction_hash: Hash256,
+    /// Chain head when the latest submission was sent
+    pub submitted_at_block: u64,
+    pub attempts: u32,
+    pub status: AnchorStatus,
+    /// Confirmations seen at the last poll
+    pub confirmations: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inclusion: Option<TxInclusion>,
+    /// Set once confirmed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<AnchorReceipt>,
+    /// Why the latest submission failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+    /// Set when a reorg orphaned this anchor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orphaned: Option<Orphaned>,
+    /// Position in [`AnchorTracker::records`] of the record this one re-anchors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supersedes: Option<usize>,
+}
+
+/// Follows anchor transactions to confirmation.
+#[derive(Debug, Clone)]
+pub struct AnchorTracker {
+    config: AnchorConfig,
+    records: Vec<AnchorRecord>,
+}
+
+impl AnchorTracker {
+    pub fn new(config: AnchorConfig) -> Self {
+        Self::from_records(config, Vec::new())
+    }
+
+    /// Resume tracking persisted records.
+    pub fn from_records(config: AnchorConfig, records: Vec<AnchorRecord>) -> Self {
+        Self { config, records }
+    }
+
+    pub fn config(&self) -> &AnchorConfig {
+        &self.config
+    }
+
+    /// All records, in submission order. Records are only ever appended, so
+    /// positions are stable.
+    pub fn records(&self) -> &[AnchorRecord] {
+        &self.records
+    }
+
+    /// The current record for `merkle_root`, if it was ever submitted.
+    pub fn get(&self, merkle_root: &Hash256) -> Option<&AnchorRecord> {
+        self.current(merkle_root).map(|i| &self.records[i])
+    }
+
+    /// The record of `transaction_hash` followed by every record that
+    /// superseded it, oldest first. Empty if the transaction is unknown.
+    pub fn lineage(&self, transaction_hash: &Hash256) -> Vec<&AnchorRecord> {
+        let mut lineage = Vec::new();
+        let mut next = self
+            .records
+            .iter()
+            .position(|r| &r.transaction_hash == transaction_hash);
+        while let Some(i) = next {
+            lineage.push(&self.records[i]);
+            next = self.records[i].orphaned.as_ref().map(|o| o.superseded_by);
+        }
+        lineage
+    }
+
+    /// Records currently in `status`.
+    pub fn with_status(&self, status: AnchorStatus) -> impl Iterator<Item = &AnchorRecord> {
+        self.records.iter().filter(move |r| r.status == status)
+    }
+
+    /// Submit `calldata` anchoring `merkle_root` and start tracking it.
+    pub fn submit(
+        &mut self,
+        chain: &mut dyn AnchorChain,
+        merkle_root: Hash256,
+        calldata: Vec<u8>,
+    ) -> Result<Hash256, AnchorError> {
+        if self.get(&merkle_root).is_some() {
+            return Err(AnchorError::AlreadyTracked(hex::encode(merkle_root)));
+        }
+        let submitted_at_block = chain.head()?;
+        let transaction_hash = chain.submit(&calldata)?;
+        self.records.push(AnchorRecord {
+            merkle_root,
+            calldata,
+            transaction_hash,
+            submitted_at_block,
+            attempts: 1,
+            status: AnchorStatus::Pending,
+            confirmations: 0,
+            inclusion: None,
+            receipt: None,
+            failure: None,
+            orphaned: None,
+            supersedes: None,
+        });
+        Ok(transaction_hash)
+    }
+
+    /// Check confirmed anchors for reorgs and pending anchors for progress.
+    ///
+    /// Re-anchors orphaned roots and returns the roots whose status changed.
+    pub fn poll(&mut self, chain: &mut dyn AnchorChain) -> Result<Vec<Hash256>, AnchorError> {
+        let head = chain.head()?;
+        let mut changed = self.reanchor_orphaned(chain, head)?;
+        for record in self
+            .records
+            .iter_mut()
+            .filter(|r| r.status == AnchorStatus::Pending)
+        {
+            let inclusion = chain.inclusion(&record.transaction_hash)?;
+            match inclusion {
+                None => {
+                    record.confirmations = 0;
+                    record.inclusion = None;
+                    if head.saturating_sub(record.submitted_at_block)
+                        >= self.config.drop_after_blocks
+                    {
+                        record.status = AnchorStatus::Failed;
+                        record.failure = Some(format!(
+                            "not mined within {} blocks",
+                            self.config.drop_after_blocks
+                        ));
+                    }
+                }
+                Some(inclusion) if !inclusion.succeeded => {
+                    record.status = AnchorStatus::Failed;
+                    record.failure = Some(format!("reverted in block {}", inclusion.block_number));
+                    record.inclusion = Some(inclusion);
+                }
+                Some(inclusion) => {
+                    record.confirmations = (head + 1).saturating_sub(inclusion.block_number);
+                    if record.confirmations >= self.config.confirmation_depth {
+                        record.status = AnchorStatus::Confirmed;
+                        record.receipt = Some(AnchorReceipt {
+                            chain_id: self.config.chain_id,
+                            registry: self.config.registry,
+                            transaction_hash: record.transaction_hash,
+                            block_number: inclusion.block_number,
+                            block_timestamp: inclusion.block_timestamp,
+                            checkpoint_id: inclusion.checkpoint_id.unwrap_or_default(),
+                            merkle_root: record.merkle_root,
+                        });
+                    }
+                    record.inclusion = Some(inclusion);
+                }
+            }
+            if record.status != AnchorStatus::Pending && !changed.contains(&record.merkle_root) {
+                changed.push(record.merkle_root);
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Resubmit the failed anchor of `merkle_root`.
+    pub fn retry(
+        &mut self,
+        chain: &mut dyn AnchorChain,
+        merkle_root: &Hash256,
+    ) -> Result<Hash256, AnchorError> {
+        let max_attempts = self.config.max_attempts;
+        let record = self
+            .current(merkle_root)
+            .map(|i| &mut self.records[i])
+            .filter(|r| r.status == AnchorStatus::Failed)
+            .ok_or_else(|| AnchorError::NotRetryable(hex::encode(merkle_root)))?;
+        if record.attempts >= max_attempts {
+            return Err(AnchorError::AttemptsExhausted(record.attempts));
+        }
+
+        record.submitted_at_block = chain.head()?;
+        record.transaction_hash = chain.submit(&record.calldata)?;
+        record.attempts += 1;
+        record.status = AnchorStatus::Pending;
+        record.confirmations = 0;
+        record.inclusion = None;
+        record.failure = None;
+        Ok(record.transaction_hash)
+    }
+
+    fn current(&self, merkle_root: &Hash256) -> Option<usize> {
+        self.records
+            .iter()
+            .rposition(|r| &r.merkle_root == merkle_root && r.status != AnchorStatus::Superseded)
+    }
+
+    /// Supersede confirmed anchors whose block left the canonical chain and
+    /// track a replacement for each.
+    fn reanchor_orphaned(
+        &mut self,
+        chain: &mut dyn AnchorChain,
+        head: u64,
+    ) -> Result<Vec<Hash256>, AnchorError> {
+        let mut reanchored = Vec::new();
+        for i in 0..self.records.len() {
+            let record = &self.records[i];
+            let Some(inclusion) = record
+                .inclusion
+                .as_ref()
+                .filter(|_| record.status == AnchorStatus::Confirmed)
+            else {
+                continue;
+            };
+            let canonical_hash = chain.block_hash(inclusion.block_number)?;
+            if canonical_hash == Some(inclusion.block_hash) {
+                continue;
+            }
+
+            // The new chain may have mined the same transaction; otherwise
+            // send it again.
+            let mined = chain.inclusion(&record.transaction_hash)?.is_some();
+            let transaction_hash = if mined {
+                record.transaction_hash
+            } else {
+                chain.submit(&record.calldata)?
+            };
+            let replacement = AnchorRecord {
+                merkle_root: record.merkle_root,
+                calldata: record.calldata.clone(),
+                transaction_hash,
+                submitted_at_block: head,
+                attempts: record.attempts + u32::from(!mined),
+                status: AnchorStatus::Pending,
+                confirmations: 0,
+                inclusion: None,
+                receipt: None,
+                failure: None,
+                orphaned: None,
+                supersedes: Some(i),
+            };
+            let orphaned = Orphaned {
+                block_number: inclusion.block_number,
+                block_hash: inclusion.block_hash,
+                canonical_hash,
+                detected_at_block: head,
+                superseded_by: self.records.len(),
+            };
+            self.records.push(replacement);
+            let record = &mut self.records[i];
+            record.status = AnchorStatus::Superseded;
+            record.orphaned = Some(orphaned);
+            reanchored.push(record.merkle_root);
+        }
+        Ok(reanchored)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AnchorError {
+    #[error("Chain client failed: {0}")]
+    Chain(String),
+
+    #[error("Root {0} is already tracked")]
+    AlreadyTracked(String),
+
+    #[error("No failed anchor for root {0}")]
+    NotRetryable(String),
+
+    #[error("Anchor was already submitted {0} times")]
+    AttemptsExhausted(u32),
+}
+
+impl ErrorCoded for AnchorError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            AnchorError::Chain(_) => ErrorCode::AnchorChainUnavailable,
+            AnchorError::AlreadyTracked(_) => ErrorCode::AnchorAlreadyTracked,
+            AnchorError::NotRetryable(_) => ErrorCode::AnchorNotRetryable,
+            AnchorError::AttemptsExhausted(_) => ErrorCode::AnchorAttemptsExhausted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Chain where the test decides when and how transactions are mined.
+    #[derive(Default)]
+    struct FakeChain {
+        head: u64,
+        submitted: Vec<Vec<u8>>,
+        mined: HashMap<Hash256, TxInclusion>,
+        /// Changes every block hash, like a reorg back to genesis
+        fork: u8,
+    }
+
+    impl FakeChain {
+        fn mine(&mut self, transaction_hash: Hash256, succeeded: bool) {
+            self.mined.insert(
+                transaction_hash,
+                TxInclusion {
+                    block_number: self.head,
+                    block_hash: [self.head as u8 ^ self.fork; 32],
+                    block_timestamp: 1_700_000_000 + self.head,
+                    succeeded,
+                    checkpoint_id: succeeded.then_some([0xC1; 32]),
+                },
+            );
+        }
+    }
+
+    impl AnchorChain for FakeChain {
+        fn head(&self) -> Result<u64, AnchorError> {
+            Ok(self.head)
+        }
+
+        fn inclusion(
+            &self,
+            transaction_hash: &Hash256,
+        ) -> Result<Option<TxInclusion>, AnchorError> {
+            Ok(self.mined.get(transaction_hash).cloned())
+        }
+
+        fn block_hash(&self, number: u64) -> Result<Option<Hash256>, AnchorError> {
+            Ok((number <= self.head).then_some([number as u8 ^ self.fork; 32]))
+        }
+
+        fn submit(&mut self, calldata: &[u8]) -> Result<Hash256, AnchorError> {
+            self.submitted.push(calldata.to_vec());
+            Ok([self.submitted.len() as u8; 32])
+        }
+    }
+
+    fn config() -> AnchorConfig {
+        AnchorConfig {
+            confirmation_depth: 3,
+            drop_after_blocks: 5,
+            max_attempts: 2,
+            ..AnchorConfig::new(1, [0xAA; 20])
+        }
+    }
+
+    #[test]
+    fn test_anchor_confirms_at_depth() {
+        let mut chain = FakeChain {
+            head: 100,
+            ..Default::default()
+        };
+        let mut tracker = AnchorTracker::new(config());
+        let tx = tracker
+            .submit(&mut chain, [7u8; 32], vec![1, 2, 3])
+            .unwrap();
+
+        chain.head = 101;
+        chain.mine(tx, true);
+        chain.head = 102;
+        assert!(tracker.poll(&mut chain).unwrap().is_empty());
+        let record = tracker.get(&[7u8; 32]).unwrap();
+        assert_eq!(
+            (record.status, record.confirmations),
+            (AnchorStatus::Pending, 2)
+        );
+        assert!(record.receipt.is_none());
+
+        chain.head = 103;
+        assert_eq!(tracker.poll(&mut chain).unwrap(), [[7u8; 32]]);
+        let record = tracker.get(&[7u8; 32]).unwrap();
+        assert_eq!(record.status, AnchorStatus::Confirmed);
+        let receipt = record.receipt.as_ref().unwrap();
+        assert_eq!(
+            (receipt.block_number, receipt.checkpoint_id),
+            (101, [0xC1; 32])
+        );
+        assert_eq!(tracker.with_status(AnchorStatus::Confirmed).count(), 1);
+
+        let err = tracker
+            .submit(&mut chain, [7u8; 32], vec![1, 2, 3])
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::AnchorAlreadyTracked);
+    }
+
+    #[test]
+    fn test_failed_anchors_are_retried_within_limit() {
+        let mut chain = FakeChain {
+            head: 100,
+            ..Default::default()
+        };
+        let mut tracker = AnchorTracker::new(config());
+        let dropped = tracker.submit(&mut chain, [7u8; 32], vec![1]).unwrap();
+        let reverted = tracker.submit(&mut chain, [8u8; 32], vec![2]).unwrap();
+        chain.mine(reverted, false);
+        assert_ne!(dropped, reverted);
+
+        // The reverted call fails at once; the unmined one after drop_after_blocks
+        assert_eq!(tracker.poll(&mut chain).unwrap(), [[8u8; 32]]);
+        chain.head = 105;
+        assert_eq!(tracker.poll(&mut chain).unwrap(), [[7u8; 32]]);
+        assert_eq!(tracker.with_status(AnchorStatus::Failed).count(), 2);
+
+        let retried = tracker.retry(&mut chain, &[7u8; 32]).unwrap();
+        assert_eq!(chain.submitted.last().unwrap(), &[1]);
+        let record = tracker.get(&[7u8; 32]).unwrap();
+        assert_eq!(
+            (record.status, record.attempts, record.transaction_hash),
+            (AnchorStatus::Pending, 2, retried)
+        );
+
+        let err = tracker.retry(&mut chain, &[7u8; 32]).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::AnchorNotRetryable);
+        chain.head = 110;
+        tracker.poll(&mut chain).unwrap();
+        let err = tracker.retry(&mut chain, &[7u8; 32]).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::AnchorAttemptsExhausted);
+    }
+
+    #[test]
+    fn test_reorg_supersedes_and_reanchors() {
+        let mut chain = FakeChain {
+            head: 100,
+            ..Default::default()
+        };
+        let mut tracker = AnchorTracker::new(config());
+        let first = tracker.submit(&mut chain, [7u8; 32], vec![1]).unwrap();
+        chain.mine(first, true);
+        chain.head = 102;
+        tracker.poll(&mut chain).unwrap();
+        let old_receipt = tracker.get(&[7u8; 32]).unwrap().receipt.clone().unwrap();
+
+        // The reorg drops the transaction: the root is sent again
+        chain.fork = 0xFF;
+        chain.mined.clear();
+        assert_eq!(tracker.poll(&mut chain).unwrap(), [[7u8; 32]]);
+        let current = tracker.get(&[7u8; 32]).unwrap();
+        assert_eq!(
+            (current.status, current.supersedes, current.attempts),
+            (AnchorStatus::Pending, Some(0), 2)
+        );
+        assert_ne!(current.transaction_hash, first);
+        assert_eq!(chain.submitted.len(), 2);
+
+        let second = current.transaction_hash;
+        chain.mine(second, true);
+        chain.head = 104;
+        tracker.poll(&mut chain).unwrap();
+
+        // The old receipt resolves to the orphaned block and its replacement
+        let lineage = tracker.lineage(&old_receipt.transaction_hash);
+        assert_eq!(lineage.len(), 2);
+        assert_eq!(lineage[0].status, AnchorStatus::Superseded);
+        let orphaned = lineage[0].orphaned.as_ref().unwrap();
+        assert_eq!(
+            (orphaned.block_number, orphaned.block_hash),
+            (100, [100; 32])
+        );
+        assert_eq!(orphaned.canonical_hash, Some([100 ^ 0xFF; 32]));
+        assert_eq!(lineage[1].status, AnchorStatus::Confirmed);
+        assert_eq!(lineage[1].receipt.as_ref().unwrap().block_number, 102);
+
+        // A reorg that re-mines the same transaction reuses it
+        chain.fork = 0x0F;
+        chain.head = 103;
+        chain.mine(second, true);
+        tracker.poll(&mut chain).unwrap();
+        assert_eq!(tracker.get(&[7u8; 32]).unwrap().transaction_hash, second);
+        assert_eq!(chain.submitted.len(), 2);
+        assert_eq!(tracker.lineage(&first).len(), 3);
+    }
+}
@@ -0,0 +1,572 @@
+//! Measurement transparency log of approved models and firmware.
+//!
+//! The fleet operator appends every model and firmware build it approves to
+//! an [`ApprovalLog`], an RFC 9162 Merkle log (the same hashing as
+//! [`crate::transparency`]) of [`Approval`] entries, and publishes signed
+//! tree heads over it. Because the log is append-only, an approval cannot be
+//! quietly backdated or withdrawn after the fact: each entry states when it
+//! takes effect and, optionally, when it lapses.
+//!
+//! - A [`ChainVerifier`](crate::ChainVerifier) given the log
+//!   ([`ChainVerifier::with_approvals`](crate::ChainVerifier::with_approvals))
+//!   rejects checkpoints whose model or firmware was not approved at the
+//!   checkpoint's timestamp.
+//! - A robot (or anyone holding its checkpoint) proves the opposite with
+//!   [`ArtifactApproval`]: inclusion proofs for both approvals under one
+//!   signed tree head, checkable without the log.
+
+use crate::checkpoint::Checkpoint;
+use crate::crypto::{ct_eq, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::transparency::{
+    leaf_hash, InclusionProof, LogError, SignedTreeHead, TransparencyLog, TreeHead,
+};
+use crate::types::Hash256;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+/// Approval entry version (for schema evolution)
+pub const APPROVAL_VERSION: u8 = 1;
+
+/// What an approval covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    /// A model build, by `ModelProvenance::model_hash`
+    Model,
+    /// A firmware/OS build, by `Checkpoint::firmware_hash`
+    Firmware,
+}
+
+impl fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ArtifactKind::Model => "model",
+            ArtifactKind::Firmware => "firmware",
+        })
+    }
+}
+
+/// One approved artifact, as logged.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Approval {
+    /// Schema version
+    pub version: u8,
+    pub kind: ArtifactKind,
+    pub hash: Hash256,
+    /// Name and version tag, for humans
+    pub name: String,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub effective_from: DateTime<Utc>,
+    /// End of the approval (exclusive); open-ended if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_until: Option<DateTime<Utc>>,
+}
+
+impl Approval {
+    /// Approval of `hash` from `effective_from` on.
+    pub fn new(
+        kind: ArtifactKind,
+        hash: Hash256,
+        name: impl Into<String>,
+        effective_from: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            version: APPROVAL_VERSION,
+            kind,
+            hash,
+            name: name.into(),
+            effective_from,
+            effective_until: None,
+        }
+    }
+
+    /// Let the approval lapse at `until`.
+    pub fn until(mut self, until: DateTime<Utc>) -> Self {
+        self.effective_until = Some(until);
+        self
+    }
+
+    /// Whether the approval covers `kind` `hash` at `at`.
+    pub fn covers(&self, kind: ArtifactKind, hash: &Hash256, at: DateTime<Utc>) -> bool {
+        self.kind == kind
+            && ct_eq(&self.hash, hash)
+            && self.effective_from <= at
+            && self.effective_until.is_none_or(|until| at < until)
+    }
+
+    /// Leaf hash of the approval in the log.
+    pub fn leaf_hash(&self) -> Result<Hash256, SerializationError> {
+        Ok(leaf_hash(&to_canonical_cbor(self)?))
+    }
+}
+
+/// Proof that one approval is in the log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalProof {
+    pub approval: Approval,
+    pub inclusion: InclusionProof,
+}
+
+impl ApprovalProof {
+    /// Check the approval covers `kind` `hash` at `at` and is included under `head`.
+    pub fn verify(
+        &self,
+        kind: ArtifactKind,
+        hash: &Hash256,
+        at: DateTime<Utc>,
+        head: &TreeHead,
+    ) -> Result<(), ApprovalError> {
+        if !self.approval.covers(kind, hash, at) {
+            return Err(ApprovalError::ProofMismatch { kind });
+        }
+        self.inclusion.verify(&self.approval.leaf_hash()?, head)?;
+        Ok(())
+    }
+}
+
+/// Proof that a checkpoint's model and firmware were approved at its timestamp.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactApproval {
+    pub model: ApprovalProof,
+    pub firmware: ApprovalProof,
+    pub tree_head: SignedTreeHead,
+}
+
+impl ArtifactApproval {
+    /// Verify the tree head signature with a log key from `logs`, and both
+    /// approvals against `checkpoint`.
+    pub fn verify(
+        &self,
+        checkpoint: &Checkpoint,
+        logs: &dyn KeyResolver,
+    ) -> Result<(), ApprovalError> {
+        self.tree_head.verify(logs)?;
+        let at = checkpoint.local_timestamp_utc;
+        let head = &self.tree_head.tree_head;
+        self.model.verify(
+            ArtifactKind::Model,
+            &checkpoint.model_provenance.model_hash,
+            at,
+            head,
+        )?;
+        self.firmware
+            .verify(ArtifactKind::Firmware, &checkpoint.firmware_hash, at, head)
+    }
+}
+
+/// An approval log as the operator publishes it: every entry plus a signed
+/// tree head over them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishedApprovals {
+    pub approvals: Vec<Approval>,
+    pub tree_head: SignedTreeHead,
+}
+
+impl PublishedApprovals {
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+}
+
+/// Append-only log of approvals.
+#[derive(Debug, Clone, Default)]
+pub struct ApprovalLog {
+    approvals: Vec<Approval>,
+    log: TransparencyLog,
+}
+
+impl ApprovalLog {
+    /// Create an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuild a published log, checking its entries reproduce the tree
+    /// head and that the head is signed by a log key from `logs`.
+    pub fn from_published(
+        published: PublishedApprovals,
+        logs: &dyn KeyResolver,
+    ) -> Result<Self, ApprovalError> {
+        published.tree_head.verify(logs)?;
+        let mut log = Self::new();
+        for approval in published.approvals {
+            log.append(approval)?;
+        }
+        if log.tree_head() != published.tree_head.tree_head {
+            return Err(ApprovalError::LogMismatch);
+        }
+        Ok(log)
+    }
+
+    /// Append an approval, returning its index.
+    pub fn append(&mut self, approval: Approval) -> Result<u64, ApprovalError> {
+        let index = self.log.append_leaf(approval.leaf_hash()?);
+        self.approvals.push(approval);
+        Ok(index)
+    }
+
+    /// Logged approvals, in order.
+    pub fn approvals(&self) -> &[Approval] {
+        &self.approvals
+    }
+
+    /// Current tree head.
+    pub fn tree_head(&self) -> TreeHead {
+        self.log.tree_head()
+    }
+
+    /// Sign the current tree head with the log key.
+    pub fn sign_head(&self, signer: &Signer) -> Result<SignedTreeHead, ApprovalError> {
+        Ok(SignedTreeHead::sign(self.tree_head(), signer)?)
+    }
+
+    /// Publish every entry under a freshly signed tree head.
+    pub fn publish(&self, signer: &Signer) -> Result<PublishedApprovals, ApprovalError> {
+        Ok(PublishedApprovals {
+            approvals: self.approvals.clone(),
+            tree_head: self.sign_head(signer)?,
+        })
+    }
+
+    /// Check `kind` `hash` was approved at `at`.
+    pub fn check(
+        &self,
+        kind: ArtifactKind,
+        hash: &Hash256,
+        at: DateTime<Utc>,
+    ) -> Result<(), ApprovalError> {
+        self.position(kind, hash, at)
+            .map(|_| ())
+            .ok_or(ApprovalError::NotApproved {
+                kind,
+                hash: *hash,
+                at,
+            })
+    }
+
+    /// Proof that `kind` `hash` was approved at `at`, under the current tree head.
+    pub fn prove(
+        &self,
+        kind: ArtifactKind,
+        hash: &Hash256,
+        at: DateTime<Utc>,
+    ) -> Result<ApprovalProof, ApprovalError> {
+        let index = self
+            .position(kind, hash, at)
+            .ok_or(ApprovalError::NotApproved {
+                kind,
+                hash: *hash,
+                at,
+            })?;
+        Ok(ApprovalProof {
+            approval: self.approvals[index].clone(),
+            inclusion: self.log.inclusion_proof(index as u64, self.log.len())?,
+        })
+    }
+
+    /// Approval proofs for `checkpoint`'s model and firmware under a fresh
+    /// signed tree head.
+    pub fn prove_checkpoint(
+        &self,
+        checkpoint: &Checkpoint,
+        signer: &Signer,
+    ) -> Result<ArtifactApproval, ApprovalError> {
+        let at = checkpoint.local_timestamp_utc;
+        Ok(ArtifactApproval {
+            model: self.prove(
+                ArtifactKind::Model,
+                &checkpoint.model_provenance.model_hash,
+                at,
+            )?,
+            firmware: self.prove(ArtifactKind::Firmware, &checkpoint.firmware_hash, at)?,
+            tree_head: self.sign_head(signer)?,
+        })
+    }
+
+    fn position(&self, kind: ArtifactKind, hash: &Hash256, at: DateTime<Utc>) -> Option<usize> {
+        self.approvals
+            .iter()
+            .position(|approval| approval.covers(kind, hash, at))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ApprovalError {
+    #[error("Approval serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Approval log error: {0}")]
+    Log(#[from] LogError),
+
+    #[error("{kind} {} was not approved at {at}", hex::encode(hash))]
+    NotApproved {
+        kind: ArtifactKind,
+        hash: Hash256,
+        at: DateTime<Utc>,
+    },
+
+    #[error("{kind} approval proof covers a different artifact or time")]
+    ProofMismatch { kind: ArtifactKind },
+
+    #[error("Published approvals do not reproduce the signed tree head")]
+    LogMismatch,
+}
+
+impl ErrorCoded for ApprovalError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ApprovalError::Serialization(e) => e.code(),
+            ApprovalError::Log(e) => e.code(),
+            ApprovalError::NotApproved { .. } => ErrorCode::ArtifactNotApproved,
+            ApprovalError::ProofMismatch { .. } => ErrorCode::ApprovalProofInvalid,
+            ApprovalError::LogMismatch => ErrorCode::ApprovalLogMismatch,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::types::ModelProvenance;
+    use chrono::Duration;
+
+    fn checkpoint(robot: &Signer, at: DateTime<Utc>) -> Checkpoint {
+        CheckpointBuilder::test_fixture()
+            .sequence(1)
+            .timestamp(at)
+            .model_provenance(ModelProvenance {
+                name: "planner-v2".to_string(),
+                model_hash: [0xaa; 32],
+                dataset_hash: None,
+                container_digest: None,
+                signature_bundle: None,
+            })
+            .firmware_hash([0xbb; 32])
+            .enclave_measurement(vec![0u8; 32])
+            .entries_root([0u8; 32])
+            .build_and_sign(robot.signing_key())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_checkpoint_approval_proof() {
+        let operator = Signer::generate();
+        let start = DateTime::UNIX_EPOCH + Duration::days(20_000);
+        let mut log = ApprovalLog::new();
+        log.append(Approval::new(
+            ArtifactKind::Firmware,
+            [0xbb; 32],
+            "fw 1.4",
+            start,
+        ))
+        .unwrap();
+        log.append(
+            Approval::new(ArtifactKind::Model, [0xaa; 32], "planner-v2", start)
+                .until(start + Duration::days(30)),
+        )
+        .unwrap();
+
+        let robot = Signer::generate();
+        let approved = checkpoint(&robot, start + Duration::days(1));
+        let proof = log.prove_checkpoint(&approved, &operator).unwrap();
+        proof.verify(&approved, &operator.verifying_key()).unwrap();
+
+        // The same proof says nothing about a checkpoint after the model lapsed
+        let lapsed = checkpoint(&robot, start + Duration::days(31));
+        let err = proof
+            .verify(&lapsed, &operator.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ApprovalProofInvalid);
+        let err = log.prove_checkpoint(&lapsed, &operator).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ArtifactNotApproved);
+
+        // A verifier rebuilds the log from its published entries and head
+        let published = log.publish(&operator).unwrap();
+        let bytes = published.to_bytes().unwrap();
+        let rebuilt = ApprovalLog::from_published(
+            PublishedApprovals::from_bytes(&bytes).unwrap(),
+            &operator.verifying_key(),
+        );
+        rebuilt
+            .unwrap()
+            .check(ArtifactKind::Firmware, &[0xbb; 32], start)
+            .unwrap();
+        let mut rewritten = published;
+        rewritten.approvals[1].effective_until = None;
+        let err = ApprovalLog::from_published(rewritten, &operator.verifying_key()).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ApprovalLogMismatch);
+    }
+
+    #[test]
+    fn test_wrong_artifact_hash_rejected() {
+        let operator = Signer::generate();
+        let start = DateTime::UNIX_EPOCH + Duration::days(20_000);
+        let mut log = ApprovalLog::new();
+        log.append(Approval::new(
+            ArtifactKind::Model,
+            [0xaa; 32],
+            "planner-v2",
+            start,
+        ))
+        .unwrap();
+        log.append(Approval::new(
+            ArtifactKind::Firmware,
+            [0xbb; 32],
+            "fw 1.4",
+            start,
+        ))
+        .unwrap();
+        let at = start + Duration::days(1);
+
+        // A hash approved as the other kind of artifact is not approved
+        let err = log
+            .check(ArtifactKind::Firmware, &[0xaa; 32], at)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ArtifactNotApproved);
+        assert!(log.check(ArtifactKind::Model, &[0xcc; 32], at).is_err());
+
+        // A proof for one model does not vouch for another
+        let proof = log.prove(ArtifactKind::Model, &[0xaa; 32], at).unwrap();
+        let head = log.tree_head();
+        let err = proof
+            .verify(ArtifactKind::Model, &[0xcc; 32], at, &head)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ApprovalProofInvalid);
+
+        // Nor does one rewritten to name it, which falls out of the log
+        let mut rewritten = proof;
+        rewritten.approval.hash = [0xcc; 32];
+        let err = rewritten
+            .verify(ArtifactKind::Model, &[0xcc; 32], at, &head)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InclusionProofInvalid);
+
+        // A checkpoint running unapproved firmware gets no proof
+        let robot = Signer::generate();
+        let mut models_only = ApprovalLog::new();
+        models_only
+            .append(Approval::new(
+                ArtifactKind::Model,
+                [0xaa; 32],
+                "planner-v2",
+                start,
+            ))
+            .unwrap();
+        let err = models_only
+            .prove_checkpoint(&checkpoint(&robot, at), &operator)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ApprovalError::NotApproved {
+                kind: ArtifactKind::Firmware,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_approval_applies_only_within_its_window() {
+        let start = DateTime::UNIX_EPOCH + Duration::days(20_000);
+        let until = start + Duration::days(30);
+        let mut log = ApprovalLog::new();
+        log.append(
+            Approval::new(ArtifactKind::Model, [0xaa; 32], "planner-v2", start).until(until),
+        )
+        .unwrap();
+
+        log.check(ArtifactKind::Model, &[0xaa; 32], start).unwrap();
+        log.check(
+            ArtifactKind::Model,
+            &[0xaa; 32],
+            until - Duration::seconds(1),
+        )
+        .unwrap();
+        // Not backdated before it took effect, and expired from `until` on
+        for at in [
+            start - Duration::seconds(1),
+            until,
+            until + Duration::days(1),
+        ] {
+            let err = log.check(ArtifactKind::Model, &[0xaa; 32], at).unwrap_err();
+            assert_eq!(err.code(), ErrorCode::ArtifactNotApproved, "{at}");
+        }
+
+        // A proof taken while approved does not cover use after expiry
+        let proof = log.prove(ArtifactKind::Model, &[0xaa; 32], start).unwrap();
+        let err = proof
+            .verify(ArtifactKind::Model, &[0xaa; 32], until, &log.tree_head())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ApprovalError::ProofMismatch {
+                kind: ArtifactKind::Model
+            }
+        ));
+
+        // A later entry re-approves the same build
+        log.append(Approval::new(
+            ArtifactKind::Model,
+            [0xaa; 32],
+            "planner-v2",
+            until + Duration::days(7),
+        ))
+        .unwrap();
+        assert!(log.check(ArtifactKind::Model, &[0xaa; 32], until).is_err());
+        log.check(ArtifactKind::Model, &[0xaa; 32], until + Duration::days(7))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_tree_head_needs_a_trusted_log_key() {
+        let (operator, impostor) = (Signer::generate(), Signer::generate());
+        let start = DateTime::UNIX_EPOCH + Duration::days(20_000);
+        let mut log = ApprovalLog::new();
+        log.append(Approval::new(
+            ArtifactKind::Model,
+            [0xaa; 32],
+            "planner-v2",
+            start,
+        ))
+        .unwrap();
+        log.append(Approval::new(
+            ArtifactKind::Firmware,
+            [0xbb; 32],
+            "fw 1.4",
+            start,
+        ))
+        .unwrap();
+        let robot = Signer::generate();
+        let approved = checkpoint(&robot, start + Duration::days(1));
+
+        // Proofs and published logs signed by a key outside the trusted set
+        let proof = log.prove_checkpoint(&approved, &impostor).unwrap();
+        let err = proof
+            .verify(&approved, &operator.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::LogUnknownSigner);
+        let err =
+            ApprovalLog::from_published(log.publish(&impostor).unwrap(), &operator.verifying_key())
+                .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::LogUnknownSigner);
+
+        // A trusted key id over a head it did not sign
+        let mut forged = log.prove_checkpoint(&approved, &operator).unwrap();
+        forged.tree_head.tree_head.tree_size += 1;
+        let err = forged
+            .verify(&approved, &operator.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::LogInvalidSignature);
+    }
+}
@@ -0,0 +1,283 @@
+//! `proptest` strategies for core types (feature `proptest`).
+//!
+//! Generated values are realistic rather than merely well-typed: checkpoints
+//! carry a valid signature from a generated key, and proofs come from an
+//! actual tree, so they verify. Downstream crates can use them to fuzz their
+//! own pipelines:
+//!
+//! ```ignore
+//! use attestation_core::Checkpoint;
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn stores_any_checkpoint(checkpoint in any::<Checkpoint>()) {
+//!         my_store.put(&checkpoint)?;
+//!     }
+//! }
+//! ```
+
+use crate::checkpoint::{Checkpoint, CheckpointBuilder};
+use crate::crypto::DigestAlgorithm;
+use crate::merkle::{Entry, MerkleMultiProof, MerkleProof, MerkleTree};
+use crate::types::{DeterminismConfig, Hash256, MissionId, ModelProvenance, RobotId, TrustMode};
+use chrono::DateTime;
+use ed25519_dalek::SigningKey;
+use proptest::prelude::*;
+
+impl Arbitrary for Entry {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<u64>(), any::<u64>(), any::<Hash256>())
+            .prop_map(|(timestamp_us, nonce, data_hash)| Entry {
+                timestamp_us,
+                nonce,
+                data_hash,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for DigestAlgorithm {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            Just(DigestAlgorithm::Sha256),
+            Just(DigestAlgorithm::Sha3_256),
+            Just(DigestAlgorithm::Blake3),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for TrustMode {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            Just(TrustMode::Trusted),
+            Just(TrustMode::SoftAttestation),
+            Just(TrustMode::Untrusted),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for ModelProvenance {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            "[a-z]{3,12}-v[0-9]{1,2}",
+            any::<Hash256>(),
+            proptest::option::of(any::<Hash256>()),
+            proptest::option::of("sha256:[0-9a-f]{64}"),
+            proptest::option::of(proptest::collection::vec(any::<u8>(), 0..256)),
+        )
+            .prop_map(
+                |(name, model_hash, dataset_hash, container_digest, signature_bundle)| {
+                    ModelProvenance {
+                        name,
+                        model_hash,
+                        dataset_hash,
+                        container_digest,
+                        signature_bundle,
+                    }
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for DeterminismConfig {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            proptest::option::of(any::<u64>()),
+            1..=256u32,
+            proptest::option::of(proptest::collection::vec("[a-z_]{1,16}=(true|false)", 0..4)),
+        )
+            .prop_map(|(rng_seed, batch_size, flags)| DeterminismConfig {
+                rng_seed,
+                batch_size,
+                flags,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for Checkpoint {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// A checkpoint signed by a generated key; use [`signed_checkpoint`] to
+    /// get the key as well.
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        signed_checkpoint()
+            .prop_map(|(checkpoint, _)| checkpoint)
+            .boxed()
+    }
+}
+
+/// A checkpoint together with the key that signed it.
+pub fn signed_checkpoint() -> impl Strategy<Value = (Checkpoint, SigningKey)> {
+    let identity = (
+        "R-[0-9]{3,6}",
+        "M-[0-9]{3,6}",
+        0..1_000_000u64,
+        1..u64::MAX,
+        // Any instant between 1970 and 2100, at nanosecond precision
+        0..4_102_444_800_000_000_000i64,
+        any::<DigestAlgorithm>(),
+    );
+    let contents = (
+        any::<ModelProvenance>(),
+        any::<Hash256>(),
+        proptest::collection::vec(any::<u8>(), 32..=48),
+        any::<Hash256>(),
+        any::<Hash256>(),
+        any::<DeterminismConfig>(),
+        any::<TrustMode>(),
+        any::<[u8; 32]>(),
+    );
+    (identity, contents).prop_map(
+        |(
+            (robot_id, mission_id, sequence, monotonic_counter, nanos, hash_alg),
+            (
+                provenance,
+                firmware_hash,
+                measurement,
+                prev_root,
+                entries_root,
+                config,
+                trust_mode,
+                seed,
+            ),
+        )| {
+            let key = SigningKey::from_bytes(&seed);
+            let checkpoint = CheckpointBuilder::new()
+                .hash_alg(hash_alg)
+                .robot_id(RobotId(robot_id))
+                .mission_id(MissionId(mission_id))
+                .sequence(sequence)
+                .monotonic_counter(monotonic_counter)
+                .timestamp(DateTime::from_timestamp_nanos(nanos))
+                .model_provenance(provenance)
+                .firmware_hash(firmware_hash)
+                .enclave_measurement(measurement)
+                .prev_root(prev_root)
+                .entries_root(entries_root)
+                .inference_config(config)
+                .trust_mode(trust_mode)
+                .build_and_sign(&key)
+                .expect("generated checkpoints have every required field");
+            (checkpoint, key)
+        },
+    )
+}
+
+/// A tree of 1 to `max_entries` entries with distinct ordering keys.
+pub fn merkle_tree(max_entries: usize) -> impl Strategy<Value = MerkleTree> {
+    (
+        any::<DigestAlgorithm>(),
+        proptest::collection::vec((any::<u64>(), any::<Hash256>()), 1..=max_entries.max(1)),
+    )
+        .prop_map(|(hash_alg, entries)| {
+            let mut tree = MerkleTree::new().with_hash_alg(hash_alg);
+            for (nonce, (timestamp_us, data_hash)) in entries.into_iter().enumerate() {
+                tree.insert(Entry {
+                    timestamp_us,
+                    nonce: nonce as u64,
+                    data_hash,
+                });
+            }
+            tree
+        })
+}
+
+impl Arbitrary for MerkleProof {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// An inclusion proof that verifies against its own `root`.
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (merkle_tree(64), any::<prop::sample::Index>())
+            .prop_map(|(tree, index)| {
+                let entries = tree.entries();
+                let entry = &entries[index.index(entries.len())];
+                tree.generate_proof(entry.timestamp_us, entry.nonce)
+                    .expect("entry is in the tree")
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for MerkleMultiProof {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// A multiproof for a non-empty subset of a tree's entries.
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            merkle_tree(64),
+            proptest::collection::vec(any::<prop::sample::Index>(), 1..8),
+        )
+            .prop_map(|(tree, picks)| {
+                let entries = tree.entries();
+                let mut keys: Vec<(u64, u64)> = picks
+                    .iter()
+                    .map(|pick| {
+                        let entry = &entries[pick.index(entries.len())];
+                        (entry.timestamp_us, entry.nonce)
+                    })
+                    .collect();
+                keys.sort_unstable();
+                keys.dedup();
+                tree.generate_multiproof(&keys)
+                    .expect("entries are in the tree")
+            })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn checkpoint_roundtrips_and_verifies((checkpoint, key) in signed_checkpoint()) {
+            let decoded = Checkpoint::from_bytes(&checkpoint.to_bytes().unwrap()).unwrap();
+            prop_assert_eq!(&decoded, &checkpoint);
+            prop_assert_eq!(decoded.compute_hash().unwrap(), checkpoint.compute_hash().unwrap());
+            prop_assert!(checkpoint.verify_signature(&key.verifying_key()).is_ok());
+
+            let mut tampered = checkpoint.clone();
+            tampered.monotonic_counter ^= 1;
+            prop_assert!(tampered.verify_signature(&key.verifying_key()).is_err());
+        }
+
+        #[test]
+        fn proofs_verify_and_bind_their_leaf(proof in any::<MerkleProof>(), multi in any::<MerkleMultiProof>()) {
+            prop_assert!(proof.verify(&proof.root));
+            let mut forged = proof.clone();
+            forged.leaf.data_hash[0] ^= 1;
+            prop_assert!(!forged.verify(&proof.root));
+
+            prop_assert!(multi.verify(&multi.root));
+            let decoded: MerkleMultiProof = crate::serialization::from_canonical_cbor(
+                &crate::serialization::to_canonical_cbor(&multi).unwrap(),
+            )
+            .unwrap();
+            prop_assert_eq!(decoded, multi);
+        }
+    }
+}
@@ -2,10 +2,30 @@
 //!
 //! This module defines the trait that all attestation adapters must implement,
 //! providing a unified API for verifying TEE quotes across different vendors.
+//!
+//! Failures are classified by [`AttestationError::category`]: only transient
+//! ones (network, undeterminable revocation status) are worth retrying. A
+//! registry configured with [`AttestationRegistry::with_retry`] retries those
+//! with exponential backoff and returns every other failure at once.
+//!
+//! Freshness is enforced per vendor: see [`AttestationRegistry::with_freshness`].
+//! Repeated submissions of the same quote can be answered without verifying
+//! again: see [`AttestationRegistry::with_dedup`].
 
-use crate::types::{AttestationResult, RevocationStatus};
+use crate::clock::{system_clock, Clock};
+use crate::collateral::{CollateralBundle, CollateralError};
+use crate::dedup::QuoteDeduplicator;
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::freshness::FreshnessPolicy;
+use crate::nonce::{NonceError, NonceManager};
+use crate::types::{AttestationResult, RevocationCheck};
 use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
 use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Trait for attestation verification adapters.
@@ -21,7 +41,8 @@ pub trait AttestationAdapter: Send + Sync {
     ///
     /// # Arguments
     /// * `quote` - The raw attestation quote bytes (vendor-specific format)
-    /// * `nonce` - Optional nonce to prevent replay (if supported by vendor)
+    /// * `nonce` - Optional nonce to prevent replay; the quote must commit to
+    ///   it (if supported by vendor)
     ///
     /// # Returns
     /// An `AttestationResult` with verification details, or an error if verification fails.
@@ -31,24 +52,156 @@ pub trait AttestationAdapter: Send + Sync {
         nonce: Option<&[u8]>,
     ) -> Result<AttestationResult, AttestationError>;
 
+    /// Verify a quote using pre-fetched collateral instead of network services.
+    ///
+    /// The registry checks the bundle's vendor and validity window before
+    /// calling this. Adapters that cannot verify offline keep the default,
+    /// which rejects the request.
+    async fn verify_with_collateral(
+        &self,
+        _quote: &[u8],
+        _nonce: Option<&[u8]>,
+        _collateral: &CollateralBundle,
+    ) -> Result<AttestationResult, AttestationError> {
+        Err(CollateralError::Unsupported(self.vendor_name().to_string()).into())
+    }
+
+    /// Verify several quotes, returning one result per quote, in order.
+    ///
+    /// Fleet re-attestation sweeps verify many quotes at once. Adapters that
+    /// can share work across a batch (collateral lookups, certificate chain
+    /// checks) override this; the default verifies each quote in turn.
+    async fn verify_quotes_batch(
+        &self,
+        quotes: &[(&[u8], Option<&[u8]>)],
+    ) -> Vec<Result<AttestationResult, AttestationError>> {
+        let mut results = Vec::with_capacity(quotes.len());
+        for (quote, nonce) in quotes {
+            results.push(self.verify_quote(quote, *nonce).await);
+        }
+        results
+    }
+
     /// Check if an enclave measurement is revoked.
     ///
     /// # Arguments
     /// * `measurement` - The enclave measurement (code hash)
     ///
     /// # Returns
-    /// The revocation status of the measurement.
-    async fn check_revocation(&self, measurement: &[u8]) -> Result<RevocationStatus, AttestationError>;
+    /// The revocation check outcome, including its source and reasons.
+    async fn check_revocation(
+        &self,
+        measurement: &[u8],
+    ) -> Result<RevocationCheck, AttestationError>;
 
     /// Get the root CA certificates for this vendor's attestation chain.
     ///
-    /// Returns PEM-encoded certificates.
-    fn root_ca_certs(&self) -> &[String];
+    /// Returns PEM-encoded certificates. Roots may be reloaded at runtime,
+    /// so this returns a snapshot.
+    async fn root_ca_certs(&self) -> Vec<String>;
 
     /// Update cached CRLs and root certificates.
     ///
-    /// Should be called periodically to refresh revocation lists.
-    async fn update_trust_anchors(&mut self) -> Result<(), AttestationError>;
+    /// Should be called periodically to refresh revocation lists. Adapters keep
+    /// their trust anchors behind interior mutability so a refresh can run
+    /// while other tasks continue to verify quotes.
+    async fn update_trust_anchors(&self) -> Result<(), AttestationError>;
+}
+
+/// Kind of attestation evidence, as recognized by [`identify_evidence`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvidenceKind {
+    /// Intel SGX DCAP quote, version 3
+    SgxQuoteV3,
+    /// Intel SGX DCAP quote, version 4
+    SgxQuoteV4,
+    /// Intel TDX quote, version 4
+    TdxQuoteV4,
+    /// AMD SEV-SNP attestation report
+    SevSnpReport,
+    /// AWS Nitro attestation document (COSE_Sign1)
+    NitroCose,
+    /// TPM 2.0 quote (TPMS_ATTEST)
+    TpmQuote,
+    /// Entity Attestation Token (CBOR-tagged CWT or JWT)
+    Eat,
+    /// Not recognized
+    Unknown,
+}
+
+impl EvidenceKind {
+    /// Vendor name of the adapter that verifies this kind of evidence.
+    pub fn vendor(&self) -> Option<&'static str> {
+        match self {
+            EvidenceKind::SgxQuoteV3 | EvidenceKind::SgxQuoteV4 => Some("intel-sgx"),
+            EvidenceKind::TdxQuoteV4 => Some("intel-tdx"),
+            EvidenceKind::SevSnpReport => Some("amd-sev-snp"),
+            EvidenceKind::NitroCose => Some("aws-nitro"),
+            EvidenceKind::TpmQuote => Some("tpm"),
+            EvidenceKind::Eat => Some("eat"),
+            EvidenceKind::Unknown => None,
+        }
+    }
+}
+
+/// Size of an SEV-SNP attestation report in bytes.
+const SEV_SNP_REPORT_LEN: usize = 0x4A0;
+/// TPM_GENERATED_VALUE magic that starts every TPMS_ATTEST structure.
+const TPM_GENERATED_VALUE: [u8; 4] = [0xFF, 0x54, 0x43, 0x47];
+/// TPM_ST_ATTEST_QUOTE structure tag.
+const TPM_ST_ATTEST_QUOTE: [u8; 2] = [0x80, 0x18];
+
+/// Recognize the kind of attestation evidence from magic bytes and structure.
+///
+/// This is a cheap sniff for routing, not validation: a recognized kind can
+/// still fail to parse in the adapter.
+pub fn identify_evidence(bytes: &[u8]) -> EvidenceKind {
+    // SEV-SNP: fixed-size report, version 2+, ECDSA P-384 signature algorithm at 0x34
+    if bytes.len() == SEV_SNP_REPORT_LEN {
+        let version = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let sig_algo = u32::from_le_bytes([bytes[0x34], bytes[0x35], bytes[0x36], bytes[0x37]]);
+        if (2..=5).contains(&version) && sig_algo == 1 {
+            return EvidenceKind::SevSnpReport;
+        }
+    }
+
+    // Intel DCAP quotes: u16 version, u16 attestation key type (ECDSA P-256/P-384), u32 TEE type
+    if bytes.len() >= 48 {
+        let version = u16::from_le_bytes([bytes[0], bytes[1]]);
+        let key_type = u16::from_le_bytes([bytes[2], bytes[3]]);
+        let tee_type = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        if matches!(key_type, 2 | 3) {
+            match (version, tee_type) {
+                (3, _) => return EvidenceKind::SgxQuoteV3,
+                (4, 0x00) => return EvidenceKind::SgxQuoteV4,
+                (4, 0x81) => return EvidenceKind::TdxQuoteV4,
+                _ => {}
+            }
+        }
+    }
+
+    // TPM: TPMS_ATTEST, optionally wrapped in a TPM2B size prefix
+    for offset in [0, 2] {
+        if bytes.len() >= offset + 6
+            && bytes[offset..offset + 4] == TPM_GENERATED_VALUE
+            && bytes[offset + 4..offset + 6] == TPM_ST_ATTEST_QUOTE
+        {
+            return EvidenceKind::TpmQuote;
+        }
+    }
+
+    // EAT: CBOR tag 61 (CWT) or a JWT ("eyJ" is base64url for '{"')
+    if bytes.starts_with(&[0xD8, 0x3D]) || bytes.starts_with(b"eyJ") {
+        return EvidenceKind::Eat;
+    }
+
+    // Nitro: COSE_Sign1 (optionally tag 18), a 4-element array starting with a bstr header
+    let cose = bytes.strip_prefix(&[0xD2]).unwrap_or(bytes);
+    if cose.len() >= 2 && cose[0] == 0x84 && cose[1] >> 5 == 2 {
+        return EvidenceKind::NitroCose;
+    }
+
+    EvidenceKind::Unknown
 }
 
 /// Errors that can occur during attestation verification.
@@ -77,23 +230,238 @@ pub enum AttestationError {
 
     #[error("Internal error: {0}")]
     Internal(String),
+
+    #[error("Nonce rejected: {0}")]
+    Nonce(#[from] NonceError),
+
+    #[error("Collateral error: {0}")]
+    Collateral(#[from] CollateralError),
+
+    #[error("Quote too old: {0}")]
+    QuoteStale(String),
+}
+
+/// Broad class of an attestation failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// A service (PCS, OCSP, vendor API) could not be reached or did not
+    /// answer; may succeed later
+    Network,
+    /// The evidence was understood and rejected (bad signature, revoked,
+    /// replayed nonce, untrusted or expired collateral)
+    Policy,
+    /// The evidence could not be parsed
+    MalformedEvidence,
+    /// The verifier is not set up for this evidence
+    Configuration,
+    /// A bug or unexpected state in an adapter
+    Internal,
+}
+
+impl AttestationError {
+    /// Which class of failure this is.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            AttestationError::Network(_) | AttestationError::RevocationCheckFailed(_) => {
+                ErrorCategory::Network
+            }
+            AttestationError::VerificationFailed(_)
+            | AttestationError::MeasurementRevoked
+            | AttestationError::Nonce(_)
+            | AttestationError::QuoteStale(_) => ErrorCategory::Policy,
+            AttestationError::InvalidQuote(_) => ErrorCategory::MalformedEvidence,
+            AttestationError::UnsupportedVendor(_) | AttestationError::Config(_) => {
+                ErrorCategory::Configuration
+            }
+            AttestationError::Internal(_) => ErrorCategory::Internal,
+            AttestationError::Collateral(CollateralError::Unsupported(_)) => {
+                ErrorCategory::Configuration
+            }
+            AttestationError::Collateral(CollateralError::Serialization(_)) => {
+                ErrorCategory::MalformedEvidence
+            }
+            AttestationError::Collateral(_) => ErrorCategory::Policy,
+        }
+    }
+
+    /// Whether the same request may succeed if repeated later.
+    pub fn is_retryable(&self) -> bool {
+        self.category() == ErrorCategory::Network
+    }
+}
+
+impl ErrorCoded for AttestationError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            AttestationError::InvalidQuote(_) => ErrorCode::InvalidQuote,
+            AttestationError::VerificationFailed(_) => ErrorCode::VerificationFailed,
+            AttestationError::RevocationCheckFailed(_) => ErrorCode::RevocationCheckFailed,
+            AttestationError::MeasurementRevoked => ErrorCode::MeasurementRevoked,
+            AttestationError::Network(_) => ErrorCode::Network,
+            AttestationError::UnsupportedVendor(_) => ErrorCode::UnsupportedVendor,
+            AttestationError::Config(_) => ErrorCode::Config,
+            AttestationError::Internal(_) => ErrorCode::Internal,
+            AttestationError::Nonce(_) => ErrorCode::NonceRejected,
+            AttestationError::Collateral(e) => e.code(),
+            AttestationError::QuoteStale(_) => ErrorCode::QuoteStale,
+        }
+    }
+}
+
+/// How often and how patiently to retry transient failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, the first one included
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound on any delay
+    pub max_backoff: Duration,
+    /// Factor applied to the delay after each retry
+    pub multiplier: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retry number `retry` (1 for the first retry).
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Async sleep supplied by the caller's runtime, e.g.
+/// `Arc::new(|d| Box::pin(tokio::time::sleep(d)))`.
+pub type SleepFn = Arc<dyn Fn(Duration) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// Run `operation` until it succeeds, fails with a non-retryable error, or
+/// `policy.max_attempts` is reached, sleeping with `sleep` between attempts.
+///
+/// Returns the last error when attempts run out.
+pub async fn retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    sleep: &SleepFn,
+    mut operation: F,
+) -> Result<T, AttestationError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, AttestationError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Err(e) if e.is_retryable() && attempt < policy.max_attempts => {
+                sleep(policy.backoff(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
 }
 
 /// Registry of attestation adapters.
 ///
 /// Allows dynamic selection of adapter based on vendor name.
 pub struct AttestationRegistry {
-    adapters: std::collections::HashMap<String, Box<dyn AttestationAdapter>>,
+    adapters: HashMap<String, Box<dyn AttestationAdapter>>,
+    freshness: HashMap<String, FreshnessPolicy>,
+    nonces: Option<Arc<NonceManager>>,
+    dedup: Option<Arc<QuoteDeduplicator>>,
+    retry: Option<(RetryPolicy, SleepFn)>,
+    clock: Arc<dyn Clock>,
 }
 
 impl AttestationRegistry {
     /// Create a new empty registry.
     pub fn new() -> Self {
         Self {
-            adapters: std::collections::HashMap::new(),
+            adapters: HashMap::new(),
+            freshness: HashMap::new(),
+            nonces: None,
+            dedup: None,
+            retry: None,
+            clock: system_clock(),
         }
     }
 
+    /// Require every verified quote to carry a nonce issued by `nonces`.
+    ///
+    /// Each nonce is consumed before the quote is verified, so a quote with an
+    /// unknown, expired or reused nonce is rejected. Adapters check that the
+    /// quote commits to the nonce (e.g. in its report data), so an old quote
+    /// resubmitted with a freshly issued nonce is rejected too.
+    pub fn with_nonce_manager(mut self, nonces: Arc<NonceManager>) -> Self {
+        self.nonces = Some(nonces);
+        self
+    }
+
+    /// Answer quotes verified successfully within `dedup`'s window from it
+    /// instead of calling the adapter again.
+    ///
+    /// Applies to [`verify_quote`](Self::verify_quote) and
+    /// [`verify_evidence`](Self::verify_evidence). Nonces are consumed and
+    /// freshness is applied to cached results as to fresh ones.
+    pub fn with_dedup(mut self, dedup: Arc<QuoteDeduplicator>) -> Self {
+        self.dedup = Some(dedup);
+        self
+    }
+
+    /// Retry adapter calls that fail transiently, per `policy`.
+    ///
+    /// Nonces are consumed once, before the first attempt.
+    pub fn with_retry(mut self, policy: RetryPolicy, sleep: SleepFn) -> Self {
+        self.retry = Some((policy, sleep));
+        self
+    }
+
+    /// Hold results from `vendor` to `policy`.
+    ///
+    /// Applied after every verification: stale evidence is rejected, and a
+    /// verdict resting on stale collateral is downgraded to
+    /// [`RevocationStatus::Unknown`](crate::types::RevocationStatus::Unknown).
+    pub fn with_freshness(mut self, vendor: impl Into<String>, policy: FreshnessPolicy) -> Self {
+        self.freshness.insert(vendor.into(), policy);
+        self
+    }
+
+    /// Freshness policy configured for `vendor`, if any.
+    pub fn freshness(&self, vendor: &str) -> Option<&FreshnessPolicy> {
+        self.freshness.get(vendor)
+    }
+
+    /// Whether the device behind `result` must attest again under its
+    /// vendor's re-attestation interval.
+    pub fn reattestation_due(&self, result: &AttestationResult) -> bool {
+        self.freshness(&result.vendor)
+            .is_some_and(|policy| policy.reattestation_due(result.verified_at, self.clock.now()))
+    }
+
+    /// Judge collateral validity and freshness against `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Register an attestation adapter.
     pub fn register(&mut self, adapter: Box<dyn AttestationAdapter>) {
         let vendor = adapter.vendor_name().to_string();
@@ -105,16 +473,21 @@ impl AttestationRegistry {
         self.adapters.get(vendor).map(|b| b.as_ref())
     }
 
-    /// Get a mutable adapter by vendor name.
-    pub fn get_mut(&mut self, vendor: &str) -> Option<&mut Box<dyn AttestationAdapter>> {
-        self.adapters.get_mut(vendor)
-    }
-
     /// Get all registered vendor names.
     pub fn vendors(&self) -> Vec<&str> {
         self.adapters.keys().map(|s| s.as_str()).collect()
     }
 
+    /// Refresh trust anchors of every registered adapter concurrently.
+    ///
+    /// Returns the outcome per vendor; one adapter failing does not stop the others.
+    pub async fn update_trust_anchors(&self) -> Vec<(String, Result<(), AttestationError>)> {
+        let updates = self.adapters.iter().map(|(vendor, adapter)| async move {
+            (vendor.clone(), adapter.update_trust_anchors().await)
+        });
+        futures::future::join_all(updates).await
+    }
+
     /// Verify a quote using the appropriate adapter.
     pub async fn verify_quote(
         &self,
@@ -122,10 +495,156 @@ impl AttestationRegistry {
         quote: &[u8],
         nonce: Option<&[u8]>,
     ) -> Result<AttestationResult, AttestationError> {
-        let adapter = self.get(vendor)
+        let adapter = self
+            .get(vendor)
+            .ok_or_else(|| AttestationError::UnsupportedVendor(vendor.to_string()))?;
+
+        if let Some(nonces) = &self.nonces {
+            nonces.consume(nonce.ok_or(NonceError::Missing)?)?;
+        }
+
+        let Some(dedup) = &self.dedup else {
+            let result = self.call(|| adapter.verify_quote(quote, nonce)).await?;
+            return self.check_freshness(result, None);
+        };
+        let key = QuoteDeduplicator::key(vendor, quote, nonce);
+        if let Some(result) = dedup.lookup(&key, self.clock.now()) {
+            return self.check_freshness(result, None);
+        }
+        let result = self.call(|| adapter.verify_quote(quote, nonce)).await?;
+        dedup.record(key, &result, self.clock.now());
+        self.check_freshness(result, None)
+    }
+
+    /// Verify a batch of quotes from `vendor` in one adapter call.
+    ///
+    /// Returns one result per quote, in order; only an unknown vendor fails
+    /// the whole batch. Nonces, deduplication and freshness apply to each
+    /// quote as in [`verify_quote`](Self::verify_quote). The retry policy
+    /// does not: quotes that failed transiently can be resubmitted.
+    pub async fn verify_quotes_batch(
+        &self,
+        vendor: &str,
+        quotes: &[(&[u8], Option<&[u8]>)],
+    ) -> Result<Vec<Result<AttestationResult, AttestationError>>, AttestationError> {
+        let adapter = self
+            .get(vendor)
             .ok_or_else(|| AttestationError::UnsupportedVendor(vendor.to_string()))?;
 
-        adapter.verify_quote(quote, nonce).await
+        let mut results: Vec<Option<Result<AttestationResult, AttestationError>>> =
+            Vec::with_capacity(quotes.len());
+        let mut pending = Vec::new();
+        for (index, (quote, nonce)) in quotes.iter().enumerate() {
+            if let Some(nonces) = &self.nonces {
+                if let Err(e) = nonce
+                    .ok_or(NonceError::Missing)
+                    .and_then(|nonce| nonces.consume(nonce))
+                {
+                    results.push(Some(Err(e.into())));
+                    continue;
+                }
+            }
+            let cached = self.dedup.as_ref().and_then(|dedup| {
+                dedup.lookup(
+                    &QuoteDeduplicator::key(vendor, quote, *nonce),
+                    self.clock.now(),
+                )
+            });
+            if cached.is_none() {
+                pending.push((index, (*quote, *nonce)));
+            }
+            results.push(cached.map(Ok));
+        }
+
+        let batch: Vec<_> = pending.iter().map(|(_, quote)| *quote).collect();
+        let verified = adapter.verify_quotes_batch(&batch).await;
+        for ((index, (quote, nonce)), result) in pending.into_iter().zip(verified) {
+            if let (Some(dedup), Ok(result)) = (&self.dedup, &result) {
+                dedup.record(
+                    QuoteDeduplicator::key(vendor, quote, nonce),
+                    result,
+                    self.clock.now(),
+                );
+            }
+            results[index] = Some(result);
+        }
+
+        Ok(results
+            .into_iter()
+            .map(|result| {
+                result
+                    .unwrap_or_else(|| {
+                        Err(AttestationError::Internal(
+                            "Adapter returned too few batch results".to_string(),
+                        ))
+                    })
+                    .and_then(|result| self.check_freshness(result, None))
+            })
+            .collect())
+    }
+
+    /// Verify evidence of unknown format, selecting the adapter by [`identify_evidence`].
+    pub async fn verify_evidence(
+        &self,
+        evidence: &[u8],
+        nonce: Option<&[u8]>,
+    ) -> Result<AttestationResult, AttestationError> {
+        let kind = identify_evidence(evidence);
+        let vendor = kind.vendor().ok_or_else(|| {
+            AttestationError::InvalidQuote("Unrecognized evidence format".to_string())
+        })?;
+
+        self.verify_quote(vendor, evidence, nonce).await
+    }
+
+    /// Verify a quote offline against an imported collateral bundle.
+    ///
+    /// The adapter is selected by the bundle's vendor; expired bundles are
+    /// rejected before the adapter sees the quote.
+    pub async fn verify_with_collateral(
+        &self,
+        quote: &[u8],
+        nonce: Option<&[u8]>,
+        collateral: &CollateralBundle,
+    ) -> Result<AttestationResult, AttestationError> {
+        let adapter = self
+            .get(&collateral.vendor)
+            .ok_or_else(|| AttestationError::UnsupportedVendor(collateral.vendor.clone()))?;
+
+        collateral.check_validity(self.clock.now())?;
+
+        if let Some(nonces) = &self.nonces {
+            nonces.consume(nonce.ok_or(NonceError::Missing)?)?;
+        }
+
+        let result = self
+            .call(|| adapter.verify_with_collateral(quote, nonce, collateral))
+            .await?;
+        self.check_freshness(result, Some(collateral.fetched_at))
+    }
+
+    /// Apply the freshness policy of the result's vendor, if one is set.
+    fn check_freshness(
+        &self,
+        mut result: AttestationResult,
+        collateral_time: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<AttestationResult, AttestationError> {
+        if let Some(policy) = self.freshness(&result.vendor) {
+            policy.apply(&mut result, collateral_time, self.clock.now())?;
+        }
+        Ok(result)
+    }
+
+    /// Run an adapter call under the retry policy, if one is set.
+    async fn call<T, F, Fut>(&self, mut operation: F) -> Result<T, AttestationError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, AttestationError>>,
+    {
+        match &self.retry {
+            Some((policy, sleep)) => retry(policy, sleep, operation).await,
+            None => operation().await,
+        }
     }
 }
 
@@ -139,6 +658,10 @@ impl fmt::Debug for AttestationRegistry {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("AttestationRegistry")
             .field("vendors", &self.vendors())
+            .field("freshness", &self.freshness)
+            .field("nonces", &self.nonces)
+            .field("dedup", &self.dedup.is_some())
+            .field("retry", &self.retry.as_ref().map(|(policy, _)| policy))
             .finish()
     }
 }
@@ -146,6 +669,7 @@ impl fmt::Debug for AttestationRegistry {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::RevocationSource;
     use chrono::Utc;
 
     // Mock adapter for testing
@@ -153,6 +677,39 @@ mod tests {
         vendor: String,
     }
 
+    // Adapter whose trust anchor refresh always fails
+    struct OfflineAdapter;
+
+    #[async_trait]
+    impl AttestationAdapter for OfflineAdapter {
+        fn vendor_name(&self) -> &str {
+            "offline"
+        }
+
+        async fn verify_quote(
+            &self,
+            _quote: &[u8],
+            _nonce: Option<&[u8]>,
+        ) -> Result<AttestationResult, AttestationError> {
+            Err(AttestationError::Network("offline".to_string()))
+        }
+
+        async fn check_revocation(
+            &self,
+            _measurement: &[u8],
+        ) -> Result<RevocationCheck, AttestationError> {
+            Err(AttestationError::Network("offline".to_string()))
+        }
+
+        async fn root_ca_certs(&self) -> Vec<String> {
+            Vec::new()
+        }
+
+        async fn update_trust_anchors(&self) -> Result<(), AttestationError> {
+            Err(AttestationError::Network("offline".to_string()))
+        }
+    }
+
     #[async_trait]
     impl AttestationAdapter for MockAdapter {
         fn vendor_name(&self) -> &str {
@@ -169,21 +726,25 @@ mod tests {
                 enclave_measurement: vec![0u8; 32],
                 quote_verified: true,
                 verified_at: Utc::now(),
-                revoke_check: RevocationStatus::Ok,
+                revoke_check: RevocationCheck::ok(RevocationSource::Registry),
                 raw_quote: None,
                 pck_chain: None,
+                claims: Default::default(),
             })
         }
 
-        async fn check_revocation(&self, _measurement: &[u8]) -> Result<RevocationStatus, AttestationError> {
-            Ok(RevocationStatus::Ok)
+        async fn check_revocation(
+            &self,
+            _measurement: &[u8],
+        ) -> Result<RevocationCheck, AttestationError> {
+            Ok(RevocationCheck::ok(RevocationSource::Registry))
         }
 
-        fn root_ca_certs(&self) -> &[String] {
-            &[]
+        async fn root_ca_certs(&self) -> Vec<String> {
+            Vec::new()
         }
 
-        async fn update_trust_anchors(&mut self) -> Result<(), AttestationError> {
+        async fn update_trust_anchors(&self) -> Result<(), AttestationError> {
             Ok(())
         }
     }
@@ -202,10 +763,414 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_registry_rejects_replayed_nonce() {
+        let nonces = Arc::new(NonceManager::new(chrono::Duration::minutes(5)));
+        let mut registry = AttestationRegistry::new().with_nonce_manager(nonces.clone());
+        registry.register(Box::new(MockAdapter {
+            vendor: "mock-vendor".to_string(),
+        }));
+
+        let nonce = nonces.issue();
+        assert!(registry
+            .verify_quote("mock-vendor", b"test", Some(&nonce))
+            .await
+            .is_ok());
+
+        let replay = registry
+            .verify_quote("mock-vendor", b"test", Some(&nonce))
+            .await;
+        assert!(matches!(
+            replay,
+            Err(AttestationError::Nonce(NonceError::Reused))
+        ));
+
+        let missing = registry.verify_quote("mock-vendor", b"test", None).await;
+        assert!(matches!(
+            missing,
+            Err(AttestationError::Nonce(NonceError::Missing))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_registry_verifies_batch_per_quote() {
+        let nonces = Arc::new(NonceManager::new(chrono::Duration::minutes(5)));
+        let mut registry = AttestationRegistry::new().with_nonce_manager(nonces.clone());
+        registry.register(Box::new(MockAdapter {
+            vendor: "mock-vendor".to_string(),
+        }));
+
+        let (first, second) = (nonces.issue(), nonces.issue());
+        let batch = [
+            (b"a".as_slice(), Some(first.as_slice())),
+            (b"b".as_slice(), Some(first.as_slice())),
+            (b"c".as_slice(), None),
+            (b"d".as_slice(), Some(second.as_slice())),
+        ];
+        let results = registry
+            .verify_quotes_batch("mock-vendor", &batch)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok() && results[3].is_ok());
+        assert!(matches!(
+            results[1],
+            Err(AttestationError::Nonce(NonceError::Reused))
+        ));
+        assert!(matches!(
+            results[2],
+            Err(AttestationError::Nonce(NonceError::Missing))
+        ));
+
+        let unknown = registry.verify_quotes_batch("other", &batch).await;
+        assert!(matches!(
+            unknown,
+            Err(AttestationError::UnsupportedVendor(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_registry_dedups_resubmitted_quotes() {
+        let nonces = Arc::new(NonceManager::new(chrono::Duration::minutes(5)));
+        let dedup = Arc::new(QuoteDeduplicator::new(chrono::Duration::minutes(10)));
+        let mut registry = AttestationRegistry::new().with_dedup(dedup.clone());
+        registry.register(Box::new(MockAdapter {
+            vendor: "mock-vendor".to_string(),
+        }));
+
+        // The cached result keeps the first verification's timestamp
+        let first = registry
+            .verify_quote("mock-vendor", b"test", None)
+            .await
+            .unwrap();
+        let second = registry
+            .verify_quote("mock-vendor", b"test", None)
+            .await
+            .unwrap();
+        assert_eq!(first.verified_at, second.verified_at);
+        assert_eq!(dedup.len(), 1);
+        registry
+            .verify_quote("mock-vendor", b"other", None)
+            .await
+            .unwrap();
+        assert_eq!(dedup.len(), 2);
+
+        // Replays are still caught by the nonce check before the cache
+        let mut registry = AttestationRegistry::new()
+            .with_dedup(dedup)
+            .with_nonce_manager(nonces.clone());
+        registry.register(Box::new(MockAdapter {
+            vendor: "mock-vendor".to_string(),
+        }));
+        let nonce = nonces.issue();
+        registry
+            .verify_quote("mock-vendor", b"test", Some(&nonce))
+            .await
+            .unwrap();
+        let replay = registry
+            .verify_quote("mock-vendor", b"test", Some(&nonce))
+            .await;
+        assert!(matches!(
+            replay,
+            Err(AttestationError::Nonce(NonceError::Reused))
+        ));
+    }
+
+    /// Adapter failing with the queued errors before succeeding
+    struct FlakyAdapter {
+        failures: std::sync::Mutex<Vec<AttestationError>>,
+        calls: std::sync::atomic::AtomicU32,
+    }
+
+    #[async_trait]
+    impl AttestationAdapter for FlakyAdapter {
+        fn vendor_name(&self) -> &str {
+            "flaky"
+        }
+
+        async fn verify_quote(
+            &self,
+            quote: &[u8],
+            nonce: Option<&[u8]>,
+        ) -> Result<AttestationResult, AttestationError> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let failure = self.failures.lock().unwrap().pop();
+            match failure {
+                Some(e) => Err(e),
+                None => {
+                    MockAdapter {
+                        vendor: "flaky".to_string(),
+                    }
+                    .verify_quote(quote, nonce)
+                    .await
+                }
+            }
+        }
+
+        async fn check_revocation(
+            &self,
+            _measurement: &[u8],
+        ) -> Result<RevocationCheck, AttestationError> {
+            Ok(RevocationCheck::ok(RevocationSource::Registry))
+        }
+
+        async fn root_ca_certs(&self) -> Vec<String> {
+            Vec::new()
+        }
+
+        async fn update_trust_anchors(&self) -> Result<(), AttestationError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_error_categories() {
+        assert!(AttestationError::Network("timeout".to_string()).is_retryable());
+        assert!(AttestationError::RevocationCheckFailed("PCS 503".to_string()).is_retryable());
+        assert_eq!(
+            AttestationError::InvalidQuote("short".to_string()).category(),
+            ErrorCategory::MalformedEvidence
+        );
+        assert_eq!(
+            AttestationError::MeasurementRevoked.category(),
+            ErrorCategory::Policy
+        );
+        assert_eq!(
+            AttestationError::Nonce(NonceError::Reused).category(),
+            ErrorCategory::Policy
+        );
+        assert_eq!(
+            AttestationError::Collateral(CollateralError::Unsupported("x".to_string())).category(),
+            ErrorCategory::Configuration
+        );
+        assert!(!AttestationError::Internal("bug".to_string()).is_retryable());
+
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(800));
+        assert_eq!(policy.backoff(20), Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_registry_retries_only_transient_failures() {
+        let slept = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorder = slept.clone();
+        let sleep: SleepFn = Arc::new(move |delay| {
+            recorder.lock().unwrap().push(delay);
+            Box::pin(async {})
+        });
+        let flaky = |failures: Vec<AttestationError>| FlakyAdapter {
+            failures: std::sync::Mutex::new(failures),
+            calls: Default::default(),
+        };
+
+        let mut registry =
+            AttestationRegistry::new().with_retry(RetryPolicy::default(), sleep.clone());
+        registry.register(Box::new(flaky(vec![
+            AttestationError::Network("reset".to_string()),
+            AttestationError::Network("timeout".to_string()),
+        ])));
+        registry.verify_quote("flaky", b"test", None).await.unwrap();
+        assert_eq!(
+            *slept.lock().unwrap(),
+            [Duration::from_millis(200), Duration::from_millis(400)]
+        );
+
+        // Malformed evidence fails at once
+        slept.lock().unwrap().clear();
+        let mut registry =
+            AttestationRegistry::new().with_retry(RetryPolicy::default(), sleep.clone());
+        registry.register(Box::new(flaky(vec![AttestationError::InvalidQuote(
+            "short".to_string(),
+        )])));
+        let err = registry
+            .verify_quote("flaky", b"test", None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.category(), ErrorCategory::MalformedEvidence);
+        assert!(slept.lock().unwrap().is_empty());
+
+        // Attempts run out on a persistent outage
+        let outage = (0..5)
+            .map(|_| AttestationError::Network("down".to_string()))
+            .collect();
+        let adapter = flaky(outage);
+        let err = retry(&RetryPolicy::default(), &sleep, || {
+            adapter.verify_quote(b"test", None)
+        })
+        .await
+        .unwrap_err();
+        assert!(err.is_retryable());
+        assert_eq!(adapter.calls.load(std::sync::atomic::Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_registry_applies_vendor_freshness() {
+        use crate::clock::MockClock;
+        use crate::types::RevocationStatus;
+
+        let now = Utc::now();
+        let clock = Arc::new(MockClock::new(now));
+        let policy = FreshnessPolicy::default()
+            .with_max_collateral_age(chrono::Duration::days(7))
+            .with_reattestation_interval(chrono::Duration::hours(1));
+        let mut registry = AttestationRegistry::new()
+            .with_clock(clock.clone())
+            .with_freshness("mock-vendor", policy);
+        registry.register(Box::new(MockAdapter {
+            vendor: "mock-vendor".to_string(),
+        }));
+
+        let mut result = registry
+            .verify_quote("mock-vendor", b"test", None)
+            .await
+            .unwrap();
+        assert_eq!(result.revoke_check.status, RevocationStatus::Ok);
+
+        // Collateral fetched ten days ago no longer backs an Ok verdict
+        let stale = registry
+            .check_freshness(result.clone(), Some(now - chrono::Duration::days(10)))
+            .unwrap();
+        assert_eq!(stale.revoke_check.status, RevocationStatus::Unknown);
+
+        result.verified_at = now;
+        assert!(!registry.reattestation_due(&result));
+        clock.advance(chrono::Duration::hours(2));
+        assert!(registry.reattestation_due(&result));
+
+        // Vendors without a policy are left alone
+        result.vendor = "other".to_string();
+        assert!(!registry.reattestation_due(&result));
+    }
+
     #[tokio::test]
     async fn test_unsupported_vendor() {
         let registry = AttestationRegistry::new();
         let result = registry.verify_quote("nonexistent", b"test", None).await;
-        assert!(matches!(result, Err(AttestationError::UnsupportedVendor(_))));
+        assert!(matches!(
+            result,
+            Err(AttestationError::UnsupportedVendor(_))
+        ));
+
+        let detail = result.unwrap_err().detail();
+        assert_eq!(detail.code.as_str(), "VB-ATT-006");
+    }
+
+    #[tokio::test]
+    async fn test_update_all_trust_anchors() {
+        let mut registry = AttestationRegistry::new();
+        registry.register(Box::new(MockAdapter {
+            vendor: "mock-vendor".to_string(),
+        }));
+        registry.register(Box::new(OfflineAdapter));
+
+        let mut outcomes = registry.update_trust_anchors().await;
+        outcomes.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes[0].1.is_ok());
+        assert!(matches!(outcomes[1].1, Err(AttestationError::Network(_))));
+    }
+
+    fn dcap_header(version: u16, tee_type: u32) -> Vec<u8> {
+        let mut quote = vec![0u8; 48 + 384];
+        quote[0..2].copy_from_slice(&version.to_le_bytes());
+        quote[2..4].copy_from_slice(&2u16.to_le_bytes());
+        quote[4..8].copy_from_slice(&tee_type.to_le_bytes());
+        quote
+    }
+
+    #[test]
+    fn test_identify_evidence() {
+        assert_eq!(
+            identify_evidence(&dcap_header(3, 0)),
+            EvidenceKind::SgxQuoteV3
+        );
+        assert_eq!(
+            identify_evidence(&dcap_header(4, 0)),
+            EvidenceKind::SgxQuoteV4
+        );
+        assert_eq!(
+            identify_evidence(&dcap_header(4, 0x81)),
+            EvidenceKind::TdxQuoteV4
+        );
+
+        let mut snp = vec![0u8; SEV_SNP_REPORT_LEN];
+        snp[0] = 2;
+        snp[0x34] = 1;
+        assert_eq!(identify_evidence(&snp), EvidenceKind::SevSnpReport);
+
+        let tpm = [
+            &[0x00, 0x10][..],
+            &TPM_GENERATED_VALUE,
+            &TPM_ST_ATTEST_QUOTE,
+        ]
+        .concat();
+        assert_eq!(identify_evidence(&tpm), EvidenceKind::TpmQuote);
+
+        assert_eq!(
+            identify_evidence(&[0x84, 0x44, 0xA1, 0x01, 0x38, 0x22]),
+            EvidenceKind::NitroCose
+        );
+        assert_eq!(
+            identify_evidence(&[0xD8, 0x3D, 0xD2, 0x84]),
+            EvidenceKind::Eat
+        );
+        assert_eq!(
+            identify_evidence(b"eyJhbGciOiJFUzI1NiJ9"),
+            EvidenceKind::Eat
+        );
+        assert_eq!(identify_evidence(b"hello"), EvidenceKind::Unknown);
+    }
+
+    #[tokio::test]
+    async fn test_verify_evidence_routes_by_kind() {
+        let mut registry = AttestationRegistry::new();
+        registry.register(Box::new(MockAdapter {
+            vendor: "intel-sgx".to_string(),
+        }));
+
+        let result = registry
+            .verify_evidence(&dcap_header(3, 0), None)
+            .await
+            .unwrap();
+        assert_eq!(result.vendor, "intel-sgx");
+
+        let tdx = registry.verify_evidence(&dcap_header(4, 0x81), None).await;
+        assert!(matches!(tdx, Err(AttestationError::UnsupportedVendor(v)) if v == "intel-tdx"));
+
+        let unknown = registry.verify_evidence(b"hello", None).await;
+        assert!(matches!(unknown, Err(AttestationError::InvalidQuote(_))));
+    }
+
+    #[tokio::test]
+    async fn test_verify_with_collateral() {
+        let mut registry = AttestationRegistry::new();
+        registry.register(Box::new(MockAdapter {
+            vendor: "mock-vendor".to_string(),
+        }));
+
+        // Mock adapter keeps the default (unsupported) hook
+        let now = Utc::now();
+        let bundle = CollateralBundle::new("mock-vendor", now, now + chrono::Duration::days(1));
+        let err = registry
+            .verify_with_collateral(b"test", None, &bundle)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AttestationError::Collateral(CollateralError::Unsupported(_))
+        ));
+        assert_eq!(err.code(), ErrorCode::CollateralUnavailable);
+
+        let expired = CollateralBundle::new(
+            "mock-vendor",
+            now - chrono::Duration::days(2),
+            now - chrono::Duration::days(1),
+        );
+        let err = registry
+            .verify_with_collateral(b"test", None, &expired)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::CollateralInvalid);
     }
 }
@@ -0,0 +1,414 @@
+//! Append-only audit log of verification decisions.
+//!
+//! Tracing output is for operators and may be sampled, rotated or dropped.
+//! Compliance needs a record that is complete and tamper-evident instead: an
+//! [`AuditLog`] turns every accept/reject decision a verifier makes into a
+//! signed [`AuditEvent`] and appends it to an [`AuditSink`] (a local file, a
+//! WORM bucket, or a `Vec` in tests).
+//!
+//! Events are numbered from 0 and each one commits to the hash of its
+//! predecessor, so dropping, reordering or editing an event breaks the chain
+//! for everything after it. [`AuditExport`] hands a contiguous range of
+//! events to an auditor, who checks it with [`AuditExport::verify`] against
+//! the verifier's published key.
+
+use crate::clock::{system_clock, Clock};
+use crate::crypto::{ct_eq, sha256, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, KeyId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Durable storage for audit events. Implementations only ever append.
+pub trait AuditSink: Send {
+    /// Persist `event` after every event appended before it.
+    fn append(&mut self, event: &AuditEvent) -> Result<(), AuditError>;
+}
+
+/// In-memory sink, for tests and short-lived tools.
+impl AuditSink for Vec<AuditEvent> {
+    fn append(&mut self, event: &AuditEvent) -> Result<(), AuditError> {
+        self.push(event.clone());
+        Ok(())
+    }
+}
+
+/// Outcome of a verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Accept,
+    Reject,
+}
+
+/// One signed verification decision.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    /// Position in the log (starts at 0, never reused)
+    pub index: u64,
+    /// [`AuditEvent::hash`] of the previous event (zero for the first)
+    pub prev_hash: Hash256,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub at: DateTime<Utc>,
+    /// Hash of the checkpoint the decision is about
+    pub checkpoint_hash: Hash256,
+    /// Policy the checkpoint was judged against
+    pub policy_id: String,
+    pub decision: Decision,
+    /// Why the checkpoint was rejected (empty when accepted)
+    pub error_codes: Vec<ErrorCode>,
+    /// Name of the verifier instance (e.g., "gateway-eu-1")
+    pub verifier: String,
+    /// Fingerprint of the verifier's signing key
+    pub verifier_key_id: KeyId,
+    /// Ed25519 signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned event (for signature computation)
+#[derive(Serialize)]
+struct UnsignedAuditEvent<'a> {
+    index: u64,
+    prev_hash: Hash256,
+    #[serde(with = "crate::serialization::timestamp")]
+    at: DateTime<Utc>,
+    checkpoint_hash: Hash256,
+    policy_id: &'a str,
+    decision: Decision,
+    error_codes: &'a [ErrorCode],
+    verifier: &'a str,
+    verifier_key_id: KeyId,
+}
+
+impl AuditEvent {
+    /// SHA-256 of the canonical CBOR encoding of the signed event.
+    pub fn hash(&self) -> Result<Hash256, SerializationError> {
+        Ok(sha256(&to_canonical_cbor(self)?))
+    }
+
+    /// Check the event's signature against the verifier keys in `keys`.
+    pub fn verify(&self, keys: &dyn KeyResolver) -> Result<(), AuditError> {
+        use ed25519_dalek::Verifier;
+
+        let verifying_key = keys
+            .resolve(&self.verifier_key_id)
+            .ok_or(AuditError::UnknownVerifier(self.verifier_key_id))?;
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        verifying_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| AuditError::InvalidSignature { index: self.index })
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedAuditEvent {
+            index: self.index,
+            prev_hash: self.prev_hash,
+            at: self.at,
+            checkpoint_hash: self.checkpoint_hash,
+            policy_id: &self.policy_id,
+            decision: self.decision,
+            error_codes: &self.error_codes,
+            verifier: &self.verifier,
+            verifier_key_id: self.verifier_key_id,
+        })
+    }
+}
+
+/// Signs verification decisions and appends them to a sink.
+pub struct AuditLog {
+    signer: Signer,
+    verifier: String,
+    sink: Box<dyn AuditSink>,
+    clock: Arc<dyn Clock>,
+    next_index: u64,
+    last_hash: Hash256,
+}
+
+impl AuditLog {
+    /// Start a new log for the verifier instance `verifier`.
+    pub fn new(signer: Signer, verifier: impl Into<String>, sink: Box<dyn AuditSink>) -> Self {
+        Self {
+            signer,
+            verifier: verifier.into(),
+            sink,
+            clock: system_clock(),
+            next_index: 0,
+            last_hash: [0u8; 32],
+        }
+    }
+
+    /// Timestamp events with `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Continue a log whose last persisted event is `last` (e.g., after a restart).
+    pub fn resume(mut self, last: &AuditEvent) -> Result<Self, AuditError> {
+        self.next_index = last.index + 1;
+        self.last_hash = last.hash()?;
+        Ok(self)
+    }
+
+    /// Record that the checkpoint with hash `checkpoint_hash` passed `policy_id`.
+    pub fn accept(
+        &mut self,
+        checkpoint_hash: Hash256,
+        policy_id: &str,
+    ) -> Result<AuditEvent, AuditError> {
+        self.append(checkpoint_hash, policy_id, Decision::Accept, Vec::new())
+    }
+
+    /// Record that the checkpoint failed `policy_id` for the given reasons.
+    pub fn reject(
+        &mut self,
+        checkpoint_hash: Hash256,
+        policy_id: &str,
+        error_codes: Vec<ErrorCode>,
+    ) -> Result<AuditEvent, AuditError> {
+        self.append(checkpoint_hash, policy_id, Decision::Reject, error_codes)
+    }
+
+    /// Record the outcome of a verification call.
+    pub fn record<T, E: ErrorCoded>(
+        &mut self,
+        checkpoint_hash: Hash256,
+        policy_id: &str,
+        outcome: &Result<T, E>,
+    ) -> Result<AuditEvent, AuditError> {
+        match outcome {
+            Ok(_) => self.accept(checkpoint_hash, policy_id),
+            Err(e) => self.reject(checkpoint_hash, policy_id, vec![e.code()]),
+        }
+    }
+
+    /// Index the next event will get.
+    pub fn next_index(&self) -> u64 {
+        self.next_index
+    }
+
+    /// Sign the event and append it. The log only advances once the sink
+    /// has accepted the event, so a failed append can be retried without
+    /// leaving a hole.
+    fn append(
+        &mut self,
+        checkpoint_hash: Hash256,
+        policy_id: &str,
+        decision: Decision,
+        error_codes: Vec<ErrorCode>,
+    ) -> Result<AuditEvent, AuditError> {
+        let mut event = AuditEvent {
+            index: self.next_index,
+            prev_hash: self.last_hash,
+            at: self.clock.now(),
+            checkpoint_hash,
+            policy_id: policy_id.to_string(),
+            decision,
+            error_codes,
+            verifier: self.verifier.clone(),
+            verifier_key_id: self.signer.key_id(),
+            signature: SignatureBytes([0u8; 64]),
+        };
+        let signature = self.signer.sign(&event.signing_payload()?);
+        event.signature = SignatureBytes::from(signature.to_bytes());
+
+        self.sink.append(&event)?;
+        self.next_index += 1;
+        self.last_hash = event.hash()?;
+        Ok(event)
+    }
+}
+
+/// A contiguous range of audit events handed to an auditor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditExport {
+    #[serde(with = "crate::serialization::timestamp")]
+    pub exported_at: DateTime<Utc>,
+    pub events: Vec<AuditEvent>,
+}
+
+impl AuditExport {
+    /// Export the events of `log` (in log order) recorded in `[from, to)`.
+    pub fn range(log: &[AuditEvent], from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        Self {
+            exported_at: Utc::now(),
+            events: log
+                .iter()
+                .skip_while(|event| event.at < from)
+                .take_while(|event| event.at < to)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Export every event from `index` on.
+    pub fn since(log: &[AuditEvent], index: u64) -> Self {
+        Self {
+            exported_at: Utc::now(),
+            events: log
+                .iter()
+                .filter(|event| event.index >= index)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    /// Check that the events are signed by known verifiers and form an
+    /// unbroken stretch of the log: consecutive indices, each linking to
+    /// the hash of the one before.
+    ///
+    /// The first event's `prev_hash` is taken as given; an auditor holding
+    /// the preceding export can compare it with that export's last event.
+    pub fn verify(&self, keys: &dyn KeyResolver) -> Result<(), AuditError> {
+        let mut previous: Option<(u64, Hash256)> = None;
+        for event in &self.events {
+            if let Some((index, hash)) = previous {
+                if event.index != index + 1 || !ct_eq(&event.prev_hash, &hash) {
+                    return Err(AuditError::Broken { index: event.index });
+                }
+            }
+            event.verify(keys)?;
+            previous = Some((event.index, event.hash()?));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AuditError {
+    #[error("Audit sink failed: {0}")]
+    Sink(String),
+
+    #[error("Audit log broken at event {index} (missing, reordered or altered event)")]
+    Broken { index: u64 },
+
+    #[error("Audit event signed by unknown verifier key {0}")]
+    UnknownVerifier(KeyId),
+
+    #[error("Invalid signature on audit event {index}")]
+    InvalidSignature { index: u64 },
+
+    #[error("Audit event serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+}
+
+impl ErrorCoded for AuditError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            AuditError::Sink(_) => ErrorCode::AuditSinkFailed,
+            AuditError::Broken { .. } => ErrorCode::AuditLogBroken,
+            AuditError::UnknownVerifier(_) | AuditError::InvalidSignature { .. } => {
+                ErrorCode::AuditEventInvalid
+            }
+            AuditError::Serialization(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Sink shared with the test, failing on demand like a full disk.
+    #[derive(Clone, Default)]
+    struct SharedSink {
+        events: Arc<Mutex<Vec<AuditEvent>>>,
+        fail: Arc<Mutex<bool>>,
+    }
+
+    impl AuditSink for SharedSink {
+        fn append(&mut self, event: &AuditEvent) -> Result<(), AuditError> {
+            if *self.fail.lock().unwrap() {
+                return Err(AuditError::Sink("disk full".to_string()));
+            }
+            self.events.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_decisions_are_signed_chained_and_exported() {
+        let signer = Signer::generate();
+        let verifying_key = signer.verifying_key();
+        let sink = SharedSink::default();
+        let mut log = AuditLog::new(signer, "gateway-eu-1", Box::new(sink.clone()));
+
+        log.accept([1u8; 32], "fleet-default-v1").unwrap();
+        let outcome: Result<(), crate::chain::ChainError> =
+            Err(crate::chain::ChainError::InvalidGenesis);
+        let rejected = log.record([2u8; 32], "fleet-default-v1", &outcome).unwrap();
+        assert_eq!(rejected.decision, Decision::Reject);
+        assert_eq!(rejected.error_codes, [ErrorCode::InvalidGenesis]);
+
+        // A failed append leaves no hole
+        *sink.fail.lock().unwrap() = true;
+        let err = log.accept([3u8; 32], "fleet-default-v1").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::AuditSinkFailed);
+        *sink.fail.lock().unwrap() = false;
+        log.accept([3u8; 32], "fleet-default-v1").unwrap();
+
+        let events = sink.events.lock().unwrap().clone();
+        assert_eq!(
+            events.iter().map(|e| e.index).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert_eq!(events[1].prev_hash, events[0].hash().unwrap());
+
+        let export = AuditExport::since(&events, 1);
+        let decoded = AuditExport::from_bytes(&export.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, export);
+        decoded.verify(&verifying_key).unwrap();
+        AuditExport::range(
+            &events,
+            events[0].at,
+            Utc::now() + chrono::Duration::seconds(1),
+        )
+        .verify(&verifying_key)
+        .unwrap();
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let signer = Signer::generate();
+        let verifying_key = signer.verifying_key();
+        let mut log = AuditLog::new(signer, "gateway-eu-1", Box::new(Vec::new()));
+        let events: Vec<AuditEvent> = (0..4u8)
+            .map(|i| log.accept([i; 32], "fleet-default-v1").unwrap())
+            .collect();
+
+        // Dropping an event breaks the chain
+        let mut dropped = AuditExport::since(&events, 0);
+        dropped.events.remove(2);
+        let err = dropped.verify(&verifying_key).unwrap_err();
+        assert!(matches!(err, AuditError::Broken { index: 3 }));
+
+        // Turning a rejection into an acceptance breaks the signature
+        let mut edited = AuditExport::since(&events, 0);
+        edited.events[1].decision = Decision::Reject;
+        assert_eq!(
+            edited.verify(&verifying_key).unwrap_err().code(),
+            ErrorCode::AuditEventInvalid
+        );
+
+        // A resumed log continues the chain
+        let mut resumed = AuditLog::new(Signer::generate(), "gateway-eu-1", Box::new(Vec::new()))
+            .resume(events.last().unwrap())
+            .unwrap();
+        let next = resumed.accept([9u8; 32], "fleet-default-v1").unwrap();
+        assert_eq!(next.index, 4);
+        assert_eq!(next.prev_hash, events[3].hash().unwrap());
+    }
+}
@@ -0,0 +1,362 @@
+//! Authentication and per-route authorization of gateway callers.
+//!
+//! Robots authenticate with mTLS client certificates, operators with API
+//! keys. TLS termination and header parsing stay in the host; it hands the
+//! gateway a [`Credential`] describing what the caller presented:
+//!
+//! - a verified client certificate, by SHA-256 fingerprint of its DER and
+//!   its SPIFFE ID (URI SAN) if it has one. The [`AuthConfig`] maps either
+//!   to a robot identity
+//! - an API key, which is hashed and compared in constant time against the
+//!   configured key hashes. Each key carries an [`OperatorRole`] and may be
+//!   limited to one tenant's robots
+//!
+//! [`Authenticator::authorize`] resolves the credential to a [`Principal`]
+//! and checks it may call the [`Route`]. A missing or unknown credential is
+//! [`AuthError::Unauthenticated`] (HTTP 401), a known caller outside its
+//! rights [`AuthError::Forbidden`] (HTTP 403); both carry an error code for
+//! the structured response body ([`ErrorCoded::detail`]).
+//!
+//! Credentials come from the [`LiveConfig`] in force at each call, so a
+//! reload adds or revokes them without a restart.
+
+use crate::crypto::{ct_eq, sha256};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::reload::{decode_hash, AuthConfig, GatewayConfig, LiveConfig, OperatorRole};
+use crate::types::{Hash256, RobotId};
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// What a caller presented.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Credential {
+    /// Client certificate the TLS layer verified
+    ClientCertificate {
+        /// SHA-256 of the certificate DER
+        fingerprint: Hash256,
+        spiffe_id: Option<String>,
+    },
+    /// Key from the `Authorization: Bearer` header
+    ApiKey(String),
+}
+
+/// An authenticated caller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Principal {
+    Robot(RobotId),
+    Operator {
+        name: String,
+        role: OperatorRole,
+        /// Tenant the operator's key is limited to
+        tenant: Option<String>,
+    },
+}
+
+impl fmt::Display for Principal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Principal::Robot(robot_id) => write!(f, "robot {}", robot_id.0),
+            Principal::Operator { name, .. } => write!(f, "operator {name}"),
+        }
+    }
+}
+
+/// Gateway routes, by what they give access to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Route {
+    /// Submit checkpoints, entries and quotes of a robot; the robot only
+    SubmitCheckpoint(RobotId),
+    /// Read one robot's checkpoints and events; the robot or a viewer
+    ReadRobot(RobotId),
+    /// Read across the fleet; viewers not limited to a tenant
+    ReadFleet,
+    /// Review quarantined submissions; reviewers
+    ReviewQuarantine,
+    /// Reload config, manage keys; admins
+    Administer,
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Route::SubmitCheckpoint(robot_id) => {
+                write!(f, "submit checkpoints of robot {}", robot_id.0)
+            }
+            Route::ReadRobot(robot_id) => write!(f, "read robot {}", robot_id.0),
+            Route::ReadFleet => f.write_str("read the fleet"),
+            Route::ReviewQuarantine => f.write_str("review quarantined submissions"),
+            Route::Administer => f.write_str("administer the gateway"),
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AuthError {
+    #[error("Unauthenticated: {0}")]
+    Unauthenticated(String),
+
+    #[error("{principal} may not {route}")]
+    Forbidden { principal: String, route: String },
+}
+
+impl AuthError {
+    /// HTTP status of the response.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            AuthError::Unauthenticated(_) => 401,
+            AuthError::Forbidden { .. } => 403,
+        }
+    }
+}
+
+impl ErrorCoded for AuthError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            AuthError::Unauthenticated(_) => ErrorCode::Unauthenticated,
+            AuthError::Forbidden { .. } => ErrorCode::Forbidden,
+        }
+    }
+}
+
+/// Authenticates callers against the live gateway config.
+pub struct Authenticator {
+    live: Arc<LiveConfig>,
+}
+
+impl Authenticator {
+    pub fn new(live: Arc<LiveConfig>) -> Self {
+        Self { live }
+    }
+
+    /// Resolve `credential` to a principal allowed to call `route`.
+    pub fn authorize(
+        &self,
+        credential: Option<&Credential>,
+        route: &Route,
+    ) -> Result<Principal, AuthError> {
+        let config = self.live.snapshot();
+        let principal = authenticate(&config.auth, credential)?;
+        if !permits(&config, &principal, route) {
+            return Err(AuthError::Forbidden {
+                principal: principal.to_string(),
+                route: route.to_string(),
+            });
+        }
+        Ok(principal)
+    }
+}
+
+fn authenticate(
+    auth: &AuthConfig,
+    credential: Option<&Credential>,
+) -> Result<Principal, AuthError> {
+    match credential {
+        None => Err(AuthError::Unauthenticated(
+            "no client certificate or API key".to_string(),
+        )),
+        Some(Credential::ClientCertificate {
+            fingerprint,
+            spiffe_id,
+        }) => auth
+            .clients
+            .iter()
+            .find(|client| {
+                let by_fingerprint = client
+                    .fingerprint
+                    .as_deref()
+                    .and_then(|hex| decode_hash(hex).ok())
+                    .is_some_and(|expected| ct_eq(&expected, fingerprint));
+                let by_spiffe = client.spiffe_id.is_some() && client.spiffe_id == *spiffe_id;
+                by_fingerprint || by_spiffe
+            })
+            .map(|client| Principal::Robot(client.robot_id.clone()))
+            .ok_or_else(|| {
+                AuthError::Unauthenticated("client certificate is not enrolled".to_string())
+            }),
+        Some(Credential::ApiKey(key)) => {
+            let presented = sha256(key.as_bytes());
+            // Compare against every key so timing does not reveal which matched
+            let mut matched = None;
+            for api_key in &auth.api_keys {
+                let hit = decode_hash(&api_key.key_hash)
+                    .is_ok_and(|expected| ct_eq(&expected, &presented));
+                if hit && matched.is_none() {
+                    matched = Some(api_key);
+                }
+            }
+            matched
+                .map(|api_key| Principal::Operator {
+                    name: api_key.name.clone(),
+                    role: api_key.role,
+                    tenant: api_key.tenant.clone(),
+                })
+                .ok_or_else(|| AuthError::Unauthenticated("API key is not valid".to_string()))
+        }
+    }
+}
+
+fn permits(config: &GatewayConfig, principal: &Principal, route: &Route) -> bool {
+    match principal {
+        Principal::Robot(robot_id) => match route {
+            Route::SubmitCheckpoint(target) | Route::ReadRobot(target) => target == robot_id,
+            _ => false,
+        },
+        Principal::Operator { role, tenant, .. } => {
+            let in_tenant = |robot_id: &RobotId| {
+                tenant
+                    .as_deref()
+                    .is_none_or(|id| config.tenant_of(robot_id).is_some_and(|t| t.id == id))
+            };
+            match route {
+                Route::SubmitCheckpoint(_) => false,
+                Route::ReadRobot(robot_id) => in_tenant(robot_id),
+                Route::ReadFleet => tenant.is_none(),
+                Route::ReviewQuarantine => *role >= OperatorRole::Reviewer && tenant.is_none(),
+                Route::Administer => *role == OperatorRole::Admin && tenant.is_none(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::TrustRequirements;
+    use crate::reload::{ApiKey, ClientCertificate, Tenant, TrustAnchors};
+
+    fn api_key(name: &str, role: OperatorRole, tenant: Option<&str>) -> ApiKey {
+        ApiKey {
+            name: name.to_string(),
+            key_hash: hex::encode(sha256(format!("{name}-secret").as_bytes())),
+            role,
+            tenant: tenant.map(str::to_string),
+        }
+    }
+
+    fn authenticator() -> Authenticator {
+        let config = GatewayConfig {
+            revision: 1,
+            trust: TrustRequirements::default(),
+            trust_anchors: TrustAnchors::default(),
+            rate_limit: None,
+            quota: None,
+            tenants: vec![Tenant {
+                id: "acme".to_string(),
+                robots: vec![RobotId("R-001".to_string())],
+                signing_keys: Vec::new(),
+                rate_limit: None,
+                quota: None,
+                trust: None,
+            }],
+            auth: AuthConfig {
+                clients: vec![
+                    ClientCertificate {
+                        robot_id: RobotId("R-001".to_string()),
+                        fingerprint: Some(hex::encode([1u8; 32])),
+                        spiffe_id: None,
+                    },
+                    ClientCertificate {
+                        robot_id: RobotId("R-002".to_string()),
+                        fingerprint: None,
+                        spiffe_id: Some("spiffe://fleet.example/robot/R-002".to_string()),
+                    },
+                ],
+                api_keys: vec![
+                    api_key("ops", OperatorRole::Admin, None),
+                    api_key("forensics", OperatorRole::Reviewer, None),
+                    api_key("acme-dashboard", OperatorRole::Viewer, Some("acme")),
+                ],
+            },
+        };
+        Authenticator::new(Arc::new(LiveConfig::new(config).unwrap()))
+    }
+
+    fn certificate(fingerprint: u8, spiffe_id: Option<&str>) -> Credential {
+        Credential::ClientCertificate {
+            fingerprint: [fingerprint; 32],
+            spiffe_id: spiffe_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_robots_authenticate_by_certificate_and_reach_only_themselves() {
+        let auth = authenticator();
+        let (r1, r2) = (RobotId("R-001".to_string()), RobotId("R-002".to_string()));
+
+        let principal = auth
+            .authorize(
+                Some(&certificate(1, None)),
+                &Route::SubmitCheckpoint(r1.clone()),
+            )
+            .unwrap();
+        assert_eq!(principal, Principal::Robot(r1.clone()));
+        let by_spiffe = certificate(9, Some("spiffe://fleet.example/robot/R-002"));
+        assert_eq!(
+            auth.authorize(Some(&by_spiffe), &Route::ReadRobot(r2.clone()))
+                .unwrap(),
+            Principal::Robot(r2.clone())
+        );
+
+        let err = auth
+            .authorize(Some(&certificate(1, None)), &Route::SubmitCheckpoint(r2))
+            .unwrap_err();
+        assert_eq!((err.http_status(), err.code()), (403, ErrorCode::Forbidden));
+        assert!(auth
+            .authorize(Some(&certificate(1, None)), &Route::ReadFleet)
+            .is_err());
+
+        let err = auth
+            .authorize(
+                Some(&certificate(2, None)),
+                &Route::SubmitCheckpoint(r1.clone()),
+            )
+            .unwrap_err();
+        assert_eq!(
+            (err.http_status(), err.code()),
+            (401, ErrorCode::Unauthenticated)
+        );
+        let err = auth.authorize(None, &Route::ReadRobot(r1)).unwrap_err();
+        assert_eq!(err.detail().code, ErrorCode::Unauthenticated);
+    }
+
+    #[test]
+    fn test_operators_authorized_by_role_and_tenant() {
+        let auth = authenticator();
+        let key = |name: &str| Credential::ApiKey(format!("{name}-secret"));
+        let (r1, r2) = (RobotId("R-001".to_string()), RobotId("R-002".to_string()));
+
+        auth.authorize(Some(&key("ops")), &Route::Administer)
+            .unwrap();
+        auth.authorize(Some(&key("forensics")), &Route::ReviewQuarantine)
+            .unwrap();
+        assert_eq!(
+            auth.authorize(Some(&key("forensics")), &Route::Administer)
+                .unwrap_err()
+                .http_status(),
+            403
+        );
+        // No operator submits evidence on a robot's behalf
+        assert!(auth
+            .authorize(Some(&key("ops")), &Route::SubmitCheckpoint(r1.clone()))
+            .is_err());
+
+        // A tenant's key reads only that tenant's robots
+        auth.authorize(Some(&key("acme-dashboard")), &Route::ReadRobot(r1))
+            .unwrap();
+        assert!(auth
+            .authorize(Some(&key("acme-dashboard")), &Route::ReadRobot(r2))
+            .is_err());
+        assert!(auth
+            .authorize(Some(&key("acme-dashboard")), &Route::ReadFleet)
+            .is_err());
+
+        let err = auth
+            .authorize(
+                Some(&Credential::ApiKey("guess".to_string())),
+                &Route::ReadFleet,
+            )
+            .unwrap_err();
+        assert_eq!(err.http_status(), 401);
+    }
+}
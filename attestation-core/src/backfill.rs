@@ -0,0 +1,565 @@
+//! Backfill of historical checkpoint archives into a gateway store.
+//!
+//! Operators migrating from a previous evidence system hand the gateway
+//! archives of checkpoints it never saw live. [`backfill`] imports one
+//! robot's archive into a [`CheckpointStore`]:
+//!
+//! - every imported checkpoint passes full chain verification
+//!   ([`ChainVerifier`]), starting from genesis or from the stored
+//!   predecessor of the archive's first checkpoint;
+//! - checkpoints the store already holds are compared by hash: identical
+//!   ones are skipped as duplicates, different ones are reported as
+//!   conflicts (two histories for the same sequence) and stop the import;
+//! - the first checkpoint failing verification stops the import. What was
+//!   imported before it is a verified chain prefix and stays.
+//!
+//! The store is never rewritten: a conflict is evidence for an operator to
+//! investigate, not something to resolve automatically.
+//!
+//! Large chains are read a page at a time: a [`CheckpointQuery`] selects a
+//! robot's checkpoints by sequence and time range, [`CheckpointStore::page`]
+//! returns one [`CheckpointPage`] with a cursor to the next, and
+//! [`CheckpointStream`] iterates over every page without holding the chain.
+
+use crate::chain::{ChainError, ChainVerifier, VerifiedCheckpoint};
+use crate::checkpoint::Checkpoint;
+use crate::crypto::ct_eq;
+use crate::error::{ErrorCode, ErrorCoded, ErrorDetail};
+use crate::keys::KeyResolver;
+use crate::rotation::KeyRotationCert;
+use crate::search::EntrySearchIndex;
+use crate::tombstone::StoredEntry;
+use crate::types::{Hash256, RobotId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Checkpoint storage of a gateway, per robot.
+pub trait CheckpointStore {
+    /// The robot's stored checkpoints in sequence order.
+    fn checkpoints(&self, robot_id: &RobotId) -> Vec<Checkpoint>;
+
+    /// Store a verified checkpoint that follows the robot's stored chain.
+    ///
+    /// Taking a [`VerifiedCheckpoint`] keeps unverified checkpoints out of
+    /// the store by construction.
+    fn insert(&mut self, checkpoint: VerifiedCheckpoint);
+
+    /// One page of the checkpoints `query` selects, in sequence order.
+    ///
+    /// The default filters [`checkpoints`](Self::checkpoints); stores with a
+    /// sequence or time index should answer from it instead of loading the
+    /// whole chain.
+    fn page(&self, query: &CheckpointQuery) -> CheckpointPage {
+        let matches = self
+            .checkpoints(&query.robot_id)
+            .into_iter()
+            .filter(|c| query.matches(c));
+        CheckpointPage::from_matches(matches, query.limit)
+    }
+
+    /// Index of the stored checkpoints' entries, for stores that keep one.
+    ///
+    /// Lets investigators find the checkpoint covering a piece of evidence
+    /// without rebuilding every tree. The default keeps no index.
+    fn entry_index(&self) -> Option<&EntrySearchIndex> {
+        None
+    }
+
+    /// Stored entries of `checkpoint`, in tree order, for stores that keep
+    /// entries. The default keeps none.
+    fn stored_entries(&self, _checkpoint: &Checkpoint) -> Option<&[StoredEntry]> {
+        None
+    }
+
+    /// Drop the stored entries of `checkpoint`, keeping the checkpoint, and
+    /// return them.
+    ///
+    /// Callers retire the entries first (see [`crate::retention`]); the
+    /// store does not check that anything else still vouches for them.
+    fn drop_entries(&mut self, _checkpoint: &Checkpoint) -> Option<Vec<StoredEntry>> {
+        None
+    }
+}
+
+/// Position after the last checkpoint of a page.
+///
+/// Opaque to clients: it renders as a hex token for query strings and
+/// command lines and parses back with [`FromStr`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageCursor {
+    pub(crate) last_sequence: u64,
+}
+
+impl fmt::Display for PageCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.last_sequence)
+    }
+}
+
+impl FromStr for PageCursor {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16).map(|last_sequence| Self { last_sequence })
+    }
+}
+
+/// Range query over one robot's checkpoints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointQuery {
+    pub robot_id: RobotId,
+    /// Lowest sequence, inclusive
+    pub from_sequence: u64,
+    /// Highest sequence, inclusive
+    pub to_sequence: Option<u64>,
+    /// Earliest robot timestamp, inclusive
+    pub since: Option<DateTime<Utc>>,
+    /// Latest robot timestamp, exclusive
+    pub until: Option<DateTime<Utc>>,
+    /// Checkpoints per page (at least one)
+    pub limit: usize,
+    /// Continue after the page that returned this cursor
+    pub after: Option<PageCursor>,
+}
+
+impl CheckpointQuery {
+    /// Page size when none is given.
+    pub const DEFAULT_LIMIT: usize = 100;
+
+    /// All of `robot_id`'s checkpoints, [`DEFAULT_LIMIT`](Self::DEFAULT_LIMIT) per page.
+    pub fn new(robot_id: RobotId) -> Self {
+        Self {
+            robot_id,
+            from_sequence: 0,
+            to_sequence: None,
+            since: None,
+            until: None,
+            limit: Self::DEFAULT_LIMIT,
+            after: None,
+        }
+    }
+
+    /// Only sequences `from..=to`.
+    pub fn with_sequences(mut self, from: u64, to: u64) -> Self {
+        self.from_sequence = from;
+        self.to_sequence = Some(to);
+        self
+    }
+
+    /// Only checkpoints timestamped in `since..until` (robot clock).
+    pub fn with_time_range(mut self, since: DateTime<Utc>, until: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self.until = Some(until);
+        self
+    }
+
+    /// Return at most `limit` checkpoints per page.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit.max(1);
+        self
+    }
+
+    /// Continue after `cursor`.
+    pub fn after(mut self, cursor: PageCursor) -> Self {
+        self.after = Some(cursor);
+        self
+    }
+
+    /// First sequence the next page can hold, cursor included.
+    pub fn start_sequence(&self) -> u64 {
+        match self.after {
+            Some(cursor) => self
+                .from_sequence
+                .max(cursor.last_sequence.saturating_add(1)),
+            None => self.from_sequence,
+        }
+    }
+
+    /// Whether `checkpoint` is selected by this query and not yet paged past.
+    pub fn matches(&self, checkpoint: &Checkpoint) -> bool {
+        let timestamp = checkpoint.local_timestamp_utc;
+        checkpoint.robot_id == self.robot_id
+            && checkpoint.sequence >= self.start_sequence()
+            && self.to_sequence.is_none_or(|to| checkpoint.sequence <= to)
+            && self.since.is_none_or(|since| timestamp >= since)
+            && self.until.is_none_or(|until| timestamp < until)
+    }
+}
+
+/// One page of a [`CheckpointQuery`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointPage {
+    /// Checkpoints in sequence order
+    pub checkpoints: Vec<Checkpoint>,
+    /// Cursor for the next page; none on the last page
+    pub next: Option<PageCursor>,
+}
+
+impl CheckpointPage {
+    /// Build a page from the matching checkpoints in sequence order, taking
+    /// at most `limit` and one more to learn whether another page follows.
+    pub fn from_matches(matches: impl IntoIterator<Item = Checkpoint>, limit: usize) -> Self {
+        let mut matches = matches.into_iter();
+        let checkpoints: Vec<Checkpoint> = matches.by_ref().take(limit).collect();
+        let next = match matches.next() {
+            Some(_) => checkpoints.last().map(|c| PageCursor {
+                last_sequence: c.sequence,
+            }),
+            None => None,
+        };
+        Self { checkpoints, next }
+    }
+}
+
+/// Every checkpoint a query selects, fetched from the store a page at a time.
+pub struct CheckpointStream<'a> {
+    store: &'a dyn CheckpointStore,
+    /// Query for the next page; none once the last page was fetched
+    query: Option<CheckpointQuery>,
+    page: std::vec::IntoIter<Checkpoint>,
+}
+
+impl<'a> CheckpointStream<'a> {
+    pub fn new(store: &'a dyn CheckpointStore, query: CheckpointQuery) -> Self {
+        Self {
+            store,
+            query: Some(query),
+            page: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl Iterator for CheckpointStream<'_> {
+    type Item = Checkpoint;
+
+    fn next(&mut self) -> Option<Checkpoint> {
+        loop {
+            if let Some(checkpoint) = self.page.next() {
+                return Some(checkpoint);
+            }
+            let query = self.query.as_mut()?;
+            let page = self.store.page(query);
+            match page.next {
+                Some(cursor) => query.after = Some(cursor),
+                None => self.query = None,
+            }
+            self.page = page.checkpoints.into_iter();
+        }
+    }
+}
+
+/// A stored and an imported checkpoint with the same sequence that differ.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackfillConflict {
+    pub sequence: u64,
+    pub stored_hash: Hash256,
+    pub imported_hash: Hash256,
+}
+
+/// Outcome of importing one archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackfillReport {
+    pub robot_id: RobotId,
+    /// Sequences newly written to the store
+    pub imported: Vec<u64>,
+    /// Sequences the store already held, byte for byte
+    pub duplicates: Vec<u64>,
+    /// Sequences where the store and the archive disagree
+    pub conflicts: Vec<BackfillConflict>,
+    /// First archive checkpoint that failed verification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<(u64, ErrorDetail)>,
+}
+
+impl BackfillReport {
+    /// Whether the whole archive was reconciled without conflicts or rejections.
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty() && self.rejected.is_none()
+    }
+}
+
+/// Import `archive` (one robot's checkpoints in sequence order) into `store`.
+///
+/// `keys` resolves the robot's signing keys and `rotations` are the key
+/// rotation certificates needed to follow the chain. Fails without touching
+/// the store if the archive is empty, the stored chain does not verify, or
+/// the archive starts after a gap in the stored chain.
+pub fn backfill(
+    store: &mut dyn CheckpointStore,
+    archive: &[Checkpoint],
+    keys: Box<dyn KeyResolver>,
+    rotations: &[KeyRotationCert],
+) -> Result<BackfillReport, BackfillError> {
+    let first = archive.first().ok_or(BackfillError::EmptyArchive)?;
+    let robot_id = first.robot_id.clone();
+    let stored = store.checkpoints(&robot_id);
+
+    let mut verifier = ChainVerifier::with_resolver(keys);
+    for cert in rotations {
+        verifier.add_rotation(cert)?;
+    }
+
+    // Replay the stored chain up to the archive's first checkpoint.
+    let mut next = 0;
+    for checkpoint in stored.iter().take_while(|c| c.sequence < first.sequence) {
+        if checkpoint.sequence != next {
+            break;
+        }
+        verifier.verify_next(checkpoint)?;
+        next += 1;
+    }
+    if next != first.sequence {
+        return Err(BackfillError::MissingPredecessor {
+            robot_id,
+            sequence: first.sequence,
+        });
+    }
+
+    let mut report = BackfillReport {
+        robot_id,
+        imported: Vec::new(),
+        duplicates: Vec::new(),
+        conflicts: Vec::new(),
+        rejected: None,
+    };
+    for checkpoint in archive {
+        let existing = stored.iter().find(|c| c.sequence == checkpoint.sequence);
+        if let Some(existing) = existing {
+            let stored_hash = existing.compute_hash()?;
+            let imported_hash = checkpoint.compute_hash()?;
+            if !ct_eq(&stored_hash, &imported_hash) || existing != checkpoint {
+                report.conflicts.push(BackfillConflict {
+                    sequence: checkpoint.sequence,
+                    stored_hash,
+                    imported_hash,
+                });
+                continue;
+            }
+        }
+        // Nothing past a divergence is imported; the rest of the overlap is
+        // still compared so every conflicting sequence is reported.
+        if !report.conflicts.is_empty() {
+            continue;
+        }
+        let verified = match verifier.accept(checkpoint.clone()) {
+            Ok(verified) => verified,
+            Err(e) => {
+                report.rejected = Some((checkpoint.sequence, e.detail()));
+                break;
+            }
+        };
+        if existing.is_some() {
+            report.duplicates.push(checkpoint.sequence);
+        } else {
+            store.insert(verified);
+            report.imported.push(checkpoint.sequence);
+        }
+    }
+    Ok(report)
+}
+
+#[derive(Debug, Error)]
+pub enum BackfillError {
+    #[error("Archive holds no checkpoints")]
+    EmptyArchive,
+
+    #[error("Archive for {robot_id} starts at #{sequence} but the store lacks its predecessors")]
+    MissingPredecessor { robot_id: RobotId, sequence: u64 },
+
+    #[error("Stored chain or rotation does not verify: {0}")]
+    Chain(#[from] ChainError),
+
+    #[error("Checkpoint hashing failed: {0}")]
+    Serialization(#[from] crate::serialization::SerializationError),
+}
+
+impl ErrorCoded for BackfillError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            BackfillError::EmptyArchive => ErrorCode::ImportEmptyArchive,
+            BackfillError::MissingPredecessor { .. } => ErrorCode::ImportMissingPredecessor,
+            BackfillError::Chain(e) => e.code(),
+            BackfillError::Serialization(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::crypto::Signer;
+    use std::collections::BTreeMap;
+
+    #[derive(Default)]
+    struct MemoryStore(BTreeMap<(String, u64), Checkpoint>);
+
+    impl CheckpointStore for MemoryStore {
+        fn checkpoints(&self, robot_id: &RobotId) -> Vec<Checkpoint> {
+            self.0
+                .values()
+                .filter(|c| &c.robot_id == robot_id)
+                .cloned()
+                .collect()
+        }
+
+        fn insert(&mut self, checkpoint: VerifiedCheckpoint) {
+            self.0.insert(
+                (checkpoint.robot_id.0.clone(), checkpoint.sequence),
+                checkpoint.into_inner(),
+            );
+        }
+    }
+
+    fn chain(signer: &Signer, count: u64, entries_seed: u8) -> Vec<Checkpoint> {
+        let mut checkpoints: Vec<Checkpoint> = Vec::new();
+        for sequence in 0..count {
+            let builder = match checkpoints.last() {
+                Some(prev) => CheckpointBuilder::continuing_from(prev).unwrap(),
+                None => CheckpointBuilder::test_fixture(),
+            };
+            let seed = if sequence >= 3 { entries_seed } else { 0 };
+            checkpoints.push(
+                builder
+                    .monotonic_counter(sequence + 1)
+                    .timestamp(
+                        chrono::DateTime::UNIX_EPOCH
+                            + chrono::Duration::minutes(sequence as i64 + 1),
+                    )
+                    .entries_root([seed; 32])
+                    .build_and_sign(signer.signing_key())
+                    .unwrap(),
+            );
+        }
+        checkpoints
+    }
+
+    /// `checkpoints`, a chain from sequence 0, as accepted by a verifier
+    fn verified(signer: &Signer, checkpoints: &[Checkpoint]) -> Vec<VerifiedCheckpoint> {
+        let mut verifier = ChainVerifier::new(signer.verifying_key());
+        checkpoints
+            .iter()
+            .map(|c| verifier.accept(c.clone()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_backfill_reconciles_with_stored_chain() {
+        let signer = Signer::generate();
+        let history = chain(&signer, 6, 0);
+        let mut store = MemoryStore::default();
+
+        // The live gateway already holds #0..=#2; the archive covers #1..=#5
+        for checkpoint in verified(&signer, &history[..3]) {
+            store.insert(checkpoint);
+        }
+        let report = backfill(
+            &mut store,
+            &history[1..],
+            Box::new(signer.verifying_key()),
+            &[],
+        )
+        .unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.duplicates, [1, 2]);
+        assert_eq!(report.imported, [3, 4, 5]);
+        assert_eq!(store.checkpoints(&history[0].robot_id), history);
+
+        // Importing the same archive again changes nothing
+        let again = backfill(&mut store, &history, Box::new(signer.verifying_key()), &[]).unwrap();
+        assert!(again.imported.is_empty());
+        assert_eq!(again.duplicates.len(), 6);
+    }
+
+    #[test]
+    fn test_range_queries_page_with_cursors() {
+        let signer = Signer::generate();
+        let history = chain(&signer, 7, 0);
+        let mut store = MemoryStore::default();
+        for checkpoint in verified(&signer, &history) {
+            store.insert(checkpoint);
+        }
+        let robot_id = history[0].robot_id.clone();
+        let sequences = |page: &CheckpointPage| {
+            page.checkpoints
+                .iter()
+                .map(|c| c.sequence)
+                .collect::<Vec<_>>()
+        };
+
+        let query = CheckpointQuery::new(robot_id.clone())
+            .with_sequences(1, 5)
+            .with_limit(2);
+        let first = store.page(&query);
+        assert_eq!(sequences(&first), [1, 2]);
+        let cursor: PageCursor = first.next.unwrap().to_string().parse().unwrap();
+        let second = store.page(&query.clone().after(cursor));
+        assert_eq!(sequences(&second), [3, 4]);
+        let last = store.page(&query.clone().after(second.next.unwrap()));
+        assert_eq!(sequences(&last), [5]);
+        assert!(last.next.is_none());
+
+        // Checkpoint #n is timestamped n + 1 minutes after the epoch
+        let epoch = chrono::DateTime::UNIX_EPOCH;
+        let by_time = CheckpointQuery::new(robot_id.clone()).with_time_range(
+            epoch + chrono::Duration::minutes(3),
+            epoch + chrono::Duration::minutes(6),
+        );
+        assert_eq!(sequences(&store.page(&by_time)), [2, 3, 4]);
+
+        let streamed: Vec<Checkpoint> =
+            CheckpointStream::new(&store, CheckpointQuery::new(robot_id).with_limit(3)).collect();
+        assert_eq!(streamed, history);
+    }
+
+    #[test]
+    fn test_backfill_reports_conflicts_and_rejections() {
+        let signer = Signer::generate();
+        let history = chain(&signer, 6, 0);
+        let fork = chain(&signer, 6, 9);
+        let mut store = MemoryStore::default();
+        for checkpoint in verified(&signer, &history[..5]) {
+            store.insert(checkpoint);
+        }
+
+        let report = backfill(&mut store, &fork, Box::new(signer.verifying_key()), &[]).unwrap();
+        assert_eq!(report.duplicates, [0, 1, 2]);
+        assert_eq!(
+            report
+                .conflicts
+                .iter()
+                .map(|c| c.sequence)
+                .collect::<Vec<_>>(),
+            [3, 4]
+        );
+        assert!(
+            report.imported.is_empty(),
+            "nothing past a divergence is imported"
+        );
+        assert_eq!(store.checkpoints(&history[0].robot_id).len(), 5);
+
+        // A forged checkpoint stops the import; the verified prefix stays
+        let mut tampered = history[5].clone();
+        tampered.monotonic_counter += 10;
+        let mut fresh = MemoryStore::default();
+        let mut archive = history[..5].to_vec();
+        archive.push(tampered);
+        let report = backfill(&mut fresh, &archive, Box::new(signer.verifying_key()), &[]).unwrap();
+        assert_eq!(report.imported, [0, 1, 2, 3, 4]);
+        let (sequence, detail) = report.rejected.unwrap();
+        assert_eq!((sequence, detail.code), (5, ErrorCode::InvalidSignature));
+
+        // An archive starting past the stored chain has no predecessor
+        let err = backfill(
+            &mut MemoryStore::default(),
+            &history[2..],
+            Box::new(signer.verifying_key()),
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ImportMissingPredecessor);
+    }
+}
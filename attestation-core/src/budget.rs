@@ -0,0 +1,324 @@
+//! Serialized size budgets for constrained links.
+//!
+//! Robots on LoRa or satellite links get a few hundred bytes per message.
+//! A [`SizeBudget`] checks the canonical CBOR size of anything sent over
+//! such a link (checkpoints, Merkle proofs, receipts) against the frame
+//! size left after transport overhead, and for checkpoints
+//! ([`SizeBudget::plan_checkpoint`]) estimates what each optional field
+//! costs and which to leave out:
+//!
+//! - [`Disposition::Strip`]: descriptive metadata (model provenance
+//!   details, inference flags, agent health summaries) a constrained link
+//!   can do without;
+//! - [`Disposition::Defer`]: commitments a verifier needs eventually
+//!   (hardware inventory, agent config, redaction policy, sub-key
+//!   certificates), to be committed by a later checkpoint sent over a
+//!   wider link.
+//!
+//! Every field is signed, so the plan applies when building a checkpoint:
+//! a signed one cannot be trimmed. Even a minimal checkpoint exceeds 256
+//! bytes (see the pinned sizes in the tests); below that the transport has
+//! to fragment, which [`CheckpointSizePlan::minimum_size`] tells up front.
+//!
+//! Hashes and signatures encode each byte as a CBOR integer of one or two
+//! bytes, so sizes move by a few bytes with their values: leave a margin.
+
+use crate::checkpoint::Checkpoint;
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::serialization::{to_canonical_cbor, SerializationError};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Size limit of one transport message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeBudget {
+    /// Bytes per message on the link
+    pub max_bytes: usize,
+    /// Bytes of each message taken by framing, headers and MACs
+    pub overhead: usize,
+}
+
+/// How to save an optional checkpoint field's bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Disposition {
+    /// Leave it out
+    Strip,
+    /// Commit it in a later checkpoint sent over a wider link
+    Defer,
+}
+
+/// Bytes an optional field adds to a checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldSaving {
+    pub field: String,
+    pub disposition: Disposition,
+    /// Canonical CBOR bytes saved by leaving the field out
+    pub saves: usize,
+}
+
+/// Size of a checkpoint against a budget, and what could be left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointSizePlan {
+    /// Canonical CBOR size
+    pub size: usize,
+    /// Bytes available per message
+    pub available: usize,
+    /// Size without any optional field
+    pub minimum_size: usize,
+    /// Present optional fields, largest saving first
+    pub savings: Vec<FieldSaving>,
+}
+
+impl CheckpointSizePlan {
+    /// Whether the checkpoint fits as it is.
+    pub fn fits(&self) -> bool {
+        self.size <= self.available
+    }
+
+    /// Whether leaving out optional fields can make it fit at all.
+    pub fn can_fit(&self) -> bool {
+        self.minimum_size <= self.available
+    }
+
+    /// The fewest largest-first fields to leave out to fit, or every
+    /// optional field when it cannot fit (the transport must fragment).
+    pub fn suggestions(&self) -> &[FieldSaving] {
+        let over = self.size.saturating_sub(self.available);
+        let mut saved = 0;
+        let needed = self
+            .savings
+            .iter()
+            .take_while(|saving| {
+                let more = saved < over;
+                saved += saving.saves;
+                more
+            })
+            .count();
+        &self.savings[..needed]
+    }
+}
+
+/// Leaves an optional field out of a checkpoint.
+type ClearField = fn(&mut Checkpoint);
+
+/// Optional checkpoint fields: name, disposition, and how to clear it.
+const OPTIONAL_FIELDS: &[(&str, Disposition, ClearField)] = &[
+    (
+        "model_provenance.signature_bundle",
+        Disposition::Strip,
+        |c| c.model_provenance.signature_bundle = None,
+    ),
+    (
+        "model_provenance.container_digest",
+        Disposition::Strip,
+        |c| c.model_provenance.container_digest = None,
+    ),
+    ("model_provenance.dataset_hash", Disposition::Strip, |c| {
+        c.model_provenance.dataset_hash = None
+    }),
+    ("inference_config.flags", Disposition::Strip, |c| {
+        c.inference_config.flags = None
+    }),
+    ("inference_config.rng_seed", Disposition::Strip, |c| {
+        c.inference_config.rng_seed = None
+    }),
+    ("agent_health", Disposition::Strip, |c| {
+        c.agent_health = None
+    }),
+    ("sub_key_certs", Disposition::Defer, |c| {
+        c.sub_key_certs.clear()
+    }),
+    ("hardware_inventory", Disposition::Defer, |c| {
+        c.hardware_inventory = None
+    }),
+    ("agent_config", Disposition::Defer, |c| {
+        c.agent_config = None
+    }),
+    ("redaction_policy", Disposition::Defer, |c| {
+        c.redaction_policy = None
+    }),
+];
+
+impl SizeBudget {
+    /// Messages of `max_bytes`, without overhead.
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            overhead: 0,
+        }
+    }
+
+    /// Reserve `overhead` bytes of every message for the transport.
+    pub fn with_overhead(mut self, overhead: usize) -> Self {
+        self.overhead = overhead;
+        self
+    }
+
+    /// Bytes available for the payload.
+    pub fn available(&self) -> usize {
+        self.max_bytes.saturating_sub(self.overhead)
+    }
+
+    /// Canonical CBOR size of `value`, failing if it exceeds the budget.
+    pub fn enforce<T: Serialize>(&self, value: &T) -> Result<usize, BudgetError> {
+        let size = to_canonical_cbor(value)?.len();
+        if size > self.available() {
+            return Err(BudgetError::Exceeded {
+                size,
+                available: self.available(),
+            });
+        }
+        Ok(size)
+    }
+
+    /// Size `checkpoint` against the budget, pricing each optional field.
+    pub fn plan_checkpoint(
+        &self,
+        checkpoint: &Checkpoint,
+    ) -> Result<CheckpointSizePlan, SerializationError> {
+        let size = checkpoint.to_bytes()?.len();
+        let mut minimal = checkpoint.clone();
+        let mut savings = Vec::new();
+        for (field, disposition, clear) in OPTIONAL_FIELDS {
+            let mut without = checkpoint.clone();
+            clear(&mut without);
+            clear(&mut minimal);
+            let saves = size - without.to_bytes()?.len();
+            if saves > 0 {
+                savings.push(FieldSaving {
+                    field: field.to_string(),
+                    disposition: *disposition,
+                    saves,
+                });
+            }
+        }
+        savings.sort_by_key(|saving| std::cmp::Reverse(saving.saves));
+
+        Ok(CheckpointSizePlan {
+            size,
+            available: self.available(),
+            minimum_size: minimal.to_bytes()?.len(),
+            savings,
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BudgetError {
+    #[error("Serialized size {size} exceeds the {available} bytes available")]
+    Exceeded { size: usize, available: usize },
+
+    #[error("Serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+}
+
+impl ErrorCoded for BudgetError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            BudgetError::Exceeded { .. } => ErrorCode::BudgetExceeded,
+            BudgetError::Serialization(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::crypto::Signer;
+    use crate::merkle::{Entry, MerkleTree};
+    use crate::types::{MissionId, ModelProvenance, RobotId};
+    use chrono::DateTime;
+
+    /// Pinned canonical sizes: a change here changes what fits on a link
+    const MIN_CHECKPOINT: usize = 615;
+    const MIN_PROOF: usize = 252;
+
+    /// Fixed key: signature and key id bytes encode to different lengths
+    fn signer() -> Signer {
+        Signer::new(ed25519_dalek::SigningKey::from_bytes(&[42u8; 32]))
+    }
+
+    /// Smallest realistic checkpoint: one-character IDs, no optional fields.
+    fn minimal() -> CheckpointBuilder {
+        CheckpointBuilder::test_fixture()
+            .robot_id(RobotId("R".to_string()))
+            .mission_id(MissionId("M".to_string()))
+            .sequence(1)
+            .timestamp(DateTime::from_timestamp(1_760_000_000, 0).unwrap())
+            .model_provenance(ModelProvenance {
+                name: "m".to_string(),
+                model_hash: [1u8; 32],
+                dataset_hash: None,
+                container_digest: None,
+                signature_bundle: None,
+            })
+            .firmware_hash([2u8; 32])
+            .enclave_measurement(vec![3u8; 32])
+            .prev_root([4u8; 32])
+            .entries_root([5u8; 32])
+    }
+
+    #[test]
+    fn test_canonical_minimum_sizes() {
+        let signer = signer();
+        let checkpoint = minimal().build_and_sign(signer.signing_key()).unwrap();
+        let plan = SizeBudget::new(256).plan_checkpoint(&checkpoint).unwrap();
+        assert_eq!(plan.size, MIN_CHECKPOINT);
+        assert_eq!(plan.minimum_size, plan.size);
+        assert!(plan.savings.is_empty());
+        assert!(!plan.can_fit());
+
+        let mut tree = MerkleTree::new();
+        tree.insert(Entry::new(1, 0, b"a"));
+        tree.insert(Entry::new(2, 0, b"b"));
+        let proof = tree.generate_proof(1, 0).unwrap();
+        assert_eq!(SizeBudget::new(256).enforce(&proof).unwrap(), MIN_PROOF);
+        let err = SizeBudget::new(256)
+            .with_overhead(256 - MIN_PROOF + 1)
+            .enforce(&proof)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::BudgetExceeded);
+    }
+
+    #[test]
+    fn test_plan_suggests_largest_fields_first() {
+        let signer = signer();
+        let checkpoint = minimal()
+            .model_provenance(ModelProvenance {
+                name: "m".to_string(),
+                model_hash: [1u8; 32],
+                dataset_hash: Some([6u8; 32]),
+                container_digest: None,
+                signature_bundle: Some(vec![0u8; 200]),
+            })
+            .agent_config([7u8; 32])
+            .build_and_sign(signer.signing_key())
+            .unwrap();
+
+        let budget = SizeBudget::new(MIN_CHECKPOINT + 100);
+        let plan = budget.plan_checkpoint(&checkpoint).unwrap();
+        assert!(!plan.fits() && plan.can_fit());
+        let total: usize = plan.savings.iter().map(|s| s.saves).sum();
+        assert_eq!(plan.minimum_size, plan.size - total);
+        let fields: Vec<&str> = plan.savings.iter().map(|s| s.field.as_str()).collect();
+        assert_eq!(fields[0], "model_provenance.signature_bundle");
+        assert_eq!(fields.len(), 3);
+
+        // Dropping the bundle alone is enough
+        let suggested = plan.suggestions();
+        assert_eq!(suggested.len(), 1);
+        assert_eq!(suggested[0].disposition, Disposition::Strip);
+        assert!(plan.size - suggested[0].saves <= budget.available());
+
+        let tight = SizeBudget::new(MIN_CHECKPOINT)
+            .plan_checkpoint(&checkpoint)
+            .unwrap();
+        assert_eq!(tight.suggestions().len(), 3);
+        assert!(tight
+            .suggestions()
+            .iter()
+            .any(|s| s.disposition == Disposition::Defer));
+    }
+}
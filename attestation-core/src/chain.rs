@@ -0,0 +1,1182 @@
+//! Checkpoint chain verification (anti-rollback enforcement).
+//!
+//! ## Rules
+//! 1. Every checkpoint carries a valid signature from the robot's key
+//! 2. All checkpoints belong to the same robot
+//! 3. Sequence numbers increase by exactly one, except across a signed gap
+//!    record the verifier's gap policy permits (see [`crate::gap`])
+//! 4. Monotonic counters strictly increase
+//! 5. `prev_root` equals the hash of the previous checkpoint
+//!    (the zero hash for the first checkpoint of a chain, the gap record's
+//!    `resume_prev_root` after a gap)
+//! 6. After a key rotation, the old key signs nothing at or beyond the
+//!    rotation's effective sequence and the new key nothing before it
+//! 7. `local_timestamp_utc` strictly increases (skew against real time is
+//!    judged by the gateway; see [`crate::clock`])
+//! 8. Missions pair up: a mission-start checkpoint is only accepted when no
+//!    mission is open, checkpoints keep the open mission's `mission_id`, and
+//!    a mission-end checkpoint links to the open mission's start checkpoint
+//!    (see [`crate::mission`])
+//! 9. A key delegated by a trusted key (see [`crate::delegation`]) only signs
+//!    checkpoints inside its certificate's scope and validity window
+//! 10. With a minimum trust mode set ([`ChainVerifier::require_trust_mode`]),
+//!     every checkpoint's `trust_mode` meets it, so a robot cannot downgrade
+//!     from `Trusted` to `Untrusted` mid-chain unnoticed
+//! 11. A heartbeat checkpoint (see [`crate::heartbeat`]) commits to no
+//!     entries and carries no mission event
+//! 12. With an approval log set ([`ChainVerifier::with_approvals`]), every
+//!     checkpoint's model and firmware were approved at its timestamp (see
+//!     [`crate::approval`])
+//!
+//! Breaking rule 3, 4 or 5 against an accepted head is a rollback attempt;
+//! see [`crate::rollback`] for alerting on it.
+//!
+//! ## Verified checkpoints
+//! [`ChainVerifier::accept`] consumes a checkpoint and hands it back as a
+//! [`VerifiedCheckpoint`], which only a verifier can construct. Code that
+//! must not act on unverified data (storing, serving, indexing) takes that
+//! type, and checkpoints arriving as bytes are held as an
+//! [`UnverifiedCheckpoint`] that exposes no fields until verified.
+
+use crate::approval::{ApprovalError, ApprovalLog, ArtifactKind};
+use crate::checkpoint::{Checkpoint, SignatureError};
+use crate::crypto::{ct_eq, key_id};
+use crate::delegation::{DelegationCert, DelegationError};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::gap::{GapError, GapPolicy, GapReason, GapRecord};
+use crate::keys::KeyResolver;
+use crate::mission::{MissionEvent, OpenMission};
+use crate::rollback::{RollbackAlert, RollbackAlertSink};
+use crate::rotation::{KeyRotationCert, RotationError};
+use crate::serialization::SerializationError;
+use crate::types::{Hash256, KeyId, MissionId, RobotId, TrustMode};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// The last accepted checkpoint of a chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainHead {
+    pub robot_id: RobotId,
+    pub sequence: u64,
+    pub monotonic_counter: u64,
+    pub timestamp: DateTime<Utc>,
+    pub hash: Hash256,
+    /// Mission started but not yet ended as of this checkpoint
+    pub mission: Option<OpenMission>,
+}
+
+impl ChainHead {
+    /// Build a head from an already-verified checkpoint.
+    ///
+    /// Only a mission-start checkpoint yields an open mission; a head built
+    /// from the middle of a mission does not know where it started, so resume
+    /// from heads the verifier produced when missions are in use.
+    pub fn from_checkpoint(checkpoint: &Checkpoint) -> Result<Self, SerializationError> {
+        let hash = checkpoint.compute_hash()?;
+        let mission = match checkpoint.mission_event {
+            Some(MissionEvent::Start { .. }) => Some(OpenMission {
+                mission_id: checkpoint.mission_id.clone(),
+                start: hash,
+            }),
+            _ => None,
+        };
+        Ok(Self {
+            robot_id: checkpoint.robot_id.clone(),
+            sequence: checkpoint.sequence,
+            monotonic_counter: checkpoint.monotonic_counter,
+            timestamp: checkpoint.local_timestamp_utc,
+            hash,
+            mission,
+        })
+    }
+}
+
+/// Minimum trust mode per robot class, as configured on a gateway.
+///
+/// Classes are operator-defined labels (e.g., "surgical", "warehouse")
+/// assigned to robots by the fleet registry.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustRequirements {
+    /// Minimum for robots whose class is not listed (none if absent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<TrustMode>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub classes: BTreeMap<String, TrustMode>,
+}
+
+impl TrustRequirements {
+    /// The minimum trust mode for a robot of `class`, if any.
+    pub fn minimum_for(&self, class: Option<&str>) -> Option<TrustMode> {
+        class
+            .and_then(|class| self.classes.get(class).copied())
+            .or(self.default)
+    }
+
+    /// A verifier for a robot of `class` with its minimum applied.
+    pub fn verifier(&self, class: Option<&str>, keys: Box<dyn KeyResolver>) -> ChainVerifier {
+        let verifier = ChainVerifier::with_resolver(keys);
+        match self.minimum_for(class) {
+            Some(minimum) => verifier.require_trust_mode(minimum),
+            None => verifier,
+        }
+    }
+}
+
+/// Incremental verifier for a single robot's checkpoint chain.
+///
+/// Feed checkpoints in order with [`ChainVerifier::verify_next`]; the verifier
+/// only advances its head when a checkpoint passes every rule.
+pub struct ChainVerifier {
+    keys: Box<dyn KeyResolver>,
+    rotations: Vec<Rotation>,
+    delegations: Vec<Delegation>,
+    min_trust_mode: Option<TrustMode>,
+    approvals: Option<Arc<ApprovalLog>>,
+    gap_policy: GapPolicy,
+    gaps: Vec<GapRecord>,
+    bridged: Vec<GapRecord>,
+    head: Option<ChainHead>,
+    alerts: Vec<Arc<dyn RollbackAlertSink>>,
+}
+
+/// An accepted key rotation.
+struct Rotation {
+    old_key_id: KeyId,
+    new_key_id: KeyId,
+    new_key: VerifyingKey,
+    effective_sequence: u64,
+}
+
+/// An accepted key delegation.
+struct Delegation {
+    child_key_id: KeyId,
+    child_key: VerifyingKey,
+    cert: DelegationCert,
+}
+
+impl ChainVerifier {
+    /// Create a verifier for a chain signed by a single key, starting at genesis.
+    pub fn new(verifying_key: VerifyingKey) -> Self {
+        Self::with_resolver(Box::new(verifying_key))
+    }
+
+    /// Create a verifier that resolves signer keys by `signer_key_id`.
+    pub fn with_resolver(keys: Box<dyn KeyResolver>) -> Self {
+        Self {
+            keys,
+            rotations: Vec::new(),
+            delegations: Vec::new(),
+            min_trust_mode: None,
+            approvals: None,
+            gap_policy: GapPolicy::default(),
+            gaps: Vec::new(),
+            bridged: Vec::new(),
+            head: None,
+            alerts: Vec::new(),
+        }
+    }
+
+    /// Continue from a previously accepted head instead of genesis.
+    pub fn resume_from(mut self, head: ChainHead) -> Self {
+        self.head = Some(head);
+        self
+    }
+
+    /// Report rollback attempts on the head to `sink` as well as rejecting them.
+    pub fn with_rollback_alerts(mut self, sink: Arc<dyn RollbackAlertSink>) -> Self {
+        self.alerts.push(sink);
+        self
+    }
+
+    /// Reject checkpoints whose trust mode is below `minimum`.
+    pub fn require_trust_mode(mut self, minimum: TrustMode) -> Self {
+        self.min_trust_mode = Some(minimum);
+        self
+    }
+
+    /// Reject checkpoints whose model or firmware `approvals` does not show
+    /// as approved at the checkpoint's timestamp.
+    pub fn with_approvals(mut self, approvals: Arc<ApprovalLog>) -> Self {
+        self.approvals = Some(approvals);
+        self
+    }
+
+    /// Accept sequence gaps justified by a gap record within `policy`.
+    pub fn with_gap_policy(mut self, policy: GapPolicy) -> Self {
+        self.gap_policy = policy;
+        self
+    }
+
+    /// Accept a key rotation signed by a key this verifier already trusts.
+    ///
+    /// Rotations can be chained: a certificate may be signed by the new key
+    /// of an earlier rotation.
+    pub fn add_rotation(&mut self, cert: &KeyRotationCert) -> Result<(), ChainError> {
+        let old_key = self
+            .resolve(&cert.old_key_id)
+            .ok_or(SignatureError::UnknownKey(cert.old_key_id))?;
+        cert.verify(&old_key)?;
+
+        let new_key = cert.new_verifying_key()?;
+        self.rotations.push(Rotation {
+            old_key_id: cert.old_key_id,
+            new_key_id: key_id(&new_key),
+            new_key,
+            effective_sequence: cert.effective_sequence,
+        });
+        Ok(())
+    }
+
+    /// Accept a delegation signed by a key this verifier already trusts
+    /// (directly or through a rotation).
+    ///
+    /// Delegated keys cannot delegate further.
+    pub fn add_delegation(&mut self, cert: &DelegationCert) -> Result<(), ChainError> {
+        let parent_key = self
+            .resolve(&cert.parent_key_id)
+            .ok_or(SignatureError::UnknownKey(cert.parent_key_id))?;
+        cert.verify(&parent_key)?;
+
+        let child_key = cert.child_verifying_key()?;
+        self.delegations.push(Delegation {
+            child_key_id: key_id(&child_key),
+            child_key,
+            cert: cert.clone(),
+        });
+        Ok(())
+    }
+
+    /// Accept a gap record signed by a key this verifier already trusts.
+    ///
+    /// The gap is only bridged if it matches the chain and the gap policy
+    /// when the checkpoint after it arrives.
+    pub fn add_gap(&mut self, record: &GapRecord) -> Result<(), ChainError> {
+        let key = self
+            .resolve(&record.signer_key_id)
+            .ok_or(SignatureError::UnknownKey(record.signer_key_id))?;
+        record.verify(&key)?;
+        self.gaps.push(record.clone());
+        Ok(())
+    }
+
+    /// Gap records the chain has been accepted across, in chain order.
+    pub fn bridged_gaps(&self) -> &[GapRecord] {
+        &self.bridged
+    }
+
+    /// The last accepted checkpoint, if any.
+    pub fn head(&self) -> Option<&ChainHead> {
+        self.head.as_ref()
+    }
+
+    /// Verify the next checkpoint in the chain and advance the head.
+    pub fn verify_next(&mut self, checkpoint: &Checkpoint) -> Result<(), ChainError> {
+        let signer = checkpoint.signer_key_id;
+        let public_key = match self.resolve(&signer) {
+            Some(key) => key,
+            None => self.delegated_key(checkpoint)?,
+        };
+        checkpoint.verify_signature(&public_key)?;
+
+        if let Some(required) = self.min_trust_mode {
+            if !checkpoint.trust_mode.satisfies(required) {
+                return Err(ChainError::TrustModeBelowMinimum {
+                    sequence: checkpoint.sequence,
+                    required,
+                    actual: checkpoint.trust_mode,
+                });
+            }
+        }
+
+        if let Some(approvals) = &self.approvals {
+            let at = checkpoint.local_timestamp_utc;
+            approvals.check(
+                ArtifactKind::Model,
+                &checkpoint.model_provenance.model_hash,
+                at,
+            )?;
+            approvals.check(ArtifactKind::Firmware, &checkpoint.firmware_hash, at)?;
+        }
+
+        if checkpoint.heartbeat
+            && (checkpoint.entries_root != [0u8; 32] || checkpoint.mission_event.is_some())
+        {
+            return Err(ChainError::HeartbeatPayload {
+                sequence: checkpoint.sequence,
+            });
+        }
+
+        for rotation in &self.rotations {
+            let sequence = checkpoint.sequence;
+            let retired = rotation.old_key_id == signer && sequence >= rotation.effective_sequence;
+            let premature = rotation.new_key_id == signer && sequence < rotation.effective_sequence;
+            if retired || premature {
+                return Err(ChainError::KeyNotValidAtSequence {
+                    key_id: signer,
+                    sequence: checkpoint.sequence,
+                });
+            }
+        }
+
+        let open = self.head.as_ref().and_then(|head| head.mission.as_ref());
+        match (open, &checkpoint.mission_event) {
+            (Some(open), Some(MissionEvent::Start { .. })) => {
+                return Err(ChainError::MissionNotEnded {
+                    open: open.mission_id.clone(),
+                });
+            }
+            (Some(open), _) if checkpoint.mission_id != open.mission_id => {
+                return Err(ChainError::MissionIdMismatch {
+                    expected: open.mission_id.clone(),
+                    actual: checkpoint.mission_id.clone(),
+                });
+            }
+            (Some(open), Some(MissionEvent::End { start, .. })) if *start != open.start => {
+                return Err(ChainError::MissionLinkBroken {
+                    sequence: checkpoint.sequence,
+                });
+            }
+            (None, Some(MissionEvent::End { .. })) => {
+                return Err(ChainError::MissionLinkBroken {
+                    sequence: checkpoint.sequence,
+                });
+            }
+            _ => {}
+        }
+
+        let gap = match self.check_link(checkpoint) {
+            Ok(gap) => gap,
+            Err(e) => {
+                if let Some(head) = &self.head {
+                    if let Some(alert) = RollbackAlert::from_rejection(head, checkpoint, &e) {
+                        self.alerts
+                            .iter()
+                            .for_each(|sink| sink.rollback_detected(&alert));
+                    }
+                }
+                return Err(e);
+            }
+        };
+
+        let mut head = ChainHead::from_checkpoint(checkpoint)?;
+        if checkpoint.mission_event.is_none() {
+            head.mission = open.cloned();
+        }
+        self.head = Some(head);
+        if let Some(index) = gap {
+            self.bridged.push(self.gaps[index].clone());
+        }
+        Ok(())
+    }
+
+    /// Rules linking `checkpoint` to the head (genesis without one).
+    ///
+    /// Returns the index of the gap record bridging a sequence gap, if any.
+    fn check_link(&self, checkpoint: &Checkpoint) -> Result<Option<usize>, ChainError> {
+        let mut gap = None;
+        match &self.head {
+            None => {
+                if checkpoint.prev_root != [0u8; 32] {
+                    return Err(ChainError::InvalidGenesis);
+                }
+            }
+            Some(head) => {
+                if checkpoint.robot_id != head.robot_id {
+                    return Err(ChainError::RobotMismatch {
+                        expected: head.robot_id.clone(),
+                        actual: checkpoint.robot_id.clone(),
+                    });
+                }
+                if checkpoint.sequence <= head.sequence {
+                    return Err(ChainError::SequenceRegression {
+                        previous: head.sequence,
+                        actual: checkpoint.sequence,
+                    });
+                }
+                if checkpoint.sequence != head.sequence + 1 {
+                    gap = Some(self.bridging_gap(head, checkpoint)?);
+                }
+                if checkpoint.monotonic_counter <= head.monotonic_counter {
+                    return Err(ChainError::CounterRegression {
+                        previous: head.monotonic_counter,
+                        actual: checkpoint.monotonic_counter,
+                    });
+                }
+                if checkpoint.local_timestamp_utc <= head.timestamp {
+                    return Err(ChainError::TimestampRegression {
+                        previous: head.timestamp,
+                        actual: checkpoint.local_timestamp_utc,
+                    });
+                }
+                let expected = gap.map_or(head.hash, |index| self.gaps[index].resume_prev_root);
+                if !ct_eq(&checkpoint.prev_root, &expected) {
+                    return Err(ChainError::PrevRootMismatch {
+                        sequence: checkpoint.sequence,
+                    });
+                }
+            }
+        }
+        Ok(gap)
+    }
+
+    /// Index of the gap record justifying the gap between `head` and
+    /// `checkpoint` under the gap policy.
+    fn bridging_gap(&self, head: &ChainHead, checkpoint: &Checkpoint) -> Result<usize, ChainError> {
+        let index = self
+            .gaps
+            .iter()
+            .position(|record| {
+                record.robot_id == head.robot_id
+                    && record.after_sequence == head.sequence
+                    && ct_eq(&record.after_hash, &head.hash)
+                    && record.resume_sequence == checkpoint.sequence
+            })
+            .ok_or(ChainError::SequenceGap {
+                expected: head.sequence + 1,
+                actual: checkpoint.sequence,
+            })?;
+        let record = &self.gaps[index];
+        if !self
+            .gap_policy
+            .permits(record, head.timestamp, checkpoint.local_timestamp_utc)
+        {
+            return Err(ChainError::GapNotPermitted {
+                after: head.sequence,
+                resume: checkpoint.sequence,
+                reason: record.reason,
+            });
+        }
+        Ok(index)
+    }
+
+    /// Resolve a signer key from the configured resolver or accepted rotations.
+    fn resolve(&self, id: &KeyId) -> Option<VerifyingKey> {
+        self.keys.resolve(id).or_else(|| {
+            self.rotations
+                .iter()
+                .find(|rotation| rotation.new_key_id == *id)
+                .map(|rotation| rotation.new_key)
+        })
+    }
+
+    /// Key of a delegation covering `checkpoint`'s signer, robot, mission and timestamp.
+    fn delegated_key(&self, checkpoint: &Checkpoint) -> Result<VerifyingKey, ChainError> {
+        let signer = checkpoint.signer_key_id;
+        let mut candidates = self
+            .delegations
+            .iter()
+            .filter(|delegation| delegation.child_key_id == signer)
+            .peekable();
+        if candidates.peek().is_none() {
+            return Err(SignatureError::UnknownKey(signer).into());
+        }
+        candidates
+            .find(|delegation| {
+                delegation.cert.covers(
+                    &checkpoint.robot_id,
+                    &checkpoint.mission_id,
+                    checkpoint.local_timestamp_utc,
+                )
+            })
+            .map(|delegation| delegation.child_key)
+            .ok_or(ChainError::DelegationOutOfScope {
+                key_id: signer,
+                sequence: checkpoint.sequence,
+            })
+    }
+
+    /// Verify the next checkpoint in the chain, advance the head, and hand
+    /// the checkpoint back as proof that it was verified.
+    pub fn accept(&mut self, checkpoint: Checkpoint) -> Result<VerifiedCheckpoint, ChainError> {
+        self.verify_next(&checkpoint)?;
+        Ok(VerifiedCheckpoint(checkpoint))
+    }
+
+    /// Verify a sequence of checkpoints in order.
+    ///
+    /// Stops at the first failure; the head reflects the last accepted checkpoint.
+    pub fn verify_chain(&mut self, checkpoints: &[Checkpoint]) -> Result<(), ChainError> {
+        for checkpoint in checkpoints {
+            self.verify_next(checkpoint)?;
+        }
+        Ok(())
+    }
+}
+
+/// A checkpoint a [`ChainVerifier`] has accepted.
+///
+/// Only [`ChainVerifier::accept`] creates one, so a function taking a
+/// `VerifiedCheckpoint` cannot be handed a checkpoint that skipped
+/// verification. Fields are read through `Deref`. It serializes as the
+/// checkpoint but cannot be deserialized: decoding bytes proves nothing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct VerifiedCheckpoint(Checkpoint);
+
+impl VerifiedCheckpoint {
+    /// Give up the proof of verification and take the checkpoint.
+    pub fn into_inner(self) -> Checkpoint {
+        self.0
+    }
+}
+
+impl std::ops::Deref for VerifiedCheckpoint {
+    type Target = Checkpoint;
+
+    fn deref(&self) -> &Checkpoint {
+        &self.0
+    }
+}
+
+impl AsRef<Checkpoint> for VerifiedCheckpoint {
+    fn as_ref(&self) -> &Checkpoint {
+        &self.0
+    }
+}
+
+/// A checkpoint received as bytes and not yet verified.
+///
+/// Its fields are private: the only ways out are [`verify`](Self::verify)
+/// and the robot id it claims, which a gateway needs to pick the chain
+/// verifier and must not trust for anything else.
+#[derive(Debug, Clone)]
+pub struct UnverifiedCheckpoint(Checkpoint);
+
+impl UnverifiedCheckpoint {
+    /// Decode a checkpoint from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        Checkpoint::from_bytes(bytes).map(Self)
+    }
+
+    /// Robot the checkpoint claims to come from, unauthenticated.
+    pub fn claimed_robot_id(&self) -> &RobotId {
+        &self.0.robot_id
+    }
+
+    /// Verify against `verifier`, which advances past it on success.
+    pub fn verify(self, verifier: &mut ChainVerifier) -> Result<VerifiedCheckpoint, ChainError> {
+        verifier.accept(self.0)
+    }
+}
+
+/// Errors detected while verifying a checkpoint chain.
+#[derive(Debug, Error)]
+pub enum ChainError {
+    #[error("Checkpoint signature error: {0}")]
+    Signature(#[from] SignatureError),
+
+    #[error("Checkpoint hashing failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("First checkpoint must have a zero prev_root")]
+    InvalidGenesis,
+
+    #[error("Robot mismatch: expected {expected}, got {actual}")]
+    RobotMismatch { expected: RobotId, actual: RobotId },
+
+    #[error("Sequence regression: {actual} after {previous}")]
+    SequenceRegression { previous: u64, actual: u64 },
+
+    #[error("Sequence gap: expected {expected}, got {actual}")]
+    SequenceGap { expected: u64, actual: u64 },
+
+    #[error("Monotonic counter regression: {actual} after {previous}")]
+    CounterRegression { previous: u64, actual: u64 },
+
+    #[error("Timestamp regression: {actual} after {previous}")]
+    TimestampRegression {
+        previous: DateTime<Utc>,
+        actual: DateTime<Utc>,
+    },
+
+    #[error("prev_root mismatch at sequence {sequence}")]
+    PrevRootMismatch { sequence: u64 },
+
+    #[error("Key rotation rejected: {0}")]
+    Rotation(#[from] RotationError),
+
+    #[error("Key {key_id} is not valid for sequence {sequence} after rotation")]
+    KeyNotValidAtSequence { key_id: KeyId, sequence: u64 },
+
+    #[error("Key delegation rejected: {0}")]
+    Delegation(#[from] DelegationError),
+
+    #[error("Delegated key {key_id} is not valid for checkpoint {sequence} (scope or validity)")]
+    DelegationOutOfScope { key_id: KeyId, sequence: u64 },
+
+    #[error("Mission started while mission {open} is still open")]
+    MissionNotEnded { open: MissionId },
+
+    #[error("Mission mismatch: expected {expected}, got {actual}")]
+    MissionIdMismatch {
+        expected: MissionId,
+        actual: MissionId,
+    },
+
+    #[error("Mission end at sequence {sequence} does not link to the open mission's start")]
+    MissionLinkBroken { sequence: u64 },
+
+    #[error("Checkpoint {sequence} has trust mode {actual}, below the required {required}")]
+    TrustModeBelowMinimum {
+        sequence: u64,
+        required: TrustMode,
+        actual: TrustMode,
+    },
+
+    #[error("Heartbeat checkpoint {sequence} commits to entries or a mission event")]
+    HeartbeatPayload { sequence: u64 },
+
+    #[error("Gap record rejected: {0}")]
+    Gap(#[from] GapError),
+
+    #[error("Gap from {after} to {resume} ({reason:?}) is not permitted by the gap policy")]
+    GapNotPermitted {
+        after: u64,
+        resume: u64,
+        reason: GapReason,
+    },
+
+    #[error("Checkpoint runs an unapproved artifact: {0}")]
+    Approval(#[from] ApprovalError),
+}
+
+impl ErrorCoded for ChainError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ChainError::Signature(e) => e.code(),
+            ChainError::Serialization(e) => e.code(),
+            ChainError::InvalidGenesis => ErrorCode::InvalidGenesis,
+            ChainError::RobotMismatch { .. } => ErrorCode::RobotMismatch,
+            ChainError::SequenceRegression { .. } => ErrorCode::SequenceRegression,
+            ChainError::SequenceGap { .. } => ErrorCode::SequenceGap,
+            ChainError::CounterRegression { .. } => ErrorCode::CounterRegression,
+            ChainError::TimestampRegression { .. } => ErrorCode::TimestampRegression,
+            ChainError::PrevRootMismatch { .. } => ErrorCode::PrevRootMismatch,
+            ChainError::Rotation(e) => e.code(),
+            ChainError::KeyNotValidAtSequence { .. } => ErrorCode::KeyNotValidAtSequence,
+            ChainError::Delegation(e) => e.code(),
+            ChainError::DelegationOutOfScope { .. } => ErrorCode::DelegationOutOfScope,
+            ChainError::MissionNotEnded { .. } => ErrorCode::MissionNotEnded,
+            ChainError::MissionIdMismatch { .. } => ErrorCode::MissionIdMismatch,
+            ChainError::MissionLinkBroken { .. } => ErrorCode::MissionLinkBroken,
+            ChainError::TrustModeBelowMinimum { .. } => ErrorCode::TrustModeBelowMinimum,
+            ChainError::HeartbeatPayload { .. } => ErrorCode::HeartbeatPayload,
+            ChainError::Gap(e) => e.code(),
+            ChainError::GapNotPermitted { .. } => ErrorCode::GapNotPermitted,
+            ChainError::Approval(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::types::*;
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+
+    fn checkpoint(key: &SigningKey, sequence: u64, counter: u64, prev_root: Hash256) -> Checkpoint {
+        CheckpointBuilder::test_fixture()
+            .sequence(sequence)
+            .monotonic_counter(counter)
+            .prev_root(prev_root)
+            .build_and_sign(key)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_valid_chain() {
+        let key = SigningKey::generate(&mut OsRng);
+        let first = checkpoint(&key, 1, 10, [0u8; 32]);
+        let second = checkpoint(&key, 2, 11, first.compute_hash().unwrap());
+
+        let mut verifier = ChainVerifier::new(key.verifying_key());
+        verifier.verify_chain(&[first, second]).unwrap();
+        assert_eq!(verifier.head().unwrap().sequence, 2);
+    }
+
+    #[test]
+    fn test_only_accepted_checkpoints_become_verified() {
+        let key = SigningKey::generate(&mut OsRng);
+        let first = checkpoint(&key, 1, 10, [0u8; 32]);
+        let second = checkpoint(&key, 2, 11, first.compute_hash().unwrap());
+        let mut forged = second.clone();
+        forged.entries_root = [9u8; 32];
+
+        let mut verifier = ChainVerifier::new(key.verifying_key());
+        let received = UnverifiedCheckpoint::from_bytes(&first.to_bytes().unwrap()).unwrap();
+        assert_eq!(received.claimed_robot_id(), &first.robot_id);
+        let verified = received.verify(&mut verifier).unwrap();
+        assert_eq!(verified.sequence, 1);
+        assert_eq!(
+            crate::serialization::to_canonical_cbor(&verified).unwrap(),
+            first.to_bytes().unwrap()
+        );
+
+        // A rejected checkpoint does not come back, and the head stays put
+        assert!(matches!(
+            verifier.accept(forged),
+            Err(ChainError::Signature(_))
+        ));
+        assert_eq!(verifier.head().unwrap().sequence, 1);
+        assert_eq!(
+            verifier.accept(second.clone()).unwrap().into_inner(),
+            second
+        );
+    }
+
+    #[test]
+    fn test_unsupported_version_rejected_wherever_decoded() {
+        use crate::serialization::{from_canonical_cbor, to_canonical_cbor};
+
+        let key = SigningKey::generate(&mut OsRng);
+        let mut relabelled = checkpoint(&key, 1, 10, [0u8; 32]);
+        relabelled.version = 1;
+        let err = UnverifiedCheckpoint::from_bytes(&relabelled.to_bytes().unwrap()).unwrap_err();
+        assert!(matches!(
+            err,
+            SerializationError::UnsupportedVersion {
+                kind: "checkpoint",
+                version: 1
+            }
+        ));
+
+        // Embedded in another record, as in log, spool and archive records
+        let record = to_canonical_cbor(&(7u64, relabelled)).unwrap();
+        let err = from_canonical_cbor::<(u64, Checkpoint)>(&record).unwrap_err();
+        assert!(
+            err.to_string().contains("Unsupported checkpoint version 1"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_sequence_regression() {
+        let key = SigningKey::generate(&mut OsRng);
+        let first = checkpoint(&key, 5, 10, [0u8; 32]);
+        let replay = checkpoint(&key, 5, 11, first.compute_hash().unwrap());
+
+        let mut verifier = ChainVerifier::new(key.verifying_key());
+        verifier.verify_next(&first).unwrap();
+        let err = verifier.verify_next(&replay).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SequenceRegression);
+        assert_eq!(err.code().as_str(), "VB-CHK-004");
+    }
+
+    #[test]
+    fn test_counter_regression_and_prev_root_mismatch() {
+        let key = SigningKey::generate(&mut OsRng);
+        let first = checkpoint(&key, 1, 10, [0u8; 32]);
+
+        let mut verifier = ChainVerifier::new(key.verifying_key());
+        verifier.verify_next(&first).unwrap();
+
+        let stale_counter = checkpoint(&key, 2, 10, first.compute_hash().unwrap());
+        assert!(matches!(
+            verifier.verify_next(&stale_counter),
+            Err(ChainError::CounterRegression { .. })
+        ));
+
+        let forked = checkpoint(&key, 2, 11, [9u8; 32]);
+        assert!(matches!(
+            verifier.verify_next(&forked),
+            Err(ChainError::PrevRootMismatch { sequence: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_sequence_gap_bridged_by_permitted_gap_record() {
+        use crate::crypto::Signer;
+        use crate::gap::{GapPolicy, GapReason, GapRecord};
+
+        let robot = Signer::new(SigningKey::generate(&mut OsRng));
+        let key = robot.signing_key();
+        let first = checkpoint(key, 1, 10, [0u8; 32]);
+        let second = checkpoint(key, 2, 11, first.compute_hash().unwrap());
+        // #3 and #4 were lost in a reboot; #5 links to the lost #4
+        let resumed = checkpoint(key, 5, 20, [4u8; 32]);
+        let head = ChainHead::from_checkpoint(&second).unwrap();
+        let record =
+            GapRecord::issue(&head, 5, [4u8; 32], GapReason::Reboot, "power loss", &robot).unwrap();
+
+        let mut unjustified = ChainVerifier::new(key.verifying_key());
+        unjustified
+            .verify_chain(&[first.clone(), second.clone()])
+            .unwrap();
+        assert!(matches!(
+            unjustified.verify_next(&resumed),
+            Err(ChainError::SequenceGap { .. })
+        ));
+
+        // Justified, but the policy does not accept reboots
+        let mut verifier = ChainVerifier::new(key.verifying_key()).with_gap_policy(
+            GapPolicy::new().allow(GapReason::TeeUnavailable, 10, chrono::Duration::hours(1)),
+        );
+        verifier.add_gap(&record).unwrap();
+        verifier
+            .verify_chain(&[first.clone(), second.clone()])
+            .unwrap();
+        let err = verifier.verify_next(&resumed).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::GapNotPermitted);
+
+        let mut verifier = ChainVerifier::new(key.verifying_key()).with_gap_policy(
+            GapPolicy::new().allow(GapReason::Reboot, 2, chrono::Duration::hours(1)),
+        );
+        verifier.add_gap(&record).unwrap();
+        verifier.verify_chain(&[first, second]).unwrap();
+        let forked = checkpoint(key, 5, 20, [9u8; 32]);
+        assert!(matches!(
+            verifier.verify_next(&forked),
+            Err(ChainError::PrevRootMismatch { sequence: 5 })
+        ));
+        verifier.verify_next(&resumed).unwrap();
+        assert_eq!(verifier.head().unwrap().sequence, 5);
+        assert_eq!(verifier.bridged_gaps().len(), 1);
+        assert_eq!(verifier.bridged_gaps()[0], record);
+
+        // A record signed by a key the verifier does not trust is refused
+        let mut stranger = ChainVerifier::new(SigningKey::generate(&mut OsRng).verifying_key());
+        assert_eq!(
+            stranger.add_gap(&record).unwrap_err().code(),
+            ErrorCode::UnknownSigningKey
+        );
+    }
+
+    #[test]
+    fn test_rollback_attempts_raise_alerts() {
+        use crate::rollback::RollbackKind;
+        use std::sync::{mpsc, Mutex};
+
+        let key = SigningKey::generate(&mut OsRng);
+        let first = checkpoint(&key, 1, 10, [0u8; 32]);
+        let second = checkpoint(&key, 2, 11, first.compute_hash().unwrap());
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let (sender, receiver) = mpsc::channel();
+        let mut verifier = ChainVerifier::new(key.verifying_key())
+            .with_rollback_alerts(recorded.clone())
+            .with_rollback_alerts(Arc::new(sender));
+        verifier
+            .verify_chain(&[first.clone(), second.clone()])
+            .unwrap();
+
+        // A replay of #1, then #3 signed over a stale head
+        assert!(verifier.verify_next(&first).is_err());
+        let stale = checkpoint(&key, 3, 12, first.compute_hash().unwrap());
+        assert!(verifier.verify_next(&stale).is_err());
+        // A gap is rejected without an alert
+        assert!(verifier
+            .verify_next(&checkpoint(&key, 9, 20, [0u8; 32]))
+            .is_err());
+
+        let alerts = recorded.lock().unwrap();
+        let kinds: Vec<RollbackKind> = alerts.iter().map(|a| a.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                RollbackKind::SequenceRegression,
+                RollbackKind::PrevRootMismatch
+            ]
+        );
+        let alert = &alerts[1];
+        assert_eq!((alert.head_sequence, alert.offered_sequence), (2, 3));
+        assert_eq!(alert.head_hash, second.compute_hash().unwrap());
+        assert_eq!(alert.offered_prev_root, first.compute_hash().unwrap());
+        assert_eq!(alert.error.code, ErrorCode::PrevRootMismatch);
+        assert_eq!(receiver.try_iter().count(), 2);
+    }
+
+    #[test]
+    fn test_timestamp_regression() {
+        let key = SigningKey::generate(&mut OsRng);
+        let first = checkpoint(&key, 1, 10, [0u8; 32]);
+        let backdated = CheckpointBuilder::continuing_from(&first)
+            .unwrap()
+            .monotonic_counter(11)
+            .timestamp(first.local_timestamp_utc)
+            .entries_root([4u8; 32])
+            .build_and_sign(&key)
+            .unwrap();
+
+        let mut verifier = ChainVerifier::new(key.verifying_key());
+        verifier.verify_next(&first).unwrap();
+        let err = verifier.verify_next(&backdated).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TimestampRegression);
+    }
+
+    #[test]
+    fn test_trust_mode_downgrade_rejected() {
+        let key = SigningKey::generate(&mut OsRng);
+        let first = checkpoint(&key, 1, 10, [0u8; 32]);
+        let downgraded = CheckpointBuilder::continuing_from(&first)
+            .unwrap()
+            .monotonic_counter(11)
+            .trust_mode(TrustMode::Untrusted)
+            .entries_root([4u8; 32])
+            .build_and_sign(&key)
+            .unwrap();
+
+        // Without a minimum the downgrade goes through
+        let mut verifier = ChainVerifier::new(key.verifying_key());
+        verifier
+            .verify_chain(&[first.clone(), downgraded.clone()])
+            .unwrap();
+
+        let requirements = TrustRequirements {
+            default: Some(TrustMode::SoftAttestation),
+            classes: BTreeMap::from([("lab".to_string(), TrustMode::Untrusted)]),
+        };
+        let mut verifier = requirements.verifier(Some("surgical"), Box::new(key.verifying_key()));
+        verifier.verify_next(&first).unwrap();
+        let err = verifier.verify_next(&downgraded).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TrustModeBelowMinimum);
+        assert!(matches!(
+            err,
+            ChainError::TrustModeBelowMinimum {
+                sequence: 2,
+                required: TrustMode::SoftAttestation,
+                actual: TrustMode::Untrusted
+            }
+        ));
+
+        let mut verifier = requirements.verifier(Some("lab"), Box::new(key.verifying_key()));
+        verifier.verify_chain(&[first, downgraded]).unwrap();
+    }
+
+    #[test]
+    fn test_unapproved_model_rejected() {
+        use crate::approval::{Approval, ApprovalLog, ArtifactKind};
+
+        let key = SigningKey::generate(&mut OsRng);
+        let first = checkpoint(&key, 1, 10, [0u8; 32]);
+        let since = DateTime::UNIX_EPOCH;
+        let mut approvals = ApprovalLog::new();
+        approvals
+            .append(Approval::new(
+                ArtifactKind::Firmware,
+                [1u8; 32],
+                "fw",
+                since,
+            ))
+            .unwrap();
+
+        let mut verifier =
+            ChainVerifier::new(key.verifying_key()).with_approvals(Arc::new(approvals.clone()));
+        let err = verifier.verify_next(&first).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ArtifactNotApproved);
+        assert!(verifier.head().is_none());
+
+        approvals
+            .append(Approval::new(
+                ArtifactKind::Model,
+                [0u8; 32],
+                "model-v1",
+                since,
+            ))
+            .unwrap();
+        let mut verifier =
+            ChainVerifier::new(key.verifying_key()).with_approvals(Arc::new(approvals));
+        verifier.verify_next(&first).unwrap();
+    }
+
+    #[test]
+    fn test_wrong_key_rejected() {
+        let key = SigningKey::generate(&mut OsRng);
+        let other = SigningKey::generate(&mut OsRng);
+        let first = checkpoint(&key, 1, 10, [0u8; 32]);
+
+        let mut verifier = ChainVerifier::new(other.verifying_key());
+        let err = verifier.verify_next(&first).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::UnknownSigningKey);
+        assert!(verifier.head().is_none());
+    }
+
+    #[test]
+    fn test_resume_with_key_ring() {
+        let key = SigningKey::generate(&mut OsRng);
+        let first = checkpoint(&key, 1, 10, [0u8; 32]);
+        let second = checkpoint(&key, 2, 11, first.compute_hash().unwrap());
+
+        let mut ring = crate::keys::KeyRing::new();
+        ring.insert(key.verifying_key());
+
+        let head = ChainHead::from_checkpoint(&first).unwrap();
+        let mut verifier = ChainVerifier::with_resolver(Box::new(ring)).resume_from(head);
+        verifier.verify_next(&second).unwrap();
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let old = crate::crypto::Signer::generate();
+        let new = crate::crypto::Signer::generate();
+        let cert = KeyRotationCert::issue(&old, &new.verifying_key(), 2).unwrap();
+
+        let first = checkpoint(old.signing_key(), 1, 10, [0u8; 32]);
+        let prev_root = first.compute_hash().unwrap();
+        let second = checkpoint(new.signing_key(), 2, 11, prev_root);
+
+        // Without the certificate the new key is unknown
+        let mut verifier = ChainVerifier::new(old.verifying_key());
+        verifier.verify_next(&first).unwrap();
+        let err = verifier.verify_next(&second).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::UnknownSigningKey);
+
+        verifier.add_rotation(&cert).unwrap();
+        verifier.verify_next(&second).unwrap();
+
+        // The retired key cannot extend the chain past the rotation
+        let mut verifier = ChainVerifier::new(old.verifying_key());
+        verifier.add_rotation(&cert).unwrap();
+        verifier.verify_next(&first).unwrap();
+        let stale = checkpoint(old.signing_key(), 2, 11, prev_root);
+        let err = verifier.verify_next(&stale).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::KeyNotValidAtSequence);
+    }
+
+    #[test]
+    fn test_rotation_from_untrusted_key_rejected() {
+        use crate::crypto::Signer;
+
+        let stranger = Signer::generate();
+        let cert =
+            KeyRotationCert::issue(&stranger, &Signer::generate().verifying_key(), 2).unwrap();
+
+        let mut verifier = ChainVerifier::new(Signer::generate().verifying_key());
+        let err = verifier.add_rotation(&cert).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::UnknownSigningKey);
+    }
+
+    #[test]
+    fn test_delegated_operational_keys() {
+        use crate::crypto::Signer;
+        use crate::delegation::DelegationScope;
+        use chrono::Duration;
+
+        let identity = Signer::generate();
+        let operational = Signer::generate();
+        let scope = DelegationScope {
+            robot_id: RobotId("R-001".to_string()),
+            mission_id: None,
+        };
+        let now = Utc::now();
+        let cert = DelegationCert::issue(
+            &identity,
+            &operational.verifying_key(),
+            scope.clone(),
+            now - Duration::hours(1),
+            now + Duration::hours(1),
+        )
+        .unwrap();
+
+        let first = checkpoint(identity.signing_key(), 1, 10, [0u8; 32]);
+        let second = checkpoint(
+            operational.signing_key(),
+            2,
+            11,
+            first.compute_hash().unwrap(),
+        );
+
+        // Unknown until the delegation is accepted
+        let mut verifier = ChainVerifier::new(identity.verifying_key());
+        verifier.verify_next(&first).unwrap();
+        assert_eq!(
+            verifier.verify_next(&second).unwrap_err().code(),
+            ErrorCode::UnknownSigningKey
+        );
+        verifier.add_delegation(&cert).unwrap();
+        verifier.verify_next(&second).unwrap();
+
+        // Expired delegations do not cover the checkpoint
+        let expired = DelegationCert::issue(
+            &identity,
+            &operational.verifying_key(),
+            scope,
+            now - Duration::hours(2),
+            now - Duration::hours(1),
+        )
+        .unwrap();
+        let mut verifier = ChainVerifier::new(identity.verifying_key());
+        verifier.add_delegation(&expired).unwrap();
+        verifier.verify_next(&first).unwrap();
+        let err = verifier.verify_next(&second).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::DelegationOutOfScope);
+
+        // Delegations must come from a trusted key, and delegated keys cannot re-delegate
+        let mut verifier = ChainVerifier::new(operational.verifying_key());
+        let err = verifier.add_delegation(&cert).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::UnknownSigningKey);
+    }
+
+    #[test]
+    fn test_mission_lifecycle_rules() {
+        use crate::crypto::Signer;
+        use crate::mission::{tests::start_checkpoint, MissionAuthorization};
+
+        let robot = Signer::generate();
+        let authorization = MissionAuthorization::issue(
+            RobotId("R-001".to_string()),
+            MissionId("M-001".to_string()),
+            &Signer::generate(),
+        )
+        .unwrap();
+        let start = start_checkpoint(&robot, &authorization);
+        let start_hash = start.compute_hash().unwrap();
+        let next = |prev: &Checkpoint| {
+            CheckpointBuilder::continuing_from(prev)
+                .unwrap()
+                .monotonic_counter(prev.monotonic_counter + 1)
+                .timestamp(prev.local_timestamp_utc + chrono::Duration::seconds(1))
+                .entries_root([4u8; 32])
+        };
+
+        let mut verifier = ChainVerifier::new(robot.verifying_key());
+        verifier.verify_next(&start).unwrap();
+
+        let restart = next(&start)
+            .mission_start([7u8; 32])
+            .build_and_sign(robot.signing_key())
+            .unwrap();
+        let err = verifier.verify_next(&restart).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::MissionNotEnded);
+
+        let other = next(&start)
+            .mission_id(MissionId("M-002".to_string()))
+            .build_and_sign(robot.signing_key())
+            .unwrap();
+        let err = verifier.verify_next(&other).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::MissionIdMismatch);
+
+        let middle = next(&start).build_and_sign(robot.signing_key()).unwrap();
+        verifier.verify_next(&middle).unwrap();
+        assert_eq!(
+            verifier.head().unwrap().mission.as_ref().unwrap().start,
+            start_hash
+        );
+
+        let unlinked = next(&middle)
+            .mission_end([9u8; 32], [0u8; 32])
+            .build_and_sign(robot.signing_key())
+            .unwrap();
+        let err = verifier.verify_next(&unlinked).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::MissionLinkBroken);
+
+        let end = next(&middle)
+            .mission_end(start_hash, [0u8; 32])
+            .build_and_sign(robot.signing_key())
+            .unwrap();
+        verifier.verify_next(&end).unwrap();
+        assert!(verifier.head().unwrap().mission.is_none());
+
+        // After the end, a different mission may start
+        let following = next(&end)
+            .mission_id(MissionId("M-002".to_string()))
+            .mission_start([7u8; 32])
+            .build_and_sign(robot.signing_key())
+            .unwrap();
+        verifier.verify_next(&following).unwrap();
+    }
+}
@@ -3,14 +3,32 @@
 //! A checkpoint is a tamper-evident snapshot of robot state at a given time,
 //! cryptographically signed by a TEE enclave.
 
+use crate::clock::{system_clock, Clock};
+use crate::crypto::{ct_eq, key_id, DigestAlgorithm};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::health::HealthSummary;
+use crate::inspect::CheckpointSummary;
+use crate::keys::KeyResolver;
+use crate::merkle::MerkleProof;
+use crate::mission::MissionEvent;
 use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
 use crate::types::*;
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::sync::Arc;
 
 /// Checkpoint version (for schema evolution)
-pub const CHECKPOINT_VERSION: u8 = 1;
+///
+/// Version 2 encodes timestamps as integer nanoseconds (see
+/// [`crate::serialization::timestamp`]); version 1 encoded them as RFC 3339
+/// strings, and is no longer decoded.
+pub const CHECKPOINT_VERSION: u8 = 2;
+
+/// The version field alone, read before the rest of a checkpoint.
+#[derive(Deserialize)]
+struct VersionTag {
+    version: u8,
+}
 
 /// A cryptographically signed checkpoint with anti-rollback protection.
 ///
@@ -23,9 +41,16 @@ pub const CHECKPOINT_VERSION: u8 = 1;
 /// All fields serialize to canonical CBOR for deterministic hashing.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Checkpoint {
-    /// Schema version
+    /// Schema version; any but [`CHECKPOINT_VERSION`] fails to deserialize,
+    /// wherever the checkpoint is embedded
+    #[serde(deserialize_with = "supported_version")]
     pub version: u8,
 
+    /// Digest algorithm of `compute_hash` and the entries Merkle tree
+    /// (absent for SHA-256)
+    #[serde(default, skip_serializing_if = "DigestAlgorithm::is_default")]
+    pub hash_alg: DigestAlgorithm,
+
     /// Robot identifier
     pub robot_id: RobotId,
 
@@ -39,6 +64,7 @@ pub struct Checkpoint {
     pub monotonic_counter: u64,
 
     /// Timestamp from robot clock (local, not authoritative)
+    #[serde(with = "crate::serialization::timestamp")]
     pub local_timestamp_utc: DateTime<Utc>,
 
     /// Model provenance (hash + supply chain metadata)
@@ -50,18 +76,49 @@ pub struct Checkpoint {
     /// TEE enclave measurement (code hash)
     pub enclave_measurement: Vec<u8>,
 
+    /// Hash of the robot's signed hardware inventory (see [`crate::inventory`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hardware_inventory: Option<Hash256>,
+
     /// Hash of previous checkpoint root (anti-rollback chaining)
     pub prev_root: Hash256,
 
     /// Merkle root of log entries since last checkpoint
     pub entries_root: Hash256,
 
+    /// Hash of the redaction policy applied to entries before hashing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redaction_policy: Option<Hash256>,
+
+    /// Hashes of the sub-key certificates whose keys signed entries (see [`crate::subkey`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sub_key_certs: Vec<Hash256>,
+
+    /// Hash of the signed agent configuration in force (see [`crate::config`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_config: Option<Hash256>,
+
+    /// Agent health since the previous checkpoint (see [`crate::health`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_health: Option<HealthSummary>,
+
     /// Deterministic inference configuration
     pub inference_config: DeterminismConfig,
 
     /// Trust mode
     pub trust_mode: TrustMode,
 
+    /// Mission start/end marker (see [`crate::mission`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mission_event: Option<MissionEvent>,
+
+    /// Liveness-only checkpoint with a zero `entries_root` (see [`crate::heartbeat`])
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub heartbeat: bool,
+
+    /// Fingerprint of the verifying key that signed this checkpoint
+    pub signer_key_id: KeyId,
+
     /// Ed25519 signature over canonical CBOR of all fields above
     pub signature: SignatureBytes,
 }
@@ -69,36 +126,62 @@ pub struct Checkpoint {
 impl Checkpoint {
     /// Compute the canonical hash of this checkpoint (for prev_root chaining).
     ///
-    /// This hash is computed over the *unsigned* checkpoint (all fields except signature).
+    /// This hash is computed over the *unsigned* checkpoint (all fields except signature)
+    /// with the checkpoint's `hash_alg`.
     pub fn compute_hash(&self) -> Result<Hash256, SerializationError> {
-        // Create unsigned version for hashing
-        let unsigned = UnsignedCheckpoint {
-            version: self.version,
-            robot_id: self.robot_id.clone(),
-            mission_id: self.mission_id.clone(),
-            sequence: self.sequence,
-            monotonic_counter: self.monotonic_counter,
-            local_timestamp_utc: self.local_timestamp_utc,
-            model_provenance: self.model_provenance.clone(),
-            firmware_hash: self.firmware_hash,
-            enclave_measurement: self.enclave_measurement.clone(),
-            prev_root: self.prev_root,
-            entries_root: self.entries_root,
-            inference_config: self.inference_config.clone(),
-            trust_mode: self.trust_mode,
-        };
+        let bytes = to_canonical_cbor(&self.unsigned())?;
+        Ok(self.hash_alg.digest(&bytes))
+    }
 
-        let bytes = to_canonical_cbor(&unsigned)?;
-        let hash = Sha256::digest(&bytes);
-        Ok(hash.into())
+    /// Whether `proof` shows an entry under this checkpoint's entries_root,
+    /// hashed with this checkpoint's `hash_alg`.
+    pub fn includes_entry(&self, proof: &MerkleProof) -> bool {
+        proof.hash_alg == self.hash_alg && proof.verify(&self.entries_root)
     }
 
     /// Verify the signature on this checkpoint.
-    pub fn verify_signature(&self, public_key: &ed25519_dalek::VerifyingKey) -> Result<(), SignatureError> {
+    ///
+    /// Fails with `KeyIdMismatch` if `public_key` is not the key named by `signer_key_id`.
+    pub fn verify_signature(
+        &self,
+        public_key: &ed25519_dalek::VerifyingKey,
+    ) -> Result<(), SignatureError> {
         use ed25519_dalek::Verifier;
 
-        let unsigned = UnsignedCheckpoint {
+        if !ct_eq(&key_id(public_key).0, &self.signer_key_id.0) {
+            return Err(SignatureError::KeyIdMismatch(self.signer_key_id));
+        }
+
+        let message =
+            to_canonical_cbor(&self.unsigned()).map_err(|_| SignatureError::SerializationFailed)?;
+
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+
+        public_key
+            .verify(&message, &signature)
+            .map_err(|_| SignatureError::InvalidSignature)
+    }
+
+    /// The bytes the signature covers: canonical CBOR of every field but
+    /// `signature`. Constrained verifiers (see `veribot-proof-verify`) check
+    /// these instead of decoding the whole checkpoint.
+    pub fn signed_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&self.unsigned())
+    }
+
+    /// Verify the signature using a key looked up by `signer_key_id`.
+    pub fn verify_with_resolver(&self, resolver: &dyn KeyResolver) -> Result<(), SignatureError> {
+        let public_key = resolver
+            .resolve(&self.signer_key_id)
+            .ok_or(SignatureError::UnknownKey(self.signer_key_id))?;
+        self.verify_signature(&public_key)
+    }
+
+    /// The signed portion of this checkpoint (all fields except signature).
+    fn unsigned(&self) -> UnsignedCheckpoint {
+        UnsignedCheckpoint {
             version: self.version,
+            hash_alg: self.hash_alg,
             robot_id: self.robot_id.clone(),
             mission_id: self.mission_id.clone(),
             sequence: self.sequence,
@@ -107,19 +190,29 @@ impl Checkpoint {
             model_provenance: self.model_provenance.clone(),
             firmware_hash: self.firmware_hash,
             enclave_measurement: self.enclave_measurement.clone(),
+            hardware_inventory: self.hardware_inventory,
             prev_root: self.prev_root,
             entries_root: self.entries_root,
+            redaction_policy: self.redaction_policy,
+            sub_key_certs: self.sub_key_certs.clone(),
+            agent_config: self.agent_config,
+            agent_health: self.agent_health,
             inference_config: self.inference_config.clone(),
             trust_mode: self.trust_mode,
-        };
-
-        let message = to_canonical_cbor(&unsigned)
-            .map_err(|_| SignatureError::SerializationFailed)?;
+            mission_event: self.mission_event,
+            heartbeat: self.heartbeat,
+            signer_key_id: self.signer_key_id,
+        }
+    }
 
-        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+    /// Summarize this checkpoint for display (truncated hashes, provenance).
+    pub fn describe(&self) -> CheckpointSummary {
+        CheckpointSummary::new(self, None)
+    }
 
-        public_key.verify(&message, &signature)
-            .map_err(|_| SignatureError::InvalidSignature)
+    /// Summarize this checkpoint including elapsed time and changes since `prev`.
+    pub fn describe_since(&self, prev: &Checkpoint) -> CheckpointSummary {
+        CheckpointSummary::new(self, Some(prev))
     }
 
     /// Serialize to canonical CBOR bytes.
@@ -128,31 +221,73 @@ impl Checkpoint {
     }
 
     /// Deserialize from canonical CBOR bytes.
+    ///
+    /// Checkpoints of any version but [`CHECKPOINT_VERSION`] are refused
+    /// with [`SerializationError::UnsupportedVersion`].
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        let VersionTag { version } = from_canonical_cbor(bytes)?;
+        if version != CHECKPOINT_VERSION {
+            return Err(SerializationError::UnsupportedVersion {
+                kind: "checkpoint",
+                version,
+            });
+        }
         from_canonical_cbor(bytes)
     }
 }
 
+/// Refuse checkpoints of any version but [`CHECKPOINT_VERSION`]. The version
+/// is serialized first, so this runs before fields whose encoding changed.
+fn supported_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    let version = u8::deserialize(deserializer)?;
+    if version != CHECKPOINT_VERSION {
+        return Err(de::Error::custom(SerializationError::UnsupportedVersion {
+            kind: "checkpoint",
+            version,
+        }));
+    }
+    Ok(version)
+}
+
 /// Unsigned checkpoint (for signature computation)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct UnsignedCheckpoint {
     pub version: u8,
+    #[serde(default, skip_serializing_if = "DigestAlgorithm::is_default")]
+    pub hash_alg: DigestAlgorithm,
     pub robot_id: RobotId,
     pub mission_id: MissionId,
     pub sequence: u64,
     pub monotonic_counter: u64,
+    #[serde(with = "crate::serialization::timestamp")]
     pub local_timestamp_utc: DateTime<Utc>,
     pub model_provenance: ModelProvenance,
     pub firmware_hash: Hash256,
     pub enclave_measurement: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hardware_inventory: Option<Hash256>,
     pub prev_root: Hash256,
     pub entries_root: Hash256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redaction_policy: Option<Hash256>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sub_key_certs: Vec<Hash256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_config: Option<Hash256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_health: Option<HealthSummary>,
     pub inference_config: DeterminismConfig,
     pub trust_mode: TrustMode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mission_event: Option<MissionEvent>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub heartbeat: bool,
+    pub signer_key_id: KeyId,
 }
 
 /// Builder for constructing checkpoints.
 pub struct CheckpointBuilder {
+    hash_alg: DigestAlgorithm,
     robot_id: Option<RobotId>,
     mission_id: Option<MissionId>,
     sequence: Option<u64>,
@@ -161,15 +296,27 @@ pub struct CheckpointBuilder {
     model_provenance: Option<ModelProvenance>,
     firmware_hash: Option<Hash256>,
     enclave_measurement: Option<Vec<u8>>,
+    hardware_inventory: Option<Hash256>,
     prev_root: Option<Hash256>,
     entries_root: Option<Hash256>,
+    redaction_policy: Option<Hash256>,
+    sub_key_certs: Vec<Hash256>,
+    agent_config: Option<Hash256>,
+    agent_health: Option<HealthSummary>,
     inference_config: Option<DeterminismConfig>,
     trust_mode: Option<TrustMode>,
+    mission_event: Option<MissionEvent>,
+    heartbeat: bool,
+    /// Counter of the checkpoint being continued (set by `continuing_from`)
+    prev_counter: Option<u64>,
+    /// Source of the timestamp when none is set
+    clock: Arc<dyn Clock>,
 }
 
 impl CheckpointBuilder {
     pub fn new() -> Self {
         Self {
+            hash_alg: DigestAlgorithm::default(),
             robot_id: None,
             mission_id: None,
             sequence: None,
@@ -178,13 +325,112 @@ impl CheckpointBuilder {
             model_provenance: None,
             firmware_hash: None,
             enclave_measurement: None,
+            hardware_inventory: None,
             prev_root: None,
             entries_root: None,
+            redaction_policy: None,
+            sub_key_certs: Vec::new(),
+            agent_config: None,
+            agent_health: None,
             inference_config: None,
             trust_mode: None,
+            mission_event: None,
+            heartbeat: false,
+            prev_counter: None,
+            clock: system_clock(),
         }
     }
 
+    /// A genesis builder with every required field set, for tests.
+    ///
+    /// Robot `R-001`, mission `M-001`, sequence 0 and counter 1; override
+    /// whatever a test depends on.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn test_fixture() -> Self {
+        Self::new()
+            .robot_id(RobotId("R-001".to_string()))
+            .mission_id(MissionId("M-001".to_string()))
+            .sequence(0)
+            .monotonic_counter(1)
+            .model_provenance(ModelProvenance {
+                name: "model-v1".to_string(),
+                model_hash: [0u8; 32],
+                dataset_hash: None,
+                container_digest: None,
+                signature_bundle: None,
+            })
+            .firmware_hash([1u8; 32])
+            .enclave_measurement(vec![2u8; 32])
+            .prev_root([0u8; 32])
+            .entries_root([3u8; 32])
+            .inference_config(DeterminismConfig {
+                rng_seed: None,
+                batch_size: 1,
+                flags: None,
+            })
+    }
+
+    /// Start a builder for the checkpoint that follows `prev`.
+    ///
+    /// Sets `sequence = prev.sequence + 1` and `prev_root = prev.compute_hash()`, and
+    /// carries forward the hash algorithm, robot/mission IDs, provenance, firmware, enclave measurement,
+    /// hardware inventory, agent config, inference config and trust mode (but not mission events, the
+    /// redaction policy, sub-key certificates or agent health, which describe this checkpoint alone). Only `entries_root` and
+    /// `monotonic_counter` must still be supplied; the counter is checked to exceed `prev`'s
+    /// at build time.
+    pub fn continuing_from(prev: &Checkpoint) -> Result<Self, BuildError> {
+        let prev_root = prev
+            .compute_hash()
+            .map_err(|_| BuildError::SerializationFailed)?;
+
+        Ok(Self {
+            hash_alg: prev.hash_alg,
+            robot_id: Some(prev.robot_id.clone()),
+            mission_id: Some(prev.mission_id.clone()),
+            sequence: Some(prev.sequence + 1),
+            monotonic_counter: None,
+            local_timestamp_utc: None,
+            model_provenance: Some(prev.model_provenance.clone()),
+            firmware_hash: Some(prev.firmware_hash),
+            enclave_measurement: Some(prev.enclave_measurement.clone()),
+            hardware_inventory: prev.hardware_inventory,
+            prev_root: Some(prev_root),
+            entries_root: None,
+            redaction_policy: None,
+            sub_key_certs: Vec::new(),
+            agent_config: prev.agent_config,
+            agent_health: None,
+            inference_config: Some(prev.inference_config.clone()),
+            trust_mode: Some(prev.trust_mode),
+            mission_event: None,
+            heartbeat: false,
+            prev_counter: Some(prev.monotonic_counter),
+            clock: system_clock(),
+        })
+    }
+
+    /// Start a builder for the heartbeat that follows `prev`.
+    ///
+    /// Like [`continuing_from`](Self::continuing_from), with a zero
+    /// `entries_root`: only `monotonic_counter` must still be supplied.
+    /// Entries, mission events, a redaction policy or sub-key certificates
+    /// fail the build with [`BuildError::HeartbeatPayload`].
+    pub fn heartbeat_from(prev: &Checkpoint) -> Result<Self, BuildError> {
+        let mut builder = Self::continuing_from(prev)?;
+        builder.entries_root = Some([0u8; 32]);
+        builder.heartbeat = true;
+        Ok(builder)
+    }
+
+    /// Hash the checkpoint and its entries tree with `alg` (default SHA-256).
+    ///
+    /// `prev_root` is always the previous checkpoint's hash under its own
+    /// algorithm, so a chain can switch algorithms at any checkpoint.
+    pub fn hash_alg(mut self, alg: DigestAlgorithm) -> Self {
+        self.hash_alg = alg;
+        self
+    }
+
     pub fn robot_id(mut self, id: RobotId) -> Self {
         self.robot_id = Some(id);
         self
@@ -210,6 +456,12 @@ impl CheckpointBuilder {
         self
     }
 
+    /// Read the timestamp from `clock` when none is set (default: system clock).
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn model_provenance(mut self, prov: ModelProvenance) -> Self {
         self.model_provenance = Some(prov);
         self
@@ -225,6 +477,12 @@ impl CheckpointBuilder {
         self
     }
 
+    /// Reference the robot's signed hardware inventory by its hash.
+    pub fn hardware_inventory(mut self, hash: Hash256) -> Self {
+        self.hardware_inventory = Some(hash);
+        self
+    }
+
     pub fn prev_root(mut self, root: Hash256) -> Self {
         self.prev_root = Some(root);
         self
@@ -235,6 +493,30 @@ impl CheckpointBuilder {
         self
     }
 
+    /// Record the hash of the redaction policy applied to this checkpoint's entries.
+    pub fn redaction_policy(mut self, hash: Hash256) -> Self {
+        self.redaction_policy = Some(hash);
+        self
+    }
+
+    /// Commit to a sub-key certificate (by hash) whose key signed entries of this checkpoint.
+    pub fn sub_key_cert(mut self, hash: Hash256) -> Self {
+        self.sub_key_certs.push(hash);
+        self
+    }
+
+    /// Commit to the signed agent configuration (by hash) in force on the robot.
+    pub fn agent_config(mut self, hash: Hash256) -> Self {
+        self.agent_config = Some(hash);
+        self
+    }
+
+    /// Report agent health since the previous checkpoint (see [`crate::health`]).
+    pub fn agent_health(mut self, summary: HealthSummary) -> Self {
+        self.agent_health = Some(summary);
+        self
+    }
+
     pub fn inference_config(mut self, config: DeterminismConfig) -> Self {
         self.inference_config = Some(config);
         self
@@ -245,6 +527,23 @@ impl CheckpointBuilder {
         self
     }
 
+    /// Mark this checkpoint as the start of a mission authorized by the
+    /// [`crate::MissionAuthorization`] with hash `authorization`.
+    pub fn mission_start(mut self, authorization: Hash256) -> Self {
+        self.mission_event = Some(MissionEvent::Start { authorization });
+        self
+    }
+
+    /// Mark this checkpoint as the end of the mission started by the
+    /// checkpoint with hash `start`, committing to its summary root.
+    pub fn mission_end(mut self, start: Hash256, summary_root: Hash256) -> Self {
+        self.mission_event = Some(MissionEvent::End {
+            start,
+            summary_root,
+        });
+        self
+    }
+
     /// Build and sign the checkpoint using the provided signing key.
     pub fn build_and_sign(
         self,
@@ -252,29 +551,69 @@ impl CheckpointBuilder {
     ) -> Result<Checkpoint, BuildError> {
         use ed25519_dalek::Signer;
 
+        if let (Some(previous), Some(actual)) = (self.prev_counter, self.monotonic_counter) {
+            if actual <= previous {
+                return Err(BuildError::CounterRegression { previous, actual });
+            }
+        }
+
+        if self.heartbeat
+            && (self.entries_root != Some([0u8; 32])
+                || self.mission_event.is_some()
+                || self.redaction_policy.is_some()
+                || !self.sub_key_certs.is_empty())
+        {
+            return Err(BuildError::HeartbeatPayload);
+        }
+
         let unsigned = UnsignedCheckpoint {
             version: CHECKPOINT_VERSION,
+            hash_alg: self.hash_alg,
             robot_id: self.robot_id.ok_or(BuildError::MissingField("robot_id"))?,
-            mission_id: self.mission_id.ok_or(BuildError::MissingField("mission_id"))?,
+            mission_id: self
+                .mission_id
+                .ok_or(BuildError::MissingField("mission_id"))?,
             sequence: self.sequence.ok_or(BuildError::MissingField("sequence"))?,
-            monotonic_counter: self.monotonic_counter.ok_or(BuildError::MissingField("monotonic_counter"))?,
-            local_timestamp_utc: self.local_timestamp_utc.unwrap_or_else(Utc::now),
-            model_provenance: self.model_provenance.ok_or(BuildError::MissingField("model_provenance"))?,
-            firmware_hash: self.firmware_hash.ok_or(BuildError::MissingField("firmware_hash"))?,
-            enclave_measurement: self.enclave_measurement.ok_or(BuildError::MissingField("enclave_measurement"))?,
-            prev_root: self.prev_root.ok_or(BuildError::MissingField("prev_root"))?,
-            entries_root: self.entries_root.ok_or(BuildError::MissingField("entries_root"))?,
-            inference_config: self.inference_config.ok_or(BuildError::MissingField("inference_config"))?,
+            monotonic_counter: self
+                .monotonic_counter
+                .ok_or(BuildError::MissingField("monotonic_counter"))?,
+            local_timestamp_utc: self.local_timestamp_utc.unwrap_or_else(|| self.clock.now()),
+            model_provenance: self
+                .model_provenance
+                .ok_or(BuildError::MissingField("model_provenance"))?,
+            firmware_hash: self
+                .firmware_hash
+                .ok_or(BuildError::MissingField("firmware_hash"))?,
+            enclave_measurement: self
+                .enclave_measurement
+                .ok_or(BuildError::MissingField("enclave_measurement"))?,
+            hardware_inventory: self.hardware_inventory,
+            prev_root: self
+                .prev_root
+                .ok_or(BuildError::MissingField("prev_root"))?,
+            entries_root: self
+                .entries_root
+                .ok_or(BuildError::MissingField("entries_root"))?,
+            redaction_policy: self.redaction_policy,
+            sub_key_certs: self.sub_key_certs,
+            agent_config: self.agent_config,
+            agent_health: self.agent_health,
+            inference_config: self
+                .inference_config
+                .ok_or(BuildError::MissingField("inference_config"))?,
             trust_mode: self.trust_mode.unwrap_or(TrustMode::Trusted),
+            mission_event: self.mission_event,
+            heartbeat: self.heartbeat,
+            signer_key_id: key_id(&signing_key.verifying_key()),
         };
 
-        let message = to_canonical_cbor(&unsigned)
-            .map_err(|_| BuildError::SerializationFailed)?;
+        let message = to_canonical_cbor(&unsigned).map_err(|_| BuildError::SerializationFailed)?;
 
         let signature = signing_key.sign(&message);
 
         Ok(Checkpoint {
             version: unsigned.version,
+            hash_alg: unsigned.hash_alg,
             robot_id: unsigned.robot_id,
             mission_id: unsigned.mission_id,
             sequence: unsigned.sequence,
@@ -283,10 +622,18 @@ impl CheckpointBuilder {
             model_provenance: unsigned.model_provenance,
             firmware_hash: unsigned.firmware_hash,
             enclave_measurement: unsigned.enclave_measurement,
+            hardware_inventory: unsigned.hardware_inventory,
             prev_root: unsigned.prev_root,
             entries_root: unsigned.entries_root,
+            redaction_policy: unsigned.redaction_policy,
+            sub_key_certs: unsigned.sub_key_certs,
+            agent_config: unsigned.agent_config,
+            agent_health: unsigned.agent_health,
             inference_config: unsigned.inference_config,
             trust_mode: unsigned.trust_mode,
+            mission_event: unsigned.mission_event,
+            heartbeat: unsigned.heartbeat,
+            signer_key_id: unsigned.signer_key_id,
             signature: SignatureBytes::from(signature.to_bytes()),
         })
     }
@@ -305,6 +652,23 @@ pub enum BuildError {
 
     #[error("Serialization failed")]
     SerializationFailed,
+
+    #[error("Monotonic counter must exceed previous checkpoint's ({actual} <= {previous})")]
+    CounterRegression { previous: u64, actual: u64 },
+
+    #[error("Heartbeat checkpoints carry no entries, mission events, redaction policy or sub-key certificates")]
+    HeartbeatPayload,
+}
+
+impl ErrorCoded for BuildError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            BuildError::MissingField(_) => ErrorCode::MissingField,
+            BuildError::SerializationFailed => ErrorCode::SigningPayload,
+            BuildError::CounterRegression { .. } => ErrorCode::CounterRegression,
+            BuildError::HeartbeatPayload => ErrorCode::HeartbeatPayload,
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -314,6 +678,23 @@ pub enum SignatureError {
 
     #[error("Invalid signature")]
     InvalidSignature,
+
+    #[error("Verifying key does not match signer key id {0}")]
+    KeyIdMismatch(KeyId),
+
+    #[error("No verifying key known for key id {0}")]
+    UnknownKey(KeyId),
+}
+
+impl ErrorCoded for SignatureError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            SignatureError::SerializationFailed => ErrorCode::SigningPayload,
+            SignatureError::InvalidSignature => ErrorCode::InvalidSignature,
+            SignatureError::KeyIdMismatch(_) => ErrorCode::KeyIdMismatch,
+            SignatureError::UnknownKey(_) => ErrorCode::UnknownSigningKey,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -326,22 +707,11 @@ mod tests {
         let mut csprng = OsRng;
         let signing_key = SigningKey::generate(&mut csprng);
 
-        let checkpoint = CheckpointBuilder::new()
-            .robot_id(RobotId("R-001".to_string()))
+        let checkpoint = CheckpointBuilder::test_fixture()
             .mission_id(MissionId("M-2025-10-11-01".to_string()))
             .sequence(1)
             .monotonic_counter(100)
-            .model_provenance(ModelProvenance {
-                name: "model-v1".to_string(),
-                model_hash: [0u8; 32],
-                dataset_hash: None,
-                container_digest: None,
-                signature_bundle: None,
-            })
-            .firmware_hash([1u8; 32])
             .enclave_measurement(vec![2u8; 48])
-            .prev_root([0u8; 32])
-            .entries_root([3u8; 32])
             .inference_config(DeterminismConfig {
                 rng_seed: Some(42),
                 batch_size: 1,
@@ -362,6 +732,131 @@ mod tests {
         assert!(checkpoint.verify_signature(&verifying_key).is_ok());
     }
 
+    #[test]
+    fn test_missing_field_error_code() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let err = CheckpointBuilder::new()
+            .build_and_sign(&signing_key)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::MissingField);
+        assert_eq!(err.detail().code.as_str(), "VB-CHK-001");
+    }
+
+    #[test]
+    fn test_default_timestamp_from_clock() {
+        let (prev, signing_key) = create_test_checkpoint();
+        let at = DateTime::from_timestamp(1_760_000_000, 0).unwrap();
+        let next = CheckpointBuilder::continuing_from(&prev)
+            .unwrap()
+            .monotonic_counter(101)
+            .entries_root([4u8; 32])
+            .clock(Arc::new(crate::clock::MockClock::new(at)))
+            .build_and_sign(&signing_key)
+            .unwrap();
+        assert_eq!(next.local_timestamp_utc, at);
+    }
+
+    #[test]
+    fn test_continuing_from() {
+        let (prev, signing_key) = create_test_checkpoint();
+
+        let next = CheckpointBuilder::continuing_from(&prev)
+            .unwrap()
+            .monotonic_counter(101)
+            .entries_root([4u8; 32])
+            .build_and_sign(&signing_key)
+            .unwrap();
+
+        assert_eq!(next.sequence, prev.sequence + 1);
+        assert_eq!(next.prev_root, prev.compute_hash().unwrap());
+        assert_eq!(next.model_provenance, prev.model_provenance);
+        assert_eq!(next.trust_mode, prev.trust_mode);
+
+        let mut verifier = crate::chain::ChainVerifier::new(signing_key.verifying_key());
+        verifier.verify_chain(&[prev, next]).unwrap();
+    }
+
+    #[test]
+    fn test_hash_alg_threads_through_chain() {
+        let (sha256_checkpoint, signing_key) = create_test_checkpoint();
+        let bytes = sha256_checkpoint.to_bytes().unwrap();
+        assert!(!bytes.windows(8).any(|w| w == b"hash_alg"));
+
+        // Switch to SHA3-256 mid-chain: prev_root is the SHA-256 hash of the predecessor
+        let mut tree = crate::merkle::MerkleTree::new().with_hash_alg(DigestAlgorithm::Sha3_256);
+        tree.insert(crate::merkle::Entry::new(1000, 0, b"entry"));
+        let next = CheckpointBuilder::continuing_from(&sha256_checkpoint)
+            .unwrap()
+            .hash_alg(DigestAlgorithm::Sha3_256)
+            .monotonic_counter(101)
+            .entries_root(tree.root())
+            .build_and_sign(&signing_key)
+            .unwrap();
+        assert_eq!(next.prev_root, sha256_checkpoint.compute_hash().unwrap());
+        assert!(next.verify_signature(&signing_key.verifying_key()).is_ok());
+        assert_eq!(
+            next.compute_hash().unwrap(),
+            DigestAlgorithm::Sha3_256.digest(&to_canonical_cbor(&next.unsigned()).unwrap())
+        );
+        assert_eq!(
+            Checkpoint::from_bytes(&next.to_bytes().unwrap()).unwrap(),
+            next
+        );
+
+        // The algorithm is carried forward, and proofs must use it
+        let after = CheckpointBuilder::continuing_from(&next).unwrap();
+        assert_eq!(after.hash_alg, DigestAlgorithm::Sha3_256);
+        let proof = tree.generate_proof(1000, 0).unwrap();
+        assert!(next.includes_entry(&proof));
+        let mut sha256_tree = crate::merkle::MerkleTree::new();
+        sha256_tree.insert(crate::merkle::Entry::new(1000, 0, b"entry"));
+        assert!(!next.includes_entry(&sha256_tree.generate_proof(1000, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_continuing_from_requires_new_fields() {
+        let (prev, signing_key) = create_test_checkpoint();
+
+        let err = CheckpointBuilder::continuing_from(&prev)
+            .unwrap()
+            .monotonic_counter(101)
+            .build_and_sign(&signing_key)
+            .unwrap_err();
+        assert!(matches!(err, BuildError::MissingField("entries_root")));
+
+        let err = CheckpointBuilder::continuing_from(&prev)
+            .unwrap()
+            .monotonic_counter(prev.monotonic_counter)
+            .entries_root([4u8; 32])
+            .build_and_sign(&signing_key)
+            .unwrap_err();
+        assert!(matches!(err, BuildError::CounterRegression { .. }));
+    }
+
+    #[test]
+    fn test_signer_key_id_and_resolver() {
+        let (checkpoint, signing_key) = create_test_checkpoint();
+        assert_eq!(
+            checkpoint.signer_key_id,
+            key_id(&signing_key.verifying_key())
+        );
+
+        let mut keys = crate::keys::KeyRing::new();
+        assert!(matches!(
+            checkpoint.verify_with_resolver(&keys),
+            Err(SignatureError::UnknownKey(_))
+        ));
+
+        keys.insert(signing_key.verifying_key());
+        assert!(checkpoint.verify_with_resolver(&keys).is_ok());
+
+        let other = SigningKey::generate(&mut OsRng);
+        assert!(matches!(
+            checkpoint.verify_signature(&other.verifying_key()),
+            Err(SignatureError::KeyIdMismatch(_))
+        ));
+    }
+
     #[test]
     fn test_checkpoint_hash_determinism() {
         let (checkpoint, _) = create_test_checkpoint();
@@ -383,4 +878,30 @@ mod tests {
         assert_eq!(checkpoint, decoded);
         assert!(decoded.verify_signature(&verifying_key).is_ok());
     }
+
+    #[test]
+    fn test_version_1_checkpoint_rejected() {
+        // Version 1 timestamps were RFC 3339 strings
+        let (checkpoint, _) = create_test_checkpoint();
+        let mut value = ciborium::Value::serialized(&checkpoint).unwrap();
+        for (key, field) in value.as_map_mut().unwrap() {
+            match key.as_text().unwrap() {
+                "version" => *field = ciborium::Value::from(1u8),
+                "local_timestamp_utc" => {
+                    *field = ciborium::Value::from(checkpoint.local_timestamp_utc.to_rfc3339())
+                }
+                _ => {}
+            }
+        }
+        let bytes = to_canonical_cbor(&value).unwrap();
+        let err = Checkpoint::from_bytes(&bytes).unwrap_err();
+        assert!(matches!(
+            err,
+            SerializationError::UnsupportedVersion {
+                kind: "checkpoint",
+                version: 1
+            }
+        ));
+        assert_eq!(err.code(), ErrorCode::UnsupportedVersion);
+    }
 }
@@ -0,0 +1,315 @@
+//! Time sources and clock skew policy for checkpoint timestamps.
+//!
+//! Components that read the current time (checkpoint builders, nonce
+//! expiry, adapter caches, audit logs) take it from a [`Clock`]. Production
+//! code uses [`SystemClock`]; tests substitute a [`MockClock`] and move it by
+//! hand, so expiry, freshness and skew logic run deterministically.
+//!
+//! A checkpoint's `local_timestamp_utc` is whatever the robot's clock said.
+//! The gateway compares it with its own receive time under a
+//! [`ClockSkewPolicy`] and signs the outcome as a [`SkewDecision`], which it
+//! returns alongside the checkpoint's inclusion proof. Relying parties can
+//! then tell how far a timestamp can be trusted without trusting the robot's
+//! clock. (That timestamps strictly increase within a chain is enforced by
+//! [`crate::ChainVerifier`].)
+
+use crate::checkpoint::Checkpoint;
+use crate::crypto::{ct_eq, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, KeyId, SignatureBytes};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// Source of the current time.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// The system clock as a shareable [`Clock`], the default wherever one is taken.
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// A clock that only moves when told to.
+///
+/// Clones share the same time, so a test can keep one handle and pass
+/// another to the component under test.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl MockClock {
+    /// A clock stopped at `now`.
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Jump to `now` (may go backwards, to test regressions).
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.lock() = now;
+    }
+
+    /// Move forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.lock();
+        *now += by;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, DateTime<Utc>> {
+        self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.lock()
+    }
+}
+
+/// How far a checkpoint timestamp may differ from the gateway receive time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSkewPolicy {
+    /// How far the robot's clock may run ahead of the gateway's
+    pub max_ahead: Duration,
+    /// How long before receipt a checkpoint may have been cut
+    /// (covers upload latency and store-and-forward over poor links)
+    pub max_behind: Duration,
+}
+
+impl Default for ClockSkewPolicy {
+    fn default() -> Self {
+        Self {
+            max_ahead: Duration::seconds(30),
+            max_behind: Duration::minutes(10),
+        }
+    }
+}
+
+impl ClockSkewPolicy {
+    /// Whether a timestamp `skew` ahead of the receive time (negative: behind) is acceptable.
+    pub fn allows(&self, skew: Duration) -> bool {
+        skew <= self.max_ahead && -skew <= self.max_behind
+    }
+
+    /// Judge `checkpoint` against `received_at` and sign the outcome as `gateway`.
+    ///
+    /// Rejections are signed too, so a gateway can prove why it refused a checkpoint.
+    pub fn decide(
+        &self,
+        checkpoint: &Checkpoint,
+        received_at: DateTime<Utc>,
+        gateway: &Signer,
+    ) -> Result<SkewDecision, ClockError> {
+        let skew = checkpoint.local_timestamp_utc - received_at;
+        let mut decision = SkewDecision {
+            checkpoint_hash: checkpoint.compute_hash()?,
+            claimed: checkpoint.local_timestamp_utc,
+            received_at,
+            skew_ms: skew.num_milliseconds(),
+            max_ahead_ms: self.max_ahead.num_milliseconds(),
+            max_behind_ms: self.max_behind.num_milliseconds(),
+            accepted: self.allows(skew),
+            gateway_key_id: gateway.key_id(),
+            signature: SignatureBytes([0u8; 64]),
+        };
+        let signature = gateway.sign(&decision.signing_payload()?);
+        decision.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(decision)
+    }
+}
+
+/// A gateway's signed judgement of a checkpoint timestamp.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkewDecision {
+    /// Hash of the judged checkpoint
+    pub checkpoint_hash: Hash256,
+    /// Timestamp claimed by the robot
+    #[serde(with = "crate::serialization::timestamp")]
+    pub claimed: DateTime<Utc>,
+    /// Gateway receive time
+    #[serde(with = "crate::serialization::timestamp")]
+    pub received_at: DateTime<Utc>,
+    /// `claimed - received_at` in milliseconds (positive: robot clock ahead)
+    pub skew_ms: i64,
+    /// Policy bounds applied
+    pub max_ahead_ms: i64,
+    pub max_behind_ms: i64,
+    pub accepted: bool,
+    /// Fingerprint of the gateway's signing key
+    pub gateway_key_id: KeyId,
+    /// Ed25519 signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned decision (for signature computation)
+#[derive(Serialize)]
+struct UnsignedSkewDecision {
+    checkpoint_hash: Hash256,
+    #[serde(with = "crate::serialization::timestamp")]
+    claimed: DateTime<Utc>,
+    #[serde(with = "crate::serialization::timestamp")]
+    received_at: DateTime<Utc>,
+    skew_ms: i64,
+    max_ahead_ms: i64,
+    max_behind_ms: i64,
+    accepted: bool,
+    gateway_key_id: KeyId,
+}
+
+impl SkewDecision {
+    /// Verify the decision covers `checkpoint`, is signed by a known gateway
+    /// and accepted the timestamp.
+    pub fn verify(
+        &self,
+        checkpoint: &Checkpoint,
+        gateways: &dyn KeyResolver,
+    ) -> Result<(), ClockError> {
+        use ed25519_dalek::Verifier;
+
+        if !ct_eq(&checkpoint.compute_hash()?, &self.checkpoint_hash) {
+            return Err(ClockError::CheckpointMismatch);
+        }
+        let verifying_key = gateways
+            .resolve(&self.gateway_key_id)
+            .ok_or(ClockError::UnknownGateway(self.gateway_key_id))?;
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        verifying_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| ClockError::InvalidSignature)?;
+
+        if !self.accepted {
+            return Err(ClockError::SkewExceeded {
+                skew_ms: self.skew_ms,
+                max_ahead_ms: self.max_ahead_ms,
+                max_behind_ms: self.max_behind_ms,
+            });
+        }
+        Ok(())
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedSkewDecision {
+            checkpoint_hash: self.checkpoint_hash,
+            claimed: self.claimed,
+            received_at: self.received_at,
+            skew_ms: self.skew_ms,
+            max_ahead_ms: self.max_ahead_ms,
+            max_behind_ms: self.max_behind_ms,
+            accepted: self.accepted,
+            gateway_key_id: self.gateway_key_id,
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ClockError {
+    #[error("Skew decision serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Checkpoint timestamp is {skew_ms} ms from gateway time (allowed: +{max_ahead_ms}/-{max_behind_ms} ms)")]
+    SkewExceeded {
+        skew_ms: i64,
+        max_ahead_ms: i64,
+        max_behind_ms: i64,
+    },
+
+    #[error("Skew decision is for a different checkpoint")]
+    CheckpointMismatch,
+
+    #[error("Skew decision signed by unknown gateway key {0}")]
+    UnknownGateway(KeyId),
+
+    #[error("Invalid skew decision signature")]
+    InvalidSignature,
+}
+
+impl ErrorCoded for ClockError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ClockError::Serialization(e) => e.code(),
+            ClockError::SkewExceeded { .. } => ErrorCode::ClockSkewExceeded,
+            ClockError::CheckpointMismatch
+            | ClockError::UnknownGateway(_)
+            | ClockError::InvalidSignature => ErrorCode::SkewDecisionInvalid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+
+    fn checkpoint_at(timestamp: DateTime<Utc>) -> Checkpoint {
+        CheckpointBuilder::test_fixture()
+            .timestamp(timestamp)
+            .build_and_sign(Signer::generate().signing_key())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_policy_bounds() {
+        let policy = ClockSkewPolicy::default();
+        assert!(policy.allows(Duration::zero()));
+        assert!(policy.allows(Duration::seconds(30)));
+        assert!(!policy.allows(Duration::seconds(31)));
+        assert!(policy.allows(Duration::minutes(-10)));
+        assert!(!policy.allows(Duration::minutes(-11)));
+    }
+
+    #[test]
+    fn test_decision_signed_and_verified() {
+        let gateway = Signer::generate();
+        let now = Utc::now();
+        let checkpoint = checkpoint_at(now - Duration::seconds(5));
+
+        let decision = ClockSkewPolicy::default()
+            .decide(&checkpoint, now, &gateway)
+            .unwrap();
+        assert!(decision.accepted);
+        assert_eq!(decision.skew_ms, -5000);
+        decision
+            .verify(&checkpoint, &gateway.verifying_key())
+            .unwrap();
+
+        let mut forged = decision.clone();
+        forged.received_at = now - Duration::hours(1);
+        let err = forged
+            .verify(&checkpoint, &gateway.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SkewDecisionInvalid);
+    }
+
+    #[test]
+    fn test_future_timestamp_rejected() {
+        let gateway = Signer::generate();
+        let now = Utc::now();
+        let checkpoint = checkpoint_at(now + Duration::hours(2));
+
+        let decision = ClockSkewPolicy::default()
+            .decide(&checkpoint, now, &gateway)
+            .unwrap();
+        assert!(!decision.accepted);
+        let err = decision
+            .verify(&checkpoint, &gateway.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ClockSkewExceeded);
+    }
+}
@@ -0,0 +1,250 @@
+//! Vendor-agnostic attestation collateral bundles for offline verification.
+//!
+//! A `CollateralBundle` packages everything an adapter needs to verify quotes
+//! without network access (certificate chains, CRLs, TCB/QE identity documents).
+//! Bundles are exported on a connected machine, signed by the exporter, and
+//! imported on air-gapped verifiers.
+
+use crate::crypto::Signer;
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{KeyId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Bundle version (for schema evolution)
+pub const COLLATERAL_VERSION: u8 = 1;
+
+/// Well-known document keys.
+pub mod documents {
+    /// Intel TCB info (JSON, as served by PCS)
+    pub const TCB_INFO: &str = "tcb_info";
+    /// Intel QE identity (JSON, as served by PCS)
+    pub const QE_IDENTITY: &str = "qe_identity";
+    /// PEM chain that signed the Intel QE identity, leaf first
+    pub const QE_IDENTITY_ISSUER_CHAIN: &str = "qe_identity_issuer_chain";
+    /// AMD VCEK certificate (SEV-SNP)
+    pub const VCEK: &str = "vcek";
+
+    /// Key of the Intel TCB info of one platform (FMSPC, hex).
+    pub fn tcb_info_for(fmspc: &str) -> String {
+        format!("{TCB_INFO}/{}", fmspc.to_ascii_lowercase())
+    }
+}
+
+/// Collateral needed to verify quotes for one vendor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollateralBundle {
+    /// Schema version
+    pub version: u8,
+    /// Vendor the collateral applies to (matches `AttestationAdapter::vendor_name`)
+    pub vendor: String,
+    /// PEM-encoded certificates (root first, then intermediates)
+    pub certificates: Vec<String>,
+    /// DER-encoded certificate revocation lists
+    pub crls: Vec<Vec<u8>>,
+    /// Vendor documents keyed by name (see [`documents`])
+    pub documents: BTreeMap<String, Vec<u8>>,
+    /// When the collateral was fetched
+    #[serde(with = "crate::serialization::timestamp")]
+    pub fetched_at: DateTime<Utc>,
+    /// Earliest expiry among the included collateral
+    #[serde(with = "crate::serialization::timestamp")]
+    pub valid_until: DateTime<Utc>,
+}
+
+impl CollateralBundle {
+    /// Create an empty bundle for `vendor`.
+    pub fn new(
+        vendor: impl Into<String>,
+        fetched_at: DateTime<Utc>,
+        valid_until: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            version: COLLATERAL_VERSION,
+            vendor: vendor.into(),
+            certificates: Vec::new(),
+            crls: Vec::new(),
+            documents: BTreeMap::new(),
+            fetched_at,
+            valid_until,
+        }
+    }
+
+    /// Look up a vendor document by key.
+    pub fn document(&self, key: &str) -> Option<&[u8]> {
+        self.documents.get(key).map(|d| d.as_slice())
+    }
+
+    /// Check that the bundle is usable at `now`.
+    pub fn check_validity(&self, now: DateTime<Utc>) -> Result<(), CollateralError> {
+        if now < self.fetched_at {
+            return Err(CollateralError::NotYetValid(self.fetched_at));
+        }
+        if now > self.valid_until {
+            return Err(CollateralError::Expired(self.valid_until));
+        }
+        Ok(())
+    }
+
+    /// Sign the bundle for distribution.
+    pub fn sign(self, signer: &Signer) -> Result<SignedCollateralBundle, CollateralError> {
+        let payload = to_canonical_cbor(&self)?;
+        let signature = signer.sign(&payload);
+
+        Ok(SignedCollateralBundle {
+            bundle: self,
+            signer_key_id: signer.key_id(),
+            signature: SignatureBytes::from(signature.to_bytes()),
+        })
+    }
+}
+
+/// A collateral bundle signed by the party that exported it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedCollateralBundle {
+    pub bundle: CollateralBundle,
+    /// Fingerprint of the exporter's key
+    pub signer_key_id: KeyId,
+    /// Ed25519 signature over canonical CBOR of `bundle`
+    pub signature: SignatureBytes,
+}
+
+impl SignedCollateralBundle {
+    /// Serialize to canonical CBOR for export.
+    pub fn export(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Parse an exported bundle and verify its signature against trusted exporters.
+    pub fn import(
+        bytes: &[u8],
+        exporters: &dyn KeyResolver,
+    ) -> Result<CollateralBundle, CollateralError> {
+        let signed: SignedCollateralBundle = from_canonical_cbor(bytes)?;
+        signed.verify(exporters)?;
+        Ok(signed.bundle)
+    }
+
+    /// Verify the exporter signature.
+    pub fn verify(&self, exporters: &dyn KeyResolver) -> Result<(), CollateralError> {
+        use ed25519_dalek::Verifier;
+
+        let verifying_key = exporters
+            .resolve(&self.signer_key_id)
+            .ok_or(CollateralError::UnknownExporter(self.signer_key_id))?;
+
+        let payload = to_canonical_cbor(&self.bundle)?;
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        verifying_key
+            .verify(&payload, &signature)
+            .map_err(|_| CollateralError::InvalidSignature)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CollateralError {
+    #[error("Collateral serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Collateral bundle signed by unknown exporter {0}")]
+    UnknownExporter(KeyId),
+
+    #[error("Invalid collateral bundle signature")]
+    InvalidSignature,
+
+    #[error("Collateral expired at {0}")]
+    Expired(DateTime<Utc>),
+
+    #[error("Collateral not valid before {0}")]
+    NotYetValid(DateTime<Utc>),
+
+    #[error("Collateral is for vendor {actual}, expected {expected}")]
+    VendorMismatch { expected: String, actual: String },
+
+    #[error("Adapter {0} does not support offline collateral")]
+    Unsupported(String),
+}
+
+impl ErrorCoded for CollateralError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            CollateralError::Serialization(e) => e.code(),
+            CollateralError::UnknownExporter(_)
+            | CollateralError::InvalidSignature
+            | CollateralError::Expired(_)
+            | CollateralError::NotYetValid(_)
+            | CollateralError::VendorMismatch { .. } => ErrorCode::CollateralInvalid,
+            CollateralError::Unsupported(_) => ErrorCode::CollateralUnavailable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn bundle() -> CollateralBundle {
+        let now = Utc::now();
+        let mut bundle = CollateralBundle::new(
+            "intel-sgx",
+            now - Duration::hours(1),
+            now + Duration::days(30),
+        );
+        bundle
+            .certificates
+            .push("-----BEGIN CERTIFICATE-----\n...".to_string());
+        bundle.crls.push(vec![0x30, 0x82]);
+        bundle
+            .documents
+            .insert(documents::TCB_INFO.to_string(), b"{}".to_vec());
+        bundle
+    }
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let exporter = Signer::generate();
+        let original = bundle();
+        let exported = original.clone().sign(&exporter).unwrap().export().unwrap();
+
+        let imported =
+            SignedCollateralBundle::import(&exported, &exporter.verifying_key()).unwrap();
+        assert_eq!(imported, original);
+        assert_eq!(imported.document(documents::TCB_INFO), Some(&b"{}"[..]));
+    }
+
+    #[test]
+    fn test_import_rejects_tampering() {
+        let exporter = Signer::generate();
+        let mut signed = bundle().sign(&exporter).unwrap();
+        signed.bundle.crls.clear();
+
+        let exported = signed.export().unwrap();
+        assert!(matches!(
+            SignedCollateralBundle::import(&exported, &exporter.verifying_key()),
+            Err(CollateralError::InvalidSignature)
+        ));
+        assert!(matches!(
+            SignedCollateralBundle::import(&exported, &Signer::generate().verifying_key()),
+            Err(CollateralError::UnknownExporter(_))
+        ));
+    }
+
+    #[test]
+    fn test_validity_window() {
+        let bundle = bundle();
+        assert!(bundle.check_validity(Utc::now()).is_ok());
+        assert!(matches!(
+            bundle.check_validity(Utc::now() + Duration::days(31)),
+            Err(CollateralError::Expired(_))
+        ));
+        assert!(matches!(
+            bundle.check_validity(Utc::now() - Duration::days(1)),
+            Err(CollateralError::NotYetValid(_))
+        ));
+    }
+}
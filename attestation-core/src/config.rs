@@ -0,0 +1,392 @@
+//! Signed agent configuration.
+//!
+//! Operators distribute robot agent settings (checkpoint cadence, redaction
+//! policy, trust requirements, gateway endpoints) as a [`SignedConfig`].
+//! Every release carries a higher `revision`; a robot's [`ConfigTracker`]
+//! only moves forward, so a replayed older config (say, one without a
+//! redaction policy) is refused. The robot commits the hash of the config in
+//! force to each checkpoint (`Checkpoint::agent_config`), and verifiers look
+//! it up in a [`ConfigRegistry`] to know which policy applied.
+
+use crate::chain::TrustRequirements;
+use crate::checkpoint::Checkpoint;
+use crate::crypto::{ct_eq, sha256, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, KeyId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Config document version (for schema evolution)
+pub const CONFIG_VERSION: u8 = 1;
+
+/// Settings a robot agent runs with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentConfig {
+    /// Release number; strictly increases with every new config
+    pub revision: u64,
+    /// Seconds between checkpoints
+    pub checkpoint_interval_secs: u64,
+    /// Hash of the redaction policy applied to entries before hashing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redaction_policy: Option<Hash256>,
+    /// Minimum trust mode per robot class
+    #[serde(default)]
+    pub trust: TrustRequirements,
+    /// Gateway base URLs, in order of preference
+    pub gateway_endpoints: Vec<String>,
+}
+
+/// An agent configuration signed by a fleet operator key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedConfig {
+    /// Schema version
+    pub version: u8,
+    pub config: AgentConfig,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub issued_at: DateTime<Utc>,
+    /// Fingerprint of the signing key
+    pub signer_key_id: KeyId,
+    /// Ed25519 signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned config (for signature computation)
+#[derive(Serialize)]
+struct UnsignedConfig<'a> {
+    version: u8,
+    config: &'a AgentConfig,
+    #[serde(with = "crate::serialization::timestamp")]
+    issued_at: DateTime<Utc>,
+    signer_key_id: KeyId,
+}
+
+impl SignedConfig {
+    /// Sign `config` for distribution.
+    pub fn issue(config: AgentConfig, signer: &Signer) -> Result<Self, ConfigError> {
+        let mut signed = Self {
+            version: CONFIG_VERSION,
+            config,
+            issued_at: Utc::now(),
+            signer_key_id: signer.key_id(),
+            signature: SignatureBytes([0u8; 64]),
+        };
+        let signature = signer.sign(&signed.signing_payload()?);
+        signed.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(signed)
+    }
+
+    /// Verify the signature using a key looked up by `signer_key_id`.
+    pub fn verify(&self, keys: &dyn KeyResolver) -> Result<(), ConfigError> {
+        use ed25519_dalek::Verifier;
+
+        let verifying_key = keys
+            .resolve(&self.signer_key_id)
+            .ok_or(ConfigError::UnknownSigner(self.signer_key_id))?;
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        verifying_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| ConfigError::InvalidSignature)
+    }
+
+    /// Hash of the signed document, as committed in checkpoints.
+    pub fn compute_hash(&self) -> Result<Hash256, SerializationError> {
+        Ok(sha256(&to_canonical_cbor(self)?))
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedConfig {
+            version: self.version,
+            config: &self.config,
+            issued_at: self.issued_at,
+            signer_key_id: self.signer_key_id,
+        })
+    }
+}
+
+/// The config in force on a robot agent.
+pub struct ConfigTracker {
+    keys: Box<dyn KeyResolver>,
+    active: Option<(SignedConfig, Hash256)>,
+}
+
+impl ConfigTracker {
+    /// Accept configs signed by keys in `keys`; nothing is in force yet.
+    pub fn new(keys: Box<dyn KeyResolver>) -> Self {
+        Self { keys, active: None }
+    }
+
+    /// Verify and put `signed` in force if it is newer than the active config.
+    ///
+    /// Returns whether the config changed (re-applying the active config is
+    /// a no-op).
+    pub fn apply(&mut self, signed: SignedConfig) -> Result<bool, ConfigError> {
+        signed.verify(self.keys.as_ref())?;
+        let hash = signed.compute_hash()?;
+        if let Some((active, active_hash)) = &self.active {
+            let (current, offered) = (active.config.revision, signed.config.revision);
+            if offered < current {
+                return Err(ConfigError::Rollback {
+                    active: current,
+                    offered,
+                });
+            }
+            if offered == current {
+                if !ct_eq(&hash, active_hash) {
+                    return Err(ConfigError::Conflict { revision: offered });
+                }
+                return Ok(false);
+            }
+        }
+        self.active = Some((signed, hash));
+        Ok(true)
+    }
+
+    /// The config in force.
+    pub fn active(&self) -> Option<&SignedConfig> {
+        self.active.as_ref().map(|(config, _)| config)
+    }
+
+    /// Hash of the config in force, for `CheckpointBuilder::agent_config`.
+    pub fn active_hash(&self) -> Option<Hash256> {
+        self.active.as_ref().map(|(_, hash)| *hash)
+    }
+}
+
+/// Verified configs on record, for checking which one a checkpoint ran under.
+pub struct ConfigRegistry {
+    keys: Box<dyn KeyResolver>,
+    configs: HashMap<Hash256, SignedConfig>,
+}
+
+impl ConfigRegistry {
+    /// Accept configs signed by keys in `keys`.
+    pub fn new(keys: Box<dyn KeyResolver>) -> Self {
+        Self {
+            keys,
+            configs: HashMap::new(),
+        }
+    }
+
+    /// Verify and record a config, returning its hash.
+    pub fn record(&mut self, signed: SignedConfig) -> Result<Hash256, ConfigError> {
+        signed.verify(self.keys.as_ref())?;
+        let hash = signed.compute_hash()?;
+        let revision = signed.config.revision;
+        if self
+            .configs
+            .iter()
+            .any(|(other_hash, other)| other.config.revision == revision && *other_hash != hash)
+        {
+            return Err(ConfigError::Conflict { revision });
+        }
+        self.configs.insert(hash, signed);
+        Ok(hash)
+    }
+
+    /// Look up a config by hash.
+    pub fn get(&self, hash: &Hash256) -> Option<&SignedConfig> {
+        self.configs.get(hash)
+    }
+
+    /// The config `checkpoint` declares in force (`None` if it declares none).
+    pub fn in_force(&self, checkpoint: &Checkpoint) -> Result<Option<&SignedConfig>, ConfigError> {
+        checkpoint
+            .agent_config
+            .map(|hash| self.get(&hash).ok_or(ConfigError::UnknownConfig(hash)))
+            .transpose()
+    }
+
+    /// Check that the config declared by `next` does not roll back the one
+    /// declared by `prev`, its predecessor in the robot's chain.
+    pub fn check_transition(
+        &self,
+        prev: &Checkpoint,
+        next: &Checkpoint,
+    ) -> Result<(), ConfigError> {
+        let Some(previous) = self.in_force(prev)? else {
+            return Ok(());
+        };
+        let active = previous.config.revision;
+        match self.in_force(next)? {
+            None => Err(ConfigError::Dropped { active }),
+            Some(current) if current.config.revision < active => Err(ConfigError::Rollback {
+                active,
+                offered: current.config.revision,
+            }),
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Config signer {0} is not a trusted operator key")]
+    UnknownSigner(KeyId),
+
+    #[error("Config signature is invalid")]
+    InvalidSignature,
+
+    #[error("Config revision {offered} is older than revision {active} in force")]
+    Rollback { active: u64, offered: u64 },
+
+    #[error("Checkpoint declares no config after revision {active} was in force")]
+    Dropped { active: u64 },
+
+    #[error("Two different configs carry revision {revision}")]
+    Conflict { revision: u64 },
+
+    #[error("Config {} is not on record", hex::encode(.0))]
+    UnknownConfig(Hash256),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] SerializationError),
+}
+
+impl ErrorCoded for ConfigError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ConfigError::UnknownSigner(_) | ConfigError::InvalidSignature => {
+                ErrorCode::ConfigSignatureInvalid
+            }
+            ConfigError::Rollback { .. } | ConfigError::Dropped { .. } => ErrorCode::ConfigRollback,
+            ConfigError::Conflict { .. } => ErrorCode::ConfigConflict,
+            ConfigError::UnknownConfig(_) => ErrorCode::ConfigUnknown,
+            ConfigError::Serialization(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::keys::KeyRing;
+    use crate::types::{ModelProvenance, TrustMode};
+
+    fn config(revision: u64, interval: u64) -> AgentConfig {
+        AgentConfig {
+            revision,
+            checkpoint_interval_secs: interval,
+            redaction_policy: Some([9u8; 32]),
+            trust: TrustRequirements {
+                default: Some(TrustMode::Trusted),
+                ..Default::default()
+            },
+            gateway_endpoints: vec!["https://gw-1.example".to_string()],
+        }
+    }
+
+    fn keys(operator: &Signer) -> Box<dyn KeyResolver> {
+        let mut ring = KeyRing::new();
+        ring.insert(operator.verifying_key());
+        Box::new(ring)
+    }
+
+    #[test]
+    fn test_tracker_moves_forward_only() {
+        let operator = Signer::generate();
+        let mut tracker = ConfigTracker::new(keys(&operator));
+        let first = SignedConfig::issue(config(1, 60), &operator).unwrap();
+        let second = SignedConfig::issue(config(2, 30), &operator).unwrap();
+
+        assert!(tracker.apply(first.clone()).unwrap());
+        assert!(tracker.apply(second.clone()).unwrap());
+        assert!(!tracker.apply(second.clone()).unwrap());
+        assert_eq!(tracker.active_hash(), Some(second.compute_hash().unwrap()));
+
+        let err = tracker.apply(first).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ConfigRollback);
+        let err = tracker
+            .apply(SignedConfig::issue(config(2, 5), &operator).unwrap())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ConfigConflict);
+
+        let stranger = SignedConfig::issue(config(3, 1), &Signer::generate()).unwrap();
+        assert_eq!(
+            tracker.apply(stranger).unwrap_err().code(),
+            ErrorCode::ConfigSignatureInvalid
+        );
+        assert_eq!(
+            tracker.active().unwrap().config.checkpoint_interval_secs,
+            30
+        );
+    }
+
+    #[test]
+    fn test_registry_resolves_config_in_force() {
+        let operator = Signer::generate();
+        let mut registry = ConfigRegistry::new(keys(&operator));
+        let first = registry
+            .record(SignedConfig::issue(config(1, 60), &operator).unwrap())
+            .unwrap();
+        let second = registry
+            .record(SignedConfig::issue(config(2, 30), &operator).unwrap())
+            .unwrap();
+
+        let robot = Signer::generate();
+        let genesis = CheckpointBuilder::test_fixture()
+            .model_provenance(ModelProvenance {
+                name: "planner".to_string(),
+                model_hash: [1u8; 32],
+                dataset_hash: None,
+                container_digest: None,
+                signature_bundle: None,
+            })
+            .firmware_hash([2u8; 32])
+            .enclave_measurement(vec![3u8; 32])
+            .entries_root([4u8; 32])
+            .agent_config(second)
+            .build_and_sign(robot.signing_key())
+            .unwrap();
+        let in_force = registry.in_force(&genesis).unwrap().unwrap();
+        assert_eq!(in_force.config.revision, 2);
+
+        // The next checkpoint inherits the config unless told otherwise
+        let next = CheckpointBuilder::continuing_from(&genesis)
+            .unwrap()
+            .monotonic_counter(2)
+            .entries_root([5u8; 32])
+            .build_and_sign(robot.signing_key())
+            .unwrap();
+        assert_eq!(next.agent_config, Some(second));
+        assert!(registry.check_transition(&genesis, &next).is_ok());
+
+        let rolled_back = CheckpointBuilder::continuing_from(&genesis)
+            .unwrap()
+            .monotonic_counter(2)
+            .entries_root([5u8; 32])
+            .agent_config(first)
+            .build_and_sign(robot.signing_key())
+            .unwrap();
+        let err = registry
+            .check_transition(&genesis, &rolled_back)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ConfigRollback);
+
+        let unknown = CheckpointBuilder::continuing_from(&genesis)
+            .unwrap()
+            .monotonic_counter(2)
+            .entries_root([5u8; 32])
+            .agent_config([7u8; 32])
+            .build_and_sign(robot.signing_key())
+            .unwrap();
+        assert_eq!(
+            registry.in_force(&unknown).unwrap_err().code(),
+            ErrorCode::ConfigUnknown
+        );
+    }
+}
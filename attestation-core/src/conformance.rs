@@ -0,0 +1,465 @@
+//! Canonical encoding conformance checks for externally produced CBOR.
+//!
+//! A checkpoint or entry encoded by another implementation can decode fine
+//! and still hash differently from ours (a non-shortest length, a float, a
+//! reordered field), which only surfaces later as a broken chain. [`check`]
+//! reports every such violation with the byte offset it occurs at, so a
+//! vendor integration can be certified against the canonical form up front.
+//!
+//! Three passes run in order:
+//! 1. a structural walk of the raw CBOR (shortest-form heads, no
+//!    indefinite lengths, floats or tags, no duplicate map keys, no
+//!    truncation or trailing bytes), which keeps going after recoverable
+//!    violations so one run reports all of them;
+//! 2. decoding against the schema, reporting the decoder's offset;
+//! 3. re-encoding the decoded value and comparing it with the input, which
+//!    catches anything else that is not byte-identical (e.g., field order).
+
+use crate::checkpoint::Checkpoint;
+use crate::merkle::Entry;
+use crate::serialization::to_canonical_cbor;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Nesting deeper than this is reported rather than walked.
+pub const MAX_NESTING_DEPTH: usize = 32;
+
+/// Record type the input is checked against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConformanceKind {
+    Checkpoint,
+    Entry,
+}
+
+/// Canonicality or schema rule a [`Violation`] breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConformanceRule {
+    /// Input ends inside an item
+    Truncated,
+    /// Bytes follow the top-level item
+    TrailingBytes,
+    /// Reserved additional information or a stray break code
+    Malformed,
+    /// Integer, length or simple value not in its shortest form
+    NonMinimalEncoding,
+    /// Indefinite-length string, array or map
+    IndefiniteLength,
+    /// Floating-point value
+    FloatingPoint,
+    /// Tagged item (the schema uses none)
+    Tag,
+    /// Same key twice in one map
+    DuplicateKey,
+    /// Nested deeper than [`MAX_NESTING_DEPTH`]
+    NestingTooDeep,
+    /// Does not decode as the record type
+    Schema,
+    /// Decodes, but re-encodes to different bytes
+    NotCanonical,
+}
+
+/// One violation, at the offset of the offending byte.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Violation {
+    pub offset: usize,
+    pub rule: ConformanceRule,
+    pub message: String,
+}
+
+/// Result of checking one input.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConformanceReport {
+    pub kind: ConformanceKind,
+    /// Input length in bytes
+    pub length: usize,
+    /// Violations in the order found
+    pub violations: Vec<Violation>,
+}
+
+impl ConformanceReport {
+    /// Whether the input is exactly the canonical encoding of a valid record.
+    pub fn is_conformant(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Check `bytes` against the canonical encoding of `kind`.
+pub fn check(kind: ConformanceKind, bytes: &[u8]) -> ConformanceReport {
+    let mut walker = Walker {
+        bytes,
+        pos: 0,
+        violations: Vec::new(),
+    };
+    let structural = walker.item(0).is_ok();
+    if structural && walker.pos < bytes.len() {
+        walker.violate(
+            walker.pos,
+            ConformanceRule::TrailingBytes,
+            format!(
+                "{} bytes after the top-level item",
+                bytes.len() - walker.pos
+            ),
+        );
+    }
+    let mut violations = walker.violations;
+
+    // The schema passes only mean something on structurally sound input, and
+    // a re-encoding diff would only repeat the first structural violation
+    if structural {
+        let clean = violations.is_empty();
+        let result = match kind {
+            ConformanceKind::Checkpoint => reencode::<Checkpoint>(bytes),
+            ConformanceKind::Entry => reencode::<Entry>(bytes),
+        };
+        match result {
+            Err(violation) => violations.push(violation),
+            Ok(canonical) if clean && canonical != bytes => {
+                let offset = canonical
+                    .iter()
+                    .zip(bytes)
+                    .position(|(a, b)| a != b)
+                    .unwrap_or(canonical.len().min(bytes.len()));
+                violations.push(Violation {
+                    offset,
+                    rule: ConformanceRule::NotCanonical,
+                    message: format!(
+                        "canonical re-encoding ({} bytes) first differs here",
+                        canonical.len()
+                    ),
+                });
+            }
+            Ok(_) => {}
+        }
+    }
+
+    ConformanceReport {
+        kind,
+        length: bytes.len(),
+        violations,
+    }
+}
+
+fn reencode<T: Serialize + DeserializeOwned>(bytes: &[u8]) -> Result<Vec<u8>, Violation> {
+    let value: T = ciborium::from_reader(bytes).map_err(|e| {
+        let (offset, message) = match e {
+            ciborium::de::Error::Syntax(offset) => (offset, "syntax error".to_string()),
+            ciborium::de::Error::Semantic(offset, message) => (offset.unwrap_or(0), message),
+            ciborium::de::Error::Io(e) => (0, e.to_string()),
+            ciborium::de::Error::RecursionLimitExceeded => {
+                (0, "recursion limit exceeded".to_string())
+            }
+        };
+        Violation {
+            offset,
+            rule: ConformanceRule::Schema,
+            message,
+        }
+    })?;
+    to_canonical_cbor(&value).map_err(|e| Violation {
+        offset: 0,
+        rule: ConformanceRule::Schema,
+        message: e.to_string(),
+    })
+}
+
+/// Marks a violation the walk cannot continue past.
+struct Fatal;
+
+struct Walker<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    violations: Vec<Violation>,
+}
+
+/// Item head: major type, additional information, argument.
+struct Head {
+    offset: usize,
+    major: u8,
+    info: u8,
+    arg: u64,
+}
+
+impl Walker<'_> {
+    fn violate(&mut self, offset: usize, rule: ConformanceRule, message: impl Into<String>) {
+        self.violations.push(Violation {
+            offset,
+            rule,
+            message: message.into(),
+        });
+    }
+
+    fn take(&mut self, len: u64) -> Result<&[u8], Fatal> {
+        let start = self.pos;
+        let remaining = (self.bytes.len() - start) as u64;
+        if len > remaining {
+            self.violate(
+                start,
+                ConformanceRule::Truncated,
+                format!("{len} bytes needed, {remaining} left"),
+            );
+            return Err(Fatal);
+        }
+        self.pos += len as usize;
+        Ok(&self.bytes[start..self.pos])
+    }
+
+    fn head(&mut self) -> Result<Head, Fatal> {
+        let offset = self.pos;
+        let initial = self.take(1)?[0];
+        let major = initial >> 5;
+        let info = initial & 0x1f;
+        let (arg, minimum) = match info {
+            0..=23 => (u64::from(info), 0),
+            24 => (u64::from(self.take(1)?[0]), 24),
+            25 => (
+                u64::from(u16::from_be_bytes(self.take(2)?.try_into().unwrap())),
+                0x100,
+            ),
+            26 => (
+                u64::from(u32::from_be_bytes(self.take(4)?.try_into().unwrap())),
+                0x1_0000,
+            ),
+            27 => (
+                u64::from_be_bytes(self.take(8)?.try_into().unwrap()),
+                0x1_0000_0000,
+            ),
+            31 => (0, 0),
+            _ => {
+                self.violate(
+                    offset,
+                    ConformanceRule::Malformed,
+                    format!("reserved additional information {info}"),
+                );
+                return Err(Fatal);
+            }
+        };
+        if major == 7 && (25..=27).contains(&info) {
+            self.violate(
+                offset,
+                ConformanceRule::FloatingPoint,
+                "floating-point value",
+            );
+        } else if major == 7 && info == 24 && arg < 32 {
+            self.violate(
+                offset,
+                ConformanceRule::NonMinimalEncoding,
+                format!("simple value {arg} in two bytes"),
+            );
+        } else if arg < minimum {
+            self.violate(
+                offset,
+                ConformanceRule::NonMinimalEncoding,
+                format!("argument {arg} encoded in {} bytes", self.pos - offset - 1),
+            );
+        }
+        Ok(Head {
+            offset,
+            major,
+            info,
+            arg,
+        })
+    }
+
+    /// Whether the next byte is a break code (consuming it if so).
+    fn at_break(&mut self) -> Result<bool, Fatal> {
+        match self.bytes.get(self.pos) {
+            Some(0xff) => {
+                self.pos += 1;
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => {
+                self.violate(self.pos, ConformanceRule::Truncated, "missing break code");
+                Err(Fatal)
+            }
+        }
+    }
+
+    fn item(&mut self, depth: usize) -> Result<(), Fatal> {
+        let head = self.head()?;
+        if depth > MAX_NESTING_DEPTH {
+            self.violate(
+                head.offset,
+                ConformanceRule::NestingTooDeep,
+                format!("nested deeper than {MAX_NESTING_DEPTH}"),
+            );
+            return Err(Fatal);
+        }
+        let indefinite = head.info == 31;
+        if indefinite {
+            if matches!(head.major, 0 | 1 | 6 | 7) {
+                self.violate(
+                    head.offset,
+                    ConformanceRule::Malformed,
+                    "unexpected break code",
+                );
+                return Err(Fatal);
+            }
+            self.violate(
+                head.offset,
+                ConformanceRule::IndefiniteLength,
+                "indefinite-length item",
+            );
+        }
+
+        match head.major {
+            2 | 3 if indefinite => {
+                while !self.at_break()? {
+                    let chunk = self.head()?;
+                    if chunk.major != head.major || chunk.info == 31 {
+                        self.violate(
+                            chunk.offset,
+                            ConformanceRule::Malformed,
+                            "invalid string chunk",
+                        );
+                        return Err(Fatal);
+                    }
+                    self.take(chunk.arg)?;
+                }
+            }
+            2 => {
+                self.take(head.arg)?;
+            }
+            3 => {
+                let text = self.take(head.arg)?;
+                if std::str::from_utf8(text).is_err() {
+                    self.violate(
+                        head.offset,
+                        ConformanceRule::Malformed,
+                        "text string is not UTF-8",
+                    );
+                }
+            }
+            4 if indefinite => {
+                while !self.at_break()? {
+                    self.item(depth + 1)?;
+                }
+            }
+            4 => {
+                for _ in 0..head.arg {
+                    self.item(depth + 1)?;
+                }
+            }
+            5 => {
+                let mut keys = HashSet::new();
+                let mut remaining = head.arg;
+                while if indefinite {
+                    !self.at_break()?
+                } else {
+                    remaining > 0
+                } {
+                    remaining = remaining.saturating_sub(1);
+                    let start = self.pos;
+                    self.item(depth + 1)?;
+                    let key = &self.bytes[start..self.pos];
+                    if !keys.insert(key) {
+                        self.violate(start, ConformanceRule::DuplicateKey, "duplicate map key");
+                    }
+                    self.item(depth + 1)?;
+                }
+            }
+            6 => {
+                self.violate(
+                    head.offset,
+                    ConformanceRule::Tag,
+                    format!("tag {}", head.arg),
+                );
+                self.item(depth + 1)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::crypto::Signer;
+    use crate::types::Hash256;
+
+    fn checkpoint() -> Checkpoint {
+        CheckpointBuilder::test_fixture()
+            .build_and_sign(Signer::generate().signing_key())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_canonical_records_conform() {
+        let report = check(
+            ConformanceKind::Checkpoint,
+            &checkpoint().to_bytes().unwrap(),
+        );
+        assert!(report.is_conformant(), "{:?}", report.violations);
+        let entry = to_canonical_cbor(&Entry::new(1_000, 0, b"event")).unwrap();
+        assert!(check(ConformanceKind::Entry, &entry).is_conformant());
+    }
+
+    #[test]
+    fn test_violations_reported_with_offsets() {
+        let entry = to_canonical_cbor(&Entry::new(1_000, 0, b"event")).unwrap();
+
+        // Map of 3 written as an indefinite-length map, and trailing garbage
+        let mut bytes = entry.clone();
+        bytes[0] = 0xbf;
+        bytes.push(0xff);
+        bytes.push(0x00);
+        let report = check(ConformanceKind::Entry, &bytes);
+        let rules: Vec<_> = report
+            .violations
+            .iter()
+            .map(|v| (v.offset, v.rule))
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                (0, ConformanceRule::IndefiniteLength),
+                (bytes.len() - 1, ConformanceRule::TrailingBytes),
+            ]
+        );
+
+        // nonce = 0 written as 0x18 0x00 instead of 0x00
+        let nonce = entry.windows(6).position(|w| w == b"\x65nonce").unwrap() + 6;
+        let mut bytes = entry.clone();
+        bytes.splice(nonce..nonce + 1, [0x18, 0x00]);
+        let report = check(ConformanceKind::Entry, &bytes);
+        assert_eq!(report.violations[0].offset, nonce);
+        assert_eq!(
+            report.violations[0].rule,
+            ConformanceRule::NonMinimalEncoding
+        );
+        assert_eq!(report.violations.len(), 1);
+
+        // Well-formed, but fields out of order
+        #[derive(Serialize)]
+        struct Reordered {
+            nonce: u64,
+            timestamp_us: u64,
+            data_hash: Hash256,
+        }
+        let bytes = to_canonical_cbor(&Reordered {
+            nonce: 0,
+            timestamp_us: 1_000,
+            data_hash: [0u8; 32],
+        })
+        .unwrap();
+        let report = check(ConformanceKind::Entry, &bytes);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(
+            (report.violations[0].offset, report.violations[0].rule),
+            (1, ConformanceRule::NotCanonical)
+        );
+
+        // Structurally fine, but not an entry
+        let report = check(ConformanceKind::Checkpoint, &entry);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.violations[0].rule, ConformanceRule::Schema);
+
+        let report = check(ConformanceKind::Entry, &entry[..entry.len() - 3]);
+        assert_eq!(report.violations[0].rule, ConformanceRule::Truncated);
+    }
+}
@@ -0,0 +1,388 @@
+//! Consistency audit of anchored roots against storage.
+//!
+//! An anchor proves what a root was when it was anchored; it says nothing
+//! about whether storage still holds the data behind it. An
+//! [`AnchorAuditor`] runs periodically over every confirmed anchor of an
+//! [`AnchorTracker`] and recomputes, from storage alone:
+//!
+//! - the stored checkpoint carrying the anchored root, and its signature;
+//! - the root and enclave measurement encoded in the anchor's calldata;
+//! - the root of the checkpoint's stored entries
+//!   ([`verify_stored_entries`]), where storage keeps entries.
+//!
+//! Any mismatch is an [`AnchorDivergence`], reported and sent to every
+//! [`DivergenceSink`]: storage was corrupted or tampered with after the
+//! root was anchored.
+
+use crate::abi::measurement_word;
+use crate::anchor::{AnchorRecord, AnchorStatus, AnchorTracker};
+use crate::checkpoint::Checkpoint;
+use crate::clock::{system_clock, Clock};
+use crate::crypto::ct_eq;
+use crate::error::{ErrorCoded, ErrorDetail};
+use crate::keys::KeyResolver;
+use crate::tombstone::{verify_stored_entries, StoredEntry};
+use crate::types::Hash256;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Storage the auditor recomputes anchored roots from.
+pub trait AnchoredStorage {
+    /// The stored checkpoint whose `entries_root` is `root`.
+    fn checkpoint_for_root(&self, root: &Hash256) -> Option<Checkpoint>;
+
+    /// The checkpoint's stored entries, in tree order; `None` if this
+    /// storage does not keep entries.
+    fn entries(&self, checkpoint: &Checkpoint) -> Option<Vec<StoredEntry>>;
+}
+
+/// What no longer matches an anchored root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DivergenceKind {
+    /// No stored checkpoint carries the root
+    MissingCheckpoint,
+    /// The stored checkpoint's signature does not verify
+    CheckpointSignature,
+    /// The anchor's calldata commits to another root or measurement
+    CalldataMismatch,
+    /// The anchor receipt names another root
+    ReceiptMismatch,
+    /// The stored entries do not reproduce the root
+    EntriesDiverged,
+}
+
+/// One anchored root that storage no longer backs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnchorDivergence {
+    pub merkle_root: Hash256,
+    pub transaction_hash: Hash256,
+    pub kind: DivergenceKind,
+    /// Error behind the divergence, where there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<ErrorDetail>,
+}
+
+/// Receives divergence alerts (pager, audit log, operator dashboard).
+pub trait DivergenceSink: Send {
+    fn divergence_detected(&mut self, divergence: &AnchorDivergence);
+}
+
+/// In-memory sink, for tests and short-lived tools.
+impl DivergenceSink for Vec<AnchorDivergence> {
+    fn divergence_detected(&mut self, divergence: &AnchorDivergence) {
+        self.push(divergence.clone());
+    }
+}
+
+/// Outcome of one audit run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnchorAuditReport {
+    #[serde(with = "crate::serialization::timestamp")]
+    pub audited_at: DateTime<Utc>,
+    /// Confirmed anchors checked
+    pub checked: usize,
+    pub divergences: Vec<AnchorDivergence>,
+}
+
+impl AnchorAuditReport {
+    /// Whether storage backs every anchored root.
+    pub fn is_consistent(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+/// Re-verifies anchored roots against storage on a schedule.
+pub struct AnchorAuditor {
+    /// Robot signing keys
+    keys: Box<dyn KeyResolver>,
+    /// Tombstone approver keys
+    approvers: Box<dyn KeyResolver>,
+    sinks: Vec<Box<dyn DivergenceSink>>,
+    interval: Duration,
+    last_run: Option<DateTime<Utc>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl AnchorAuditor {
+    /// Verify checkpoints with `keys` and tombstones with `approvers`, once a day.
+    pub fn new(keys: Box<dyn KeyResolver>, approvers: Box<dyn KeyResolver>) -> Self {
+        Self {
+            keys,
+            approvers,
+            sinks: Vec::new(),
+            interval: Duration::days(1),
+            last_run: None,
+            clock: system_clock(),
+        }
+    }
+
+    /// Send divergence alerts to `sink` as well.
+    pub fn with_alert_sink(mut self, sink: Box<dyn DivergenceSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Audit every `interval` instead of daily.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Schedule against `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Whether the next run is due.
+    pub fn is_due(&self) -> bool {
+        self.last_run
+            .is_none_or(|last| self.clock.now() - last >= self.interval)
+    }
+
+    /// Check every confirmed anchor of `tracker` against `storage`.
+    pub fn audit(
+        &mut self,
+        tracker: &AnchorTracker,
+        storage: &dyn AnchoredStorage,
+    ) -> AnchorAuditReport {
+        let audited_at = self.clock.now();
+        let mut report = AnchorAuditReport {
+            audited_at,
+            checked: 0,
+            divergences: Vec::new(),
+        };
+        for record in tracker.with_status(AnchorStatus::Confirmed) {
+            report.checked += 1;
+            if let Some(divergence) = self.check(record, storage) {
+                for sink in &mut self.sinks {
+                    sink.divergence_detected(&divergence);
+                }
+                report.divergences.push(divergence);
+            }
+        }
+        self.last_run = Some(audited_at);
+        report
+    }
+
+    fn check(
+        &self,
+        record: &AnchorRecord,
+        storage: &dyn AnchoredStorage,
+    ) -> Option<AnchorDivergence> {
+        let diverged = |kind, detail| {
+            Some(AnchorDivergence {
+                merkle_root: record.merkle_root,
+                transaction_hash: record.transaction_hash,
+                kind,
+                detail,
+            })
+        };
+
+        let checkpoint = match storage.checkpoint_for_root(&record.merkle_root) {
+            Some(checkpoint) if ct_eq(&checkpoint.entries_root, &record.merkle_root) => checkpoint,
+            _ => return diverged(DivergenceKind::MissingCheckpoint, None),
+        };
+        if let Err(e) = checkpoint.verify_with_resolver(self.keys.as_ref()) {
+            return diverged(DivergenceKind::CheckpointSignature, Some(e.detail()));
+        }
+        // anchorCheckpoint calldata: selector, merkleRoot, enclaveMeasurement, ...
+        let measurement = measurement_word(&checkpoint.enclave_measurement).ok();
+        let anchored_root = record.calldata.get(4..36);
+        let anchored_measurement = record.calldata.get(36..68);
+        if !anchored_root.is_some_and(|root| ct_eq(root, &record.merkle_root))
+            || measurement.is_none()
+            || anchored_measurement != measurement.as_ref().map(|m| &m[..])
+        {
+            return diverged(DivergenceKind::CalldataMismatch, None);
+        }
+        if record
+            .receipt
+            .as_ref()
+            .is_some_and(|r| !ct_eq(&r.merkle_root, &record.merkle_root))
+        {
+            return diverged(DivergenceKind::ReceiptMismatch, None);
+        }
+        if let Some(entries) = storage.entries(&checkpoint) {
+            if let Err(e) =
+                verify_stored_entries(&entries, &record.merkle_root, self.approvers.as_ref())
+            {
+                return diverged(DivergenceKind::EntriesDiverged, Some(e.detail()));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::anchor_checkpoint_calldata;
+    use crate::anchor::AnchorConfig;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::clock::MockClock;
+    use crate::crypto::Signer;
+    use crate::forensic::AnchorReceipt;
+    use crate::merkle::{Entry, MerkleTree};
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct Storage {
+        checkpoints: HashMap<Hash256, Checkpoint>,
+        entries: HashMap<Hash256, Vec<StoredEntry>>,
+    }
+
+    impl AnchoredStorage for Storage {
+        fn checkpoint_for_root(&self, root: &Hash256) -> Option<Checkpoint> {
+            self.checkpoints.get(root).cloned()
+        }
+
+        fn entries(&self, checkpoint: &Checkpoint) -> Option<Vec<StoredEntry>> {
+            self.entries.get(&checkpoint.entries_root).cloned()
+        }
+    }
+
+    fn anchored(signer: &Signer, sequence: u64, storage: &mut Storage) -> AnchorRecord {
+        let mut tree = MerkleTree::new();
+        let entries: Vec<StoredEntry> = (0..3u64)
+            .map(|i| {
+                let payload = format!("checkpoint {sequence} record {i}").into_bytes();
+                let entry = Entry::new(1000 + i, 0, &payload);
+                tree.insert(entry.clone());
+                StoredEntry::Live { entry, payload }
+            })
+            .collect();
+        let checkpoint = CheckpointBuilder::test_fixture()
+            .sequence(sequence)
+            .monotonic_counter(sequence + 1)
+            .entries_root(tree.root())
+            .build_and_sign(signer.signing_key())
+            .unwrap();
+
+        let record = AnchorRecord {
+            merkle_root: tree.root(),
+            calldata: anchor_checkpoint_calldata(&checkpoint, "intel-sgx", &[0u8; 65]).unwrap(),
+            transaction_hash: [sequence as u8; 32],
+            submitted_at_block: 100,
+            attempts: 1,
+            status: AnchorStatus::Confirmed,
+            confirmations: 12,
+            inclusion: None,
+            receipt: None,
+            failure: None,
+            orphaned: None,
+            supersedes: None,
+        };
+        storage.entries.insert(tree.root(), entries);
+        storage.checkpoints.insert(tree.root(), checkpoint);
+        record
+    }
+
+    #[test]
+    fn test_audit_detects_divergence_from_storage() {
+        let signer = Signer::generate();
+        let mut storage = Storage::default();
+        let records = (0..3)
+            .map(|sequence| anchored(&signer, sequence, &mut storage))
+            .collect();
+        let tracker = AnchorTracker::from_records(AnchorConfig::new(1, [0xAA; 20]), records);
+        let clock = Arc::new(MockClock::new(Utc::now()));
+        let mut auditor = AnchorAuditor::new(
+            Box::new(signer.verifying_key()),
+            Box::new(signer.verifying_key()),
+        )
+        .with_interval(Duration::hours(6))
+        .with_clock(clock.clone());
+
+        assert!(auditor.is_due());
+        let report = auditor.audit(&tracker, &storage);
+        assert_eq!(report.checked, 3);
+        assert!(report.is_consistent());
+        assert!(!auditor.is_due());
+        clock.advance(Duration::hours(6));
+        assert!(auditor.is_due());
+
+        // A stored payload rewritten after anchoring, and a lost checkpoint
+        let roots: Vec<Hash256> = tracker.records().iter().map(|r| r.merkle_root).collect();
+        if let Some(StoredEntry::Live { payload, .. }) =
+            storage.entries.get_mut(&roots[0]).unwrap().first_mut()
+        {
+            payload.push(b'!');
+        }
+        storage.checkpoints.remove(&roots[2]);
+
+        let report = auditor.audit(&tracker, &storage);
+        let kinds: Vec<DivergenceKind> = report.divergences.iter().map(|d| d.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                DivergenceKind::EntriesDiverged,
+                DivergenceKind::MissingCheckpoint
+            ]
+        );
+        assert_eq!(report.divergences[0].merkle_root, roots[0]);
+    }
+
+    #[test]
+    fn test_audit_reports_each_root_mismatch() {
+        let signer = Signer::generate();
+        let mut storage = Storage::default();
+        let mut records: Vec<AnchorRecord> = (0..6)
+            .map(|sequence| anchored(&signer, sequence, &mut storage))
+            .collect();
+        let roots: Vec<Hash256> = records.iter().map(|r| r.merkle_root).collect();
+
+        // Storage answers for the root with a checkpoint committing to another
+        let other = storage.checkpoints[&roots[1]].clone();
+        storage.checkpoints.insert(roots[0], other);
+        // The stored checkpoint was altered after signing
+        storage
+            .checkpoints
+            .get_mut(&roots[1])
+            .unwrap()
+            .firmware_hash = [0xFF; 32];
+        // The calldata anchored another root, or another measurement
+        records[2].calldata[4] ^= 1;
+        records[3].calldata[36] ^= 1;
+        // The receipt names another root
+        records[4].receipt = Some(AnchorReceipt {
+            chain_id: 1,
+            registry: [0xAA; 20],
+            transaction_hash: records[4].transaction_hash,
+            block_number: 100,
+            block_timestamp: 1_700_000_000,
+            checkpoint_id: [0; 32],
+            merkle_root: [0xEE; 32],
+        });
+        // Storage lost an entry behind the root
+        storage.entries.get_mut(&roots[5]).unwrap().pop();
+
+        let tracker = AnchorTracker::from_records(AnchorConfig::new(1, [0xAA; 20]), records);
+        let mut auditor = AnchorAuditor::new(
+            Box::new(signer.verifying_key()),
+            Box::new(signer.verifying_key()),
+        );
+        let report = auditor.audit(&tracker, &storage);
+
+        let found: Vec<(Hash256, DivergenceKind)> = report
+            .divergences
+            .iter()
+            .map(|d| (d.merkle_root, d.kind))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (roots[0], DivergenceKind::MissingCheckpoint),
+                (roots[1], DivergenceKind::CheckpointSignature),
+                (roots[2], DivergenceKind::CalldataMismatch),
+                (roots[3], DivergenceKind::CalldataMismatch),
+                (roots[4], DivergenceKind::ReceiptMismatch),
+                (roots[5], DivergenceKind::EntriesDiverged),
+            ]
+        );
+        assert!(report.divergences[1].detail.is_some());
+        assert_eq!(report.checked, 6);
+    }
+}
@@ -0,0 +1,339 @@
+//! Gateway countersigning of receipts and tree heads.
+//!
+//! Gateway keys live in an HSM or a cloud KMS, never in process memory. The
+//! gateway reaches them through a [`SigningBackend`]: a PKCS#11 session or a
+//! KMS client implements the trait out of tree, and [`Signer`] implements it
+//! for development and tests. A [`Countersigner`] wraps the active backend,
+//! signs acceptance receipts ([`AttestationReceipt`]) and [`SignedTreeHead`]s
+//! with it, and appends a [`SigningAuditRecord`] for every signing operation,
+//! failed ones included.
+//!
+//! Rotating to a new backend keeps the retired verifying keys resolvable
+//! through [`Countersigner::keys`], so documents signed before the rotation
+//! still verify. A key id is never brought back once retired.
+
+use crate::clock::{system_clock, Clock};
+use crate::crypto::{key_id, sha256, Signature, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyRing;
+use crate::receipt::AttestationReceipt;
+use crate::serialization::SerializationError;
+use crate::transparency::{SignedTreeHead, TreeHead};
+use crate::types::{AttestationResult, Hash256, KeyId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// A store holding a gateway signing key (HSM slot, KMS key, in-memory key).
+pub trait SigningBackend: Send + Sync {
+    /// Backend name for audit records (e.g., "pkcs11:slot-0", "kms:alias/gw").
+    fn name(&self) -> &str;
+
+    /// Public half of the key the backend signs with.
+    fn verifying_key(&self) -> VerifyingKey;
+
+    /// Ed25519-sign `message` with the backend key.
+    fn sign(&self, message: &[u8]) -> Result<Signature, CountersignError>;
+}
+
+impl SigningBackend for Signer {
+    fn name(&self) -> &str {
+        "software"
+    }
+
+    fn verifying_key(&self) -> VerifyingKey {
+        Signer::verifying_key(self)
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Signature, CountersignError> {
+        Ok(Signer::sign(self, message))
+    }
+}
+
+/// Kind of document a countersigning operation covered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignedKind {
+    Receipt,
+    TreeHead,
+}
+
+/// One signing operation, successful or not.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningAuditRecord {
+    /// Position in the audit log (starts at 0, never reused)
+    pub index: u64,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub at: DateTime<Utc>,
+    pub kind: SignedKind,
+    pub backend: String,
+    pub key_id: KeyId,
+    /// SHA-256 of the signed payload
+    pub payload_hash: Hash256,
+    /// Why the operation failed, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorCode>,
+}
+
+/// Signs gateway documents through a rotatable backend, with an audit trail.
+pub struct Countersigner {
+    backend: Box<dyn SigningBackend>,
+    key_id: KeyId,
+    keys: KeyRing,
+    retired: Vec<KeyId>,
+    audit: Vec<SigningAuditRecord>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Countersigner {
+    pub fn new(backend: Box<dyn SigningBackend>) -> Self {
+        let mut keys = KeyRing::new();
+        let key_id = keys.insert(backend.verifying_key());
+        Self {
+            backend,
+            key_id,
+            keys,
+            retired: Vec::new(),
+            audit: Vec::new(),
+            clock: system_clock(),
+        }
+    }
+
+    /// Timestamp audit records with `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Fingerprint of the active signing key.
+    pub fn key_id(&self) -> KeyId {
+        self.key_id
+    }
+
+    /// Active and retired verifying keys.
+    pub fn keys(&self) -> &KeyRing {
+        &self.keys
+    }
+
+    /// Key ids rotated out, oldest first.
+    pub fn retired(&self) -> &[KeyId] {
+        &self.retired
+    }
+
+    /// Switch signing to `backend`, retiring the current key.
+    ///
+    /// Returns the new key id. Fails if the backend holds the active key or
+    /// one retired earlier.
+    pub fn rotate(&mut self, backend: Box<dyn SigningBackend>) -> Result<KeyId, CountersignError> {
+        let new_key_id = key_id(&backend.verifying_key());
+        if new_key_id == self.key_id || self.retired.contains(&new_key_id) {
+            return Err(CountersignError::KeyReused(new_key_id));
+        }
+        self.retired.push(self.key_id);
+        self.keys.insert(backend.verifying_key());
+        self.backend = backend;
+        self.key_id = new_key_id;
+        Ok(new_key_id)
+    }
+
+    /// Sign an acceptance receipt for `result` under `policy_id`.
+    pub fn sign_receipt(
+        &mut self,
+        result: &AttestationResult,
+        policy_id: impl Into<String>,
+    ) -> Result<AttestationReceipt, CountersignError> {
+        let mut receipt = AttestationReceipt::unsigned(result, policy_id.into(), self.key_id)?;
+        receipt.signature = self.sign(SignedKind::Receipt, &receipt.signing_payload()?)?;
+        Ok(receipt)
+    }
+
+    /// Sign `tree_head` as the gateway's log key.
+    pub fn sign_tree_head(
+        &mut self,
+        tree_head: TreeHead,
+    ) -> Result<SignedTreeHead, CountersignError> {
+        let mut sth = SignedTreeHead::unsigned(tree_head, self.key_id);
+        sth.signature = self.sign(SignedKind::TreeHead, &sth.signing_payload()?)?;
+        Ok(sth)
+    }
+
+    /// Signing operations so far, oldest first.
+    pub fn audit(&self) -> &[SigningAuditRecord] {
+        &self.audit
+    }
+
+    /// Sign through the backend, check the result against the active key and
+    /// audit the operation.
+    fn sign(
+        &mut self,
+        kind: SignedKind,
+        payload: &[u8],
+    ) -> Result<SignatureBytes, CountersignError> {
+        use ed25519_dalek::Verifier;
+
+        let outcome = self.backend.sign(payload).and_then(|signature| {
+            // A backend pointed at the wrong slot or key alias must not
+            // produce documents under this key id.
+            self.backend
+                .verifying_key()
+                .verify(payload, &signature)
+                .map_err(|_| CountersignError::KeyMismatch {
+                    backend: self.backend.name().to_string(),
+                })?;
+            Ok(SignatureBytes::from(signature.to_bytes()))
+        });
+
+        self.audit.push(SigningAuditRecord {
+            index: self.audit.len() as u64,
+            at: self.clock.now(),
+            kind,
+            backend: self.backend.name().to_string(),
+            key_id: self.key_id,
+            payload_hash: sha256(payload),
+            error: outcome.as_ref().err().map(ErrorCoded::code),
+        });
+        outcome
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CountersignError {
+    #[error("Signing backend {backend} failed: {message}")]
+    Backend { backend: String, message: String },
+
+    #[error("Signing backend {backend} signed with a key other than the active key")]
+    KeyMismatch { backend: String },
+
+    #[error("Key {0} is already active or was retired")]
+    KeyReused(KeyId),
+
+    #[error("Serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+}
+
+impl ErrorCoded for CountersignError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            CountersignError::Backend { .. } => ErrorCode::CountersignBackendFailed,
+            CountersignError::KeyMismatch { .. } => ErrorCode::CountersignKeyMismatch,
+            CountersignError::KeyReused(_) => ErrorCode::CountersignKeyReused,
+            CountersignError::Serialization(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{RevocationCheck, RevocationSource};
+
+    /// Advertises one key but signs with another, like a misconfigured HSM slot.
+    struct MisconfiguredBackend {
+        advertised: Signer,
+        actual: Signer,
+    }
+
+    impl SigningBackend for MisconfiguredBackend {
+        fn name(&self) -> &str {
+            "pkcs11:slot-7"
+        }
+
+        fn verifying_key(&self) -> VerifyingKey {
+            self.advertised.verifying_key()
+        }
+
+        fn sign(&self, message: &[u8]) -> Result<Signature, CountersignError> {
+            Ok(self.actual.sign(message))
+        }
+    }
+
+    fn result() -> AttestationResult {
+        AttestationResult {
+            vendor: "intel-sgx".to_string(),
+            enclave_measurement: vec![1u8; 32],
+            quote_verified: true,
+            verified_at: Utc::now(),
+            revoke_check: RevocationCheck::ok(RevocationSource::Crl),
+            raw_quote: None,
+            pck_chain: None,
+            claims: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_countersign_and_audit_across_rotation() {
+        let first = Signer::generate();
+        let first_id = first.key_id();
+        let first_key = first.signing_key().clone();
+        let mut countersigner = Countersigner::new(Box::new(first));
+
+        let result = result();
+        let receipt = countersigner
+            .sign_receipt(&result, "fleet-default-v1")
+            .unwrap();
+        let old_sth = countersigner
+            .sign_tree_head(TreeHead {
+                tree_size: 1,
+                root_hash: [7u8; 32],
+            })
+            .unwrap();
+
+        let second = Signer::generate();
+        let second_id = countersigner.rotate(Box::new(second)).unwrap();
+        let new_sth = countersigner
+            .sign_tree_head(TreeHead {
+                tree_size: 2,
+                root_hash: [8u8; 32],
+            })
+            .unwrap();
+
+        // Documents from before and after the rotation verify
+        receipt.verify(&result, countersigner.keys()).unwrap();
+        old_sth.verify(countersigner.keys()).unwrap();
+        new_sth.verify(countersigner.keys()).unwrap();
+        assert_eq!(old_sth.signer_key_id, first_id);
+        assert_eq!(new_sth.signer_key_id, second_id);
+        assert_eq!(countersigner.retired(), &[first_id]);
+
+        let audit = countersigner.audit();
+        assert_eq!(audit.len(), 3);
+        assert_eq!(audit[0].kind, SignedKind::Receipt);
+        assert_eq!(audit[2].kind, SignedKind::TreeHead);
+        assert_eq!(audit[2].key_id, second_id);
+        assert_eq!(audit[2].index, 2);
+        assert!(audit
+            .iter()
+            .all(|record| record.error.is_none() && record.backend == "software"));
+
+        // A retired key cannot come back
+        let err = countersigner
+            .rotate(Box::new(Signer::new(first_key)))
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::CountersignKeyReused);
+        assert_eq!(countersigner.key_id(), second_id);
+    }
+
+    #[test]
+    fn test_backend_key_mismatch_is_refused_and_audited() {
+        let backend = MisconfiguredBackend {
+            advertised: Signer::generate(),
+            actual: Signer::generate(),
+        };
+        let mut countersigner = Countersigner::new(Box::new(backend));
+
+        let err = countersigner
+            .sign_tree_head(TreeHead {
+                tree_size: 1,
+                root_hash: [7u8; 32],
+            })
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::CountersignKeyMismatch);
+
+        let audit = countersigner.audit();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].backend, "pkcs11:slot-7");
+        assert_eq!(audit[0].error, Some(ErrorCode::CountersignKeyMismatch));
+    }
+}
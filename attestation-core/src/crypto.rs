@@ -1,8 +1,10 @@
 //! Cryptographic primitives for attestation.
 
-use crate::types::Hash256;
+use crate::types::{Hash256, KeyId};
 pub use ed25519_dalek::{Signature, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::fmt;
 
 /// Compute SHA-256 hash of data.
 pub fn sha256(data: &[u8]) -> Hash256 {
@@ -16,6 +18,61 @@ pub fn blake3(data: &[u8]) -> Hash256 {
     *hash.as_bytes()
 }
 
+/// Compare two byte strings in constant time.
+///
+/// Use this wherever a computed hash, root or key id is checked against an
+/// expected one, so the comparison time leaks nothing about where they
+/// differ. Lengths are not secret: strings of different length compare
+/// unequal immediately.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    use subtle::ConstantTimeEq;
+    a.ct_eq(b).into()
+}
+
+/// Digest algorithm for checkpoint hashes, Merkle trees and notarization layers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestAlgorithm {
+    #[default]
+    Sha256,
+    Sha3_256,
+    Blake3,
+}
+
+impl DigestAlgorithm {
+    /// Hash `data` with this algorithm.
+    pub fn digest(&self, data: &[u8]) -> Hash256 {
+        match self {
+            DigestAlgorithm::Sha256 => sha256(data),
+            DigestAlgorithm::Sha3_256 => {
+                use sha3::Sha3_256;
+                Sha3_256::digest(data).into()
+            }
+            DigestAlgorithm::Blake3 => blake3(data),
+        }
+    }
+
+    /// Whether this is the default (SHA-256); used to keep it out of encodings.
+    pub fn is_default(&self) -> bool {
+        *self == DigestAlgorithm::Sha256
+    }
+}
+
+impl fmt::Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DigestAlgorithm::Sha256 => write!(f, "sha256"),
+            DigestAlgorithm::Sha3_256 => write!(f, "sha3-256"),
+            DigestAlgorithm::Blake3 => write!(f, "blake3"),
+        }
+    }
+}
+
+/// Compute the key identifier (SHA-256 fingerprint) of a verifying key.
+pub fn key_id(key: &VerifyingKey) -> KeyId {
+    KeyId(sha256(key.as_bytes()))
+}
+
 /// A signer that can create Ed25519 signatures.
 pub struct Signer {
     signing_key: SigningKey,
@@ -46,6 +103,11 @@ impl Signer {
         self.signing_key.verifying_key()
     }
 
+    /// Get the key identifier of the verifying key.
+    pub fn key_id(&self) -> KeyId {
+        key_id(&self.verifying_key())
+    }
+
     /// Get the signing key (use with caution).
     pub fn signing_key(&self) -> &SigningKey {
         &self.signing_key
@@ -84,4 +146,12 @@ mod tests {
         use ed25519_dalek::Verifier;
         assert!(signer.verifying_key().verify(message, &signature).is_ok());
     }
+
+    #[test]
+    fn test_key_id() {
+        let signer = Signer::generate();
+        assert_eq!(signer.key_id(), key_id(&signer.verifying_key()));
+        assert_ne!(signer.key_id(), Signer::generate().key_id());
+        assert_eq!(signer.key_id().to_string().len(), 64);
+    }
 }
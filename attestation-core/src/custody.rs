@@ -0,0 +1,1064 @@
+//! Proof-of-custody challenges for archived evidence.
+//!
+//! An operator that claims to retain a robot's evidence could keep the
+//! checkpoints and drop the entries behind them; nothing shows until the
+//! evidence is needed. A [`CustodyAuditor`] tests the claim by sampling: it
+//! asks the archive which checkpoints it holds and how many entries each
+//! has, then sends random (checkpoint, entry index) [`CustodyChallenge`]s.
+//! The archive must answer each with a [`CustodyProof`]: the stored entry
+//! and the Merkle path from it to the checkpoint's `entries_root`, before
+//! the challenge's deadline.
+//!
+//! An archive cannot shrink what it is audited on by claiming less. Every
+//! checkpoint the auditor holds must be claimed; one left out counts as a
+//! failed challenge. Every claimed checkpoint is also challenged at its last
+//! claimed entry, whose proof must show nothing to its right in the tree, so
+//! the claimed entry count is the real one.
+//!
+//! A live entry must come with its payload. A payload deleted under a
+//! tombstone approved for that checkpoint is not a custody failure, but the
+//! [`CustodyReport`] counts such deletions separately so that wholesale
+//! deletion stands out. If a fraction `f` of the claimed entries is gone,
+//! `n` challenges miss all of them with probability `(1 - f)^n`.
+//!
+//! Challenges are verified against checkpoints the auditor already holds,
+//! never against checkpoints the archive supplies.
+
+use crate::backfill::CheckpointStore;
+use crate::checkpoint::Checkpoint;
+use crate::clock::{system_clock, Clock};
+use crate::crypto::{ct_eq, sha256, DigestAlgorithm};
+use crate::error::{ErrorCode, ErrorCoded, ErrorDetail};
+use crate::keys::{KeyResolver, KeyRing};
+use crate::merkle::{compute_proof_siblings, reconstruct_root};
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::tombstone::{StoredEntry, Tombstone, TombstoneError};
+use crate::types::{Hash256, RobotId};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use rand::{CryptoRng, Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// A checkpoint an archive claims to hold, with its number of entries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustodyClaim {
+    pub sequence: u64,
+    pub entry_count: u64,
+}
+
+/// Request to prove custody of one entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustodyChallenge {
+    /// Random id the proof must echo
+    pub id: u64,
+    pub robot_id: RobotId,
+    pub sequence: u64,
+    /// Index of the entry in tree order
+    pub entry_index: u64,
+    /// Entry count the archive claimed for the checkpoint
+    pub entry_count: u64,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub issued_at: DateTime<Utc>,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub deadline: DateTime<Utc>,
+}
+
+/// An archive's answer to a [`CustodyChallenge`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustodyProof {
+    pub challenge_id: u64,
+    /// The challenged entry as stored
+    pub stored: StoredEntry,
+    /// Tombstone of an erased entry, whose hash it keeps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tombstone: Option<Tombstone>,
+    /// Merkle path from the entry's leaf to the entries root
+    pub siblings: Vec<Hash256>,
+}
+
+impl CustodyProof {
+    /// Answer `challenge` from a checkpoint's stored entries, in tree order.
+    ///
+    /// `tombstones` are looked up by hash when the challenged entry was
+    /// erased.
+    pub fn generate(
+        challenge: &CustodyChallenge,
+        entries: &[StoredEntry],
+        tombstones: &[Tombstone],
+    ) -> Result<Self, CustodyError> {
+        let missing = || CustodyError::NotHeld {
+            sequence: challenge.sequence,
+            entry_index: challenge.entry_index,
+        };
+        let index = usize::try_from(challenge.entry_index).map_err(|_| missing())?;
+        let stored = entries.get(index).ok_or_else(missing)?.clone();
+        let tombstone = match &stored {
+            StoredEntry::Erased(erased) => Some(
+                tombstones
+                    .iter()
+                    .find(|t| {
+                        t.compute_hash()
+                            .is_ok_and(|hash| ct_eq(&hash, &erased.tombstone_hash))
+                    })
+                    .ok_or_else(missing)?
+                    .clone(),
+            ),
+            _ => None,
+        };
+        let leaves: Vec<Hash256> = entries.iter().map(StoredEntry::leaf_hash).collect();
+        Ok(Self {
+            challenge_id: challenge.id,
+            stored,
+            tombstone,
+            siblings: compute_proof_siblings(DigestAlgorithm::Sha256, &leaves, index),
+        })
+    }
+
+    /// Check this proof against `challenge` and the auditor's copy of the
+    /// challenged checkpoint.
+    ///
+    /// Returns [`CustodyVerdict::Proven`] for a live entry whose payload
+    /// matches its data hash, and [`CustodyVerdict::Deleted`] for one whose
+    /// payload was deleted under a tombstone `approvers` signed for this
+    /// checkpoint. The deadline is not checked here.
+    pub fn verify(
+        &self,
+        challenge: &CustodyChallenge,
+        checkpoint: &Checkpoint,
+        approvers: &dyn KeyResolver,
+    ) -> Result<CustodyVerdict, CustodyError> {
+        if self.challenge_id != challenge.id || checkpoint.sequence != challenge.sequence {
+            return Err(CustodyError::WrongChallenge(self.challenge_id));
+        }
+        if self.siblings.len() != tree_depth(challenge.entry_count)
+            || challenge.entry_index >= challenge.entry_count
+        {
+            return Err(CustodyError::InvalidProof(challenge.sequence));
+        }
+        let leaf_hash = self.stored.leaf_hash();
+        let root = reconstruct_root(
+            DigestAlgorithm::Sha256,
+            leaf_hash,
+            challenge.entry_index as usize,
+            &self.siblings,
+        );
+        if !ct_eq(&root, &checkpoint.entries_root) {
+            return Err(CustodyError::InvalidProof(challenge.sequence));
+        }
+        if challenge.entry_index + 1 == challenge.entry_count
+            && !ends_tree(leaf_hash, challenge.entry_index, &self.siblings)
+        {
+            return Err(CustodyError::EntriesUnclaimed(challenge.sequence));
+        }
+
+        let tombstone = match &self.stored {
+            StoredEntry::Live { entry, payload } => {
+                if !ct_eq(&sha256(payload), &entry.data_hash) {
+                    return Err(CustodyError::PayloadMismatch(challenge.sequence));
+                }
+                return Ok(CustodyVerdict::Proven);
+            }
+            StoredEntry::Tombstoned(tombstone) => tombstone,
+            StoredEntry::Erased(erased) => match &self.tombstone {
+                Some(tombstone)
+                    if ct_eq(&tombstone.compute_hash()?, &erased.tombstone_hash)
+                        && ct_eq(&tombstone.entry.hash(), &leaf_hash) =>
+                {
+                    tombstone
+                }
+                _ => return Err(CustodyError::DeletionUnproven(challenge.sequence)),
+            },
+        };
+        if !ct_eq(&tombstone.entries_root, &checkpoint.entries_root) {
+            return Err(CustodyError::DeletionUnproven(challenge.sequence));
+        }
+        tombstone.verify(approvers)?;
+        Ok(CustodyVerdict::Deleted)
+    }
+}
+
+/// Number of siblings on a leaf's path in a tree of `leaf_count` leaves.
+fn tree_depth(leaf_count: u64) -> usize {
+    let (mut width, mut depth) = (leaf_count, 0);
+    while width > 1 {
+        width = width.div_ceil(2);
+        depth += 1;
+    }
+    depth
+}
+
+/// Whether `siblings` place the leaf at `index` last in its tree: wherever
+/// its path is a left child, the tree paired it with itself, which it does
+/// only with nothing to its right.
+fn ends_tree(leaf_hash: Hash256, index: u64, siblings: &[Hash256]) -> bool {
+    (0..siblings.len()).all(|level| {
+        !(index >> level).is_multiple_of(2) || {
+            let node = reconstruct_root(
+                DigestAlgorithm::Sha256,
+                leaf_hash,
+                index as usize,
+                &siblings[..level],
+            );
+            ct_eq(&siblings[level], &node)
+        }
+    })
+}
+
+/// An archive answering custody challenges.
+#[async_trait]
+pub trait CustodyProver: Send + Sync {
+    /// Checkpoints of `robot_id` the archive claims to hold.
+    async fn holdings(&self, robot_id: &RobotId) -> Result<Vec<CustodyClaim>, CustodyError>;
+
+    /// Prove custody of the challenged entry.
+    async fn prove(&self, challenge: &CustodyChallenge) -> Result<CustodyProof, CustodyError>;
+}
+
+/// Result of one challenge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CustodyVerdict {
+    /// The entry and its payload were produced in time
+    Proven,
+    /// The payload was deleted under an approved tombstone
+    Deleted,
+    /// A valid proof arrived after the deadline
+    Late,
+    /// No proof, or one that does not verify
+    Failed(ErrorDetail),
+}
+
+/// A challenge and how the archive answered it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChallengeOutcome {
+    pub challenge: CustodyChallenge,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub answered_at: DateTime<Utc>,
+    pub verdict: CustodyVerdict,
+}
+
+/// Outcome of one custody audit of a robot's archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustodyReport {
+    pub robot_id: RobotId,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub audited_at: DateTime<Utc>,
+    /// Checkpoints claimed by the archive that the auditor holds
+    pub checkpoints_claimed: u64,
+    /// Entries claimed under those checkpoints
+    pub entries_claimed: u64,
+    pub outcomes: Vec<ChallengeOutcome>,
+}
+
+impl CustodyReport {
+    /// Number of challenges with `verdict`'s kind.
+    fn count(&self, matches: impl Fn(&CustodyVerdict) -> bool) -> usize {
+        self.outcomes.iter().filter(|o| matches(&o.verdict)).count()
+    }
+
+    pub fn proven(&self) -> usize {
+        self.count(|v| *v == CustodyVerdict::Proven)
+    }
+
+    pub fn deleted(&self) -> usize {
+        self.count(|v| *v == CustodyVerdict::Deleted)
+    }
+
+    pub fn late(&self) -> usize {
+        self.count(|v| *v == CustodyVerdict::Late)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|v| matches!(v, CustodyVerdict::Failed(_)))
+    }
+
+    /// Fraction of challenges, approved deletions aside, proven in time.
+    ///
+    /// An audit in which every challenged entry was deleted scores 1.0;
+    /// check [`deleted`](Self::deleted) as well.
+    pub fn score(&self) -> f64 {
+        let scored = self.outcomes.len() - self.deleted();
+        if scored == 0 {
+            return 1.0;
+        }
+        self.proven() as f64 / scored as f64
+    }
+
+    /// Whether the archive scored at least `min_score`.
+    pub fn passed(&self, min_score: f64) -> bool {
+        !self.outcomes.is_empty() && self.score() >= min_score
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+}
+
+/// Challenges archives to prove custody of the evidence they claim.
+pub struct CustodyAuditor {
+    /// Time an archive has to answer each challenge
+    deadline: Duration,
+    /// Keys whose tombstones excuse a deleted payload
+    approvers: Box<dyn KeyResolver>,
+    clock: Arc<dyn Clock>,
+}
+
+impl CustodyAuditor {
+    /// Audit with `deadline` per challenge. No deletions are approved.
+    pub fn new(deadline: Duration) -> Self {
+        Self {
+            deadline,
+            approvers: Box::new(KeyRing::new()),
+            clock: system_clock(),
+        }
+    }
+
+    /// Accept deletions under tombstones signed by `approvers`.
+    pub fn with_approvers(mut self, approvers: Box<dyn KeyResolver>) -> Self {
+        self.approvers = approvers;
+        self
+    }
+
+    /// Issue challenges and time answers with `clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Send `challenges` random challenges about `robot_id`'s entries to
+    /// `prover`, one at a time, and verify the answers against the
+    /// checkpoints in `store`.
+    ///
+    /// Before them, each claimed checkpoint is challenged at its last
+    /// entry, and each checkpoint in `store` with entries that the archive
+    /// does not claim is reported as a failed challenge, without asking.
+    pub async fn audit(
+        &self,
+        prover: &dyn CustodyProver,
+        store: &dyn CheckpointStore,
+        robot_id: &RobotId,
+        challenges: usize,
+    ) -> Result<CustodyReport, CustodyError> {
+        self.audit_with_rng(prover, store, robot_id, challenges, &mut rand::rngs::OsRng)
+            .await
+    }
+
+    /// [`audit`](Self::audit) with a caller-provided RNG (deterministic tests).
+    pub async fn audit_with_rng<R: RngCore + CryptoRng + Send>(
+        &self,
+        prover: &dyn CustodyProver,
+        store: &dyn CheckpointStore,
+        robot_id: &RobotId,
+        challenges: usize,
+        rng: &mut R,
+    ) -> Result<CustodyReport, CustodyError> {
+        let held: BTreeMap<u64, Checkpoint> = store
+            .checkpoints(robot_id)
+            .into_iter()
+            .map(|c| (c.sequence, c))
+            .collect();
+        // Claims about checkpoints the auditor cannot check are ignored
+        let mut claims: Vec<CustodyClaim> = prover
+            .holdings(robot_id)
+            .await?
+            .into_iter()
+            .filter(|claim| claim.entry_count > 0 && held.contains_key(&claim.sequence))
+            .collect();
+        claims.sort_by_key(|claim| claim.sequence);
+        claims.dedup_by_key(|claim| claim.sequence);
+        // Heartbeats, like any checkpoint without entries, have the zero root
+        let unclaimed: Vec<u64> = held
+            .values()
+            .filter(|c| {
+                c.entries_root != [0u8; 32]
+                    && claims
+                        .binary_search_by_key(&c.sequence, |claim| claim.sequence)
+                        .is_err()
+            })
+            .map(|c| c.sequence)
+            .collect();
+        let entries_claimed = claims
+            .iter()
+            .try_fold(0u64, |total, claim| total.checked_add(claim.entry_count));
+        let entries_claimed = match entries_claimed {
+            Some(total) if total > 0 || !unclaimed.is_empty() => total,
+            _ => return Err(CustodyError::NothingClaimed(robot_id.clone())),
+        };
+
+        let mut report = CustodyReport {
+            robot_id: robot_id.clone(),
+            audited_at: self.clock.now(),
+            checkpoints_claimed: claims.len() as u64,
+            entries_claimed,
+            outcomes: Vec::with_capacity(unclaimed.len() + claims.len() + challenges),
+        };
+        for sequence in unclaimed {
+            let issued_at = self.clock.now();
+            report.outcomes.push(ChallengeOutcome {
+                challenge: CustodyChallenge {
+                    id: rng.next_u64(),
+                    robot_id: robot_id.clone(),
+                    sequence,
+                    entry_index: 0,
+                    entry_count: 0,
+                    issued_at,
+                    deadline: issued_at,
+                },
+                answered_at: issued_at,
+                verdict: CustodyVerdict::Failed(CustodyError::Unclaimed(sequence).detail()),
+            });
+        }
+        for claim in &claims {
+            let outcome = self
+                .challenge(
+                    prover,
+                    &held[&claim.sequence],
+                    claim,
+                    claim.entry_count - 1,
+                    rng,
+                )
+                .await;
+            report.outcomes.push(outcome);
+        }
+        if entries_claimed == 0 {
+            return Ok(report);
+        }
+        for _ in 0..challenges {
+            // Uniform over claimed entries, so larger checkpoints are challenged more
+            let mut pick = rng.gen_range(0..entries_claimed);
+            let claim = claims
+                .iter()
+                .find(|claim| {
+                    let here = pick < claim.entry_count;
+                    if !here {
+                        pick -= claim.entry_count;
+                    }
+                    here
+                })
+                .expect("pick is below the total entry count");
+            let outcome = self
+                .challenge(prover, &held[&claim.sequence], claim, pick, rng)
+                .await;
+            report.outcomes.push(outcome);
+        }
+        Ok(report)
+    }
+
+    /// Challenge `prover` to prove entry `entry_index` of `checkpoint`, and
+    /// judge its answer.
+    async fn challenge<R: RngCore + Send>(
+        &self,
+        prover: &dyn CustodyProver,
+        checkpoint: &Checkpoint,
+        claim: &CustodyClaim,
+        entry_index: u64,
+        rng: &mut R,
+    ) -> ChallengeOutcome {
+        let issued_at = self.clock.now();
+        let challenge = CustodyChallenge {
+            id: rng.next_u64(),
+            robot_id: checkpoint.robot_id.clone(),
+            sequence: claim.sequence,
+            entry_index,
+            entry_count: claim.entry_count,
+            issued_at,
+            deadline: issued_at + self.deadline,
+        };
+
+        let answer = prover.prove(&challenge).await;
+        let answered_at = self.clock.now();
+        let verdict = match answer
+            .and_then(|proof| proof.verify(&challenge, checkpoint, self.approvers.as_ref()))
+        {
+            Ok(_) if answered_at > challenge.deadline => CustodyVerdict::Late,
+            Ok(verdict) => verdict,
+            Err(e) => CustodyVerdict::Failed(e.detail()),
+        };
+        ChallengeOutcome {
+            challenge,
+            answered_at,
+            verdict,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CustodyError {
+    #[error("Archive did not answer: {0}")]
+    Unavailable(String),
+
+    #[error("Archive does not hold entry {entry_index} of checkpoint #{sequence}")]
+    NotHeld { sequence: u64, entry_index: u64 },
+
+    #[error("Proof answers challenge {0}, not the one asked")]
+    WrongChallenge(u64),
+
+    #[error("Custody proof does not reproduce the entries_root of checkpoint #{0}")]
+    InvalidProof(u64),
+
+    #[error("Payload does not match its entry in checkpoint #{0}")]
+    PayloadMismatch(u64),
+
+    #[error("Deleted entry in checkpoint #{0} has no approved tombstone for it")]
+    DeletionUnproven(u64),
+
+    #[error("Tombstone rejected: {0}")]
+    Tombstone(#[from] TombstoneError),
+
+    #[error("Archive claims no entries of the audited checkpoints of {0}")]
+    NothingClaimed(RobotId),
+
+    #[error("Archive does not claim checkpoint #{0}, which the auditor holds")]
+    Unclaimed(u64),
+
+    #[error("Checkpoint #{0} has entries past the count the archive claims")]
+    EntriesUnclaimed(u64),
+
+    #[error("Serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+}
+
+impl ErrorCoded for CustodyError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            CustodyError::Unavailable(_) => ErrorCode::CustodyUnanswered,
+            CustodyError::NotHeld { .. } => ErrorCode::CustodyEntryMissing,
+            CustodyError::WrongChallenge(_) | CustodyError::InvalidProof(_) => {
+                ErrorCode::CustodyProofInvalid
+            }
+            CustodyError::PayloadMismatch(_) => ErrorCode::CustodyPayloadMismatch,
+            CustodyError::DeletionUnproven(_) => ErrorCode::CustodyDeletionUnproven,
+            CustodyError::Tombstone(e) => e.code(),
+            CustodyError::NothingClaimed(_) => ErrorCode::CustodyNothingClaimed,
+            CustodyError::Unclaimed(_) => ErrorCode::CustodyCheckpointUnclaimed,
+            CustodyError::EntriesUnclaimed(_) => ErrorCode::CustodyEntriesUnclaimed,
+            CustodyError::Serialization(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::{ChainVerifier, VerifiedCheckpoint};
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::clock::MockClock;
+    use crate::crypto::Signer;
+    use crate::merkle::{Entry, MerkleTree};
+    use crate::tombstone::{DeletionReason, ErasedEntry};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::sync::Mutex;
+
+    /// The auditor's own copy of the checkpoints.
+    struct Held(Vec<Checkpoint>);
+
+    impl CheckpointStore for Held {
+        fn checkpoints(&self, _robot_id: &RobotId) -> Vec<Checkpoint> {
+            self.0.clone()
+        }
+
+        fn insert(&mut self, checkpoint: VerifiedCheckpoint) {
+            self.0.push(checkpoint.into_inner());
+        }
+    }
+
+    /// Archive of one checkpoint's stored entries; drops entries at or past
+    /// `keep` and takes `delay` to answer.
+    struct Archive {
+        sequence: u64,
+        entries: Vec<StoredEntry>,
+        keep: usize,
+        delay: Duration,
+        clock: MockClock,
+        tampered: Mutex<bool>,
+    }
+
+    #[async_trait]
+    impl CustodyProver for Archive {
+        async fn holdings(&self, _robot_id: &RobotId) -> Result<Vec<CustodyClaim>, CustodyError> {
+            Ok(vec![
+                CustodyClaim {
+                    sequence: self.sequence,
+                    entry_count: self.entries.len() as u64,
+                },
+                // Not a checkpoint the auditor holds
+                CustodyClaim {
+                    sequence: 99,
+                    entry_count: 1000,
+                },
+            ])
+        }
+
+        async fn prove(&self, challenge: &CustodyChallenge) -> Result<CustodyProof, CustodyError> {
+            self.clock.advance(self.delay);
+            if challenge.entry_index as usize >= self.keep {
+                return Err(CustodyError::NotHeld {
+                    sequence: challenge.sequence,
+                    entry_index: challenge.entry_index,
+                });
+            }
+            let mut proof = CustodyProof::generate(challenge, &self.entries, &[])?;
+            if std::mem::take(&mut *self.tampered.lock().unwrap()) {
+                if let StoredEntry::Live { payload, .. } = &mut proof.stored {
+                    payload.push(0);
+                }
+            }
+            Ok(proof)
+        }
+    }
+
+    /// Checkpoint over `count` entries, the last of them tombstoned.
+    fn setup(signer: &Signer, approver: &Signer, count: u8) -> (Checkpoint, Vec<StoredEntry>) {
+        let payloads: Vec<Vec<u8>> = (0..count).map(|i| vec![i; 16]).collect();
+        let mut tree = MerkleTree::new();
+        for (i, payload) in payloads.iter().enumerate() {
+            tree.insert(Entry::new(1000 * i as u64, 0, payload));
+        }
+        let checkpoint = CheckpointBuilder::test_fixture()
+            .entries_root(tree.root())
+            .build_and_sign(signer.signing_key())
+            .unwrap();
+        let mut entries: Vec<StoredEntry> = tree
+            .entries()
+            .into_iter()
+            .zip(payloads)
+            .map(|(entry, payload)| StoredEntry::Live {
+                entry: entry.clone(),
+                payload,
+            })
+            .collect();
+        // The last payload was deleted under an approved tombstone
+        let last = entries.pop().unwrap();
+        entries.push(
+            last.tombstone(
+                checkpoint.entries_root,
+                DeletionReason::RetentionExpired,
+                approver,
+            )
+            .unwrap(),
+        );
+        (checkpoint, entries)
+    }
+
+    #[tokio::test]
+    async fn test_custody_audit_scores_archives() {
+        let (signer, approver) = (Signer::generate(), Signer::generate());
+        let (checkpoint, entries) = setup(&signer, &approver, 5);
+        let robot_id = checkpoint.robot_id.clone();
+        let mut store = Held(Vec::new());
+        store.insert(
+            ChainVerifier::new(signer.verifying_key())
+                .accept(checkpoint.clone())
+                .unwrap(),
+        );
+
+        let clock = MockClock::new(DateTime::UNIX_EPOCH + Duration::days(20_000));
+        let mut approvers = KeyRing::new();
+        approvers.insert(approver.verifying_key());
+        let auditor = CustodyAuditor::new(Duration::seconds(5))
+            .with_approvers(Box::new(approvers))
+            .with_clock(Arc::new(clock.clone()));
+        let archive = |keep, delay| Archive {
+            sequence: 0,
+            entries: entries.clone(),
+            keep,
+            delay,
+            clock: clock.clone(),
+            tampered: Mutex::new(false),
+        };
+
+        // A complete archive proves every entry; the tombstoned one is excused
+        let report = auditor
+            .audit_with_rng(
+                &archive(5, Duration::seconds(1)),
+                &store,
+                &robot_id,
+                40,
+                &mut StdRng::seed_from_u64(7),
+            )
+            .await
+            .unwrap();
+        assert_eq!((report.checkpoints_claimed, report.entries_claimed), (1, 5));
+        // One more challenge for the last claimed entry
+        assert_eq!(report.outcomes.len(), 41);
+        assert_eq!(report.proven() + report.deleted(), 41);
+        assert!(report.deleted() > 0);
+        assert!(report.passed(1.0));
+        assert_eq!(
+            CustodyReport::from_bytes(&report.to_bytes().unwrap()).unwrap(),
+            report
+        );
+
+        // An archive missing entries fails the challenges that hit them
+        let report = auditor
+            .audit_with_rng(
+                &archive(2, Duration::seconds(1)),
+                &store,
+                &robot_id,
+                40,
+                &mut StdRng::seed_from_u64(7),
+            )
+            .await
+            .unwrap();
+        assert!(report.failed() > 0 && report.proven() > 0);
+        assert!(!report.passed(0.9));
+        let failure = report.outcomes.iter().find_map(|o| match &o.verdict {
+            CustodyVerdict::Failed(detail) => Some(detail.code),
+            _ => None,
+        });
+        assert_eq!(failure, Some(ErrorCode::CustodyEntryMissing));
+
+        // Answers past the deadline are late, even when valid
+        let report = auditor
+            .audit_with_rng(
+                &archive(5, Duration::seconds(6)),
+                &store,
+                &robot_id,
+                10,
+                &mut StdRng::seed_from_u64(7),
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.late() + report.deleted(), 11);
+        assert_eq!(report.score(), 0.0);
+
+        // Altered payloads do not prove custody
+        let tampered = archive(4, Duration::seconds(1));
+        *tampered.tampered.lock().unwrap() = true;
+        let report = auditor
+            .audit_with_rng(
+                &tampered,
+                &store,
+                &robot_id,
+                1,
+                &mut StdRng::seed_from_u64(7),
+            )
+            .await
+            .unwrap();
+        // The last entry is past those kept; the random challenge gets the altered payload
+        let verdict = &report.outcomes[1].verdict;
+        assert!(
+            matches!(verdict, CustodyVerdict::Failed(d) if d.code == ErrorCode::CustodyPayloadMismatch),
+            "{verdict:?}"
+        );
+    }
+
+    /// An archive claiming only `claims`, whatever it holds.
+    struct Narrowed {
+        archive: Archive,
+        claims: Vec<CustodyClaim>,
+    }
+
+    #[async_trait]
+    impl CustodyProver for Narrowed {
+        async fn holdings(&self, _robot_id: &RobotId) -> Result<Vec<CustodyClaim>, CustodyError> {
+            Ok(self.claims.clone())
+        }
+
+        async fn prove(&self, challenge: &CustodyChallenge) -> Result<CustodyProof, CustodyError> {
+            self.archive.prove(challenge).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_archive_cannot_narrow_what_is_audited() {
+        let (signer, approver) = (Signer::generate(), Signer::generate());
+        let (checkpoint, entries) = setup(&signer, &approver, 8);
+        let robot_id = checkpoint.robot_id.clone();
+        let clock = MockClock::new(DateTime::UNIX_EPOCH + Duration::days(20_000));
+        let mut approvers = KeyRing::new();
+        approvers.insert(approver.verifying_key());
+        let auditor = CustodyAuditor::new(Duration::seconds(5))
+            .with_approvers(Box::new(approvers))
+            .with_clock(Arc::new(clock.clone()));
+        let narrowed = |claims| Narrowed {
+            archive: Archive {
+                sequence: 0,
+                entries: entries.clone(),
+                keep: 8,
+                delay: Duration::seconds(1),
+                clock: clock.clone(),
+                tampered: Mutex::new(false),
+            },
+            claims,
+        };
+        let failures = |report: &CustodyReport| -> Vec<(u64, ErrorCode)> {
+            report
+                .outcomes
+                .iter()
+                .filter_map(|o| match &o.verdict {
+                    CustodyVerdict::Failed(detail) => Some((o.challenge.sequence, detail.code)),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // A checkpoint left out of the holdings fails rather than goes unaudited
+        let dropped = CheckpointBuilder::continuing_from(&checkpoint)
+            .unwrap()
+            .monotonic_counter(2)
+            .entries_root([9u8; 32])
+            .build_and_sign(signer.signing_key())
+            .unwrap();
+        let store = Held(vec![checkpoint.clone(), dropped]);
+        let claims = vec![CustodyClaim {
+            sequence: 0,
+            entry_count: 8,
+        }];
+        let report = auditor
+            .audit_with_rng(
+                &narrowed(claims),
+                &store,
+                &robot_id,
+                20,
+                &mut StdRng::seed_from_u64(7),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            failures(&report),
+            vec![(1, ErrorCode::CustodyCheckpointUnclaimed)]
+        );
+        assert!(!report.passed(1.0));
+
+        // Claiming none of them is no way out either
+        let report = auditor
+            .audit_with_rng(
+                &narrowed(Vec::new()),
+                &store,
+                &robot_id,
+                20,
+                &mut StdRng::seed_from_u64(7),
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.outcomes.len(), 2);
+        assert_eq!(report.score(), 0.0);
+
+        // Under-claiming entries at the same tree depth is caught at the last
+        // claimed entry, while random challenges below it all pass
+        let store = Held(vec![checkpoint]);
+        let claims = vec![CustodyClaim {
+            sequence: 0,
+            entry_count: 6,
+        }];
+        let report = auditor
+            .audit_with_rng(
+                &narrowed(claims),
+                &store,
+                &robot_id,
+                20,
+                &mut StdRng::seed_from_u64(7),
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.outcomes[0].challenge.entry_index, 5);
+        for outcome in &report.outcomes {
+            let at_last = outcome.challenge.entry_index == 5;
+            match &outcome.verdict {
+                CustodyVerdict::Failed(detail) => {
+                    assert!(at_last && detail.code == ErrorCode::CustodyEntriesUnclaimed)
+                }
+                verdict => assert!(!at_last && *verdict == CustodyVerdict::Proven),
+            }
+        }
+
+        // The full count passes
+        let claims = vec![CustodyClaim {
+            sequence: 0,
+            entry_count: 8,
+        }];
+        let report = auditor
+            .audit_with_rng(
+                &narrowed(claims),
+                &store,
+                &robot_id,
+                20,
+                &mut StdRng::seed_from_u64(7),
+            )
+            .await
+            .unwrap();
+        assert!(report.passed(1.0));
+        assert_eq!(report.outcomes[0].verdict, CustodyVerdict::Deleted);
+    }
+
+    #[test]
+    fn test_proof_must_match_challenged_position() {
+        let (signer, approver) = (Signer::generate(), Signer::generate());
+        let (checkpoint, entries) = setup(&signer, &approver, 5);
+        let now = Utc::now();
+        let challenge = |entry_index, entry_count| CustodyChallenge {
+            id: 1,
+            robot_id: checkpoint.robot_id.clone(),
+            sequence: 0,
+            entry_index,
+            entry_count,
+            issued_at: now,
+            deadline: now,
+        };
+        let approvers = KeyRing::new();
+        let proof = CustodyProof::generate(&challenge(1, 5), &entries, &[]).unwrap();
+        assert_eq!(
+            proof
+                .verify(&challenge(1, 5), &checkpoint, &approvers)
+                .unwrap(),
+            CustodyVerdict::Proven
+        );
+
+        // The same proof does not answer for another index or claimed size
+        let err = proof
+            .verify(&challenge(0, 5), &checkpoint, &approvers)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::CustodyProofInvalid);
+        assert!(proof
+            .verify(&challenge(1, 2), &checkpoint, &approvers)
+            .is_err());
+
+        // A tombstone by a key the auditor does not accept is no excuse
+        let proof = CustodyProof::generate(&challenge(4, 5), &entries, &[]).unwrap();
+        let err = proof
+            .verify(&challenge(4, 5), &checkpoint, &approvers)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TombstoneUnknownApprover);
+    }
+
+    #[test]
+    fn test_erased_entries_proven_by_their_tombstone() {
+        let (signer, approver) = (Signer::generate(), Signer::generate());
+        let (checkpoint, mut entries) = setup(&signer, &approver, 5);
+        let now = Utc::now();
+        let challenge = |entry_index| CustodyChallenge {
+            id: 1,
+            robot_id: checkpoint.robot_id.clone(),
+            sequence: 0,
+            entry_index,
+            entry_count: 5,
+            issued_at: now,
+            deadline: now,
+        };
+        let mut approvers = KeyRing::new();
+        approvers.insert(approver.verifying_key());
+
+        // Erase the tombstoned entry, keeping its leaf and tombstone hashes
+        let StoredEntry::Tombstoned(tombstone) = entries[4].clone() else {
+            panic!("last entry is tombstoned");
+        };
+        entries[4] = StoredEntry::Erased(ErasedEntry {
+            leaf_hash: tombstone.entry.hash(),
+            tombstone_hash: tombstone.compute_hash().unwrap(),
+        });
+        let other = Tombstone::issue(
+            entries[3].entry().unwrap().clone(),
+            checkpoint.entries_root,
+            DeletionReason::RetentionExpired,
+            &approver,
+        )
+        .unwrap();
+
+        // The tombstone is looked up by hash among those the archive keeps
+        let proof =
+            CustodyProof::generate(&challenge(4), &entries, &[other.clone(), tombstone.clone()])
+                .unwrap();
+        assert_eq!(proof.tombstone.as_ref(), Some(&tombstone));
+        assert_eq!(
+            proof
+                .verify(&challenge(4), &checkpoint, &approvers)
+                .unwrap(),
+            CustodyVerdict::Deleted
+        );
+
+        // Without it the archive cannot answer for the erased entry
+        let err = CustodyProof::generate(&challenge(4), &entries, std::slice::from_ref(&other))
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::CustodyEntryMissing);
+
+        // Another entry's tombstone, even an approved one, does not excuse it
+        let mut swapped = proof.clone();
+        swapped.tombstone = Some(other);
+        let err = swapped
+            .verify(&challenge(4), &checkpoint, &approvers)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::CustodyDeletionUnproven);
+        let mut missing = proof;
+        missing.tombstone = None;
+        assert!(matches!(
+            missing.verify(&challenge(4), &checkpoint, &approvers),
+            Err(CustodyError::DeletionUnproven(0))
+        ));
+    }
+
+    #[test]
+    fn test_tampered_proof_hashes_rejected() {
+        let (signer, approver) = (Signer::generate(), Signer::generate());
+        let (checkpoint, entries) = setup(&signer, &approver, 5);
+        let now = Utc::now();
+        let challenge = |entry_index| CustodyChallenge {
+            id: 1,
+            robot_id: checkpoint.robot_id.clone(),
+            sequence: 0,
+            entry_index,
+            entry_count: 5,
+            issued_at: now,
+            deadline: now,
+        };
+        let mut approvers = KeyRing::new();
+        approvers.insert(approver.verifying_key());
+        let proof = CustodyProof::generate(&challenge(2), &entries, &[]).unwrap();
+
+        // An altered sibling on the path
+        let mut tampered = proof.clone();
+        tampered.siblings[1][0] ^= 1;
+        let err = tampered
+            .verify(&challenge(2), &checkpoint, &approvers)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::CustodyProofInvalid);
+
+        // An entry whose data hash was rewritten to match a substituted payload
+        let mut tampered = proof.clone();
+        if let StoredEntry::Live { entry, payload } = &mut tampered.stored {
+            *payload = b"substituted".to_vec();
+            entry.data_hash = sha256(payload);
+        }
+        let err = tampered
+            .verify(&challenge(2), &checkpoint, &approvers)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::CustodyProofInvalid);
+
+        // An erased entry claiming a leaf hash that was never committed
+        let mut tampered = proof;
+        tampered.stored = StoredEntry::Erased(ErasedEntry {
+            leaf_hash: [0xEE; 32],
+            tombstone_hash: [0; 32],
+        });
+        let err = tampered
+            .verify(&challenge(2), &checkpoint, &approvers)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::CustodyProofInvalid);
+
+        // A tombstone approved for another checkpoint's root
+        let mut proof = CustodyProof::generate(&challenge(4), &entries, &[]).unwrap();
+        if let StoredEntry::Tombstoned(tombstone) = &mut proof.stored {
+            *tombstone = Tombstone::issue(
+                tombstone.entry.clone(),
+                [0xAA; 32],
+                DeletionReason::RetentionExpired,
+                &approver,
+            )
+            .unwrap();
+        }
+        let err = proof
+            .verify(&challenge(4), &checkpoint, &approvers)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::CustodyDeletionUnproven);
+    }
+}
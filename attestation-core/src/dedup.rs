@@ -0,0 +1,185 @@
+//! Deduplication of resubmitted quotes.
+//!
+//! A misconfigured agent can submit the same evidence thousands of times,
+//! and every submission would otherwise run full verification (for SGX,
+//! DCAP collateral fetches from the PCS included). A [`QuoteDeduplicator`]
+//! remembers successful results by the hash of vendor, quote and nonce for
+//! a configurable window; an
+//! [`AttestationRegistry`](crate::AttestationRegistry) configured with
+//! [`with_dedup`](crate::AttestationRegistry::with_dedup) answers repeats
+//! from it. Nonces are still consumed and freshness is still applied, so a
+//! cached result never outlives the rules a fresh one would face.
+//!
+//! Failures are not cached: a transient one should be retried, and a
+//! permanent one is cheap to reproduce. Results can also be kept in a
+//! [`QuoteResultStore`], so a restarted gateway does not verify the same
+//! evidence again.
+
+use crate::crypto::sha256;
+use crate::types::{AttestationResult, Hash256};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Persistent store of verified results, in [`AttestationResult::to_bytes`] form.
+pub trait QuoteResultStore: Send + Sync {
+    fn load(&self, key: &Hash256) -> Option<Vec<u8>>;
+    fn save(&self, key: Hash256, result: Vec<u8>);
+}
+
+/// In-memory store, for tests and single-process deployments.
+impl QuoteResultStore for Mutex<HashMap<Hash256, Vec<u8>>> {
+    fn load(&self, key: &Hash256) -> Option<Vec<u8>> {
+        self.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(key)
+            .cloned()
+    }
+
+    fn save(&self, key: Hash256, result: Vec<u8>) {
+        self.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key, result);
+    }
+}
+
+/// Remembers verified quotes for a window after their verification.
+pub struct QuoteDeduplicator {
+    window: Duration,
+    max_entries: usize,
+    cache: Mutex<HashMap<Hash256, AttestationResult>>,
+    store: Option<Arc<dyn QuoteResultStore>>,
+}
+
+impl QuoteDeduplicator {
+    /// Default bound on results held in memory
+    pub const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+    /// Reuse results for `window` after they were verified.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            max_entries: Self::DEFAULT_MAX_ENTRIES,
+            cache: Mutex::new(HashMap::new()),
+            store: None,
+        }
+    }
+
+    /// Hold at most `max_entries` results in memory.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Keep results in `store` as well, and fall back to it on a memory miss.
+    pub fn with_store(mut self, store: Arc<dyn QuoteResultStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Deduplication key of a submission.
+    pub fn key(vendor: &str, quote: &[u8], nonce: Option<&[u8]>) -> Hash256 {
+        let mut data = Vec::with_capacity(16 + vendor.len() + quote.len());
+        for part in [vendor.as_bytes(), quote, nonce.unwrap_or_default()] {
+            data.extend_from_slice(&(part.len() as u64).to_be_bytes());
+            data.extend_from_slice(part);
+        }
+        sha256(&data)
+    }
+
+    /// The result for `key`, if it was verified within the window before `now`.
+    pub fn lookup(&self, key: &Hash256, now: DateTime<Utc>) -> Option<AttestationResult> {
+        let mut cache = self.lock();
+        if let Some(result) = cache.get(key) {
+            if self.is_fresh(result, now) {
+                return Some(result.clone());
+            }
+            cache.remove(key);
+            return None;
+        }
+        let stored = self.store.as_ref()?.load(key)?;
+        let result = AttestationResult::from_bytes(&stored).ok()?;
+        if !self.is_fresh(&result, now) {
+            return None;
+        }
+        if cache.len() < self.max_entries {
+            cache.insert(*key, result.clone());
+        }
+        Some(result)
+    }
+
+    /// Remember a successful `result` under `key`.
+    pub fn record(&self, key: Hash256, result: &AttestationResult, now: DateTime<Utc>) {
+        if let (Some(store), Ok(bytes)) = (&self.store, result.to_bytes()) {
+            store.save(key, bytes);
+        }
+        let mut cache = self.lock();
+        if cache.len() >= self.max_entries {
+            cache.retain(|_, cached| now - cached.verified_at < self.window);
+        }
+        if cache.len() < self.max_entries {
+            cache.insert(key, result.clone());
+        }
+    }
+
+    /// Results held in memory.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no results are held in memory.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn is_fresh(&self, result: &AttestationResult, now: DateTime<Utc>) -> bool {
+        now - result.verified_at < self.window
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Hash256, AttestationResult>> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{RevocationCheck, RevocationSource};
+
+    #[test]
+    fn test_window_and_persistent_fallback() {
+        let now = Utc::now();
+        let result = AttestationResult {
+            vendor: "intel-sgx".to_string(),
+            enclave_measurement: vec![1u8; 32],
+            quote_verified: true,
+            verified_at: now,
+            revoke_check: RevocationCheck::ok(RevocationSource::Crl),
+            raw_quote: None,
+            pck_chain: None,
+            claims: Default::default(),
+        };
+        let key = QuoteDeduplicator::key("intel-sgx", b"quote", Some(b"nonce"));
+        assert_ne!(key, QuoteDeduplicator::key("intel-sgx", b"quote", None));
+        assert_ne!(
+            key,
+            QuoteDeduplicator::key("intel-sgxq", b"uote", Some(b"nonce"))
+        );
+
+        let store: Arc<Mutex<HashMap<Hash256, Vec<u8>>>> = Arc::default();
+        let dedup = QuoteDeduplicator::new(Duration::minutes(10)).with_store(store.clone());
+        assert!(dedup.lookup(&key, now).is_none());
+        dedup.record(key, &result, now);
+        let hash = result.compute_hash().unwrap();
+        let cached = dedup.lookup(&key, now + Duration::minutes(9)).unwrap();
+        assert_eq!(cached.compute_hash().unwrap(), hash);
+        assert!(dedup.lookup(&key, now + Duration::minutes(10)).is_none());
+        assert!(dedup.is_empty());
+
+        // A restarted process picks the result up from the store
+        let restarted = QuoteDeduplicator::new(Duration::minutes(10)).with_store(store);
+        let stored = restarted.lookup(&key, now + Duration::minutes(1)).unwrap();
+        assert_eq!(stored.compute_hash().unwrap(), hash);
+        assert_eq!(restarted.len(), 1);
+    }
+}
@@ -0,0 +1,212 @@
+//! Key delegation certificates.
+//!
+//! The enclave identity key should not sign every checkpoint: it is the key
+//! attestation vouches for and is expensive to rotate. Instead it signs a
+//! `DelegationCert` handing checkpoint signing to an operational key for one
+//! robot (optionally one mission) and a bounded time window. A
+//! [`crate::ChainVerifier`] that trusts the identity key accepts checkpoints
+//! signed by the operational key while the certificate covers them, so the
+//! operational key can rotate as often as needed without breaking the trust
+//! link. Unlike a [`crate::KeyRotationCert`], the parent key stays valid.
+
+use crate::crypto::{ct_eq, key_id, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{KeyId, MissionId, RobotId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Delegation certificate version (for schema evolution)
+pub const DELEGATION_VERSION: u8 = 1;
+
+/// What a delegated key may sign checkpoints for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DelegationScope {
+    pub robot_id: RobotId,
+    /// Restrict to one mission; any mission of the robot if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mission_id: Option<MissionId>,
+}
+
+impl DelegationScope {
+    /// Whether a checkpoint for `robot_id` / `mission_id` is in scope.
+    pub fn covers(&self, robot_id: &RobotId, mission_id: &MissionId) -> bool {
+        &self.robot_id == robot_id && self.mission_id.as_ref().is_none_or(|m| m == mission_id)
+    }
+}
+
+/// Statement by a parent key delegating checkpoint signing to a child key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DelegationCert {
+    /// Schema version
+    pub version: u8,
+    /// Fingerprint of the delegating key (the signer)
+    pub parent_key_id: KeyId,
+    /// Delegated Ed25519 verifying key
+    pub child_key: [u8; 32],
+    pub scope: DelegationScope,
+    /// Checkpoint timestamps the delegation covers (inclusive)
+    #[serde(with = "crate::serialization::timestamp")]
+    pub not_before: DateTime<Utc>,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub not_after: DateTime<Utc>,
+    /// Ed25519 signature by the parent key over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned delegation certificate (for signature computation)
+#[derive(Serialize)]
+struct UnsignedDelegation<'a> {
+    version: u8,
+    parent_key_id: KeyId,
+    child_key: [u8; 32],
+    scope: &'a DelegationScope,
+    #[serde(with = "crate::serialization::timestamp")]
+    not_before: DateTime<Utc>,
+    #[serde(with = "crate::serialization::timestamp")]
+    not_after: DateTime<Utc>,
+}
+
+impl DelegationCert {
+    /// Delegate from `parent` to `child_key` within `scope` between
+    /// `not_before` and `not_after`.
+    pub fn issue(
+        parent: &Signer,
+        child_key: &VerifyingKey,
+        scope: DelegationScope,
+        not_before: DateTime<Utc>,
+        not_after: DateTime<Utc>,
+    ) -> Result<Self, DelegationError> {
+        let mut cert = Self {
+            version: DELEGATION_VERSION,
+            parent_key_id: parent.key_id(),
+            child_key: child_key.to_bytes(),
+            scope,
+            not_before,
+            not_after,
+            signature: SignatureBytes([0u8; 64]),
+        };
+        let signature = parent.sign(&cert.signing_payload()?);
+        cert.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(cert)
+    }
+
+    /// The delegated verifying key.
+    pub fn child_verifying_key(&self) -> Result<VerifyingKey, DelegationError> {
+        VerifyingKey::from_bytes(&self.child_key).map_err(|_| DelegationError::InvalidChildKey)
+    }
+
+    /// Fingerprint of the delegated key.
+    pub fn child_key_id(&self) -> Result<KeyId, DelegationError> {
+        Ok(key_id(&self.child_verifying_key()?))
+    }
+
+    /// Whether the delegation covers a checkpoint with this robot, mission and timestamp.
+    pub fn covers(&self, robot_id: &RobotId, mission_id: &MissionId, at: DateTime<Utc>) -> bool {
+        self.scope.covers(robot_id, mission_id) && self.not_before <= at && at <= self.not_after
+    }
+
+    /// Verify the certificate signature against the parent key.
+    pub fn verify(&self, parent_key: &VerifyingKey) -> Result<(), DelegationError> {
+        use ed25519_dalek::Verifier;
+
+        if !ct_eq(&key_id(parent_key).0, &self.parent_key_id.0) || self.not_after < self.not_before
+        {
+            return Err(DelegationError::InvalidSignature);
+        }
+        self.child_verifying_key()?;
+
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        parent_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| DelegationError::InvalidSignature)
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedDelegation {
+            version: self.version,
+            parent_key_id: self.parent_key_id,
+            child_key: self.child_key,
+            scope: &self.scope,
+            not_before: self.not_before,
+            not_after: self.not_after,
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DelegationError {
+    #[error("Delegation certificate serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Delegation certificate names an invalid child key")]
+    InvalidChildKey,
+
+    #[error("Invalid delegation certificate signature")]
+    InvalidSignature,
+}
+
+impl ErrorCoded for DelegationError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            DelegationError::Serialization(e) => e.code(),
+            DelegationError::InvalidChildKey | DelegationError::InvalidSignature => {
+                ErrorCode::DelegationInvalid
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_issue_verify_roundtrip() {
+        let identity = Signer::generate();
+        let operational = Signer::generate();
+        let now = Utc::now();
+        let scope = DelegationScope {
+            robot_id: RobotId("R-001".to_string()),
+            mission_id: Some(MissionId("M-001".to_string())),
+        };
+        let cert = DelegationCert::issue(
+            &identity,
+            &operational.verifying_key(),
+            scope,
+            now,
+            now + Duration::hours(1),
+        )
+        .unwrap();
+
+        cert.verify(&identity.verifying_key()).unwrap();
+        assert_eq!(cert.child_key_id().unwrap(), operational.key_id());
+        let decoded = DelegationCert::from_bytes(&cert.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, cert);
+
+        let robot = RobotId("R-001".to_string());
+        assert!(cert.covers(&robot, &MissionId("M-001".to_string()), now));
+        assert!(!cert.covers(&robot, &MissionId("M-002".to_string()), now));
+        assert!(!cert.covers(
+            &robot,
+            &MissionId("M-001".to_string()),
+            now + Duration::hours(2)
+        ));
+
+        let err = cert.verify(&operational.verifying_key()).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::DelegationInvalid);
+    }
+}
@@ -0,0 +1,390 @@
+//! Vendor endorsements attached to attestation results.
+//!
+//! An [`Endorsement`] is a signed statement from a platform vendor or OEM
+//! about the hardware an attestation came from: a platform certificate for
+//! an FMSPC, a device statement for a specific measurement. Endorsements
+//! travel next to the evidence; [`Endorsements::attach`] checks each one
+//! against the configured endorser keys and the attestation result it is
+//! presented with, and records the outcome as claims on the result so
+//! policy evaluation sees it like any other claim:
+//!
+//! - `endorsement.<kind>.status`: text, one of [`EndorsementStatus::as_str`]
+//! - `endorsement.<kind>.<claim>`: the endorsed claims, only when the
+//!   endorsement is valid
+
+use crate::clock::{system_clock, Clock};
+use crate::crypto::Signer;
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{AttestationResult, ClaimValue, KeyId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Endorsement version (for schema evolution)
+pub const ENDORSEMENT_VERSION: u8 = 1;
+
+/// What an endorsement is about.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndorsementSubject {
+    /// The enclave measurement of the result
+    Measurement(Vec<u8>),
+    /// A claim of the result having this value (e.g., "sgx.fmspc")
+    Claim { key: String, value: ClaimValue },
+}
+
+impl EndorsementSubject {
+    /// Whether `result` is what this subject names.
+    pub fn matches(&self, result: &AttestationResult) -> bool {
+        match self {
+            EndorsementSubject::Measurement(measurement) => {
+                &result.enclave_measurement == measurement
+            }
+            EndorsementSubject::Claim { key, value } => result.claim(key) == Some(value),
+        }
+    }
+}
+
+/// A signed vendor or OEM statement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Endorsement {
+    /// Schema version
+    pub version: u8,
+    /// Kind of statement, used as the claim namespace (e.g.,
+    /// "platform_certificate", "oem_device")
+    pub kind: String,
+    pub subject: EndorsementSubject,
+    /// Endorsed claims (e.g., "security_level", "certified")
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub claims: BTreeMap<String, ClaimValue>,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub not_before: DateTime<Utc>,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub not_after: DateTime<Utc>,
+    /// Fingerprint of the endorser key
+    pub signer_key_id: KeyId,
+    /// Ed25519 signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned endorsement (for signature computation)
+#[derive(Serialize)]
+struct UnsignedEndorsement<'a> {
+    version: u8,
+    kind: &'a str,
+    subject: &'a EndorsementSubject,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    claims: &'a BTreeMap<String, ClaimValue>,
+    #[serde(with = "crate::serialization::timestamp")]
+    not_before: DateTime<Utc>,
+    #[serde(with = "crate::serialization::timestamp")]
+    not_after: DateTime<Utc>,
+    signer_key_id: KeyId,
+}
+
+impl Endorsement {
+    /// Sign a statement about `subject`, valid from `not_before` to `not_after`.
+    pub fn issue(
+        kind: &str,
+        subject: EndorsementSubject,
+        claims: BTreeMap<String, ClaimValue>,
+        not_before: DateTime<Utc>,
+        not_after: DateTime<Utc>,
+        signer: &Signer,
+    ) -> Result<Self, EndorsementError> {
+        let mut endorsement = Self {
+            version: ENDORSEMENT_VERSION,
+            kind: kind.to_string(),
+            subject,
+            claims,
+            not_before,
+            not_after,
+            signer_key_id: signer.key_id(),
+            signature: SignatureBytes([0u8; 64]),
+        };
+        let signature = signer.sign(&endorsement.signing_payload()?);
+        endorsement.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(endorsement)
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedEndorsement {
+            version: self.version,
+            kind: &self.kind,
+            subject: &self.subject,
+            claims: &self.claims,
+            not_before: self.not_before,
+            not_after: self.not_after,
+            signer_key_id: self.signer_key_id,
+        })
+    }
+}
+
+/// Outcome of checking one endorsement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndorsementStatus {
+    Valid,
+    /// Signed by a key that is not a configured endorser
+    Untrusted,
+    /// Signature does not verify
+    InvalidSignature,
+    /// Endorses something other than the presented result
+    SubjectMismatch,
+    /// Outside its validity window
+    Expired,
+}
+
+impl EndorsementStatus {
+    /// Claim value recorded under `endorsement.<kind>.status`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EndorsementStatus::Valid => "valid",
+            EndorsementStatus::Untrusted => "untrusted",
+            EndorsementStatus::InvalidSignature => "invalid_signature",
+            EndorsementStatus::SubjectMismatch => "subject_mismatch",
+            EndorsementStatus::Expired => "expired",
+        }
+    }
+
+    /// Error code for policies that require the endorsement (none if valid).
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match self {
+            EndorsementStatus::Valid => None,
+            EndorsementStatus::Untrusted => Some(ErrorCode::EndorsementUntrusted),
+            EndorsementStatus::InvalidSignature | EndorsementStatus::SubjectMismatch => {
+                Some(ErrorCode::EndorsementInvalid)
+            }
+            EndorsementStatus::Expired => Some(ErrorCode::EndorsementExpired),
+        }
+    }
+}
+
+/// Checks endorsements against the configured endorser keys.
+pub struct Endorsements {
+    endorsers: Box<dyn KeyResolver>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Endorsements {
+    /// Trust endorsements signed by keys in `endorsers`.
+    pub fn new(endorsers: Box<dyn KeyResolver>) -> Self {
+        Self {
+            endorsers,
+            clock: system_clock(),
+        }
+    }
+
+    /// Judge validity windows against `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Check `endorsement` as presented with `result`.
+    pub fn check(
+        &self,
+        endorsement: &Endorsement,
+        result: &AttestationResult,
+    ) -> EndorsementStatus {
+        use ed25519_dalek::Verifier;
+
+        let Some(key) = self.endorsers.resolve(&endorsement.signer_key_id) else {
+            return EndorsementStatus::Untrusted;
+        };
+        let signature = ed25519_dalek::Signature::from_bytes(endorsement.signature.as_ref());
+        let signed = endorsement
+            .signing_payload()
+            .is_ok_and(|payload| key.verify(&payload, &signature).is_ok());
+        if !signed {
+            return EndorsementStatus::InvalidSignature;
+        }
+        if !endorsement.subject.matches(result) {
+            return EndorsementStatus::SubjectMismatch;
+        }
+        let now = self.clock.now();
+        if now < endorsement.not_before || now > endorsement.not_after {
+            return EndorsementStatus::Expired;
+        }
+        EndorsementStatus::Valid
+    }
+
+    /// Check each endorsement and record the outcomes as claims on `result`.
+    ///
+    /// When several endorsements share a kind, a valid one wins; otherwise
+    /// the last status is recorded.
+    pub fn attach(
+        &self,
+        result: &mut AttestationResult,
+        endorsements: &[Endorsement],
+    ) -> Vec<EndorsementStatus> {
+        let statuses: Vec<EndorsementStatus> = endorsements
+            .iter()
+            .map(|endorsement| self.check(endorsement, result))
+            .collect();
+
+        for (endorsement, status) in endorsements.iter().zip(&statuses) {
+            let prefix = format!("endorsement.{}", endorsement.kind);
+            let status_key = format!("{prefix}.status");
+            let already_valid =
+                result.claim(&status_key).and_then(ClaimValue::as_str) == Some("valid");
+            if already_valid {
+                continue;
+            }
+            result
+                .claims
+                .insert(status_key, ClaimValue::Text(status.as_str().to_string()));
+            if *status == EndorsementStatus::Valid {
+                for (key, value) in &endorsement.claims {
+                    result
+                        .claims
+                        .insert(format!("{prefix}.{key}"), value.clone());
+                }
+            }
+        }
+        statuses
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum EndorsementError {
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] SerializationError),
+}
+
+impl ErrorCoded for EndorsementError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            EndorsementError::Serialization(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::keys::KeyRing;
+    use crate::types::{RevocationCheck, RevocationSource};
+
+    fn result(at: DateTime<Utc>) -> AttestationResult {
+        let mut claims = BTreeMap::new();
+        claims.insert(
+            "sgx.fmspc".to_string(),
+            ClaimValue::Text("00906ED50000".to_string()),
+        );
+        AttestationResult {
+            vendor: "intel-sgx".to_string(),
+            enclave_measurement: vec![7u8; 32],
+            quote_verified: true,
+            verified_at: at,
+            revoke_check: RevocationCheck::ok(RevocationSource::Crl),
+            raw_quote: None,
+            pck_chain: None,
+            claims,
+        }
+    }
+
+    #[test]
+    fn test_valid_endorsement_surfaces_claims() {
+        let at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let vendor = Signer::generate();
+        let mut keys = KeyRing::new();
+        keys.insert(vendor.verifying_key());
+        let endorsements =
+            Endorsements::new(Box::new(keys)).with_clock(Arc::new(MockClock::new(at)));
+
+        let platform = Endorsement::issue(
+            "platform_certificate",
+            EndorsementSubject::Claim {
+                key: "sgx.fmspc".to_string(),
+                value: ClaimValue::Text("00906ED50000".to_string()),
+            },
+            BTreeMap::from([("certified".to_string(), ClaimValue::Bool(true))]),
+            at - chrono::Duration::days(30),
+            at + chrono::Duration::days(335),
+            &vendor,
+        )
+        .unwrap();
+        let decoded = Endorsement::from_bytes(&platform.to_bytes().unwrap()).unwrap();
+
+        let mut result = result(at);
+        let statuses = endorsements.attach(&mut result, &[decoded]);
+        assert_eq!(statuses, vec![EndorsementStatus::Valid]);
+        assert_eq!(
+            result.claim("endorsement.platform_certificate.status"),
+            Some(&ClaimValue::Text("valid".to_string()))
+        );
+        assert_eq!(
+            result.claim("endorsement.platform_certificate.certified"),
+            Some(&ClaimValue::Bool(true))
+        );
+    }
+
+    #[test]
+    fn test_failed_checks_are_recorded_without_claims() {
+        let at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let vendor = Signer::generate();
+        let mut keys = KeyRing::new();
+        keys.insert(vendor.verifying_key());
+        let clock = Arc::new(MockClock::new(at));
+        let endorsements = Endorsements::new(Box::new(keys)).with_clock(clock.clone());
+        let issue = |subject: Vec<u8>, signer: &Signer| {
+            Endorsement::issue(
+                "oem_device",
+                EndorsementSubject::Measurement(subject),
+                BTreeMap::from([("model".to_string(), ClaimValue::Text("H1".to_string()))]),
+                at,
+                at + chrono::Duration::days(1),
+                signer,
+            )
+            .unwrap()
+        };
+
+        let result = result(at);
+        assert_eq!(
+            endorsements.check(&issue(vec![7u8; 32], &Signer::generate()), &result),
+            EndorsementStatus::Untrusted
+        );
+        assert_eq!(
+            endorsements.check(&issue(vec![8u8; 32], &vendor), &result),
+            EndorsementStatus::SubjectMismatch
+        );
+
+        let mut forged = issue(vec![7u8; 32], &vendor);
+        forged
+            .claims
+            .insert("model".to_string(), ClaimValue::Text("H2".to_string()));
+        assert_eq!(
+            endorsements.check(&forged, &result),
+            EndorsementStatus::InvalidSignature
+        );
+
+        clock.advance(chrono::Duration::days(2));
+        let mut expired = result.clone();
+        let statuses = endorsements.attach(&mut expired, &[issue(vec![7u8; 32], &vendor)]);
+        assert_eq!(
+            statuses[0].error_code(),
+            Some(ErrorCode::EndorsementExpired)
+        );
+        assert_eq!(
+            expired.claim("endorsement.oem_device.status"),
+            Some(&ClaimValue::Text("expired".to_string()))
+        );
+        assert_eq!(expired.claim("endorsement.oem_device.model"), None);
+    }
+}
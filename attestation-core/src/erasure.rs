@@ -0,0 +1,227 @@
+//! Data-subject erasure that keeps evidentiary integrity.
+//!
+//! A GDPR-style erasure request needs more than a [`Tombstone`]: the
+//! tombstoned entry still carries its timestamp and nonce, which can place a
+//! person at a time and location. [`erase`] deletes the selected entries'
+//! payloads and then the entries themselves from storage, keeping:
+//!
+//! - in the store, each entry's leaf hash and the hash of its tombstone
+//!   ([`StoredEntry::Erased`]), so [`verify_stored_entries`] still
+//!   reproduces the committed `entries_root`;
+//! - the tombstones, handed back for sealed retention as proof that each
+//!   deletion was approved;
+//! - a signed [`ErasureRecord`] listing what was erased under which request.
+//!
+//! [`verify_stored_entries`]: crate::tombstone::verify_stored_entries
+
+use crate::crypto::{ct_eq, Signer};
+use crate::keys::KeyResolver;
+use crate::merkle::Entry;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::tombstone::{DeletionReason, ErasedEntry, StoredEntry, Tombstone, TombstoneError};
+use crate::types::{Hash256, KeyId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Erasure record version (for schema evolution)
+pub const ERASURE_VERSION: u8 = 1;
+
+/// Signed record of one erasure request carried out on one checkpoint's entries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErasureRecord {
+    /// Schema version
+    pub version: u8,
+    /// Reference of the data-subject request (e.g., "DSR-2031")
+    pub request: String,
+    /// `entries_root` of the checkpoint whose entries were erased
+    pub entries_root: Hash256,
+    /// Erased entries, in tree order
+    pub erased: Vec<ErasedEntry>,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub erased_at: DateTime<Utc>,
+    /// Fingerprint of the approver's signing key
+    pub approver_key_id: KeyId,
+    /// Ed25519 signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned erasure record (for signature computation)
+#[derive(Serialize)]
+struct UnsignedErasureRecord<'a> {
+    version: u8,
+    request: &'a str,
+    entries_root: Hash256,
+    erased: &'a [ErasedEntry],
+    #[serde(with = "crate::serialization::timestamp")]
+    erased_at: DateTime<Utc>,
+    approver_key_id: KeyId,
+}
+
+impl ErasureRecord {
+    /// Verify the approver's signature.
+    pub fn verify(&self, approvers: &dyn KeyResolver) -> Result<(), TombstoneError> {
+        use ed25519_dalek::Verifier;
+
+        let verifying_key = approvers
+            .resolve(&self.approver_key_id)
+            .ok_or(TombstoneError::UnknownApprover(self.approver_key_id))?;
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        verifying_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| TombstoneError::InvalidSignature)
+    }
+
+    /// Whether `tombstone` is the retained proof for one of the erased entries.
+    pub fn proves(&self, tombstone: &Tombstone) -> Result<bool, SerializationError> {
+        let erased = ErasedEntry {
+            leaf_hash: tombstone.entry.hash(),
+            tombstone_hash: tombstone.compute_hash()?,
+        };
+        Ok(ct_eq(&tombstone.entries_root, &self.entries_root) && self.erased.contains(&erased))
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedErasureRecord {
+            version: self.version,
+            request: &self.request,
+            entries_root: self.entries_root,
+            erased: &self.erased,
+            erased_at: self.erased_at,
+            approver_key_id: self.approver_key_id,
+        })
+    }
+}
+
+/// Outcome of [`erase`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Erasure {
+    pub record: ErasureRecord,
+    /// Tombstones of the erased entries, to be retained outside the store
+    pub tombstones: Vec<Tombstone>,
+}
+
+/// Erase the entries `selected` picks from one checkpoint's stored entries.
+///
+/// Live entries are tombstoned under [`DeletionReason::SubjectRequest`];
+/// entries tombstoned earlier keep their tombstone. Every selected entry is
+/// then replaced by its leaf and tombstone hashes. Entries erased before are
+/// left as they are.
+pub fn erase(
+    entries: &mut [StoredEntry],
+    entries_root: Hash256,
+    request: &str,
+    selected: impl Fn(&Entry) -> bool,
+    approver: &Signer,
+) -> Result<Erasure, TombstoneError> {
+    let mut tombstones = Vec::new();
+    let mut erased = Vec::new();
+    for stored in entries.iter_mut() {
+        if !stored.entry().is_some_and(&selected) {
+            continue;
+        }
+        let reason = DeletionReason::SubjectRequest(request.to_string());
+        let StoredEntry::Tombstoned(tombstone) =
+            stored.clone().tombstone(entries_root, reason, approver)?
+        else {
+            unreachable!("selected entries are live or tombstoned");
+        };
+        let entry = ErasedEntry {
+            leaf_hash: tombstone.entry.hash(),
+            tombstone_hash: tombstone.compute_hash()?,
+        };
+        *stored = StoredEntry::Erased(entry.clone());
+        erased.push(entry);
+        tombstones.push(tombstone);
+    }
+
+    let mut record = ErasureRecord {
+        version: ERASURE_VERSION,
+        request: request.to_string(),
+        entries_root,
+        erased,
+        erased_at: Utc::now(),
+        approver_key_id: approver.key_id(),
+        signature: SignatureBytes([0u8; 64]),
+    };
+    let signature = approver.sign(&record.signing_payload()?);
+    record.signature = SignatureBytes::from(signature.to_bytes());
+    Ok(Erasure { record, tombstones })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorCode, ErrorCoded};
+    use crate::merkle::MerkleTree;
+    use crate::tombstone::verify_stored_entries;
+
+    #[test]
+    fn test_erasure_keeps_root_and_proofs() {
+        let approver = Signer::generate();
+        let mut tree = MerkleTree::new();
+        let mut entries: Vec<StoredEntry> = (0..5u64)
+            .map(|i| {
+                let payload = format!("operator {} at bay {i}", i % 2).into_bytes();
+                let entry = Entry::new(1000 + i, 0, &payload);
+                tree.insert(entry.clone());
+                StoredEntry::Live { entry, payload }
+            })
+            .collect();
+        let root = tree.root();
+        entries[3] = entries[3]
+            .clone()
+            .tombstone(root, DeletionReason::RetentionExpired, &approver)
+            .unwrap();
+
+        // Operator 1's records are entries 1 and 3 (already tombstoned)
+        let erasure = erase(
+            &mut entries,
+            root,
+            "DSR-2031",
+            |e| e.timestamp_us % 2 == 1,
+            &approver,
+        )
+        .unwrap();
+        assert!(matches!(entries[1], StoredEntry::Erased(_)));
+        assert!(matches!(entries[3], StoredEntry::Erased(_)));
+        assert!(matches!(entries[0], StoredEntry::Live { .. }));
+        verify_stored_entries(&entries, &root, &approver.verifying_key()).unwrap();
+
+        let record = ErasureRecord::from_bytes(&erasure.record.to_bytes().unwrap()).unwrap();
+        record.verify(&approver.verifying_key()).unwrap();
+        assert_eq!(record.erased.len(), 2);
+        for tombstone in &erasure.tombstones {
+            assert!(record.proves(tombstone).unwrap());
+        }
+        assert_eq!(
+            erasure.tombstones[1].reason,
+            DeletionReason::RetentionExpired
+        );
+
+        // Erasing the wrong leaf hash breaks the root
+        let mut forged = entries.clone();
+        forged[1] = StoredEntry::Erased(ErasedEntry {
+            leaf_hash: [7u8; 32],
+            tombstone_hash: [0u8; 32],
+        });
+        let err = verify_stored_entries(&forged, &root, &approver.verifying_key()).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::EntriesRootAltered);
+
+        let mut tampered = record;
+        tampered.erased.pop();
+        assert!(matches!(
+            tampered.verify(&approver.verifying_key()),
+            Err(TombstoneError::InvalidSignature)
+        ));
+    }
+}
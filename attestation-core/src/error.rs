@@ -0,0 +1,788 @@
+//! Machine-readable error codes shared across crates.
+//!
+//! Every error type in the attestation pipeline maps to a stable code
+//! (e.g., `VB-ATT-001`, `VB-CHK-004`) so operators can alert on codes
+//! instead of matching error strings.
+//!
+//! ## Code Families
+//! - `VB-ATT-*`: attestation evidence and adapters
+//! - `VB-ANC-*`: on-chain anchor confirmation tracking
+//! - `VB-AUD-*`: append-only audit log of verification decisions
+//! - `VB-CFG-*`: signed agent configuration, gateway configuration reload and rollback protection
+//! - `VB-GWY-*`: gateway tenant scoping, caller authentication, per-robot rate limits and storage quotas
+//! - `VB-QTN-*`: quarantine and review of rejected submissions
+//! - `VB-CHK-*`: checkpoint construction, signatures and chaining
+//! - `VB-RCP-*`: signed verifier receipts
+//! - `VB-CSG-*`: gateway countersigning backends and key rotation
+//! - `VB-END-*`: vendor endorsements attached to attestation results
+//! - `VB-ESC-*`: key escrow shares and threshold reconstruction
+//! - `VB-KRL-*`: attestation-gated secret release
+//! - `VB-REP-*`: replication between gateway stores
+//! - `VB-SYN-*`: differential checkpoint sync between verifiers and gateways
+//! - `VB-SPL-*`: robot-side persistent spooling of outbound evidence
+//! - `VB-HST-*`: retained root history, entry garbage collection and retention
+//! - `VB-IDX-*`: cross-checkpoint entry search index
+//! - `VB-CUS-*`: proof-of-custody challenges for archived evidence
+//! - `VB-SHD-*`: time-sharded checkpoint storage, shard roots and archival
+//! - `VB-IMP-*`: backfill of historical checkpoint archives
+//! - `VB-INV-*`: hardware inventory documents
+//! - `VB-LOG-*`: checkpoint transparency log
+//! - `VB-APR-*`: measurement transparency log of approved models and firmware
+//! - `VB-MSN-*`: mission authorization, completeness and summaries
+//! - `VB-NOT-*`: long-term evidence notarization
+//! - `VB-PKG-*`: forensic evidence packages
+//! - `VB-QRM-*`: quorum notarization across gateways
+//! - `VB-OPR-*`: operator intervention records
+//! - `VB-SUB-*`: sub-key certificates and per-entry signatures
+//! - `VB-TMB-*`: tombstoned entries and payload deletion
+//! - `VB-SER-*`: canonical serialization and on-chain ABI encoding
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Stable error code.
+///
+/// Codes are never renumbered; retired codes are not reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// VB-ATT-001: quote could not be parsed
+    InvalidQuote,
+    /// VB-ATT-002: quote or certificate chain failed verification
+    VerificationFailed,
+    /// VB-ATT-003: revocation status could not be determined
+    RevocationCheckFailed,
+    /// VB-ATT-004: enclave measurement is revoked
+    MeasurementRevoked,
+    /// VB-ATT-005: network failure talking to a collateral service
+    Network,
+    /// VB-ATT-006: no adapter registered for the vendor
+    UnsupportedVendor,
+    /// VB-ATT-007: adapter misconfiguration
+    Config,
+    /// VB-ATT-008: internal adapter error
+    Internal,
+    /// VB-ATT-009: certificate in the attestation chain is revoked
+    CertificateRevoked,
+    /// VB-ATT-010: collateral service returned an error or unusable data
+    CollateralUnavailable,
+    /// VB-ATT-011: nonce missing, unknown, expired or reused
+    NonceRejected,
+    /// VB-ATT-012: offline collateral bundle is unsigned, untrusted or expired
+    CollateralInvalid,
+    /// VB-ATT-013: quote older than the vendor freshness policy allows
+    QuoteStale,
+
+    /// VB-ANC-001: chain client failed or is unreachable
+    AnchorChainUnavailable,
+    /// VB-ANC-002: root already has a tracked anchor
+    AnchorAlreadyTracked,
+    /// VB-ANC-003: no failed anchor exists for the root
+    AnchorNotRetryable,
+    /// VB-ANC-004: anchor reached its submission limit
+    AnchorAttemptsExhausted,
+
+    /// VB-AUD-001: audit event could not be appended to the log
+    AuditSinkFailed,
+    /// VB-AUD-002: audit log has a missing, reordered or altered event
+    AuditLogBroken,
+    /// VB-AUD-003: audit event signature does not verify or its key is unknown
+    AuditEventInvalid,
+
+    /// VB-CFG-001: agent configuration signer is unknown or its signature is invalid
+    ConfigSignatureInvalid,
+    /// VB-CFG-002: agent configuration revision is older than the one in force
+    ConfigRollback,
+    /// VB-CFG-003: two different agent configurations share a revision
+    ConfigConflict,
+    /// VB-CFG-004: checkpoint references an agent configuration not on record
+    ConfigUnknown,
+    /// VB-CFG-005: gateway configuration file cannot be read or parsed
+    ConfigUnreadable,
+    /// VB-CFG-006: gateway configuration fails validation
+    ConfigInvalid,
+
+    /// VB-GWY-001: request names a tenant the gateway does not serve
+    TenantUnknown,
+    /// VB-GWY-002: robot does not belong to the requesting tenant
+    TenantRobotOutside,
+    /// VB-GWY-003: caller presented no known client certificate or API key
+    Unauthenticated,
+    /// VB-GWY-004: authenticated caller may not use the route
+    Forbidden,
+    /// VB-GWY-005: robot exceeded its request rate limit
+    RateLimited,
+    /// VB-GWY-006: robot would exceed its storage quota
+    QuotaExceeded,
+
+    /// VB-QTN-001: no quarantined submission has the given id
+    QuarantineUnknown,
+    /// VB-QTN-002: quarantined submission was already reviewed
+    QuarantineReviewed,
+
+    /// VB-CHK-001: checkpoint is missing a required field
+    MissingField,
+    /// VB-CHK-002: checkpoint could not be serialized for signing
+    SigningPayload,
+    /// VB-CHK-003: checkpoint signature is invalid
+    InvalidSignature,
+    /// VB-CHK-004: sequence number did not increase
+    SequenceRegression,
+    /// VB-CHK-005: sequence number skipped ahead
+    SequenceGap,
+    /// VB-CHK-006: monotonic counter did not increase
+    CounterRegression,
+    /// VB-CHK-007: prev_root does not match the previous checkpoint hash
+    PrevRootMismatch,
+    /// VB-CHK-008: checkpoint belongs to a different robot
+    RobotMismatch,
+    /// VB-CHK-009: first checkpoint of a chain does not start from the zero root
+    InvalidGenesis,
+    /// VB-CHK-010: no verifying key is known for the checkpoint's signer key id
+    UnknownSigningKey,
+    /// VB-CHK-011: supplied verifying key does not match the signer key id
+    KeyIdMismatch,
+    /// VB-CHK-012: key rotation certificate is malformed or badly signed
+    RotationInvalid,
+    /// VB-CHK-013: signer key was rotated out (or not yet in) at this sequence
+    KeyNotValidAtSequence,
+    /// VB-CHK-014: checkpoint timestamp did not increase
+    TimestampRegression,
+    /// VB-CHK-015: checkpoint timestamp is too far from the gateway receive time
+    ClockSkewExceeded,
+    /// VB-CHK-016: clock skew decision is malformed or badly signed
+    SkewDecisionInvalid,
+    /// VB-CHK-017: a mission started before the open mission ended
+    MissionNotEnded,
+    /// VB-CHK-018: checkpoint belongs to a different mission than the open one
+    MissionIdMismatch,
+    /// VB-CHK-019: mission-end checkpoint does not link to the mission's start
+    MissionLinkBroken,
+    /// VB-CHK-020: delegation certificate is malformed or badly signed
+    DelegationInvalid,
+    /// VB-CHK-021: delegated key signed outside its scope or validity window
+    DelegationOutOfScope,
+    /// VB-CHK-022: checkpoint trust mode is below the required minimum
+    TrustModeBelowMinimum,
+    /// VB-CHK-023: heartbeat checkpoint commits to entries or a mission event
+    HeartbeatPayload,
+    /// VB-CHK-024: gap record is badly signed or names an empty range
+    GapRecordInvalid,
+    /// VB-CHK-025: sequence gap is justified, but not by a reason or length the gap policy allows
+    GapNotPermitted,
+
+    /// VB-RCP-001: receipt does not cover the presented attestation result
+    ReceiptResultMismatch,
+    /// VB-RCP-002: receipt was signed by an unknown verifier
+    ReceiptUnknownVerifier,
+    /// VB-RCP-003: receipt signature is invalid
+    ReceiptInvalidSignature,
+
+    /// VB-CSG-001: signing backend (HSM, KMS) failed or is unavailable
+    CountersignBackendFailed,
+    /// VB-CSG-002: signing backend signed with a key other than the active key
+    CountersignKeyMismatch,
+    /// VB-CSG-003: rotation target key is already active or was retired
+    CountersignKeyReused,
+
+    /// VB-END-001: endorsement is signed by a key that is not a configured endorser
+    EndorsementUntrusted,
+    /// VB-END-002: endorsement signature is invalid or it endorses a different subject
+    EndorsementInvalid,
+    /// VB-END-003: endorsement is expired or not yet valid
+    EndorsementExpired,
+
+    /// VB-ESC-001: share threshold or count is out of range
+    EscrowInvalidParameters,
+    /// VB-ESC-002: key share is malformed or its signature does not verify
+    EscrowShareInvalid,
+    /// VB-ESC-003: key shares come from different splits or repeat an index
+    EscrowSharesMismatched,
+    /// VB-ESC-004: fewer key shares than the threshold were presented
+    EscrowNotEnoughShares,
+    /// VB-ESC-005: reconstructed key does not match the escrowed public key
+    EscrowKeyMismatch,
+
+    /// VB-KRL-001: no secret is configured under the requested id
+    ReleaseSecretUnknown,
+    /// VB-KRL-002: attested measurement is not allowed the secret or differs from the checkpoint
+    ReleaseMeasurementDenied,
+    /// VB-KRL-003: evidence does not bind the enclave wrapping key and nonce
+    ReleaseKeyNotBound,
+    /// VB-KRL-004: checkpoint head presented for release is too old
+    ReleaseCheckpointStale,
+    /// VB-KRL-005: wrapped secret could not be decrypted
+    SecretUnwrapFailed,
+
+    /// VB-REP-001: fork evidence does not show two signed checkpoints for one sequence
+    ForkEvidenceInvalid,
+
+    /// VB-SYN-001: checkpoint the client holds is not in the server's chain
+    SyncHeadMismatch,
+    /// VB-SYN-002: sync response lacks the tree head or consistency proof the client needs
+    SyncProofMissing,
+
+    /// VB-SPL-001: spool is at its record or byte limit; the item was not enqueued
+    SpoolFull,
+    /// VB-SPL-002: spool directory could not be read or written
+    SpoolStorageFailed,
+    /// VB-SPL-003: no pending spool record has the given id
+    SpoolRecordUnknown,
+
+    /// VB-HST-001: retained entries do not reproduce the checkpoint's entries_root
+    HistoryRootMismatch,
+    /// VB-HST-002: checkpoint is not newer than the last retained root
+    HistoryOutOfOrder,
+    /// VB-HST-003: retired root is not provable from the root history
+    HistoryUnprovable,
+
+    /// VB-IDX-001: indexed entries do not reproduce the checkpoint entries_root
+    IndexRootMismatch,
+
+    /// VB-CUS-001: archive did not answer a custody challenge
+    CustodyUnanswered,
+    /// VB-CUS-002: archive does not hold the challenged entry
+    CustodyEntryMissing,
+    /// VB-CUS-003: custody proof does not reproduce the challenged checkpoint's entries_root
+    CustodyProofInvalid,
+    /// VB-CUS-004: returned payload does not match the entry's data hash
+    CustodyPayloadMismatch,
+    /// VB-CUS-005: deleted entry lacks an approved tombstone for this checkpoint
+    CustodyDeletionUnproven,
+    /// VB-CUS-006: archive claims no entries of the audited checkpoints
+    CustodyNothingClaimed,
+    /// VB-CUS-007: archive does not claim a checkpoint the auditor holds
+    CustodyCheckpointUnclaimed,
+    /// VB-CUS-008: checkpoint has entries past the count the archive claims
+    CustodyEntriesUnclaimed,
+
+    /// VB-SHD-001: no shard holds the requested window or checkpoint
+    ShardUnknown,
+    /// VB-SHD-002: shard contents do not reproduce its recorded root
+    ShardRootMismatch,
+    /// VB-SHD-003: stored entries do not reproduce the checkpoint's entries_root
+    ShardEntriesMismatch,
+
+    /// VB-IMP-001: archive to import holds no checkpoints
+    ImportEmptyArchive,
+    /// VB-IMP-002: archive starts after a gap in the stored chain
+    ImportMissingPredecessor,
+
+    /// VB-INV-001: inventory was signed by an unknown key
+    InventoryUnknownSigner,
+    /// VB-INV-002: inventory signature is invalid
+    InventoryInvalidSignature,
+    /// VB-INV-003: checkpoint does not reference a hardware inventory
+    InventoryMissing,
+    /// VB-INV-004: referenced hardware inventory is not on record
+    InventoryUnknown,
+    /// VB-INV-005: inventory belongs to a different robot
+    InventoryRobotMismatch,
+
+    /// VB-LOG-001: tree head was signed by an unknown log key
+    LogUnknownSigner,
+    /// VB-LOG-002: tree head signature is invalid
+    LogInvalidSignature,
+    /// VB-LOG-003: inclusion proof does not match the tree head
+    InclusionProofInvalid,
+    /// VB-LOG-004: consistency proof does not link the tree heads (split view)
+    ConsistencyProofInvalid,
+    /// VB-LOG-005: requested index or tree size is beyond the log
+    LogIndexOutOfRange,
+    /// VB-LOG-006: checkpoint timestamp receipt covers a different checkpoint
+    LogTimestampMismatch,
+    /// VB-LOG-007: checkpoint was not merged within the promised delay
+    LogMergeDelayExceeded,
+    /// VB-LOG-008: tree heads offered as equivocation evidence do not conflict
+    NotEquivocation,
+
+    /// VB-APR-001: checkpoint model or firmware was not approved at the checkpoint time
+    ArtifactNotApproved,
+    /// VB-APR-002: approval proof names a different artifact or time than it is offered for
+    ApprovalProofInvalid,
+    /// VB-APR-003: published approvals do not reproduce the signed tree head
+    ApprovalLogMismatch,
+
+    /// VB-MSN-001: mission authorization is unknown, badly signed or mismatched
+    MissionAuthorizationInvalid,
+    /// VB-MSN-002: checkpoints do not run from a mission start to a mission end
+    MissionIncomplete,
+    /// VB-MSN-003: mission-end checkpoint does not commit to the summary
+    SummaryNotCommitted,
+    /// VB-MSN-004: summary disagrees with sampled entries
+    SummaryInconsistent,
+    /// VB-NOT-001: notarization layer does not match the evidence
+    NotarizationEvidenceMismatch,
+    /// VB-NOT-002: notarization layers are missing, reordered or unlinked
+    NotarizationBrokenLink,
+    /// VB-NOT-003: notarization layer was signed by an unknown notary
+    NotarizationUnknownNotary,
+    /// VB-NOT-004: notarization layer signature is invalid
+    NotarizationInvalidSignature,
+    /// VB-NOT-005: a layer's algorithms were retired before it was renewed
+    NotarizationAlgorithmRetired,
+
+    /// VB-PKG-001: package was assembled by an unknown key or is badly signed
+    PackageSignatureInvalid,
+    /// VB-PKG-002: package evidence does not belong to its checkpoint
+    PackageEvidenceMismatch,
+    /// VB-PKG-003: package is empty or its entry proofs leave out part of the window
+    PackageIncomplete,
+    /// VB-QRM-001: fewer trusted gateways logged the checkpoint than required
+    QuorumNotReached,
+    /// VB-QRM-002: gateway receipt is for a different checkpoint
+    QuorumCheckpointMismatch,
+    /// VB-QRM-003: gateway logged a chain's checkpoints out of order
+    QuorumOutOfOrder,
+    /// VB-QRM-004: gateway could not be reached or rejected the submission
+    GatewayUnavailable,
+
+    /// VB-OPR-001: operator key is not in the fleet registry
+    OperatorUnknown,
+    /// VB-OPR-002: operator key is registered to a different operator
+    OperatorIdentityMismatch,
+    /// VB-OPR-003: operator action signature invalid or entry does not commit to it
+    OperatorActionInvalid,
+
+    /// VB-SUB-001: sub-key certificate is malformed or not signed by the checkpoint signer
+    SubKeyCertInvalid,
+    /// VB-SUB-002: checkpoint does not commit to the sub-key certificate
+    SubKeyCertNotCommitted,
+    /// VB-SUB-003: attributed entry is not in the checkpoint's entries_root
+    SubKeyEntryNotCommitted,
+    /// VB-SUB-004: entry falls outside the sub-key certificate's validity
+    SubKeyOutsideValidity,
+    /// VB-SUB-005: entry signature does not verify under the sub-key
+    EntrySignatureInvalid,
+
+    /// VB-TMB-001: tombstone was approved by an unknown key
+    TombstoneUnknownApprover,
+    /// VB-TMB-002: tombstone signature is invalid
+    TombstoneInvalidSignature,
+    /// VB-TMB-003: stored payload does not match its entry's data hash
+    PayloadMismatch,
+    /// VB-TMB-004: stored entries no longer reproduce the committed root
+    EntriesRootAltered,
+
+    /// VB-SER-001: CBOR encoding failed
+    Encode,
+    /// VB-SER-002: CBOR decoding failed
+    Decode,
+    /// VB-SER-003: bytes are not in canonical form
+    NonCanonical,
+    /// VB-SER-004: value has no representation in the on-chain ABI layout
+    AbiEncode,
+    /// VB-SER-005: a known-answer test vector disagrees with this implementation
+    KnownAnswerMismatch,
+    /// VB-SER-006: stored document has a schema version this implementation does not know
+    UnsupportedVersion,
+    /// VB-SER-007: serialized value exceeds its transport size budget
+    BudgetExceeded,
+}
+
+impl ErrorCode {
+    /// All defined codes.
+    pub const ALL: &'static [ErrorCode] = &[
+        ErrorCode::InvalidQuote,
+        ErrorCode::VerificationFailed,
+        ErrorCode::RevocationCheckFailed,
+        ErrorCode::MeasurementRevoked,
+        ErrorCode::Network,
+        ErrorCode::UnsupportedVendor,
+        ErrorCode::Config,
+        ErrorCode::Internal,
+        ErrorCode::CertificateRevoked,
+        ErrorCode::CollateralUnavailable,
+        ErrorCode::NonceRejected,
+        ErrorCode::CollateralInvalid,
+        ErrorCode::QuoteStale,
+        ErrorCode::AnchorChainUnavailable,
+        ErrorCode::AnchorAlreadyTracked,
+        ErrorCode::AnchorNotRetryable,
+        ErrorCode::AnchorAttemptsExhausted,
+        ErrorCode::AuditSinkFailed,
+        ErrorCode::AuditLogBroken,
+        ErrorCode::AuditEventInvalid,
+        ErrorCode::ConfigSignatureInvalid,
+        ErrorCode::ConfigRollback,
+        ErrorCode::ConfigConflict,
+        ErrorCode::ConfigUnknown,
+        ErrorCode::ConfigUnreadable,
+        ErrorCode::ConfigInvalid,
+        ErrorCode::TenantUnknown,
+        ErrorCode::TenantRobotOutside,
+        ErrorCode::Unauthenticated,
+        ErrorCode::Forbidden,
+        ErrorCode::RateLimited,
+        ErrorCode::QuotaExceeded,
+        ErrorCode::QuarantineUnknown,
+        ErrorCode::QuarantineReviewed,
+        ErrorCode::MissingField,
+        ErrorCode::SigningPayload,
+        ErrorCode::InvalidSignature,
+        ErrorCode::SequenceRegression,
+        ErrorCode::SequenceGap,
+        ErrorCode::CounterRegression,
+        ErrorCode::PrevRootMismatch,
+        ErrorCode::RobotMismatch,
+        ErrorCode::InvalidGenesis,
+        ErrorCode::UnknownSigningKey,
+        ErrorCode::KeyIdMismatch,
+        ErrorCode::RotationInvalid,
+        ErrorCode::KeyNotValidAtSequence,
+        ErrorCode::TimestampRegression,
+        ErrorCode::ClockSkewExceeded,
+        ErrorCode::SkewDecisionInvalid,
+        ErrorCode::MissionNotEnded,
+        ErrorCode::MissionIdMismatch,
+        ErrorCode::MissionLinkBroken,
+        ErrorCode::DelegationInvalid,
+        ErrorCode::DelegationOutOfScope,
+        ErrorCode::TrustModeBelowMinimum,
+        ErrorCode::HeartbeatPayload,
+        ErrorCode::GapRecordInvalid,
+        ErrorCode::GapNotPermitted,
+        ErrorCode::ReceiptResultMismatch,
+        ErrorCode::ReceiptUnknownVerifier,
+        ErrorCode::ReceiptInvalidSignature,
+        ErrorCode::CountersignBackendFailed,
+        ErrorCode::CountersignKeyMismatch,
+        ErrorCode::CountersignKeyReused,
+        ErrorCode::EndorsementUntrusted,
+        ErrorCode::EndorsementInvalid,
+        ErrorCode::EndorsementExpired,
+        ErrorCode::EscrowInvalidParameters,
+        ErrorCode::EscrowShareInvalid,
+        ErrorCode::EscrowSharesMismatched,
+        ErrorCode::EscrowNotEnoughShares,
+        ErrorCode::EscrowKeyMismatch,
+        ErrorCode::ReleaseSecretUnknown,
+        ErrorCode::ReleaseMeasurementDenied,
+        ErrorCode::ReleaseKeyNotBound,
+        ErrorCode::ReleaseCheckpointStale,
+        ErrorCode::SecretUnwrapFailed,
+        ErrorCode::ForkEvidenceInvalid,
+        ErrorCode::SyncHeadMismatch,
+        ErrorCode::SyncProofMissing,
+        ErrorCode::SpoolFull,
+        ErrorCode::SpoolStorageFailed,
+        ErrorCode::SpoolRecordUnknown,
+        ErrorCode::HistoryRootMismatch,
+        ErrorCode::HistoryOutOfOrder,
+        ErrorCode::HistoryUnprovable,
+        ErrorCode::IndexRootMismatch,
+        ErrorCode::CustodyUnanswered,
+        ErrorCode::CustodyEntryMissing,
+        ErrorCode::CustodyProofInvalid,
+        ErrorCode::CustodyPayloadMismatch,
+        ErrorCode::CustodyDeletionUnproven,
+        ErrorCode::CustodyNothingClaimed,
+        ErrorCode::CustodyCheckpointUnclaimed,
+        ErrorCode::CustodyEntriesUnclaimed,
+        ErrorCode::ShardUnknown,
+        ErrorCode::ShardRootMismatch,
+        ErrorCode::ShardEntriesMismatch,
+        ErrorCode::ImportEmptyArchive,
+        ErrorCode::ImportMissingPredecessor,
+        ErrorCode::InventoryUnknownSigner,
+        ErrorCode::InventoryInvalidSignature,
+        ErrorCode::InventoryMissing,
+        ErrorCode::InventoryUnknown,
+        ErrorCode::InventoryRobotMismatch,
+        ErrorCode::LogUnknownSigner,
+        ErrorCode::LogInvalidSignature,
+        ErrorCode::InclusionProofInvalid,
+        ErrorCode::ConsistencyProofInvalid,
+        ErrorCode::LogIndexOutOfRange,
+        ErrorCode::LogTimestampMismatch,
+        ErrorCode::LogMergeDelayExceeded,
+        ErrorCode::NotEquivocation,
+        ErrorCode::ArtifactNotApproved,
+        ErrorCode::ApprovalProofInvalid,
+        ErrorCode::ApprovalLogMismatch,
+        ErrorCode::MissionAuthorizationInvalid,
+        ErrorCode::MissionIncomplete,
+        ErrorCode::SummaryNotCommitted,
+        ErrorCode::SummaryInconsistent,
+        ErrorCode::NotarizationEvidenceMismatch,
+        ErrorCode::NotarizationBrokenLink,
+        ErrorCode::NotarizationUnknownNotary,
+        ErrorCode::NotarizationInvalidSignature,
+        ErrorCode::NotarizationAlgorithmRetired,
+        ErrorCode::PackageSignatureInvalid,
+        ErrorCode::PackageEvidenceMismatch,
+        ErrorCode::PackageIncomplete,
+        ErrorCode::QuorumNotReached,
+        ErrorCode::QuorumCheckpointMismatch,
+        ErrorCode::QuorumOutOfOrder,
+        ErrorCode::GatewayUnavailable,
+        ErrorCode::OperatorUnknown,
+        ErrorCode::OperatorIdentityMismatch,
+        ErrorCode::OperatorActionInvalid,
+        ErrorCode::SubKeyCertInvalid,
+        ErrorCode::SubKeyCertNotCommitted,
+        ErrorCode::SubKeyEntryNotCommitted,
+        ErrorCode::SubKeyOutsideValidity,
+        ErrorCode::EntrySignatureInvalid,
+        ErrorCode::TombstoneUnknownApprover,
+        ErrorCode::TombstoneInvalidSignature,
+        ErrorCode::PayloadMismatch,
+        ErrorCode::EntriesRootAltered,
+        ErrorCode::Encode,
+        ErrorCode::Decode,
+        ErrorCode::NonCanonical,
+        ErrorCode::AbiEncode,
+        ErrorCode::KnownAnswerMismatch,
+        ErrorCode::UnsupportedVersion,
+        ErrorCode::BudgetExceeded,
+    ];
+
+    /// The stable string form of this code (e.g., "VB-CHK-004").
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidQuote => "VB-ATT-001",
+            ErrorCode::VerificationFailed => "VB-ATT-002",
+            ErrorCode::RevocationCheckFailed => "VB-ATT-003",
+            ErrorCode::MeasurementRevoked => "VB-ATT-004",
+            ErrorCode::Network => "VB-ATT-005",
+            ErrorCode::UnsupportedVendor => "VB-ATT-006",
+            ErrorCode::Config => "VB-ATT-007",
+            ErrorCode::Internal => "VB-ATT-008",
+            ErrorCode::CertificateRevoked => "VB-ATT-009",
+            ErrorCode::CollateralUnavailable => "VB-ATT-010",
+            ErrorCode::NonceRejected => "VB-ATT-011",
+            ErrorCode::CollateralInvalid => "VB-ATT-012",
+            ErrorCode::QuoteStale => "VB-ATT-013",
+            ErrorCode::AnchorChainUnavailable => "VB-ANC-001",
+            ErrorCode::AnchorAlreadyTracked => "VB-ANC-002",
+            ErrorCode::AnchorNotRetryable => "VB-ANC-003",
+            ErrorCode::AnchorAttemptsExhausted => "VB-ANC-004",
+            ErrorCode::AuditSinkFailed => "VB-AUD-001",
+            ErrorCode::AuditLogBroken => "VB-AUD-002",
+            ErrorCode::AuditEventInvalid => "VB-AUD-003",
+            ErrorCode::ConfigSignatureInvalid => "VB-CFG-001",
+            ErrorCode::ConfigRollback => "VB-CFG-002",
+            ErrorCode::ConfigConflict => "VB-CFG-003",
+            ErrorCode::ConfigUnknown => "VB-CFG-004",
+            ErrorCode::ConfigUnreadable => "VB-CFG-005",
+            ErrorCode::ConfigInvalid => "VB-CFG-006",
+            ErrorCode::TenantUnknown => "VB-GWY-001",
+            ErrorCode::TenantRobotOutside => "VB-GWY-002",
+            ErrorCode::Unauthenticated => "VB-GWY-003",
+            ErrorCode::Forbidden => "VB-GWY-004",
+            ErrorCode::RateLimited => "VB-GWY-005",
+            ErrorCode::QuotaExceeded => "VB-GWY-006",
+            ErrorCode::QuarantineUnknown => "VB-QTN-001",
+            ErrorCode::QuarantineReviewed => "VB-QTN-002",
+            ErrorCode::MissingField => "VB-CHK-001",
+            ErrorCode::SigningPayload => "VB-CHK-002",
+            ErrorCode::InvalidSignature => "VB-CHK-003",
+            ErrorCode::SequenceRegression => "VB-CHK-004",
+            ErrorCode::SequenceGap => "VB-CHK-005",
+            ErrorCode::CounterRegression => "VB-CHK-006",
+            ErrorCode::PrevRootMismatch => "VB-CHK-007",
+            ErrorCode::RobotMismatch => "VB-CHK-008",
+            ErrorCode::InvalidGenesis => "VB-CHK-009",
+            ErrorCode::UnknownSigningKey => "VB-CHK-010",
+            ErrorCode::KeyIdMismatch => "VB-CHK-011",
+            ErrorCode::RotationInvalid => "VB-CHK-012",
+            ErrorCode::KeyNotValidAtSequence => "VB-CHK-013",
+            ErrorCode::TimestampRegression => "VB-CHK-014",
+            ErrorCode::ClockSkewExceeded => "VB-CHK-015",
+            ErrorCode::SkewDecisionInvalid => "VB-CHK-016",
+            ErrorCode::MissionNotEnded => "VB-CHK-017",
+            ErrorCode::MissionIdMismatch => "VB-CHK-018",
+            ErrorCode::MissionLinkBroken => "VB-CHK-019",
+            ErrorCode::DelegationInvalid => "VB-CHK-020",
+            ErrorCode::DelegationOutOfScope => "VB-CHK-021",
+            ErrorCode::TrustModeBelowMinimum => "VB-CHK-022",
+            ErrorCode::HeartbeatPayload => "VB-CHK-023",
+            ErrorCode::GapRecordInvalid => "VB-CHK-024",
+            ErrorCode::GapNotPermitted => "VB-CHK-025",
+            ErrorCode::ReceiptResultMismatch => "VB-RCP-001",
+            ErrorCode::ReceiptUnknownVerifier => "VB-RCP-002",
+            ErrorCode::ReceiptInvalidSignature => "VB-RCP-003",
+            ErrorCode::CountersignBackendFailed => "VB-CSG-001",
+            ErrorCode::CountersignKeyMismatch => "VB-CSG-002",
+            ErrorCode::CountersignKeyReused => "VB-CSG-003",
+            ErrorCode::EndorsementUntrusted => "VB-END-001",
+            ErrorCode::EndorsementInvalid => "VB-END-002",
+            ErrorCode::EndorsementExpired => "VB-END-003",
+            ErrorCode::EscrowInvalidParameters => "VB-ESC-001",
+            ErrorCode::EscrowShareInvalid => "VB-ESC-002",
+            ErrorCode::EscrowSharesMismatched => "VB-ESC-003",
+            ErrorCode::EscrowNotEnoughShares => "VB-ESC-004",
+            ErrorCode::EscrowKeyMismatch => "VB-ESC-005",
+            ErrorCode::ReleaseSecretUnknown => "VB-KRL-001",
+            ErrorCode::ReleaseMeasurementDenied => "VB-KRL-002",
+            ErrorCode::ReleaseKeyNotBound => "VB-KRL-003",
+            ErrorCode::ReleaseCheckpointStale => "VB-KRL-004",
+            ErrorCode::SecretUnwrapFailed => "VB-KRL-005",
+            ErrorCode::ForkEvidenceInvalid => "VB-REP-001",
+            ErrorCode::SyncHeadMismatch => "VB-SYN-001",
+            ErrorCode::SyncProofMissing => "VB-SYN-002",
+            ErrorCode::SpoolFull => "VB-SPL-001",
+            ErrorCode::SpoolStorageFailed => "VB-SPL-002",
+            ErrorCode::SpoolRecordUnknown => "VB-SPL-003",
+            ErrorCode::HistoryRootMismatch => "VB-HST-001",
+            ErrorCode::HistoryOutOfOrder => "VB-HST-002",
+            ErrorCode::HistoryUnprovable => "VB-HST-003",
+            ErrorCode::IndexRootMismatch => "VB-IDX-001",
+            ErrorCode::CustodyUnanswered => "VB-CUS-001",
+            ErrorCode::CustodyEntryMissing => "VB-CUS-002",
+            ErrorCode::CustodyProofInvalid => "VB-CUS-003",
+            ErrorCode::CustodyPayloadMismatch => "VB-CUS-004",
+            ErrorCode::CustodyDeletionUnproven => "VB-CUS-005",
+            ErrorCode::CustodyNothingClaimed => "VB-CUS-006",
+            ErrorCode::CustodyCheckpointUnclaimed => "VB-CUS-007",
+            ErrorCode::CustodyEntriesUnclaimed => "VB-CUS-008",
+            ErrorCode::ShardUnknown => "VB-SHD-001",
+            ErrorCode::ShardRootMismatch => "VB-SHD-002",
+            ErrorCode::ShardEntriesMismatch => "VB-SHD-003",
+            ErrorCode::ImportEmptyArchive => "VB-IMP-001",
+            ErrorCode::ImportMissingPredecessor => "VB-IMP-002",
+            ErrorCode::InventoryUnknownSigner => "VB-INV-001",
+            ErrorCode::InventoryInvalidSignature => "VB-INV-002",
+            ErrorCode::InventoryMissing => "VB-INV-003",
+            ErrorCode::InventoryUnknown => "VB-INV-004",
+            ErrorCode::InventoryRobotMismatch => "VB-INV-005",
+            ErrorCode::LogUnknownSigner => "VB-LOG-001",
+            ErrorCode::LogInvalidSignature => "VB-LOG-002",
+            ErrorCode::InclusionProofInvalid => "VB-LOG-003",
+            ErrorCode::ConsistencyProofInvalid => "VB-LOG-004",
+            ErrorCode::LogIndexOutOfRange => "VB-LOG-005",
+            ErrorCode::LogTimestampMismatch => "VB-LOG-006",
+            ErrorCode::LogMergeDelayExceeded => "VB-LOG-007",
+            ErrorCode::NotEquivocation => "VB-LOG-008",
+            ErrorCode::ArtifactNotApproved => "VB-APR-001",
+            ErrorCode::ApprovalProofInvalid => "VB-APR-002",
+            ErrorCode::ApprovalLogMismatch => "VB-APR-003",
+            ErrorCode::MissionAuthorizationInvalid => "VB-MSN-001",
+            ErrorCode::MissionIncomplete => "VB-MSN-002",
+            ErrorCode::SummaryNotCommitted => "VB-MSN-003",
+            ErrorCode::SummaryInconsistent => "VB-MSN-004",
+            ErrorCode::NotarizationEvidenceMismatch => "VB-NOT-001",
+            ErrorCode::NotarizationBrokenLink => "VB-NOT-002",
+            ErrorCode::NotarizationUnknownNotary => "VB-NOT-003",
+            ErrorCode::NotarizationInvalidSignature => "VB-NOT-004",
+            ErrorCode::NotarizationAlgorithmRetired => "VB-NOT-005",
+            ErrorCode::PackageSignatureInvalid => "VB-PKG-001",
+            ErrorCode::PackageEvidenceMismatch => "VB-PKG-002",
+            ErrorCode::PackageIncomplete => "VB-PKG-003",
+            ErrorCode::QuorumNotReached => "VB-QRM-001",
+            ErrorCode::QuorumCheckpointMismatch => "VB-QRM-002",
+            ErrorCode::QuorumOutOfOrder => "VB-QRM-003",
+            ErrorCode::GatewayUnavailable => "VB-QRM-004",
+            ErrorCode::OperatorUnknown => "VB-OPR-001",
+            ErrorCode::OperatorIdentityMismatch => "VB-OPR-002",
+            ErrorCode::OperatorActionInvalid => "VB-OPR-003",
+            ErrorCode::SubKeyCertInvalid => "VB-SUB-001",
+            ErrorCode::SubKeyCertNotCommitted => "VB-SUB-002",
+            ErrorCode::SubKeyEntryNotCommitted => "VB-SUB-003",
+            ErrorCode::SubKeyOutsideValidity => "VB-SUB-004",
+            ErrorCode::EntrySignatureInvalid => "VB-SUB-005",
+            ErrorCode::TombstoneUnknownApprover => "VB-TMB-001",
+            ErrorCode::TombstoneInvalidSignature => "VB-TMB-002",
+            ErrorCode::PayloadMismatch => "VB-TMB-003",
+            ErrorCode::EntriesRootAltered => "VB-TMB-004",
+            ErrorCode::Encode => "VB-SER-001",
+            ErrorCode::Decode => "VB-SER-002",
+            ErrorCode::NonCanonical => "VB-SER-003",
+            ErrorCode::AbiEncode => "VB-SER-004",
+            ErrorCode::KnownAnswerMismatch => "VB-SER-005",
+            ErrorCode::UnsupportedVersion => "VB-SER-006",
+            ErrorCode::BudgetExceeded => "VB-SER-007",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ErrorCode {
+    type Err = UnknownErrorCode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ErrorCode::ALL
+            .iter()
+            .copied()
+            .find(|code| code.as_str() == s)
+            .ok_or_else(|| UnknownErrorCode(s.to_string()))
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Returned when parsing a string that is not a known error code.
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown error code: {0}")]
+pub struct UnknownErrorCode(pub String);
+
+/// Errors that carry a stable machine-readable code.
+pub trait ErrorCoded: fmt::Display {
+    /// The stable code for this error.
+    fn code(&self) -> ErrorCode;
+
+    /// Serializable detail suitable for API responses.
+    fn detail(&self) -> ErrorDetail {
+        ErrorDetail {
+            code: self.code(),
+            message: self.to_string(),
+        }
+    }
+}
+
+/// Serializable error detail for API responses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorDetail {
+    /// Stable error code
+    pub code: ErrorCode,
+    /// Human-readable message (not stable; do not match on it)
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_codes_are_unique() {
+        let codes: HashSet<&str> = ErrorCode::ALL.iter().map(|c| c.as_str()).collect();
+        assert_eq!(codes.len(), ErrorCode::ALL.len());
+    }
+
+    #[test]
+    fn test_code_string_roundtrip() {
+        for code in ErrorCode::ALL {
+            assert_eq!(code.as_str().parse::<ErrorCode>().unwrap(), *code);
+        }
+        assert!("VB-XXX-999".parse::<ErrorCode>().is_err());
+    }
+
+    #[test]
+    fn test_error_detail_serialization() {
+        let detail = ErrorDetail {
+            code: ErrorCode::SequenceRegression,
+            message: "Sequence regression".to_string(),
+        };
+
+        let bytes = crate::serialization::to_canonical_cbor(&detail).unwrap();
+        let decoded: ErrorDetail = crate::serialization::from_canonical_cbor(&bytes).unwrap();
+        assert_eq!(decoded, detail);
+    }
+}
@@ -0,0 +1,341 @@
+//! Key escrow by Shamir secret sharing.
+//!
+//! Fleet root and gateway signing keys are split into `total` shares of
+//! which any `threshold` rebuild the key, so disaster recovery needs several
+//! custodians and no single one ever holds the whole key. Sharing is done
+//! byte-wise over GF(2^8) on the 32-byte Ed25519 seed.
+//!
+//! Each [`KeyShare`] is signed by the key it is a share of. A custodian can
+//! therefore check their share against the escrowed public key at any time
+//! ([`KeyShare::verify`]) without learning anything about the key, and
+//! [`reconstruct_key`] refuses corrupted or foreign shares before combining
+//! them, then checks the result against the public key.
+
+use crate::crypto::{key_id, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, KeyId, SignatureBytes};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Key share version (for schema evolution)
+pub const SHARE_VERSION: u8 = 1;
+
+/// One custodian's share of an escrowed key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyShare {
+    /// Schema version
+    pub version: u8,
+    /// Fingerprint of the escrowed key (and of the key that signed this share)
+    pub key_id: KeyId,
+    /// Random identifier of the split; shares of different splits never combine
+    pub split_id: Hash256,
+    /// Shares needed to rebuild the key
+    pub threshold: u8,
+    /// Shares issued
+    pub total: u8,
+    /// Evaluation point, 1 to `total`
+    pub index: u8,
+    /// Share bytes
+    pub value: [u8; 32],
+    /// Ed25519 signature by the escrowed key over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned share (for signature computation)
+#[derive(Serialize)]
+struct UnsignedShare<'a> {
+    version: u8,
+    key_id: KeyId,
+    split_id: &'a Hash256,
+    threshold: u8,
+    total: u8,
+    index: u8,
+    value: &'a [u8; 32],
+}
+
+impl KeyShare {
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedShare {
+            version: self.version,
+            key_id: self.key_id,
+            split_id: &self.split_id,
+            threshold: self.threshold,
+            total: self.total,
+            index: self.index,
+            value: &self.value,
+        })
+    }
+
+    /// Check that this share was issued for `public_key` and is intact.
+    pub fn verify(&self, public_key: &VerifyingKey) -> Result<(), EscrowError> {
+        use ed25519_dalek::Verifier;
+
+        let well_formed = self.version == SHARE_VERSION
+            && self.index >= 1
+            && self.index <= self.total
+            && self.threshold >= 1
+            && self.threshold <= self.total;
+        if !well_formed || self.key_id != key_id(public_key) {
+            return Err(EscrowError::InvalidShare { index: self.index });
+        }
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        public_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| EscrowError::InvalidShare { index: self.index })
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+}
+
+/// Split `key` into `total` shares, any `threshold` of which rebuild it.
+pub fn split_key(key: &SigningKey, threshold: u8, total: u8) -> Result<Vec<KeyShare>, EscrowError> {
+    split_key_with_rng(key, threshold, total, &mut rand::rngs::OsRng)
+}
+
+/// [`split_key`] drawing polynomial coefficients from `rng`.
+pub fn split_key_with_rng<R: RngCore + CryptoRng>(
+    key: &SigningKey,
+    threshold: u8,
+    total: u8,
+    rng: &mut R,
+) -> Result<Vec<KeyShare>, EscrowError> {
+    if threshold < 2 {
+        return Err(EscrowError::InvalidParameters(
+            "threshold must be at least 2",
+        ));
+    }
+    if total < threshold {
+        return Err(EscrowError::InvalidParameters(
+            "total must be at least the threshold",
+        ));
+    }
+
+    let secret = key.to_bytes();
+    let signer = Signer::new(key.clone());
+    let mut split_id = [0u8; 32];
+    rng.fill_bytes(&mut split_id);
+
+    // coefficients[byte][degree]; degree 0 is the secret byte
+    let mut coefficients = vec![[0u8; 255]; secret.len()];
+    for (byte, poly) in secret.iter().zip(coefficients.iter_mut()) {
+        poly[0] = *byte;
+        rng.fill_bytes(&mut poly[1..threshold as usize]);
+    }
+
+    let mut shares = Vec::with_capacity(total as usize);
+    for index in 1..=total {
+        let mut value = [0u8; 32];
+        for (out, poly) in value.iter_mut().zip(&coefficients) {
+            *out = evaluate(&poly[..threshold as usize], index);
+        }
+        let mut share = KeyShare {
+            version: SHARE_VERSION,
+            key_id: signer.key_id(),
+            split_id,
+            threshold,
+            total,
+            index,
+            value,
+            signature: SignatureBytes([0u8; 64]),
+        };
+        share.signature = SignatureBytes::from(signer.sign(&share.signing_payload()?).to_bytes());
+        shares.push(share);
+    }
+    coefficients.iter_mut().for_each(|poly| poly.fill(0));
+    Ok(shares)
+}
+
+/// Rebuild the key escrowed for `public_key` from at least `threshold` shares.
+///
+/// Every share is verified first; extra shares beyond the threshold are
+/// ignored.
+pub fn reconstruct_key(
+    shares: &[KeyShare],
+    public_key: &VerifyingKey,
+) -> Result<SigningKey, EscrowError> {
+    let first = shares
+        .first()
+        .ok_or(EscrowError::NotEnoughShares { have: 0, need: 2 })?;
+    for (i, share) in shares.iter().enumerate() {
+        share.verify(public_key)?;
+        let same_split = share.split_id == first.split_id
+            && share.threshold == first.threshold
+            && share.total == first.total;
+        if !same_split || shares[..i].iter().any(|other| other.index == share.index) {
+            return Err(EscrowError::MismatchedShares);
+        }
+    }
+    let need = first.threshold as usize;
+    if shares.len() < need {
+        return Err(EscrowError::NotEnoughShares {
+            have: shares.len(),
+            need,
+        });
+    }
+
+    let points = &shares[..need];
+    let mut secret = [0u8; 32];
+    for (i, share) in points.iter().enumerate() {
+        // Lagrange basis polynomial of this share, evaluated at 0
+        let mut basis = 1u8;
+        for (j, other) in points.iter().enumerate() {
+            if i != j {
+                basis = gf_mul(basis, gf_div(other.index, other.index ^ share.index));
+            }
+        }
+        for (out, y) in secret.iter_mut().zip(&share.value) {
+            *out ^= gf_mul(basis, *y);
+        }
+    }
+
+    let key = SigningKey::from_bytes(&secret);
+    secret.fill(0);
+    if key.verifying_key() != *public_key {
+        return Err(EscrowError::KeyMismatch);
+    }
+    Ok(key)
+}
+
+/// Horner evaluation of `poly` (lowest degree first) at `x`.
+fn evaluate(poly: &[u8], x: u8) -> u8 {
+    poly.iter()
+        .rev()
+        .fold(0, |acc, coefficient| gf_mul(acc, x) ^ coefficient)
+}
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1, without
+/// data-dependent branches.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+    product
+}
+
+/// `a / b` in GF(2^8); `b` is never zero here (share indices are distinct).
+fn gf_div(a: u8, b: u8) -> u8 {
+    // b^254 is the inverse of b
+    let mut inverse = 1u8;
+    let mut power = b;
+    for bit in 0..8 {
+        if (254 >> bit) & 1 == 1 {
+            inverse = gf_mul(inverse, power);
+        }
+        power = gf_mul(power, power);
+    }
+    gf_mul(a, inverse)
+}
+
+#[derive(Debug, Error)]
+pub enum EscrowError {
+    #[error("Invalid split parameters: {0}")]
+    InvalidParameters(&'static str),
+
+    #[error("Key share {index} is malformed or not signed by the escrowed key")]
+    InvalidShare { index: u8 },
+
+    #[error("Key shares come from different splits or repeat an index")]
+    MismatchedShares,
+
+    #[error("{have} key shares presented, {need} needed")]
+    NotEnoughShares { have: usize, need: usize },
+
+    #[error("Reconstructed key does not match the escrowed public key")]
+    KeyMismatch,
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] SerializationError),
+}
+
+impl ErrorCoded for EscrowError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            EscrowError::InvalidParameters(_) => ErrorCode::EscrowInvalidParameters,
+            EscrowError::InvalidShare { .. } => ErrorCode::EscrowShareInvalid,
+            EscrowError::MismatchedShares => ErrorCode::EscrowSharesMismatched,
+            EscrowError::NotEnoughShares { .. } => ErrorCode::EscrowNotEnoughShares,
+            EscrowError::KeyMismatch => ErrorCode::EscrowKeyMismatch,
+            EscrowError::Serialization(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_any_threshold_subset_rebuilds_key() {
+        let key = SigningKey::from_bytes(&[42u8; 32]);
+        let public = key.verifying_key();
+        let shares = split_key_with_rng(&key, 3, 5, &mut StdRng::seed_from_u64(7)).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert!(shares.iter().all(|share| share.verify(&public).is_ok()));
+
+        for picks in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let subset: Vec<KeyShare> = picks.iter().map(|&i| shares[i].clone()).collect();
+            assert_eq!(
+                reconstruct_key(&subset, &public).unwrap().to_bytes(),
+                key.to_bytes()
+            );
+        }
+
+        let err = reconstruct_key(&shares[..2], &public).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::EscrowNotEnoughShares);
+
+        let roundtrip = KeyShare::from_bytes(&shares[0].to_bytes().unwrap()).unwrap();
+        assert_eq!(roundtrip, shares[0]);
+    }
+
+    #[test]
+    fn test_corrupted_and_foreign_shares_rejected() {
+        let key = SigningKey::from_bytes(&[42u8; 32]);
+        let public = key.verifying_key();
+        let mut rng = StdRng::seed_from_u64(7);
+        let shares = split_key_with_rng(&key, 2, 3, &mut rng).unwrap();
+
+        let mut corrupted = shares.clone();
+        corrupted[1].value[0] ^= 1;
+        let err = reconstruct_key(&corrupted, &public).unwrap_err();
+        assert!(matches!(err, EscrowError::InvalidShare { index: 2 }));
+
+        // A share of another split of the same key does not mix in
+        let other_split = split_key_with_rng(&key, 2, 3, &mut rng).unwrap();
+        let mixed = vec![shares[0].clone(), other_split[1].clone()];
+        let err = reconstruct_key(&mixed, &public).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::EscrowSharesMismatched);
+
+        let duplicated = vec![shares[0].clone(), shares[0].clone()];
+        assert!(matches!(
+            reconstruct_key(&duplicated, &public),
+            Err(EscrowError::MismatchedShares)
+        ));
+
+        let stranger = SigningKey::from_bytes(&[7u8; 32]).verifying_key();
+        assert!(matches!(
+            shares[0].verify(&stranger),
+            Err(EscrowError::InvalidShare { index: 1 })
+        ));
+        assert_eq!(
+            split_key(&key, 1, 3).unwrap_err().code(),
+            ErrorCode::EscrowInvalidParameters
+        );
+    }
+}
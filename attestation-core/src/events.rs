@@ -0,0 +1,310 @@
+//! Live stream of gateway events for dashboards.
+//!
+//! Dashboards follow the fleet without polling: the ingestion path
+//! [publishes](EventHub::publish) a [`GatewayEvent`] for every accepted
+//! checkpoint, rejected submission and quarantined submission, and each
+//! WebSocket or SSE connection holds a [`Subscription`] filtered to one
+//! robot, one tenant or the whole fleet. The host authorizes the
+//! connection first ([`Route::ReadRobot`](crate::auth::Route) or
+//! [`Route::ReadFleet`](crate::auth::Route)) and writes each event as
+//!
+//! - WebSocket: one text frame of [`GatewayEvent::to_json`]
+//! - SSE: [`GatewayEvent::to_sse`], whose `id` lets a reconnecting client
+//!   see what it missed (events are not replayed)
+//!
+//! Publishing never blocks ingestion. Each subscription buffers a bounded
+//! number of events; a subscriber that falls behind is disconnected and
+//! sees [`Subscription::lagged`], and subscribers that went away are
+//! dropped on the next publish.
+
+use crate::clock::{system_clock, Clock};
+use crate::error::ErrorDetail;
+use crate::reload::LiveConfig;
+use crate::types::{Hash256, RobotId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// What happened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    CheckpointAccepted {
+        sequence: u64,
+        /// Hex checkpoint hash
+        checkpoint_hash: String,
+    },
+    SubmissionRejected {
+        failure: ErrorDetail,
+    },
+    Quarantined {
+        quarantine_id: u64,
+        failure: ErrorDetail,
+    },
+}
+
+impl EventKind {
+    pub fn checkpoint_accepted(sequence: u64, checkpoint_hash: &Hash256) -> Self {
+        EventKind::CheckpointAccepted {
+            sequence,
+            checkpoint_hash: hex::encode(checkpoint_hash),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            EventKind::CheckpointAccepted { .. } => "checkpoint_accepted",
+            EventKind::SubmissionRejected { .. } => "submission_rejected",
+            EventKind::Quarantined { .. } => "quarantined",
+        }
+    }
+}
+
+/// One event of one robot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GatewayEvent {
+    /// Position in the hub's stream, from 0
+    pub index: u64,
+    pub robot_id: RobotId,
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl GatewayEvent {
+    /// JSON text, for a WebSocket frame or an SSE data line.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("events serialize to JSON")
+    }
+
+    /// The event as an SSE message.
+    pub fn to_sse(&self) -> String {
+        format!(
+            "id: {}\nevent: {}\ndata: {}\n\n",
+            self.index,
+            self.kind.name(),
+            self.to_json()
+        )
+    }
+}
+
+/// Which robots a subscription follows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventFilter {
+    Robot(RobotId),
+    /// Robots of the tenant, as configured when each event is published
+    Tenant(String),
+    Fleet,
+}
+
+struct Subscriber {
+    filter: EventFilter,
+    sender: SyncSender<GatewayEvent>,
+    lagged: Arc<AtomicBool>,
+}
+
+/// Fans gateway events out to subscriptions.
+pub struct EventHub {
+    live: Arc<LiveConfig>,
+    clock: Arc<dyn Clock>,
+    capacity: usize,
+    state: Mutex<HubState>,
+}
+
+struct HubState {
+    next_index: u64,
+    subscribers: Vec<Subscriber>,
+}
+
+impl EventHub {
+    /// Hub resolving tenant filters by `live`, buffering `capacity` events
+    /// (at least one) per subscription.
+    pub fn new(live: Arc<LiveConfig>, capacity: usize) -> Self {
+        Self {
+            live,
+            clock: system_clock(),
+            capacity: capacity.max(1),
+            state: Mutex::new(HubState {
+                next_index: 0,
+                subscribers: Vec::new(),
+            }),
+        }
+    }
+
+    /// Timestamp events by `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Follow the events `filter` selects from now on.
+    pub fn subscribe(&self, filter: EventFilter) -> Subscription {
+        let (sender, receiver) = mpsc::sync_channel(self.capacity);
+        let lagged = Arc::new(AtomicBool::new(false));
+        self.lock().subscribers.push(Subscriber {
+            filter,
+            sender,
+            lagged: lagged.clone(),
+        });
+        Subscription { receiver, lagged }
+    }
+
+    /// Deliver an event of `robot_id` to every matching subscription, and
+    /// return it.
+    pub fn publish(&self, robot_id: RobotId, kind: EventKind) -> GatewayEvent {
+        let config = self.live.snapshot();
+        let tenant = config.tenant_of(&robot_id).map(|tenant| tenant.id.clone());
+        let mut state = self.lock();
+        let event = GatewayEvent {
+            index: state.next_index,
+            robot_id,
+            at: self.clock.now(),
+            kind,
+        };
+        state.next_index += 1;
+        state.subscribers.retain(|subscriber| {
+            let selected = match &subscriber.filter {
+                EventFilter::Robot(robot_id) => *robot_id == event.robot_id,
+                EventFilter::Tenant(id) => tenant.as_ref() == Some(id),
+                EventFilter::Fleet => true,
+            };
+            if !selected {
+                return true;
+            }
+            match subscriber.sender.try_send(event.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    subscriber.lagged.store(true, Ordering::Relaxed);
+                    false
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            }
+        });
+        event
+    }
+
+    /// Subscriptions still connected.
+    pub fn subscribers(&self) -> usize {
+        self.lock().subscribers.len()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HubState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// One connection's view of the stream.
+pub struct Subscription {
+    receiver: Receiver<GatewayEvent>,
+    lagged: Arc<AtomicBool>,
+}
+
+impl Subscription {
+    /// The next buffered event, if any.
+    pub fn try_next(&self) -> Option<GatewayEvent> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Wait for the next event; `None` once the subscription was dropped by
+    /// the hub and its buffer drained.
+    pub fn next(&self) -> Option<GatewayEvent> {
+        self.receiver.recv().ok()
+    }
+
+    /// Whether the hub disconnected this subscription for falling behind.
+    pub fn lagged(&self) -> bool {
+        self.lagged.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::TrustRequirements;
+    use crate::clock::MockClock;
+    use crate::error::ErrorCode;
+    use crate::reload::{AuthConfig, GatewayConfig, Tenant, TrustAnchors};
+
+    fn hub(capacity: usize) -> EventHub {
+        let config = GatewayConfig {
+            revision: 1,
+            trust: TrustRequirements::default(),
+            trust_anchors: TrustAnchors::default(),
+            rate_limit: None,
+            quota: None,
+            tenants: vec![Tenant {
+                id: "acme".to_string(),
+                robots: vec![RobotId("R-001".to_string())],
+                signing_keys: Vec::new(),
+                rate_limit: None,
+                quota: None,
+                trust: None,
+            }],
+            auth: AuthConfig::default(),
+        };
+        let clock = MockClock::new(
+            DateTime::parse_from_rfc3339("2024-01-15T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+        );
+        EventHub::new(Arc::new(LiveConfig::new(config).unwrap()), capacity)
+            .with_clock(Arc::new(clock))
+    }
+
+    #[test]
+    fn test_subscriptions_receive_only_their_robots_events() {
+        let hub = hub(8);
+        let (r1, r2) = (RobotId("R-001".to_string()), RobotId("R-002".to_string()));
+        let robot = hub.subscribe(EventFilter::Robot(r2.clone()));
+        let tenant = hub.subscribe(EventFilter::Tenant("acme".to_string()));
+        let fleet = hub.subscribe(EventFilter::Fleet);
+
+        hub.publish(r1.clone(), EventKind::checkpoint_accepted(0, &[0xAB; 32]));
+        let failure = ErrorDetail {
+            code: ErrorCode::SequenceRegression,
+            message: "Sequence regression".to_string(),
+        };
+        hub.publish(r2.clone(), EventKind::SubmissionRejected { failure });
+
+        assert_eq!(robot.try_next().unwrap().index, 1);
+        assert!(robot.try_next().is_none());
+        let accepted = tenant.try_next().unwrap();
+        assert_eq!(accepted.robot_id, r1);
+        assert!(tenant.try_next().is_none());
+        assert_eq!(fleet.try_next().unwrap(), accepted);
+        assert_eq!(fleet.try_next().unwrap().robot_id, r2);
+
+        // Wire forms for WebSocket and SSE clients
+        let json: serde_json::Value = serde_json::from_str(&accepted.to_json()).unwrap();
+        assert_eq!(json["type"], "checkpoint_accepted");
+        assert_eq!(json["checkpoint_hash"], hex::encode([0xAB; 32]));
+        assert!(accepted
+            .to_sse()
+            .starts_with("id: 0\nevent: checkpoint_accepted\ndata: {"));
+        assert!(accepted.to_sse().ends_with("}\n\n"));
+    }
+
+    #[test]
+    fn test_slow_and_closed_subscribers_are_dropped() {
+        let hub = hub(1);
+        let robot = RobotId("R-001".to_string());
+        let slow = hub.subscribe(EventFilter::Fleet);
+        let closed = hub.subscribe(EventFilter::Fleet);
+        drop(closed);
+        let keeping_up = hub.subscribe(EventFilter::Fleet);
+
+        hub.publish(robot.clone(), EventKind::checkpoint_accepted(0, &[0; 32]));
+        assert_eq!(hub.subscribers(), 2);
+        assert!(keeping_up.try_next().is_some());
+        hub.publish(robot, EventKind::checkpoint_accepted(1, &[1; 32]));
+
+        // Publishing did not block on the full buffer
+        assert_eq!(hub.subscribers(), 1);
+        assert!(slow.lagged());
+        assert_eq!(slow.next().unwrap().index, 0);
+        assert!(slow.next().is_none());
+        assert!(!keeping_up.lagged());
+    }
+}
@@ -0,0 +1,536 @@
+//! Forensic evidence packages.
+//!
+//! When an incident is investigated, the operator hands over a
+//! [`ForensicPackage`]: everything needed to check what a robot logged in a
+//! time window, without access to the operator's systems. For each relevant
+//! checkpoint it holds the checkpoint itself, a multiproof for the entries
+//! logged in the window, the attestation results (and verifier receipts) for
+//! the enclave that signed it, and the on-chain anchor receipts for its
+//! entries root. The package is signed by whoever assembled it.
+//!
+//! Entry proofs also include the nearest entry on either side of the window,
+//! so an investigator can tell that no entry in the window was left out.
+
+use crate::chain::{ChainError, ChainHead, ChainVerifier};
+use crate::checkpoint::Checkpoint;
+use crate::crypto::{ct_eq, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::merkle::{MerkleMultiProof, MerkleTree};
+use crate::receipt::{AttestationReceipt, ReceiptError};
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{AttestationResult, Hash256, KeyId, RobotId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Package version (for schema evolution)
+pub const PACKAGE_VERSION: u8 = 1;
+
+/// Where a checkpoint's entries root was anchored on chain.
+///
+/// Investigators confirm the receipt against the chain itself; the package
+/// only checks that it names the checkpoint's entries root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnchorReceipt {
+    pub chain_id: u64,
+    /// Address of the `RobotAttestationRegistry` contract
+    pub registry: [u8; 20],
+    pub transaction_hash: Hash256,
+    pub block_number: u64,
+    pub block_timestamp: u64,
+    /// `checkpointId` emitted by the registry
+    pub checkpoint_id: Hash256,
+    /// Anchored Merkle root (the checkpoint's `entries_root`)
+    pub merkle_root: Hash256,
+}
+
+/// An attestation result with the verifier receipts issued for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttestedResult {
+    pub result: AttestationResult,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub receipts: Vec<AttestationReceipt>,
+}
+
+/// Evidence for one checkpoint in a package.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointEvidence {
+    pub checkpoint: Checkpoint,
+    /// Entries logged in the window plus their bounding neighbours
+    /// (absent when the checkpoint has none in the window)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entries: Option<MerkleMultiProof>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attestations: Vec<AttestedResult>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anchors: Vec<AnchorReceipt>,
+}
+
+/// Source of the evidence a package is assembled from.
+pub trait EvidenceStore {
+    /// The robot's checkpoints in sequence order.
+    fn checkpoints(&self, robot_id: &RobotId) -> Vec<Checkpoint>;
+
+    /// The entries committed by `checkpoint`, if they are retained.
+    fn entries(&self, checkpoint: &Checkpoint) -> Option<MerkleTree>;
+
+    /// Attestation results for the enclave that signed `checkpoint`.
+    fn attestations(&self, checkpoint: &Checkpoint) -> Vec<AttestedResult>;
+
+    /// Anchor receipts for `checkpoint`'s entries root.
+    fn anchors(&self, checkpoint: &Checkpoint) -> Vec<AnchorReceipt>;
+}
+
+/// A signed, self-contained bundle of evidence for one robot and time window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForensicPackage {
+    /// Schema version
+    pub version: u8,
+    pub robot_id: RobotId,
+    /// Start of the window (inclusive)
+    #[serde(with = "crate::serialization::timestamp")]
+    pub from: DateTime<Utc>,
+    /// End of the window (inclusive)
+    #[serde(with = "crate::serialization::timestamp")]
+    pub to: DateTime<Utc>,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub assembled_at: DateTime<Utc>,
+    /// Consecutive checkpoints covering the window
+    pub checkpoints: Vec<CheckpointEvidence>,
+    /// Fingerprint of the assembler's signing key
+    pub assembler_key_id: KeyId,
+    /// Ed25519 signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned package (for signature computation)
+#[derive(Serialize)]
+struct UnsignedPackage<'a> {
+    version: u8,
+    robot_id: &'a RobotId,
+    #[serde(with = "crate::serialization::timestamp")]
+    from: DateTime<Utc>,
+    #[serde(with = "crate::serialization::timestamp")]
+    to: DateTime<Utc>,
+    #[serde(with = "crate::serialization::timestamp")]
+    assembled_at: DateTime<Utc>,
+    checkpoints: &'a [CheckpointEvidence],
+    assembler_key_id: KeyId,
+}
+
+impl ForensicPackage {
+    /// Assemble and sign the package for `robot_id` between `from` and `to`.
+    ///
+    /// A checkpoint is relevant if it was cut in the window or commits an
+    /// entry logged in it; the package holds every checkpoint from the first
+    /// relevant one to the last, so the chain between them can be checked.
+    pub fn assemble(
+        store: &dyn EvidenceStore,
+        robot_id: &RobotId,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        assembler: &Signer,
+    ) -> Result<Self, ForensicError> {
+        let window = micros(from)..=micros(to);
+        let mut evidence: Vec<CheckpointEvidence> = Vec::new();
+        let mut last_relevant = None;
+
+        for checkpoint in store.checkpoints(robot_id) {
+            let entries = store.entries(&checkpoint).and_then(|tree| {
+                let all = tree.entries();
+                let first = all.iter().position(|e| window.contains(&e.timestamp_us))?;
+                let last = all.iter().rposition(|e| window.contains(&e.timestamp_us))?;
+                let bounded = first.saturating_sub(1)..=(last + 1).min(all.len() - 1);
+                let keys: Vec<_> = all[bounded]
+                    .iter()
+                    .map(|e| (e.timestamp_us, e.nonce))
+                    .collect();
+                tree.generate_multiproof(&keys)
+            });
+            let relevant =
+                entries.is_some() || (from..=to).contains(&checkpoint.local_timestamp_utc);
+            if !relevant && evidence.is_empty() {
+                continue;
+            }
+
+            evidence.push(CheckpointEvidence {
+                entries,
+                attestations: store.attestations(&checkpoint),
+                anchors: store.anchors(&checkpoint),
+                checkpoint,
+            });
+            if relevant {
+                last_relevant = Some(evidence.len());
+            }
+        }
+        evidence.truncate(last_relevant.unwrap_or(0));
+        if evidence.is_empty() {
+            return Err(ForensicError::Empty);
+        }
+
+        let mut package = Self {
+            version: PACKAGE_VERSION,
+            robot_id: robot_id.clone(),
+            from,
+            to,
+            assembled_at: Utc::now(),
+            checkpoints: evidence,
+            assembler_key_id: assembler.key_id(),
+            signature: SignatureBytes([0u8; 64]),
+        };
+        let signature = assembler.sign(&package.signing_payload()?);
+        package.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(package)
+    }
+
+    /// Verify the package.
+    ///
+    /// Checks the assembler's signature (`authorities` resolves assembler
+    /// and attestation verifier keys), that the checkpoints form an unbroken
+    /// chain under `robot_keys`, that every entry proof is complete for the
+    /// window, and that attestation results, receipts and anchors belong to
+    /// their checkpoint.
+    pub fn verify(
+        &self,
+        robot_keys: Box<dyn KeyResolver>,
+        authorities: &dyn KeyResolver,
+    ) -> Result<(), ForensicError> {
+        use ed25519_dalek::Verifier;
+
+        let verifying_key = authorities
+            .resolve(&self.assembler_key_id)
+            .ok_or(ForensicError::UnknownAssembler(self.assembler_key_id))?;
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        verifying_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| ForensicError::InvalidSignature)?;
+
+        let first = &self
+            .checkpoints
+            .first()
+            .ok_or(ForensicError::Empty)?
+            .checkpoint;
+        if first.robot_id != self.robot_id {
+            return Err(ForensicError::EvidenceMismatch {
+                sequence: first.sequence,
+                reason: "checkpoint is for a different robot",
+            });
+        }
+        first
+            .verify_with_resolver(robot_keys.as_ref())
+            .map_err(ChainError::from)?;
+        let mut chain = ChainVerifier::with_resolver(robot_keys)
+            .resume_from(ChainHead::from_checkpoint(first)?);
+        for evidence in &self.checkpoints[1..] {
+            chain.verify_next(&evidence.checkpoint)?;
+        }
+
+        for evidence in &self.checkpoints {
+            self.verify_evidence(evidence, authorities)?;
+        }
+        Ok(())
+    }
+
+    fn verify_evidence(
+        &self,
+        evidence: &CheckpointEvidence,
+        authorities: &dyn KeyResolver,
+    ) -> Result<(), ForensicError> {
+        let checkpoint = &evidence.checkpoint;
+        let mismatch = |reason| ForensicError::EvidenceMismatch {
+            sequence: checkpoint.sequence,
+            reason,
+        };
+
+        if let Some(proof) = &evidence.entries {
+            if proof.hash_alg != checkpoint.hash_alg || !proof.verify(&checkpoint.entries_root) {
+                return Err(mismatch("entry proof does not match the entries root"));
+            }
+            let window = micros(self.from)..=micros(self.to);
+            let inside: Vec<usize> = proof
+                .leaves
+                .iter()
+                .filter(|(_, entry)| window.contains(&entry.timestamp_us))
+                .map(|(index, _)| *index)
+                .collect();
+            let (Some(&first), Some(&last)) = (inside.first(), inside.last()) else {
+                return Err(mismatch("entry proof holds no entries in the window"));
+            };
+            let lower = first.saturating_sub(1);
+            let upper = (last + 1).min(proof.leaf_count - 1);
+            let proven: Vec<usize> = proof.leaves.iter().map(|(index, _)| *index).collect();
+            if proven != (lower..=upper).collect::<Vec<_>>() {
+                return Err(ForensicError::Incomplete {
+                    sequence: checkpoint.sequence,
+                });
+            }
+        }
+
+        for attested in &evidence.attestations {
+            if attested.result.enclave_measurement != checkpoint.enclave_measurement {
+                return Err(mismatch("attestation result is for a different enclave"));
+            }
+            for receipt in &attested.receipts {
+                receipt.verify(&attested.result, authorities)?;
+            }
+        }
+
+        if evidence
+            .anchors
+            .iter()
+            .any(|anchor| !ct_eq(&anchor.merkle_root, &checkpoint.entries_root))
+        {
+            return Err(mismatch("anchor receipt is for a different entries root"));
+        }
+        Ok(())
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedPackage {
+            version: self.version,
+            robot_id: &self.robot_id,
+            from: self.from,
+            to: self.to,
+            assembled_at: self.assembled_at,
+            checkpoints: &self.checkpoints,
+            assembler_key_id: self.assembler_key_id,
+        })
+    }
+}
+
+/// Entry timestamp (microseconds since the Unix epoch) for `at`.
+fn micros(at: DateTime<Utc>) -> u64 {
+    at.timestamp_micros().max(0) as u64
+}
+
+#[derive(Debug, Error)]
+pub enum ForensicError {
+    #[error("Package serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("No evidence for the robot in the requested window")]
+    Empty,
+
+    #[error("Package assembled by unknown key {0}")]
+    UnknownAssembler(KeyId),
+
+    #[error("Invalid package signature")]
+    InvalidSignature,
+
+    #[error("Evidence for checkpoint {sequence} does not match: {reason}")]
+    EvidenceMismatch { sequence: u64, reason: &'static str },
+
+    #[error("Entry proof for checkpoint {sequence} does not cover the whole window")]
+    Incomplete { sequence: u64 },
+
+    #[error("Package chain error: {0}")]
+    Chain(#[from] ChainError),
+
+    #[error("Package receipt error: {0}")]
+    Receipt(#[from] ReceiptError),
+}
+
+impl ErrorCoded for ForensicError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ForensicError::Serialization(e) => e.code(),
+            ForensicError::Empty | ForensicError::Incomplete { .. } => ErrorCode::PackageIncomplete,
+            ForensicError::UnknownAssembler(_) | ForensicError::InvalidSignature => {
+                ErrorCode::PackageSignatureInvalid
+            }
+            ForensicError::EvidenceMismatch { .. } => ErrorCode::PackageEvidenceMismatch,
+            ForensicError::Chain(e) => e.code(),
+            ForensicError::Receipt(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::keys::KeyRing;
+    use crate::merkle::Entry;
+    use crate::types::{RevocationCheck, RevocationSource};
+    use chrono::{Duration, TimeZone};
+    use std::collections::HashMap;
+
+    /// Checkpoints one minute apart from `t0`, each committing entries
+    /// logged every 10 seconds during the preceding minute.
+    struct Store {
+        checkpoints: Vec<Checkpoint>,
+        trees: HashMap<Hash256, Vec<Entry>>,
+    }
+
+    fn t0() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap()
+    }
+
+    impl Store {
+        fn new(robot: &Signer, count: u64) -> Self {
+            let mut checkpoints: Vec<Checkpoint> = Vec::new();
+            let mut trees = HashMap::new();
+            for sequence in 0..count {
+                let cut = t0() + Duration::minutes(sequence as i64 + 1);
+                let entries: Vec<Entry> = (1..=6)
+                    .map(|i| {
+                        let at = cut - Duration::seconds(60 - 10 * i);
+                        Entry::new(micros(at), 0, format!("{sequence}/{i}").as_bytes())
+                    })
+                    .collect();
+                let mut tree = MerkleTree::new();
+                for entry in &entries {
+                    tree.insert(entry.clone());
+                }
+
+                let builder = match checkpoints.last() {
+                    Some(prev) => CheckpointBuilder::continuing_from(prev).unwrap(),
+                    None => CheckpointBuilder::test_fixture(),
+                };
+                let checkpoint = builder
+                    .monotonic_counter(sequence + 1)
+                    .timestamp(cut)
+                    .entries_root(tree.root())
+                    .build_and_sign(robot.signing_key())
+                    .unwrap();
+                trees.insert(checkpoint.entries_root, entries);
+                checkpoints.push(checkpoint);
+            }
+            Self { checkpoints, trees }
+        }
+    }
+
+    impl EvidenceStore for Store {
+        fn checkpoints(&self, robot_id: &RobotId) -> Vec<Checkpoint> {
+            self.checkpoints
+                .iter()
+                .filter(|c| &c.robot_id == robot_id)
+                .cloned()
+                .collect()
+        }
+
+        fn entries(&self, checkpoint: &Checkpoint) -> Option<MerkleTree> {
+            let mut tree = MerkleTree::new();
+            for entry in self.trees.get(&checkpoint.entries_root)? {
+                tree.insert(entry.clone());
+            }
+            Some(tree)
+        }
+
+        fn attestations(&self, checkpoint: &Checkpoint) -> Vec<AttestedResult> {
+            vec![AttestedResult {
+                result: AttestationResult {
+                    vendor: "intel-sgx".to_string(),
+                    enclave_measurement: checkpoint.enclave_measurement.clone(),
+                    quote_verified: true,
+                    verified_at: checkpoint.local_timestamp_utc,
+                    revoke_check: RevocationCheck::ok(RevocationSource::Crl),
+                    raw_quote: None,
+                    pck_chain: None,
+                    claims: Default::default(),
+                },
+                receipts: Vec::new(),
+            }]
+        }
+
+        fn anchors(&self, checkpoint: &Checkpoint) -> Vec<AnchorReceipt> {
+            vec![AnchorReceipt {
+                chain_id: 1,
+                registry: [0x11; 20],
+                transaction_hash: [0x22; 32],
+                block_number: 100 + checkpoint.sequence,
+                block_timestamp: checkpoint.local_timestamp_utc.timestamp() as u64 + 12,
+                checkpoint_id: [0x33; 32],
+                merkle_root: checkpoint.entries_root,
+            }]
+        }
+    }
+
+    #[test]
+    fn test_assemble_and_verify() {
+        let (robot, assembler) = (Signer::generate(), Signer::generate());
+        let store = Store::new(&robot, 5);
+
+        // 12:01:25 - 12:02:35 spans the second and third checkpoints' entries
+        let from = t0() + Duration::seconds(85);
+        let to = t0() + Duration::seconds(155);
+        let package =
+            ForensicPackage::assemble(&store, &RobotId("R-001".to_string()), from, to, &assembler)
+                .unwrap();
+        let sequences: Vec<u64> = package
+            .checkpoints
+            .iter()
+            .map(|e| e.checkpoint.sequence)
+            .collect();
+        assert_eq!(sequences, vec![1, 2]);
+
+        let mut authorities = KeyRing::new();
+        authorities.insert(assembler.verifying_key());
+        let decoded = ForensicPackage::from_bytes(&package.to_bytes().unwrap()).unwrap();
+        decoded
+            .verify(Box::new(robot.verifying_key()), &authorities)
+            .unwrap();
+
+        let err =
+            ForensicPackage::assemble(&store, &RobotId("R-404".to_string()), from, to, &assembler)
+                .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::PackageIncomplete);
+    }
+
+    #[test]
+    fn test_rejects_omitted_entries_and_gaps() {
+        let (robot, assembler) = (Signer::generate(), Signer::generate());
+        let store = Store::new(&robot, 4);
+        let from = t0() + Duration::seconds(70);
+        let to = t0() + Duration::seconds(190);
+        let package =
+            ForensicPackage::assemble(&store, &RobotId("R-001".to_string()), from, to, &assembler)
+                .unwrap();
+        assert_eq!(package.checkpoints.len(), 3);
+
+        // Re-signing after dropping an entry from a proof is detected...
+        let resign = |mut package: ForensicPackage| {
+            let signature = assembler.sign(&package.signing_payload().unwrap());
+            package.signature = SignatureBytes::from(signature.to_bytes());
+            package
+        };
+        let mut omitted = package.clone();
+        let tree = store.entries(&omitted.checkpoints[1].checkpoint).unwrap();
+        let keys: Vec<_> = tree.entries()[..4]
+            .iter()
+            .map(|e| (e.timestamp_us, e.nonce))
+            .collect();
+        omitted.checkpoints[1].entries = tree.generate_multiproof(&keys);
+        let err = resign(omitted)
+            .verify(Box::new(robot.verifying_key()), &assembler.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::PackageIncomplete);
+
+        // ...as is dropping a checkpoint from the middle
+        let mut gapped = package.clone();
+        gapped.checkpoints.remove(1);
+        let err = resign(gapped)
+            .verify(Box::new(robot.verifying_key()), &assembler.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SequenceGap);
+
+        // and any edit without the assembler's key
+        let mut edited = package;
+        edited.checkpoints[0].anchors.clear();
+        let err = edited
+            .verify(Box::new(robot.verifying_key()), &assembler.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::PackageSignatureInvalid);
+    }
+}
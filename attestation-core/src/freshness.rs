@@ -0,0 +1,220 @@
+//! Per-vendor attestation freshness policy.
+//!
+//! A [`FreshnessPolicy`] bounds how old the evidence and the collateral
+//! behind a verdict may be, and how often a device must re-attest. The
+//! [`AttestationRegistry`](crate::AttestationRegistry) applies the policy of
+//! the result's vendor after every verification, so all adapters are held
+//! to the same rules:
+//!
+//! - evidence older than `max_quote_age` is rejected (`VB-ATT-013`);
+//! - a revocation verdict based on collateral older than
+//!   `max_collateral_age`, or on a CRL past its next update, is downgraded
+//!   from `Ok` to `Unknown` instead of passing. The reason is recorded in
+//!   the [`STALE_CLAIM`] claim for policy evaluation.
+
+use crate::attestation::AttestationError;
+use crate::types::{AttestationResult, ClaimValue, RevocationStatus};
+use chrono::{DateTime, Duration, Utc};
+
+/// Claim with the time the evidence was produced, in seconds since the Unix
+/// epoch. Set by adapters whose evidence carries a timestamp.
+pub const QUOTE_TIME_CLAIM: &str = "quote.generated_at";
+
+/// Claim set when a verdict was downgraded: "collateral" or "quote_time".
+pub const STALE_CLAIM: &str = "freshness.stale";
+
+/// Freshness requirements for one vendor. Unset bounds are not enforced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FreshnessPolicy {
+    /// Oldest acceptable evidence, by [`QUOTE_TIME_CLAIM`]
+    pub max_quote_age: Option<Duration>,
+    /// Oldest acceptable collateral (CRL issue time or bundle fetch time)
+    pub max_collateral_age: Option<Duration>,
+    /// How long a verified result stands before the device must re-attest
+    pub reattestation_interval: Option<Duration>,
+}
+
+impl FreshnessPolicy {
+    /// Reject evidence older than `age`.
+    ///
+    /// Evidence without a [`QUOTE_TIME_CLAIM`] cannot be dated; its verdict
+    /// is downgraded instead.
+    pub fn with_max_quote_age(mut self, age: Duration) -> Self {
+        self.max_quote_age = Some(age);
+        self
+    }
+
+    /// Downgrade verdicts based on collateral older than `age`.
+    pub fn with_max_collateral_age(mut self, age: Duration) -> Self {
+        self.max_collateral_age = Some(age);
+        self
+    }
+
+    /// Require re-attestation every `interval`.
+    pub fn with_reattestation_interval(mut self, interval: Duration) -> Self {
+        self.reattestation_interval = Some(interval);
+        self
+    }
+
+    /// Apply the policy to a freshly verified `result`.
+    ///
+    /// `collateral_time` is when offline collateral was fetched; without it
+    /// the issue time of the consulted CRL is used, if any.
+    pub fn apply(
+        &self,
+        result: &mut AttestationResult,
+        collateral_time: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Result<(), AttestationError> {
+        if let Some(max_age) = self.max_quote_age {
+            let generated_at = result
+                .claim(QUOTE_TIME_CLAIM)
+                .and_then(ClaimValue::as_u64)
+                .and_then(|secs| DateTime::from_timestamp(i64::try_from(secs).ok()?, 0));
+            match generated_at {
+                Some(at) if now - at > max_age => {
+                    return Err(AttestationError::QuoteStale(format!(
+                        "evidence is {}s old, at most {}s allowed",
+                        (now - at).num_seconds(),
+                        max_age.num_seconds()
+                    )));
+                }
+                Some(_) => {}
+                None => downgrade(result, "quote_time"),
+            }
+        }
+
+        let collateral_time =
+            collateral_time.or(result.revoke_check.crl_freshness.map(|crl| crl.this_update));
+        let too_old = match (self.max_collateral_age, collateral_time) {
+            (Some(max_age), Some(at)) => now - at > max_age,
+            _ => false,
+        };
+        if too_old || result.revoke_check.is_stale(now) {
+            downgrade(result, "collateral");
+        }
+        Ok(())
+    }
+
+    /// Whether a result verified at `verified_at` is due for re-attestation at `now`.
+    pub fn reattestation_due(&self, verified_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        self.reattestation_interval
+            .is_some_and(|interval| now - verified_at >= interval)
+    }
+}
+
+/// Turn an `Ok` revocation verdict into `Unknown`; a revoked one stays revoked.
+fn downgrade(result: &mut AttestationResult, reason: &str) {
+    if result.revoke_check.status == RevocationStatus::Ok {
+        result.revoke_check.status = RevocationStatus::Unknown;
+    }
+    result
+        .claims
+        .entry(STALE_CLAIM.to_string())
+        .or_insert_with(|| ClaimValue::Text(reason.to_string()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CrlFreshness, RevocationCheck, RevocationSource};
+    use crate::ErrorCoded;
+
+    fn result(
+        now: DateTime<Utc>,
+        quote_age: Option<Duration>,
+        crl_age: Duration,
+    ) -> AttestationResult {
+        let crl = CrlFreshness {
+            this_update: now - crl_age,
+            next_update: now - crl_age + Duration::days(30),
+        };
+        let mut result = AttestationResult {
+            vendor: "intel-sgx".to_string(),
+            enclave_measurement: vec![0u8; 32],
+            quote_verified: true,
+            verified_at: now,
+            revoke_check: RevocationCheck::ok(RevocationSource::Crl).with_crl_freshness(crl),
+            raw_quote: None,
+            pck_chain: None,
+            claims: Default::default(),
+        };
+        if let Some(age) = quote_age {
+            let generated = (now - age).timestamp() as u64;
+            result
+                .claims
+                .insert(QUOTE_TIME_CLAIM.to_string(), ClaimValue::Uint(generated));
+        }
+        result
+    }
+
+    #[test]
+    fn test_stale_collateral_downgrades_to_unknown() {
+        let now = Utc::now();
+        let policy = FreshnessPolicy::default().with_max_collateral_age(Duration::days(7));
+
+        let mut fresh = result(now, None, Duration::days(1));
+        policy.apply(&mut fresh, None, now).unwrap();
+        assert_eq!(fresh.revoke_check.status, RevocationStatus::Ok);
+        assert!(fresh.claim(STALE_CLAIM).is_none());
+
+        let mut stale = result(now, None, Duration::days(8));
+        policy.apply(&mut stale, None, now).unwrap();
+        assert_eq!(stale.revoke_check.status, RevocationStatus::Unknown);
+        assert_eq!(
+            stale.claim(STALE_CLAIM).and_then(ClaimValue::as_str),
+            Some("collateral")
+        );
+
+        // Offline collateral is dated by its fetch time
+        let mut offline = result(now, None, Duration::days(1));
+        policy
+            .apply(&mut offline, Some(now - Duration::days(10)), now)
+            .unwrap();
+        assert_eq!(offline.revoke_check.status, RevocationStatus::Unknown);
+
+        // A CRL past its next update is stale whatever the configured age
+        let mut expired = result(now, None, Duration::days(31));
+        FreshnessPolicy::default()
+            .apply(&mut expired, None, now)
+            .unwrap();
+        assert_eq!(expired.revoke_check.status, RevocationStatus::Unknown);
+    }
+
+    #[test]
+    fn test_quote_age_and_reattestation() {
+        let now = Utc::now();
+        let policy = FreshnessPolicy::default()
+            .with_max_quote_age(Duration::minutes(5))
+            .with_reattestation_interval(Duration::hours(24));
+
+        policy
+            .apply(
+                &mut result(now, Some(Duration::minutes(1)), Duration::days(1)),
+                None,
+                now,
+            )
+            .unwrap();
+
+        let err = policy
+            .apply(
+                &mut result(now, Some(Duration::minutes(10)), Duration::days(1)),
+                None,
+                now,
+            )
+            .unwrap_err();
+        assert_eq!(err.code().as_str(), "VB-ATT-013");
+
+        let mut undated = result(now, None, Duration::days(1));
+        policy.apply(&mut undated, None, now).unwrap();
+        assert_eq!(undated.revoke_check.status, RevocationStatus::Unknown);
+        assert_eq!(
+            undated.claim(STALE_CLAIM).and_then(ClaimValue::as_str),
+            Some("quote_time")
+        );
+
+        assert!(!policy.reattestation_due(now - Duration::hours(23), now));
+        assert!(policy.reattestation_due(now - Duration::hours(24), now));
+        assert!(!FreshnessPolicy::default().reattestation_due(now - Duration::days(365), now));
+    }
+}
@@ -0,0 +1,446 @@
+//! Signed sequence gap justifications.
+//!
+//! Checkpoints can go missing for mundane reasons: a robot reboots before
+//! unflushed checkpoints reach storage, or the TEE is unavailable and the
+//! checkpoints it did sign are lost with it. Without more information a
+//! verifier cannot tell that from a robot suppressing evidence, so every
+//! sequence gap is rejected.
+//!
+//! A [`GapRecord`] is the robot's signed statement that the sequences
+//! between two checkpoints are missing and why. It pins both ends: the hash
+//! of the last checkpoint before the gap and the `prev_root` the first
+//! checkpoint after it carries. A [`ChainVerifier`](crate::ChainVerifier)
+//! given the record ([`ChainVerifier::add_gap`](crate::ChainVerifier::add_gap))
+//! accepts the gap only if its [`GapPolicy`] allows the stated reason for
+//! that many missing sequences over that much time, and lists it in
+//! [`ChainVerifier::bridged_gaps`](crate::ChainVerifier::bridged_gaps).
+
+use crate::chain::ChainHead;
+use crate::crypto::Signer;
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, KeyId, RobotId, SignatureBytes};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Gap record version (for schema evolution)
+pub const GAP_RECORD_VERSION: u8 = 1;
+
+/// Why checkpoints are missing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapReason {
+    /// Robot restarted before the checkpoints were persisted
+    Reboot,
+    /// TEE was unavailable (crash, firmware update, attestation outage)
+    TeeUnavailable,
+    /// Checkpoint storage was lost or corrupted
+    StorageLoss,
+    /// Anything else; see the record's `detail`
+    Other,
+}
+
+/// Robot-signed justification for a sequence gap.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GapRecord {
+    /// Schema version
+    pub version: u8,
+    pub robot_id: RobotId,
+    /// Sequence of the last checkpoint before the gap
+    pub after_sequence: u64,
+    /// Hash of that checkpoint
+    pub after_hash: Hash256,
+    /// Sequence of the first checkpoint after the gap
+    pub resume_sequence: u64,
+    /// `prev_root` of that checkpoint (the hash of a missing checkpoint)
+    pub resume_prev_root: Hash256,
+    pub reason: GapReason,
+    /// Free-form explanation (e.g., a crash report id)
+    pub detail: String,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub issued_at: DateTime<Utc>,
+    /// Fingerprint of the robot key that signed the record
+    pub signer_key_id: KeyId,
+    /// Ed25519 signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned gap record (for signature computation)
+#[derive(Serialize)]
+struct UnsignedGapRecord<'a> {
+    version: u8,
+    robot_id: &'a RobotId,
+    after_sequence: u64,
+    after_hash: Hash256,
+    resume_sequence: u64,
+    resume_prev_root: Hash256,
+    reason: GapReason,
+    detail: &'a str,
+    #[serde(with = "crate::serialization::timestamp")]
+    issued_at: DateTime<Utc>,
+    signer_key_id: KeyId,
+}
+
+impl GapRecord {
+    /// Justify the gap between `after` (the last checkpoint before it) and
+    /// checkpoint `resume_sequence`, whose `prev_root` is `resume_prev_root`,
+    /// signed by the robot's key.
+    pub fn issue(
+        after: &ChainHead,
+        resume_sequence: u64,
+        resume_prev_root: Hash256,
+        reason: GapReason,
+        detail: impl Into<String>,
+        robot: &Signer,
+    ) -> Result<Self, GapError> {
+        let mut record = Self {
+            version: GAP_RECORD_VERSION,
+            robot_id: after.robot_id.clone(),
+            after_sequence: after.sequence,
+            after_hash: after.hash,
+            resume_sequence,
+            resume_prev_root,
+            reason,
+            detail: detail.into(),
+            issued_at: Utc::now(),
+            signer_key_id: robot.key_id(),
+            signature: SignatureBytes([0u8; 64]),
+        };
+        record.check_range()?;
+        let signature = robot.sign(&record.signing_payload()?);
+        record.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(record)
+    }
+
+    /// Number of sequences the record declares missing.
+    pub fn missing(&self) -> u64 {
+        self.resume_sequence.saturating_sub(self.after_sequence + 1)
+    }
+
+    /// Verify the signature with a key resolved from `keys`.
+    pub fn verify(&self, keys: &dyn KeyResolver) -> Result<(), GapError> {
+        use ed25519_dalek::Verifier;
+
+        self.check_range()?;
+        let key = keys
+            .resolve(&self.signer_key_id)
+            .ok_or(GapError::UnknownKey(self.signer_key_id))?;
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        key.verify(&self.signing_payload()?, &signature)
+            .map_err(|_| GapError::InvalidSignature)
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    fn check_range(&self) -> Result<(), GapError> {
+        if self.resume_sequence <= self.after_sequence.saturating_add(1) {
+            return Err(GapError::EmptyRange {
+                after: self.after_sequence,
+                resume: self.resume_sequence,
+            });
+        }
+        Ok(())
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedGapRecord {
+            version: self.version,
+            robot_id: &self.robot_id,
+            after_sequence: self.after_sequence,
+            after_hash: self.after_hash,
+            resume_sequence: self.resume_sequence,
+            resume_prev_root: self.resume_prev_root,
+            reason: self.reason,
+            detail: &self.detail,
+            issued_at: self.issued_at,
+            signer_key_id: self.signer_key_id,
+        })
+    }
+}
+
+/// How large a gap each reason may justify.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GapAllowance {
+    /// Most sequences a single gap may skip
+    pub max_missing: u64,
+    /// Longest time between the checkpoints either side of the gap
+    pub max_duration: Duration,
+}
+
+/// Reasons a verifier accepts sequence gaps for. Empty by default: no gap
+/// is accepted, justified or not.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GapPolicy {
+    allowances: BTreeMap<GapReason, GapAllowance>,
+}
+
+impl GapPolicy {
+    /// A policy accepting no gaps.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept gaps for `reason` of up to `max_missing` sequences spanning up
+    /// to `max_duration`.
+    pub fn allow(mut self, reason: GapReason, max_missing: u64, max_duration: Duration) -> Self {
+        self.allowances.insert(
+            reason,
+            GapAllowance {
+                max_missing,
+                max_duration,
+            },
+        );
+        self
+    }
+
+    /// The allowance for `reason`, if any.
+    pub fn allowance(&self, reason: GapReason) -> Option<&GapAllowance> {
+        self.allowances.get(&reason)
+    }
+
+    /// Whether `record` justifies a gap between checkpoints timestamped
+    /// `from` and `until`.
+    pub fn permits(&self, record: &GapRecord, from: DateTime<Utc>, until: DateTime<Utc>) -> bool {
+        self.allowance(record.reason).is_some_and(|allowance| {
+            record.missing() <= allowance.max_missing && until - from <= allowance.max_duration
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum GapError {
+    #[error("Gap record serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Gap record from {after} to {resume} skips no sequence")]
+    EmptyRange { after: u64, resume: u64 },
+
+    #[error("Gap record signed by unknown key {0}")]
+    UnknownKey(KeyId),
+
+    #[error("Invalid gap record signature")]
+    InvalidSignature,
+}
+
+impl ErrorCoded for GapError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            GapError::Serialization(e) => e.code(),
+            GapError::UnknownKey(_) => ErrorCode::UnknownSigningKey,
+            GapError::EmptyRange { .. } | GapError::InvalidSignature => ErrorCode::GapRecordInvalid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::{ChainError, ChainVerifier};
+    use crate::checkpoint::{Checkpoint, CheckpointBuilder};
+
+    fn head(sequence: u64) -> ChainHead {
+        ChainHead {
+            robot_id: RobotId("R-001".to_string()),
+            sequence,
+            monotonic_counter: sequence,
+            timestamp: DateTime::UNIX_EPOCH,
+            hash: [1u8; 32],
+            mission: None,
+        }
+    }
+
+    #[test]
+    fn test_gap_record_signature_and_policy() {
+        let robot = Signer::generate();
+        let record = GapRecord::issue(
+            &head(4),
+            8,
+            [2u8; 32],
+            GapReason::Reboot,
+            "watchdog reset",
+            &robot,
+        )
+        .unwrap();
+        assert_eq!(record.missing(), 3);
+        record.verify(&robot.verifying_key()).unwrap();
+        assert_eq!(
+            GapRecord::from_bytes(&record.to_bytes().unwrap()).unwrap(),
+            record
+        );
+
+        let mut tampered = record.clone();
+        tampered.resume_sequence = 20;
+        assert!(matches!(
+            tampered.verify(&robot.verifying_key()),
+            Err(GapError::InvalidSignature)
+        ));
+        let other = Signer::generate();
+        assert_eq!(
+            record.verify(&other.verifying_key()).unwrap_err().code(),
+            ErrorCode::UnknownSigningKey
+        );
+
+        let from = DateTime::UNIX_EPOCH;
+        let policy = GapPolicy::new().allow(GapReason::Reboot, 5, Duration::minutes(10));
+        assert!(policy.permits(&record, from, from + Duration::minutes(5)));
+        assert!(!policy.permits(&record, from, from + Duration::hours(1)));
+        assert!(!GapPolicy::new().permits(&record, from, from));
+        let strict = GapPolicy::new().allow(GapReason::Reboot, 2, Duration::minutes(10));
+        assert!(!strict.permits(&record, from, from));
+
+        let empty = GapRecord::issue(&head(4), 5, [2u8; 32], GapReason::Other, "", &robot);
+        assert_eq!(empty.unwrap_err().code(), ErrorCode::GapRecordInvalid);
+    }
+
+    fn checkpoint(robot: &Signer, sequence: u64, prev_root: Hash256) -> Checkpoint {
+        CheckpointBuilder::test_fixture()
+            .sequence(sequence)
+            .monotonic_counter(sequence)
+            .prev_root(prev_root)
+            .build_and_sign(robot.signing_key())
+            .unwrap()
+    }
+
+    /// Verifier accepting reboot gaps of up to 10 sequences, with `records` added.
+    fn verifier(robot: &Signer, records: &[&GapRecord]) -> ChainVerifier {
+        let mut verifier = ChainVerifier::new(robot.verifying_key())
+            .with_gap_policy(GapPolicy::new().allow(GapReason::Reboot, 10, Duration::hours(1)));
+        for record in records {
+            verifier.add_gap(record).unwrap();
+        }
+        verifier
+    }
+
+    fn gap(
+        after: &Checkpoint,
+        resume: u64,
+        resume_prev_root: Hash256,
+        robot: &Signer,
+    ) -> GapRecord {
+        let head = ChainHead::from_checkpoint(after).unwrap();
+        GapRecord::issue(
+            &head,
+            resume,
+            resume_prev_root,
+            GapReason::Reboot,
+            "watchdog reset",
+            robot,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_gap_must_start_at_the_head_it_names() {
+        let robot = Signer::generate();
+        let first = checkpoint(&robot, 1, [0u8; 32]);
+        let second = checkpoint(&robot, 2, first.compute_hash().unwrap());
+        // A sibling #2 that never reached the verifier
+        let sibling = checkpoint(&robot, 2, [7u8; 32]);
+        let resumed = checkpoint(&robot, 5, [4u8; 32]);
+
+        let record = gap(&sibling, 5, [4u8; 32], &robot);
+        let mut verifier = verifier(&robot, &[&record]);
+        verifier
+            .verify_chain(&[first.clone(), second.clone()])
+            .unwrap();
+        let err = verifier.verify_next(&resumed).unwrap_err();
+        assert!(matches!(
+            err,
+            ChainError::SequenceGap {
+                expected: 3,
+                actual: 5
+            }
+        ));
+        assert!(verifier.bridged_gaps().is_empty());
+
+        // Rewriting the hash to the verifier's head breaks the signature
+        let mut rewritten = record;
+        rewritten.after_hash = second.compute_hash().unwrap();
+        assert!(matches!(
+            rewritten.verify(&robot.verifying_key()),
+            Err(GapError::InvalidSignature)
+        ));
+        assert!(verifier.add_gap(&rewritten).is_err());
+        assert!(verifier.verify_next(&resumed).is_err());
+    }
+
+    #[test]
+    fn test_overlapping_gaps_bridge_only_from_the_head() {
+        let robot = Signer::generate();
+        let first = checkpoint(&robot, 1, [0u8; 32]);
+        let second = checkpoint(&robot, 2, first.compute_hash().unwrap());
+        let fifth = checkpoint(&robot, 5, [4u8; 32]);
+        let seventh = checkpoint(&robot, 7, [6u8; 32]);
+
+        // Two records from #2, and one from #3, inside the first gap
+        let to_fifth = gap(&second, 5, [4u8; 32], &robot);
+        let to_sixth = gap(&second, 6, [5u8; 32], &robot);
+        let third = checkpoint(&robot, 3, second.compute_hash().unwrap());
+        let from_third = gap(&third, 7, [6u8; 32], &robot);
+        let mut verifier = verifier(&robot, &[&to_sixth, &from_third, &to_fifth]);
+        verifier.verify_chain(&[first, second]).unwrap();
+
+        // The record ending where the chain resumes bridges the gap
+        verifier.verify_next(&fifth).unwrap();
+        assert_eq!(verifier.bridged_gaps(), [to_fifth]);
+
+        // A record starting inside a bridged gap does not extend it
+        let err = verifier.verify_next(&seventh).unwrap_err();
+        assert!(matches!(
+            err,
+            ChainError::SequenceGap {
+                expected: 6,
+                actual: 7
+            }
+        ));
+        assert_eq!(verifier.head().unwrap().sequence, 5);
+    }
+
+    #[test]
+    fn test_out_of_order_gaps() {
+        let robot = Signer::generate();
+        let first = checkpoint(&robot, 1, [0u8; 32]);
+        let second = checkpoint(&robot, 2, first.compute_hash().unwrap());
+        let fifth = checkpoint(&robot, 5, [4u8; 32]);
+        let eighth = checkpoint(&robot, 8, [7u8; 32]);
+
+        // Records added latest first are bridged in chain order
+        let early = gap(&second, 5, [4u8; 32], &robot);
+        let late = gap(&fifth, 8, [7u8; 32], &robot);
+        let mut verifier = verifier(&robot, &[&late, &early]);
+        verifier
+            .verify_chain(&[first, second.clone(), fifth.clone(), eighth])
+            .unwrap();
+        assert_eq!(verifier.bridged_gaps(), [early, late.clone()]);
+
+        // A record resuming before it starts skips nothing
+        let head = ChainHead::from_checkpoint(&fifth).unwrap();
+        let backwards = GapRecord::issue(&head, 3, [2u8; 32], GapReason::Reboot, "", &robot);
+        assert!(matches!(
+            backwards,
+            Err(GapError::EmptyRange {
+                after: 5,
+                resume: 3
+            })
+        ));
+        let mut reversed = late;
+        reversed.resume_sequence = 2;
+        assert_eq!(
+            reversed.verify(&robot.verifying_key()).unwrap_err().code(),
+            ErrorCode::GapRecordInvalid
+        );
+        assert!(verifier.add_gap(&reversed).is_err());
+        assert_eq!(reversed.missing(), 0);
+    }
+}
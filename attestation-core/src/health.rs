@@ -0,0 +1,550 @@
+//! Agent health telemetry.
+//!
+//! An attestation agent rarely fails all at once: its outbound queue backs
+//! up, its signer backend starts failing, its Merkle tree grows because
+//! checkpoints go out late. Evidence gaps follow. To see this coming, the
+//! agent periodically samples its own state as an [`AgentHealth`] record and
+//! logs it as an ordinary Merkle entry, so every sample is signed evidence
+//! like any other. A [`HealthRecorder`] folds the samples since the last
+//! checkpoint into a [`HealthSummary`], which the next checkpoint (or
+//! heartbeat) carries as `agent_health`.
+//!
+//! Health records are typed entries (record type [`HEALTH_RECORD_TYPE`]), so
+//! mission summaries count them and the search index finds them by kind.
+//! Fleet operators read the summaries off verified chains and let a
+//! [`HealthPolicy`] flag agents that are degrading.
+
+use crate::checkpoint::Checkpoint;
+use crate::merkle::{Entry, InsertOutcome, MerkleTree};
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Record type of [`AgentHealth`] entries.
+pub const HEALTH_RECORD_TYPE: &str = "agent_health";
+
+/// State of the agent's signer backend, least to most severe.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignerStatus {
+    #[default]
+    Ok,
+    /// Signing works but the backend reports errors or fell back
+    Degraded,
+    /// Signing failed
+    Unavailable,
+}
+
+impl fmt::Display for SignerStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignerStatus::Ok => write!(f, "ok"),
+            SignerStatus::Degraded => write!(f, "degraded"),
+            SignerStatus::Unavailable => write!(f, "unavailable"),
+        }
+    }
+}
+
+/// One sample of an agent's own state, logged as an entry payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentHealth {
+    /// Always [`HEALTH_RECORD_TYPE`]
+    pub record_type: String,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub sampled_at: DateTime<Utc>,
+    /// Records waiting in the outbound spool
+    pub queue_depth: u64,
+    /// Last value read from the monotonic counter
+    pub last_counter: u64,
+    pub signer: SignerStatus,
+    /// Entries in the Merkle tree not yet checkpointed
+    pub tree_size: u64,
+}
+
+impl AgentHealth {
+    /// Empty sample taken at `sampled_at`, with a healthy signer.
+    pub fn new(sampled_at: DateTime<Utc>) -> Self {
+        Self {
+            record_type: HEALTH_RECORD_TYPE.to_string(),
+            sampled_at,
+            queue_depth: 0,
+            last_counter: 0,
+            signer: SignerStatus::Ok,
+            tree_size: 0,
+        }
+    }
+
+    /// Serialize to canonical CBOR bytes (the entry payload).
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// The health record in an entry payload, if it is one.
+    pub fn from_payload(payload: &[u8]) -> Option<Self> {
+        from_canonical_cbor::<Self>(payload)
+            .ok()
+            .filter(|health| health.record_type == HEALTH_RECORD_TYPE)
+    }
+}
+
+/// What a checkpoint reports about the samples logged since the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthSummary {
+    pub samples: u64,
+    pub max_queue_depth: u64,
+    pub last_counter: u64,
+    /// Worst signer status sampled
+    pub signer: SignerStatus,
+    /// Samples whose signer status was not ok
+    pub signer_faults: u64,
+    pub max_tree_size: u64,
+}
+
+impl HealthSummary {
+    /// Summary of `samples`, or `None` if there are none.
+    pub fn from_samples<'a>(samples: impl IntoIterator<Item = &'a AgentHealth>) -> Option<Self> {
+        let mut summary: Option<Self> = None;
+        for sample in samples {
+            match &mut summary {
+                Some(summary) => summary.add(sample),
+                None => summary = Some(Self::first(sample)),
+            }
+        }
+        summary
+    }
+
+    fn first(sample: &AgentHealth) -> Self {
+        Self {
+            samples: 1,
+            max_queue_depth: sample.queue_depth,
+            last_counter: sample.last_counter,
+            signer: sample.signer,
+            signer_faults: u64::from(sample.signer != SignerStatus::Ok),
+            max_tree_size: sample.tree_size,
+        }
+    }
+
+    fn add(&mut self, sample: &AgentHealth) {
+        self.samples += 1;
+        self.max_queue_depth = self.max_queue_depth.max(sample.queue_depth);
+        self.last_counter = self.last_counter.max(sample.last_counter);
+        self.signer = self.signer.max(sample.signer);
+        self.signer_faults += u64::from(sample.signer != SignerStatus::Ok);
+        self.max_tree_size = self.max_tree_size.max(sample.tree_size);
+    }
+}
+
+/// Agent-side log of health samples between checkpoints.
+#[derive(Debug, Clone, Default)]
+pub struct HealthRecorder {
+    pending: Option<HealthSummary>,
+}
+
+impl HealthRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Log `sample` into `tree` as an entry keyed by its sample time and
+    /// `nonce`. Returns the payload, for whoever retains payloads.
+    ///
+    /// A sample the tree rejects as a duplicate is not summarized.
+    pub fn record(
+        &mut self,
+        tree: &mut MerkleTree,
+        sample: &AgentHealth,
+        nonce: u64,
+    ) -> Result<Vec<u8>, SerializationError> {
+        let payload = sample.to_bytes()?;
+        let timestamp_us = sample.sampled_at.timestamp_micros().max(0) as u64;
+        if matches!(
+            tree.insert(Entry::new(timestamp_us, nonce, &payload)),
+            InsertOutcome::Rejected
+        ) {
+            return Ok(payload);
+        }
+        match &mut self.pending {
+            Some(summary) => summary.add(sample),
+            None => self.pending = Some(HealthSummary::first(sample)),
+        }
+        Ok(payload)
+    }
+
+    /// Summary of the samples since the last call, for
+    /// [`CheckpointBuilder::agent_health`](crate::CheckpointBuilder::agent_health).
+    pub fn take_summary(&mut self) -> Option<HealthSummary> {
+        self.pending.take()
+    }
+}
+
+/// Thresholds above which a fleet operator wants to hear about an agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthPolicy {
+    pub max_queue_depth: u64,
+    pub max_tree_size: u64,
+    /// Signer faults tolerated per checkpoint
+    pub max_signer_faults: u64,
+    /// Rises in queue depth over consecutive checkpoints that count as a
+    /// backlog building up, even below `max_queue_depth`
+    pub growth_window: usize,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self {
+            max_queue_depth: 1_000,
+            max_tree_size: 100_000,
+            max_signer_faults: 0,
+            growth_window: 3,
+        }
+    }
+}
+
+/// Sign of degradation found in a checkpoint's health summary.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HealthIssue {
+    /// Outbound queue deeper than the policy allows
+    QueueBacklog { depth: u64 },
+    /// Queue depth rose across the last `checkpoints` checkpoints
+    QueueGrowing { checkpoints: usize },
+    /// Merkle tree larger than the policy allows
+    TreeBacklog { size: u64 },
+    /// Signer backend faulted more often than the policy allows
+    SignerFaults { faults: u64, worst: SignerStatus },
+}
+
+/// A [`HealthIssue`] with the checkpoint that showed it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthAlert {
+    pub sequence: u64,
+    pub issue: HealthIssue,
+}
+
+impl HealthPolicy {
+    /// Issues in one health summary.
+    pub fn issues(&self, summary: &HealthSummary) -> Vec<HealthIssue> {
+        let mut issues = Vec::new();
+        if summary.max_queue_depth > self.max_queue_depth {
+            issues.push(HealthIssue::QueueBacklog {
+                depth: summary.max_queue_depth,
+            });
+        }
+        if summary.max_tree_size > self.max_tree_size {
+            issues.push(HealthIssue::TreeBacklog {
+                size: summary.max_tree_size,
+            });
+        }
+        if summary.signer_faults > self.max_signer_faults {
+            issues.push(HealthIssue::SignerFaults {
+                faults: summary.signer_faults,
+                worst: summary.signer,
+            });
+        }
+        issues
+    }
+
+    /// Alerts over a verified chain, in chain order. Checkpoints without a
+    /// health summary are skipped.
+    pub fn assess(&self, checkpoints: &[Checkpoint]) -> Vec<HealthAlert> {
+        let mut alerts = Vec::new();
+        let mut rising = 0usize;
+        let mut previous_depth = None;
+        for checkpoint in checkpoints {
+            let Some(summary) = &checkpoint.agent_health else {
+                continue;
+            };
+            let alert = |issue| HealthAlert {
+                sequence: checkpoint.sequence,
+                issue,
+            };
+            alerts.extend(self.issues(summary).into_iter().map(alert));
+
+            rising = match previous_depth {
+                Some(previous) if summary.max_queue_depth > previous => rising + 1,
+                _ => 0,
+            };
+            previous_depth = Some(summary.max_queue_depth);
+            if self.growth_window > 0 && rising == self.growth_window {
+                alerts.push(alert(HealthIssue::QueueGrowing {
+                    checkpoints: rising + 1,
+                }));
+            }
+        }
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::crypto::Signer;
+    use crate::summary::MissionSummary;
+    use crate::types::MissionId;
+
+    #[test]
+    fn test_samples_logged_and_summarized_into_checkpoints() {
+        let signer = Signer::generate();
+        let start = DateTime::UNIX_EPOCH + chrono::Duration::days(20_000);
+        let mut recorder = HealthRecorder::new();
+        let mut summary = MissionSummary::new(MissionId("M-001".to_string()));
+        let mut checkpoints: Vec<Checkpoint> = Vec::new();
+
+        // Queue depth climbs steadily; the signer degrades once, at the end
+        for sequence in 0..4u64 {
+            let mut tree = MerkleTree::new();
+            for i in 0..2u64 {
+                let mut sample =
+                    AgentHealth::new(start + chrono::Duration::seconds((10 * sequence + i) as i64));
+                sample.queue_depth = 100 * sequence + i;
+                sample.last_counter = 2 * sequence + i;
+                sample.tree_size = tree.len() as u64;
+                if sequence == 3 && i == 1 {
+                    sample.signer = SignerStatus::Degraded;
+                }
+                let payload = recorder.record(&mut tree, &sample, 0).unwrap();
+                assert_eq!(AgentHealth::from_payload(&payload), Some(sample));
+                summary.add(&payload);
+            }
+
+            let builder = match checkpoints.last() {
+                Some(prev) => CheckpointBuilder::continuing_from(prev).unwrap(),
+                None => CheckpointBuilder::test_fixture(),
+            };
+            let checkpoint = builder
+                .monotonic_counter(sequence + 1)
+                .entries_root(tree.root())
+                .agent_health(recorder.take_summary().unwrap())
+                .build_and_sign(signer.signing_key())
+                .unwrap();
+            checkpoint
+                .verify_signature(&signer.verifying_key())
+                .unwrap();
+            checkpoints.push(checkpoint);
+        }
+        assert_eq!(recorder.take_summary(), None);
+        assert_eq!(summary.entry_counts.get(HEALTH_RECORD_TYPE), Some(&8));
+
+        let last = checkpoints[3].agent_health.unwrap();
+        assert_eq!(
+            (
+                last.samples,
+                last.max_queue_depth,
+                last.last_counter,
+                last.max_tree_size
+            ),
+            (2, 301, 7, 1)
+        );
+        assert_eq!(
+            (last.signer, last.signer_faults),
+            (SignerStatus::Degraded, 1)
+        );
+
+        // The summary is not carried into the next checkpoint
+        let next = CheckpointBuilder::heartbeat_from(&checkpoints[3])
+            .unwrap()
+            .monotonic_counter(5)
+            .build_and_sign(signer.signing_key())
+            .unwrap();
+        assert_eq!(next.agent_health, None);
+
+        let alerts = HealthPolicy::default().assess(&checkpoints);
+        assert_eq!(
+            alerts,
+            vec![
+                HealthAlert {
+                    sequence: 3,
+                    issue: HealthIssue::SignerFaults {
+                        faults: 1,
+                        worst: SignerStatus::Degraded
+                    },
+                },
+                HealthAlert {
+                    sequence: 3,
+                    issue: HealthIssue::QueueGrowing { checkpoints: 4 },
+                },
+            ]
+        );
+        let strict = HealthPolicy {
+            max_queue_depth: 200,
+            ..HealthPolicy::default()
+        };
+        assert!(strict
+            .issues(&last)
+            .contains(&HealthIssue::QueueBacklog { depth: 301 }));
+    }
+
+    /// Summary of one sample.
+    fn sampled(queue_depth: u64, tree_size: u64, signer: SignerStatus) -> HealthSummary {
+        let mut sample = AgentHealth::new(DateTime::UNIX_EPOCH);
+        sample.queue_depth = queue_depth;
+        sample.tree_size = tree_size;
+        sample.signer = signer;
+        HealthSummary::from_samples([&sample]).unwrap()
+    }
+
+    fn checkpoint(signer: &Signer, sequence: u64, health: Option<HealthSummary>) -> Checkpoint {
+        let builder = CheckpointBuilder::test_fixture()
+            .sequence(sequence)
+            .monotonic_counter(sequence + 1);
+        let builder = match health {
+            Some(health) => builder.agent_health(health),
+            None => builder,
+        };
+        builder.build_and_sign(signer.signing_key()).unwrap()
+    }
+
+    #[test]
+    fn test_each_threshold_flags_its_issue() {
+        let policy = HealthPolicy {
+            max_queue_depth: 10,
+            max_tree_size: 100,
+            max_signer_faults: 1,
+            growth_window: 3,
+        };
+        // At the limits is still healthy
+        assert!(policy
+            .issues(&sampled(10, 100, SignerStatus::Ok))
+            .is_empty());
+
+        assert_eq!(
+            policy.issues(&sampled(11, 100, SignerStatus::Ok)),
+            [HealthIssue::QueueBacklog { depth: 11 }]
+        );
+        assert_eq!(
+            policy.issues(&sampled(10, 101, SignerStatus::Ok)),
+            [HealthIssue::TreeBacklog { size: 101 }]
+        );
+
+        // One fault is tolerated; the worst status of the rest is reported
+        let ok = AgentHealth::new(DateTime::UNIX_EPOCH);
+        let degraded = AgentHealth {
+            signer: SignerStatus::Degraded,
+            ..ok.clone()
+        };
+        let unavailable = AgentHealth {
+            signer: SignerStatus::Unavailable,
+            ..ok.clone()
+        };
+        let once = HealthSummary::from_samples([&ok, &degraded]).unwrap();
+        assert!(policy.issues(&once).is_empty());
+        let failing = HealthSummary::from_samples([&degraded, &unavailable, &ok]).unwrap();
+        assert_eq!(
+            (failing.signer, failing.signer_faults),
+            (SignerStatus::Unavailable, 2)
+        );
+        assert_eq!(
+            policy.issues(&failing),
+            [HealthIssue::SignerFaults {
+                faults: 2,
+                worst: SignerStatus::Unavailable
+            }]
+        );
+
+        // Everything at once is reported together
+        let mut overloaded = sampled(50, 500, SignerStatus::Unavailable);
+        overloaded.signer_faults = 3;
+        assert_eq!(policy.issues(&overloaded).len(), 3);
+        assert_eq!(
+            HealthSummary::from_samples(std::iter::empty::<&AgentHealth>()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_assess_follows_degradation_and_recovery() {
+        let signer = Signer::generate();
+        let policy = HealthPolicy {
+            max_queue_depth: 100,
+            growth_window: 2,
+            ..HealthPolicy::default()
+        };
+        let healthy = || Some(sampled(5, 10, SignerStatus::Ok));
+        let chain = [
+            checkpoint(&signer, 0, healthy()),
+            // Degraded: the signer falls back
+            checkpoint(&signer, 1, Some(sampled(5, 10, SignerStatus::Degraded))),
+            // Unhealthy: signing fails and the queue backs up
+            checkpoint(
+                &signer,
+                2,
+                Some(sampled(500, 10, SignerStatus::Unavailable)),
+            ),
+            // A checkpoint without a summary says nothing either way
+            checkpoint(&signer, 3, None),
+            // Recovered
+            checkpoint(&signer, 4, healthy()),
+        ];
+        let alerts = policy.assess(&chain);
+        let found: Vec<(u64, &HealthIssue)> =
+            alerts.iter().map(|a| (a.sequence, &a.issue)).collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    1,
+                    &HealthIssue::SignerFaults {
+                        faults: 1,
+                        worst: SignerStatus::Degraded
+                    }
+                ),
+                (2, &HealthIssue::QueueBacklog { depth: 500 }),
+                (
+                    2,
+                    &HealthIssue::SignerFaults {
+                        faults: 1,
+                        worst: SignerStatus::Unavailable
+                    }
+                ),
+            ]
+        );
+        assert!(policy.assess(&chain[4..]).is_empty());
+    }
+
+    #[test]
+    fn test_queue_growth_alerts_once_per_run() {
+        let signer = Signer::generate();
+        let policy = HealthPolicy {
+            growth_window: 2,
+            ..HealthPolicy::default()
+        };
+        let depths = [10, 20, 30, 40, 15, 25, 35];
+        let chain: Vec<Checkpoint> = depths
+            .iter()
+            .enumerate()
+            .map(|(sequence, depth)| {
+                checkpoint(
+                    &signer,
+                    sequence as u64,
+                    Some(sampled(*depth, 0, SignerStatus::Ok)),
+                )
+            })
+            .collect();
+
+        // Rising from #0 alerts at #2 and not again at #3; the drop at #4
+        // starts a new run that alerts at #6
+        let alerts = policy.assess(&chain);
+        assert_eq!(
+            alerts,
+            [
+                HealthAlert {
+                    sequence: 2,
+                    issue: HealthIssue::QueueGrowing { checkpoints: 3 },
+                },
+                HealthAlert {
+                    sequence: 6,
+                    issue: HealthIssue::QueueGrowing { checkpoints: 3 },
+                },
+            ]
+        );
+        // A falling queue is not growing, and a zero window disables the check
+        assert!(policy.assess(&chain[3..5]).is_empty());
+        let disabled = HealthPolicy {
+            growth_window: 0,
+            ..policy
+        };
+        assert!(disabled.assess(&chain).is_empty());
+    }
+}
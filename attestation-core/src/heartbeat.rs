@@ -0,0 +1,137 @@
+//! Heartbeat checkpoints and liveness monitoring.
+//!
+//! An idle robot produces no entries, and without checkpoints its chain goes
+//! quiet: a robot that stopped and one that was switched off to hide
+//! something look the same. Robots therefore emit a heartbeat at a fixed
+//! cadence whenever no other checkpoint was due, built with
+//! [`CheckpointBuilder::heartbeat_from`](crate::CheckpointBuilder::heartbeat_from).
+//! A heartbeat is an ordinary signed checkpoint with `heartbeat` set and a
+//! zero `entries_root`, so [`ChainVerifier`](crate::ChainVerifier) holds it
+//! to the same sequence, counter and `prev_root` rules as any other.
+//!
+//! A [`HeartbeatPolicy`] tells the robot when a heartbeat is due and the
+//! monitoring side when a robot has gone silent, and finds the
+//! [`CoverageGap`]s in a verified chain.
+
+use crate::checkpoint::Checkpoint;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Heartbeat cadence and how late one may be before a robot counts as silent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartbeatPolicy {
+    pub interval: Duration,
+    /// Lateness tolerated on top of `interval`
+    pub grace: Duration,
+}
+
+/// Time between two consecutive checkpoints that exceeds the policy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageGap {
+    /// Sequence of the checkpoint before the gap
+    pub after_sequence: u64,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub from: DateTime<Utc>,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub until: DateTime<Utc>,
+}
+
+impl HeartbeatPolicy {
+    /// Heartbeats every `interval`, with a grace of half an interval.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            grace: interval / 2,
+        }
+    }
+
+    /// Tolerate `grace` of lateness instead.
+    pub fn with_grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    /// Whether a robot whose last checkpoint was at `last` must emit a heartbeat at `now`.
+    pub fn is_due(&self, last: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        now - last >= self.interval
+    }
+
+    /// Whether a robot last heard from at `last_seen` counts as silent at `now`.
+    pub fn is_silent(&self, last_seen: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        now - last_seen > self.interval + self.grace
+    }
+
+    /// Gaps in `checkpoints` (in chain order) longer than the policy allows.
+    pub fn coverage_gaps(&self, checkpoints: &[Checkpoint]) -> Vec<CoverageGap> {
+        checkpoints
+            .windows(2)
+            .filter(|pair| self.is_silent(pair[0].local_timestamp_utc, pair[1].local_timestamp_utc))
+            .map(|pair| CoverageGap {
+                after_sequence: pair[0].sequence,
+                from: pair[0].local_timestamp_utc,
+                until: pair[1].local_timestamp_utc,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::{ChainError, ChainVerifier};
+    use crate::checkpoint::{BuildError, CheckpointBuilder};
+    use crate::crypto::Signer;
+
+    #[test]
+    fn test_heartbeats_chain_and_reveal_gaps() {
+        let signer = Signer::generate();
+        let start = Utc::now() - Duration::hours(1);
+        let genesis = CheckpointBuilder::test_fixture()
+            .timestamp(start)
+            .build_and_sign(signer.signing_key())
+            .unwrap();
+
+        // Idle robot: heartbeats at 0:01 and 0:02, then nothing until 0:10
+        let mut chain = vec![genesis];
+        for minutes in [1, 2, 10] {
+            let prev = chain.last().unwrap();
+            let heartbeat = CheckpointBuilder::heartbeat_from(prev)
+                .unwrap()
+                .monotonic_counter(prev.monotonic_counter + 1)
+                .timestamp(start + Duration::minutes(minutes))
+                .build_and_sign(signer.signing_key())
+                .unwrap();
+            chain.push(heartbeat);
+        }
+        assert!(chain[1].heartbeat && chain[1].entries_root == [0u8; 32]);
+        ChainVerifier::new(signer.verifying_key())
+            .verify_chain(&chain)
+            .unwrap();
+
+        let policy = HeartbeatPolicy::new(Duration::minutes(1));
+        let gaps = policy.coverage_gaps(&chain);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].after_sequence, 2);
+        assert!(policy.is_due(start, start + Duration::minutes(1)));
+        assert!(!policy.is_silent(start, start + Duration::seconds(90)));
+        assert!(policy.is_silent(start, start + Duration::seconds(91)));
+
+        // Heartbeats cannot smuggle entries
+        let err = CheckpointBuilder::heartbeat_from(&chain[3])
+            .unwrap()
+            .monotonic_counter(10)
+            .entries_root([4u8; 32])
+            .build_and_sign(signer.signing_key())
+            .unwrap_err();
+        assert!(matches!(err, BuildError::HeartbeatPayload));
+
+        let mut forged = chain[1].clone();
+        forged.entries_root = [4u8; 32];
+        let signature = signer.sign(&forged.signed_bytes().unwrap());
+        forged.signature = signature.to_bytes().into();
+        let err = ChainVerifier::new(signer.verifying_key())
+            .verify_chain(&[chain[0].clone(), forged])
+            .unwrap_err();
+        assert!(matches!(err, ChainError::HeartbeatPayload { sequence: 1 }));
+    }
+}
@@ -0,0 +1,320 @@
+//! Root history retained after entry garbage collection.
+//!
+//! Raw entries only need to be kept until their checkpoint is anchored;
+//! after that a robot (or gateway) can drop them and keep a
+//! [`RetainedRoot`] per checkpoint instead: the checkpoint hash, its
+//! entries_root, the entry count and the frontier (last entry key). The
+//! retained roots form a Merkle tree of their own, so anyone holding the
+//! history root can be shown that an old entries_root belongs to it
+//! ([`RootHistoryProof`]) without the entries themselves.
+//!
+//! ## Hashing
+//! - Leaf: `SHA-256(canonical CBOR of the RetainedRoot)`
+//! - Nodes and odd levels as in [`crate::merkle`], always with SHA-256
+
+use crate::checkpoint::Checkpoint;
+use crate::crypto::{ct_eq, sha256, DigestAlgorithm};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::merkle::{compute_merkle_root, compute_proof_siblings, reconstruct_root, MerkleTree};
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::tombstone::StoredEntry;
+use crate::types::Hash256;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// What is kept of one checkpoint once its entries are dropped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetainedRoot {
+    pub sequence: u64,
+    pub checkpoint_hash: Hash256,
+    pub entries_root: Hash256,
+    pub entry_count: u64,
+    /// (timestamp_us, nonce) of the checkpoint's last entry
+    pub frontier: Option<(u64, u64)>,
+}
+
+impl RetainedRoot {
+    /// Leaf hash in the history tree.
+    pub fn leaf_hash(&self) -> Result<Hash256, SerializationError> {
+        Ok(sha256(&to_canonical_cbor(self)?))
+    }
+}
+
+/// Retained roots of consecutive checkpoints, oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootHistory {
+    roots: Vec<RetainedRoot>,
+}
+
+impl RootHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `checkpoint`, whose entries are (still) in `tree`.
+    ///
+    /// The tree must reproduce the checkpoint's entries_root, so a history
+    /// never vouches for a root it did not see the entries of.
+    pub fn record(
+        &mut self,
+        checkpoint: &Checkpoint,
+        tree: &MerkleTree,
+    ) -> Result<(), HistoryError> {
+        self.push(checkpoint, tree.root(), tree.len() as u64, tree.frontier())
+    }
+
+    /// Record `checkpoint` from the entries a gateway stores for it, in tree
+    /// order.
+    ///
+    /// Erased entries count but have no key, so the frontier is the key of
+    /// the last entry that was not erased.
+    pub fn record_stored(
+        &mut self,
+        checkpoint: &Checkpoint,
+        entries: &[StoredEntry],
+    ) -> Result<(), HistoryError> {
+        let leaves: Vec<Hash256> = entries.iter().map(StoredEntry::leaf_hash).collect();
+        let frontier = entries
+            .iter()
+            .rev()
+            .find_map(StoredEntry::entry)
+            .map(|entry| (entry.timestamp_us, entry.nonce));
+        self.push(
+            checkpoint,
+            compute_merkle_root(DigestAlgorithm::Sha256, &leaves),
+            entries.len() as u64,
+            frontier,
+        )
+    }
+
+    /// Record `checkpoint` and drop its entries from `tree`.
+    pub fn retire(
+        &mut self,
+        checkpoint: &Checkpoint,
+        tree: &mut MerkleTree,
+    ) -> Result<(), HistoryError> {
+        self.record(checkpoint, tree)?;
+        tree.clear();
+        Ok(())
+    }
+
+    /// Retained roots, oldest first.
+    pub fn roots(&self) -> &[RetainedRoot] {
+        &self.roots
+    }
+
+    /// The retained root of checkpoint `sequence`.
+    pub fn get(&self, sequence: u64) -> Option<&RetainedRoot> {
+        self.position(sequence).map(|index| &self.roots[index])
+    }
+
+    /// Latest frontier over all retained checkpoints.
+    pub fn frontier(&self) -> Option<(u64, u64)> {
+        self.roots.iter().rev().find_map(|root| root.frontier)
+    }
+
+    /// Root of the history tree (zero hash if empty).
+    pub fn root(&self) -> Result<Hash256, HistoryError> {
+        Ok(compute_merkle_root(
+            DigestAlgorithm::Sha256,
+            &self.leaf_hashes()?,
+        ))
+    }
+
+    /// Prove that checkpoint `sequence`'s retained root is in the history.
+    ///
+    /// Returns `Ok(None)` if the checkpoint was never recorded.
+    pub fn prove(&self, sequence: u64) -> Result<Option<RootHistoryProof>, HistoryError> {
+        let Some(index) = self.position(sequence) else {
+            return Ok(None);
+        };
+        let leaves = self.leaf_hashes()?;
+        Ok(Some(RootHistoryProof {
+            retained: self.roots[index].clone(),
+            index,
+            siblings: compute_proof_siblings(DigestAlgorithm::Sha256, &leaves, index),
+            history_root: compute_merkle_root(DigestAlgorithm::Sha256, &leaves),
+        }))
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    fn push(
+        &mut self,
+        checkpoint: &Checkpoint,
+        entries_root: Hash256,
+        entry_count: u64,
+        frontier: Option<(u64, u64)>,
+    ) -> Result<(), HistoryError> {
+        if !ct_eq(&entries_root, &checkpoint.entries_root) {
+            return Err(HistoryError::RootMismatch(checkpoint.sequence));
+        }
+        if let Some(last) = self.roots.last() {
+            if checkpoint.sequence <= last.sequence {
+                return Err(HistoryError::OutOfOrder {
+                    previous: last.sequence,
+                    actual: checkpoint.sequence,
+                });
+            }
+        }
+        self.roots.push(RetainedRoot {
+            sequence: checkpoint.sequence,
+            checkpoint_hash: checkpoint.compute_hash()?,
+            entries_root,
+            entry_count,
+            frontier,
+        });
+        Ok(())
+    }
+
+    fn position(&self, sequence: u64) -> Option<usize> {
+        self.roots
+            .binary_search_by_key(&sequence, |root| root.sequence)
+            .ok()
+    }
+
+    fn leaf_hashes(&self) -> Result<Vec<Hash256>, SerializationError> {
+        self.roots.iter().map(RetainedRoot::leaf_hash).collect()
+    }
+}
+
+/// Proof that a retained root belongs to a root history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootHistoryProof {
+    pub retained: RetainedRoot,
+    pub index: usize,
+    pub siblings: Vec<Hash256>,
+    pub history_root: Hash256,
+}
+
+impl RootHistoryProof {
+    /// Verify this proof against a known history root.
+    pub fn verify(&self, expected_root: &Hash256) -> bool {
+        if !ct_eq(&self.history_root, expected_root) {
+            return false;
+        }
+        match self.retained.leaf_hash() {
+            Ok(leaf) => ct_eq(
+                &reconstruct_root(DigestAlgorithm::Sha256, leaf, self.index, &self.siblings),
+                expected_root,
+            ),
+            Err(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error("Entries do not reproduce the entries_root of checkpoint #{0}")]
+    RootMismatch(u64),
+
+    #[error("Checkpoint #{actual} is not newer than retained checkpoint #{previous}")]
+    OutOfOrder { previous: u64, actual: u64 },
+
+    #[error("Serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+}
+
+impl ErrorCoded for HistoryError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            HistoryError::RootMismatch(_) => ErrorCode::HistoryRootMismatch,
+            HistoryError::OutOfOrder { .. } => ErrorCode::HistoryOutOfOrder,
+            HistoryError::Serialization(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::crypto::Signer;
+    use crate::merkle::Entry;
+
+    /// Five checkpoints of three entries each, retired into a history.
+    fn history(signer: &Signer) -> (RootHistory, Vec<Checkpoint>) {
+        let mut history = RootHistory::new();
+        let mut tree = MerkleTree::new();
+        let mut checkpoints: Vec<Checkpoint> = Vec::new();
+        for sequence in 0..5u64 {
+            for i in 0..3 {
+                tree.insert(Entry::new(
+                    1000 * (3 * sequence + i),
+                    0,
+                    &[sequence as u8, i as u8],
+                ));
+            }
+            let builder = match checkpoints.last() {
+                Some(prev) => CheckpointBuilder::continuing_from(prev).unwrap(),
+                None => CheckpointBuilder::test_fixture(),
+            };
+            let checkpoint = builder
+                .monotonic_counter(sequence + 1)
+                .entries_root(tree.root())
+                .build_and_sign(signer.signing_key())
+                .unwrap();
+            history.retire(&checkpoint, &mut tree).unwrap();
+            assert!(tree.is_empty());
+            checkpoints.push(checkpoint);
+        }
+        (history, checkpoints)
+    }
+
+    #[test]
+    fn test_old_roots_provable_after_entries_dropped() {
+        let signer = Signer::generate();
+        let (history, checkpoints) = history(&signer);
+        let root = history.root().unwrap();
+
+        for checkpoint in &checkpoints {
+            let proof = history.prove(checkpoint.sequence).unwrap().unwrap();
+            assert!(proof.verify(&root));
+            assert_eq!(proof.retained.entries_root, checkpoint.entries_root);
+            assert_eq!(
+                proof.retained.checkpoint_hash,
+                checkpoint.compute_hash().unwrap()
+            );
+            assert_eq!(proof.retained.entry_count, 3);
+        }
+        assert_eq!(history.frontier(), Some((14_000, 0)));
+        assert!(history.prove(9).unwrap().is_none());
+
+        // A substituted root does not verify
+        let mut forged = history.prove(2).unwrap().unwrap();
+        forged.retained.entries_root = [0xee; 32];
+        assert!(!forged.verify(&root));
+
+        let decoded = RootHistory::from_bytes(&history.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.root().unwrap(), root);
+    }
+
+    #[test]
+    fn test_record_requires_matching_entries_in_order() {
+        let signer = Signer::generate();
+        let (mut history, checkpoints) = history(&signer);
+
+        let err = history
+            .record(&checkpoints[4], &MerkleTree::new())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::HistoryRootMismatch);
+
+        let mut stale = RootHistory::new();
+        let mut tree = MerkleTree::new();
+        for i in 12..15 {
+            tree.insert(Entry::new(1000 * i, 0, &[4, (i - 12) as u8]));
+        }
+        stale.record(&checkpoints[4], &tree).unwrap();
+        let err = stale.record(&checkpoints[4], &tree).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::HistoryOutOfOrder);
+        assert_eq!(stale.roots().len(), 1);
+    }
+}
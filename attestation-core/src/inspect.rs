@@ -0,0 +1,331 @@
+//! Human-readable checkpoint summaries.
+//!
+//! Used by the CLI `inspect` command and debug logging instead of raw `Debug`
+//! output: hashes are truncated, and when the previous checkpoint is known the
+//! summary shows elapsed time and what changed in provenance.
+
+use crate::checkpoint::Checkpoint;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Number of leading bytes shown for truncated hashes.
+const SHORT_HASH_BYTES: usize = 8;
+
+/// Structured, printable summary of a checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointSummary {
+    pub robot_id: String,
+    pub mission_id: String,
+    pub sequence: u64,
+    pub monotonic_counter: u64,
+    pub local_timestamp_utc: DateTime<Utc>,
+    pub trust_mode: String,
+    pub model: String,
+    pub model_hash: String,
+    pub firmware_hash: String,
+    pub enclave_measurement: String,
+    pub prev_root: String,
+    pub entries_root: String,
+    /// Mission start/end marker, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mission_event: Option<String>,
+    /// Whether this is a liveness-only heartbeat
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub heartbeat: bool,
+    /// Truncated checkpoint hash (absent if hashing failed)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Delta against the previous checkpoint, if one was supplied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since_previous: Option<CheckpointDelta>,
+}
+
+/// Differences between a checkpoint and its predecessor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointDelta {
+    /// Change in sequence number
+    pub sequence_delta: i128,
+    /// Change in monotonic counter
+    pub counter_delta: i128,
+    /// Time between local timestamps (milliseconds, negative if clock went backwards)
+    pub elapsed_ms: i64,
+    /// Whether `prev_root` matches the previous checkpoint's hash
+    pub prev_root_linked: bool,
+    /// Provenance and configuration fields that changed
+    pub changes: Vec<FieldChange>,
+}
+
+/// A single changed field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl CheckpointSummary {
+    /// Summarize `checkpoint`, optionally relative to its predecessor.
+    pub fn new(checkpoint: &Checkpoint, prev: Option<&Checkpoint>) -> Self {
+        Self {
+            robot_id: checkpoint.robot_id.to_string(),
+            mission_id: checkpoint.mission_id.to_string(),
+            sequence: checkpoint.sequence,
+            monotonic_counter: checkpoint.monotonic_counter,
+            local_timestamp_utc: checkpoint.local_timestamp_utc,
+            trust_mode: checkpoint.trust_mode.to_string(),
+            model: checkpoint.model_provenance.name.clone(),
+            model_hash: short_hex(&checkpoint.model_provenance.model_hash),
+            firmware_hash: short_hex(&checkpoint.firmware_hash),
+            enclave_measurement: short_hex(&checkpoint.enclave_measurement),
+            prev_root: short_hex(&checkpoint.prev_root),
+            entries_root: short_hex(&checkpoint.entries_root),
+            mission_event: checkpoint.mission_event.map(|event| event.to_string()),
+            heartbeat: checkpoint.heartbeat,
+            hash: checkpoint.compute_hash().ok().map(|h| short_hex(&h)),
+            since_previous: prev.map(|prev| CheckpointDelta::between(prev, checkpoint)),
+        }
+    }
+}
+
+impl CheckpointDelta {
+    /// Compute the delta from `prev` to `next`.
+    pub fn between(prev: &Checkpoint, next: &Checkpoint) -> Self {
+        let mut changes = Vec::new();
+        let mut diff = |field: &str, from: String, to: String| {
+            if from != to {
+                changes.push(FieldChange {
+                    field: field.to_string(),
+                    from,
+                    to,
+                });
+            }
+        };
+
+        let (a, b) = (&prev.model_provenance, &next.model_provenance);
+        diff("model.name", a.name.clone(), b.name.clone());
+        diff(
+            "model.model_hash",
+            short_hex(&a.model_hash),
+            short_hex(&b.model_hash),
+        );
+        diff(
+            "model.dataset_hash",
+            a.dataset_hash
+                .as_ref()
+                .map(|h| short_hex(h))
+                .unwrap_or_default(),
+            b.dataset_hash
+                .as_ref()
+                .map(|h| short_hex(h))
+                .unwrap_or_default(),
+        );
+        diff(
+            "model.container_digest",
+            a.container_digest.clone().unwrap_or_default(),
+            b.container_digest.clone().unwrap_or_default(),
+        );
+        diff(
+            "firmware_hash",
+            short_hex(&prev.firmware_hash),
+            short_hex(&next.firmware_hash),
+        );
+        diff(
+            "enclave_measurement",
+            short_hex(&prev.enclave_measurement),
+            short_hex(&next.enclave_measurement),
+        );
+        diff(
+            "hardware_inventory",
+            prev.hardware_inventory
+                .as_ref()
+                .map(|h| short_hex(h))
+                .unwrap_or_default(),
+            next.hardware_inventory
+                .as_ref()
+                .map(|h| short_hex(h))
+                .unwrap_or_default(),
+        );
+        diff(
+            "redaction_policy",
+            prev.redaction_policy
+                .as_ref()
+                .map(|h| short_hex(h))
+                .unwrap_or_default(),
+            next.redaction_policy
+                .as_ref()
+                .map(|h| short_hex(h))
+                .unwrap_or_default(),
+        );
+        diff(
+            "trust_mode",
+            prev.trust_mode.to_string(),
+            next.trust_mode.to_string(),
+        );
+        diff(
+            "mission_id",
+            prev.mission_id.to_string(),
+            next.mission_id.to_string(),
+        );
+        diff(
+            "inference_config",
+            format!("{:?}", prev.inference_config),
+            format!("{:?}", next.inference_config),
+        );
+
+        Self {
+            sequence_delta: next.sequence as i128 - prev.sequence as i128,
+            counter_delta: next.monotonic_counter as i128 - prev.monotonic_counter as i128,
+            elapsed_ms: (next.local_timestamp_utc - prev.local_timestamp_utc).num_milliseconds(),
+            prev_root_linked: prev.compute_hash().is_ok_and(|h| h == next.prev_root),
+            changes,
+        }
+    }
+}
+
+impl fmt::Display for CheckpointSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Checkpoint #{} ({} / {})",
+            self.sequence, self.robot_id, self.mission_id
+        )?;
+        if let Some(hash) = &self.hash {
+            writeln!(f, "  hash:              {}", hash)?;
+        }
+        writeln!(
+            f,
+            "  timestamp:         {}",
+            self.local_timestamp_utc.to_rfc3339()
+        )?;
+        writeln!(f, "  monotonic counter: {}", self.monotonic_counter)?;
+        writeln!(f, "  trust mode:        {}", self.trust_mode)?;
+        writeln!(
+            f,
+            "  model:             {} ({})",
+            self.model, self.model_hash
+        )?;
+        writeln!(f, "  firmware:          {}", self.firmware_hash)?;
+        writeln!(f, "  enclave:           {}", self.enclave_measurement)?;
+        writeln!(f, "  prev_root:         {}", self.prev_root)?;
+        write!(f, "  entries_root:      {}", self.entries_root)?;
+        if let Some(event) = &self.mission_event {
+            write!(f, "\n  mission event:     {}", event)?;
+        }
+        if self.heartbeat {
+            write!(f, "\n  heartbeat:         yes")?;
+        }
+
+        if let Some(delta) = &self.since_previous {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "  since previous:    {:+} seq, {:+} counter, {} ms{}",
+                delta.sequence_delta,
+                delta.counter_delta,
+                delta.elapsed_ms,
+                if delta.prev_root_linked {
+                    ""
+                } else {
+                    " (prev_root NOT linked)"
+                }
+            )?;
+            if delta.changes.is_empty() {
+                write!(f, "  changes:           none")?;
+            } else {
+                write!(f, "  changes:")?;
+                for change in &delta.changes {
+                    write!(
+                        f,
+                        "\n    {}: {} -> {}",
+                        change.field, change.from, change.to
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Hex-encode the first few bytes of `bytes`, marking truncation with "…".
+pub fn short_hex(bytes: &[u8]) -> String {
+    let mut out: String = bytes
+        .iter()
+        .take(SHORT_HASH_BYTES)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    if bytes.len() > SHORT_HASH_BYTES {
+        out.push('…');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::types::*;
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+
+    fn first_checkpoint(key: &SigningKey) -> Checkpoint {
+        CheckpointBuilder::test_fixture()
+            .sequence(1)
+            .monotonic_counter(10)
+            .model_provenance(ModelProvenance {
+                name: "model-v1".to_string(),
+                model_hash: [0xAB; 32],
+                dataset_hash: None,
+                container_digest: None,
+                signature_bundle: None,
+            })
+            .build_and_sign(key)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_short_hex() {
+        assert_eq!(short_hex(&[0xAB; 4]), "abababab");
+        assert_eq!(short_hex(&[0xAB; 32]), "abababababababab…");
+    }
+
+    #[test]
+    fn test_describe_standalone() {
+        let key = SigningKey::generate(&mut OsRng);
+        let checkpoint = first_checkpoint(&key);
+
+        let summary = checkpoint.describe();
+        assert_eq!(summary.sequence, 1);
+        assert_eq!(summary.model_hash, "abababababababab…");
+        assert!(summary.since_previous.is_none());
+        assert!(summary
+            .to_string()
+            .contains("Checkpoint #1 (R-001 / M-001)"));
+    }
+
+    #[test]
+    fn test_describe_since_reports_provenance_changes() {
+        let key = SigningKey::generate(&mut OsRng);
+        let prev = first_checkpoint(&key);
+
+        let mut provenance = prev.model_provenance.clone();
+        provenance.name = "model-v2".to_string();
+        let next = CheckpointBuilder::continuing_from(&prev)
+            .unwrap()
+            .model_provenance(provenance)
+            .timestamp(prev.local_timestamp_utc + chrono::Duration::seconds(5))
+            .monotonic_counter(12)
+            .entries_root([4u8; 32])
+            .build_and_sign(&key)
+            .unwrap();
+
+        let delta = next.describe_since(&prev).since_previous.unwrap();
+        assert_eq!(delta.sequence_delta, 1);
+        assert_eq!(delta.counter_delta, 2);
+        assert_eq!(delta.elapsed_ms, 5000);
+        assert!(delta.prev_root_linked);
+        assert_eq!(delta.changes.len(), 1);
+        assert_eq!(delta.changes[0].field, "model.name");
+    }
+}
@@ -0,0 +1,404 @@
+//! Per-robot hardware root-of-trust inventory.
+//!
+//! A `HardwareInventory` is a signed record of a robot's attested hardware
+//! (TEE type, TPM endorsement key, firmware version, secure-boot state).
+//! Checkpoints reference it by hash, so a verifier walking a chain can tell
+//! when the hardware behind a robot identity changed.
+
+use crate::checkpoint::Checkpoint;
+use crate::crypto::{sha256, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, KeyId, RobotId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Inventory version (for schema evolution)
+pub const INVENTORY_VERSION: u8 = 1;
+
+/// Secure-boot state reported by the platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecureBootState {
+    Enabled,
+    Disabled,
+    Unknown,
+}
+
+/// Attested hardware of a single robot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareProfile {
+    /// TEE vendor (matches `AttestationAdapter::vendor_name`, e.g., "intel-sgx")
+    pub tee_type: String,
+    /// SHA-256 of the TPM endorsement key (public part), if the robot has a TPM
+    pub tpm_ek_hash: Option<Hash256>,
+    /// Platform firmware version string
+    pub firmware_version: String,
+    /// Secure-boot state at the time of recording
+    pub secure_boot: SecureBootState,
+}
+
+impl HardwareProfile {
+    /// Names of the fields that differ between `self` and `other`.
+    pub fn changed_fields(&self, other: &HardwareProfile) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.tee_type != other.tee_type {
+            changed.push("tee_type");
+        }
+        if self.tpm_ek_hash != other.tpm_ek_hash {
+            changed.push("tpm_ek_hash");
+        }
+        if self.firmware_version != other.firmware_version {
+            changed.push("firmware_version");
+        }
+        if self.secure_boot != other.secure_boot {
+            changed.push("secure_boot");
+        }
+        changed
+    }
+}
+
+/// A signed hardware inventory document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareInventory {
+    /// Schema version
+    pub version: u8,
+    /// Robot the hardware belongs to
+    pub robot_id: RobotId,
+    /// Attested hardware
+    pub profile: HardwareProfile,
+    /// When the inventory was recorded
+    #[serde(with = "crate::serialization::timestamp")]
+    pub recorded_at: DateTime<Utc>,
+    /// Fingerprint of the signing key
+    pub signer_key_id: KeyId,
+    /// Ed25519 signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned inventory (for signature computation)
+#[derive(Serialize)]
+struct UnsignedInventory<'a> {
+    version: u8,
+    robot_id: &'a RobotId,
+    profile: &'a HardwareProfile,
+    #[serde(with = "crate::serialization::timestamp")]
+    recorded_at: DateTime<Utc>,
+    signer_key_id: KeyId,
+}
+
+impl HardwareInventory {
+    /// Record and sign the hardware profile of `robot_id`.
+    pub fn issue(
+        robot_id: RobotId,
+        profile: HardwareProfile,
+        signer: &Signer,
+    ) -> Result<Self, InventoryError> {
+        let mut inventory = Self {
+            version: INVENTORY_VERSION,
+            robot_id,
+            profile,
+            recorded_at: Utc::now(),
+            signer_key_id: signer.key_id(),
+            signature: SignatureBytes([0u8; 64]),
+        };
+
+        let signature = signer.sign(&inventory.signing_payload()?);
+        inventory.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(inventory)
+    }
+
+    /// Verify the signature using a key looked up by `signer_key_id`.
+    pub fn verify(&self, keys: &dyn KeyResolver) -> Result<(), InventoryError> {
+        use ed25519_dalek::Verifier;
+
+        let verifying_key = keys
+            .resolve(&self.signer_key_id)
+            .ok_or(InventoryError::UnknownSigner(self.signer_key_id))?;
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        verifying_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| InventoryError::InvalidSignature)
+    }
+
+    /// Hash of the signed document, as referenced from checkpoints.
+    pub fn compute_hash(&self) -> Result<Hash256, SerializationError> {
+        Ok(sha256(&to_canonical_cbor(self)?))
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedInventory {
+            version: self.version,
+            robot_id: &self.robot_id,
+            profile: &self.profile,
+            recorded_at: self.recorded_at,
+            signer_key_id: self.signer_key_id,
+        })
+    }
+}
+
+/// A change of hardware behind a robot identity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HardwareSwap {
+    pub robot_id: RobotId,
+    /// Hash of the inventory in effect before the change
+    pub previous: Hash256,
+    /// Hash of the inventory in effect after the change
+    pub current: Hash256,
+    /// Profile fields that changed
+    pub changed: Vec<&'static str>,
+}
+
+/// In-memory record of verified hardware inventories, per robot.
+pub struct InventoryRegistry {
+    keys: Box<dyn KeyResolver>,
+    documents: HashMap<Hash256, HardwareInventory>,
+    history: HashMap<RobotId, Vec<Hash256>>,
+}
+
+impl InventoryRegistry {
+    /// Create a registry accepting inventories signed by keys in `keys`.
+    pub fn new(keys: Box<dyn KeyResolver>) -> Self {
+        Self {
+            keys,
+            documents: HashMap::new(),
+            history: HashMap::new(),
+        }
+    }
+
+    /// Verify and record an inventory, returning its hash.
+    ///
+    /// Also returns the hardware swap if the robot's profile differs from its
+    /// previously recorded one.
+    pub fn record(
+        &mut self,
+        inventory: HardwareInventory,
+    ) -> Result<(Hash256, Option<HardwareSwap>), InventoryError> {
+        inventory.verify(self.keys.as_ref())?;
+        let hash = inventory.compute_hash()?;
+
+        let previous = self
+            .history
+            .get(&inventory.robot_id)
+            .and_then(|hashes| hashes.last());
+        let swap = previous.and_then(|previous| {
+            let changed = self.documents[previous]
+                .profile
+                .changed_fields(&inventory.profile);
+            (!changed.is_empty()).then(|| HardwareSwap {
+                robot_id: inventory.robot_id.clone(),
+                previous: *previous,
+                current: hash,
+                changed,
+            })
+        });
+
+        self.history
+            .entry(inventory.robot_id.clone())
+            .or_default()
+            .push(hash);
+        self.documents.insert(hash, inventory);
+        Ok((hash, swap))
+    }
+
+    /// Look up an inventory by hash.
+    pub fn get(&self, hash: &Hash256) -> Option<&HardwareInventory> {
+        self.documents.get(hash)
+    }
+
+    /// The most recently recorded inventory of `robot_id`.
+    pub fn current(&self, robot_id: &RobotId) -> Option<&HardwareInventory> {
+        self.history
+            .get(robot_id)
+            .and_then(|hashes| hashes.last())
+            .and_then(|hash| self.documents.get(hash))
+    }
+
+    /// All recorded inventories of `robot_id`, oldest first.
+    pub fn history(&self, robot_id: &RobotId) -> Vec<&HardwareInventory> {
+        self.history
+            .get(robot_id)
+            .map(|hashes| {
+                hashes
+                    .iter()
+                    .filter_map(|hash| self.documents.get(hash))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Resolve the inventory a checkpoint references, checking it belongs to the same robot.
+    pub fn resolve_checkpoint(
+        &self,
+        checkpoint: &Checkpoint,
+    ) -> Result<&HardwareInventory, InventoryError> {
+        let hash = checkpoint
+            .hardware_inventory
+            .ok_or(InventoryError::MissingReference)?;
+        let inventory = self
+            .get(&hash)
+            .ok_or(InventoryError::UnknownInventory(hash))?;
+        if inventory.robot_id != checkpoint.robot_id {
+            return Err(InventoryError::RobotMismatch {
+                expected: checkpoint.robot_id.clone(),
+                actual: inventory.robot_id.clone(),
+            });
+        }
+        Ok(inventory)
+    }
+
+    /// Detect a hardware swap between two consecutive checkpoints of a robot.
+    pub fn detect_swap(
+        &self,
+        prev: &Checkpoint,
+        next: &Checkpoint,
+    ) -> Result<Option<HardwareSwap>, InventoryError> {
+        let before = self.resolve_checkpoint(prev)?;
+        let after = self.resolve_checkpoint(next)?;
+
+        let changed = before.profile.changed_fields(&after.profile);
+        if changed.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(HardwareSwap {
+            robot_id: next.robot_id.clone(),
+            previous: prev.hardware_inventory.unwrap_or_default(),
+            current: next.hardware_inventory.unwrap_or_default(),
+            changed,
+        }))
+    }
+}
+
+impl std::fmt::Debug for InventoryRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InventoryRegistry")
+            .field("documents", &self.documents.len())
+            .field("robots", &self.history.len())
+            .finish()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum InventoryError {
+    #[error("Inventory serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Inventory signed by unknown key {0}")]
+    UnknownSigner(KeyId),
+
+    #[error("Invalid inventory signature")]
+    InvalidSignature,
+
+    #[error("Checkpoint does not reference a hardware inventory")]
+    MissingReference,
+
+    #[error("Unknown hardware inventory {}", crate::inspect::short_hex(.0))]
+    UnknownInventory(Hash256),
+
+    #[error("Inventory robot mismatch: expected {expected}, got {actual}")]
+    RobotMismatch { expected: RobotId, actual: RobotId },
+}
+
+impl ErrorCoded for InventoryError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            InventoryError::Serialization(e) => e.code(),
+            InventoryError::UnknownSigner(_) => ErrorCode::InventoryUnknownSigner,
+            InventoryError::InvalidSignature => ErrorCode::InventoryInvalidSignature,
+            InventoryError::MissingReference => ErrorCode::InventoryMissing,
+            InventoryError::UnknownInventory(_) => ErrorCode::InventoryUnknown,
+            InventoryError::RobotMismatch { .. } => ErrorCode::InventoryRobotMismatch,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+
+    fn profile(firmware_version: &str) -> HardwareProfile {
+        HardwareProfile {
+            tee_type: "intel-sgx".to_string(),
+            tpm_ek_hash: Some([7u8; 32]),
+            firmware_version: firmware_version.to_string(),
+            secure_boot: SecureBootState::Enabled,
+        }
+    }
+
+    fn checkpoint(signer: &Signer, sequence: u64, inventory: Hash256) -> Checkpoint {
+        CheckpointBuilder::test_fixture()
+            .sequence(sequence)
+            .monotonic_counter(sequence)
+            .hardware_inventory(inventory)
+            .build_and_sign(signer.signing_key())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_record_and_query() {
+        let signer = Signer::generate();
+        let robot = RobotId("R-001".to_string());
+        let mut registry = InventoryRegistry::new(Box::new(signer.verifying_key()));
+
+        let inventory = HardwareInventory::issue(robot.clone(), profile("1.0"), &signer).unwrap();
+        let (hash, swap) = registry.record(inventory.clone()).unwrap();
+        assert!(swap.is_none());
+        assert_eq!(registry.get(&hash), Some(&inventory));
+        assert_eq!(registry.current(&robot), Some(&inventory));
+
+        let decoded = HardwareInventory::from_bytes(&inventory.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.compute_hash().unwrap(), hash);
+    }
+
+    #[test]
+    fn test_swap_detected() {
+        let signer = Signer::generate();
+        let robot = RobotId("R-001".to_string());
+        let mut registry = InventoryRegistry::new(Box::new(signer.verifying_key()));
+
+        let (first, _) = registry
+            .record(HardwareInventory::issue(robot.clone(), profile("1.0"), &signer).unwrap())
+            .unwrap();
+        let (second, swap) = registry
+            .record(HardwareInventory::issue(robot.clone(), profile("2.0"), &signer).unwrap())
+            .unwrap();
+        assert_eq!(swap.unwrap().changed, vec!["firmware_version"]);
+        assert_eq!(registry.history(&robot).len(), 2);
+
+        let before = checkpoint(&signer, 1, first);
+        let after = checkpoint(&signer, 2, second);
+        let swap = registry.detect_swap(&before, &after).unwrap().unwrap();
+        assert_eq!((swap.previous, swap.current), (first, second));
+        assert!(registry.detect_swap(&before, &before).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rejects_untrusted_and_unknown() {
+        let signer = Signer::generate();
+        let robot = RobotId("R-001".to_string());
+        let mut registry = InventoryRegistry::new(Box::new(Signer::generate().verifying_key()));
+
+        let inventory = HardwareInventory::issue(robot, profile("1.0"), &signer).unwrap();
+        let err = registry.record(inventory).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InventoryUnknownSigner);
+
+        let err = registry
+            .resolve_checkpoint(&checkpoint(&signer, 1, [9u8; 32]))
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InventoryUnknown);
+    }
+}
@@ -0,0 +1,345 @@
+//! Known-answer test vectors.
+//!
+//! Third-party implementations (gateway ports, on-robot agents in other
+//! languages, smart-contract verifiers) must agree with this crate bit for
+//! bit. [`KatSuite::generate`] produces a fixed set of vectors from fixed
+//! keys, entries and timestamps: leaf hashes, Merkle roots, the canonical
+//! CBOR of each signed checkpoint, its hash and its signature (Ed25519 is
+//! deterministic, so signatures are reproducible too). [`KatSuite::validate`]
+//! recomputes every expected value from a suite's inputs, so a suite written
+//! by another implementation can be checked against this one.
+//!
+//! All byte strings are lowercase hex so suites can be exchanged as JSON
+//! (`veribot kat generate`).
+
+use crate::checkpoint::{Checkpoint, CheckpointBuilder};
+use crate::crypto::key_id;
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::merkle::{Entry, MerkleTree};
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{DeterminismConfig, MissionId, ModelProvenance, RobotId, TrustMode};
+use chrono::{TimeZone, Utc};
+use ed25519_dalek::SigningKey;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Suite version; bumped whenever an encoding changes on purpose.
+pub const KAT_VERSION: u8 = 2;
+
+/// A set of known-answer vectors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KatSuite {
+    pub version: u8,
+    pub vectors: Vec<KnownAnswer>,
+}
+
+/// One checkpoint with its inputs and expected outputs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KnownAnswer {
+    pub name: String,
+    /// Ed25519 secret key seed
+    pub secret_key: String,
+    pub public_key: String,
+    pub key_id: String,
+    pub entries: Vec<KnownEntry>,
+    pub merkle_root: String,
+    /// Canonical CBOR of the signed checkpoint
+    pub checkpoint_cbor: String,
+    pub checkpoint_hash: String,
+    pub signature: String,
+}
+
+/// A log entry with its expected leaf hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KnownEntry {
+    pub timestamp_us: u64,
+    pub nonce: u64,
+    pub data: String,
+    pub leaf_hash: String,
+}
+
+impl KatSuite {
+    /// The reference vectors.
+    pub fn generate() -> Result<Self, KatError> {
+        let key = SigningKey::from_bytes(&[0x42; 32]);
+        let rotated = SigningKey::from_bytes(&[0x07; 32]);
+        let records: [&[u8]; 4] = [b"", b"lidar:ok", b"plan:dock", b"\x00\xff binary"];
+
+        let genesis = reference_builder()
+            .sequence(0)
+            .monotonic_counter(1)
+            .prev_root([0u8; 32]);
+        let single = vector("single-entry", &key, &records[..1], genesis)?;
+
+        let (prev, _) = decode(&single)?;
+        let next = CheckpointBuilder::continuing_from(&prev)?
+            .monotonic_counter(2)
+            .timestamp(prev.local_timestamp_utc + chrono::Duration::seconds(60));
+        let odd = vector("odd-tree-continuation", &key, &records[..3], next)?;
+
+        let optional = reference_builder()
+            .sequence(7)
+            .monotonic_counter(1_000_000)
+            .prev_root([0xab; 32])
+            .hardware_inventory([0x11; 32])
+            .redaction_policy([0x22; 32])
+            .mission_start([0x33; 32])
+            .trust_mode(TrustMode::SoftAttestation);
+        let full = vector("optional-fields", &rotated, &records, optional)?;
+
+        Ok(Self {
+            version: KAT_VERSION,
+            vectors: vec![single, odd, full],
+        })
+    }
+
+    /// Recompute every expected value from each vector's inputs.
+    pub fn validate(&self) -> Result<(), KatError> {
+        if self.version != KAT_VERSION {
+            return Err(KatError::UnsupportedVersion(self.version));
+        }
+        for known in &self.vectors {
+            known.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+}
+
+impl KnownAnswer {
+    fn validate(&self) -> Result<(), KatError> {
+        let mismatch = |field| KatError::Mismatch {
+            vector: self.name.clone(),
+            field,
+        };
+
+        let seed: [u8; 32] = self
+            .bytes("secret_key", &self.secret_key)?
+            .try_into()
+            .map_err(|_| self.malformed("secret_key"))?;
+        let verifying_key = SigningKey::from_bytes(&seed).verifying_key();
+        if hex::encode(verifying_key.as_bytes()) != self.public_key {
+            return Err(mismatch("public_key"));
+        }
+        if key_id(&verifying_key).to_string() != self.key_id {
+            return Err(mismatch("key_id"));
+        }
+
+        let mut tree = MerkleTree::new();
+        for entry in &self.entries {
+            let leaf = Entry::new(
+                entry.timestamp_us,
+                entry.nonce,
+                &self.bytes("data", &entry.data)?,
+            );
+            if hex::encode(leaf.hash()) != entry.leaf_hash {
+                return Err(mismatch("leaf_hash"));
+            }
+            tree.insert(leaf);
+        }
+        if hex::encode(tree.root()) != self.merkle_root {
+            return Err(mismatch("merkle_root"));
+        }
+
+        let (checkpoint, cbor) = decode(self)?;
+        if checkpoint.to_bytes()? != cbor {
+            return Err(mismatch("checkpoint_cbor"));
+        }
+        if hex::encode(checkpoint.entries_root) != self.merkle_root {
+            return Err(mismatch("checkpoint_cbor"));
+        }
+        if hex::encode(checkpoint.compute_hash()?) != self.checkpoint_hash {
+            return Err(mismatch("checkpoint_hash"));
+        }
+        if hex::encode(checkpoint.signature.as_ref()) != self.signature
+            || checkpoint.verify_signature(&verifying_key).is_err()
+        {
+            return Err(mismatch("signature"));
+        }
+        Ok(())
+    }
+
+    fn bytes(&self, field: &'static str, value: &str) -> Result<Vec<u8>, KatError> {
+        hex::decode(value).map_err(|_| self.malformed(field))
+    }
+
+    fn malformed(&self, field: &'static str) -> KatError {
+        KatError::Malformed {
+            vector: self.name.clone(),
+            field,
+        }
+    }
+}
+
+/// Builder with the fields shared by every reference checkpoint.
+fn reference_builder() -> CheckpointBuilder {
+    CheckpointBuilder::new()
+        .robot_id(RobotId("KAT-ROBOT-1".to_string()))
+        .mission_id(MissionId("KAT-MISSION-1".to_string()))
+        .timestamp(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap())
+        .model_provenance(ModelProvenance {
+            name: "kat-model".to_string(),
+            model_hash: [0x01; 32],
+            dataset_hash: Some([0x02; 32]),
+            container_digest: Some("sha256:kat".to_string()),
+            signature_bundle: None,
+        })
+        .firmware_hash([0x03; 32])
+        .enclave_measurement(vec![0x04; 48])
+        .inference_config(DeterminismConfig {
+            rng_seed: Some(42),
+            batch_size: 1,
+            flags: None,
+        })
+}
+
+fn vector(
+    name: &str,
+    key: &SigningKey,
+    records: &[&[u8]],
+    builder: CheckpointBuilder,
+) -> Result<KnownAnswer, KatError> {
+    let entries: Vec<Entry> = records
+        .iter()
+        .enumerate()
+        .map(|(i, data)| Entry::new(1_735_689_600_000_000 + i as u64 * 1000, i as u64, data))
+        .collect();
+    let mut tree = MerkleTree::new();
+    for entry in &entries {
+        tree.insert(entry.clone());
+    }
+
+    let checkpoint = builder.entries_root(tree.root()).build_and_sign(key)?;
+    Ok(KnownAnswer {
+        name: name.to_string(),
+        secret_key: hex::encode(key.to_bytes()),
+        public_key: hex::encode(key.verifying_key().as_bytes()),
+        key_id: key_id(&key.verifying_key()).to_string(),
+        entries: entries
+            .iter()
+            .zip(records)
+            .map(|(entry, data)| KnownEntry {
+                timestamp_us: entry.timestamp_us,
+                nonce: entry.nonce,
+                data: hex::encode(data),
+                leaf_hash: hex::encode(entry.hash()),
+            })
+            .collect(),
+        merkle_root: hex::encode(tree.root()),
+        checkpoint_cbor: hex::encode(checkpoint.to_bytes()?),
+        checkpoint_hash: hex::encode(checkpoint.compute_hash()?),
+        signature: hex::encode(checkpoint.signature.as_ref()),
+    })
+}
+
+/// Decode a vector's checkpoint, returning it with its raw bytes.
+fn decode(known: &KnownAnswer) -> Result<(Checkpoint, Vec<u8>), KatError> {
+    let cbor = known.bytes("checkpoint_cbor", &known.checkpoint_cbor)?;
+    Ok((Checkpoint::from_bytes(&cbor)?, cbor))
+}
+
+#[derive(Debug, Error)]
+pub enum KatError {
+    #[error("Test vector serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Reference checkpoint could not be built: {0}")]
+    Build(#[from] crate::checkpoint::BuildError),
+
+    #[error("Unsupported test vector suite version {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("Test vector {vector:?}: {field} is not valid hex of the right length")]
+    Malformed { vector: String, field: &'static str },
+
+    #[error("Test vector {vector:?}: {field} does not match the reference implementation")]
+    Mismatch { vector: String, field: &'static str },
+}
+
+impl ErrorCoded for KatError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            KatError::Serialization(e) => e.code(),
+            KatError::Build(e) => e.code(),
+            KatError::UnsupportedVersion(_)
+            | KatError::Malformed { .. }
+            | KatError::Mismatch { .. } => ErrorCode::KnownAnswerMismatch,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_suite_validates_and_is_stable() {
+        let suite = KatSuite::generate().unwrap();
+        suite.validate().unwrap();
+        assert_eq!(suite, KatSuite::generate().unwrap());
+        assert_eq!(
+            KatSuite::from_bytes(&suite.to_bytes().unwrap()).unwrap(),
+            suite
+        );
+
+        // The continuation vector chains onto the genesis vector
+        let (genesis, _) = decode(&suite.vectors[0]).unwrap();
+        let (next, _) = decode(&suite.vectors[1]).unwrap();
+        assert_eq!(next.prev_root, genesis.compute_hash().unwrap());
+        assert_eq!(
+            hex::encode(genesis.compute_hash().unwrap()),
+            suite.vectors[0].checkpoint_hash
+        );
+    }
+
+    #[test]
+    fn test_pinned_reference_values() {
+        // Changing any of these is a wire-format break: bump KAT_VERSION.
+        // A one-leaf tree's root is the leaf hash itself.
+        let suite = KatSuite::generate().unwrap();
+        let single = &suite.vectors[0];
+        assert_eq!(single.entries[0].leaf_hash, PINNED_SINGLE_LEAF);
+        assert_eq!(single.merkle_root, PINNED_SINGLE_LEAF);
+        assert_eq!(single.checkpoint_hash, PINNED_GENESIS_HASH);
+    }
+
+    #[test]
+    fn test_detects_divergent_implementation() {
+        let mut suite = KatSuite::generate().unwrap();
+        suite.vectors[1].entries[2].leaf_hash = "00".repeat(32);
+        let err = suite.validate().unwrap_err();
+        assert!(matches!(
+            err,
+            KatError::Mismatch {
+                field: "leaf_hash",
+                ..
+            }
+        ));
+        assert_eq!(err.code(), ErrorCode::KnownAnswerMismatch);
+
+        let mut suite = KatSuite::generate().unwrap();
+        suite.vectors[2].signature.replace_range(0..2, "ff");
+        let err = suite.validate().unwrap_err();
+        assert!(matches!(
+            err,
+            KatError::Mismatch {
+                field: "signature",
+                ..
+            }
+        ));
+    }
+
+    const PINNED_SINGLE_LEAF: &str =
+        "cffd66f1907a527cfa72d2152cc67618e02371151c33130fd4dbd36f7323509c";
+    const PINNED_GENESIS_HASH: &str =
+        "9891cb19dfb471ef475d1403a076165cf9cbbd24c7a938bb5ff20bace3bf6272";
+}
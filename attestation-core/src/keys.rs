@@ -0,0 +1,94 @@
+//! Verifying key lookup by key identifier.
+//!
+//! Checkpoints name their signer by [`KeyId`] (a SHA-256 fingerprint of the
+//! verifying key), so verifiers can resolve the right key from a fleet
+//! registry instead of having it supplied out-of-band for every call.
+
+use crate::crypto::key_id;
+use crate::types::KeyId;
+use ed25519_dalek::VerifyingKey;
+use std::collections::HashMap;
+
+/// Resolves verifying keys by key identifier.
+pub trait KeyResolver: Send + Sync {
+    /// Look up the verifying key for `key_id`, if known.
+    fn resolve(&self, key_id: &KeyId) -> Option<VerifyingKey>;
+}
+
+/// A single key resolves only its own identifier.
+impl KeyResolver for VerifyingKey {
+    fn resolve(&self, id: &KeyId) -> Option<VerifyingKey> {
+        (key_id(self) == *id).then_some(*self)
+    }
+}
+
+/// In-memory set of known verifying keys (e.g., loaded from a fleet registry).
+#[derive(Debug, Clone, Default)]
+pub struct KeyRing {
+    keys: HashMap<KeyId, VerifyingKey>,
+}
+
+impl KeyRing {
+    /// Create an empty key ring.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a verifying key, returning its identifier.
+    pub fn insert(&mut self, key: VerifyingKey) -> KeyId {
+        let id = key_id(&key);
+        self.keys.insert(id, key);
+        id
+    }
+
+    /// Remove a key by identifier.
+    pub fn remove(&mut self, id: &KeyId) -> Option<VerifyingKey> {
+        self.keys.remove(id)
+    }
+
+    /// Number of keys in the ring.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Check if the ring is empty.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl KeyResolver for KeyRing {
+    fn resolve(&self, id: &KeyId) -> Option<VerifyingKey> {
+        self.keys.get(id).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Signer;
+
+    #[test]
+    fn test_single_key_resolver() {
+        let signer = Signer::generate();
+        let key = signer.verifying_key();
+
+        assert_eq!(key.resolve(&signer.key_id()), Some(key));
+        assert_eq!(key.resolve(&Signer::generate().key_id()), None);
+    }
+
+    #[test]
+    fn test_key_ring() {
+        let a = Signer::generate();
+        let b = Signer::generate();
+
+        let mut ring = KeyRing::new();
+        ring.insert(a.verifying_key());
+        ring.insert(b.verifying_key());
+        assert_eq!(ring.len(), 2);
+        assert_eq!(ring.resolve(&b.key_id()), Some(b.verifying_key()));
+
+        ring.remove(&b.key_id());
+        assert_eq!(ring.resolve(&b.key_id()), None);
+    }
+}
@@ -8,18 +8,199 @@
 //! - **Anti-rollback**: Monotonic counters + prev_root chaining
 //! - **Multi-vendor attestation**: Pluggable adapter interface
 //! - **Merkle trees**: Incremental, sorted by timestamp+nonce
+//!
+//! ## Async runtimes
+//! Nothing here depends on an executor. Async traits are plain
+//! `async-trait` futures, and code that waits (e.g. [`attestation::retry`])
+//! sleeps through a [`attestation::SleepFn`] supplied by the caller, so the
+//! crate runs under tokio, async-std or an embedded executor alike.
 
+pub mod abi;
+pub mod anchor;
+pub mod approval;
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
 pub mod attestation;
+pub mod audit;
+pub mod auth;
+pub mod backfill;
+pub mod budget;
+pub mod chain;
 pub mod checkpoint;
+pub mod clock;
+pub mod collateral;
+pub mod config;
+pub mod conformance;
+pub mod consistency;
+pub mod countersign;
 pub mod crypto;
+pub mod custody;
+pub mod dedup;
+pub mod delegation;
+pub mod endorsement;
+pub mod erasure;
+pub mod error;
+pub mod escrow;
+pub mod events;
+pub mod forensic;
+pub mod freshness;
+pub mod gap;
+pub mod health;
+pub mod heartbeat;
+pub mod history;
+pub mod inspect;
+pub mod inventory;
+pub mod kat;
+pub mod keys;
 pub mod merkle;
+pub mod mission;
+pub mod monitor;
+pub mod nonce;
+pub mod notarization;
+pub mod operator;
+pub mod quarantine;
+pub mod quorum;
+pub mod ratelimit;
+pub mod receipt;
+pub mod release;
+pub mod reload;
+pub mod replication;
+pub mod result;
+pub mod retention;
+pub mod rollback;
+pub mod rotation;
+pub mod search;
 pub mod serialization;
+pub mod shard;
+pub mod spool;
+pub mod subkey;
+pub mod summary;
+pub mod sync;
+pub mod tenant;
+pub mod tombstone;
+pub mod transparency;
 pub mod types;
 
-pub use attestation::{AttestationAdapter, AttestationError, AttestationRegistry};
+pub use abi::AbiError;
+pub use anchor::{
+    AnchorChain, AnchorConfig, AnchorError, AnchorRecord, AnchorStatus, AnchorTracker, Orphaned,
+    TxInclusion,
+};
+pub use approval::{
+    Approval, ApprovalError, ApprovalLog, ApprovalProof, ArtifactApproval, ArtifactKind,
+    PublishedApprovals,
+};
+pub use attestation::{
+    identify_evidence, retry, AttestationAdapter, AttestationError, AttestationRegistry,
+    ErrorCategory, EvidenceKind, RetryPolicy, SleepFn,
+};
+pub use audit::{AuditError, AuditEvent, AuditExport, AuditLog, AuditSink, Decision};
+pub use auth::{AuthError, Authenticator, Credential, Principal, Route};
+pub use backfill::{
+    backfill, BackfillConflict, BackfillError, BackfillReport, CheckpointPage, CheckpointQuery,
+    CheckpointStore, CheckpointStream, PageCursor,
+};
+pub use budget::{BudgetError, CheckpointSizePlan, Disposition, FieldSaving, SizeBudget};
+pub use chain::{
+    ChainError, ChainHead, ChainVerifier, TrustRequirements, UnverifiedCheckpoint,
+    VerifiedCheckpoint,
+};
 pub use checkpoint::{Checkpoint, CheckpointBuilder};
-pub use crypto::{Signature, Signer};
-pub use merkle::{Entry, MerkleTree, MerkleProof};
+pub use clock::{
+    system_clock, Clock, ClockError, ClockSkewPolicy, MockClock, SkewDecision, SystemClock,
+};
+pub use collateral::{CollateralBundle, CollateralError, SignedCollateralBundle};
+pub use config::{AgentConfig, ConfigError, ConfigRegistry, ConfigTracker, SignedConfig};
+pub use conformance::{ConformanceKind, ConformanceReport, ConformanceRule, Violation};
+pub use consistency::{
+    AnchorAuditReport, AnchorAuditor, AnchorDivergence, AnchoredStorage, DivergenceKind,
+    DivergenceSink,
+};
+pub use countersign::{
+    CountersignError, Countersigner, SignedKind, SigningAuditRecord, SigningBackend,
+};
+pub use crypto::{ct_eq, DigestAlgorithm, Signature, Signer};
+pub use custody::{
+    ChallengeOutcome, CustodyAuditor, CustodyChallenge, CustodyClaim, CustodyError, CustodyProof,
+    CustodyProver, CustodyReport, CustodyVerdict,
+};
+pub use dedup::{QuoteDeduplicator, QuoteResultStore};
+pub use delegation::{DelegationCert, DelegationError, DelegationScope};
+pub use endorsement::{
+    Endorsement, EndorsementError, EndorsementStatus, EndorsementSubject, Endorsements,
+};
+pub use erasure::{erase, Erasure, ErasureRecord};
+pub use error::{ErrorCode, ErrorCoded, ErrorDetail};
+pub use escrow::{reconstruct_key, split_key, EscrowError, KeyShare};
+pub use events::{EventFilter, EventHub, EventKind, GatewayEvent, Subscription};
+pub use forensic::{
+    AnchorReceipt, AttestedResult, CheckpointEvidence, EvidenceStore, ForensicError,
+    ForensicPackage,
+};
+pub use freshness::FreshnessPolicy;
+pub use gap::{GapAllowance, GapError, GapPolicy, GapReason, GapRecord};
+pub use health::{
+    AgentHealth, HealthAlert, HealthIssue, HealthPolicy, HealthRecorder, HealthSummary,
+    SignerStatus, HEALTH_RECORD_TYPE,
+};
+pub use heartbeat::{CoverageGap, HeartbeatPolicy};
+pub use history::{HistoryError, RetainedRoot, RootHistory, RootHistoryProof};
+pub use inspect::CheckpointSummary;
+pub use inventory::{HardwareInventory, HardwareProfile, InventoryError, InventoryRegistry};
+pub use kat::{KatError, KatSuite, KnownAnswer, KnownEntry};
+pub use keys::{KeyResolver, KeyRing};
+pub use merkle::{
+    DuplicatePolicy, Entry, InsertOutcome, MerkleMultiProof, MerkleProof, MerkleTree,
+};
+pub use mission::{verify_mission, MissionAuthorization, MissionError, MissionEvent, OpenMission};
+pub use monitor::{
+    EquivocationEvidence, LogVantage, MonitorError, MonitorReport, SplitHorizonMonitor,
+    SplitViewKind, VantageFailure,
+};
+pub use nonce::{NonceError, NonceManager};
+pub use notarization::{
+    AlgorithmPolicy, EvidenceRecord, Notarization, NotarizationError, SignatureAlgorithm,
+};
+pub use operator::{OperatorAction, OperatorCommand, OperatorError, OperatorRegistry};
+pub use quarantine::{
+    Quarantine, QuarantineAlertSink, QuarantineError, QuarantineReview, QuarantineStatus,
+    QuarantinedSubmission,
+};
+pub use quorum::{submit_to_quorum, GatewayReceipt, NotaryGateway, QuorumError, QuorumReceipt};
+pub use ratelimit::{LimiterStats, RateLimitError, RateLimiter, StorageUsage};
+pub use receipt::{AttestationReceipt, ReceiptError};
+pub use release::{
+    report_data_binding, KeyReleaseError, KeyReleaseRequest, KeyReleaseServer, WrappedSecret,
+};
+pub use reload::{
+    ApiKey, AuthConfig, ClientCertificate, ConfigParser, ConfigReloader, GatewayConfig, LiveConfig,
+    OperatorRole, Quota, RateLimit, ReloadError, ReloadOutcome, Tenant, TrustAnchors,
+};
+pub use replication::{
+    ForkAlertSink, ForkEvidence, ReplicationError, ReplicationReport, Replicator,
+};
+pub use retention::{CompactionReport, Compactor, RetentionError, RetentionPolicy};
+pub use rollback::{RollbackAlert, RollbackAlertSink, RollbackKind};
+pub use rotation::{KeyRotationCert, RotationError};
+pub use search::{EntryLocation, EntrySearchIndex, SearchError};
+pub use shard::{Shard, ShardError, ShardId, ShardProof, ShardState, ShardSummary, ShardedStore};
+pub use spool::{
+    DrainReport, Spool, SpoolBacklog, SpoolError, SpoolKind, SpoolLimits, SpoolRecord,
+    SpoolTransport,
+};
+pub use subkey::{sign_entry, EntryAttribution, SubKeyCert, SubKeyError};
+pub use summary::{MissionRecord, MissionSummary, SummaryError, SummarySample};
+pub use sync::{
+    HeldCheckpoint, SyncClient, SyncError, SyncReport, SyncRequest, SyncResponse, SyncServer,
+    SyncTransport,
+};
+pub use tenant::{TenantError, TenantScope};
+pub use tombstone::{
+    verify_stored_entries, DeletionReason, ErasedEntry, StoredEntry, Tombstone, TombstoneError,
+};
+pub use transparency::{
+    CheckpointTimestamp, LogError, LoggedCheckpoint, SignedTreeHead, TransparencyLog, TreeHead,
+};
 pub use types::*;
 
 // Re-export Hash256 from types
@@ -30,8 +211,6 @@ pub use ed25519_dalek::{SigningKey, VerifyingKey};
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     #[test]
     fn test_version() {
         assert_eq!(env!("CARGO_PKG_VERSION"), "0.1.0");
@@ -2,13 +2,16 @@
 //!
 //! ## Key Properties
 //! - Sorted by (timestamp, nonce) for deterministic ordering
+//! - Duplicate (timestamp, nonce) keys handled by an explicit [`DuplicatePolicy`]
 //! - Incremental updates (efficient for streaming logs)
 //! - Proof generation for selective disclosure
+//! - Leaf and node hashes use the tree's [`DigestAlgorithm`] (SHA-256 unless
+//!   the checkpoint names another `hash_alg`)
 
-use crate::crypto::sha256;
+use crate::crypto::{ct_eq, sha256, DigestAlgorithm};
 use crate::types::Hash256;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{btree_map, BTreeMap, BTreeSet};
 
 /// A Merkle tree entry (timestamp + nonce ensures deterministic ordering).
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -31,35 +34,129 @@ impl Entry {
         }
     }
 
-    /// Compute the hash of this entry (for Merkle tree leaf).
+    /// Compute the SHA-256 hash of this entry (for Merkle tree leaf).
     pub fn hash(&self) -> Hash256 {
+        self.hash_with(DigestAlgorithm::Sha256)
+    }
+
+    /// Compute the leaf hash of this entry with `alg`.
+    pub fn hash_with(&self, alg: DigestAlgorithm) -> Hash256 {
         // Deterministic serialization of (timestamp, nonce, data_hash)
         let mut buf = Vec::with_capacity(8 + 8 + 32);
         buf.extend_from_slice(&self.timestamp_us.to_be_bytes());
         buf.extend_from_slice(&self.nonce.to_be_bytes());
         buf.extend_from_slice(&self.data_hash);
-        sha256(&buf)
+        alg.digest(&buf)
+    }
+}
+
+/// What [`MerkleTree::insert`] does when an entry with the same
+/// (timestamp, nonce) is already pending.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// Keep the existing entry and drop the new one
+    #[default]
+    Reject,
+    /// Overwrite the existing entry
+    Replace,
+    /// Store the new entry under the next free nonce for its timestamp
+    BumpNonce,
+}
+
+/// Result of [`MerkleTree::insert`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InsertOutcome {
+    /// No entry had this (timestamp, nonce)
+    Inserted,
+    /// The existing entry was kept; the new one was not added
+    Rejected,
+    /// The existing entry (returned) was overwritten
+    Replaced(Entry),
+    /// The new entry was added with this nonce instead of its own
+    Bumped { nonce: u64 },
+}
+
+impl InsertOutcome {
+    /// Whether an entry with the same (timestamp, nonce) already existed.
+    pub fn existed(&self) -> bool {
+        !matches!(self, InsertOutcome::Inserted)
     }
 }
 
 /// Incremental Merkle tree.
 ///
 /// Uses BTreeMap to maintain sorted order by (timestamp, nonce).
+#[derive(Debug, Clone)]
 pub struct MerkleTree {
     entries: BTreeMap<(u64, u64), Entry>,
+    policy: DuplicatePolicy,
+    hash_alg: DigestAlgorithm,
 }
 
 impl MerkleTree {
-    /// Create a new empty Merkle tree.
+    /// Create a new empty Merkle tree that rejects duplicate keys.
     pub fn new() -> Self {
+        Self::with_policy(DuplicatePolicy::default())
+    }
+
+    /// Create a new empty Merkle tree with an explicit duplicate policy.
+    pub fn with_policy(policy: DuplicatePolicy) -> Self {
         Self {
             entries: BTreeMap::new(),
+            policy,
+            hash_alg: DigestAlgorithm::default(),
         }
     }
 
+    /// Hash leaves and nodes with `alg` instead of SHA-256.
+    ///
+    /// Must match the `hash_alg` of the checkpoint the root goes into.
+    pub fn with_hash_alg(mut self, alg: DigestAlgorithm) -> Self {
+        self.hash_alg = alg;
+        self
+    }
+
+    /// The duplicate policy applied by [`MerkleTree::insert`].
+    pub fn policy(&self) -> DuplicatePolicy {
+        self.policy
+    }
+
+    /// The digest algorithm of leaves and nodes.
+    pub fn hash_alg(&self) -> DigestAlgorithm {
+        self.hash_alg
+    }
+
     /// Insert an entry into the tree.
-    pub fn insert(&mut self, entry: Entry) {
-        self.entries.insert((entry.timestamp_us, entry.nonce), entry);
+    ///
+    /// A producer reusing a (timestamp, nonce) would otherwise overwrite data
+    /// silently before the root is cut; the outcome reports what happened.
+    /// Under [`DuplicatePolicy::BumpNonce`] the entry is rejected if every
+    /// higher nonce at its timestamp is taken.
+    pub fn insert(&mut self, mut entry: Entry) -> InsertOutcome {
+        let mut existing = match self.entries.entry((entry.timestamp_us, entry.nonce)) {
+            btree_map::Entry::Vacant(slot) => {
+                slot.insert(entry);
+                return InsertOutcome::Inserted;
+            }
+            btree_map::Entry::Occupied(existing) => existing,
+        };
+        match self.policy {
+            DuplicatePolicy::Reject => InsertOutcome::Rejected,
+            DuplicatePolicy::Replace => InsertOutcome::Replaced(existing.insert(entry)),
+            DuplicatePolicy::BumpNonce => {
+                let mut nonce = entry.nonce;
+                while self.entries.contains_key(&(entry.timestamp_us, nonce)) {
+                    match nonce.checked_add(1) {
+                        Some(next) => nonce = next,
+                        None => return InsertOutcome::Rejected,
+                    }
+                }
+                entry.nonce = nonce;
+                self.entries.insert((entry.timestamp_us, nonce), entry);
+                InsertOutcome::Bumped { nonce }
+            }
+        }
     }
 
     /// Get the number of entries.
@@ -80,8 +177,7 @@ impl MerkleTree {
             return [0u8; 32];
         }
 
-        let leaves: Vec<Hash256> = self.entries.values().map(|e| e.hash()).collect();
-        compute_merkle_root(&leaves)
+        compute_merkle_root(self.hash_alg, &self.leaf_hashes())
     }
 
     /// Generate a Merkle proof for a specific entry.
@@ -89,16 +185,61 @@ impl MerkleTree {
     /// Returns the sibling hashes needed to reconstruct the root.
     pub fn generate_proof(&self, timestamp_us: u64, nonce: u64) -> Option<MerkleProof> {
         let leaves: Vec<Entry> = self.entries.values().cloned().collect();
-        let index = leaves.iter().position(|e| e.timestamp_us == timestamp_us && e.nonce == nonce)?;
+        let index = leaves
+            .iter()
+            .position(|e| e.timestamp_us == timestamp_us && e.nonce == nonce)?;
 
-        let leaf_hashes: Vec<Hash256> = leaves.iter().map(|e| e.hash()).collect();
-        let siblings = compute_proof_siblings(&leaf_hashes, index);
+        let siblings = compute_proof_siblings(self.hash_alg, &self.leaf_hashes(), index);
 
         Some(MerkleProof {
             leaf: leaves[index].clone(),
             leaf_index: index,
             siblings,
             root: self.root(),
+            hash_alg: self.hash_alg,
+        })
+    }
+
+    /// Generate one proof covering several entries.
+    ///
+    /// Returns `None` if any `(timestamp_us, nonce)` is not in the tree.
+    pub fn generate_multiproof(&self, keys: &[(u64, u64)]) -> Option<MerkleMultiProof> {
+        let leaves: Vec<&Entry> = self.entries.values().collect();
+        let mut indices = BTreeSet::new();
+        for key in keys {
+            indices.insert(
+                leaves
+                    .iter()
+                    .position(|e| (e.timestamp_us, e.nonce) == *key)?,
+            );
+        }
+
+        let mut level = self.leaf_hashes();
+        let mut known = indices.clone();
+        let mut hashes = Vec::new();
+        while level.len() > 1 {
+            for &index in &known {
+                let sibling = index ^ 1;
+                if sibling < level.len() && !known.contains(&sibling) {
+                    hashes.push(level[sibling]);
+                }
+            }
+            known = known.iter().map(|index| index / 2).collect();
+            level = level
+                .chunks(2)
+                .map(|chunk| hash_pair(self.hash_alg, &chunk[0], chunk.get(1).unwrap_or(&chunk[0])))
+                .collect();
+        }
+
+        Some(MerkleMultiProof {
+            leaf_count: leaves.len(),
+            leaves: indices
+                .into_iter()
+                .map(|i| (i, leaves[i].clone()))
+                .collect(),
+            hashes,
+            root: self.root(),
+            hash_alg: self.hash_alg,
         })
     }
 
@@ -107,10 +248,25 @@ impl MerkleTree {
         self.entries.clear();
     }
 
+    /// Key of the last (highest) entry, if any.
+    ///
+    /// After the entries are dropped this is all that is needed to keep
+    /// ordering guarantees for later entries.
+    pub fn frontier(&self) -> Option<(u64, u64)> {
+        self.entries.keys().next_back().copied()
+    }
+
     /// Get all entries in sorted order.
     pub fn entries(&self) -> Vec<&Entry> {
         self.entries.values().collect()
     }
+
+    fn leaf_hashes(&self) -> Vec<Hash256> {
+        self.entries
+            .values()
+            .map(|e| e.hash_with(self.hash_alg))
+            .collect()
+    }
 }
 
 impl Default for MerkleTree {
@@ -126,22 +282,96 @@ pub struct MerkleProof {
     pub leaf_index: usize,
     pub siblings: Vec<Hash256>,
     pub root: Hash256,
+    /// Digest algorithm of the tree (absent for SHA-256)
+    #[serde(default, skip_serializing_if = "DigestAlgorithm::is_default")]
+    pub hash_alg: DigestAlgorithm,
 }
 
 impl MerkleProof {
     /// Verify this proof against a known root.
     pub fn verify(&self, expected_root: &Hash256) -> bool {
-        if &self.root != expected_root {
+        if !ct_eq(&self.root, expected_root) {
             return false;
         }
 
-        let computed_root = reconstruct_root(self.leaf.hash(), self.leaf_index, &self.siblings);
-        &computed_root == expected_root
+        let computed_root = reconstruct_root(
+            self.hash_alg,
+            self.leaf.hash_with(self.hash_alg),
+            self.leaf_index,
+            &self.siblings,
+        );
+        ct_eq(&computed_root, expected_root)
+    }
+}
+
+/// A Merkle proof for several entries at once.
+///
+/// Sibling hashes shared between the proven leaves' paths are sent once;
+/// `hashes` holds the remaining ones level by level, left to right.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleMultiProof {
+    /// Number of leaves in the tree
+    pub leaf_count: usize,
+    /// Proven entries with their leaf indices, ascending
+    pub leaves: Vec<(usize, Entry)>,
+    pub hashes: Vec<Hash256>,
+    pub root: Hash256,
+    /// Digest algorithm of the tree (absent for SHA-256)
+    #[serde(default, skip_serializing_if = "DigestAlgorithm::is_default")]
+    pub hash_alg: DigestAlgorithm,
+}
+
+impl MerkleMultiProof {
+    /// Verify this proof against a known root.
+    pub fn verify(&self, expected_root: &Hash256) -> bool {
+        if !ct_eq(&self.root, expected_root) || self.leaves.is_empty() {
+            return false;
+        }
+
+        let mut known = BTreeMap::new();
+        for (index, entry) in &self.leaves {
+            if *index >= self.leaf_count
+                || known
+                    .insert(*index, entry.hash_with(self.hash_alg))
+                    .is_some()
+            {
+                return false;
+            }
+        }
+
+        let mut width = self.leaf_count;
+        let mut hashes = self.hashes.iter();
+        while width > 1 {
+            let mut next = BTreeMap::new();
+            for (&index, hash) in &known {
+                if next.contains_key(&(index / 2)) {
+                    continue;
+                }
+                let sibling = match known.get(&(index ^ 1)) {
+                    _ if index ^ 1 >= width => hash,
+                    Some(sibling) => sibling,
+                    None => match hashes.next() {
+                        Some(sibling) => sibling,
+                        None => return false,
+                    },
+                };
+                let parent = if index.is_multiple_of(2) {
+                    hash_pair(self.hash_alg, hash, sibling)
+                } else {
+                    hash_pair(self.hash_alg, sibling, hash)
+                };
+                next.insert(index / 2, parent);
+            }
+            known = next;
+            width = width.div_ceil(2);
+        }
+
+        hashes.next().is_none() && known.get(&0).is_some_and(|root| ct_eq(root, expected_root))
     }
 }
 
 /// Compute the Merkle root from leaf hashes.
-fn compute_merkle_root(leaves: &[Hash256]) -> Hash256 {
+pub(crate) fn compute_merkle_root(alg: DigestAlgorithm, leaves: &[Hash256]) -> Hash256 {
     if leaves.is_empty() {
         return [0u8; 32];
     }
@@ -157,10 +387,10 @@ fn compute_merkle_root(leaves: &[Hash256]) -> Hash256 {
 
         for chunk in level.chunks(2) {
             let hash = if chunk.len() == 2 {
-                hash_pair(&chunk[0], &chunk[1])
+                hash_pair(alg, &chunk[0], &chunk[1])
             } else {
                 // Odd number of nodes - hash with itself
-                hash_pair(&chunk[0], &chunk[0])
+                hash_pair(alg, &chunk[0], &chunk[0])
             };
             next_level.push(hash);
         }
@@ -172,7 +402,11 @@ fn compute_merkle_root(leaves: &[Hash256]) -> Hash256 {
 }
 
 /// Compute sibling hashes for a Merkle proof.
-fn compute_proof_siblings(leaves: &[Hash256], index: usize) -> Vec<Hash256> {
+pub(crate) fn compute_proof_siblings(
+    alg: DigestAlgorithm,
+    leaves: &[Hash256],
+    index: usize,
+) -> Vec<Hash256> {
     if leaves.len() <= 1 {
         return Vec::new();
     }
@@ -182,7 +416,7 @@ fn compute_proof_siblings(leaves: &[Hash256], index: usize) -> Vec<Hash256> {
     let mut current_index = index;
 
     while level.len() > 1 {
-        let sibling_index = if current_index % 2 == 0 {
+        let sibling_index = if current_index.is_multiple_of(2) {
             current_index + 1
         } else {
             current_index - 1
@@ -199,9 +433,9 @@ fn compute_proof_siblings(leaves: &[Hash256], index: usize) -> Vec<Hash256> {
         let mut next_level = Vec::new();
         for chunk in level.chunks(2) {
             let hash = if chunk.len() == 2 {
-                hash_pair(&chunk[0], &chunk[1])
+                hash_pair(alg, &chunk[0], &chunk[1])
             } else {
-                hash_pair(&chunk[0], &chunk[0])
+                hash_pair(alg, &chunk[0], &chunk[0])
             };
             next_level.push(hash);
         }
@@ -214,14 +448,19 @@ fn compute_proof_siblings(leaves: &[Hash256], index: usize) -> Vec<Hash256> {
 }
 
 /// Reconstruct Merkle root from leaf and sibling hashes.
-fn reconstruct_root(leaf_hash: Hash256, mut index: usize, siblings: &[Hash256]) -> Hash256 {
+pub(crate) fn reconstruct_root(
+    alg: DigestAlgorithm,
+    leaf_hash: Hash256,
+    mut index: usize,
+    siblings: &[Hash256],
+) -> Hash256 {
     let mut current_hash = leaf_hash;
 
     for sibling in siblings {
-        current_hash = if index % 2 == 0 {
-            hash_pair(&current_hash, sibling)
+        current_hash = if index.is_multiple_of(2) {
+            hash_pair(alg, &current_hash, sibling)
         } else {
-            hash_pair(sibling, &current_hash)
+            hash_pair(alg, sibling, &current_hash)
         };
         index /= 2;
     }
@@ -230,11 +469,11 @@ fn reconstruct_root(leaf_hash: Hash256, mut index: usize, siblings: &[Hash256])
 }
 
 /// Hash two nodes together.
-fn hash_pair(left: &Hash256, right: &Hash256) -> Hash256 {
+fn hash_pair(alg: DigestAlgorithm, left: &Hash256, right: &Hash256) -> Hash256 {
     let mut buf = Vec::with_capacity(64);
     buf.extend_from_slice(left);
     buf.extend_from_slice(right);
-    sha256(&buf)
+    alg.digest(&buf)
 }
 
 #[cfg(test)]
@@ -312,6 +551,114 @@ mod tests {
         tree2.insert(Entry::new(2000, 0, b"data2"));
         tree2.insert(Entry::new(1000, 0, b"data1"));
 
-        assert_eq!(tree1.root(), tree2.root(), "Root should be deterministic regardless of insertion order");
+        assert_eq!(
+            tree1.root(),
+            tree2.root(),
+            "Root should be deterministic regardless of insertion order"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_policies() {
+        let original = Entry::new(1000, 0, b"committed");
+        let clash = Entry::new(1000, 0, b"overwrite");
+
+        let mut tree = MerkleTree::new();
+        assert_eq!(tree.insert(original.clone()), InsertOutcome::Inserted);
+        let root = tree.root();
+        let outcome = tree.insert(clash.clone());
+        assert!(outcome.existed());
+        assert_eq!(outcome, InsertOutcome::Rejected);
+        assert_eq!(tree.root(), root);
+
+        let mut tree = MerkleTree::with_policy(DuplicatePolicy::Replace);
+        tree.insert(original.clone());
+        assert_eq!(
+            tree.insert(clash.clone()),
+            InsertOutcome::Replaced(original.clone())
+        );
+        assert_eq!(tree.entries(), vec![&clash]);
+
+        let mut tree = MerkleTree::with_policy(DuplicatePolicy::BumpNonce);
+        tree.insert(original.clone());
+        tree.insert(Entry::new(1000, 1, b"next"));
+        assert_eq!(
+            tree.insert(clash.clone()),
+            InsertOutcome::Bumped { nonce: 2 }
+        );
+        assert_eq!(tree.len(), 3);
+        assert_eq!(
+            tree.generate_proof(1000, 2).unwrap().leaf.data_hash,
+            clash.data_hash
+        );
+
+        tree.insert(Entry::new(2000, u64::MAX, b"last"));
+        assert_eq!(
+            tree.insert(Entry::new(2000, u64::MAX, b"again")),
+            InsertOutcome::Rejected
+        );
+    }
+
+    #[test]
+    fn test_multiproof() {
+        let mut tree = MerkleTree::new();
+        for i in 0..7u64 {
+            tree.insert(Entry::new(1000 * (i + 1), 0, format!("data{i}").as_bytes()));
+        }
+        let root = tree.root();
+
+        for keys in [
+            vec![(1000, 0)],
+            vec![(2000, 0), (3000, 0), (7000, 0)],
+            vec![(5000, 0), (6000, 0)],
+        ] {
+            let proof = tree.generate_multiproof(&keys).unwrap();
+            assert_eq!(proof.leaves.len(), keys.len());
+            assert!(proof.verify(&root));
+        }
+        assert!(tree.generate_multiproof(&[(1234, 0)]).is_none());
+
+        let mut proof = tree.generate_multiproof(&[(2000, 0), (7000, 0)]).unwrap();
+        proof.leaves[1].1.data_hash[0] ^= 0xFF;
+        assert!(!proof.verify(&root));
+
+        let mut proof = tree.generate_multiproof(&[(2000, 0), (7000, 0)]).unwrap();
+        proof.hashes.push([0u8; 32]);
+        assert!(!proof.verify(&root));
+    }
+
+    #[test]
+    fn test_hash_algorithms() {
+        let mut roots = Vec::new();
+        for alg in [
+            DigestAlgorithm::Sha256,
+            DigestAlgorithm::Sha3_256,
+            DigestAlgorithm::Blake3,
+        ] {
+            let mut tree = MerkleTree::new().with_hash_alg(alg);
+            for i in 0..5u64 {
+                tree.insert(Entry::new(1000 * (i + 1), 0, format!("data{i}").as_bytes()));
+            }
+            let root = tree.root();
+            let proof = tree.generate_proof(3000, 0).unwrap();
+            assert_eq!(proof.hash_alg, alg);
+            assert!(proof.verify(&root));
+            assert!(tree
+                .generate_multiproof(&[(1000, 0), (5000, 0)])
+                .unwrap()
+                .verify(&root));
+
+            // The algorithm travels with the proof; claiming another one fails
+            let mut relabeled = proof.clone();
+            relabeled.hash_alg = if alg == DigestAlgorithm::Blake3 {
+                DigestAlgorithm::Sha256
+            } else {
+                DigestAlgorithm::Blake3
+            };
+            assert!(!relabeled.verify(&root));
+            roots.push(root);
+        }
+        assert_ne!(roots[0], roots[1]);
+        assert_ne!(roots[1], roots[2]);
     }
 }
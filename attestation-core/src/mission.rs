@@ -0,0 +1,347 @@
+//! Mission lifecycle: operator authorization and start/end checkpoints.
+//!
+//! A mission opens with a checkpoint carrying [`MissionEvent::Start`], which
+//! commits to the operator's signed [`MissionAuthorization`], and closes with
+//! one carrying [`MissionEvent::End`], which links back to the start
+//! checkpoint and commits to the mission's summary root. Between the two,
+//! [`crate::ChainVerifier`] keeps every checkpoint on the same mission, so a
+//! verified start-to-end run is the mission's complete evidence.
+//! [`verify_mission`] checks such a run on its own.
+
+use crate::chain::{ChainError, ChainHead, ChainVerifier};
+use crate::checkpoint::Checkpoint;
+use crate::crypto::{ct_eq, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, KeyId, MissionId, RobotId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use thiserror::Error;
+
+/// Lifecycle marker carried by mission-start and mission-end checkpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissionEvent {
+    /// First checkpoint of a mission
+    Start {
+        /// Hash of the operator's [`MissionAuthorization`]
+        authorization: Hash256,
+    },
+    /// Last checkpoint of a mission
+    End {
+        /// Hash of the mission-start checkpoint
+        start: Hash256,
+        /// Hash of the mission's [`crate::MissionSummary`]
+        summary_root: Hash256,
+    },
+}
+
+impl fmt::Display for MissionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MissionEvent::Start { .. } => write!(f, "mission start"),
+            MissionEvent::End { .. } => write!(f, "mission end"),
+        }
+    }
+}
+
+/// An operator's signed go-ahead for a robot to run a mission.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MissionAuthorization {
+    pub robot_id: RobotId,
+    pub mission_id: MissionId,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub issued_at: DateTime<Utc>,
+    /// Fingerprint of the operator's signing key
+    pub operator_key_id: KeyId,
+    /// Ed25519 signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned authorization (for signature computation)
+#[derive(Serialize)]
+struct UnsignedMissionAuthorization<'a> {
+    robot_id: &'a RobotId,
+    mission_id: &'a MissionId,
+    #[serde(with = "crate::serialization::timestamp")]
+    issued_at: DateTime<Utc>,
+    operator_key_id: KeyId,
+}
+
+impl MissionAuthorization {
+    /// Authorize `robot_id` to run `mission_id`, signed by `operator`.
+    pub fn issue(
+        robot_id: RobotId,
+        mission_id: MissionId,
+        operator: &Signer,
+    ) -> Result<Self, SerializationError> {
+        let mut authorization = Self {
+            robot_id,
+            mission_id,
+            issued_at: Utc::now(),
+            operator_key_id: operator.key_id(),
+            signature: SignatureBytes([0u8; 64]),
+        };
+        let signature = operator.sign(&authorization.signing_payload()?);
+        authorization.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(authorization)
+    }
+
+    /// Verify the operator's signature.
+    pub fn verify(&self, operators: &dyn KeyResolver) -> Result<(), MissionError> {
+        use ed25519_dalek::Verifier;
+
+        let verifying_key = operators
+            .resolve(&self.operator_key_id)
+            .ok_or(MissionError::UnknownOperator(self.operator_key_id))?;
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        verifying_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| MissionError::InvalidAuthorization)
+    }
+
+    /// Hash committed by the mission-start checkpoint (covers the signature).
+    pub fn compute_hash(&self) -> Result<Hash256, SerializationError> {
+        Ok(Sha256::digest(self.to_bytes()?).into())
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedMissionAuthorization {
+            robot_id: &self.robot_id,
+            mission_id: &self.mission_id,
+            issued_at: self.issued_at,
+            operator_key_id: self.operator_key_id,
+        })
+    }
+}
+
+/// A mission that has started but not yet ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenMission {
+    pub mission_id: MissionId,
+    /// Hash of the mission-start checkpoint
+    pub start: Hash256,
+}
+
+/// Check that `checkpoints` are one complete, authorized mission.
+///
+/// The run must open with a start checkpoint committing to `authorization`
+/// (signed by a key in `operators` for the same robot and mission), close
+/// with an end checkpoint, and form a valid chain under `robot_keys` in
+/// between. Returns the end checkpoint's summary root.
+pub fn verify_mission(
+    checkpoints: &[Checkpoint],
+    authorization: &MissionAuthorization,
+    robot_keys: Box<dyn KeyResolver>,
+    operators: &dyn KeyResolver,
+) -> Result<Hash256, MissionError> {
+    let (Some(start), Some(end)) = (checkpoints.first(), checkpoints.last()) else {
+        return Err(MissionError::Incomplete("no checkpoints"));
+    };
+    let Some(MissionEvent::Start {
+        authorization: committed,
+    }) = start.mission_event
+    else {
+        return Err(MissionError::Incomplete(
+            "first checkpoint is not a mission start",
+        ));
+    };
+    let Some(MissionEvent::End { summary_root, .. }) = end.mission_event else {
+        return Err(MissionError::Incomplete(
+            "last checkpoint is not a mission end",
+        ));
+    };
+
+    authorization.verify(operators)?;
+    if !ct_eq(&committed, &authorization.compute_hash()?)
+        || authorization.robot_id != start.robot_id
+        || authorization.mission_id != start.mission_id
+    {
+        return Err(MissionError::AuthorizationMismatch);
+    }
+
+    start
+        .verify_with_resolver(robot_keys.as_ref())
+        .map_err(ChainError::from)?;
+    let mut verifier =
+        ChainVerifier::with_resolver(robot_keys).resume_from(ChainHead::from_checkpoint(start)?);
+    verifier.verify_chain(&checkpoints[1..])?;
+    Ok(summary_root)
+}
+
+#[derive(Debug, Error)]
+pub enum MissionError {
+    #[error("Mission serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Mission authorization signed by unknown operator key {0}")]
+    UnknownOperator(KeyId),
+
+    #[error("Invalid mission authorization signature")]
+    InvalidAuthorization,
+
+    #[error("Mission authorization does not match the mission-start checkpoint")]
+    AuthorizationMismatch,
+
+    #[error("Incomplete mission: {0}")]
+    Incomplete(&'static str),
+
+    #[error("Mission chain error: {0}")]
+    Chain(#[from] ChainError),
+}
+
+impl ErrorCoded for MissionError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            MissionError::Serialization(e) => e.code(),
+            MissionError::UnknownOperator(_)
+            | MissionError::InvalidAuthorization
+            | MissionError::AuthorizationMismatch => ErrorCode::MissionAuthorizationInvalid,
+            MissionError::Incomplete(_) => ErrorCode::MissionIncomplete,
+            MissionError::Chain(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use chrono::Duration;
+
+    pub(crate) fn start_checkpoint(
+        robot: &Signer,
+        authorization: &MissionAuthorization,
+    ) -> Checkpoint {
+        CheckpointBuilder::test_fixture()
+            .robot_id(authorization.robot_id.clone())
+            .mission_id(authorization.mission_id.clone())
+            .timestamp(Utc::now() - Duration::hours(1))
+            .mission_start(authorization.compute_hash().unwrap())
+            .build_and_sign(robot.signing_key())
+            .unwrap()
+    }
+
+    /// Start, `middle` plain checkpoints, then an end with `summary_root`.
+    fn mission(
+        robot: &Signer,
+        authorization: &MissionAuthorization,
+        middle: u64,
+    ) -> Vec<Checkpoint> {
+        let start = start_checkpoint(robot, authorization);
+        let start_hash = start.compute_hash().unwrap();
+        let mut checkpoints = vec![start];
+        for i in 1..=middle + 1 {
+            let prev = checkpoints.last().unwrap();
+            let mut builder = CheckpointBuilder::continuing_from(prev)
+                .unwrap()
+                .monotonic_counter(prev.monotonic_counter + 1)
+                .timestamp(prev.local_timestamp_utc + Duration::seconds(1))
+                .entries_root([i as u8; 32]);
+            if i == middle + 1 {
+                builder = builder.mission_end(start_hash, [0xAA; 32]);
+            }
+            checkpoints.push(builder.build_and_sign(robot.signing_key()).unwrap());
+        }
+        checkpoints
+    }
+
+    fn authorize(operator: &Signer) -> MissionAuthorization {
+        MissionAuthorization::issue(
+            RobotId("R-001".to_string()),
+            MissionId("M-001".to_string()),
+            operator,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_complete_mission_verifies() {
+        let (robot, operator) = (Signer::generate(), Signer::generate());
+        let authorization = authorize(&operator);
+        let checkpoints = mission(&robot, &authorization, 2);
+
+        let summary_root = verify_mission(
+            &checkpoints,
+            &authorization,
+            Box::new(robot.verifying_key()),
+            &operator.verifying_key(),
+        )
+        .unwrap();
+        assert_eq!(summary_root, [0xAA; 32]);
+
+        let decoded = MissionAuthorization::from_bytes(&authorization.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, authorization);
+    }
+
+    #[test]
+    fn test_truncated_mission_rejected() {
+        let (robot, operator) = (Signer::generate(), Signer::generate());
+        let authorization = authorize(&operator);
+        let checkpoints = mission(&robot, &authorization, 2);
+
+        let err = verify_mission(
+            &checkpoints[..3],
+            &authorization,
+            Box::new(robot.verifying_key()),
+            &operator.verifying_key(),
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::MissionIncomplete);
+
+        // Dropping a middle checkpoint breaks the chain
+        let gapped = [&checkpoints[..1], &checkpoints[2..]].concat();
+        let err = verify_mission(
+            &gapped,
+            &authorization,
+            Box::new(robot.verifying_key()),
+            &operator.verifying_key(),
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SequenceGap);
+    }
+
+    #[test]
+    fn test_authorization_must_match() {
+        let (robot, operator) = (Signer::generate(), Signer::generate());
+        let authorization = authorize(&operator);
+        let checkpoints = mission(&robot, &authorization, 1);
+
+        let err = verify_mission(
+            &checkpoints,
+            &authorization,
+            Box::new(robot.verifying_key()),
+            &Signer::generate().verifying_key(),
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::MissionAuthorizationInvalid);
+
+        let other = MissionAuthorization::issue(
+            RobotId("R-001".to_string()),
+            MissionId("M-002".to_string()),
+            &operator,
+        )
+        .unwrap();
+        let err = verify_mission(
+            &checkpoints,
+            &other,
+            Box::new(robot.verifying_key()),
+            &operator.verifying_key(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, MissionError::AuthorizationMismatch));
+    }
+}
@@ -0,0 +1,423 @@
+//! Split-horizon monitoring of gateway tree heads.
+//!
+//! A gateway can present one history to the robots and another to auditors.
+//! Each view verifies on its own, so the split only shows when views are
+//! compared. A [`SplitHorizonMonitor`] fetches signed tree heads of a log
+//! from several [`LogVantage`]s (mirrors, other networks, peers'
+//! gossip) and across polls, and checks that they describe one history:
+//!
+//! - two heads of the same size must have the same root;
+//! - a smaller head must be a prefix of a larger one, by a consistency proof
+//!   the log serves.
+//!
+//! A violation is recorded as [`EquivocationEvidence`]. Two validly signed
+//! heads of the same size with different roots are conclusive on their own
+//! ([`EquivocationEvidence::verify`]); a missing or failing consistency
+//! proof is recorded as well, for operators to follow up.
+
+use crate::clock::{system_clock, Clock};
+use crate::error::{ErrorCode, ErrorCoded, ErrorDetail};
+use crate::keys::KeyResolver;
+use crate::transparency::{ConsistencyProof, LogError, SignedTreeHead, TreeHead};
+use crate::types::KeyId;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// One place a log's tree heads can be fetched from.
+#[async_trait]
+pub trait LogVantage: Send + Sync {
+    /// Name used in evidence and reports (e.g., mirror URL).
+    fn name(&self) -> &str;
+
+    /// The log's latest signed tree head, as seen from here.
+    async fn signed_tree_head(&self) -> Result<SignedTreeHead, MonitorError>;
+
+    /// Proof that the tree of `first_size` is a prefix of the one of `second_size`.
+    async fn consistency_proof(
+        &self,
+        first_size: u64,
+        second_size: u64,
+    ) -> Result<ConsistencyProof, MonitorError>;
+}
+
+/// How two tree heads disagree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitViewKind {
+    /// Same size, different roots
+    ConflictingRoots,
+    /// No valid consistency proof from the smaller head to the larger
+    InconsistentHistory,
+}
+
+/// Two signed tree heads of one log that do not describe one history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EquivocationEvidence {
+    pub kind: SplitViewKind,
+    /// The smaller (or equal) head and where it was seen
+    pub first: SignedTreeHead,
+    pub first_vantage: String,
+    pub second: SignedTreeHead,
+    pub second_vantage: String,
+    /// Consistency proof served for the pair, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<ConsistencyProof>,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub detected_at: DateTime<Utc>,
+}
+
+impl EquivocationEvidence {
+    /// Check that both heads are signed by the same trusted log and, for
+    /// [`SplitViewKind::ConflictingRoots`], that they conflict.
+    ///
+    /// Conflicting roots prove equivocation to anyone; an inconsistent
+    /// history only shows the log did not prove consistency when asked.
+    pub fn verify(&self, logs: &dyn KeyResolver) -> Result<(), MonitorError> {
+        self.first.verify(logs)?;
+        self.second.verify(logs)?;
+        let (a, b) = (&self.first.tree_head, &self.second.tree_head);
+        let shown = self.first.signer_key_id == self.second.signer_key_id
+            && match self.kind {
+                SplitViewKind::ConflictingRoots => {
+                    a.tree_size == b.tree_size && a.root_hash != b.root_hash
+                }
+                SplitViewKind::InconsistentHistory => {
+                    a.tree_size < b.tree_size
+                        && self
+                            .proof
+                            .as_ref()
+                            .is_none_or(|proof| proof.verify(a, b).is_err())
+                }
+            };
+        if !shown {
+            return Err(MonitorError::NotEquivocation);
+        }
+        Ok(())
+    }
+
+    /// Whether the evidence proves equivocation on its own.
+    pub fn is_conclusive(&self) -> bool {
+        self.kind == SplitViewKind::ConflictingRoots
+    }
+}
+
+/// A vantage that could not be used in a poll.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VantageFailure {
+    pub vantage: String,
+    pub error: ErrorDetail,
+}
+
+/// Outcome of one poll.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MonitorReport {
+    #[serde(with = "crate::serialization::timestamp")]
+    pub polled_at: DateTime<Utc>,
+    /// Valid heads fetched, per vantage
+    pub heads: Vec<(String, SignedTreeHead)>,
+    pub failures: Vec<VantageFailure>,
+    pub equivocations: Vec<EquivocationEvidence>,
+}
+
+impl MonitorReport {
+    /// Whether every vantage answered and all views agree.
+    pub fn is_consistent(&self) -> bool {
+        self.failures.is_empty() && self.equivocations.is_empty()
+    }
+}
+
+/// Compares a log's tree heads across vantages and polls.
+pub struct SplitHorizonMonitor {
+    /// Trusted log keys
+    logs: Box<dyn KeyResolver>,
+    vantages: Vec<Box<dyn LogVantage>>,
+    /// Largest head verified so far, per log key, and where it was seen
+    latest: HashMap<KeyId, (String, SignedTreeHead)>,
+    evidence: Vec<EquivocationEvidence>,
+    clock: Arc<dyn Clock>,
+}
+
+impl SplitHorizonMonitor {
+    /// Monitor logs signing with keys from `logs`.
+    pub fn new(logs: Box<dyn KeyResolver>) -> Self {
+        Self {
+            logs,
+            vantages: Vec::new(),
+            latest: HashMap::new(),
+            evidence: Vec::new(),
+            clock: system_clock(),
+        }
+    }
+
+    /// Fetch tree heads from `vantage` as well.
+    pub fn with_vantage(mut self, vantage: Box<dyn LogVantage>) -> Self {
+        self.vantages.push(vantage);
+        self
+    }
+
+    /// Timestamp evidence with `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// All evidence recorded so far.
+    pub fn evidence(&self) -> &[EquivocationEvidence] {
+        &self.evidence
+    }
+
+    /// Largest verified head of the log signing with `log`.
+    pub fn latest(&self, log: &KeyId) -> Option<&SignedTreeHead> {
+        self.latest.get(log).map(|(_, head)| head)
+    }
+
+    /// Fetch a head from every vantage and compare them with each other and
+    /// with the largest head of earlier polls. The report lists evidence
+    /// not recorded by an earlier poll.
+    pub async fn poll(&mut self) -> MonitorReport {
+        let polled_at = self.clock.now();
+        let mut report = MonitorReport {
+            polled_at,
+            heads: Vec::new(),
+            failures: Vec::new(),
+            equivocations: Vec::new(),
+        };
+
+        // Heads per log, with the vantage index that served them (None: an earlier poll)
+        let mut by_log: BTreeMap<KeyId, Vec<(Option<usize>, String, SignedTreeHead)>> =
+            BTreeMap::new();
+        for (log, (vantage, head)) in &self.latest {
+            by_log
+                .entry(*log)
+                .or_default()
+                .push((None, vantage.clone(), head.clone()));
+        }
+        for (index, vantage) in self.vantages.iter().enumerate() {
+            let fetched = vantage.signed_tree_head().await;
+            let head = match fetched.and_then(|head| {
+                head.verify(self.logs.as_ref())
+                    .map(|_| head)
+                    .map_err(Into::into)
+            }) {
+                Ok(head) => head,
+                Err(e) => {
+                    report.failures.push(VantageFailure {
+                        vantage: vantage.name().to_string(),
+                        error: e.detail(),
+                    });
+                    continue;
+                }
+            };
+            report
+                .heads
+                .push((vantage.name().to_string(), head.clone()));
+            by_log.entry(head.signer_key_id).or_default().push((
+                Some(index),
+                vantage.name().to_string(),
+                head,
+            ));
+        }
+
+        for (log, mut heads) in by_log {
+            heads.sort_by_key(|(_, _, head)| (head.tree_head.tree_size, head.tree_head.root_hash));
+            heads.dedup_by(|later, earlier| later.2.tree_head == earlier.2.tree_head);
+            for pair in heads.windows(2) {
+                let [(first_at, first_vantage, first), (second_at, second_vantage, second)] = pair
+                else {
+                    continue;
+                };
+                let (a, b) = (&first.tree_head, &second.tree_head);
+                if self.has_evidence(a, b) {
+                    continue;
+                }
+                let (kind, proof) = if a.tree_size == b.tree_size {
+                    if a.root_hash == b.root_hash {
+                        continue;
+                    }
+                    (SplitViewKind::ConflictingRoots, None)
+                } else {
+                    // Ask the vantage that served the larger head, else the smaller's
+                    let prover = second_at.or(*first_at).map(|index| &self.vantages[index]);
+                    let proof = match prover {
+                        Some(prover) => prover
+                            .consistency_proof(a.tree_size, b.tree_size)
+                            .await
+                            .ok(),
+                        None => None,
+                    };
+                    if proof
+                        .as_ref()
+                        .is_some_and(|proof| proof.verify(a, b).is_ok())
+                    {
+                        continue;
+                    }
+                    (SplitViewKind::InconsistentHistory, proof)
+                };
+                let evidence = EquivocationEvidence {
+                    kind,
+                    first: first.clone(),
+                    first_vantage: first_vantage.clone(),
+                    second: second.clone(),
+                    second_vantage: second_vantage.clone(),
+                    proof,
+                    detected_at: polled_at,
+                };
+                self.evidence.push(evidence.clone());
+                report.equivocations.push(evidence);
+            }
+            if let Some((_, vantage, head)) = heads.pop() {
+                self.latest.insert(log, (vantage, head));
+            }
+        }
+        report
+    }
+
+    fn has_evidence(&self, first: &TreeHead, second: &TreeHead) -> bool {
+        self.evidence
+            .iter()
+            .any(|e| e.first.tree_head == *first && e.second.tree_head == *second)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum MonitorError {
+    #[error("Tree head rejected: {0}")]
+    Log(#[from] LogError),
+
+    #[error("Vantage {vantage} failed: {reason}")]
+    Vantage { vantage: String, reason: String },
+
+    #[error("Tree heads do not show a split view")]
+    NotEquivocation,
+}
+
+impl ErrorCoded for MonitorError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            MonitorError::Log(e) => e.code(),
+            MonitorError::Vantage { .. } => ErrorCode::GatewayUnavailable,
+            MonitorError::NotEquivocation => ErrorCode::NotEquivocation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Signer;
+    use crate::transparency::{leaf_hash, TransparencyLog};
+    use std::sync::Mutex;
+
+    /// A view of a log served by one mirror.
+    struct Mirror {
+        name: String,
+        log: Arc<Mutex<TransparencyLog>>,
+        signer: Arc<Signer>,
+    }
+
+    impl Mirror {
+        fn new(name: &str, leaves: &[&[u8]], signer: &Arc<Signer>) -> Box<Self> {
+            let mut log = TransparencyLog::new();
+            for leaf in leaves {
+                log.append_leaf(leaf_hash(leaf));
+            }
+            Box::new(Self {
+                name: name.to_string(),
+                log: Arc::new(Mutex::new(log)),
+                signer: signer.clone(),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl LogVantage for Mirror {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        async fn signed_tree_head(&self) -> Result<SignedTreeHead, MonitorError> {
+            let head = self.log.lock().unwrap().tree_head();
+            Ok(SignedTreeHead::sign(head, &self.signer)?)
+        }
+
+        async fn consistency_proof(
+            &self,
+            first_size: u64,
+            second_size: u64,
+        ) -> Result<ConsistencyProof, MonitorError> {
+            Ok(self
+                .log
+                .lock()
+                .unwrap()
+                .consistency_proof(first_size, second_size)?)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_views_recorded() {
+        let gateway = Arc::new(Signer::generate());
+        let key = gateway.verifying_key();
+        let mut monitor = SplitHorizonMonitor::new(Box::new(key))
+            .with_vantage(Mirror::new("robots", &[b"a", b"b", b"c"], &gateway))
+            .with_vantage(Mirror::new("auditors", &[b"a", b"b", b"x"], &gateway))
+            .with_vantage(Mirror::new("archive", &[b"a", b"b"], &gateway));
+
+        let report = monitor.poll().await;
+        assert_eq!(report.heads.len(), 3);
+        assert_eq!(report.equivocations.len(), 1);
+        let evidence = &report.equivocations[0];
+        assert_eq!(evidence.kind, SplitViewKind::ConflictingRoots);
+        assert!(evidence.is_conclusive());
+        evidence.verify(&key).unwrap();
+
+        // Heads that agree are not evidence
+        let mut forged = evidence.clone();
+        forged.second = forged.first.clone();
+        assert!(matches!(
+            forged.verify(&key),
+            Err(MonitorError::NotEquivocation)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_history_rewrite_across_polls() {
+        let gateway = Arc::new(Signer::generate());
+        let mirror = Mirror::new("gateway", &[b"a", b"b", b"c"], &gateway);
+        let log = mirror.log.clone();
+        let mut monitor =
+            SplitHorizonMonitor::new(Box::new(gateway.verifying_key())).with_vantage(mirror);
+
+        assert!(monitor.poll().await.is_consistent());
+        log.lock().unwrap().append_leaf(leaf_hash(b"d"));
+        assert!(monitor.poll().await.is_consistent());
+        assert_eq!(
+            monitor
+                .latest(&gateway.key_id())
+                .unwrap()
+                .tree_head
+                .tree_size,
+            4
+        );
+
+        // The gateway rewrites entry "b" and grows the log
+        let mut rewritten = TransparencyLog::new();
+        for leaf in [b"a", b"x", b"c", b"d", b"e"] {
+            rewritten.append_leaf(leaf_hash(leaf));
+        }
+        *log.lock().unwrap() = rewritten;
+        let report = monitor.poll().await;
+        assert_eq!(report.equivocations.len(), 1);
+        assert_eq!(
+            report.equivocations[0].kind,
+            SplitViewKind::InconsistentHistory
+        );
+        report.equivocations[0]
+            .verify(&gateway.verifying_key())
+            .unwrap();
+        assert_eq!(monitor.evidence().len(), 1);
+    }
+}
@@ -0,0 +1,190 @@
+//! Nonce issuance and replay protection for attestation quotes.
+//!
+//! The verifier issues a random nonce, the enclave binds it into its quote,
+//! and the nonce is consumed exactly once when the quote is verified.
+//! Unknown, expired and reused nonces are rejected.
+
+use crate::clock::{system_clock, Clock};
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// Length of issued nonces in bytes.
+pub const NONCE_LEN: usize = 32;
+
+/// A verifier-issued nonce.
+pub type Nonce = [u8; NONCE_LEN];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum NonceError {
+    #[error("Nonce required but not supplied")]
+    Missing,
+
+    #[error("Nonce has invalid length {0}")]
+    InvalidLength(usize),
+
+    #[error("Nonce was not issued by this verifier")]
+    Unknown,
+
+    #[error("Nonce expired")]
+    Expired,
+
+    #[error("Nonce already used")]
+    Reused,
+}
+
+/// Issues nonces with expiry and tracks their consumption.
+///
+/// Consumed nonces are remembered until their original expiry so replays are
+/// reported as `Reused` rather than `Unknown`.
+pub struct NonceManager {
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+    state: Mutex<NonceState>,
+}
+
+#[derive(Default)]
+struct NonceState {
+    /// Outstanding nonces and their expiry
+    issued: HashMap<Nonce, DateTime<Utc>>,
+    /// Consumed nonces and their original expiry
+    consumed: HashMap<Nonce, DateTime<Utc>>,
+}
+
+impl NonceManager {
+    /// Create a manager whose nonces are valid for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            clock: system_clock(),
+            state: Mutex::new(NonceState::default()),
+        }
+    }
+
+    /// Read expiry times from `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Issue a fresh random nonce.
+    pub fn issue(&self) -> Nonce {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+        let expires_at = self.clock.now() + self.ttl;
+        self.lock().issued.insert(nonce, expires_at);
+        nonce
+    }
+
+    /// Consume a nonce, rejecting unknown, expired or reused values.
+    pub fn consume(&self, nonce: &[u8]) -> Result<(), NonceError> {
+        let nonce: Nonce = nonce
+            .try_into()
+            .map_err(|_| NonceError::InvalidLength(nonce.len()))?;
+
+        let now = self.clock.now();
+        let mut state = self.lock();
+
+        if state.consumed.contains_key(&nonce) {
+            return Err(NonceError::Reused);
+        }
+
+        let expires_at = state.issued.remove(&nonce).ok_or(NonceError::Unknown)?;
+        if now > expires_at {
+            return Err(NonceError::Expired);
+        }
+
+        state.consumed.insert(nonce, expires_at);
+        Ok(())
+    }
+
+    /// Drop expired nonces (issued and consumed) to bound memory use.
+    pub fn purge_expired(&self) {
+        let now = self.clock.now();
+        let mut state = self.lock();
+        state.issued.retain(|_, expires_at| *expires_at >= now);
+        state.consumed.retain(|_, expires_at| *expires_at >= now);
+    }
+
+    /// Number of issued nonces not yet consumed.
+    pub fn outstanding(&self) -> usize {
+        self.lock().issued.len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, NonceState> {
+        // A panic while holding the lock cannot leave the maps inconsistent
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl std::fmt::Debug for NonceManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NonceManager")
+            .field("ttl", &self.ttl)
+            .field("outstanding", &self.outstanding())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_issue_and_consume() {
+        let manager = NonceManager::new(Duration::minutes(5));
+        let nonce = manager.issue();
+        assert_eq!(manager.outstanding(), 1);
+
+        assert!(manager.consume(&nonce).is_ok());
+        assert_eq!(manager.outstanding(), 0);
+    }
+
+    #[test]
+    fn test_reuse_rejected() {
+        let manager = NonceManager::new(Duration::minutes(5));
+        let nonce = manager.issue();
+
+        manager.consume(&nonce).unwrap();
+        assert_eq!(manager.consume(&nonce), Err(NonceError::Reused));
+    }
+
+    #[test]
+    fn test_unknown_and_malformed_rejected() {
+        let manager = NonceManager::new(Duration::minutes(5));
+        assert_eq!(manager.consume(&[7u8; NONCE_LEN]), Err(NonceError::Unknown));
+        assert_eq!(manager.consume(b"short"), Err(NonceError::InvalidLength(5)));
+    }
+
+    #[test]
+    fn test_expired_rejected() {
+        let manager = NonceManager::new(Duration::milliseconds(-1));
+        let nonce = manager.issue();
+        assert_eq!(manager.consume(&nonce), Err(NonceError::Expired));
+
+        let nonce = manager.issue();
+        manager.purge_expired();
+        assert_eq!(manager.consume(&nonce), Err(NonceError::Unknown));
+    }
+
+    #[test]
+    fn test_expiry_follows_clock() {
+        let clock = MockClock::new(DateTime::UNIX_EPOCH + Duration::days(20_000));
+        let manager = NonceManager::new(Duration::minutes(5)).with_clock(Arc::new(clock.clone()));
+
+        let nonce = manager.issue();
+        clock.advance(Duration::minutes(5));
+        assert!(
+            manager.consume(&nonce).is_ok(),
+            "valid up to and including expiry"
+        );
+
+        let nonce = manager.issue();
+        clock.advance(Duration::minutes(5) + Duration::milliseconds(1));
+        assert_eq!(manager.consume(&nonce), Err(NonceError::Expired));
+    }
+}
@@ -0,0 +1,451 @@
+//! Long-term signature refresh (re-notarization) for archived evidence.
+//!
+//! Evidence retained for decades outlives the algorithms that originally
+//! protected it. An [`EvidenceRecord`] is a chain of [`Notarization`] layers:
+//! each layer re-hashes the evidence with a current digest algorithm, hashes
+//! the previous layer, and is signed by a notary. As long as every layer was
+//! added before the algorithms of the layer below it were retired, the whole
+//! record stays verifiable even after the original algorithms are broken.
+//!
+//! An archival job calls [`EvidenceRecord::is_due`] on each stored record
+//! (e.g., yearly) and [`EvidenceRecord::renew`] on those that are due.
+
+use crate::crypto::{ct_eq, key_id, DigestAlgorithm, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, KeyId, SignatureBytes};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use thiserror::Error;
+
+/// Notarization layer version (for schema evolution)
+pub const NOTARIZATION_VERSION: u8 = 1;
+
+/// Signature algorithm used by a notarization layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureAlgorithm {
+    Ed25519,
+}
+
+impl fmt::Display for SignatureAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureAlgorithm::Ed25519 => write!(f, "ed25519"),
+        }
+    }
+}
+
+/// One notary's signed statement over the evidence and the layer below.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notarization {
+    /// Schema version
+    pub version: u8,
+    pub digest_algorithm: DigestAlgorithm,
+    pub signature_algorithm: SignatureAlgorithm,
+    /// Digest of the evidence bytes under `digest_algorithm`
+    pub evidence_digest: Hash256,
+    /// Digest of the previous layer's canonical CBOR under `digest_algorithm`
+    /// (absent for the first layer)
+    pub previous: Option<Hash256>,
+    /// When the layer was added
+    #[serde(with = "crate::serialization::timestamp")]
+    pub notarized_at: DateTime<Utc>,
+    /// Fingerprint of the notary's signing key
+    pub notary_key_id: KeyId,
+    /// Signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned notarization (for signature computation)
+#[derive(Serialize)]
+struct UnsignedNotarization {
+    version: u8,
+    digest_algorithm: DigestAlgorithm,
+    signature_algorithm: SignatureAlgorithm,
+    evidence_digest: Hash256,
+    previous: Option<Hash256>,
+    #[serde(with = "crate::serialization::timestamp")]
+    notarized_at: DateTime<Utc>,
+    notary_key_id: KeyId,
+}
+
+impl Notarization {
+    fn issue(
+        evidence: &[u8],
+        previous: Option<&Notarization>,
+        algorithm: DigestAlgorithm,
+        notary: &Signer,
+        notarized_at: DateTime<Utc>,
+    ) -> Result<Self, NotarizationError> {
+        let previous = previous
+            .map(|layer| Ok::<_, SerializationError>(algorithm.digest(&layer.to_bytes()?)))
+            .transpose()?;
+        let mut layer = Self {
+            version: NOTARIZATION_VERSION,
+            digest_algorithm: algorithm,
+            signature_algorithm: SignatureAlgorithm::Ed25519,
+            evidence_digest: algorithm.digest(evidence),
+            previous,
+            notarized_at,
+            notary_key_id: notary.key_id(),
+            signature: SignatureBytes([0u8; 64]),
+        };
+        let signature = notary.sign(&layer.signing_payload()?);
+        layer.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(layer)
+    }
+
+    /// Verify the layer signature against a known notary key.
+    pub fn verify_signature(&self, notaries: &dyn KeyResolver) -> Result<(), NotarizationError> {
+        use ed25519_dalek::Verifier;
+
+        let verifying_key = notaries
+            .resolve(&self.notary_key_id)
+            .ok_or(NotarizationError::UnknownNotary(self.notary_key_id))?;
+        if !ct_eq(&key_id(&verifying_key).0, &self.notary_key_id.0) {
+            return Err(NotarizationError::UnknownNotary(self.notary_key_id));
+        }
+
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        verifying_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| NotarizationError::InvalidSignature(self.notarized_at))
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedNotarization {
+            version: self.version,
+            digest_algorithm: self.digest_algorithm,
+            signature_algorithm: self.signature_algorithm,
+            evidence_digest: self.evidence_digest,
+            previous: self.previous,
+            notarized_at: self.notarized_at,
+            notary_key_id: self.notary_key_id,
+        })
+    }
+}
+
+/// When each algorithm stops being trusted to protect evidence.
+///
+/// Algorithms without a sunset are trusted indefinitely.
+#[derive(Debug, Clone, Default)]
+pub struct AlgorithmPolicy {
+    digest_sunsets: HashMap<DigestAlgorithm, DateTime<Utc>>,
+    signature_sunsets: HashMap<SignatureAlgorithm, DateTime<Utc>>,
+}
+
+impl AlgorithmPolicy {
+    /// Create a policy that trusts every algorithm.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop trusting `algorithm` from `at`.
+    pub fn retire_digest(mut self, algorithm: DigestAlgorithm, at: DateTime<Utc>) -> Self {
+        self.digest_sunsets.insert(algorithm, at);
+        self
+    }
+
+    /// Stop trusting `algorithm` from `at`.
+    pub fn retire_signature(mut self, algorithm: SignatureAlgorithm, at: DateTime<Utc>) -> Self {
+        self.signature_sunsets.insert(algorithm, at);
+        self
+    }
+
+    /// Earliest sunset of the algorithms a layer relies on.
+    fn sunset(&self, layer: &Notarization) -> Option<DateTime<Utc>> {
+        let digest = self.digest_sunsets.get(&layer.digest_algorithm);
+        let signature = self.signature_sunsets.get(&layer.signature_algorithm);
+        digest.into_iter().chain(signature).min().copied()
+    }
+}
+
+/// Evidence protected by a chain of notarization layers, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvidenceRecord {
+    pub layers: Vec<Notarization>,
+}
+
+impl EvidenceRecord {
+    /// Notarize `evidence` for the first time.
+    pub fn notarize(
+        evidence: &[u8],
+        algorithm: DigestAlgorithm,
+        notary: &Signer,
+    ) -> Result<Self, NotarizationError> {
+        Ok(Self {
+            layers: vec![Notarization::issue(
+                evidence,
+                None,
+                algorithm,
+                notary,
+                Utc::now(),
+            )?],
+        })
+    }
+
+    /// Wrap the record in a fresh layer using `algorithm`.
+    pub fn renew(
+        &mut self,
+        evidence: &[u8],
+        algorithm: DigestAlgorithm,
+        notary: &Signer,
+    ) -> Result<(), NotarizationError> {
+        let latest = self.latest().ok_or(NotarizationError::Empty)?;
+        // Layers must be strictly ordered even if the notary's clock is coarse.
+        let notarized_at = Utc::now().max(latest.notarized_at + Duration::microseconds(1));
+        let layer = Notarization::issue(evidence, Some(latest), algorithm, notary, notarized_at)?;
+        self.layers.push(layer);
+        Ok(())
+    }
+
+    /// The most recent layer.
+    pub fn latest(&self) -> Option<&Notarization> {
+        self.layers.last()
+    }
+
+    /// Whether the latest layer is at least `max_age` old at `now`.
+    pub fn is_due(&self, now: DateTime<Utc>, max_age: Duration) -> bool {
+        self.latest()
+            .is_none_or(|layer| now - layer.notarized_at >= max_age)
+    }
+
+    /// Verify every layer against `evidence` as of `now`.
+    ///
+    /// Each layer's algorithms must still have been trusted when the next
+    /// layer was added (or at `now`, for the latest layer).
+    pub fn verify(
+        &self,
+        evidence: &[u8],
+        notaries: &dyn KeyResolver,
+        policy: &AlgorithmPolicy,
+        now: DateTime<Utc>,
+    ) -> Result<(), NotarizationError> {
+        if self.layers.is_empty() {
+            return Err(NotarizationError::Empty);
+        }
+
+        for (index, layer) in self.layers.iter().enumerate() {
+            if !ct_eq(
+                &layer.evidence_digest,
+                &layer.digest_algorithm.digest(evidence),
+            ) {
+                return Err(NotarizationError::EvidenceMismatch(layer.notarized_at));
+            }
+
+            let expected_previous = match index.checked_sub(1).map(|i| &self.layers[i]) {
+                Some(prev) => {
+                    if layer.notarized_at <= prev.notarized_at {
+                        return Err(NotarizationError::BrokenLink(layer.notarized_at));
+                    }
+                    Some(layer.digest_algorithm.digest(&prev.to_bytes()?))
+                }
+                None => None,
+            };
+            if layer.previous != expected_previous {
+                return Err(NotarizationError::BrokenLink(layer.notarized_at));
+            }
+
+            layer.verify_signature(notaries)?;
+
+            let protected_until = self
+                .layers
+                .get(index + 1)
+                .map_or(now, |next| next.notarized_at);
+            if let Some(sunset) = policy.sunset(layer) {
+                if protected_until >= sunset {
+                    return Err(NotarizationError::AlgorithmRetired {
+                        notarized_at: layer.notarized_at,
+                        sunset,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum NotarizationError {
+    #[error("Notarization serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Evidence record has no notarization layers")]
+    Empty,
+
+    #[error("Layer notarized at {0} does not match the evidence")]
+    EvidenceMismatch(DateTime<Utc>),
+
+    #[error("Layer notarized at {0} does not cover the previous layer")]
+    BrokenLink(DateTime<Utc>),
+
+    #[error("Unknown notary key {0}")]
+    UnknownNotary(KeyId),
+
+    #[error("Invalid signature on layer notarized at {0}")]
+    InvalidSignature(DateTime<Utc>),
+
+    #[error("Layer notarized at {notarized_at} was not renewed before its algorithms were retired at {sunset}")]
+    AlgorithmRetired {
+        notarized_at: DateTime<Utc>,
+        sunset: DateTime<Utc>,
+    },
+}
+
+impl ErrorCoded for NotarizationError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            NotarizationError::Serialization(e) => e.code(),
+            NotarizationError::EvidenceMismatch(_) => ErrorCode::NotarizationEvidenceMismatch,
+            NotarizationError::Empty | NotarizationError::BrokenLink(_) => {
+                ErrorCode::NotarizationBrokenLink
+            }
+            NotarizationError::UnknownNotary(_) => ErrorCode::NotarizationUnknownNotary,
+            NotarizationError::InvalidSignature(_) => ErrorCode::NotarizationInvalidSignature,
+            NotarizationError::AlgorithmRetired { .. } => ErrorCode::NotarizationAlgorithmRetired,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyRing;
+
+    const EVIDENCE: &[u8] = b"checkpoint chain archive";
+
+    fn notaries(signers: &[&Signer]) -> KeyRing {
+        let mut ring = KeyRing::new();
+        for signer in signers {
+            ring.insert(signer.verifying_key());
+        }
+        ring
+    }
+
+    #[test]
+    fn test_notarize_renew_and_verify() {
+        let first = Signer::generate();
+        let second = Signer::generate();
+        let mut record =
+            EvidenceRecord::notarize(EVIDENCE, DigestAlgorithm::Sha256, &first).unwrap();
+        record
+            .renew(EVIDENCE, DigestAlgorithm::Sha3_256, &second)
+            .unwrap();
+        assert_eq!(record.layers.len(), 2);
+
+        let ring = notaries(&[&first, &second]);
+        record
+            .verify(EVIDENCE, &ring, &AlgorithmPolicy::new(), Utc::now())
+            .unwrap();
+
+        let decoded = EvidenceRecord::from_bytes(&record.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, record);
+        let err = decoded
+            .verify(
+                b"other evidence",
+                &ring,
+                &AlgorithmPolicy::new(),
+                Utc::now(),
+            )
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NotarizationEvidenceMismatch);
+    }
+
+    #[test]
+    fn test_renewal_protects_retired_algorithm() {
+        let notary = Signer::generate();
+        let ring = notaries(&[&notary]);
+        let mut record =
+            EvidenceRecord::notarize(EVIDENCE, DigestAlgorithm::Sha256, &notary).unwrap();
+
+        let later = Utc::now() + Duration::days(365 * 5);
+        let policy = AlgorithmPolicy::new().retire_digest(DigestAlgorithm::Sha256, later);
+        assert!(record.verify(EVIDENCE, &ring, &policy, Utc::now()).is_ok());
+        assert!(matches!(
+            record.verify(EVIDENCE, &ring, &policy, later),
+            Err(NotarizationError::AlgorithmRetired { .. })
+        ));
+
+        // Renewed before the sunset: SHA-256 is only relied on until the renewal.
+        record
+            .renew(EVIDENCE, DigestAlgorithm::Blake3, &notary)
+            .unwrap();
+        assert!(record.verify(EVIDENCE, &ring, &policy, later).is_ok());
+
+        // A retired signature algorithm also expires the latest layer.
+        let policy = policy.retire_signature(SignatureAlgorithm::Ed25519, later);
+        let err = record.verify(EVIDENCE, &ring, &policy, later).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NotarizationAlgorithmRetired);
+    }
+
+    #[test]
+    fn test_rejects_dropped_or_tampered_layers() {
+        let notary = Signer::generate();
+        let ring = notaries(&[&notary]);
+        let mut record =
+            EvidenceRecord::notarize(EVIDENCE, DigestAlgorithm::Sha256, &notary).unwrap();
+        record
+            .renew(EVIDENCE, DigestAlgorithm::Sha256, &notary)
+            .unwrap();
+        record
+            .renew(EVIDENCE, DigestAlgorithm::Sha256, &notary)
+            .unwrap();
+
+        let mut dropped = record.clone();
+        dropped.layers.remove(1);
+        let err = dropped
+            .verify(EVIDENCE, &ring, &AlgorithmPolicy::new(), Utc::now())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NotarizationBrokenLink);
+
+        let mut tampered = record.clone();
+        tampered.layers[0].notarized_at -= Duration::days(1);
+        let err = tampered
+            .verify(EVIDENCE, &ring, &AlgorithmPolicy::new(), Utc::now())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NotarizationInvalidSignature);
+
+        let stranger = KeyRing::new();
+        assert!(matches!(
+            record.verify(EVIDENCE, &stranger, &AlgorithmPolicy::new(), Utc::now()),
+            Err(NotarizationError::UnknownNotary(_))
+        ));
+        assert!(matches!(
+            EvidenceRecord { layers: Vec::new() }.verify(
+                EVIDENCE,
+                &ring,
+                &AlgorithmPolicy::new(),
+                Utc::now()
+            ),
+            Err(NotarizationError::Empty)
+        ));
+    }
+
+    #[test]
+    fn test_is_due() {
+        let notary = Signer::generate();
+        let record = EvidenceRecord::notarize(EVIDENCE, DigestAlgorithm::Sha256, &notary).unwrap();
+        let year = Duration::days(365);
+        assert!(!record.is_due(Utc::now(), year));
+        assert!(record.is_due(Utc::now() + year, year));
+    }
+}
@@ -0,0 +1,336 @@
+//! Operator intervention records.
+//!
+//! When a human takes over a robot through a teleoperation gateway, the log
+//! must show who did it and on whose authority. The gateway has the
+//! operator sign an [`OperatorAction`] (operator identity, command, and a
+//! hash of the authorization token the session was opened with) and
+//! inserts it into the robot's log as an ordinary Merkle entry
+//! ([`OperatorAction::to_entry`]), so the takeover is committed by the next
+//! checkpoint like any other event.
+//!
+//! [`OperatorAction::verify`] resolves the signing key through an
+//! [`OperatorRegistry`] loaded from the fleet registry and checks that the
+//! key belongs to the operator the record names: a valid signature by some
+//! other operator's key is rejected.
+//!
+//! Authorization tokens are bearer secrets, so only their SHA-256 is
+//! recorded; [`OperatorAction::authorized_by`] checks a token against it.
+
+use crate::crypto::{ct_eq, key_id, sha256, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::merkle::Entry;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, KeyId, RobotId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Operator action version (for schema evolution)
+pub const OPERATOR_ACTION_VERSION: u8 = 1;
+
+/// What the operator did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperatorCommand {
+    /// Human took manual control
+    Takeover,
+    /// Control handed back to the autonomy stack
+    Handback,
+    /// Robot stopped by the operator
+    EmergencyStop,
+    /// Any other command, by name
+    Command(String),
+}
+
+/// Signed record of a human intervention, logged as a Merkle entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperatorAction {
+    /// Schema version
+    pub version: u8,
+    pub robot_id: RobotId,
+    /// Operator identity, as registered in the fleet registry
+    pub operator_id: String,
+    pub command: OperatorCommand,
+    /// SHA-256 of the authorization token the session was opened with
+    pub authorization_hash: Hash256,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub issued_at: DateTime<Utc>,
+    /// Fingerprint of the operator's signing key
+    pub operator_key_id: KeyId,
+    /// Ed25519 signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned operator action (for signature computation)
+#[derive(Serialize)]
+struct UnsignedOperatorAction<'a> {
+    version: u8,
+    robot_id: &'a RobotId,
+    operator_id: &'a str,
+    command: &'a OperatorCommand,
+    authorization_hash: Hash256,
+    #[serde(with = "crate::serialization::timestamp")]
+    issued_at: DateTime<Utc>,
+    operator_key_id: KeyId,
+}
+
+impl OperatorAction {
+    /// Record `command` on `robot_id` by `operator_id`, authorized by `token`.
+    pub fn issue(
+        robot_id: RobotId,
+        operator_id: impl Into<String>,
+        command: OperatorCommand,
+        token: &[u8],
+        operator: &Signer,
+    ) -> Result<Self, OperatorError> {
+        let mut action = Self {
+            version: OPERATOR_ACTION_VERSION,
+            robot_id,
+            operator_id: operator_id.into(),
+            command,
+            authorization_hash: sha256(token),
+            issued_at: Utc::now(),
+            operator_key_id: operator.key_id(),
+            signature: SignatureBytes([0u8; 64]),
+        };
+        let signature = operator.sign(&action.signing_payload()?);
+        action.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(action)
+    }
+
+    /// Verify the signature and that the key belongs to the named operator.
+    pub fn verify(&self, registry: &OperatorRegistry) -> Result<(), OperatorError> {
+        use ed25519_dalek::Verifier;
+
+        let (operator_id, verifying_key) = registry
+            .operators
+            .get(&self.operator_key_id)
+            .ok_or(OperatorError::UnknownOperator(self.operator_key_id))?;
+        if *operator_id != self.operator_id {
+            return Err(OperatorError::IdentityMismatch {
+                claimed: self.operator_id.clone(),
+                registered: operator_id.clone(),
+            });
+        }
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        verifying_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| OperatorError::InvalidSignature)
+    }
+
+    /// Verify the action and that `entry` commits to it.
+    pub fn verify_entry(
+        &self,
+        entry: &Entry,
+        registry: &OperatorRegistry,
+    ) -> Result<(), OperatorError> {
+        if !ct_eq(&entry.data_hash, &sha256(&self.to_bytes()?)) {
+            return Err(OperatorError::EntryMismatch);
+        }
+        self.verify(registry)
+    }
+
+    /// Whether the session was opened with `token`.
+    pub fn authorized_by(&self, token: &[u8]) -> bool {
+        ct_eq(&sha256(token), &self.authorization_hash)
+    }
+
+    /// Merkle entry committing to this action; its payload is [`Self::to_bytes`].
+    pub fn to_entry(&self, timestamp_us: u64, nonce: u64) -> Result<Entry, SerializationError> {
+        Ok(Entry::new(timestamp_us, nonce, &self.to_bytes()?))
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedOperatorAction {
+            version: self.version,
+            robot_id: &self.robot_id,
+            operator_id: &self.operator_id,
+            command: &self.command,
+            authorization_hash: self.authorization_hash,
+            issued_at: self.issued_at,
+            operator_key_id: self.operator_key_id,
+        })
+    }
+}
+
+/// Operator keys by fingerprint, with the operator each is registered to
+/// (e.g., loaded from the fleet registry).
+#[derive(Debug, Clone, Default)]
+pub struct OperatorRegistry {
+    operators: HashMap<KeyId, (String, VerifyingKey)>,
+}
+
+impl OperatorRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `key` to `operator_id`, returning its key id.
+    pub fn register(&mut self, operator_id: impl Into<String>, key: VerifyingKey) -> KeyId {
+        let id = key_id(&key);
+        self.operators.insert(id, (operator_id.into(), key));
+        id
+    }
+
+    /// Remove a key (e.g., when an operator leaves).
+    pub fn remove(&mut self, id: &KeyId) -> Option<VerifyingKey> {
+        self.operators.remove(id).map(|(_, key)| key)
+    }
+
+    /// Operator a key is registered to.
+    pub fn operator_of(&self, id: &KeyId) -> Option<&str> {
+        self.operators
+            .get(id)
+            .map(|(operator_id, _)| operator_id.as_str())
+    }
+}
+
+impl KeyResolver for OperatorRegistry {
+    fn resolve(&self, id: &KeyId) -> Option<VerifyingKey> {
+        self.operators.get(id).map(|(_, key)| *key)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum OperatorError {
+    #[error("Operator action serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Operator action signed by unregistered key {0}")]
+    UnknownOperator(KeyId),
+
+    #[error("Operator action names {claimed}, but its key is registered to {registered}")]
+    IdentityMismatch { claimed: String, registered: String },
+
+    #[error("Invalid operator action signature")]
+    InvalidSignature,
+
+    #[error("Entry does not commit to the operator action")]
+    EntryMismatch,
+}
+
+impl ErrorCoded for OperatorError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            OperatorError::Serialization(e) => e.code(),
+            OperatorError::UnknownOperator(_) => ErrorCode::OperatorUnknown,
+            OperatorError::IdentityMismatch { .. } => ErrorCode::OperatorIdentityMismatch,
+            OperatorError::InvalidSignature | OperatorError::EntryMismatch => {
+                ErrorCode::OperatorActionInvalid
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::MerkleTree;
+
+    #[test]
+    fn test_takeover_attributable_to_registered_operator() {
+        let alice = Signer::generate();
+        let bob = Signer::generate();
+        let mut registry = OperatorRegistry::new();
+        registry.register("alice", alice.verifying_key());
+        registry.register("bob", bob.verifying_key());
+
+        let action = OperatorAction::issue(
+            RobotId("R-001".to_string()),
+            "alice",
+            OperatorCommand::Takeover,
+            b"session-token",
+            &alice,
+        )
+        .unwrap();
+        let entry = action.to_entry(1_000, 0).unwrap();
+        let mut tree = MerkleTree::new();
+        tree.insert(entry.clone());
+        assert!(tree.generate_proof(1_000, 0).unwrap().verify(&tree.root()));
+        action.verify_entry(&entry, &registry).unwrap();
+        assert!(action.authorized_by(b"session-token"));
+        assert!(!action.authorized_by(b"other-token"));
+        assert_eq!(
+            OperatorAction::from_bytes(&action.to_bytes().unwrap()).unwrap(),
+            action
+        );
+
+        // Bob cannot act in Alice's name
+        let forged = OperatorAction::issue(
+            RobotId("R-001".to_string()),
+            "alice",
+            OperatorCommand::EmergencyStop,
+            b"session-token",
+            &bob,
+        )
+        .unwrap();
+        let err = forged.verify(&registry).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::OperatorIdentityMismatch);
+
+        let mut tampered = action.clone();
+        tampered.command = OperatorCommand::Handback;
+        assert!(matches!(
+            tampered.verify(&registry),
+            Err(OperatorError::InvalidSignature)
+        ));
+        assert!(matches!(
+            tampered.verify_entry(&entry, &registry),
+            Err(OperatorError::EntryMismatch)
+        ));
+
+        registry.remove(&alice.key_id());
+        assert_eq!(
+            action.verify(&registry).unwrap_err().code(),
+            ErrorCode::OperatorUnknown
+        );
+    }
+
+    #[test]
+    fn test_entry_not_committing_to_action_rejected() {
+        let alice = Signer::generate();
+        let mut registry = OperatorRegistry::new();
+        registry.register("alice", alice.verifying_key());
+        let issue = |command| {
+            OperatorAction::issue(
+                RobotId("R-001".to_string()),
+                "alice",
+                command,
+                b"session-token",
+                &alice,
+            )
+            .unwrap()
+        };
+        let takeover = issue(OperatorCommand::Takeover);
+        let handback = issue(OperatorCommand::Handback);
+
+        // Both actions are genuine, but each entry commits to only one of them
+        let logged = handback.to_entry(1_000, 0).unwrap();
+        takeover.verify(&registry).unwrap();
+        let err = takeover.verify_entry(&logged, &registry).unwrap_err();
+        assert!(matches!(err, OperatorError::EntryMismatch));
+        assert_eq!(err.code(), ErrorCode::OperatorActionInvalid);
+        handback.verify_entry(&logged, &registry).unwrap();
+
+        // An entry whose data hash was rewritten, timestamp and nonce kept
+        let mut rewritten = takeover.to_entry(1_000, 0).unwrap();
+        rewritten.data_hash[0] ^= 1;
+        assert!(matches!(
+            takeover.verify_entry(&rewritten, &registry),
+            Err(OperatorError::EntryMismatch)
+        ));
+    }
+}
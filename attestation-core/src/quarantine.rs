@@ -0,0 +1,327 @@
+//! Quarantine of rejected submissions.
+//!
+//! A submission the gateway rejects is evidence too: a forged quote, a
+//! rolled-back chain or entries that do not match their root are exactly
+//! what a forensics team needs to look at. Instead of dropping it, the
+//! ingestion path files it with [`Quarantine::hold`], keeping the bytes as
+//! received and the structured failure report ([`ErrorDetail`]) that was
+//! returned to the submitter, and every [`QuarantineAlertSink`] is told.
+//!
+//! Reviewers (see [`Route::ReviewQuarantine`](crate::auth::Route)) work
+//! through [`Quarantine::pending`] and settle each submission once:
+//!
+//! - [`approve`](Quarantine::approve) releases the bytes for another pass
+//!   through ingestion, e.g. after a missing trust anchor was added. The
+//!   submission is verified again in full; approval never admits it by
+//!   itself
+//! - [`reject`](Quarantine::reject) confirms the rejection
+//!
+//! Either way the submission and its review stay in quarantine as a record.
+
+use crate::clock::{system_clock, Clock};
+use crate::crypto::sha256;
+use crate::error::{ErrorCode, ErrorCoded, ErrorDetail};
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, RobotId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// Where a quarantined submission stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuarantineStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// A reviewer's decision on a quarantined submission.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantineReview {
+    pub reviewer: String,
+    pub note: String,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub reviewed_at: DateTime<Utc>,
+}
+
+/// A rejected submission, kept for review.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantinedSubmission {
+    pub id: u64,
+    pub robot_id: RobotId,
+    /// The submission as received
+    pub submission: Vec<u8>,
+    /// SHA-256 of `submission`
+    pub submission_hash: Hash256,
+    /// The rejection, as returned to the submitter
+    pub failure: ErrorDetail,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub received_at: DateTime<Utc>,
+    pub status: QuarantineStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<QuarantineReview>,
+}
+
+/// Receives an alert for every submission put in quarantine.
+pub trait QuarantineAlertSink: Send + Sync {
+    fn quarantined(&self, submission: &QuarantinedSubmission);
+}
+
+/// In-memory sink, for tests and short-lived tools.
+impl QuarantineAlertSink for Mutex<Vec<QuarantinedSubmission>> {
+    fn quarantined(&self, submission: &QuarantinedSubmission) {
+        self.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(submission.clone());
+    }
+}
+
+/// Hands alerts to a delivery task; alerts are dropped once it has gone away.
+impl QuarantineAlertSink for mpsc::Sender<QuarantinedSubmission> {
+    fn quarantined(&self, submission: &QuarantinedSubmission) {
+        let _ = self.send(submission.clone());
+    }
+}
+
+/// Rejected submissions awaiting or past review.
+pub struct Quarantine {
+    submissions: BTreeMap<u64, QuarantinedSubmission>,
+    alerts: Vec<Arc<dyn QuarantineAlertSink>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for Quarantine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Quarantine {
+    pub fn new() -> Self {
+        Self::from_submissions(Vec::new())
+    }
+
+    /// Resume with persisted submissions.
+    pub fn from_submissions(submissions: Vec<QuarantinedSubmission>) -> Self {
+        Self {
+            submissions: submissions.into_iter().map(|s| (s.id, s)).collect(),
+            alerts: Vec::new(),
+            clock: system_clock(),
+        }
+    }
+
+    /// Also alert `sink` of every submission put in quarantine.
+    pub fn with_alerts(mut self, sink: Arc<dyn QuarantineAlertSink>) -> Self {
+        self.alerts.push(sink);
+        self
+    }
+
+    /// Timestamp submissions and reviews by `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Put a submission `robot_id` sent, rejected with `error`, in quarantine.
+    /// Returns its id.
+    pub fn hold<E: ErrorCoded>(
+        &mut self,
+        robot_id: RobotId,
+        submission: Vec<u8>,
+        error: &E,
+    ) -> u64 {
+        let id = self
+            .submissions
+            .keys()
+            .next_back()
+            .map_or(0, |last| last + 1);
+        let held = QuarantinedSubmission {
+            id,
+            robot_id,
+            submission_hash: sha256(&submission),
+            submission,
+            failure: error.detail(),
+            received_at: self.clock.now(),
+            status: QuarantineStatus::Pending,
+            review: None,
+        };
+        for sink in &self.alerts {
+            sink.quarantined(&held);
+        }
+        self.submissions.insert(id, held);
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&QuarantinedSubmission> {
+        self.submissions.get(&id)
+    }
+
+    /// Submissions awaiting review, oldest first.
+    pub fn pending(&self) -> impl Iterator<Item = &QuarantinedSubmission> {
+        self.submissions
+            .values()
+            .filter(|s| s.status == QuarantineStatus::Pending)
+    }
+
+    /// Every submission of `robot_id`, oldest first.
+    pub fn for_robot<'a>(
+        &'a self,
+        robot_id: &'a RobotId,
+    ) -> impl Iterator<Item = &'a QuarantinedSubmission> {
+        self.submissions
+            .values()
+            .filter(move |s| &s.robot_id == robot_id)
+    }
+
+    /// All submissions, for persisting.
+    pub fn submissions(&self) -> impl Iterator<Item = &QuarantinedSubmission> {
+        self.submissions.values()
+    }
+
+    /// Release submission `id` for another pass through ingestion, and
+    /// return its bytes.
+    pub fn approve(
+        &mut self,
+        id: u64,
+        reviewer: &str,
+        note: &str,
+    ) -> Result<Vec<u8>, QuarantineError> {
+        let submission = self.settle(id, QuarantineStatus::Approved, reviewer, note)?;
+        Ok(submission.submission.clone())
+    }
+
+    /// Confirm the rejection of submission `id`.
+    pub fn reject(&mut self, id: u64, reviewer: &str, note: &str) -> Result<(), QuarantineError> {
+        self.settle(id, QuarantineStatus::Rejected, reviewer, note)?;
+        Ok(())
+    }
+
+    /// Serialize all submissions to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&self.submissions().collect::<Vec<_>>())
+    }
+
+    /// Deserialize submissions written by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        Ok(Self::from_submissions(from_canonical_cbor(bytes)?))
+    }
+
+    fn settle(
+        &mut self,
+        id: u64,
+        status: QuarantineStatus,
+        reviewer: &str,
+        note: &str,
+    ) -> Result<&QuarantinedSubmission, QuarantineError> {
+        let reviewed_at = self.clock.now();
+        let submission = self
+            .submissions
+            .get_mut(&id)
+            .ok_or(QuarantineError::Unknown(id))?;
+        if submission.status != QuarantineStatus::Pending {
+            return Err(QuarantineError::AlreadyReviewed(id));
+        }
+        submission.status = status;
+        submission.review = Some(QuarantineReview {
+            reviewer: reviewer.to_string(),
+            note: note.to_string(),
+            reviewed_at,
+        });
+        Ok(submission)
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum QuarantineError {
+    #[error("No quarantined submission {0}")]
+    Unknown(u64),
+
+    #[error("Quarantined submission {0} was already reviewed")]
+    AlreadyReviewed(u64),
+}
+
+impl ErrorCoded for QuarantineError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            QuarantineError::Unknown(_) => ErrorCode::QuarantineUnknown,
+            QuarantineError::AlreadyReviewed(_) => ErrorCode::QuarantineReviewed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::ChainError;
+    use crate::clock::MockClock;
+    use chrono::Duration;
+
+    fn start() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-01-15T00:00:00Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    #[test]
+    fn test_rejected_submissions_held_alerted_and_reviewed_once() {
+        let clock = MockClock::new(start());
+        let alerts = Arc::new(Mutex::new(Vec::new()));
+        let (sender, receiver) = mpsc::channel();
+        let mut quarantine = Quarantine::new()
+            .with_clock(Arc::new(clock.clone()))
+            .with_alerts(alerts.clone())
+            .with_alerts(Arc::new(sender));
+        let robot = RobotId("R-001".to_string());
+        let rollback = ChainError::SequenceRegression {
+            previous: 5,
+            actual: 3,
+        };
+
+        let first = quarantine.hold(robot.clone(), b"checkpoint #3".to_vec(), &rollback);
+        let second = quarantine.hold(
+            RobotId("R-002".to_string()),
+            b"forged quote".to_vec(),
+            &rollback,
+        );
+        let held = quarantine.get(first).unwrap();
+        assert_eq!(held.failure.code, ErrorCode::SequenceRegression);
+        assert_eq!(held.submission_hash, sha256(b"checkpoint #3"));
+        assert_eq!(alerts.lock().unwrap().len(), 2);
+        assert_eq!(receiver.try_recv().unwrap().id, first);
+        assert_eq!(quarantine.pending().count(), 2);
+
+        clock.advance(Duration::hours(1));
+        assert_eq!(
+            quarantine
+                .approve(first, "forensics", "anchor added")
+                .unwrap(),
+            b"checkpoint #3"
+        );
+        quarantine
+            .reject(second, "forensics", "quote forged")
+            .unwrap();
+        assert_eq!(quarantine.pending().count(), 0);
+        let review = quarantine.get(first).unwrap().review.as_ref().unwrap();
+        assert_eq!(review.reviewed_at, start() + Duration::hours(1));
+
+        // Each submission is settled once, and nothing is deleted
+        let err = quarantine
+            .reject(first, "forensics", "changed my mind")
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::QuarantineReviewed);
+        assert_eq!(
+            quarantine.approve(7, "forensics", "").unwrap_err().code(),
+            ErrorCode::QuarantineUnknown
+        );
+        let restored = Quarantine::from_bytes(&quarantine.to_bytes().unwrap()).unwrap();
+        assert_eq!(
+            restored.for_robot(&robot).next().unwrap().status,
+            QuarantineStatus::Approved
+        );
+        assert_eq!(restored.submissions().count(), 2);
+    }
+}
@@ -0,0 +1,421 @@
+//! Quorum notarization across independent gateways.
+//!
+//! A robot submits each checkpoint to several gateways run by independent
+//! operators. Every gateway appends it to its own [transparency log] and
+//! answers with an inclusion proof under a signed tree head. A
+//! [`QuorumReceipt`] collects those answers; relying parties accept the
+//! checkpoint only if at least `threshold` distinct, trusted gateways logged
+//! it, so no single operator can suppress it.
+//!
+//! [`QuorumReceipt::check_order`] additionally catches a gateway that logged
+//! two checkpoints of a chain in the wrong order.
+//!
+//! [transparency log]: crate::transparency
+
+use crate::checkpoint::Checkpoint;
+use crate::clock::{ClockError, SkewDecision};
+use crate::crypto::ct_eq;
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::transparency::{
+    checkpoint_leaf_hash, InclusionProof, LogError, LoggedCheckpoint, SignedTreeHead,
+};
+use crate::types::{Hash256, KeyId};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// A gateway that logs checkpoints.
+#[async_trait]
+pub trait NotaryGateway: Send + Sync {
+    /// Operator-facing name (e.g., base URL), used in error reports.
+    fn name(&self) -> &str;
+
+    /// Append `checkpoint` to the gateway's log and return its proof of inclusion.
+    async fn submit(&self, checkpoint: &Checkpoint) -> Result<LoggedCheckpoint, QuorumError>;
+}
+
+/// One gateway's proof that it logged the checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GatewayReceipt {
+    pub inclusion: InclusionProof,
+    pub tree_head: SignedTreeHead,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<SkewDecision>,
+}
+
+impl GatewayReceipt {
+    /// Fingerprint of the gateway's log key.
+    pub fn gateway(&self) -> KeyId {
+        self.tree_head.signer_key_id
+    }
+
+    fn verify(
+        &self,
+        checkpoint: &Checkpoint,
+        leaf: &Hash256,
+        gateways: &dyn KeyResolver,
+    ) -> Result<(), LogError> {
+        self.tree_head.verify(gateways)?;
+        self.inclusion.verify(leaf, &self.tree_head.tree_head)?;
+        if let Some(clock) = &self.clock {
+            if clock.gateway_key_id != self.gateway() {
+                return Err(LogError::Clock(ClockError::UnknownGateway(
+                    clock.gateway_key_id,
+                )));
+            }
+            clock.verify(checkpoint, gateways)?;
+        }
+        Ok(())
+    }
+}
+
+/// Receipts from several gateways for one checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuorumReceipt {
+    /// Log leaf hash of the checkpoint
+    pub leaf_hash: Hash256,
+    /// At most one receipt per gateway
+    pub receipts: Vec<GatewayReceipt>,
+}
+
+impl QuorumReceipt {
+    /// Start an empty receipt for `checkpoint`.
+    pub fn new(checkpoint: &Checkpoint) -> Result<Self, QuorumError> {
+        Ok(Self {
+            leaf_hash: checkpoint_leaf_hash(checkpoint)?,
+            receipts: Vec::new(),
+        })
+    }
+
+    /// Add a gateway's answer, replacing any earlier receipt from the same gateway.
+    ///
+    /// The inclusion proof is checked here; tree head signatures are checked
+    /// by [`QuorumReceipt::verify`] against the caller's trusted gateway keys.
+    pub fn add(&mut self, logged: LoggedCheckpoint) -> Result<(), QuorumError> {
+        if !ct_eq(&checkpoint_leaf_hash(&logged.checkpoint)?, &self.leaf_hash) {
+            return Err(QuorumError::CheckpointMismatch);
+        }
+        let receipt = GatewayReceipt {
+            inclusion: logged.inclusion,
+            tree_head: logged.tree_head,
+            clock: logged.clock,
+        };
+        receipt
+            .inclusion
+            .verify(&self.leaf_hash, &receipt.tree_head.tree_head)?;
+
+        self.receipts.retain(|r| r.gateway() != receipt.gateway());
+        self.receipts.push(receipt);
+        Ok(())
+    }
+
+    /// Check that at least `threshold` distinct trusted gateways logged `checkpoint`.
+    ///
+    /// Receipts from unknown gateways, with bad proofs, or whose gateway
+    /// rejected the checkpoint timestamp do not count.
+    /// Returns the gateways that vouched for the checkpoint.
+    pub fn verify(
+        &self,
+        checkpoint: &Checkpoint,
+        gateways: &dyn KeyResolver,
+        threshold: usize,
+    ) -> Result<Vec<KeyId>, QuorumError> {
+        let leaf = checkpoint_leaf_hash(checkpoint)?;
+        if !ct_eq(&leaf, &self.leaf_hash) {
+            return Err(QuorumError::CheckpointMismatch);
+        }
+
+        let mut vouched: Vec<KeyId> = Vec::new();
+        for receipt in &self.receipts {
+            let gateway = receipt.gateway();
+            if !vouched.contains(&gateway) && receipt.verify(checkpoint, &leaf, gateways).is_ok() {
+                vouched.push(gateway);
+            }
+        }
+
+        if vouched.len() < threshold.max(1) {
+            return Err(QuorumError::InsufficientQuorum {
+                valid: vouched.len(),
+                required: threshold.max(1),
+            });
+        }
+        Ok(vouched)
+    }
+
+    /// Check that every gateway holding receipts for both `earlier` and this
+    /// checkpoint logged `earlier` first.
+    pub fn check_order(&self, earlier: &QuorumReceipt) -> Result<(), QuorumError> {
+        for receipt in &self.receipts {
+            let Some(previous) = earlier
+                .receipts
+                .iter()
+                .find(|r| r.gateway() == receipt.gateway())
+            else {
+                continue;
+            };
+            if receipt.inclusion.leaf_index <= previous.inclusion.leaf_index {
+                return Err(QuorumError::OutOfOrder(receipt.gateway()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+}
+
+/// Submit `checkpoint` to every gateway concurrently and collect their receipts.
+///
+/// One gateway failing does not stop the others. Fails with
+/// [`QuorumError::InsufficientQuorum`] if fewer than `threshold` gateways
+/// returned a valid receipt.
+pub async fn submit_to_quorum(
+    gateways: &[Arc<dyn NotaryGateway>],
+    checkpoint: &Checkpoint,
+    threshold: usize,
+) -> Result<QuorumReceipt, QuorumError> {
+    let mut quorum = QuorumReceipt::new(checkpoint)?;
+    let submissions = gateways.iter().map(|gateway| gateway.submit(checkpoint));
+    // Failed or malformed answers simply do not count towards the quorum.
+    for logged in futures::future::join_all(submissions)
+        .await
+        .into_iter()
+        .flatten()
+    {
+        let _ = quorum.add(logged);
+    }
+
+    if quorum.receipts.len() < threshold.max(1) {
+        return Err(QuorumError::InsufficientQuorum {
+            valid: quorum.receipts.len(),
+            required: threshold.max(1),
+        });
+    }
+    Ok(quorum)
+}
+
+#[derive(Debug, Error)]
+pub enum QuorumError {
+    #[error("Quorum receipt serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Gateway receipt is invalid: {0}")]
+    Log(#[from] LogError),
+
+    #[error("Receipt is for a different checkpoint")]
+    CheckpointMismatch,
+
+    #[error("Only {valid} of {required} required gateways logged the checkpoint")]
+    InsufficientQuorum { valid: usize, required: usize },
+
+    #[error("Gateway {0} logged the checkpoints out of order")]
+    OutOfOrder(KeyId),
+
+    #[error("Gateway {gateway} failed: {reason}")]
+    Gateway { gateway: String, reason: String },
+}
+
+impl ErrorCoded for QuorumError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            QuorumError::Serialization(e) => e.code(),
+            QuorumError::Log(e) => e.code(),
+            QuorumError::CheckpointMismatch => ErrorCode::QuorumCheckpointMismatch,
+            QuorumError::InsufficientQuorum { .. } => ErrorCode::QuorumNotReached,
+            QuorumError::OutOfOrder(_) => ErrorCode::QuorumOutOfOrder,
+            QuorumError::Gateway { .. } => ErrorCode::GatewayUnavailable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::clock::ClockSkewPolicy;
+    use crate::crypto::Signer;
+    use crate::keys::KeyRing;
+    use crate::transparency::TransparencyLog;
+    use chrono::{Duration, Utc};
+    use std::sync::Mutex;
+
+    /// Gateway backed by an in-memory log.
+    struct LocalGateway {
+        name: String,
+        signer: Signer,
+        log: Mutex<TransparencyLog>,
+        down: bool,
+        skew: Option<ClockSkewPolicy>,
+    }
+
+    impl LocalGateway {
+        fn new(name: &str) -> Self {
+            Self {
+                name: name.to_string(),
+                signer: Signer::generate(),
+                log: Mutex::new(TransparencyLog::new()),
+                down: false,
+                skew: None,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl NotaryGateway for LocalGateway {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        async fn submit(&self, checkpoint: &Checkpoint) -> Result<LoggedCheckpoint, QuorumError> {
+            if self.down {
+                return Err(QuorumError::Gateway {
+                    gateway: self.name.clone(),
+                    reason: "connection refused".to_string(),
+                });
+            }
+            let mut log = self.log.lock().unwrap();
+            let index = log.append_checkpoint(checkpoint)?;
+            let mut logged = log.serve(index, checkpoint.clone(), &self.signer)?;
+            if let Some(policy) = &self.skew {
+                let decision = policy.decide(checkpoint, Utc::now(), &self.signer);
+                logged.clock = Some(decision.map_err(LogError::from)?);
+            }
+            Ok(logged)
+        }
+    }
+
+    fn signed_checkpoint(sequence: u64) -> Checkpoint {
+        CheckpointBuilder::test_fixture()
+            .sequence(sequence)
+            .monotonic_counter(sequence + 1)
+            .entries_root([sequence as u8; 32])
+            .build_and_sign(Signer::generate().signing_key())
+            .unwrap()
+    }
+
+    fn fleet(names: &[&str]) -> (Vec<Arc<LocalGateway>>, KeyRing) {
+        let gateways: Vec<Arc<LocalGateway>> = names
+            .iter()
+            .map(|name| Arc::new(LocalGateway::new(name)))
+            .collect();
+        let mut keys = KeyRing::new();
+        for gateway in &gateways {
+            keys.insert(gateway.signer.verifying_key());
+        }
+        (gateways, keys)
+    }
+
+    fn dyn_gateways(gateways: &[Arc<LocalGateway>]) -> Vec<Arc<dyn NotaryGateway>> {
+        gateways
+            .iter()
+            .map(|g| g.clone() as Arc<dyn NotaryGateway>)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_quorum_reached_despite_one_gateway_down() {
+        let (mut gateways, keys) = fleet(&["a", "b", "c"]);
+        Arc::get_mut(&mut gateways[2]).unwrap().down = true;
+        let checkpoint = signed_checkpoint(0);
+
+        let receipt = submit_to_quorum(&dyn_gateways(&gateways), &checkpoint, 2)
+            .await
+            .unwrap();
+        assert_eq!(receipt.verify(&checkpoint, &keys, 2).unwrap().len(), 2);
+
+        let decoded = QuorumReceipt::from_bytes(&receipt.to_bytes().unwrap()).unwrap();
+        let err = decoded.verify(&checkpoint, &keys, 3).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::QuorumNotReached);
+        assert!(submit_to_quorum(&dyn_gateways(&gateways), &checkpoint, 3)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_untrusted_and_duplicate_gateways_do_not_count() {
+        let (gateways, keys) = fleet(&["a", "b"]);
+        let (rogue, _) = fleet(&["rogue"]);
+        let checkpoint = signed_checkpoint(0);
+
+        let mut receipt = QuorumReceipt::new(&checkpoint).unwrap();
+        receipt
+            .add(gateways[0].submit(&checkpoint).await.unwrap())
+            .unwrap();
+        receipt
+            .add(gateways[0].submit(&checkpoint).await.unwrap())
+            .unwrap();
+        receipt
+            .add(rogue[0].submit(&checkpoint).await.unwrap())
+            .unwrap();
+        assert_eq!(receipt.receipts.len(), 2);
+        assert!(matches!(
+            receipt.verify(&checkpoint, &keys, 2),
+            Err(QuorumError::InsufficientQuorum {
+                valid: 1,
+                required: 2
+            })
+        ));
+
+        let other = signed_checkpoint(1);
+        assert!(matches!(
+            receipt.add(gateways[1].submit(&other).await.unwrap()),
+            Err(QuorumError::CheckpointMismatch)
+        ));
+        assert!(receipt.verify(&other, &keys, 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_timestamp_does_not_count() {
+        let (mut gateways, keys) = fleet(&["strict", "lenient"]);
+        let strict = ClockSkewPolicy {
+            max_ahead: Duration::zero(),
+            max_behind: Duration::zero(),
+        };
+        Arc::get_mut(&mut gateways[0]).unwrap().skew = Some(strict);
+        Arc::get_mut(&mut gateways[1]).unwrap().skew = Some(ClockSkewPolicy::default());
+        let checkpoint = signed_checkpoint(0);
+
+        let receipt = submit_to_quorum(&dyn_gateways(&gateways), &checkpoint, 2)
+            .await
+            .unwrap();
+        assert!(receipt.receipts.iter().all(|r| r.clock.is_some()));
+        let vouched = receipt.verify(&checkpoint, &keys, 1).unwrap();
+        assert_eq!(vouched, vec![gateways[1].signer.key_id()]);
+    }
+
+    #[tokio::test]
+    async fn test_check_order() {
+        let (gateways, _) = fleet(&["a", "b"]);
+        let (first, second) = (signed_checkpoint(0), signed_checkpoint(1));
+
+        // Gateway "b" logs the later checkpoint first.
+        let mut first_receipt = QuorumReceipt::new(&first).unwrap();
+        let mut second_receipt = QuorumReceipt::new(&second).unwrap();
+        first_receipt
+            .add(gateways[0].submit(&first).await.unwrap())
+            .unwrap();
+        second_receipt
+            .add(gateways[0].submit(&second).await.unwrap())
+            .unwrap();
+        assert!(second_receipt.check_order(&first_receipt).is_ok());
+
+        second_receipt
+            .add(gateways[1].submit(&second).await.unwrap())
+            .unwrap();
+        first_receipt
+            .add(gateways[1].submit(&first).await.unwrap())
+            .unwrap();
+        let err = second_receipt.check_order(&first_receipt).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::QuorumOutOfOrder);
+    }
+}
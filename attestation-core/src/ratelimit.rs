@@ -0,0 +1,353 @@
+//! Per-robot rate limiting and storage quotas for the gateway.
+//!
+//! One robot flooding checkpoints must not starve verification for the rest
+//! of the fleet. Before verifying a submission the ingestion path calls
+//! [`RateLimiter::admit`], which takes a token from the robot's bucket, and
+//! [`RateLimiter::check_quota`], which compares what the robot already
+//! stores against its [`Quota`].
+//!
+//! Limits come from the [`LiveConfig`] in force at each call (see
+//! [`GatewayConfig::rate_limit_for`] and [`GatewayConfig::quota_for`]), so a
+//! reload changes them without a restart. A robot whose limit changed keeps
+//! the tokens it had, up to the new burst. Robots without a limit or quota
+//! are not limited.
+
+use crate::clock::{system_clock, Clock};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::reload::{GatewayConfig, LiveConfig, Quota, RateLimit};
+use crate::types::RobotId;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use thiserror::Error;
+
+/// Units of a token: refilling `per_minute` units every millisecond adds
+/// `per_minute` tokens a minute without fractions.
+const TOKEN: u64 = 60_000;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RateLimitError {
+    #[error("Robot {robot} exceeded its rate limit; retry in {retry_after_ms} ms")]
+    Throttled { robot: String, retry_after_ms: u64 },
+
+    #[error("Robot {robot} would exceed its storage quota of {limit} {what}")]
+    QuotaExceeded {
+        robot: String,
+        what: &'static str,
+        limit: u64,
+    },
+}
+
+impl ErrorCoded for RateLimitError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            RateLimitError::Throttled { .. } => ErrorCode::RateLimited,
+            RateLimitError::QuotaExceeded { .. } => ErrorCode::QuotaExceeded,
+        }
+    }
+}
+
+/// What a robot stores at the gateway, as its storage reports it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageUsage {
+    pub checkpoints: u64,
+    pub bytes: u64,
+}
+
+/// Admission counts of one robot, for metrics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LimiterStats {
+    pub admitted: u64,
+    pub throttled: u64,
+    pub over_quota: u64,
+}
+
+struct Bucket {
+    limit: RateLimit,
+    /// Tokens left, in [`TOKEN`] units
+    units: u64,
+    refilled_at: DateTime<Utc>,
+}
+
+impl Bucket {
+    fn full(limit: RateLimit, now: DateTime<Utc>) -> Self {
+        Self {
+            limit,
+            units: u64::from(limit.burst) * TOKEN,
+            refilled_at: now,
+        }
+    }
+
+    fn refill(&mut self, limit: RateLimit, now: DateTime<Utc>) {
+        let elapsed = (now - self.refilled_at).num_milliseconds().max(0) as u64;
+        let capacity = u64::from(limit.burst) * TOKEN;
+        self.units = self
+            .units
+            .saturating_add(elapsed.saturating_mul(u64::from(limit.per_minute)))
+            .min(capacity);
+        self.limit = limit;
+        // A clock stepping back does not refill the bucket twice
+        self.refilled_at = self.refilled_at.max(now);
+    }
+}
+
+/// Token buckets and quota checks, one per robot.
+pub struct RateLimiter {
+    live: Arc<LiveConfig>,
+    clock: Arc<dyn Clock>,
+    buckets: Mutex<HashMap<RobotId, Bucket>>,
+    stats: Mutex<HashMap<RobotId, LimiterStats>>,
+}
+
+impl RateLimiter {
+    /// Limit robots as the config in `live` says.
+    pub fn new(live: Arc<LiveConfig>) -> Self {
+        Self {
+            live,
+            clock: system_clock(),
+            buckets: Mutex::new(HashMap::new()),
+            stats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Refill buckets by `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Take a token for one request from `robot_id`.
+    ///
+    /// Fails with [`RateLimitError::Throttled`], and the time until a token
+    /// is available, while the robot's bucket is empty.
+    pub fn admit(&self, robot_id: &RobotId) -> Result<(), RateLimitError> {
+        let outcome = self.take(&self.live.snapshot(), robot_id);
+        self.count(robot_id, |stats| match outcome {
+            Ok(()) => stats.admitted += 1,
+            Err(_) => stats.throttled += 1,
+        });
+        outcome
+    }
+
+    /// Check that storing `incoming_bytes` more for `robot_id`, in one more
+    /// checkpoint, keeps it within its quota.
+    pub fn check_quota(
+        &self,
+        robot_id: &RobotId,
+        usage: StorageUsage,
+        incoming_bytes: u64,
+    ) -> Result<(), RateLimitError> {
+        let Some(quota) = self.live.snapshot().quota_for(robot_id) else {
+            return Ok(());
+        };
+        let outcome = check_quota(robot_id, quota, usage, incoming_bytes);
+        if outcome.is_err() {
+            self.count(robot_id, |stats| stats.over_quota += 1);
+        }
+        outcome
+    }
+
+    /// Admission counts of `robot_id` so far.
+    pub fn stats(&self, robot_id: &RobotId) -> LimiterStats {
+        lock(&self.stats).get(robot_id).copied().unwrap_or_default()
+    }
+
+    fn take(&self, config: &GatewayConfig, robot_id: &RobotId) -> Result<(), RateLimitError> {
+        let mut buckets = lock(&self.buckets);
+        let Some(limit) = config.rate_limit_for(robot_id) else {
+            buckets.remove(robot_id);
+            return Ok(());
+        };
+        let now = self.clock.now();
+        let bucket = buckets
+            .entry(robot_id.clone())
+            .or_insert_with(|| Bucket::full(limit, now));
+        bucket.refill(limit, now);
+        if bucket.units < TOKEN {
+            let missing = TOKEN - bucket.units;
+            return Err(RateLimitError::Throttled {
+                robot: robot_id.0.clone(),
+                retry_after_ms: missing.div_ceil(u64::from(bucket.limit.per_minute)),
+            });
+        }
+        bucket.units -= TOKEN;
+        Ok(())
+    }
+
+    fn count(&self, robot_id: &RobotId, update: impl FnOnce(&mut LimiterStats)) {
+        update(lock(&self.stats).entry(robot_id.clone()).or_default());
+    }
+}
+
+fn check_quota(
+    robot_id: &RobotId,
+    quota: Quota,
+    usage: StorageUsage,
+    incoming_bytes: u64,
+) -> Result<(), RateLimitError> {
+    let exceeded = |what, limit| RateLimitError::QuotaExceeded {
+        robot: robot_id.0.clone(),
+        what,
+        limit,
+    };
+    if usage.checkpoints.saturating_add(1) > quota.max_checkpoints {
+        return Err(exceeded("checkpoints", quota.max_checkpoints));
+    }
+    if usage.bytes.saturating_add(incoming_bytes) > quota.max_bytes {
+        return Err(exceeded("bytes", quota.max_bytes));
+    }
+    Ok(())
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::TrustRequirements;
+    use crate::clock::MockClock;
+    use crate::reload::{AuthConfig, Tenant, TrustAnchors};
+    use chrono::Duration;
+
+    fn config(revision: u64, per_minute: u32) -> GatewayConfig {
+        GatewayConfig {
+            revision,
+            trust: TrustRequirements::default(),
+            trust_anchors: TrustAnchors::default(),
+            rate_limit: Some(RateLimit {
+                per_minute,
+                burst: 2,
+            }),
+            quota: Some(Quota {
+                max_checkpoints: 10,
+                max_bytes: 1_000,
+            }),
+            tenants: vec![Tenant {
+                id: "lab".to_string(),
+                robots: vec![RobotId("R-LAB".to_string())],
+                signing_keys: Vec::new(),
+                rate_limit: None,
+                quota: None,
+                trust: None,
+            }],
+            auth: AuthConfig::default(),
+        }
+    }
+
+    fn start() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-01-15T00:00:00Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    #[test]
+    fn test_bucket_allows_burst_then_refills() {
+        let live = Arc::new(LiveConfig::new(config(1, 60)).unwrap());
+        let clock = MockClock::new(start());
+        let limiter = RateLimiter::new(live).with_clock(Arc::new(clock.clone()));
+        let robot = RobotId("R-001".to_string());
+
+        limiter.admit(&robot).unwrap();
+        limiter.admit(&robot).unwrap();
+        let err = limiter.admit(&robot).unwrap_err();
+        assert_eq!(
+            err,
+            RateLimitError::Throttled {
+                robot: "R-001".to_string(),
+                retry_after_ms: 1_000
+            }
+        );
+        assert_eq!(err.code(), ErrorCode::RateLimited);
+
+        // One token a second; another robot has its own bucket
+        limiter.admit(&RobotId("R-002".to_string())).unwrap();
+        clock.advance(Duration::milliseconds(999));
+        assert!(limiter.admit(&robot).is_err());
+        clock.advance(Duration::milliseconds(1));
+        limiter.admit(&robot).unwrap();
+
+        // Idle time refills no more than the burst
+        clock.advance(Duration::hours(1));
+        limiter.admit(&robot).unwrap();
+        limiter.admit(&robot).unwrap();
+        assert!(limiter.admit(&robot).is_err());
+        assert_eq!(
+            limiter.stats(&robot),
+            LimiterStats {
+                admitted: 5,
+                throttled: 3,
+                over_quota: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_limits_follow_reloads() {
+        let live = Arc::new(LiveConfig::new(config(1, 60)).unwrap());
+        let clock = MockClock::new(start());
+        let limiter = RateLimiter::new(live.clone()).with_clock(Arc::new(clock.clone()));
+        let robot = RobotId("R-001".to_string());
+        limiter.admit(&robot).unwrap();
+        limiter.admit(&robot).unwrap();
+        assert!(limiter.admit(&robot).is_err());
+
+        // A faster refill applies from the next request
+        live.swap(config(2, 6_000)).unwrap();
+        clock.advance(Duration::milliseconds(10));
+        limiter.admit(&robot).unwrap();
+
+        // Without a gateway-wide limit, robots are not limited
+        let mut unlimited = config(3, 60);
+        unlimited.rate_limit = None;
+        live.swap(unlimited).unwrap();
+        for _ in 0..10 {
+            limiter.admit(&robot).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_quota_enforced_per_robot() {
+        let mut gateway = config(1, 60);
+        gateway.tenants[0].quota = Some(Quota {
+            max_checkpoints: 1_000,
+            max_bytes: 1_000_000,
+        });
+        let limiter = RateLimiter::new(Arc::new(LiveConfig::new(gateway).unwrap()));
+        let robot = RobotId("R-001".to_string());
+
+        let usage = StorageUsage {
+            checkpoints: 9,
+            bytes: 900,
+        };
+        limiter.check_quota(&robot, usage, 100).unwrap();
+        let err = limiter.check_quota(&robot, usage, 101).unwrap_err();
+        assert!(matches!(
+            err,
+            RateLimitError::QuotaExceeded {
+                what: "bytes",
+                limit: 1_000,
+                ..
+            }
+        ));
+        assert_eq!(err.code(), ErrorCode::QuotaExceeded);
+        let full = StorageUsage {
+            checkpoints: 10,
+            bytes: 0,
+        };
+        assert!(matches!(
+            limiter.check_quota(&robot, full, 1),
+            Err(RateLimitError::QuotaExceeded {
+                what: "checkpoints",
+                ..
+            })
+        ));
+        assert_eq!(limiter.stats(&robot).over_quota, 2);
+
+        // A tenant's own quota replaces the gateway-wide one
+        limiter
+            .check_quota(&RobotId("R-LAB".to_string()), full, 10_000)
+            .unwrap();
+    }
+}
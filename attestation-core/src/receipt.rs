@@ -0,0 +1,253 @@
+//! Signed verifier receipts for attestation results.
+//!
+//! A verifier that has checked a quote can sign a compact receipt binding the
+//! hash of its `AttestationResult` to its own key, the policy it applied and
+//! the time. Relying parties check the receipt instead of re-running DCAP.
+//!
+//! Since version 2 the bound hash is [`AttestationResult::compute_hash`], so
+//! a receipt references the stored form of the result. Version 1 receipts
+//! hashed the result's plain serde encoding and still verify.
+
+use crate::crypto::{ct_eq, key_id, sha256, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{AttestationResult, Hash256, KeyId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Receipt version (for schema evolution)
+pub const RECEIPT_VERSION: u8 = 2;
+
+/// A verifier's signed statement about an attestation result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttestationReceipt {
+    /// Schema version
+    pub version: u8,
+    /// Hash of the attestation result ([`AttestationResult::compute_hash`])
+    pub result_hash: Hash256,
+    /// Fingerprint of the verifier's signing key
+    pub verifier_key_id: KeyId,
+    /// Identifier of the policy the verifier applied
+    pub policy_id: String,
+    /// When the receipt was issued
+    #[serde(with = "crate::serialization::timestamp")]
+    pub issued_at: DateTime<Utc>,
+    /// Ed25519 signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned receipt (for signature computation)
+#[derive(Serialize)]
+struct UnsignedReceipt<'a> {
+    version: u8,
+    result_hash: Hash256,
+    verifier_key_id: KeyId,
+    policy_id: &'a str,
+    #[serde(with = "crate::serialization::timestamp")]
+    issued_at: DateTime<Utc>,
+}
+
+impl AttestationReceipt {
+    /// Sign a receipt for `result` under `policy_id`.
+    pub fn issue(
+        result: &AttestationResult,
+        policy_id: impl Into<String>,
+        signer: &Signer,
+    ) -> Result<Self, ReceiptError> {
+        let mut receipt = Self::unsigned(result, policy_id.into(), signer.key_id())?;
+        let signature = signer.sign(&receipt.signing_payload()?);
+        receipt.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(receipt)
+    }
+
+    /// A receipt with every field but the signature filled in.
+    pub(crate) fn unsigned(
+        result: &AttestationResult,
+        policy_id: String,
+        verifier_key_id: KeyId,
+    ) -> Result<Self, SerializationError> {
+        Ok(Self {
+            version: RECEIPT_VERSION,
+            result_hash: result.compute_hash()?,
+            verifier_key_id,
+            policy_id,
+            issued_at: Utc::now(),
+            signature: SignatureBytes([0u8; 64]),
+        })
+    }
+
+    /// Verify the receipt signature against `verifying_key`.
+    pub fn verify_signature(
+        &self,
+        verifying_key: &ed25519_dalek::VerifyingKey,
+    ) -> Result<(), ReceiptError> {
+        use ed25519_dalek::Verifier;
+
+        if !ct_eq(&key_id(verifying_key).0, &self.verifier_key_id.0) {
+            return Err(ReceiptError::UnknownVerifier(self.verifier_key_id));
+        }
+
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        verifying_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| ReceiptError::InvalidSignature)
+    }
+
+    /// Verify that this receipt covers `result` and was signed by a known verifier.
+    pub fn verify(
+        &self,
+        result: &AttestationResult,
+        verifiers: &dyn KeyResolver,
+    ) -> Result<(), ReceiptError> {
+        if !ct_eq(&result_hash(self.version, result)?, &self.result_hash) {
+            return Err(ReceiptError::ResultMismatch);
+        }
+
+        let verifying_key = verifiers
+            .resolve(&self.verifier_key_id)
+            .ok_or(ReceiptError::UnknownVerifier(self.verifier_key_id))?;
+        self.verify_signature(&verifying_key)
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    pub(crate) fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedReceipt {
+            version: self.version,
+            result_hash: self.result_hash,
+            verifier_key_id: self.verifier_key_id,
+            policy_id: &self.policy_id,
+            issued_at: self.issued_at,
+        })
+    }
+}
+
+/// Hash an attestation result as a receipt of `version` binds it.
+fn result_hash(version: u8, result: &AttestationResult) -> Result<Hash256, SerializationError> {
+    match version {
+        1 => Ok(sha256(&to_canonical_cbor(result)?)),
+        _ => result.compute_hash(),
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ReceiptError {
+    #[error("Receipt serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Receipt does not cover this attestation result")]
+    ResultMismatch,
+
+    #[error("Unknown verifier key {0}")]
+    UnknownVerifier(KeyId),
+
+    #[error("Invalid receipt signature")]
+    InvalidSignature,
+}
+
+impl ErrorCoded for ReceiptError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ReceiptError::Serialization(e) => e.code(),
+            ReceiptError::ResultMismatch => ErrorCode::ReceiptResultMismatch,
+            ReceiptError::UnknownVerifier(_) => ErrorCode::ReceiptUnknownVerifier,
+            ReceiptError::InvalidSignature => ErrorCode::ReceiptInvalidSignature,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyRing;
+    use crate::types::{RevocationCheck, RevocationSource};
+
+    fn result() -> AttestationResult {
+        AttestationResult {
+            vendor: "intel-sgx".to_string(),
+            enclave_measurement: vec![1u8; 32],
+            quote_verified: true,
+            verified_at: Utc::now(),
+            revoke_check: RevocationCheck::ok(RevocationSource::Crl),
+            raw_quote: None,
+            pck_chain: None,
+            claims: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_issue_and_verify() {
+        let verifier = Signer::generate();
+        let result = result();
+        let receipt = AttestationReceipt::issue(&result, "fleet-default-v1", &verifier).unwrap();
+
+        let mut verifiers = KeyRing::new();
+        verifiers.insert(verifier.verifying_key());
+        assert!(receipt.verify(&result, &verifiers).is_ok());
+
+        let decoded = AttestationReceipt::from_bytes(&receipt.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, receipt);
+    }
+
+    #[test]
+    fn test_version_1_receipt_still_verifies() {
+        let verifier = Signer::generate();
+        let result = result();
+        let mut receipt =
+            AttestationReceipt::unsigned(&result, "p".to_string(), verifier.key_id()).unwrap();
+        receipt.version = 1;
+        receipt.result_hash = sha256(&to_canonical_cbor(&result).unwrap());
+        receipt.signature = SignatureBytes::from(
+            verifier
+                .sign(&receipt.signing_payload().unwrap())
+                .to_bytes(),
+        );
+        receipt.verify(&result, &verifier.verifying_key()).unwrap();
+
+        // The current version binds the stored form of the result
+        let current = AttestationReceipt::issue(&result, "p", &verifier).unwrap();
+        assert_eq!(current.version, RECEIPT_VERSION);
+        assert_eq!(current.result_hash, result.compute_hash().unwrap());
+    }
+
+    #[test]
+    fn test_receipt_rejects_other_result() {
+        let verifier = Signer::generate();
+        let receipt = AttestationReceipt::issue(&result(), "p", &verifier).unwrap();
+
+        let mut other = result();
+        other.quote_verified = false;
+        assert!(matches!(
+            receipt.verify(&other, &verifier.verifying_key()),
+            Err(ReceiptError::ResultMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_receipt_rejects_tampering_and_unknown_verifier() {
+        let verifier = Signer::generate();
+        let result = result();
+        let mut receipt = AttestationReceipt::issue(&result, "p", &verifier).unwrap();
+
+        assert!(matches!(
+            receipt.verify(&result, &Signer::generate().verifying_key()),
+            Err(ReceiptError::UnknownVerifier(_))
+        ));
+
+        receipt.policy_id = "permissive".to_string();
+        let err = receipt
+            .verify(&result, &verifier.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ReceiptInvalidSignature);
+    }
+}
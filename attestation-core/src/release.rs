@@ -0,0 +1,577 @@
+//! Attestation-gated secret release.
+//!
+//! Model decryption keys and similar secrets should only reach an enclave
+//! running approved code on a robot whose chain is intact. A
+//! [`KeyReleaseServer`] hands a secret out against a [`KeyReleaseRequest`]
+//! carrying:
+//!
+//! - a quote, verified through the [`AttestationRegistry`] (nonce, freshness
+//!   and revocation rules included);
+//! - the robot's latest checkpoint, signed by a key of the fleet and no older
+//!   than the server allows, whose enclave measurement must be the attested
+//!   one;
+//! - an X25519 public key generated inside the enclave, bound to the quote
+//!   by the report data claim: its first 32 bytes must be
+//!   [`report_data_binding`] of the nonce and the key.
+//!
+//! The secret is released only to measurements configured for it, wrapped
+//! to the enclave key ([`WrappedSecret`]): ephemeral X25519, HKDF-SHA256
+//! and ChaCha20-Poly1305, so only the attested enclave can unwrap it.
+
+use crate::attestation::{AttestationError, AttestationRegistry};
+use crate::checkpoint::{Checkpoint, SignatureError};
+use crate::clock::{system_clock, Clock};
+use crate::crypto::sha256;
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{ClaimValue, Hash256, RevocationStatus};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use chrono::Duration;
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use thiserror::Error;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+/// Wrapped secret version (for schema evolution)
+pub const WRAPPED_SECRET_VERSION: u8 = 1;
+
+/// HKDF info string of the wrapping key
+const WRAP_INFO: &[u8] = b"veribot/key-release/v1";
+
+/// Report data an enclave must put in its quote to bind `public_key` to `nonce`.
+pub fn report_data_binding(nonce: &[u8], public_key: &[u8; 32]) -> Hash256 {
+    let mut data = Vec::with_capacity(nonce.len() + 32);
+    data.extend_from_slice(nonce);
+    data.extend_from_slice(public_key);
+    sha256(&data)
+}
+
+/// Evidence presented for a secret.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyReleaseRequest {
+    pub vendor: String,
+    pub quote: Vec<u8>,
+    /// Nonce issued by the server's nonce manager
+    pub nonce: Vec<u8>,
+    /// The robot's latest checkpoint
+    pub checkpoint: Checkpoint,
+    /// X25519 public key generated inside the enclave
+    pub enclave_public_key: [u8; 32],
+}
+
+/// A secret encrypted to an enclave's X25519 key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WrappedSecret {
+    /// Schema version
+    pub version: u8,
+    pub secret_id: String,
+    /// Measurement the secret was released to
+    pub enclave_measurement: Vec<u8>,
+    /// Sender's ephemeral X25519 public key
+    pub ephemeral_public_key: [u8; 32],
+    /// ChaCha20-Poly1305 ciphertext; all fields above are associated data
+    pub ciphertext: Vec<u8>,
+}
+
+/// Associated data of a wrapped secret (everything but the ciphertext)
+#[derive(Serialize)]
+struct WrappedSecretHeader<'a> {
+    version: u8,
+    secret_id: &'a str,
+    enclave_measurement: &'a [u8],
+    ephemeral_public_key: &'a [u8; 32],
+}
+
+impl WrappedSecret {
+    /// Encrypt `secret` to `recipient`.
+    pub fn wrap(
+        secret_id: impl Into<String>,
+        secret: &[u8],
+        enclave_measurement: Vec<u8>,
+        recipient: &[u8; 32],
+    ) -> Result<Self, KeyReleaseError> {
+        let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+        let ephemeral_public_key = PublicKey::from(&ephemeral).to_bytes();
+        let shared = ephemeral.diffie_hellman(&PublicKey::from(*recipient));
+        let mut wrapped = Self {
+            version: WRAPPED_SECRET_VERSION,
+            secret_id: secret_id.into(),
+            enclave_measurement,
+            ephemeral_public_key,
+            ciphertext: Vec::new(),
+        };
+        let cipher = wrapped.cipher(shared.as_bytes(), recipient);
+        let aad = wrapped.header()?;
+        wrapped.ciphertext = cipher
+            .encrypt(
+                &Default::default(),
+                Payload {
+                    msg: secret,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| KeyReleaseError::Unwrap)?;
+        Ok(wrapped)
+    }
+
+    /// Decrypt with the enclave's X25519 secret.
+    pub fn unwrap_with(&self, enclave_secret: &StaticSecret) -> Result<Vec<u8>, KeyReleaseError> {
+        let recipient = PublicKey::from(enclave_secret).to_bytes();
+        let shared = enclave_secret.diffie_hellman(&PublicKey::from(self.ephemeral_public_key));
+        let cipher = self.cipher(shared.as_bytes(), &recipient);
+        let aad = self.header()?;
+        cipher
+            .decrypt(
+                &Default::default(),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| KeyReleaseError::Unwrap)
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    /// Wrapping key: HKDF over the shared secret, salted with both public keys.
+    /// Each key encrypts one message, so the all-zero AEAD nonce is safe.
+    fn cipher(&self, shared: &[u8; 32], recipient: &[u8; 32]) -> ChaCha20Poly1305 {
+        let mut salt = [0u8; 64];
+        salt[..32].copy_from_slice(&self.ephemeral_public_key);
+        salt[32..].copy_from_slice(recipient);
+        let mut key = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(&salt), shared)
+            .expand(WRAP_INFO, &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        ChaCha20Poly1305::new(&key.into())
+    }
+
+    fn header(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&WrappedSecretHeader {
+            version: self.version,
+            secret_id: &self.secret_id,
+            enclave_measurement: &self.enclave_measurement,
+            ephemeral_public_key: &self.ephemeral_public_key,
+        })
+    }
+}
+
+/// A secret and the measurements it may be released to.
+struct ReleasableSecret {
+    secret: Vec<u8>,
+    measurements: Vec<Vec<u8>>,
+}
+
+/// Releases secrets to attested enclaves.
+pub struct KeyReleaseServer {
+    registry: Arc<AttestationRegistry>,
+    /// Fleet keys the checkpoint head must be signed with
+    robots: Box<dyn KeyResolver>,
+    secrets: HashMap<String, ReleasableSecret>,
+    binding_claim: String,
+    max_checkpoint_age: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl KeyReleaseServer {
+    /// Default claim carrying the report data
+    pub const DEFAULT_BINDING_CLAIM: &'static str = "sgx.report_data";
+
+    /// Verify quotes with `registry` and checkpoints with `robots`.
+    pub fn new(registry: Arc<AttestationRegistry>, robots: Box<dyn KeyResolver>) -> Self {
+        Self {
+            registry,
+            robots,
+            secrets: HashMap::new(),
+            binding_claim: Self::DEFAULT_BINDING_CLAIM.to_string(),
+            max_checkpoint_age: Duration::minutes(10),
+            clock: system_clock(),
+        }
+    }
+
+    /// Release `secret` under `secret_id` to any of `measurements`.
+    pub fn with_secret(
+        mut self,
+        secret_id: impl Into<String>,
+        secret: Vec<u8>,
+        measurements: Vec<Vec<u8>>,
+    ) -> Self {
+        self.secrets.insert(
+            secret_id.into(),
+            ReleasableSecret {
+                secret,
+                measurements,
+            },
+        );
+        self
+    }
+
+    /// Read the report data from `claim` (e.g., "tdx.report_data").
+    pub fn with_binding_claim(mut self, claim: impl Into<String>) -> Self {
+        self.binding_claim = claim.into();
+        self
+    }
+
+    /// Accept checkpoint heads up to `max_age` old.
+    pub fn with_max_checkpoint_age(mut self, max_age: Duration) -> Self {
+        self.max_checkpoint_age = max_age;
+        self
+    }
+
+    /// Judge checkpoint age with `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Verify `request` and wrap the secret `secret_id` to its enclave key.
+    pub async fn release(
+        &self,
+        secret_id: &str,
+        request: &KeyReleaseRequest,
+    ) -> Result<WrappedSecret, KeyReleaseError> {
+        let releasable = self
+            .secrets
+            .get(secret_id)
+            .ok_or_else(|| KeyReleaseError::UnknownSecret(secret_id.to_string()))?;
+
+        let checkpoint = &request.checkpoint;
+        checkpoint.verify_with_resolver(self.robots.as_ref())?;
+        if self.clock.now() - checkpoint.local_timestamp_utc > self.max_checkpoint_age {
+            return Err(KeyReleaseError::StaleCheckpoint {
+                sequence: checkpoint.sequence,
+            });
+        }
+
+        let result = self
+            .registry
+            .verify_quote(&request.vendor, &request.quote, Some(&request.nonce))
+            .await?;
+        if !result.quote_verified {
+            return Err(
+                AttestationError::VerificationFailed("quote not verified".to_string()).into(),
+            );
+        }
+        match result.revoke_check.status {
+            RevocationStatus::Ok => {}
+            RevocationStatus::Revoked => return Err(AttestationError::MeasurementRevoked.into()),
+            RevocationStatus::Unknown => {
+                return Err(AttestationError::RevocationCheckFailed(
+                    "revocation status unknown".to_string(),
+                )
+                .into())
+            }
+        }
+
+        let measurement = &result.enclave_measurement;
+        if *measurement != checkpoint.enclave_measurement
+            || !releasable.measurements.contains(measurement)
+        {
+            return Err(KeyReleaseError::MeasurementDenied);
+        }
+
+        let binding = report_data_binding(&request.nonce, &request.enclave_public_key);
+        match result.claim(&self.binding_claim) {
+            Some(ClaimValue::Bytes(report_data)) if report_data.starts_with(&binding) => {}
+            _ => return Err(KeyReleaseError::KeyNotBound),
+        }
+
+        WrappedSecret::wrap(
+            secret_id,
+            &releasable.secret,
+            measurement.clone(),
+            &request.enclave_public_key,
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum KeyReleaseError {
+    #[error("No secret configured under {0}")]
+    UnknownSecret(String),
+
+    #[error("Attestation rejected: {0}")]
+    Attestation(#[from] AttestationError),
+
+    #[error("Checkpoint head rejected: {0}")]
+    Checkpoint(#[from] SignatureError),
+
+    #[error("Checkpoint {sequence} is too old for key release")]
+    StaleCheckpoint { sequence: u64 },
+
+    #[error("Enclave measurement may not receive the secret")]
+    MeasurementDenied,
+
+    #[error("Report data does not bind the enclave key to the nonce")]
+    KeyNotBound,
+
+    #[error("Wrapped secret could not be sealed or opened")]
+    Unwrap,
+
+    #[error("Wrapped secret serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+}
+
+impl ErrorCoded for KeyReleaseError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            KeyReleaseError::UnknownSecret(_) => ErrorCode::ReleaseSecretUnknown,
+            KeyReleaseError::Attestation(e) => e.code(),
+            KeyReleaseError::Checkpoint(e) => e.code(),
+            KeyReleaseError::StaleCheckpoint { .. } => ErrorCode::ReleaseCheckpointStale,
+            KeyReleaseError::MeasurementDenied => ErrorCode::ReleaseMeasurementDenied,
+            KeyReleaseError::KeyNotBound => ErrorCode::ReleaseKeyNotBound,
+            KeyReleaseError::Unwrap => ErrorCode::SecretUnwrapFailed,
+            KeyReleaseError::Serialization(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::AttestationAdapter;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::crypto::Signer;
+    use crate::types::{AttestationResult, RevocationCheck, RevocationReason, RevocationSource};
+    use async_trait::async_trait;
+    use chrono::Utc;
+
+    /// Adapter whose "quote" is the report data of an enclave measuring [2; 32]
+    struct ReportDataAdapter {
+        quote_verified: bool,
+        revoke_check: RevocationCheck,
+    }
+
+    impl ReportDataAdapter {
+        fn trusted() -> Self {
+            Self {
+                quote_verified: true,
+                revoke_check: RevocationCheck::ok(RevocationSource::Registry),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl AttestationAdapter for ReportDataAdapter {
+        fn vendor_name(&self) -> &str {
+            "mock"
+        }
+
+        async fn verify_quote(
+            &self,
+            quote: &[u8],
+            _nonce: Option<&[u8]>,
+        ) -> Result<AttestationResult, AttestationError> {
+            if quote.starts_with(b"forged") {
+                return Err(AttestationError::VerificationFailed(
+                    "bad quote signature".to_string(),
+                ));
+            }
+            let mut claims = std::collections::BTreeMap::new();
+            claims.insert(
+                "mock.report_data".to_string(),
+                ClaimValue::Bytes(quote.to_vec()),
+            );
+            Ok(AttestationResult {
+                vendor: "mock".to_string(),
+                enclave_measurement: vec![2u8; 32],
+                quote_verified: self.quote_verified,
+                verified_at: Utc::now(),
+                revoke_check: self.revoke_check.clone(),
+                raw_quote: None,
+                pck_chain: None,
+                claims,
+            })
+        }
+
+        async fn check_revocation(
+            &self,
+            _measurement: &[u8],
+        ) -> Result<RevocationCheck, AttestationError> {
+            Ok(RevocationCheck::ok(RevocationSource::Registry))
+        }
+
+        async fn root_ca_certs(&self) -> Vec<String> {
+            Vec::new()
+        }
+
+        async fn update_trust_anchors(&self) -> Result<(), AttestationError> {
+            Ok(())
+        }
+    }
+
+    fn checkpoint(robot: &Signer, measurement: Vec<u8>) -> Checkpoint {
+        CheckpointBuilder::test_fixture()
+            .timestamp(Utc::now())
+            .enclave_measurement(measurement)
+            .build_and_sign(robot.signing_key())
+            .unwrap()
+    }
+
+    /// Server releasing "model-key" to `measurements`, attesting with `adapter`.
+    fn release_server(
+        robot: &Signer,
+        adapter: ReportDataAdapter,
+        measurements: Vec<Vec<u8>>,
+    ) -> KeyReleaseServer {
+        let mut registry = AttestationRegistry::new();
+        registry.register(Box::new(adapter));
+        KeyReleaseServer::new(Arc::new(registry), Box::new(robot.verifying_key()))
+            .with_secret("model-key", b"model decryption key".to_vec(), measurements)
+            .with_binding_claim("mock.report_data")
+    }
+
+    /// A request from an enclave measuring [2; 32], and the enclave's secret.
+    fn release_request(robot: &Signer) -> (KeyReleaseRequest, StaticSecret) {
+        let enclave = StaticSecret::random_from_rng(OsRng);
+        let enclave_public_key = PublicKey::from(&enclave).to_bytes();
+        let nonce = b"server nonce".to_vec();
+        let request = KeyReleaseRequest {
+            vendor: "mock".to_string(),
+            quote: report_data_binding(&nonce, &enclave_public_key).to_vec(),
+            nonce,
+            checkpoint: checkpoint(robot, vec![2u8; 32]),
+            enclave_public_key,
+        };
+        (request, enclave)
+    }
+
+    #[tokio::test]
+    async fn test_secret_released_only_to_bound_enclave_key() {
+        let robot = Signer::generate();
+        let server = release_server(&robot, ReportDataAdapter::trusted(), vec![vec![2u8; 32]]);
+        let (mut request, enclave) = release_request(&robot);
+
+        let wrapped = server.release("model-key", &request).await.unwrap();
+        let wrapped = WrappedSecret::from_bytes(&wrapped.to_bytes().unwrap()).unwrap();
+        assert_eq!(
+            wrapped.unwrap_with(&enclave).unwrap(),
+            b"model decryption key"
+        );
+        let other = StaticSecret::random_from_rng(OsRng);
+        assert_eq!(
+            wrapped.unwrap_with(&other).unwrap_err().code(),
+            ErrorCode::SecretUnwrapFailed
+        );
+        let mut relabeled = wrapped.clone();
+        relabeled.secret_id = "other-key".to_string();
+        assert!(relabeled.unwrap_with(&enclave).is_err());
+
+        // A key the quote does not vouch for gets nothing
+        let mut swapped = request.clone();
+        swapped.enclave_public_key = PublicKey::from(&other).to_bytes();
+        let err = server.release("model-key", &swapped).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ReleaseKeyNotBound);
+
+        // Nor does a robot whose checkpoint reports other code
+        request.checkpoint = checkpoint(&robot, vec![9u8; 32]);
+        let err = server.release("model-key", &request).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ReleaseMeasurementDenied);
+        assert_eq!(
+            server
+                .release("unknown", &request)
+                .await
+                .unwrap_err()
+                .code(),
+            ErrorCode::ReleaseSecretUnknown
+        );
+    }
+
+    #[tokio::test]
+    async fn test_measurements_must_match_checkpoint_and_releasable_set() {
+        let robot = Signer::generate();
+        let (mut request, _) = release_request(&robot);
+
+        // The checkpoint reports other code than the quote attests, even one
+        // the secret may go to
+        let server = release_server(
+            &robot,
+            ReportDataAdapter::trusted(),
+            vec![vec![2u8; 32], vec![9u8; 32]],
+        );
+        let attested = request.checkpoint.clone();
+        request.checkpoint = checkpoint(&robot, vec![9u8; 32]);
+        let err = server.release("model-key", &request).await.unwrap_err();
+        assert!(matches!(err, KeyReleaseError::MeasurementDenied));
+
+        // The attested code matches the checkpoint but the secret is not for it
+        request.checkpoint = attested;
+        let server = release_server(&robot, ReportDataAdapter::trusted(), vec![vec![7u8; 32]]);
+        let err = server.release("model-key", &request).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ReleaseMeasurementDenied);
+    }
+
+    #[tokio::test]
+    async fn test_nothing_released_on_failed_attestation() {
+        let robot = Signer::generate();
+        let (request, _) = release_request(&robot);
+        let measurement = vec![vec![2u8; 32]];
+
+        let mut forged = request.clone();
+        forged.quote = b"forged quote".to_vec();
+        let server = release_server(&robot, ReportDataAdapter::trusted(), measurement.clone());
+        let err = server.release("model-key", &forged).await.unwrap_err();
+        assert!(matches!(
+            err,
+            KeyReleaseError::Attestation(AttestationError::VerificationFailed(_))
+        ));
+
+        let unverified = ReportDataAdapter {
+            quote_verified: false,
+            ..ReportDataAdapter::trusted()
+        };
+        let err = release_server(&robot, unverified, measurement.clone())
+            .release("model-key", &request)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::VerificationFailed);
+
+        let revoked = ReportDataAdapter {
+            revoke_check: RevocationCheck::revoked(
+                RevocationSource::Registry,
+                RevocationReason::MeasurementRevoked,
+                None,
+            ),
+            ..ReportDataAdapter::trusted()
+        };
+        let err = release_server(&robot, revoked, measurement.clone())
+            .release("model-key", &request)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::MeasurementRevoked);
+
+        let unknown = ReportDataAdapter {
+            revoke_check: RevocationCheck::unknown(RevocationSource::Registry),
+            ..ReportDataAdapter::trusted()
+        };
+        let err = release_server(&robot, unknown, measurement.clone())
+            .release("model-key", &request)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::RevocationCheckFailed);
+
+        // A checkpoint head from a key outside the fleet fails before attestation
+        let mut foreign = request.clone();
+        foreign.checkpoint = checkpoint(&Signer::generate(), vec![2u8; 32]);
+        let err = release_server(&robot, ReportDataAdapter::trusted(), measurement)
+            .release("model-key", &foreign)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, KeyReleaseError::Checkpoint(_)));
+    }
+}
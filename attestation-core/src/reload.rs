@@ -0,0 +1,760 @@
+//! Hot-reloadable gateway configuration.
+//!
+//! A gateway's policies (trust requirements), trust anchors, rate limits and
+//! storage quotas (enforced by [`crate::ratelimit`]), tenant definitions
+//! and client credentials (checked by [`crate::auth`]) live in a
+//! [`GatewayConfig`] file. The ingestion path
+//! reads them through a [`LiveConfig`]: each request takes a
+//! [`snapshot`](LiveConfig::snapshot) and keeps using it to the end, while a
+//! [`ConfigReloader`] swaps in new revisions underneath without a restart.
+//!
+//! A new file is parsed and [validated](GatewayConfig::validate) in full
+//! before the swap, and its revision must exceed the one in force; anything
+//! else is reported and the old configuration stays. Reloads are triggered
+//! two ways, and the runtime wiring is left to the host:
+//!
+//! - **SIGHUP**: the signal handler calls [`ConfigReloader::reload`], which
+//!   always re-reads the file
+//! - **File watch**: [`ConfigReloader::watch`] (or a host loop calling
+//!   [`ConfigReloader::poll`]) re-reads only when the file contents changed
+
+use crate::attestation::SleepFn;
+use crate::chain::TrustRequirements;
+use crate::crypto::{sha256, VerifyingKey};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyRing;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, RobotId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Everything a gateway can change without restarting.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GatewayConfig {
+    /// Release number; strictly increases with every new config
+    pub revision: u64,
+    /// Minimum trust mode per robot class, for robots outside any tenant
+    /// with its own requirements
+    #[serde(default)]
+    pub trust: TrustRequirements,
+    #[serde(default)]
+    pub trust_anchors: TrustAnchors,
+    /// Limit for robots whose tenant sets none (unlimited if absent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    /// Storage quota for robots whose tenant sets none (unlimited if absent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<Quota>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tenants: Vec<Tenant>,
+    /// Client certificates and API keys the gateway accepts (see
+    /// [`crate::auth`])
+    #[serde(default, skip_serializing_if = "AuthConfig::is_empty")]
+    pub auth: AuthConfig,
+}
+
+/// Keys and roots the gateway verifies evidence against.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustAnchors {
+    /// Hex Ed25519 verifying keys of robots and fleet operators
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signing_keys: Vec<String>,
+    /// PEM root certificates for TEE attestation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attestation_roots: Vec<String>,
+}
+
+/// Token bucket: `burst` requests at once, refilled at `per_minute`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub per_minute: u32,
+    pub burst: u32,
+}
+
+/// Storage a single robot may hold at the gateway.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quota {
+    pub max_checkpoints: u64,
+    /// Checkpoint and entry payload bytes
+    pub max_bytes: u64,
+}
+
+/// A customer whose robots share limits and requirements (see
+/// [`crate::tenant`] for scoping requests to one).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tenant {
+    pub id: String,
+    pub robots: Vec<RobotId>,
+    /// Hex Ed25519 verifying keys that replace the gateway-wide signing key
+    /// anchors for this tenant's robots
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signing_keys: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<Quota>,
+    /// Replaces the gateway-wide trust requirements for this tenant's robots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trust: Option<TrustRequirements>,
+}
+
+/// Credentials the gateway authenticates callers by.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clients: Vec<ClientCertificate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_keys: Vec<ApiKey>,
+}
+
+impl AuthConfig {
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty() && self.api_keys.is_empty()
+    }
+}
+
+/// A robot's mTLS client certificate, matched by fingerprint, SPIFFE ID or
+/// both.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientCertificate {
+    pub robot_id: RobotId,
+    /// Hex SHA-256 of the certificate DER
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// URI SAN such as `spiffe://fleet.example/robot/R-001`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spiffe_id: Option<String>,
+}
+
+/// An operator's API key. Only its hash is configured.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub name: String,
+    /// Hex SHA-256 of the key
+    pub key_hash: String,
+    pub role: OperatorRole,
+    /// Limits the key to one tenant's robots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+}
+
+/// What an operator may do; each role may do everything the ones before it may.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperatorRole {
+    /// Read checkpoints, audit records and events
+    Viewer,
+    /// Also review quarantined submissions
+    Reviewer,
+    /// Also administer the gateway
+    Admin,
+}
+
+impl GatewayConfig {
+    /// Check the config is internally consistent: signing keys decode,
+    /// roots are PEM certificates, rate limits and quotas admit traffic,
+    /// tenant ids are unique and non-empty, no robot belongs to two
+    /// tenants, and client certificates and API keys are well formed.
+    pub fn validate(&self) -> Result<(), ReloadError> {
+        let tenant_keys = self.tenants.iter().flat_map(|tenant| &tenant.signing_keys);
+        for key in self.trust_anchors.signing_keys.iter().chain(tenant_keys) {
+            decode_key(key)?;
+        }
+        for root in &self.trust_anchors.attestation_roots {
+            if !root.contains("-----BEGIN CERTIFICATE-----") {
+                return Err(ReloadError::Invalid(
+                    "attestation root is not a PEM certificate".to_string(),
+                ));
+            }
+        }
+        let limits = self
+            .rate_limit
+            .iter()
+            .chain(self.tenants.iter().filter_map(|t| t.rate_limit.as_ref()));
+        for limit in limits {
+            if limit.per_minute == 0 || limit.burst == 0 {
+                return Err(ReloadError::Invalid(
+                    "rate limit admits no requests".to_string(),
+                ));
+            }
+        }
+        let quotas = self
+            .quota
+            .iter()
+            .chain(self.tenants.iter().filter_map(|t| t.quota.as_ref()));
+        for quota in quotas {
+            if quota.max_checkpoints == 0 || quota.max_bytes == 0 {
+                return Err(ReloadError::Invalid(
+                    "storage quota admits no checkpoints".to_string(),
+                ));
+            }
+        }
+        let mut ids = HashSet::new();
+        let mut robots = HashSet::new();
+        for tenant in &self.tenants {
+            if tenant.id.is_empty() || !ids.insert(tenant.id.as_str()) {
+                return Err(ReloadError::Invalid(format!(
+                    "tenant id {:?} is empty or repeated",
+                    tenant.id
+                )));
+            }
+            if let Some(robot) = tenant.robots.iter().find(|robot| !robots.insert(*robot)) {
+                return Err(ReloadError::Invalid(format!(
+                    "robot {} is in more than one tenant",
+                    robot.0
+                )));
+            }
+        }
+        for client in &self.auth.clients {
+            if client.fingerprint.is_none() && client.spiffe_id.is_none() {
+                return Err(ReloadError::Invalid(format!(
+                    "client of robot {} matches no certificate",
+                    client.robot_id.0
+                )));
+            }
+            if let Some(fingerprint) = &client.fingerprint {
+                decode_hash(fingerprint)?;
+            }
+        }
+        for key in &self.auth.api_keys {
+            decode_hash(&key.key_hash)?;
+            if key
+                .tenant
+                .as_deref()
+                .is_some_and(|id| self.tenant(id).is_none())
+            {
+                return Err(ReloadError::Invalid(format!(
+                    "API key {} names an unknown tenant",
+                    key.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The signing key trust anchors as a key ring.
+    pub fn key_ring(&self) -> Result<KeyRing, ReloadError> {
+        key_ring(&self.trust_anchors.signing_keys)
+    }
+
+    /// The signing keys `robot_id`'s checkpoints are verified against: its
+    /// tenant's, if the tenant sets any, else the gateway-wide ones.
+    pub fn key_ring_for(&self, robot_id: &RobotId) -> Result<KeyRing, ReloadError> {
+        match self.tenant_of(robot_id) {
+            Some(tenant) => self.tenant_key_ring(tenant),
+            None => self.key_ring(),
+        }
+    }
+
+    /// The signing keys `tenant`'s checkpoints are verified against.
+    pub fn tenant_key_ring(&self, tenant: &Tenant) -> Result<KeyRing, ReloadError> {
+        if tenant.signing_keys.is_empty() {
+            return self.key_ring();
+        }
+        key_ring(&tenant.signing_keys)
+    }
+
+    /// The tenant with `id`, if any.
+    pub fn tenant(&self, id: &str) -> Option<&Tenant> {
+        self.tenants.iter().find(|tenant| tenant.id == id)
+    }
+
+    /// The tenant `robot_id` belongs to, if any.
+    pub fn tenant_of(&self, robot_id: &RobotId) -> Option<&Tenant> {
+        self.tenants
+            .iter()
+            .find(|tenant| tenant.robots.contains(robot_id))
+    }
+
+    /// The rate limit applied to `robot_id`, if any.
+    pub fn rate_limit_for(&self, robot_id: &RobotId) -> Option<RateLimit> {
+        self.tenant_of(robot_id)
+            .and_then(|tenant| tenant.rate_limit)
+            .or(self.rate_limit)
+    }
+
+    /// The storage quota applied to `robot_id`, if any.
+    pub fn quota_for(&self, robot_id: &RobotId) -> Option<Quota> {
+        self.tenant_of(robot_id)
+            .and_then(|tenant| tenant.quota)
+            .or(self.quota)
+    }
+
+    /// The trust requirements applied to `robot_id`.
+    pub fn trust_for(&self, robot_id: &RobotId) -> &TrustRequirements {
+        self.tenant_of(robot_id)
+            .and_then(|tenant| tenant.trust.as_ref())
+            .unwrap_or(&self.trust)
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+}
+
+fn key_ring(hex_keys: &[String]) -> Result<KeyRing, ReloadError> {
+    let mut ring = KeyRing::new();
+    for key in hex_keys {
+        ring.insert(decode_key(key)?);
+    }
+    Ok(ring)
+}
+
+/// Decode a hex SHA-256 hash from the auth section.
+pub(crate) fn decode_hash(hex_hash: &str) -> Result<Hash256, ReloadError> {
+    hex::decode(hex_hash)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ReloadError::Invalid(format!("{hex_hash:?} is not a hex SHA-256 hash")))
+}
+
+fn decode_key(hex_key: &str) -> Result<VerifyingKey, ReloadError> {
+    let invalid =
+        || ReloadError::Invalid(format!("signing key {hex_key:?} is not a hex Ed25519 key"));
+    let bytes: [u8; 32] = hex::decode(hex_key)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(invalid)?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| invalid())
+}
+
+/// Result of offering a configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReloadOutcome {
+    /// Same as the config in force; nothing swapped
+    Unchanged,
+    /// Swapped in `revision`, replacing `previous`
+    Applied { previous: u64, revision: u64 },
+}
+
+/// The configuration in force, swapped atomically on reload.
+#[derive(Debug)]
+pub struct LiveConfig {
+    current: RwLock<Arc<GatewayConfig>>,
+}
+
+impl LiveConfig {
+    /// Start with `initial`, which must validate.
+    pub fn new(initial: GatewayConfig) -> Result<Self, ReloadError> {
+        initial.validate()?;
+        Ok(Self {
+            current: RwLock::new(Arc::new(initial)),
+        })
+    }
+
+    /// The config in force. A reload does not affect snapshots already taken.
+    pub fn snapshot(&self) -> Arc<GatewayConfig> {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Validate `next` and put it in force if it supersedes the current one.
+    pub fn swap(&self, next: GatewayConfig) -> Result<ReloadOutcome, ReloadError> {
+        next.validate()?;
+        let mut current = self.current.write().unwrap_or_else(|e| e.into_inner());
+        if **current == next {
+            return Ok(ReloadOutcome::Unchanged);
+        }
+        if next.revision <= current.revision {
+            return Err(ReloadError::Rollback {
+                active: current.revision,
+                offered: next.revision,
+            });
+        }
+        let outcome = ReloadOutcome::Applied {
+            previous: current.revision,
+            revision: next.revision,
+        };
+        *current = Arc::new(next);
+        Ok(outcome)
+    }
+}
+
+/// Parses a config file's bytes (CBOR, TOML, JSON, ... as the host likes).
+pub type ConfigParser = Box<dyn Fn(&[u8]) -> Result<GatewayConfig, String> + Send + Sync>;
+
+/// Reloads a [`LiveConfig`] from a file.
+pub struct ConfigReloader {
+    path: PathBuf,
+    parse: ConfigParser,
+    live: Arc<LiveConfig>,
+    /// Digest of the file contents last read, applied or not
+    seen: Mutex<Hash256>,
+}
+
+impl ConfigReloader {
+    /// Load the initial config from `path`, failing if it does not validate.
+    pub fn open(path: impl Into<PathBuf>, parse: ConfigParser) -> Result<Self, ReloadError> {
+        let path = path.into();
+        let bytes = read(&path)?;
+        let config = parse(&bytes).map_err(ReloadError::Parse)?;
+        Ok(Self {
+            live: Arc::new(LiveConfig::new(config)?),
+            seen: Mutex::new(sha256(&bytes)),
+            path,
+            parse,
+        })
+    }
+
+    /// The config this reloader updates, for the ingestion path to read.
+    pub fn live(&self) -> Arc<LiveConfig> {
+        self.live.clone()
+    }
+
+    /// Re-read the file and swap it in (e.g., on SIGHUP).
+    ///
+    /// On any error the config in force stays.
+    pub fn reload(&self) -> Result<ReloadOutcome, ReloadError> {
+        let bytes = read(&self.path)?;
+        *self.seen.lock().unwrap_or_else(|e| e.into_inner()) = sha256(&bytes);
+        self.live
+            .swap((self.parse)(&bytes).map_err(ReloadError::Parse)?)
+    }
+
+    /// Reload only if the file contents changed since last read.
+    ///
+    /// A broken file is reported once, not on every poll; fixing it (or a
+    /// [`reload`](Self::reload)) tries again.
+    pub fn poll(&self) -> Result<ReloadOutcome, ReloadError> {
+        let bytes = read(&self.path)?;
+        let digest = sha256(&bytes);
+        {
+            let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+            if *seen == digest {
+                return Ok(ReloadOutcome::Unchanged);
+            }
+            *seen = digest;
+        }
+        self.live
+            .swap((self.parse)(&bytes).map_err(ReloadError::Parse)?)
+    }
+
+    /// Poll the file every `interval`, sleeping with `sleep`, and pass each
+    /// swap or failure to `on_reload`. Runs until the future is dropped.
+    pub async fn watch(
+        &self,
+        interval: Duration,
+        sleep: &SleepFn,
+        mut on_reload: impl FnMut(Result<ReloadOutcome, ReloadError>),
+    ) {
+        loop {
+            sleep(interval).await;
+            match self.poll() {
+                Ok(ReloadOutcome::Unchanged) => {}
+                result => on_reload(result),
+            }
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, ReloadError> {
+    std::fs::read(path).map_err(|e| ReloadError::Unreadable {
+        path: path.display().to_string(),
+        reason: e.to_string(),
+    })
+}
+
+#[derive(Debug, Error)]
+pub enum ReloadError {
+    #[error("Cannot read gateway config {path}: {reason}")]
+    Unreadable { path: String, reason: String },
+
+    #[error("Cannot parse gateway config: {0}")]
+    Parse(String),
+
+    #[error("Invalid gateway config: {0}")]
+    Invalid(String),
+
+    #[error("Gateway config revision {offered} does not supersede revision {active} in force")]
+    Rollback { active: u64, offered: u64 },
+}
+
+impl ErrorCoded for ReloadError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ReloadError::Unreadable { .. } | ReloadError::Parse(_) => ErrorCode::ConfigUnreadable,
+            ReloadError::Invalid(_) => ErrorCode::ConfigInvalid,
+            ReloadError::Rollback { .. } => ErrorCode::ConfigRollback,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Signer;
+    use crate::types::TrustMode;
+
+    fn config(revision: u64) -> GatewayConfig {
+        GatewayConfig {
+            revision,
+            trust: TrustRequirements::default(),
+            trust_anchors: TrustAnchors {
+                signing_keys: vec![hex::encode(Signer::generate().verifying_key().as_bytes())],
+                attestation_roots: Vec::new(),
+            },
+            rate_limit: Some(RateLimit {
+                per_minute: 600,
+                burst: 20,
+            }),
+            quota: None,
+            tenants: vec![Tenant {
+                id: "acme".to_string(),
+                robots: vec![RobotId("R-001".to_string())],
+                signing_keys: Vec::new(),
+                rate_limit: None,
+                quota: None,
+                trust: Some(TrustRequirements {
+                    default: Some(TrustMode::Trusted),
+                    classes: Default::default(),
+                }),
+            }],
+            auth: AuthConfig::default(),
+        }
+    }
+
+    fn parser() -> ConfigParser {
+        Box::new(|bytes| GatewayConfig::from_bytes(bytes).map_err(|e| e.to_string()))
+    }
+
+    #[test]
+    fn test_reload_swaps_only_valid_newer_configs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gateway.cbor");
+        std::fs::write(&path, config(1).to_bytes().unwrap()).unwrap();
+        let reloader = ConfigReloader::open(&path, parser()).unwrap();
+        let live = reloader.live();
+        let held = live.snapshot();
+        let robot = RobotId("R-001".to_string());
+        assert_eq!(held.trust_for(&robot).default, Some(TrustMode::Trusted));
+        assert_eq!(held.rate_limit_for(&robot).unwrap().burst, 20);
+        assert_eq!(reloader.poll().unwrap(), ReloadOutcome::Unchanged);
+
+        // A broken file is reported once and the old config stays in force
+        let mut invalid = config(2);
+        invalid.tenants.push(invalid.tenants[0].clone());
+        std::fs::write(&path, invalid.to_bytes().unwrap()).unwrap();
+        assert_eq!(
+            reloader.poll().unwrap_err().code(),
+            ErrorCode::ConfigInvalid
+        );
+        assert_eq!(reloader.poll().unwrap(), ReloadOutcome::Unchanged);
+        std::fs::write(&path, b"not cbor").unwrap();
+        assert_eq!(
+            reloader.reload().unwrap_err().code(),
+            ErrorCode::ConfigUnreadable
+        );
+        std::fs::write(&path, config(1).to_bytes().unwrap()).unwrap();
+        assert_eq!(
+            reloader.poll().unwrap_err().code(),
+            ErrorCode::ConfigRollback
+        );
+        assert_eq!(live.snapshot().revision, 1);
+
+        std::fs::write(&path, config(2).to_bytes().unwrap()).unwrap();
+        assert_eq!(
+            reloader.poll().unwrap(),
+            ReloadOutcome::Applied {
+                previous: 1,
+                revision: 2
+            }
+        );
+        assert_eq!(live.snapshot().revision, 2);
+        assert_eq!(live.snapshot().key_ring().unwrap().len(), 1);
+        // Requests already in flight keep the config they started with
+        assert_eq!(held.revision, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_watch_reports_each_change_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gateway.cbor");
+        std::fs::write(&path, config(1).to_bytes().unwrap()).unwrap();
+        let reloader = ConfigReloader::open(&path, parser()).unwrap();
+
+        // Each tick the file is rewritten with the next version, then the watch polls it
+        let versions = [
+            config(2).to_bytes().unwrap(),
+            b"not cbor".to_vec(),
+            b"not cbor".to_vec(),
+            config(1).to_bytes().unwrap(),
+            config(3).to_bytes().unwrap(),
+        ];
+        let script = Arc::new(Mutex::new(
+            versions
+                .into_iter()
+                .collect::<std::collections::VecDeque<_>>(),
+        ));
+        let ticks = Arc::new(Mutex::new(Vec::new()));
+        let (file, recorder) = (path.clone(), ticks.clone());
+        let sleep: SleepFn = Arc::new(move |interval| {
+            recorder.lock().unwrap().push(interval);
+            match script.lock().unwrap().pop_front() {
+                Some(bytes) => {
+                    std::fs::write(&file, bytes).unwrap();
+                    Box::pin(async {})
+                }
+                None => Box::pin(std::future::pending()),
+            }
+        });
+        let mut outcomes = Vec::new();
+        let watch = reloader.watch(Duration::from_secs(5), &sleep, |result| {
+            outcomes.push(result.map_err(|e| e.code()))
+        });
+        assert!(tokio::time::timeout(Duration::from_secs(60), watch)
+            .await
+            .is_err());
+
+        assert_eq!(
+            outcomes,
+            [
+                Ok(ReloadOutcome::Applied {
+                    previous: 1,
+                    revision: 2
+                }),
+                Err(ErrorCode::ConfigUnreadable),
+                Err(ErrorCode::ConfigRollback),
+                Ok(ReloadOutcome::Applied {
+                    previous: 2,
+                    revision: 3
+                }),
+            ]
+        );
+        assert_eq!(ticks.lock().unwrap().len(), 6);
+        assert!(ticks
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|tick| *tick == Duration::from_secs(5)));
+        assert_eq!(reloader.live().snapshot().revision, 3);
+    }
+
+    #[test]
+    fn test_swap_rejects_revision_conflicts() {
+        let initial = config(2);
+        let live = LiveConfig::new(initial.clone()).unwrap();
+        assert_eq!(
+            live.swap(initial.clone()).unwrap(),
+            ReloadOutcome::Unchanged
+        );
+
+        // Different contents under the revision in force, or an older one
+        let mut conflicting = initial.clone();
+        conflicting.rate_limit = None;
+        let err = live.swap(conflicting).unwrap_err();
+        assert!(matches!(
+            err,
+            ReloadError::Rollback {
+                active: 2,
+                offered: 2
+            }
+        ));
+        assert_eq!(err.code(), ErrorCode::ConfigRollback);
+        assert!(matches!(
+            live.swap(config(1)),
+            Err(ReloadError::Rollback {
+                active: 2,
+                offered: 1
+            })
+        ));
+        assert_eq!(*live.snapshot(), initial);
+    }
+
+    #[test]
+    fn test_invalid_configs_leave_the_snapshot_in_force() {
+        let live = LiveConfig::new(config(1)).unwrap();
+        let before = live.snapshot();
+        let invalid: [fn(&mut GatewayConfig); 6] = [
+            |c| c.trust_anchors.signing_keys.push("not hex".to_string()),
+            |c| {
+                c.trust_anchors
+                    .attestation_roots
+                    .push("not a certificate".to_string())
+            },
+            |c| {
+                c.rate_limit = Some(RateLimit {
+                    per_minute: 0,
+                    burst: 1,
+                })
+            },
+            |c| c.tenants[0].id.clear(),
+            |c| {
+                let mut other = c.tenants[0].clone();
+                other.id = "globex".to_string();
+                c.tenants.push(other);
+            },
+            |c| {
+                c.auth.api_keys.push(ApiKey {
+                    name: "ops".to_string(),
+                    key_hash: hex::encode([0u8; 32]),
+                    role: OperatorRole::Admin,
+                    tenant: Some("initech".to_string()),
+                })
+            },
+        ];
+        for break_config in invalid {
+            let mut next = config(2);
+            break_config(&mut next);
+            assert_eq!(
+                live.swap(next).unwrap_err().code(),
+                ErrorCode::ConfigInvalid
+            );
+            assert!(Arc::ptr_eq(&live.snapshot(), &before));
+        }
+        assert!(LiveConfig::new(GatewayConfig {
+            quota: Some(Quota {
+                max_checkpoints: 0,
+                max_bytes: 1
+            }),
+            ..config(1)
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_readers_see_whole_configs_during_swaps() {
+        let versioned = |revision: u64| GatewayConfig {
+            rate_limit: Some(RateLimit {
+                per_minute: 600,
+                burst: revision as u32,
+            }),
+            ..config(revision)
+        };
+        let live = LiveConfig::new(versioned(1)).unwrap();
+        let done = std::sync::atomic::AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let mut last = 0;
+                    while !done.load(std::sync::atomic::Ordering::Relaxed) {
+                        // Never a mix of two revisions, and never going back
+                        let snapshot = live.snapshot();
+                        assert_eq!(
+                            u64::from(snapshot.rate_limit.unwrap().burst),
+                            snapshot.revision
+                        );
+                        assert!(snapshot.revision >= last);
+                        last = snapshot.revision;
+                    }
+                });
+            }
+            for revision in 2..=200 {
+                live.swap(versioned(revision)).unwrap();
+            }
+            done.store(true, std::sync::atomic::Ordering::Relaxed);
+        });
+        assert_eq!(live.snapshot().revision, 200);
+    }
+}
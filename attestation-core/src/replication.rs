@@ -0,0 +1,392 @@
+//! Replication of accepted checkpoints between gateway stores.
+//!
+//! Gateways in different regions each accept checkpoints from the robots
+//! near them and copy them to one another in the background. A
+//! [`Replicator`] pulls one robot's checkpoints from a peer's store, a page
+//! at a time from where it last stopped, and writes the ones the local
+//! store lacks after full chain verification.
+//!
+//! Two different checkpoints for the same robot and sequence mean the
+//! robot's key signed two histories (or one gateway's store was tampered
+//! with). Such a fork is never resolved automatically: the pair is kept as
+//! [`ForkEvidence`], handed to every [`ForkAlertSink`], and nothing past it
+//! is replicated for that robot; every later pass reports it again until an
+//! operator resolves it.
+
+use crate::backfill::{CheckpointQuery, CheckpointStore, CheckpointStream, PageCursor};
+use crate::chain::{ChainError, ChainHead, ChainVerifier};
+use crate::checkpoint::{Checkpoint, SignatureError};
+use crate::error::{ErrorCode, ErrorCoded, ErrorDetail};
+use crate::keys::KeyResolver;
+use crate::rotation::KeyRotationCert;
+use crate::serialization::SerializationError;
+use crate::types::RobotId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Two signed checkpoints claiming the same robot and sequence.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForkEvidence {
+    /// The checkpoint in the local store
+    pub local: Checkpoint,
+    /// The checkpoint replicated from the peer
+    pub remote: Checkpoint,
+}
+
+impl ForkEvidence {
+    /// Check that both checkpoints are validly signed, name the same robot
+    /// and sequence, and differ.
+    pub fn verify(&self, keys: &dyn KeyResolver) -> Result<(), ReplicationError> {
+        let same_slot = self.local.robot_id == self.remote.robot_id
+            && self.local.sequence == self.remote.sequence;
+        if !same_slot || self.local.compute_hash()? == self.remote.compute_hash()? {
+            return Err(ReplicationError::NotAFork);
+        }
+        self.local.verify_with_resolver(keys)?;
+        self.remote.verify_with_resolver(keys)?;
+        Ok(())
+    }
+}
+
+/// Receives fork alerts (pager, audit log, operator dashboard).
+pub trait ForkAlertSink: Send {
+    fn fork_detected(&mut self, evidence: &ForkEvidence);
+}
+
+/// In-memory sink, for tests and short-lived tools.
+impl ForkAlertSink for Vec<ForkEvidence> {
+    fn fork_detected(&mut self, evidence: &ForkEvidence) {
+        self.push(evidence.clone());
+    }
+}
+
+/// Outcome of one replication pass for one robot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicationReport {
+    pub robot_id: RobotId,
+    /// Sequences newly written to the local store
+    pub replicated: Vec<u64>,
+    /// Sequences the local store already held, identical
+    pub already_present: Vec<u64>,
+    /// Sequences where the stores disagree
+    pub forks: Vec<u64>,
+    /// First peer checkpoint that failed verification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<(u64, ErrorDetail)>,
+}
+
+/// Pulls checkpoints from a peer store, remembering where each robot left off.
+pub struct Replicator {
+    cursors: HashMap<RobotId, PageCursor>,
+    sinks: Vec<Box<dyn ForkAlertSink>>,
+    page_size: usize,
+}
+
+impl Replicator {
+    pub fn new() -> Self {
+        Self {
+            cursors: HashMap::new(),
+            sinks: Vec::new(),
+            page_size: CheckpointQuery::DEFAULT_LIMIT,
+        }
+    }
+
+    /// Send fork alerts to `sink` as well.
+    pub fn with_alert_sink(mut self, sink: Box<dyn ForkAlertSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Read the peer store `page_size` checkpoints at a time.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Where replication of `robot_id` will resume.
+    pub fn cursor(&self, robot_id: &RobotId) -> Option<PageCursor> {
+        self.cursors.get(robot_id).copied()
+    }
+
+    /// Copy `robot_id`'s new checkpoints from `peer` into `local`.
+    ///
+    /// `keys` and `rotations` verify the chain, as for
+    /// [`backfill`](crate::backfill::backfill). A fork or a rejected
+    /// checkpoint ends the pass; the cursor stays before it, so a later pass
+    /// retries it.
+    pub fn replicate(
+        &mut self,
+        peer: &dyn CheckpointStore,
+        local: &mut dyn CheckpointStore,
+        robot_id: &RobotId,
+        keys: Box<dyn KeyResolver>,
+        rotations: &[KeyRotationCert],
+    ) -> Result<ReplicationReport, ReplicationError> {
+        let mut verifier = ChainVerifier::with_resolver(keys);
+        for cert in rotations {
+            verifier.add_rotation(cert)?;
+        }
+
+        // Resume from the local copy of the last checkpoint handled; start
+        // over if the local store no longer has it.
+        let mut query = CheckpointQuery::new(robot_id.clone()).with_limit(self.page_size);
+        if let Some(cursor) = self.cursor(robot_id) {
+            match stored_at(local, robot_id, cursor.last_sequence) {
+                Some(head) => {
+                    verifier = verifier.resume_from(ChainHead::from_checkpoint(&head)?);
+                    query = query.after(cursor);
+                }
+                None => {
+                    self.cursors.remove(robot_id);
+                }
+            }
+        }
+
+        let mut report = ReplicationReport {
+            robot_id: robot_id.clone(),
+            replicated: Vec::new(),
+            already_present: Vec::new(),
+            forks: Vec::new(),
+            rejected: None,
+        };
+        for remote in CheckpointStream::new(peer, query) {
+            let sequence = remote.sequence;
+            match stored_at(local, robot_id, sequence) {
+                Some(stored) if stored.compute_hash()? != remote.compute_hash()? => {
+                    let evidence = ForkEvidence {
+                        local: stored,
+                        remote,
+                    };
+                    for sink in &mut self.sinks {
+                        sink.fork_detected(&evidence);
+                    }
+                    report.forks.push(sequence);
+                    break;
+                }
+                Some(stored) => {
+                    if let Err(e) = verifier.verify_next(&stored) {
+                        report.rejected = Some((sequence, e.detail()));
+                        break;
+                    }
+                    report.already_present.push(sequence);
+                }
+                None => match verifier.accept(remote) {
+                    Ok(verified) => {
+                        local.insert(verified);
+                        report.replicated.push(sequence);
+                    }
+                    Err(e) => {
+                        report.rejected = Some((sequence, e.detail()));
+                        break;
+                    }
+                },
+            }
+            self.cursors.insert(
+                robot_id.clone(),
+                PageCursor {
+                    last_sequence: sequence,
+                },
+            );
+        }
+        Ok(report)
+    }
+}
+
+impl Default for Replicator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The stored checkpoint of `robot_id` at `sequence`.
+fn stored_at(store: &dyn CheckpointStore, robot_id: &RobotId, sequence: u64) -> Option<Checkpoint> {
+    let query = CheckpointQuery::new(robot_id.clone()).with_sequences(sequence, sequence);
+    store.page(&query).checkpoints.pop()
+}
+
+#[derive(Debug, Error)]
+pub enum ReplicationError {
+    #[error("Checkpoints are identical or do not claim the same robot and sequence")]
+    NotAFork,
+
+    #[error("Checkpoint signature error: {0}")]
+    Signature(#[from] SignatureError),
+
+    #[error("Stored chain or rotation does not verify: {0}")]
+    Chain(#[from] ChainError),
+
+    #[error("Checkpoint hashing failed: {0}")]
+    Serialization(#[from] SerializationError),
+}
+
+impl ErrorCoded for ReplicationError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ReplicationError::NotAFork => ErrorCode::ForkEvidenceInvalid,
+            ReplicationError::Signature(e) => e.code(),
+            ReplicationError::Chain(e) => e.code(),
+            ReplicationError::Serialization(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::VerifiedCheckpoint;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::crypto::Signer;
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct MemoryStore(BTreeMap<u64, Checkpoint>);
+
+    impl CheckpointStore for MemoryStore {
+        fn checkpoints(&self, robot_id: &RobotId) -> Vec<Checkpoint> {
+            self.0
+                .values()
+                .filter(|c| &c.robot_id == robot_id)
+                .cloned()
+                .collect()
+        }
+
+        fn insert(&mut self, checkpoint: VerifiedCheckpoint) {
+            self.0.insert(checkpoint.sequence, checkpoint.into_inner());
+        }
+    }
+
+    /// Shares alerts with the test after the sink moves into the replicator
+    struct SharedSink(Arc<Mutex<Vec<ForkEvidence>>>);
+
+    impl ForkAlertSink for SharedSink {
+        fn fork_detected(&mut self, evidence: &ForkEvidence) {
+            self.0.lock().unwrap().fork_detected(evidence);
+        }
+    }
+
+    fn chain(signer: &Signer, count: u64, fork_at: u64) -> Vec<Checkpoint> {
+        let mut checkpoints: Vec<Checkpoint> = Vec::new();
+        for sequence in 0..count {
+            let builder = match checkpoints.last() {
+                Some(prev) => CheckpointBuilder::continuing_from(prev).unwrap(),
+                None => CheckpointBuilder::test_fixture(),
+            };
+            let root = if sequence >= fork_at { 9 } else { 0 };
+            checkpoints.push(
+                builder
+                    .monotonic_counter(sequence + 1)
+                    .timestamp(
+                        chrono::DateTime::UNIX_EPOCH
+                            + chrono::Duration::minutes(sequence as i64 + 1),
+                    )
+                    .entries_root([root; 32])
+                    .build_and_sign(signer.signing_key())
+                    .unwrap(),
+            );
+        }
+        checkpoints
+    }
+
+    /// `checkpoints`, a chain from sequence 0, as accepted by a verifier
+    fn verified(signer: &Signer, checkpoints: &[Checkpoint]) -> Vec<VerifiedCheckpoint> {
+        let mut verifier = ChainVerifier::new(signer.verifying_key());
+        checkpoints
+            .iter()
+            .map(|c| verifier.accept(c.clone()).unwrap())
+            .collect()
+    }
+
+    fn store_of(signer: &Signer, checkpoints: &[Checkpoint]) -> MemoryStore {
+        let mut store = MemoryStore::default();
+        for checkpoint in verified(signer, checkpoints) {
+            store.insert(checkpoint);
+        }
+        store
+    }
+
+    #[test]
+    fn test_replicates_incrementally_from_cursor() {
+        let signer = Signer::generate();
+        let history = chain(&signer, 7, u64::MAX);
+        let robot_id = history[0].robot_id.clone();
+        let mut peer = store_of(&signer, &history[..4]);
+        let mut local = store_of(&signer, &history[..2]);
+        let mut replicator = Replicator::new().with_page_size(2);
+
+        let report = replicator
+            .replicate(
+                &peer,
+                &mut local,
+                &robot_id,
+                Box::new(signer.verifying_key()),
+                &[],
+            )
+            .unwrap();
+        assert_eq!(report.already_present, [0, 1]);
+        assert_eq!(report.replicated, [2, 3]);
+
+        // The next pass only reads what the peer accepted since
+        for checkpoint in verified(&signer, &history).into_iter().skip(4) {
+            peer.insert(checkpoint);
+        }
+        let report = replicator
+            .replicate(
+                &peer,
+                &mut local,
+                &robot_id,
+                Box::new(signer.verifying_key()),
+                &[],
+            )
+            .unwrap();
+        assert!(report.already_present.is_empty());
+        assert_eq!(report.replicated, [4, 5, 6]);
+        assert_eq!(local.checkpoints(&robot_id), history);
+    }
+
+    #[test]
+    fn test_fork_raises_alert_with_evidence() {
+        let signer = Signer::generate();
+        let ours = chain(&signer, 5, u64::MAX);
+        let theirs = chain(&signer, 5, 3);
+        let robot_id = ours[0].robot_id.clone();
+        let alerts = Arc::new(Mutex::new(Vec::new()));
+        let mut replicator =
+            Replicator::new().with_alert_sink(Box::new(SharedSink(alerts.clone())));
+
+        let mut local = store_of(&signer, &ours[..4]);
+        let report = replicator
+            .replicate(
+                &store_of(&signer, &theirs),
+                &mut local,
+                &robot_id,
+                Box::new(signer.verifying_key()),
+                &[],
+            )
+            .unwrap();
+        assert_eq!(report.forks, [3]);
+        assert!(
+            report.replicated.is_empty(),
+            "nothing past a fork is replicated"
+        );
+        assert_eq!(local.checkpoints(&robot_id).len(), 4);
+
+        let alerts = alerts.lock().unwrap();
+        assert_eq!(alerts.len(), 1);
+        alerts[0].verify(&signer.verifying_key()).unwrap();
+        assert_eq!(alerts[0].remote, theirs[3]);
+
+        let not_a_fork = ForkEvidence {
+            local: ours[1].clone(),
+            remote: ours[1].clone(),
+        };
+        assert_eq!(
+            not_a_fork
+                .verify(&signer.verifying_key())
+                .unwrap_err()
+                .code(),
+            ErrorCode::ForkEvidenceInvalid
+        );
+    }
+}
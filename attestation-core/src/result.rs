@@ -0,0 +1,227 @@
+//! Versioned storage encoding of attestation results.
+//!
+//! [`AttestationResult`] serializes with chrono's RFC 3339 timestamps, which
+//! is fine for JSON reports but not for anything hashed (see
+//! [`crate::serialization::timestamp`]). Archived results instead use
+//! [`AttestationResult::to_bytes`]: canonical CBOR of a version-tagged copy
+//! with integer-nanosecond timestamps. [`AttestationResult::compute_hash`]
+//! hashes exactly those bytes, so receipts and other documents can reference
+//! a stored result by hash and anyone holding the bytes can check them.
+//!
+//! A change to the encoding bumps [`RESULT_VERSION`]; decoders keep reading
+//! every earlier version.
+
+use crate::crypto::sha256;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{
+    AttestationResult, ClaimValue, CrlFreshness, Hash256, RevocationCheck, RevocationReason,
+    RevocationSource, RevocationStatus,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Storage encoding version of attestation results
+pub const RESULT_VERSION: u8 = 1;
+
+/// Version tag decoded ahead of the rest, so unknown versions fail cleanly.
+#[derive(Deserialize)]
+struct VersionTag {
+    version: u8,
+}
+
+/// Stored form of an [`AttestationResult`], version 1
+#[derive(Serialize, Deserialize)]
+struct ResultV1 {
+    version: u8,
+    vendor: String,
+    enclave_measurement: Vec<u8>,
+    quote_verified: bool,
+    #[serde(with = "crate::serialization::timestamp")]
+    verified_at: DateTime<Utc>,
+    revoke_check: RevocationCheckV1,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    raw_quote: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pck_chain: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    claims: BTreeMap<String, ClaimValue>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RevocationCheckV1 {
+    status: RevocationStatus,
+    source: RevocationSource,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    reasons: Vec<RevocationReason>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "optional_timestamp"
+    )]
+    revoked_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    crl_freshness: Option<CrlFreshnessV1>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CrlFreshnessV1 {
+    #[serde(with = "crate::serialization::timestamp")]
+    this_update: DateTime<Utc>,
+    #[serde(with = "crate::serialization::timestamp")]
+    next_update: DateTime<Utc>,
+}
+
+impl AttestationResult {
+    /// Encode for storage as canonical CBOR (current [`RESULT_VERSION`]).
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        let check = &self.revoke_check;
+        to_canonical_cbor(&ResultV1 {
+            version: RESULT_VERSION,
+            vendor: self.vendor.clone(),
+            enclave_measurement: self.enclave_measurement.clone(),
+            quote_verified: self.quote_verified,
+            verified_at: self.verified_at,
+            revoke_check: RevocationCheckV1 {
+                status: check.status,
+                source: check.source,
+                reasons: check.reasons.clone(),
+                revoked_at: check.revoked_at,
+                crl_freshness: check.crl_freshness.map(|crl| CrlFreshnessV1 {
+                    this_update: crl.this_update,
+                    next_update: crl.next_update,
+                }),
+            },
+            raw_quote: self.raw_quote.clone(),
+            pck_chain: self.pck_chain.clone(),
+            claims: self.claims.clone(),
+        })
+    }
+
+    /// Decode a stored result of any known version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        let VersionTag { version } = from_canonical_cbor(bytes)?;
+        if version != RESULT_VERSION {
+            return Err(SerializationError::UnsupportedVersion {
+                kind: "attestation result",
+                version,
+            });
+        }
+        let stored: ResultV1 = from_canonical_cbor(bytes)?;
+        let check = stored.revoke_check;
+        Ok(Self {
+            vendor: stored.vendor,
+            enclave_measurement: stored.enclave_measurement,
+            quote_verified: stored.quote_verified,
+            verified_at: stored.verified_at,
+            revoke_check: RevocationCheck {
+                status: check.status,
+                source: check.source,
+                reasons: check.reasons,
+                revoked_at: check.revoked_at,
+                crl_freshness: check.crl_freshness.map(|crl| CrlFreshness {
+                    this_update: crl.this_update,
+                    next_update: crl.next_update,
+                }),
+            },
+            raw_quote: stored.raw_quote,
+            pck_chain: stored.pck_chain,
+            claims: stored.claims,
+        })
+    }
+
+    /// SHA-256 of [`AttestationResult::to_bytes`].
+    pub fn compute_hash(&self) -> Result<Hash256, SerializationError> {
+        Ok(sha256(&self.to_bytes()?))
+    }
+}
+
+/// [`crate::serialization::timestamp`] for optional fields.
+mod optional_timestamp {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => crate::serialization::timestamp::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "crate::serialization::timestamp")] DateTime<Utc>);
+
+        Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(value)| value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorCode, ErrorCoded};
+
+    fn result() -> AttestationResult {
+        let verified_at = DateTime::from_timestamp(1_700_000_000, 123_456_789).unwrap();
+        let mut claims = BTreeMap::new();
+        claims.insert("sgx.isv_svn".to_string(), ClaimValue::Uint(3));
+        AttestationResult {
+            vendor: "intel-sgx".to_string(),
+            enclave_measurement: vec![7u8; 32],
+            quote_verified: true,
+            verified_at,
+            revoke_check: RevocationCheck::revoked(
+                RevocationSource::Crl,
+                RevocationReason::KeyCompromise,
+                Some(verified_at - chrono::Duration::days(1)),
+            )
+            .with_crl_freshness(CrlFreshness {
+                this_update: verified_at - chrono::Duration::hours(2),
+                next_update: verified_at + chrono::Duration::days(7),
+            }),
+            raw_quote: Some(vec![1, 2, 3]),
+            pck_chain: None,
+            claims,
+        }
+    }
+
+    #[test]
+    fn test_stored_result_roundtrips_with_stable_hash() {
+        let result = result();
+        let bytes = result.to_bytes().unwrap();
+        let decoded = AttestationResult::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes().unwrap(), bytes);
+        assert_eq!(decoded.verified_at, result.verified_at);
+        assert_eq!(decoded.revoke_check, result.revoke_check);
+        assert_eq!(decoded.claims, result.claims);
+        assert_eq!(decoded.compute_hash().unwrap(), sha256(&bytes));
+
+        let mut other = result.clone();
+        other.quote_verified = false;
+        assert_ne!(
+            other.compute_hash().unwrap(),
+            result.compute_hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unknown_version_rejected() {
+        #[derive(Serialize)]
+        struct Future {
+            version: u8,
+            vendor: String,
+        }
+        let bytes = to_canonical_cbor(&Future {
+            version: RESULT_VERSION + 1,
+            vendor: "intel-sgx".to_string(),
+        })
+        .unwrap();
+        let err = AttestationResult::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::UnsupportedVersion);
+    }
+}
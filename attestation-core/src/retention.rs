@@ -0,0 +1,359 @@
+//! Retention of stored entries at the gateway.
+//!
+//! Raw entries are what makes a gateway store grow; the checkpoints over
+//! them are small. A [`RetentionPolicy`] keeps each robot's latest
+//! `keep_hot` checkpoints with their entries and retires the entries of
+//! older ones. The gateway runs [`Compactor::compact`] periodically in the
+//! background. Retiring a checkpoint records it in the robot's
+//! [`RootHistory`], then drops its entries from the [`CheckpointStore`];
+//! the checkpoint itself stays. Whole windows of checkpoints move to cold
+//! storage separately ([`ShardedStore::archive`](crate::shard::ShardedStore::archive)).
+//!
+//! An anchored root must never be orphaned, i.e. left on chain with nothing
+//! at the gateway to show what it commits to. Before dropping the entries of
+//! a checkpoint the compactor checks that:
+//!
+//! - its entries_root is confirmed on chain. A pending, failed or reorged
+//!   anchor holds back that checkpoint and every later one of the robot, so
+//!   the history stays in sequence order;
+//! - the stored entries reproduce the entries_root;
+//! - the history proves the retired root.
+
+use crate::anchor::{AnchorStatus, AnchorTracker};
+use crate::backfill::CheckpointStore;
+use crate::checkpoint::Checkpoint;
+use crate::crypto::ct_eq;
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::history::{HistoryError, RootHistory};
+use crate::types::RobotId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// How much of each robot's chain keeps its entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Latest checkpoints per robot whose entries are never retired
+    pub keep_hot: usize,
+}
+
+/// What one compaction run did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactionReport {
+    /// Checkpoints whose entries were dropped
+    pub retired: Vec<(RobotId, u64)>,
+    /// Per robot, the first cold checkpoint held back because its root is
+    /// not confirmed on chain yet
+    pub awaiting_anchor: Vec<(RobotId, u64)>,
+    pub entries_dropped: u64,
+}
+
+/// Retires cold entries into per-robot root histories.
+#[derive(Debug, Clone)]
+pub struct Compactor {
+    policy: RetentionPolicy,
+    histories: HashMap<RobotId, RootHistory>,
+}
+
+impl Compactor {
+    pub fn new(policy: RetentionPolicy) -> Self {
+        Self::from_histories(policy, HashMap::new())
+    }
+
+    /// Resume compaction with persisted histories.
+    pub fn from_histories(
+        policy: RetentionPolicy,
+        histories: HashMap<RobotId, RootHistory>,
+    ) -> Self {
+        Self { policy, histories }
+    }
+
+    pub fn policy(&self) -> RetentionPolicy {
+        self.policy
+    }
+
+    /// The root history of `robot_id`, if any of its checkpoints was retired.
+    pub fn history(&self, robot_id: &RobotId) -> Option<&RootHistory> {
+        self.histories.get(robot_id)
+    }
+
+    /// All histories, for persisting.
+    pub fn histories(&self) -> &HashMap<RobotId, RootHistory> {
+        &self.histories
+    }
+
+    /// Retire the entries of `robots`' cold checkpoints whose roots
+    /// `anchors` shows confirmed.
+    ///
+    /// Stops at the first checkpoint failing a safety check; what was
+    /// retired before it stays retired.
+    pub fn compact(
+        &mut self,
+        store: &mut impl CheckpointStore,
+        anchors: &AnchorTracker,
+        robots: &[RobotId],
+    ) -> Result<CompactionReport, RetentionError> {
+        let mut report = CompactionReport::default();
+        for robot_id in robots {
+            let checkpoints = store.checkpoints(robot_id);
+            let cold = checkpoints.len().saturating_sub(self.policy.keep_hot);
+            let history = self.histories.entry(robot_id.clone()).or_default();
+            for checkpoint in &checkpoints[..cold] {
+                // Already retired, or never stored with entries
+                let Some(entries) = store.stored_entries(checkpoint) else {
+                    continue;
+                };
+                if !confirmed(anchors, checkpoint) {
+                    report
+                        .awaiting_anchor
+                        .push((robot_id.clone(), checkpoint.sequence));
+                    break;
+                }
+                // A run interrupted after recording drops the entries next time
+                if history.get(checkpoint.sequence).is_none() {
+                    history.record_stored(checkpoint, entries)?;
+                }
+                check_provable(history, checkpoint)?;
+                let dropped = store
+                    .drop_entries(checkpoint)
+                    .map_or(0, |entries| entries.len());
+                report.retired.push((robot_id.clone(), checkpoint.sequence));
+                report.entries_dropped += dropped as u64;
+            }
+        }
+        Ok(report)
+    }
+}
+
+fn confirmed(anchors: &AnchorTracker, checkpoint: &Checkpoint) -> bool {
+    anchors
+        .get(&checkpoint.entries_root)
+        .is_some_and(|record| record.status == AnchorStatus::Confirmed)
+}
+
+fn check_provable(history: &RootHistory, checkpoint: &Checkpoint) -> Result<(), RetentionError> {
+    let proof = history.prove(checkpoint.sequence)?;
+    let provable = proof.is_some_and(|proof| {
+        history.root().is_ok_and(|root| proof.verify(&root))
+            && ct_eq(&proof.retained.entries_root, &checkpoint.entries_root)
+            && checkpoint
+                .compute_hash()
+                .is_ok_and(|hash| ct_eq(&proof.retained.checkpoint_hash, &hash))
+    });
+    if !provable {
+        return Err(RetentionError::Unprovable {
+            robot: checkpoint.robot_id.0.clone(),
+            sequence: checkpoint.sequence,
+        });
+    }
+    Ok(())
+}
+
+#[derive(Debug, Error)]
+pub enum RetentionError {
+    #[error("Root history of robot {robot} does not prove checkpoint #{sequence}")]
+    Unprovable { robot: String, sequence: u64 },
+
+    #[error(transparent)]
+    History(#[from] HistoryError),
+}
+
+impl ErrorCoded for RetentionError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            RetentionError::Unprovable { .. } => ErrorCode::HistoryUnprovable,
+            RetentionError::History(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anchor::{AnchorConfig, AnchorRecord};
+    use crate::chain::{ChainVerifier, VerifiedCheckpoint};
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::crypto::Signer;
+    use crate::merkle::{Entry, MerkleTree};
+    use crate::shard::ShardedStore;
+    use crate::tombstone::StoredEntry;
+    use crate::types::Hash256;
+    use chrono::{DateTime, Duration};
+
+    /// Five hourly checkpoints of two entries each.
+    fn chain(signer: &Signer) -> Vec<(VerifiedCheckpoint, Vec<StoredEntry>)> {
+        let mut verifier = ChainVerifier::new(signer.verifying_key());
+        let mut out: Vec<(VerifiedCheckpoint, Vec<StoredEntry>)> = Vec::new();
+        for sequence in 0..5u64 {
+            let mut tree = MerkleTree::new();
+            let payloads: Vec<Vec<u8>> = (0..2u8).map(|i| vec![sequence as u8, i]).collect();
+            for (i, payload) in payloads.iter().enumerate() {
+                tree.insert(Entry::new(1000 * (2 * sequence + i as u64), 0, payload));
+            }
+            let builder = match out.last() {
+                Some((prev, _)) => CheckpointBuilder::continuing_from(prev).unwrap(),
+                None => CheckpointBuilder::test_fixture(),
+            };
+            let checkpoint = builder
+                .monotonic_counter(sequence + 1)
+                .timestamp(
+                    DateTime::UNIX_EPOCH
+                        + Duration::days(20_000)
+                        + Duration::hours(sequence as i64),
+                )
+                .entries_root(tree.root())
+                .build_and_sign(signer.signing_key())
+                .unwrap();
+            let entries = tree
+                .entries()
+                .into_iter()
+                .zip(payloads)
+                .map(|(entry, payload)| StoredEntry::Live {
+                    entry: entry.clone(),
+                    payload,
+                })
+                .collect();
+            out.push((verifier.accept(checkpoint).unwrap(), entries));
+        }
+        out
+    }
+
+    fn anchor(root: Hash256, status: AnchorStatus) -> AnchorRecord {
+        AnchorRecord {
+            merkle_root: root,
+            calldata: Vec::new(),
+            transaction_hash: root,
+            submitted_at_block: 1,
+            attempts: 1,
+            status,
+            confirmations: 0,
+            inclusion: None,
+            receipt: None,
+            failure: None,
+            orphaned: None,
+            supersedes: None,
+        }
+    }
+
+    #[test]
+    fn test_compaction_retires_only_cold_anchored_checkpoints() {
+        let signer = Signer::generate();
+        let robot_id = RobotId("R-001".to_string());
+        let mut store = ShardedStore::new(Duration::hours(2));
+        let mut checkpoints = Vec::new();
+        for (checkpoint, entries) in chain(&signer) {
+            checkpoints.push(checkpoint.clone().into_inner());
+            store.insert(checkpoint);
+            store
+                .insert_entries(checkpoints.last().unwrap(), entries)
+                .unwrap();
+        }
+        let root_of_roots = store.root_of_roots().unwrap();
+
+        // #0 and #1 confirmed, #2 still pending; #3 and #4 are hot
+        let status = [
+            AnchorStatus::Confirmed,
+            AnchorStatus::Confirmed,
+            AnchorStatus::Pending,
+        ];
+        let records = checkpoints
+            .iter()
+            .zip(status)
+            .map(|(c, s)| anchor(c.entries_root, s))
+            .collect();
+        let config = AnchorConfig::new(31337, [0xAA; 20]);
+        let mut compactor = Compactor::new(RetentionPolicy { keep_hot: 2 });
+        let robots = [robot_id.clone()];
+        let report = compactor
+            .compact(
+                &mut store,
+                &AnchorTracker::from_records(config.clone(), records),
+                &robots,
+            )
+            .unwrap();
+        assert_eq!(
+            report.retired,
+            [(robot_id.clone(), 0), (robot_id.clone(), 1)]
+        );
+        assert_eq!(report.awaiting_anchor, [(robot_id.clone(), 2)]);
+        assert_eq!(report.entries_dropped, 4);
+
+        // Retired checkpoints stay stored and provable, without their entries
+        assert_eq!(store.checkpoints(&robot_id).len(), 5);
+        assert_eq!(store.root_of_roots().unwrap(), root_of_roots);
+        assert!(store.entries(&checkpoints[1]).is_none());
+        assert!(store.entries(&checkpoints[2]).is_some());
+        let history = compactor.history(&robot_id).unwrap();
+        let proof = history.prove(1).unwrap().unwrap();
+        assert!(proof.verify(&history.root().unwrap()));
+        assert_eq!(proof.retained.entries_root, checkpoints[1].entries_root);
+
+        // Once every root is confirmed, only the hot checkpoints keep entries
+        let records = checkpoints
+            .iter()
+            .map(|c| anchor(c.entries_root, AnchorStatus::Confirmed))
+            .collect();
+        let report = compactor
+            .compact(
+                &mut store,
+                &AnchorTracker::from_records(config, records),
+                &robots,
+            )
+            .unwrap();
+        assert_eq!(report.retired, [(robot_id.clone(), 2)]);
+        assert!(report.awaiting_anchor.is_empty());
+        assert!(store.entries(&checkpoints[3]).is_some());
+        assert_eq!(compactor.history(&robot_id).unwrap().roots().len(), 3);
+    }
+
+    /// Store whose entries were altered behind its back.
+    #[derive(Default)]
+    struct TamperedStore {
+        checkpoints: Vec<Checkpoint>,
+        entries: HashMap<u64, Vec<StoredEntry>>,
+    }
+
+    impl CheckpointStore for TamperedStore {
+        fn checkpoints(&self, _robot_id: &RobotId) -> Vec<Checkpoint> {
+            self.checkpoints.clone()
+        }
+
+        fn insert(&mut self, checkpoint: VerifiedCheckpoint) {
+            self.checkpoints.push(checkpoint.into_inner());
+        }
+
+        fn stored_entries(&self, checkpoint: &Checkpoint) -> Option<&[StoredEntry]> {
+            self.entries.get(&checkpoint.sequence).map(Vec::as_slice)
+        }
+
+        fn drop_entries(&mut self, checkpoint: &Checkpoint) -> Option<Vec<StoredEntry>> {
+            self.entries.remove(&checkpoint.sequence)
+        }
+    }
+
+    #[test]
+    fn test_entries_not_reproducing_the_root_are_kept() {
+        let signer = Signer::generate();
+        let robot_id = RobotId("R-001".to_string());
+        let mut store = TamperedStore::default();
+        let mut records = Vec::new();
+        for (checkpoint, mut entries) in chain(&signer) {
+            if checkpoint.sequence == 1 {
+                entries.pop();
+            }
+            store.entries.insert(checkpoint.sequence, entries);
+            records.push(anchor(checkpoint.entries_root, AnchorStatus::Confirmed));
+            store.insert(checkpoint);
+        }
+        let anchors = AnchorTracker::from_records(AnchorConfig::new(31337, [0xAA; 20]), records);
+
+        let mut compactor = Compactor::new(RetentionPolicy { keep_hot: 0 });
+        let err = compactor
+            .compact(&mut store, &anchors, std::slice::from_ref(&robot_id))
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::HistoryRootMismatch);
+        assert!(!store.entries.contains_key(&0));
+        assert_eq!(store.entries[&1].len(), 1);
+        assert_eq!(compactor.history(&robot_id).unwrap().roots().len(), 1);
+    }
+}
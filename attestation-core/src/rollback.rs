@@ -0,0 +1,213 @@
+//! Alerts on rollback attempts.
+//!
+//! A checkpoint that goes back in sequence or counter, or whose `prev_root`
+//! does not link to the accepted head, is either a replay or a robot
+//! restored from an old snapshot. Rejecting it only tells the submitter;
+//! a [`ChainVerifier`](crate::ChainVerifier) configured with
+//! [`with_rollback_alerts`](crate::ChainVerifier::with_rollback_alerts)
+//! also emits a [`RollbackAlert`] with the full context to every
+//! [`RollbackAlertSink`], for operators to act on.
+//!
+//! Sinks are called inline while verifying, so delivery to a webhook,
+//! syslog or paging service belongs behind a channel: the
+//! `mpsc::Sender` sink hands alerts to a task that does the I/O.
+
+use crate::chain::{ChainError, ChainHead};
+use crate::checkpoint::Checkpoint;
+use crate::error::{ErrorCoded, ErrorDetail};
+use crate::types::{Hash256, KeyId, RobotId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc;
+use std::sync::Mutex;
+
+/// Which anti-rollback rule a checkpoint broke.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RollbackKind {
+    SequenceRegression,
+    CounterRegression,
+    PrevRootMismatch,
+}
+
+/// A rejected checkpoint that tried to roll a chain back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RollbackAlert {
+    pub kind: RollbackKind,
+    /// The rejection, as returned to the submitter
+    pub error: ErrorDetail,
+    pub robot_id: RobotId,
+    /// Key that signed the offered checkpoint
+    pub signer_key_id: KeyId,
+    /// Last accepted checkpoint
+    pub head_sequence: u64,
+    pub head_counter: u64,
+    pub head_hash: Hash256,
+    /// The offered checkpoint
+    pub offered_sequence: u64,
+    pub offered_counter: u64,
+    pub offered_prev_root: Hash256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offered_hash: Option<Hash256>,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub detected_at: DateTime<Utc>,
+}
+
+impl RollbackAlert {
+    /// The alert for `error`, if it is a rollback attempt on `head`.
+    pub fn from_rejection(
+        head: &ChainHead,
+        checkpoint: &Checkpoint,
+        error: &ChainError,
+    ) -> Option<Self> {
+        let kind = match error {
+            ChainError::SequenceRegression { .. } => RollbackKind::SequenceRegression,
+            ChainError::CounterRegression { .. } => RollbackKind::CounterRegression,
+            ChainError::PrevRootMismatch { .. } => RollbackKind::PrevRootMismatch,
+            _ => return None,
+        };
+        Some(Self {
+            kind,
+            error: error.detail(),
+            robot_id: checkpoint.robot_id.clone(),
+            signer_key_id: checkpoint.signer_key_id,
+            head_sequence: head.sequence,
+            head_counter: head.monotonic_counter,
+            head_hash: head.hash,
+            offered_sequence: checkpoint.sequence,
+            offered_counter: checkpoint.monotonic_counter,
+            offered_prev_root: checkpoint.prev_root,
+            offered_hash: checkpoint.compute_hash().ok(),
+            detected_at: Utc::now(),
+        })
+    }
+}
+
+/// Receives rollback alerts. Shared by the verifiers of many robots.
+pub trait RollbackAlertSink: Send + Sync {
+    fn rollback_detected(&self, alert: &RollbackAlert);
+}
+
+/// In-memory sink, for tests and short-lived tools.
+impl RollbackAlertSink for Mutex<Vec<RollbackAlert>> {
+    fn rollback_detected(&self, alert: &RollbackAlert) {
+        self.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(alert.clone());
+    }
+}
+
+/// Hands alerts to a delivery task; alerts are dropped once it has gone away.
+impl RollbackAlertSink for mpsc::Sender<RollbackAlert> {
+    fn rollback_detected(&self, alert: &RollbackAlert) {
+        let _ = self.send(alert.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::ChainVerifier;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::crypto::Signer;
+    use crate::error::ErrorCode;
+    use std::sync::Arc;
+
+    fn checkpoint(signer: &Signer, sequence: u64, counter: u64, prev_root: Hash256) -> Checkpoint {
+        CheckpointBuilder::test_fixture()
+            .sequence(sequence)
+            .monotonic_counter(counter)
+            .prev_root(prev_root)
+            .build_and_sign(signer.signing_key())
+            .unwrap()
+    }
+
+    /// Verifier alerting to a recording sink and a channel.
+    fn verifier(
+        signer: &Signer,
+    ) -> (
+        ChainVerifier,
+        Arc<Mutex<Vec<RollbackAlert>>>,
+        mpsc::Receiver<RollbackAlert>,
+    ) {
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let (sender, receiver) = mpsc::channel();
+        let verifier = ChainVerifier::new(signer.verifying_key())
+            .with_rollback_alerts(recorded.clone())
+            .with_rollback_alerts(Arc::new(sender));
+        (verifier, recorded, receiver)
+    }
+
+    #[test]
+    fn test_sequence_and_counter_rollbacks_alert_every_sink() {
+        let signer = Signer::generate();
+        let (mut verifier, recorded, receiver) = verifier(&signer);
+        let first = checkpoint(&signer, 1, 10, [0u8; 32]);
+        let second = checkpoint(&signer, 2, 11, first.compute_hash().unwrap());
+        verifier
+            .verify_chain(&[first.clone(), second.clone()])
+            .unwrap();
+        let head_hash = second.compute_hash().unwrap();
+
+        // #2 again under a different root, then #3 with the counter of #2
+        let replayed = checkpoint(&signer, 2, 12, head_hash);
+        let err = verifier.verify_next(&replayed).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SequenceRegression);
+        let rewound = checkpoint(&signer, 3, 11, head_hash);
+        let err = verifier.verify_next(&rewound).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::CounterRegression);
+
+        let alerts = recorded.lock().unwrap().clone();
+        let kinds: Vec<RollbackKind> = alerts.iter().map(|a| a.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                RollbackKind::SequenceRegression,
+                RollbackKind::CounterRegression
+            ]
+        );
+        let alert = &alerts[1];
+        assert_eq!(alert.robot_id, second.robot_id);
+        assert_eq!(alert.signer_key_id, signer.key_id());
+        assert_eq!(
+            (alert.head_sequence, alert.head_counter, alert.head_hash),
+            (2, 11, head_hash)
+        );
+        assert_eq!((alert.offered_sequence, alert.offered_counter), (3, 11));
+        assert_eq!(alert.offered_hash, Some(rewound.compute_hash().unwrap()));
+        assert_eq!(alert.error.code, ErrorCode::CounterRegression);
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), alerts);
+        // The rejected checkpoints did not move the head
+        assert_eq!(verifier.head().unwrap().sequence, 2);
+    }
+
+    #[test]
+    fn test_progression_and_other_rejections_stay_silent() {
+        let signer = Signer::generate();
+        let (mut verifier, recorded, receiver) = verifier(&signer);
+        let mut prev_root = [0u8; 32];
+        for sequence in 1..=5 {
+            let next = checkpoint(&signer, sequence, 10 + sequence, prev_root);
+            verifier.verify_next(&next).unwrap();
+            prev_root = next.compute_hash().unwrap();
+        }
+
+        // A skipped sequence and a foreign signer are rejected, but are not rollbacks
+        let gap = checkpoint(&signer, 9, 30, prev_root);
+        assert_eq!(
+            verifier.verify_next(&gap).unwrap_err().code(),
+            ErrorCode::SequenceGap
+        );
+        let forged = checkpoint(&Signer::generate(), 6, 16, prev_root);
+        assert!(verifier.verify_next(&forged).is_err());
+
+        assert!(recorded.lock().unwrap().is_empty());
+        assert!(receiver.try_recv().is_err());
+        let head = verifier.head().unwrap().clone();
+        let gap_error = ChainError::SequenceGap {
+            expected: 6,
+            actual: 9,
+        };
+        assert!(RollbackAlert::from_rejection(&head, &gap, &gap_error).is_none());
+    }
+}
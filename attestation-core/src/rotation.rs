@@ -0,0 +1,168 @@
+//! Key rotation certificates.
+//!
+//! When a robot rotates its checkpoint signing key, the outgoing key signs a
+//! `KeyRotationCert` naming the incoming key and the first sequence number it
+//! signs. A [`crate::ChainVerifier`] that trusts the old key can then follow
+//! the chain across the rotation without an out-of-band key update.
+
+use crate::crypto::{ct_eq, key_id, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{KeyId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Rotation certificate version (for schema evolution)
+pub const ROTATION_VERSION: u8 = 1;
+
+/// Statement by an outgoing key handing over to a new key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRotationCert {
+    /// Schema version
+    pub version: u8,
+    /// Fingerprint of the outgoing key (the signer)
+    pub old_key_id: KeyId,
+    /// Incoming Ed25519 verifying key
+    pub new_key: [u8; 32],
+    /// First checkpoint sequence signed by the new key
+    pub effective_sequence: u64,
+    /// When the rotation was issued
+    #[serde(with = "crate::serialization::timestamp")]
+    pub issued_at: DateTime<Utc>,
+    /// Ed25519 signature by the old key over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned rotation certificate (for signature computation)
+#[derive(Serialize)]
+struct UnsignedRotation {
+    version: u8,
+    old_key_id: KeyId,
+    new_key: [u8; 32],
+    effective_sequence: u64,
+    #[serde(with = "crate::serialization::timestamp")]
+    issued_at: DateTime<Utc>,
+}
+
+impl KeyRotationCert {
+    /// Hand over from `old` to `new_key`, effective at `effective_sequence`.
+    pub fn issue(
+        old: &Signer,
+        new_key: &VerifyingKey,
+        effective_sequence: u64,
+    ) -> Result<Self, RotationError> {
+        let mut cert = Self {
+            version: ROTATION_VERSION,
+            old_key_id: old.key_id(),
+            new_key: new_key.to_bytes(),
+            effective_sequence,
+            issued_at: Utc::now(),
+            signature: SignatureBytes([0u8; 64]),
+        };
+        let signature = old.sign(&cert.signing_payload()?);
+        cert.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(cert)
+    }
+
+    /// The incoming verifying key.
+    pub fn new_verifying_key(&self) -> Result<VerifyingKey, RotationError> {
+        VerifyingKey::from_bytes(&self.new_key).map_err(|_| RotationError::InvalidNewKey)
+    }
+
+    /// Fingerprint of the incoming key.
+    pub fn new_key_id(&self) -> Result<KeyId, RotationError> {
+        Ok(key_id(&self.new_verifying_key()?))
+    }
+
+    /// Verify the certificate signature against the outgoing key.
+    pub fn verify(&self, old_key: &VerifyingKey) -> Result<(), RotationError> {
+        use ed25519_dalek::Verifier;
+
+        if !ct_eq(&key_id(old_key).0, &self.old_key_id.0) {
+            return Err(RotationError::InvalidSignature);
+        }
+        self.new_verifying_key()?;
+
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        old_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| RotationError::InvalidSignature)
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedRotation {
+            version: self.version,
+            old_key_id: self.old_key_id,
+            new_key: self.new_key,
+            effective_sequence: self.effective_sequence,
+            issued_at: self.issued_at,
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RotationError {
+    #[error("Rotation certificate serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Rotation certificate names an invalid new key")]
+    InvalidNewKey,
+
+    #[error("Invalid rotation certificate signature")]
+    InvalidSignature,
+}
+
+impl ErrorCoded for RotationError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            RotationError::Serialization(e) => e.code(),
+            RotationError::InvalidNewKey | RotationError::InvalidSignature => {
+                ErrorCode::RotationInvalid
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_and_verify() {
+        let old = Signer::generate();
+        let new = Signer::generate();
+        let cert = KeyRotationCert::issue(&old, &new.verifying_key(), 100).unwrap();
+
+        cert.verify(&old.verifying_key()).unwrap();
+        assert_eq!(cert.new_key_id().unwrap(), new.key_id());
+
+        let decoded = KeyRotationCert::from_bytes(&cert.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, cert);
+    }
+
+    #[test]
+    fn test_rejects_tampering() {
+        let old = Signer::generate();
+        let new = Signer::generate();
+        let mut cert = KeyRotationCert::issue(&old, &new.verifying_key(), 100).unwrap();
+
+        assert!(cert.verify(&new.verifying_key()).is_err());
+        cert.effective_sequence = 1;
+        assert!(matches!(
+            cert.verify(&old.verifying_key()),
+            Err(RotationError::InvalidSignature)
+        ));
+    }
+}
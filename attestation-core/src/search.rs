@@ -0,0 +1,243 @@
+//! Cross-checkpoint entry search index.
+//!
+//! A checkpoint commits to its entries only through `entries_root`, so
+//! finding the checkpoint that covers a piece of evidence otherwise means
+//! rebuilding every tree. An [`EntrySearchIndex`] maps each entry's
+//! `data_hash` (and, for payloads that are a [`MissionRecord`], its record
+//! type) to the [`EntryLocation`]s that commit it: robot, sequence,
+//! checkpoint hash and entries_root, plus the entry key needed to ask for a
+//! Merkle proof.
+//!
+//! The index is a lookup aid, not evidence: a location it returns still has
+//! to be confirmed with the checkpoint and an inclusion proof. It only
+//! accepts a checkpoint together with entries that reproduce its
+//! entries_root, so it never points at a checkpoint that does not commit
+//! the entry. Storage backends expose one through
+//! [`CheckpointStore::entry_index`](crate::CheckpointStore::entry_index).
+
+use crate::checkpoint::Checkpoint;
+use crate::crypto::{ct_eq, sha256};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::merkle::MerkleTree;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::summary::MissionRecord;
+use crate::types::{Hash256, RobotId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+
+/// Where one indexed entry is committed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryLocation {
+    pub robot_id: RobotId,
+    pub sequence: u64,
+    pub checkpoint_hash: Hash256,
+    pub entries_root: Hash256,
+    /// Entry key, for [`MerkleTree::generate_proof`]
+    pub timestamp_us: u64,
+    pub nonce: u64,
+}
+
+/// Index from entry data hash and record type to committing checkpoints.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntrySearchIndex {
+    by_hash: BTreeMap<Hash256, Vec<EntryLocation>>,
+    by_kind: BTreeMap<String, Vec<EntryLocation>>,
+    /// Hashes of the checkpoints indexed so far
+    indexed: BTreeSet<Hash256>,
+}
+
+impl EntrySearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index the entries of `checkpoint`, which are in `tree`.
+    ///
+    /// `payloads` are whichever entry payloads are still at hand; those
+    /// that decode as a [`MissionRecord`] are also indexed by record type.
+    /// The tree must reproduce the checkpoint's entries_root. Indexing a
+    /// checkpoint twice is a no-op. Returns the number of entries indexed.
+    pub fn index(
+        &mut self,
+        checkpoint: &Checkpoint,
+        tree: &MerkleTree,
+        payloads: &[&[u8]],
+    ) -> Result<usize, SearchError> {
+        if tree.hash_alg() != checkpoint.hash_alg || !ct_eq(&tree.root(), &checkpoint.entries_root)
+        {
+            return Err(SearchError::RootMismatch(checkpoint.sequence));
+        }
+        let checkpoint_hash = checkpoint.compute_hash()?;
+        if !self.indexed.insert(checkpoint_hash) {
+            return Ok(0);
+        }
+
+        let kinds: BTreeMap<Hash256, String> = payloads
+            .iter()
+            .filter_map(|payload| {
+                let record = from_canonical_cbor::<MissionRecord>(payload).ok()?;
+                Some((sha256(payload), record.record_type))
+            })
+            .collect();
+        let entries = tree.entries();
+        for entry in &entries {
+            let location = EntryLocation {
+                robot_id: checkpoint.robot_id.clone(),
+                sequence: checkpoint.sequence,
+                checkpoint_hash,
+                entries_root: checkpoint.entries_root,
+                timestamp_us: entry.timestamp_us,
+                nonce: entry.nonce,
+            };
+            if let Some(kind) = kinds.get(&entry.data_hash) {
+                self.by_kind
+                    .entry(kind.clone())
+                    .or_default()
+                    .push(location.clone());
+            }
+            self.by_hash
+                .entry(entry.data_hash)
+                .or_default()
+                .push(location);
+        }
+        Ok(entries.len())
+    }
+
+    /// Every location committing an entry with `data_hash`.
+    pub fn find(&self, data_hash: &Hash256) -> Vec<&EntryLocation> {
+        self.by_hash.get(data_hash).into_iter().flatten().collect()
+    }
+
+    /// Locations of entries whose payload is a [`MissionRecord`] of
+    /// `record_type`, optionally only those of `robot_id`.
+    pub fn find_kind(&self, record_type: &str, robot_id: Option<&RobotId>) -> Vec<&EntryLocation> {
+        self.by_kind
+            .get(record_type)
+            .into_iter()
+            .flatten()
+            .filter(|location| robot_id.is_none_or(|robot_id| &location.robot_id == robot_id))
+            .collect()
+    }
+
+    /// Whether `checkpoint_hash` has been indexed.
+    pub fn contains_checkpoint(&self, checkpoint_hash: &Hash256) -> bool {
+        self.indexed.contains(checkpoint_hash)
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SearchError {
+    #[error("Entries do not reproduce the entries_root of checkpoint #{0}")]
+    RootMismatch(u64),
+
+    #[error("Serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+}
+
+impl ErrorCoded for SearchError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            SearchError::RootMismatch(_) => ErrorCode::IndexRootMismatch,
+            SearchError::Serialization(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::crypto::Signer;
+    use crate::merkle::Entry;
+
+    fn record(record_type: &str, distance_mm: u64) -> Vec<u8> {
+        MissionRecord {
+            record_type: record_type.to_string(),
+            distance_mm,
+            intervention: false,
+            anomalies: Vec::new(),
+        }
+        .to_bytes()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_locates_entries_across_checkpoints() {
+        let signer = Signer::generate();
+        let mut index = EntrySearchIndex::new();
+        let mut checkpoints: Vec<Checkpoint> = Vec::new();
+        let shared = b"camera frame 17".to_vec();
+        for sequence in 0..3u64 {
+            let payloads = [
+                record("perception", sequence),
+                record("plan", sequence),
+                shared.clone(),
+            ];
+            let mut tree = MerkleTree::new();
+            for (i, payload) in payloads.iter().enumerate() {
+                tree.insert(Entry::new(1000 * (3 * sequence + i as u64), 0, payload));
+            }
+            let builder = match checkpoints.last() {
+                Some(prev) => CheckpointBuilder::continuing_from(prev).unwrap(),
+                None => CheckpointBuilder::test_fixture(),
+            };
+            let checkpoint = builder
+                .monotonic_counter(sequence + 1)
+                .entries_root(tree.root())
+                .build_and_sign(signer.signing_key())
+                .unwrap();
+
+            // Entries that do not reproduce the root are refused
+            let err = index
+                .index(&checkpoint, &MerkleTree::new(), &[])
+                .unwrap_err();
+            assert_eq!(err.code(), ErrorCode::IndexRootMismatch);
+
+            let payloads: Vec<&[u8]> = payloads.iter().map(Vec::as_slice).collect();
+            assert_eq!(index.index(&checkpoint, &tree, &payloads).unwrap(), 3);
+            assert_eq!(index.index(&checkpoint, &tree, &payloads).unwrap(), 0);
+            checkpoints.push(checkpoint);
+        }
+
+        // A plan record is found in exactly the checkpoint committing it
+        let found = index.find(&sha256(&record("plan", 1)));
+        assert_eq!(found.len(), 1);
+        let location = found[0];
+        let checkpoint = &checkpoints[1];
+        assert_eq!(location.checkpoint_hash, checkpoint.compute_hash().unwrap());
+        assert_eq!(location.entries_root, checkpoint.entries_root);
+        assert_eq!((location.timestamp_us, location.nonce), (4000, 0));
+
+        // The same evidence logged under every checkpoint is found under each
+        let found = index.find(&sha256(&shared));
+        assert_eq!(
+            found.iter().map(|l| l.sequence).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(
+            index
+                .find_kind("perception", Some(&RobotId("R-001".to_string())))
+                .len(),
+            3
+        );
+        assert!(index
+            .find_kind("perception", Some(&RobotId("R-002".to_string())))
+            .is_empty());
+        assert!(index.find(&[0xee; 32]).is_empty());
+
+        let decoded = EntrySearchIndex::from_bytes(&index.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, index);
+        assert!(decoded.contains_checkpoint(&checkpoints[2].compute_hash().unwrap()));
+    }
+}
@@ -8,7 +8,9 @@
 //! 2. Integers encoded in minimal form
 //! 3. Floating-point disabled (use fixed-point or integers)
 //! 4. No indefinite-length encoding
+//! 5. Timestamps are integer nanoseconds since the Unix epoch (see [`timestamp`])
 
+use crate::error::{ErrorCode, ErrorCoded};
 use serde::{Deserialize, Serialize};
 use std::io::Read;
 use thiserror::Error;
@@ -23,6 +25,20 @@ pub enum SerializationError {
 
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
+
+    #[error("Unsupported {kind} version {version}")]
+    UnsupportedVersion { kind: &'static str, version: u8 },
+}
+
+impl ErrorCoded for SerializationError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            SerializationError::Encode(_) => ErrorCode::Encode,
+            SerializationError::Decode(_) => ErrorCode::Decode,
+            SerializationError::Io(_) => ErrorCode::NonCanonical,
+            SerializationError::UnsupportedVersion { .. } => ErrorCode::UnsupportedVersion,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, SerializationError>;
@@ -47,6 +63,46 @@ pub fn from_canonical_cbor<T: for<'de> Deserialize<'de>>(bytes: &[u8]) -> Result
     Ok(value)
 }
 
+/// Canonical encoding for `DateTime<Utc>` fields.
+///
+/// chrono's own serde impl writes an RFC 3339 string whose fractional digits
+/// depend on the value (and have changed between chrono releases), so the same
+/// instant could hash differently. Every hashed or signed timestamp instead uses
+/// `#[serde(with = "crate::serialization::timestamp")]`: a plain CBOR integer
+/// counting nanoseconds since the Unix epoch (no tag). Any value that encodes
+/// decodes to exactly the same `DateTime`; instants outside 1677-2262 and leap
+/// seconds have no such representation and fail to encode.
+pub mod timestamp {
+    use chrono::{DateTime, Timelike, Utc};
+    use serde::{de, ser, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(to_nanos(value).map_err(ser::Error::custom)?)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        let nanos = i64::deserialize(deserializer).map_err(|e| {
+            de::Error::custom(format!("timestamp must be integer nanoseconds: {e}"))
+        })?;
+        Ok(DateTime::from_timestamp_nanos(nanos))
+    }
+
+    /// The canonical integer for `value`.
+    pub fn to_nanos(value: &DateTime<Utc>) -> Result<i64, String> {
+        if value.nanosecond() >= 1_000_000_000 {
+            return Err(format!("leap second {value} has no canonical encoding"));
+        }
+        value
+            .timestamp_nanos_opt()
+            .ok_or_else(|| format!("timestamp {value} is outside the canonical range"))
+    }
+}
+
 /// Verify that CBOR bytes are in canonical form.
 ///
 /// Checks for:
@@ -101,7 +157,7 @@ fn verify_canonical_item<R: Read>(reader: &mut R) -> Result<()> {
 
     // Recursively verify based on major type
     match major_type {
-        0 | 1 | 7 => {}, // Unsigned int, negative int, simple/special - no nested data
+        0 | 1 | 7 => {} // Unsigned int, negative int, simple/special - no nested data
         2 | 3 => {
             // Byte string or text string - skip content
             let mut buf = vec![0u8; length];
@@ -172,6 +228,47 @@ mod tests {
         assert_eq!(obj, decoded);
     }
 
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Stamped {
+        #[serde(with = "timestamp")]
+        at: chrono::DateTime<chrono::Utc>,
+    }
+
+    #[test]
+    fn test_timestamp_encoding_is_fixed_and_round_trips() {
+        use chrono::{TimeZone, Utc};
+
+        // {"at": 1_735_689_600_123_456_789}
+        let at = Utc.timestamp_opt(1_735_689_600, 123_456_789).unwrap();
+        let bytes = to_canonical_cbor(&Stamped { at }).unwrap();
+        assert_eq!(
+            bytes,
+            [
+                &[0xa1, 0x62, b'a', b't', 0x1b][..],
+                &1_735_689_600_123_456_789u64.to_be_bytes()
+            ]
+            .concat()
+        );
+        assert_eq!(from_canonical_cbor::<Stamped>(&bytes).unwrap().at, at);
+
+        // Whole seconds and pre-epoch instants use the same integer form
+        for at in [
+            Utc.timestamp_opt(1_735_689_600, 0).unwrap(),
+            Utc.timestamp_opt(-1, 5).unwrap(),
+        ] {
+            let bytes = to_canonical_cbor(&Stamped { at }).unwrap();
+            assert_eq!(from_canonical_cbor::<Stamped>(&bytes).unwrap().at, at);
+        }
+
+        // No canonical form: leap seconds and out-of-range instants; RFC 3339 text is refused
+        let leap = Utc.timestamp_opt(1_483_228_799, 1_500_000_000).unwrap();
+        assert!(to_canonical_cbor(&Stamped { at: leap }).is_err());
+        let far = Utc.with_ymd_and_hms(2300, 1, 1, 0, 0, 0).unwrap();
+        assert!(to_canonical_cbor(&Stamped { at: far }).is_err());
+        let text = to_canonical_cbor(&BTreeMap::from([("at", "2025-01-01T00:00:00Z")])).unwrap();
+        assert!(from_canonical_cbor::<Stamped>(&text).is_err());
+    }
+
     #[test]
     fn test_map_key_ordering() {
         // BTreeMap ensures sorted keys, which ciborium preserves
@@ -206,6 +303,9 @@ mod tests {
         let bytes2 = to_canonical_cbor(&obj).unwrap();
         let hash2 = Sha256::digest(&bytes2);
 
-        assert_eq!(hash1, hash2, "Hashes must be identical for canonical serialization");
+        assert_eq!(
+            hash1, hash2,
+            "Hashes must be identical for canonical serialization"
+        );
     }
 }
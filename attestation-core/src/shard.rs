@@ -0,0 +1,653 @@
+//! Time-sharded checkpoint storage.
+//!
+//! A fleet's checkpoints accumulate without bound, and whole-store
+//! operations (integrity checks, pruning, moving old data to cold storage)
+//! get slower with every day of history. A [`ShardedStore`] files each
+//! checkpoint, and the stored entries behind it, into the shard of the time
+//! window its `local_timestamp_utc` falls in. Each shard has a Merkle root
+//! over its checkpoints, and the shard roots form a root-of-roots over the
+//! whole store, so:
+//!
+//! - a shard is verified on its own ([`Shard::root`], [`Shard::verify_entries`]);
+//! - an old shard is archived as one CBOR blob ([`ShardedStore::archive`]) or
+//!   pruned ([`ShardedStore::prune_before`]); its [`ShardSummary`] stays, so
+//!   the root-of-roots does not change and an archive brought back later is
+//!   checked against it ([`ShardedStore::verify_archive`]);
+//! - a checkpoint is shown to be in the store by a [`ShardProof`].
+//!
+//! A checkpoint arriving for a window whose shard was already sealed opens
+//! a new generation of that window rather than reopening the sealed one.
+//!
+//! ## Hashing
+//! - Shard leaf: the checkpoint hash; leaves ordered by robot, then sequence
+//! - Root-of-roots leaf: `SHA-256(canonical CBOR of (id, root, checkpoint_count))`
+//! - Nodes and odd levels as in [`crate::merkle`], always with SHA-256
+
+use crate::backfill::CheckpointStore;
+use crate::chain::VerifiedCheckpoint;
+use crate::checkpoint::Checkpoint;
+use crate::crypto::{ct_eq, sha256, DigestAlgorithm};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::merkle::{compute_merkle_root, compute_proof_siblings, reconstruct_root};
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::tombstone::StoredEntry;
+use crate::types::{Hash256, RobotId};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use thiserror::Error;
+
+/// Identifies one shard: its window and, for checkpoints that arrived after
+/// the window was sealed, its generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ShardId {
+    /// Start of the window, in seconds since the Unix epoch
+    pub window_start: i64,
+    pub generation: u32,
+}
+
+impl fmt::Display for ShardId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.window_start, self.generation)
+    }
+}
+
+/// Whether a shard's contents are still in the store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShardState {
+    /// Held by the store and accepting checkpoints
+    Live,
+    /// Exported with [`ShardedStore::archive`]
+    Archived,
+    /// Dropped with [`ShardedStore::prune_before`]
+    Pruned,
+}
+
+/// What the store keeps of a shard, whether or not it holds the contents.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardSummary {
+    pub id: ShardId,
+    pub root: Hash256,
+    pub checkpoint_count: u64,
+    pub entry_count: u64,
+    pub state: ShardState,
+}
+
+impl ShardSummary {
+    /// Leaf hash in the root-of-roots tree; the state is not part of it.
+    pub fn leaf_hash(&self) -> Result<Hash256, SerializationError> {
+        summary_leaf(self.id, self.root, self.checkpoint_count)
+    }
+}
+
+/// The checkpoints of one window, with the stored entries behind them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Shard {
+    pub id: ShardId,
+    /// Ordered by robot, then sequence
+    checkpoints: Vec<Checkpoint>,
+    /// Stored entries in tree order, by checkpoint hash
+    entries: BTreeMap<Hash256, Vec<StoredEntry>>,
+}
+
+impl Shard {
+    fn new(id: ShardId) -> Self {
+        Self {
+            id,
+            checkpoints: Vec::new(),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+
+    /// Stored entries of the checkpoint with `checkpoint_hash`, if kept.
+    pub fn entries(&self, checkpoint_hash: &Hash256) -> Option<&[StoredEntry]> {
+        self.entries.get(checkpoint_hash).map(Vec::as_slice)
+    }
+
+    /// Merkle root over the shard's checkpoint hashes (zero hash if empty).
+    pub fn root(&self) -> Result<Hash256, ShardError> {
+        Ok(compute_merkle_root(
+            DigestAlgorithm::Sha256,
+            &self.leaf_hashes()?,
+        ))
+    }
+
+    /// Check that the stored entries of every checkpoint reproduce its
+    /// entries_root and belong to a checkpoint of this shard.
+    pub fn verify_entries(&self) -> Result<(), ShardError> {
+        let mut checked = 0;
+        for checkpoint in &self.checkpoints {
+            let Some(entries) = self.entries.get(&checkpoint.compute_hash()?) else {
+                continue;
+            };
+            let leaves: Vec<Hash256> = entries.iter().map(StoredEntry::leaf_hash).collect();
+            if !ct_eq(
+                &compute_merkle_root(DigestAlgorithm::Sha256, &leaves),
+                &checkpoint.entries_root,
+            ) {
+                return Err(ShardError::EntriesMismatch(checkpoint.sequence));
+            }
+            checked += 1;
+        }
+        if checked != self.entries.len() {
+            return Err(ShardError::RootMismatch(self.id));
+        }
+        Ok(())
+    }
+
+    /// Summary of the shard in `state`.
+    pub fn summary(&self, state: ShardState) -> Result<ShardSummary, ShardError> {
+        Ok(ShardSummary {
+            id: self.id,
+            root: self.root()?,
+            checkpoint_count: self.checkpoints.len() as u64,
+            entry_count: self
+                .entries
+                .values()
+                .map(|entries| entries.len() as u64)
+                .sum(),
+            state,
+        })
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    fn position(&self, robot_id: &RobotId, sequence: u64) -> Result<usize, usize> {
+        self.checkpoints.binary_search_by(|c| {
+            (c.robot_id.0.as_str(), c.sequence).cmp(&(robot_id.0.as_str(), sequence))
+        })
+    }
+
+    fn leaf_hashes(&self) -> Result<Vec<Hash256>, SerializationError> {
+        self.checkpoints
+            .iter()
+            .map(Checkpoint::compute_hash)
+            .collect()
+    }
+}
+
+/// Checkpoint store sharded by time window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardedStore {
+    /// Window length in seconds
+    window_secs: i64,
+    /// Live shards, by window start
+    live: BTreeMap<i64, Shard>,
+    /// Summaries of archived and pruned shards
+    sealed: BTreeMap<ShardId, ShardSummary>,
+}
+
+impl ShardedStore {
+    /// Shard by windows of `window` (at least one second).
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window_secs: window.num_seconds().max(1),
+            live: BTreeMap::new(),
+            sealed: BTreeMap::new(),
+        }
+    }
+
+    /// Start of the window `at` falls in.
+    pub fn window_start(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let start = at.timestamp().div_euclid(self.window_secs) * self.window_secs;
+        DateTime::from_timestamp(start, 0).unwrap_or(at)
+    }
+
+    /// The live shard of the window starting at `window_start`.
+    pub fn shard(&self, window_start: DateTime<Utc>) -> Option<&Shard> {
+        self.live.get(&window_start.timestamp())
+    }
+
+    /// Summaries of every shard, live or sealed, in id order.
+    pub fn summaries(&self) -> Result<Vec<ShardSummary>, ShardError> {
+        let mut summaries: Vec<ShardSummary> = self.sealed.values().cloned().collect();
+        for shard in self.live.values() {
+            summaries.push(shard.summary(ShardState::Live)?);
+        }
+        summaries.sort_by_key(|summary| summary.id);
+        Ok(summaries)
+    }
+
+    /// Root over all shard summaries (zero hash if empty).
+    ///
+    /// Archiving or pruning a shard leaves it unchanged.
+    pub fn root_of_roots(&self) -> Result<Hash256, ShardError> {
+        let leaves = self.summary_leaves(&self.summaries()?)?;
+        Ok(compute_merkle_root(DigestAlgorithm::Sha256, &leaves))
+    }
+
+    /// Keep the stored entries of `checkpoint`, which must be in a live shard.
+    ///
+    /// The entries, in tree order, must reproduce its entries_root.
+    pub fn insert_entries(
+        &mut self,
+        checkpoint: &Checkpoint,
+        entries: Vec<StoredEntry>,
+    ) -> Result<(), ShardError> {
+        let start = self
+            .window_start(checkpoint.local_timestamp_utc)
+            .timestamp();
+        let shard = self
+            .live
+            .get_mut(&start)
+            .filter(|shard| {
+                shard
+                    .position(&checkpoint.robot_id, checkpoint.sequence)
+                    .is_ok()
+            })
+            .ok_or(ShardError::UnknownCheckpoint(checkpoint.sequence))?;
+        let leaves: Vec<Hash256> = entries.iter().map(StoredEntry::leaf_hash).collect();
+        if !ct_eq(
+            &compute_merkle_root(DigestAlgorithm::Sha256, &leaves),
+            &checkpoint.entries_root,
+        ) {
+            return Err(ShardError::EntriesMismatch(checkpoint.sequence));
+        }
+        shard.entries.insert(checkpoint.compute_hash()?, entries);
+        Ok(())
+    }
+
+    /// Stored entries of `checkpoint`, if its shard is live and keeps them.
+    pub fn entries(&self, checkpoint: &Checkpoint) -> Option<&[StoredEntry]> {
+        let shard = self.shard(self.window_start(checkpoint.local_timestamp_utc))?;
+        shard.entries(&checkpoint.compute_hash().ok()?)
+    }
+
+    /// Seal the live shard of the window starting at `window_start` and
+    /// return it as CBOR, for cold storage.
+    pub fn archive(&mut self, window_start: DateTime<Utc>) -> Result<Vec<u8>, ShardError> {
+        let start = window_start.timestamp();
+        let shard = self
+            .live
+            .get(&start)
+            .ok_or(ShardError::UnknownWindow(start))?;
+        let bytes = shard.to_bytes()?;
+        let summary = shard.summary(ShardState::Archived)?;
+        self.live.remove(&start);
+        self.sealed.insert(summary.id, summary);
+        Ok(bytes)
+    }
+
+    /// Drop the live shards of windows that end by `before`, keeping their
+    /// summaries. Returns the ids of the dropped shards.
+    pub fn prune_before(&mut self, before: DateTime<Utc>) -> Result<Vec<ShardId>, ShardError> {
+        let due: Vec<i64> = self
+            .live
+            .keys()
+            .copied()
+            .filter(|start| start + self.window_secs <= before.timestamp())
+            .collect();
+        let mut pruned = Vec::with_capacity(due.len());
+        for start in due {
+            let summary = self.live[&start].summary(ShardState::Pruned)?;
+            self.live.remove(&start);
+            pruned.push(summary.id);
+            self.sealed.insert(summary.id, summary);
+        }
+        Ok(pruned)
+    }
+
+    /// Check an archived shard against the summary kept for it, and return
+    /// its contents.
+    pub fn verify_archive(&self, bytes: &[u8]) -> Result<Shard, ShardError> {
+        let shard = Shard::from_bytes(bytes)?;
+        let recorded = self
+            .sealed
+            .get(&shard.id)
+            .ok_or(ShardError::UnknownShard(shard.id))?;
+        let actual = shard.summary(recorded.state)?;
+        if actual != *recorded {
+            return Err(ShardError::RootMismatch(shard.id));
+        }
+        shard.verify_entries()?;
+        Ok(shard)
+    }
+
+    /// Prove that a checkpoint of a live shard is in the store.
+    ///
+    /// Returns `Ok(None)` if no live shard holds it.
+    pub fn prove(&self, checkpoint: &Checkpoint) -> Result<Option<ShardProof>, ShardError> {
+        let Some(shard) = self.shard(self.window_start(checkpoint.local_timestamp_utc)) else {
+            return Ok(None);
+        };
+        let Ok(index) = shard.position(&checkpoint.robot_id, checkpoint.sequence) else {
+            return Ok(None);
+        };
+        let leaves = shard.leaf_hashes()?;
+        let summaries = self.summaries()?;
+        let shard_index = summaries
+            .iter()
+            .position(|summary| summary.id == shard.id)
+            .expect("live shards are summarized");
+        let summary_leaves = self.summary_leaves(&summaries)?;
+        Ok(Some(ShardProof {
+            checkpoint_hash: leaves[index],
+            index,
+            siblings: compute_proof_siblings(DigestAlgorithm::Sha256, &leaves, index),
+            shard_id: shard.id,
+            shard_root: compute_merkle_root(DigestAlgorithm::Sha256, &leaves),
+            checkpoint_count: leaves.len() as u64,
+            shard_index,
+            shard_siblings: compute_proof_siblings(
+                DigestAlgorithm::Sha256,
+                &summary_leaves,
+                shard_index,
+            ),
+            root_of_roots: compute_merkle_root(DigestAlgorithm::Sha256, &summary_leaves),
+        }))
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    fn summary_leaves(
+        &self,
+        summaries: &[ShardSummary],
+    ) -> Result<Vec<Hash256>, SerializationError> {
+        summaries.iter().map(ShardSummary::leaf_hash).collect()
+    }
+}
+
+/// Live shards only: archived and pruned checkpoints are no longer returned.
+impl CheckpointStore for ShardedStore {
+    fn checkpoints(&self, robot_id: &RobotId) -> Vec<Checkpoint> {
+        let mut checkpoints: Vec<Checkpoint> = self
+            .live
+            .values()
+            .flat_map(|shard| {
+                shard
+                    .checkpoints
+                    .iter()
+                    .filter(|c| &c.robot_id == robot_id)
+                    .cloned()
+            })
+            .collect();
+        checkpoints.sort_by_key(|c| c.sequence);
+        checkpoints
+    }
+
+    fn insert(&mut self, checkpoint: VerifiedCheckpoint) {
+        let start = self
+            .window_start(checkpoint.local_timestamp_utc)
+            .timestamp();
+        let generation = self
+            .sealed
+            .range(
+                ShardId {
+                    window_start: start,
+                    generation: 0,
+                }..,
+            )
+            .take_while(|(id, _)| id.window_start == start)
+            .count() as u32;
+        let shard = self.live.entry(start).or_insert_with(|| {
+            Shard::new(ShardId {
+                window_start: start,
+                generation,
+            })
+        });
+        let checkpoint = checkpoint.into_inner();
+        match shard.position(&checkpoint.robot_id, checkpoint.sequence) {
+            Ok(index) => shard.checkpoints[index] = checkpoint,
+            Err(index) => shard.checkpoints.insert(index, checkpoint),
+        }
+    }
+
+    fn stored_entries(&self, checkpoint: &Checkpoint) -> Option<&[StoredEntry]> {
+        self.entries(checkpoint)
+    }
+
+    fn drop_entries(&mut self, checkpoint: &Checkpoint) -> Option<Vec<StoredEntry>> {
+        let start = self
+            .window_start(checkpoint.local_timestamp_utc)
+            .timestamp();
+        let hash = checkpoint.compute_hash().ok()?;
+        self.live.get_mut(&start)?.entries.remove(&hash)
+    }
+}
+
+/// Proof that a checkpoint is in a shard, and the shard in the store.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardProof {
+    pub checkpoint_hash: Hash256,
+    /// Position of the checkpoint in its shard
+    pub index: usize,
+    pub siblings: Vec<Hash256>,
+    pub shard_id: ShardId,
+    pub shard_root: Hash256,
+    pub checkpoint_count: u64,
+    /// Position of the shard among all shard summaries
+    pub shard_index: usize,
+    pub shard_siblings: Vec<Hash256>,
+    pub root_of_roots: Hash256,
+}
+
+impl ShardProof {
+    /// Verify that `checkpoint` is in the store with root-of-roots
+    /// `expected_root`.
+    pub fn verify(&self, checkpoint: &Checkpoint, expected_root: &Hash256) -> bool {
+        if !ct_eq(&self.root_of_roots, expected_root)
+            || !checkpoint
+                .compute_hash()
+                .is_ok_and(|hash| ct_eq(&hash, &self.checkpoint_hash))
+        {
+            return false;
+        }
+        let shard_root = reconstruct_root(
+            DigestAlgorithm::Sha256,
+            self.checkpoint_hash,
+            self.index,
+            &self.siblings,
+        );
+        if !ct_eq(&shard_root, &self.shard_root) {
+            return false;
+        }
+        let Ok(leaf) = summary_leaf(self.shard_id, self.shard_root, self.checkpoint_count) else {
+            return false;
+        };
+        ct_eq(
+            &reconstruct_root(
+                DigestAlgorithm::Sha256,
+                leaf,
+                self.shard_index,
+                &self.shard_siblings,
+            ),
+            expected_root,
+        )
+    }
+}
+
+fn summary_leaf(
+    id: ShardId,
+    root: Hash256,
+    checkpoint_count: u64,
+) -> Result<Hash256, SerializationError> {
+    Ok(sha256(&to_canonical_cbor(&(id, root, checkpoint_count))?))
+}
+
+#[derive(Debug, Error)]
+pub enum ShardError {
+    #[error("No live shard for the window starting at {0}")]
+    UnknownWindow(i64),
+
+    #[error("No sealed shard {0}")]
+    UnknownShard(ShardId),
+
+    #[error("Checkpoint #{0} is not in a live shard")]
+    UnknownCheckpoint(u64),
+
+    #[error("Contents of shard {0} do not reproduce its recorded root")]
+    RootMismatch(ShardId),
+
+    #[error("Stored entries do not reproduce the entries_root of checkpoint #{0}")]
+    EntriesMismatch(u64),
+
+    #[error("Serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+}
+
+impl ErrorCoded for ShardError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ShardError::UnknownWindow(_)
+            | ShardError::UnknownShard(_)
+            | ShardError::UnknownCheckpoint(_) => ErrorCode::ShardUnknown,
+            ShardError::RootMismatch(_) => ErrorCode::ShardRootMismatch,
+            ShardError::EntriesMismatch(_) => ErrorCode::ShardEntriesMismatch,
+            ShardError::Serialization(e) => e.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::{ChainHead, ChainVerifier};
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::crypto::Signer;
+    use crate::merkle::{Entry, MerkleTree};
+
+    /// Six hourly checkpoints of two entries each, from 00:30 on day 20000.
+    fn checkpoints(signer: &Signer) -> Vec<(Checkpoint, Vec<StoredEntry>)> {
+        let start = DateTime::UNIX_EPOCH + Duration::days(20_000) + Duration::minutes(30);
+        let mut out: Vec<(Checkpoint, Vec<StoredEntry>)> = Vec::new();
+        for sequence in 0..6u64 {
+            let mut tree = MerkleTree::new();
+            let payloads: Vec<Vec<u8>> = (0..2u8).map(|i| vec![sequence as u8, i]).collect();
+            for (i, payload) in payloads.iter().enumerate() {
+                tree.insert(Entry::new(1000 * (2 * sequence + i as u64), 0, payload));
+            }
+            let builder = match out.last() {
+                Some((prev, _)) => CheckpointBuilder::continuing_from(prev).unwrap(),
+                None => CheckpointBuilder::test_fixture(),
+            };
+            let checkpoint = builder
+                .monotonic_counter(sequence + 1)
+                .timestamp(start + Duration::hours(sequence as i64))
+                .entries_root(tree.root())
+                .build_and_sign(signer.signing_key())
+                .unwrap();
+            let entries = tree
+                .entries()
+                .into_iter()
+                .zip(payloads)
+                .map(|(entry, payload)| StoredEntry::Live {
+                    entry: entry.clone(),
+                    payload,
+                })
+                .collect();
+            out.push((checkpoint, entries));
+        }
+        out
+    }
+
+    /// Store of the test checkpoints, in two-hour shards.
+    fn store(signer: &Signer) -> (ShardedStore, Vec<Checkpoint>) {
+        let mut store = ShardedStore::new(Duration::hours(2));
+        let mut verifier = ChainVerifier::new(signer.verifying_key());
+        let mut all = Vec::new();
+        for (checkpoint, entries) in checkpoints(signer) {
+            store.insert(verifier.accept(checkpoint.clone()).unwrap());
+            store.insert_entries(&checkpoint, entries).unwrap();
+            all.push(checkpoint);
+        }
+        (store, all)
+    }
+
+    #[test]
+    fn test_shards_archive_and_prune_without_changing_root_of_roots() {
+        let signer = Signer::generate();
+        let (mut store, checkpoints) = store(&signer);
+        let robot_id = &checkpoints[0].robot_id;
+        let summaries = store.summaries().unwrap();
+        assert_eq!(summaries.len(), 3);
+        assert!(summaries
+            .iter()
+            .all(|s| s.checkpoint_count == 2 && s.entry_count == 4));
+        let root = store.root_of_roots().unwrap();
+
+        // Entries that do not match a checkpoint are refused
+        let err = store
+            .insert_entries(&checkpoints[0], Vec::new())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ShardEntriesMismatch);
+
+        // Archive the first window and prune the second
+        let first = store.window_start(checkpoints[0].local_timestamp_utc);
+        let archive = store.archive(first).unwrap();
+        assert_eq!(
+            store
+                .prune_before(first + Duration::hours(4))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(store.root_of_roots().unwrap(), root);
+        assert_eq!(store.checkpoints(robot_id).len(), 2);
+        assert!(store.entries(&checkpoints[0]).is_none());
+        assert_eq!(store.entries(&checkpoints[5]).unwrap().len(), 2);
+
+        // The archive checks out against its kept summary; a tampered one does not
+        let shard = store.verify_archive(&archive).unwrap();
+        assert_eq!(shard.checkpoints(), &checkpoints[..2]);
+        let mut tampered = Shard::from_bytes(&archive).unwrap();
+        tampered.checkpoints.pop();
+        let err = store
+            .verify_archive(&tampered.to_bytes().unwrap())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ShardRootMismatch);
+
+        // A late checkpoint for a sealed window opens a new generation
+        let head = ChainHead::from_checkpoint(&checkpoints[0]).unwrap();
+        let late = ChainVerifier::new(signer.verifying_key())
+            .resume_from(head)
+            .accept(checkpoints[1].clone())
+            .unwrap();
+        store.insert(late);
+        let ids: Vec<ShardId> = store.summaries().unwrap().iter().map(|s| s.id).collect();
+        assert_eq!(ids[0].window_start, ids[1].window_start);
+        assert_eq!((ids[0].generation, ids[1].generation), (0, 1));
+        assert_ne!(store.root_of_roots().unwrap(), root);
+
+        let decoded = ShardedStore::from_bytes(&store.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, store);
+    }
+
+    #[test]
+    fn test_checkpoint_proven_through_shard_root() {
+        let signer = Signer::generate();
+        let (store, checkpoints) = store(&signer);
+        let root = store.root_of_roots().unwrap();
+
+        for checkpoint in &checkpoints {
+            let proof = store.prove(checkpoint).unwrap().unwrap();
+            assert!(proof.verify(checkpoint, &root));
+        }
+        let proof = store.prove(&checkpoints[3]).unwrap().unwrap();
+        assert!(!proof.verify(&checkpoints[2], &root));
+        let mut forged = proof.clone();
+        forged.checkpoint_count += 1;
+        assert!(!forged.verify(&checkpoints[3], &root));
+    }
+}
@@ -0,0 +1,627 @@
+//! Robot-side persistent spooling of outbound evidence.
+//!
+//! A robot keeps producing checkpoints while its uplink is down. A
+//! [`Spool`] writes each outbound item (a checkpoint, or any other envelope
+//! bound for the gateway) to its own file in a spool directory before it is
+//! sent, and deletes it only once a [`SpoolTransport`] has delivered it, so
+//! the backlog survives reboots and is drained in order when connectivity
+//! returns ([`Spool::drain`]).
+//!
+//! - **Bounded**: [`SpoolLimits`] caps the backlog by records and bytes; a
+//!   full spool rejects new items with [`SpoolError::Full`] instead of
+//!   dropping old ones.
+//! - **Integrity-checked**: every record carries the SHA-256 of its payload
+//!   and is written to a temporary file, synced and renamed into place.
+//!   Records that fail to decode or verify, when the spool is opened or when
+//!   they come up for delivery, are moved to `quarantine/` and counted in
+//!   [`SpoolBacklog::quarantined`], never deleted. Ids are never reused, so
+//!   a quarantined record is never overwritten.
+//! - **Observable**: [`Spool::backlog`] reports depth, size and the age of
+//!   the oldest pending item for health reporting.
+
+use crate::attestation::RetryPolicy;
+use crate::checkpoint::Checkpoint;
+use crate::clock::{system_clock, Clock};
+use crate::crypto::{ct_eq, sha256};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::Hash256;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Spool record version (for schema evolution)
+pub const SPOOL_RECORD_VERSION: u8 = 1;
+
+/// Extension of committed record files.
+const RECORD_EXTENSION: &str = "spool";
+/// Extension of records still being written.
+const TEMP_EXTENSION: &str = "tmp";
+/// Subdirectory for records that failed their integrity check.
+const QUARANTINE_DIR: &str = "quarantine";
+/// File holding the next id, written when the spool empties so ids keep
+/// increasing once no record is left to recover them from.
+const NEXT_ID_FILE: &str = "next_id";
+
+/// What a spooled payload is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpoolKind {
+    /// Canonical CBOR of a [`Checkpoint`]
+    Checkpoint,
+    /// Any other message for the gateway (entries, attestation results, ...)
+    Envelope,
+}
+
+/// One spooled item, as stored on disk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpoolRecord {
+    /// Schema version
+    pub version: u8,
+    /// Position in the spool; delivery follows id order
+    pub id: u64,
+    pub kind: SpoolKind,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub enqueued_at: DateTime<Utc>,
+    /// Failed delivery attempts so far
+    pub attempts: u32,
+    pub payload: Vec<u8>,
+    /// SHA-256 of `payload`
+    pub digest: Hash256,
+}
+
+impl SpoolRecord {
+    /// Whether the payload matches its digest.
+    pub fn is_intact(&self) -> bool {
+        ct_eq(&sha256(&self.payload), &self.digest)
+    }
+}
+
+/// Delivers spooled items to the gateway (e.g., an HTTP or MQTT client).
+#[async_trait]
+pub trait SpoolTransport: Send + Sync {
+    /// Deliver `record`; an error leaves it spooled for a later attempt.
+    async fn deliver(&self, record: &SpoolRecord) -> Result<(), SpoolError>;
+}
+
+/// Backlog bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpoolLimits {
+    pub max_records: usize,
+    /// Payload bytes across all pending records
+    pub max_bytes: u64,
+}
+
+impl Default for SpoolLimits {
+    fn default() -> Self {
+        Self {
+            max_records: 100_000,
+            max_bytes: 1 << 30,
+        }
+    }
+}
+
+/// Backlog depth, for health reporting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpoolBacklog {
+    pub records: usize,
+    /// Payload bytes across all pending records
+    pub bytes: u64,
+    pub oldest_enqueued_at: Option<DateTime<Utc>>,
+    /// Records set aside as corrupt
+    pub quarantined: usize,
+}
+
+/// Outcome of [`Spool::drain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrainReport {
+    /// Ids delivered and removed, in order
+    pub delivered: Vec<u64>,
+    /// Ids that failed their integrity check and were quarantined
+    pub quarantined: Vec<u64>,
+    /// Record that failed, with its attempt count and the error
+    pub failed: Option<(u64, u32, String)>,
+    /// When to drain again after a failure
+    pub retry_after: Option<Duration>,
+    pub backlog: SpoolBacklog,
+}
+
+/// Pending record: payload size and enqueue time.
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    bytes: u64,
+    enqueued_at: DateTime<Utc>,
+}
+
+/// Directory-backed FIFO of outbound items.
+pub struct Spool {
+    dir: PathBuf,
+    limits: SpoolLimits,
+    backoff: RetryPolicy,
+    clock: Arc<dyn Clock>,
+    pending: BTreeMap<u64, Pending>,
+    next_id: u64,
+    quarantined: usize,
+}
+
+impl Spool {
+    /// Open (or create) the spool in `dir`, recovering its backlog.
+    ///
+    /// Half-written records from an interrupted enqueue are removed (that
+    /// enqueue never returned); records that fail their integrity check are
+    /// moved to `quarantine/`.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, SpoolError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let mut spool = Self {
+            dir,
+            limits: SpoolLimits::default(),
+            backoff: RetryPolicy::default(),
+            clock: system_clock(),
+            pending: BTreeMap::new(),
+            next_id: 0,
+            quarantined: 0,
+        };
+        spool.recover()?;
+        Ok(spool)
+    }
+
+    /// Bound the backlog by `limits`.
+    pub fn with_limits(mut self, limits: SpoolLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Space drain retries by `policy`'s backoff. Its `max_attempts` is
+    /// ignored: spooled evidence is retried until delivered.
+    pub fn with_backoff(mut self, policy: RetryPolicy) -> Self {
+        self.backoff = policy;
+        self
+    }
+
+    /// Read enqueue times from `clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Persist `payload` for delivery, returning its id.
+    pub fn enqueue(&mut self, kind: SpoolKind, payload: &[u8]) -> Result<u64, SpoolError> {
+        let backlog = self.backlog();
+        if backlog.records >= self.limits.max_records
+            || backlog.bytes + payload.len() as u64 > self.limits.max_bytes
+        {
+            return Err(SpoolError::Full {
+                records: backlog.records,
+                bytes: backlog.bytes,
+            });
+        }
+
+        let record = SpoolRecord {
+            version: SPOOL_RECORD_VERSION,
+            id: self.next_id,
+            kind,
+            enqueued_at: self.clock.now(),
+            attempts: 0,
+            payload: payload.to_vec(),
+            digest: sha256(payload),
+        };
+        self.write(&record)?;
+        self.pending.insert(
+            record.id,
+            Pending {
+                bytes: payload.len() as u64,
+                enqueued_at: record.enqueued_at,
+            },
+        );
+        self.next_id += 1;
+        Ok(record.id)
+    }
+
+    /// Persist a checkpoint for delivery.
+    pub fn enqueue_checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<u64, SpoolError> {
+        self.enqueue(SpoolKind::Checkpoint, &checkpoint.to_bytes()?)
+    }
+
+    /// The oldest pending record, if any.
+    pub fn peek(&self) -> Result<Option<SpoolRecord>, SpoolError> {
+        match self.pending.keys().next() {
+            Some(id) => self.read(*id).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove a delivered record.
+    pub fn ack(&mut self, id: u64) -> Result<(), SpoolError> {
+        if self.pending.remove(&id).is_none() {
+            return Err(SpoolError::UnknownRecord(id));
+        }
+        if self.pending.is_empty() {
+            self.save_next_id()?;
+        }
+        fs::remove_file(self.record_path(id))?;
+        Ok(())
+    }
+
+    /// Count a failed delivery of `id`, returning its attempts so far.
+    pub fn record_failure(&mut self, id: u64) -> Result<u32, SpoolError> {
+        if !self.pending.contains_key(&id) {
+            return Err(SpoolError::UnknownRecord(id));
+        }
+        let mut record = self.read(id)?;
+        record.attempts = record.attempts.saturating_add(1);
+        self.write(&record)?;
+        Ok(record.attempts)
+    }
+
+    /// Deliver pending records in order through `transport`, up to `max`.
+    ///
+    /// Stops at the first failure so later records never overtake an
+    /// earlier one; the report says when to try again. A record that no
+    /// longer passes its integrity check is quarantined and skipped.
+    pub async fn drain(
+        &mut self,
+        transport: &dyn SpoolTransport,
+        max: usize,
+    ) -> Result<DrainReport, SpoolError> {
+        let mut delivered = Vec::new();
+        let mut quarantined = Vec::new();
+        let mut failed = None;
+        while delivered.len() < max {
+            let Some(&id) = self.pending.keys().next() else {
+                break;
+            };
+            let record = match self.read(id) {
+                Ok(record) => record,
+                Err(SpoolError::Io(e)) => return Err(e.into()),
+                Err(_) => {
+                    self.pending.remove(&id);
+                    self.quarantine(&self.record_path(id))?;
+                    quarantined.push(id);
+                    continue;
+                }
+            };
+            match transport.deliver(&record).await {
+                Ok(()) => {
+                    self.ack(record.id)?;
+                    delivered.push(record.id);
+                }
+                Err(e) => {
+                    let attempts = self.record_failure(record.id)?;
+                    failed = Some((record.id, attempts, e.to_string()));
+                    break;
+                }
+            }
+        }
+        Ok(DrainReport {
+            retry_after: failed
+                .as_ref()
+                .map(|(_, attempts, _)| self.backoff.backoff(*attempts)),
+            delivered,
+            quarantined,
+            failed,
+            backlog: self.backlog(),
+        })
+    }
+
+    /// Current backlog.
+    pub fn backlog(&self) -> SpoolBacklog {
+        SpoolBacklog {
+            records: self.pending.len(),
+            bytes: self.pending.values().map(|pending| pending.bytes).sum(),
+            oldest_enqueued_at: self
+                .pending
+                .values()
+                .next()
+                .map(|pending| pending.enqueued_at),
+            quarantined: self.quarantined,
+        }
+    }
+
+    fn record_path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{id:020}.{RECORD_EXTENSION}"))
+    }
+
+    fn read(&self, id: u64) -> Result<SpoolRecord, SpoolError> {
+        let record: SpoolRecord = from_canonical_cbor(&fs::read(self.record_path(id))?)?;
+        if record.id != id || !record.is_intact() {
+            return Err(SpoolError::Corrupt(id));
+        }
+        Ok(record)
+    }
+
+    /// Write `record` atomically: temporary file, sync, rename.
+    fn write(&self, record: &SpoolRecord) -> Result<(), SpoolError> {
+        write_atomically(&self.record_path(record.id), &to_canonical_cbor(record)?)
+    }
+
+    fn save_next_id(&self) -> Result<(), SpoolError> {
+        write_atomically(
+            &self.dir.join(NEXT_ID_FILE),
+            self.next_id.to_string().as_bytes(),
+        )
+    }
+
+    /// Move the record file at `path` to `quarantine/`.
+    fn quarantine(&mut self, path: &Path) -> Result<(), SpoolError> {
+        let quarantine = self.dir.join(QUARANTINE_DIR);
+        fs::create_dir_all(&quarantine)?;
+        fs::rename(path, quarantine.join(path.file_name().unwrap_or_default()))?;
+        self.count_quarantined(path);
+        Ok(())
+    }
+
+    /// Count a quarantined record file, and keep its id from being reused.
+    fn count_quarantined(&mut self, path: &Path) {
+        self.quarantined += 1;
+        if let Some(id) = record_id(path) {
+            self.next_id = self.next_id.max(id.saturating_add(1));
+        }
+    }
+
+    fn recover(&mut self) -> Result<(), SpoolError> {
+        // Ids keep increasing across reboots, past quarantined records and
+        // past a drained spool's last record too
+        let saved = fs::read_to_string(self.dir.join(NEXT_ID_FILE)).ok();
+        self.next_id = saved
+            .and_then(|saved| saved.trim().parse().ok())
+            .unwrap_or(0);
+        let quarantine = self.dir.join(QUARANTINE_DIR);
+        if quarantine.is_dir() {
+            for dir_entry in fs::read_dir(&quarantine)? {
+                self.count_quarantined(&dir_entry?.path());
+            }
+        }
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            let extension = path.extension().and_then(|e| e.to_str());
+            if extension == Some(TEMP_EXTENSION) {
+                fs::remove_file(&path)?;
+                continue;
+            }
+            if extension != Some(RECORD_EXTENSION) {
+                continue;
+            }
+            match record_id(&path).map(|id| (id, self.read(id))) {
+                Some((id, Ok(record))) => {
+                    self.pending.insert(
+                        id,
+                        Pending {
+                            bytes: record.payload.len() as u64,
+                            enqueued_at: record.enqueued_at,
+                        },
+                    );
+                }
+                Some((_, Err(SpoolError::Io(e)))) => return Err(e.into()),
+                _ => self.quarantine(&path)?,
+            }
+        }
+        self.next_id = self
+            .pending
+            .keys()
+            .next_back()
+            .map_or(0, |id| id + 1)
+            .max(self.next_id);
+        Ok(())
+    }
+}
+
+/// The id a record file is named after.
+fn record_id(path: &Path) -> Option<u64> {
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+/// Write `bytes` to `path` atomically: temporary file, sync, rename.
+fn write_atomically(path: &Path, bytes: &[u8]) -> Result<(), SpoolError> {
+    let temp = path.with_extension(TEMP_EXTENSION);
+    let mut file = fs::File::create(&temp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+#[derive(Debug, Error)]
+pub enum SpoolError {
+    #[error("Spool I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Spool record serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Spool is full ({records} records, {bytes} bytes)")]
+    Full { records: usize, bytes: u64 },
+
+    #[error("Spool record {0} failed its integrity check")]
+    Corrupt(u64),
+
+    #[error("No pending spool record {0}")]
+    UnknownRecord(u64),
+
+    #[error("Transport error: {0}")]
+    Transport(String),
+}
+
+impl ErrorCoded for SpoolError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            SpoolError::Io(_) | SpoolError::Corrupt(_) => ErrorCode::SpoolStorageFailed,
+            SpoolError::Serialization(e) => e.code(),
+            SpoolError::Full { .. } => ErrorCode::SpoolFull,
+            SpoolError::UnknownRecord(_) => ErrorCode::SpoolRecordUnknown,
+            SpoolError::Transport(_) => ErrorCode::GatewayUnavailable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+
+    /// Gateway that can be taken offline.
+    #[derive(Default)]
+    struct Uplink {
+        offline: AtomicBool,
+        received: Mutex<Vec<Vec<u8>>>,
+    }
+
+    #[async_trait]
+    impl SpoolTransport for Uplink {
+        async fn deliver(&self, record: &SpoolRecord) -> Result<(), SpoolError> {
+            if self.offline.load(Ordering::SeqCst) {
+                return Err(SpoolError::Transport("no route to gateway".to_string()));
+            }
+            self.received.lock().unwrap().push(record.payload.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backlog_survives_reopen_and_drains_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let uplink = Uplink::default();
+        uplink.offline.store(true, Ordering::SeqCst);
+
+        let mut spool = Spool::open(dir.path()).unwrap();
+        for payload in [b"one".as_slice(), b"two", b"three"] {
+            spool.enqueue(SpoolKind::Envelope, payload).unwrap();
+        }
+        let report = spool.drain(&uplink, 10).await.unwrap();
+        assert!(report.delivered.is_empty());
+        assert_eq!(
+            report
+                .failed
+                .as_ref()
+                .map(|(id, attempts, _)| (*id, *attempts)),
+            Some((0, 1))
+        );
+        assert_eq!(report.retry_after, Some(RetryPolicy::default().backoff(1)));
+        drop(spool);
+
+        // Reboot: the backlog and attempt count are still there
+        let mut spool = Spool::open(dir.path()).unwrap();
+        assert_eq!(spool.backlog().records, 3);
+        assert_eq!(spool.backlog().bytes, 11);
+        assert_eq!(spool.peek().unwrap().unwrap().attempts, 1);
+        assert_eq!(spool.enqueue(SpoolKind::Envelope, b"four").unwrap(), 3);
+
+        uplink.offline.store(false, Ordering::SeqCst);
+        let report = spool.drain(&uplink, 10).await.unwrap();
+        assert_eq!(report.delivered, [0, 1, 2, 3]);
+        assert_eq!(report.backlog.records, 0);
+        assert_eq!(
+            *uplink.received.lock().unwrap(),
+            [
+                b"one".to_vec(),
+                b"two".to_vec(),
+                b"three".to_vec(),
+                b"four".to_vec()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_full_spool_rejects_and_corrupt_records_are_quarantined() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spool = Spool::open(dir.path()).unwrap().with_limits(SpoolLimits {
+            max_records: 2,
+            max_bytes: 1024,
+        });
+        spool.enqueue(SpoolKind::Envelope, b"first").unwrap();
+        spool.enqueue(SpoolKind::Envelope, b"second").unwrap();
+        let err = spool.enqueue(SpoolKind::Envelope, b"third").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SpoolFull);
+        drop(spool);
+
+        // Flip a payload byte of the first record; leave a half-written one behind
+        let path = dir.path().join(format!("{:020}.spool", 0));
+        let mut record: SpoolRecord = from_canonical_cbor(&fs::read(&path).unwrap()).unwrap();
+        record.payload[0] ^= 0xff;
+        fs::write(&path, to_canonical_cbor(&record).unwrap()).unwrap();
+        fs::write(dir.path().join(format!("{:020}.tmp", 2)), b"partial").unwrap();
+
+        let spool = Spool::open(dir.path()).unwrap();
+        let backlog = spool.backlog();
+        assert_eq!((backlog.records, backlog.quarantined), (1, 1));
+        assert_eq!(spool.peek().unwrap().unwrap().payload, b"second");
+        assert!(dir
+            .path()
+            .join(QUARANTINE_DIR)
+            .join(format!("{:020}.spool", 0))
+            .exists());
+        assert!(!dir.path().join(format!("{:020}.tmp", 2)).exists());
+    }
+
+    /// Flip a payload byte of record `id` in the spool at `dir`.
+    fn corrupt(dir: &Path, id: u64) {
+        let path = dir.join(format!("{id:020}.spool"));
+        let mut record: SpoolRecord = from_canonical_cbor(&fs::read(&path).unwrap()).unwrap();
+        record.payload[0] ^= 0xff;
+        fs::write(&path, to_canonical_cbor(&record).unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ids_not_reused_after_quarantine_or_full_drain() {
+        let dir = tempfile::tempdir().unwrap();
+        let uplink = Uplink::default();
+        let mut spool = Spool::open(dir.path()).unwrap();
+        for payload in [b"one".as_slice(), b"two", b"three"] {
+            spool.enqueue(SpoolKind::Envelope, payload).unwrap();
+        }
+        drop(spool);
+
+        // The highest record is quarantined on reopen; its id stays taken
+        corrupt(dir.path(), 2);
+        let mut spool = Spool::open(dir.path()).unwrap();
+        assert_eq!(spool.enqueue(SpoolKind::Envelope, b"four").unwrap(), 3);
+        assert_eq!(spool.drain(&uplink, 10).await.unwrap().delivered, [0, 1, 3]);
+        drop(spool);
+
+        // Nothing is left to recover ids from after a full drain
+        let mut spool = Spool::open(dir.path()).unwrap();
+        assert_eq!(spool.backlog().records, 0);
+        assert_eq!(spool.enqueue(SpoolKind::Envelope, b"five").unwrap(), 4);
+        let quarantined = dir
+            .path()
+            .join(QUARANTINE_DIR)
+            .join(format!("{:020}.spool", 2));
+        let record: SpoolRecord = from_canonical_cbor(&fs::read(quarantined).unwrap()).unwrap();
+        assert_eq!(record.id, 2);
+    }
+
+    #[tokio::test]
+    async fn test_record_corrupted_while_running_is_quarantined_by_drain() {
+        let dir = tempfile::tempdir().unwrap();
+        let uplink = Uplink::default();
+        let mut spool = Spool::open(dir.path()).unwrap();
+        for payload in [b"one".as_slice(), b"two", b"three"] {
+            spool.enqueue(SpoolKind::Envelope, payload).unwrap();
+        }
+        corrupt(dir.path(), 0);
+        assert!(matches!(spool.peek(), Err(SpoolError::Corrupt(0))));
+
+        // The rest is still delivered, in order
+        let report = spool.drain(&uplink, 10).await.unwrap();
+        assert_eq!(report.quarantined, [0]);
+        assert_eq!(report.delivered, [1, 2]);
+        assert_eq!((report.backlog.records, report.backlog.quarantined), (0, 1));
+        assert_eq!(
+            *uplink.received.lock().unwrap(),
+            [b"two".to_vec(), b"three".to_vec()]
+        );
+        assert!(dir
+            .path()
+            .join(QUARANTINE_DIR)
+            .join(format!("{:020}.spool", 0))
+            .exists());
+    }
+}
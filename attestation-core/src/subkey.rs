@@ -0,0 +1,346 @@
+//! Per-entry signatures from short-lived process sub-keys.
+//!
+//! A checkpoint proves that the robot's enclave key saw an entry, not which
+//! software component produced it. To attribute entries, the enclave key
+//! issues each component (planner, perception, teleop bridge, ...) a
+//! [`SubKeyCert`] for a short-lived key; the component signs the leaf hash of
+//! every entry it logs, and each checkpoint commits to the hashes of the
+//! certificates whose keys signed its entries (`sub_key_certs`).
+//!
+//! An [`EntryAttribution`] bundles one entry's inclusion proof, its signature
+//! and the certificate, and verifies the full link: entry -> entries_root,
+//! certificate committed by and signed for the checkpoint's signer, entry
+//! inside the certificate's validity window, and entry signed by the sub-key.
+//! Entry signatures are kept beside the log, not in the leaf, so entries
+//! without one are still valid; they are just unattributed.
+
+use crate::checkpoint::Checkpoint;
+use crate::crypto::{ct_eq, key_id, sha256, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::merkle::{Entry, MerkleProof};
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, KeyId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Sub-key certificate version (for schema evolution)
+pub const SUBKEY_VERSION: u8 = 1;
+
+/// Delegation of entry signing to one component's short-lived key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubKeyCert {
+    /// Schema version
+    pub version: u8,
+    /// Software component the key belongs to
+    pub component: String,
+    /// Ed25519 verifying key of the sub-key
+    pub sub_key: [u8; 32],
+    /// Validity window for the entries it signs (inclusive)
+    #[serde(with = "crate::serialization::timestamp")]
+    pub not_before: DateTime<Utc>,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub not_after: DateTime<Utc>,
+    /// Fingerprint of the enclave key (the checkpoint signer)
+    pub enclave_key_id: KeyId,
+    /// Ed25519 signature by the enclave key over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned certificate (for signature computation)
+#[derive(Serialize)]
+struct UnsignedSubKeyCert<'a> {
+    version: u8,
+    component: &'a str,
+    sub_key: [u8; 32],
+    #[serde(with = "crate::serialization::timestamp")]
+    not_before: DateTime<Utc>,
+    #[serde(with = "crate::serialization::timestamp")]
+    not_after: DateTime<Utc>,
+    enclave_key_id: KeyId,
+}
+
+impl SubKeyCert {
+    /// Delegate to `sub_key` for `component` between `not_before` and `not_after`.
+    pub fn issue(
+        enclave: &Signer,
+        component: impl Into<String>,
+        sub_key: &VerifyingKey,
+        not_before: DateTime<Utc>,
+        not_after: DateTime<Utc>,
+    ) -> Result<Self, SubKeyError> {
+        let mut cert = Self {
+            version: SUBKEY_VERSION,
+            component: component.into(),
+            sub_key: sub_key.to_bytes(),
+            not_before,
+            not_after,
+            enclave_key_id: enclave.key_id(),
+            signature: SignatureBytes([0u8; 64]),
+        };
+        let signature = enclave.sign(&cert.signing_payload()?);
+        cert.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(cert)
+    }
+
+    /// The sub-key.
+    pub fn sub_verifying_key(&self) -> Result<VerifyingKey, SubKeyError> {
+        VerifyingKey::from_bytes(&self.sub_key).map_err(|_| SubKeyError::InvalidCert)
+    }
+
+    /// Verify the certificate signature against the enclave key.
+    pub fn verify(&self, enclave_key: &VerifyingKey) -> Result<(), SubKeyError> {
+        use ed25519_dalek::Verifier;
+
+        if !ct_eq(&key_id(enclave_key).0, &self.enclave_key_id.0)
+            || self.not_after < self.not_before
+        {
+            return Err(SubKeyError::InvalidCert);
+        }
+        self.sub_verifying_key()?;
+
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        enclave_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| SubKeyError::InvalidCert)
+    }
+
+    /// Hash committed in `Checkpoint::sub_key_certs`.
+    pub fn compute_hash(&self) -> Result<Hash256, SerializationError> {
+        Ok(sha256(&self.to_bytes()?))
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedSubKeyCert {
+            version: self.version,
+            component: &self.component,
+            sub_key: self.sub_key,
+            not_before: self.not_before,
+            not_after: self.not_after,
+            enclave_key_id: self.enclave_key_id,
+        })
+    }
+}
+
+/// Sign an entry's leaf hash with a component's sub-key.
+pub fn sign_entry(entry: &Entry, sub_key: &Signer) -> SignatureBytes {
+    SignatureBytes::from(sub_key.sign(&entry.hash()).to_bytes())
+}
+
+/// Evidence attributing one committed entry to a software component.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryAttribution {
+    /// Inclusion of the entry in the checkpoint's entries_root
+    pub proof: MerkleProof,
+    /// Sub-key signature over the entry's leaf hash
+    pub entry_signature: SignatureBytes,
+    pub cert: SubKeyCert,
+}
+
+impl EntryAttribution {
+    /// Verify the attribution against `checkpoint`, signed by `enclave_key`
+    /// (whose checkpoint signature the caller has already checked).
+    ///
+    /// Returns the attributed component.
+    pub fn verify(
+        &self,
+        checkpoint: &Checkpoint,
+        enclave_key: &VerifyingKey,
+    ) -> Result<&str, SubKeyError> {
+        use ed25519_dalek::Verifier;
+
+        if !checkpoint.includes_entry(&self.proof) {
+            return Err(SubKeyError::EntryNotCommitted);
+        }
+        if !checkpoint
+            .sub_key_certs
+            .contains(&self.cert.compute_hash()?)
+        {
+            return Err(SubKeyError::CertNotCommitted);
+        }
+        if self.cert.enclave_key_id != checkpoint.signer_key_id {
+            return Err(SubKeyError::InvalidCert);
+        }
+        self.cert.verify(enclave_key)?;
+
+        let entry = &self.proof.leaf;
+        let at = i64::try_from(entry.timestamp_us)
+            .ok()
+            .and_then(DateTime::<Utc>::from_timestamp_micros)
+            .ok_or(SubKeyError::OutsideValidity)?;
+        if at < self.cert.not_before || at > self.cert.not_after {
+            return Err(SubKeyError::OutsideValidity);
+        }
+
+        let signature = ed25519_dalek::Signature::from_bytes(self.entry_signature.as_ref());
+        self.cert
+            .sub_verifying_key()?
+            .verify(&entry.hash(), &signature)
+            .map_err(|_| SubKeyError::InvalidEntrySignature)?;
+        Ok(&self.cert.component)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SubKeyError {
+    #[error("Sub-key certificate serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Sub-key certificate is not validly signed by the checkpoint signer")]
+    InvalidCert,
+
+    #[error("Checkpoint does not commit to the sub-key certificate")]
+    CertNotCommitted,
+
+    #[error("Entry is not committed by the checkpoint")]
+    EntryNotCommitted,
+
+    #[error("Entry timestamp is outside the sub-key certificate's validity")]
+    OutsideValidity,
+
+    #[error("Invalid entry signature")]
+    InvalidEntrySignature,
+}
+
+impl ErrorCoded for SubKeyError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            SubKeyError::Serialization(e) => e.code(),
+            SubKeyError::InvalidCert => ErrorCode::SubKeyCertInvalid,
+            SubKeyError::CertNotCommitted => ErrorCode::SubKeyCertNotCommitted,
+            SubKeyError::EntryNotCommitted => ErrorCode::SubKeyEntryNotCommitted,
+            SubKeyError::OutsideValidity => ErrorCode::SubKeyOutsideValidity,
+            SubKeyError::InvalidEntrySignature => ErrorCode::EntrySignatureInvalid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::merkle::MerkleTree;
+    use chrono::Duration;
+
+    const T0: u64 = 1_700_000_000_000_000;
+
+    struct Fixture {
+        enclave: Signer,
+        checkpoint: Checkpoint,
+        attribution: EntryAttribution,
+    }
+
+    /// A checkpoint of four entries one second apart, the third signed by the
+    /// planner's sub-key, valid for `validity` from the first entry.
+    fn fixture(commit: bool, validity: Duration) -> Fixture {
+        let enclave = Signer::generate();
+        let planner = Signer::generate();
+        let start = DateTime::<Utc>::from_timestamp_micros(T0 as i64).unwrap();
+        let cert = SubKeyCert::issue(
+            &enclave,
+            "planner",
+            &planner.verifying_key(),
+            start,
+            start + validity,
+        )
+        .unwrap();
+
+        let mut tree = MerkleTree::new();
+        for i in 0..4u64 {
+            tree.insert(Entry::new(
+                T0 + i * 1_000_000,
+                0,
+                format!("entry-{i}").as_bytes(),
+            ));
+        }
+        let proof = tree.generate_proof(T0 + 2_000_000, 0).unwrap();
+        let entry_signature = sign_entry(&proof.leaf, &planner);
+
+        let mut builder = CheckpointBuilder::test_fixture().entries_root(tree.root());
+        if commit {
+            builder = builder.sub_key_cert(cert.compute_hash().unwrap());
+        }
+        let checkpoint = builder.build_and_sign(enclave.signing_key()).unwrap();
+
+        Fixture {
+            enclave,
+            checkpoint,
+            attribution: EntryAttribution {
+                proof,
+                entry_signature,
+                cert,
+            },
+        }
+    }
+
+    #[test]
+    fn test_attributes_entry_to_component() {
+        let f = fixture(true, Duration::minutes(5));
+        let component = f
+            .attribution
+            .verify(&f.checkpoint, &f.enclave.verifying_key())
+            .unwrap();
+        assert_eq!(component, "planner");
+
+        let bytes = f.attribution.cert.to_bytes().unwrap();
+        assert_eq!(SubKeyCert::from_bytes(&bytes).unwrap(), f.attribution.cert);
+    }
+
+    #[test]
+    fn test_rejects_broken_links() {
+        let f = fixture(false, Duration::minutes(5));
+        let err = f
+            .attribution
+            .verify(&f.checkpoint, &f.enclave.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SubKeyCertNotCommitted);
+
+        let f = fixture(true, Duration::minutes(5));
+        let mut forged = f.attribution.clone();
+        forged.entry_signature = sign_entry(&forged.proof.leaf, &Signer::generate());
+        let err = forged
+            .verify(&f.checkpoint, &f.enclave.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::EntrySignatureInvalid);
+
+        let mut other_entry = f.attribution.clone();
+        other_entry.proof.leaf.nonce = 7;
+        let err = other_entry
+            .verify(&f.checkpoint, &f.enclave.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SubKeyEntryNotCommitted);
+
+        let err = f
+            .attribution
+            .verify(&f.checkpoint, &Signer::generate().verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SubKeyCertInvalid);
+    }
+
+    #[test]
+    fn test_rejects_entries_outside_validity() {
+        let f = fixture(true, Duration::seconds(1));
+        let err = f
+            .attribution
+            .verify(&f.checkpoint, &f.enclave.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SubKeyOutsideValidity);
+
+        let f = fixture(true, Duration::seconds(2));
+        assert!(f
+            .attribution
+            .verify(&f.checkpoint, &f.enclave.verifying_key())
+            .is_ok());
+    }
+}
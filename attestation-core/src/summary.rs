@@ -0,0 +1,370 @@
+//! Per-mission summary statistics.
+//!
+//! A robot accumulates a [`MissionSummary`] from its log entries during a
+//! mission and commits to its hash in the mission-end checkpoint
+//! ([`crate::MissionEvent::End`]'s `summary_root`). Entries whose payload is a
+//! CBOR [`MissionRecord`] contribute their type, distance, interventions and
+//! anomaly flags; other payloads are counted as [`UNTYPED_RECORD`].
+//!
+//! An auditor who cannot fetch every payload checks the summary with
+//! [`MissionSummary::verify`] and a handful of [`SummarySample`]s: each is a
+//! payload with its Merkle proof against a mission checkpoint, and must be
+//! consistent with the committed totals.
+
+use crate::checkpoint::Checkpoint;
+use crate::crypto::{ct_eq, sha256};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::merkle::MerkleProof;
+use crate::mission::MissionEvent;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, MissionId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+
+/// Record type counted for payloads that are not a [`MissionRecord`].
+pub const UNTYPED_RECORD: &str = "untyped";
+
+/// Typed log entry payload understood by mission summaries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MissionRecord {
+    /// Record type (e.g. "perception", "plan", "actuation")
+    pub record_type: String,
+    /// Distance traveled since the previous record, in millimetres
+    #[serde(default)]
+    pub distance_mm: u64,
+    /// Whether a human operator intervened
+    #[serde(default)]
+    pub intervention: bool,
+    /// Anomaly flags raised by this record
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anomalies: Vec<String>,
+}
+
+impl MissionRecord {
+    /// Serialize to canonical CBOR bytes (the entry payload).
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+}
+
+/// Statistics over every entry of a mission.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MissionSummary {
+    pub mission_id: MissionId,
+    /// Total entries
+    pub entries: u64,
+    /// Entries per record type
+    pub entry_counts: BTreeMap<String, u64>,
+    /// Total distance traveled, in millimetres
+    pub distance_mm: u64,
+    pub interventions: u64,
+    /// Every anomaly flag raised during the mission
+    pub anomaly_flags: BTreeSet<String>,
+}
+
+impl MissionSummary {
+    /// Start an empty summary.
+    pub fn new(mission_id: MissionId) -> Self {
+        Self {
+            mission_id,
+            entries: 0,
+            entry_counts: BTreeMap::new(),
+            distance_mm: 0,
+            interventions: 0,
+            anomaly_flags: BTreeSet::new(),
+        }
+    }
+
+    /// Account for one entry payload.
+    pub fn add(&mut self, payload: &[u8]) {
+        self.entries += 1;
+        let Ok(record) = from_canonical_cbor::<MissionRecord>(payload) else {
+            *self
+                .entry_counts
+                .entry(UNTYPED_RECORD.to_string())
+                .or_default() += 1;
+            return;
+        };
+        *self.entry_counts.entry(record.record_type).or_default() += 1;
+        self.distance_mm += record.distance_mm;
+        self.interventions += u64::from(record.intervention);
+        self.anomaly_flags.extend(record.anomalies);
+    }
+
+    /// Hash committed as the mission-end checkpoint's `summary_root`.
+    pub fn compute_hash(&self) -> Result<Hash256, SerializationError> {
+        Ok(sha256(&self.to_bytes()?))
+    }
+
+    /// Check the summary against a mission's checkpoints and sampled entries.
+    ///
+    /// `checkpoints` must be the mission's verified start-to-end run (see
+    /// [`crate::verify_mission`]); its end checkpoint must commit to this
+    /// summary, and every sample must prove into one of its checkpoints and
+    /// agree with the totals.
+    pub fn verify(
+        &self,
+        checkpoints: &[Checkpoint],
+        samples: &[SummarySample],
+    ) -> Result<(), SummaryError> {
+        let end = checkpoints.last().ok_or(SummaryError::NotCommitted)?;
+        match end.mission_event {
+            Some(MissionEvent::End { summary_root, .. })
+                if ct_eq(&summary_root, &self.compute_hash()?)
+                    && end.mission_id == self.mission_id => {}
+            _ => return Err(SummaryError::NotCommitted),
+        }
+        if self.entry_counts.values().sum::<u64>() != self.entries {
+            return Err(SummaryError::Inconsistent(
+                "entry counts do not add up to the total".to_string(),
+            ));
+        }
+
+        for sample in samples {
+            let checkpoint = checkpoints
+                .iter()
+                .find(|checkpoint| checkpoint.sequence == sample.sequence)
+                .ok_or(SummaryError::SampleInvalid {
+                    sequence: sample.sequence,
+                    reason: "checkpoint is not part of the mission",
+                })?;
+            if !checkpoint.includes_entry(&sample.proof) {
+                return Err(SummaryError::SampleInvalid {
+                    sequence: sample.sequence,
+                    reason: "Merkle proof does not match the entries root",
+                });
+            }
+            if !ct_eq(&sha256(&sample.payload), &sample.proof.leaf.data_hash) {
+                return Err(SummaryError::SampleInvalid {
+                    sequence: sample.sequence,
+                    reason: "payload does not match the proven entry",
+                });
+            }
+            self.check_sample(&sample.payload)?;
+        }
+        Ok(())
+    }
+
+    /// Whether a single payload could have contributed to these totals.
+    fn check_sample(&self, payload: &[u8]) -> Result<(), SummaryError> {
+        let Ok(record) = from_canonical_cbor::<MissionRecord>(payload) else {
+            return match self.entry_counts.get(UNTYPED_RECORD) {
+                Some(count) if *count > 0 => Ok(()),
+                _ => Err(SummaryError::Inconsistent(
+                    "untyped entry not counted".to_string(),
+                )),
+            };
+        };
+        if self
+            .entry_counts
+            .get(&record.record_type)
+            .is_none_or(|count| *count == 0)
+        {
+            return Err(SummaryError::Inconsistent(format!(
+                "{} entry not counted",
+                record.record_type
+            )));
+        }
+        if record.distance_mm > self.distance_mm {
+            return Err(SummaryError::Inconsistent(
+                "entry exceeds total distance".to_string(),
+            ));
+        }
+        if record.intervention && self.interventions == 0 {
+            return Err(SummaryError::Inconsistent(
+                "intervention not counted".to_string(),
+            ));
+        }
+        if let Some(flag) = record
+            .anomalies
+            .iter()
+            .find(|flag| !self.anomaly_flags.contains(*flag))
+        {
+            return Err(SummaryError::Inconsistent(format!(
+                "anomaly flag {flag:?} not reported"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+}
+
+/// An entry payload disclosed to back a summary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SummarySample {
+    /// Sequence of the checkpoint that committed the entry
+    pub sequence: u64,
+    pub payload: Vec<u8>,
+    /// Proof of the entry against that checkpoint's `entries_root`
+    pub proof: MerkleProof,
+}
+
+#[derive(Debug, Error)]
+pub enum SummaryError {
+    #[error("Summary serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Mission-end checkpoint does not commit to this summary")]
+    NotCommitted,
+
+    #[error("Summary sample from checkpoint {sequence} is invalid: {reason}")]
+    SampleInvalid { sequence: u64, reason: &'static str },
+
+    #[error("Summary is inconsistent with its entries: {0}")]
+    Inconsistent(String),
+}
+
+impl ErrorCoded for SummaryError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            SummaryError::Serialization(e) => e.code(),
+            SummaryError::NotCommitted => ErrorCode::SummaryNotCommitted,
+            SummaryError::SampleInvalid { .. } | SummaryError::Inconsistent(_) => {
+                ErrorCode::SummaryInconsistent
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::crypto::Signer;
+    use crate::merkle::{Entry, MerkleTree};
+    use crate::mission::{tests::start_checkpoint, MissionAuthorization};
+    use crate::types::RobotId;
+
+    fn record(
+        record_type: &str,
+        distance_mm: u64,
+        intervention: bool,
+        anomalies: &[&str],
+    ) -> Vec<u8> {
+        MissionRecord {
+            record_type: record_type.to_string(),
+            distance_mm,
+            intervention,
+            anomalies: anomalies.iter().map(|flag| flag.to_string()).collect(),
+        }
+        .to_bytes()
+        .unwrap()
+    }
+
+    /// A two-checkpoint mission logging `payloads` whose end commits to the
+    /// summary of `summarized` (normally the same payloads).
+    fn mission(
+        payloads: &[Vec<u8>],
+        summarized: &[Vec<u8>],
+    ) -> (Vec<Checkpoint>, MissionSummary, MerkleTree) {
+        let robot = Signer::generate();
+        let authorization = MissionAuthorization::issue(
+            RobotId("R-001".to_string()),
+            MissionId("M-001".to_string()),
+            &Signer::generate(),
+        )
+        .unwrap();
+        let start = start_checkpoint(&robot, &authorization);
+
+        let mut tree = MerkleTree::new();
+        for (i, payload) in payloads.iter().enumerate() {
+            tree.insert(Entry::new(1000 + i as u64, i as u64, payload));
+        }
+        let mut summary = MissionSummary::new(start.mission_id.clone());
+        for payload in summarized {
+            summary.add(payload);
+        }
+        let end = CheckpointBuilder::continuing_from(&start)
+            .unwrap()
+            .monotonic_counter(start.monotonic_counter + 1)
+            .timestamp(start.local_timestamp_utc + chrono::Duration::seconds(1))
+            .entries_root(tree.root())
+            .mission_end(
+                start.compute_hash().unwrap(),
+                summary.compute_hash().unwrap(),
+            )
+            .build_and_sign(robot.signing_key())
+            .unwrap();
+        (vec![start, end], summary, tree)
+    }
+
+    fn sample(tree: &MerkleTree, payloads: &[Vec<u8>], i: usize) -> SummarySample {
+        SummarySample {
+            sequence: 1,
+            payload: payloads[i].clone(),
+            proof: tree.generate_proof(1000 + i as u64, i as u64).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_summary_accumulates_records() {
+        let payloads = vec![
+            record("perception", 0, false, &[]),
+            record("actuation", 1500, false, &[]),
+            record("actuation", 2500, true, &["obstacle_close"]),
+            b"opaque sensor blob".to_vec(),
+        ];
+        let (checkpoints, summary, tree) = mission(&payloads, &payloads);
+
+        assert_eq!(summary.entries, 4);
+        assert_eq!(summary.entry_counts["actuation"], 2);
+        assert_eq!(summary.entry_counts[UNTYPED_RECORD], 1);
+        assert_eq!(summary.distance_mm, 4000);
+        assert_eq!(summary.interventions, 1);
+        assert!(summary.anomaly_flags.contains("obstacle_close"));
+
+        let samples: Vec<_> = (0..payloads.len())
+            .map(|i| sample(&tree, &payloads, i))
+            .collect();
+        summary.verify(&checkpoints, &samples).unwrap();
+
+        let decoded = MissionSummary::from_bytes(&summary.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, summary);
+    }
+
+    #[test]
+    fn test_edited_summary_not_committed() {
+        let payloads = vec![record("actuation", 1500, true, &[])];
+        let (checkpoints, mut summary, _) = mission(&payloads, &payloads);
+
+        summary.interventions = 0;
+        let err = summary.verify(&checkpoints, &[]).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SummaryNotCommitted);
+    }
+
+    #[test]
+    fn test_sample_exposes_understated_summary() {
+        let payloads = vec![
+            record("actuation", 1500, false, &[]),
+            record("actuation", 10, false, &["lidar_dropout"]),
+        ];
+        // The robot hides the anomaly from the committed summary
+        let hidden = vec![payloads[0].clone(), record("actuation", 10, false, &[])];
+        let (checkpoints, summary, tree) = mission(&payloads, &hidden);
+        summary
+            .verify(&checkpoints, &[sample(&tree, &payloads, 0)])
+            .unwrap();
+
+        // Sampling the anomalous entry exposes it
+        let err = summary
+            .verify(&checkpoints, &[sample(&tree, &payloads, 1)])
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SummaryInconsistent);
+
+        // A payload swapped under a valid proof is rejected
+        let mut forged = sample(&tree, &payloads, 1);
+        forged.payload = payloads[0].clone();
+        let err = summary.verify(&checkpoints, &[forged]).unwrap_err();
+        assert!(matches!(err, SummaryError::SampleInvalid { .. }));
+    }
+}
@@ -0,0 +1,503 @@
+//! Differential checkpoint sync.
+//!
+//! A verifier that already holds part of a robot's chain should not have to
+//! download the whole archive again to catch up. With this protocol it names
+//! the last checkpoint it holds (sequence and hash) and the size of the last
+//! log tree head it saw. The gateway answers with:
+//!
+//! - the checkpoints after that one, a page at a time;
+//! - its current signed tree head, with a consistency proof from the tree
+//!   size the client saw, when the gateway keeps a
+//!   [`TransparencyLog`](crate::TransparencyLog).
+//!
+//! The [`SyncClient`] resumes chain verification from its head, so the new
+//! checkpoints must link onto what it holds, and it only accepts a tree head
+//! that extends the one it had. A gateway that rewrote history fails one of
+//! the two checks.
+//!
+//! Messages are plain serde types with canonical CBOR encodings, so any
+//! transport can carry them: implement [`SyncTransport`] for an HTTP or gRPC
+//! client, and answer requests with a [`SyncServer`] on the gateway side.
+
+use crate::backfill::{CheckpointQuery, CheckpointStore};
+use crate::chain::{ChainError, ChainHead, ChainVerifier};
+use crate::checkpoint::Checkpoint;
+use crate::crypto::Signer;
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::transparency::{ConsistencyProof, LogError, SignedTreeHead, TransparencyLog};
+use crate::types::{Hash256, RobotId};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Last checkpoint a client holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeldCheckpoint {
+    pub sequence: u64,
+    /// [`Checkpoint::compute_hash`] of the held checkpoint
+    pub hash: Hash256,
+}
+
+/// What a client holds and wants next.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncRequest {
+    pub robot_id: RobotId,
+    /// Last checkpoint held; the whole chain is requested if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub held: Option<HeldCheckpoint>,
+    /// Size of the last tree head the client verified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tree_size: Option<u64>,
+    /// Checkpoints wanted in this response
+    pub limit: usize,
+}
+
+/// Checkpoints the client lacks, with proof the log only grew.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncResponse {
+    /// Checkpoints after the held one, in sequence order
+    pub checkpoints: Vec<Checkpoint>,
+    /// Whether more checkpoints follow
+    pub more: bool,
+    /// Current tree head of the gateway's log, if it keeps one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tree_head: Option<SignedTreeHead>,
+    /// Consistency proof from the requested tree size to `tree_head`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consistency: Option<ConsistencyProof>,
+}
+
+impl SyncRequest {
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+}
+
+impl SyncResponse {
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+}
+
+/// Carries sync requests to a gateway (e.g., an HTTP or gRPC client).
+#[async_trait]
+pub trait SyncTransport: Send + Sync {
+    async fn sync(&self, request: &SyncRequest) -> Result<SyncResponse, SyncError>;
+}
+
+/// Answers sync requests from a gateway's checkpoint store and log.
+pub struct SyncServer<'a> {
+    store: &'a dyn CheckpointStore,
+    log: Option<(&'a TransparencyLog, &'a Signer)>,
+    max_limit: usize,
+}
+
+impl<'a> SyncServer<'a> {
+    /// Serve checkpoints from `store`.
+    pub fn new(store: &'a dyn CheckpointStore) -> Self {
+        Self {
+            store,
+            log: None,
+            max_limit: CheckpointQuery::DEFAULT_LIMIT,
+        }
+    }
+
+    /// Include tree heads of `log`, signed by `signer`, with consistency proofs.
+    pub fn with_log(mut self, log: &'a TransparencyLog, signer: &'a Signer) -> Self {
+        self.log = Some((log, signer));
+        self
+    }
+
+    /// Serve at most `max_limit` checkpoints per response.
+    pub fn with_max_limit(mut self, max_limit: usize) -> Self {
+        self.max_limit = max_limit.max(1);
+        self
+    }
+
+    /// Answer `request`, refusing it if the client's checkpoint is not in the stored chain.
+    pub fn respond(&self, request: &SyncRequest) -> Result<SyncResponse, SyncError> {
+        let mut query = CheckpointQuery::new(request.robot_id.clone())
+            .with_limit(request.limit.clamp(1, self.max_limit));
+        if let Some(held) = request.held {
+            let stored = self
+                .store
+                .page(
+                    &CheckpointQuery::new(request.robot_id.clone())
+                        .with_sequences(held.sequence, held.sequence),
+                )
+                .checkpoints
+                .pop();
+            match stored {
+                Some(stored) if stored.compute_hash()? == held.hash => {}
+                _ => {
+                    return Err(SyncError::HeadMismatch {
+                        sequence: held.sequence,
+                    })
+                }
+            }
+            query.from_sequence = held.sequence + 1;
+        }
+        let page = self.store.page(&query);
+
+        let (tree_head, consistency) = match self.log {
+            Some((log, signer)) => {
+                let head = log.tree_head();
+                let consistency = match request.tree_size {
+                    Some(size) => Some(log.consistency_proof(size, head.tree_size)?),
+                    None => None,
+                };
+                (Some(SignedTreeHead::sign(head, signer)?), consistency)
+            }
+            None => (None, None),
+        };
+        Ok(SyncResponse {
+            checkpoints: page.checkpoints,
+            more: page.next.is_some(),
+            tree_head,
+            consistency,
+        })
+    }
+}
+
+/// Outcome of a sync run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub robot_id: RobotId,
+    /// Sequences received and stored
+    pub received: Vec<u64>,
+    /// Requests made
+    pub round_trips: usize,
+    /// Tree head the client holds after the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tree_head: Option<SignedTreeHead>,
+}
+
+/// Catches a partial copy of a robot's chain up with a gateway.
+pub struct SyncClient {
+    robot_id: RobotId,
+    verifier: ChainVerifier,
+    /// Trusted log keys; tree heads are required when set
+    logs: Option<Box<dyn KeyResolver>>,
+    tree_head: Option<SignedTreeHead>,
+    page_size: usize,
+}
+
+impl SyncClient {
+    /// Sync `robot_id`'s chain from the start, verifying it with `keys`.
+    pub fn new(robot_id: RobotId, keys: Box<dyn KeyResolver>) -> Self {
+        Self {
+            robot_id,
+            verifier: ChainVerifier::with_resolver(keys),
+            logs: None,
+            tree_head: None,
+            page_size: CheckpointQuery::DEFAULT_LIMIT,
+        }
+    }
+
+    /// Continue after `head`, the last checkpoint already held and verified.
+    pub fn resume_from(mut self, head: ChainHead) -> Self {
+        self.verifier = self.verifier.resume_from(head);
+        self
+    }
+
+    /// Require tree heads signed by `logs`, each extending the previous one.
+    pub fn with_log(mut self, logs: Box<dyn KeyResolver>) -> Self {
+        self.logs = Some(logs);
+        self
+    }
+
+    /// Tree head verified by an earlier run, for the first consistency proof.
+    pub fn with_tree_head(mut self, tree_head: SignedTreeHead) -> Self {
+        self.tree_head = Some(tree_head);
+        self
+    }
+
+    /// Ask for `page_size` checkpoints per request.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Access the chain verifier (e.g., to add rotations or delegations).
+    pub fn verifier_mut(&mut self) -> &mut ChainVerifier {
+        &mut self.verifier
+    }
+
+    /// The last verified checkpoint.
+    pub fn head(&self) -> Option<&ChainHead> {
+        self.verifier.head()
+    }
+
+    /// The last verified tree head.
+    pub fn tree_head(&self) -> Option<&SignedTreeHead> {
+        self.tree_head.as_ref()
+    }
+
+    /// The next request to send.
+    pub fn request(&self) -> SyncRequest {
+        SyncRequest {
+            robot_id: self.robot_id.clone(),
+            held: self.head().map(|head| HeldCheckpoint {
+                sequence: head.sequence,
+                hash: head.hash,
+            }),
+            tree_size: self.tree_head.as_ref().map(|sth| sth.tree_head.tree_size),
+            limit: self.page_size,
+        }
+    }
+
+    /// Verify `response` against what the client holds and store its
+    /// checkpoints in `local`, returning their sequences.
+    ///
+    /// The tree head is checked first, then each checkpoint in turn; those
+    /// before a rejected one stay stored and the head advances past them.
+    pub fn apply(
+        &mut self,
+        response: &SyncResponse,
+        local: &mut dyn CheckpointStore,
+    ) -> Result<Vec<u64>, SyncError> {
+        if let Some(logs) = &self.logs {
+            let tree_head = response.tree_head.as_ref().ok_or(SyncError::ProofMissing)?;
+            tree_head.verify(logs.as_ref())?;
+            if let Some(held) = &self.tree_head {
+                let proof = response
+                    .consistency
+                    .as_ref()
+                    .ok_or(SyncError::ProofMissing)?;
+                proof.verify(&held.tree_head, &tree_head.tree_head)?;
+            }
+            self.tree_head = Some(tree_head.clone());
+        }
+
+        let mut received = Vec::with_capacity(response.checkpoints.len());
+        for checkpoint in &response.checkpoints {
+            local.insert(self.verifier.accept(checkpoint.clone())?);
+            received.push(checkpoint.sequence);
+        }
+        Ok(received)
+    }
+
+    /// Request and apply pages until the client has caught up.
+    pub async fn sync(
+        &mut self,
+        transport: &dyn SyncTransport,
+        local: &mut dyn CheckpointStore,
+    ) -> Result<SyncReport, SyncError> {
+        let mut report = SyncReport {
+            robot_id: self.robot_id.clone(),
+            received: Vec::new(),
+            round_trips: 0,
+            tree_head: None,
+        };
+        loop {
+            let response = transport.sync(&self.request()).await?;
+            report.round_trips += 1;
+            report.received.extend(self.apply(&response, local)?);
+            if !response.more || response.checkpoints.is_empty() {
+                break;
+            }
+        }
+        report.tree_head = self.tree_head.clone();
+        Ok(report)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SyncError {
+    #[error("Checkpoint {sequence} held by the client is not in the server's chain")]
+    HeadMismatch { sequence: u64 },
+
+    #[error("Sync response lacks the tree head or consistency proof required")]
+    ProofMissing,
+
+    #[error("Synced chain rejected: {0}")]
+    Chain(#[from] ChainError),
+
+    #[error("Tree head rejected: {0}")]
+    Log(#[from] LogError),
+
+    #[error("Sync serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Sync transport failed: {0}")]
+    Transport(String),
+}
+
+impl ErrorCoded for SyncError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            SyncError::HeadMismatch { .. } => ErrorCode::SyncHeadMismatch,
+            SyncError::ProofMissing => ErrorCode::SyncProofMissing,
+            SyncError::Chain(e) => e.code(),
+            SyncError::Log(e) => e.code(),
+            SyncError::Serialization(e) => e.code(),
+            SyncError::Transport(_) => ErrorCode::GatewayUnavailable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::VerifiedCheckpoint;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::transparency::checkpoint_leaf_hash;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryStore(BTreeMap<u64, Checkpoint>);
+
+    impl CheckpointStore for MemoryStore {
+        fn checkpoints(&self, robot_id: &RobotId) -> Vec<Checkpoint> {
+            self.0
+                .values()
+                .filter(|c| &c.robot_id == robot_id)
+                .cloned()
+                .collect()
+        }
+
+        fn insert(&mut self, checkpoint: VerifiedCheckpoint) {
+            self.0.insert(checkpoint.sequence, checkpoint.into_inner());
+        }
+    }
+
+    /// Gateway state behind an in-process transport.
+    struct Gateway {
+        store: MemoryStore,
+        log: TransparencyLog,
+        signer: Signer,
+        requests: Mutex<Vec<SyncRequest>>,
+    }
+
+    #[async_trait]
+    impl SyncTransport for Gateway {
+        async fn sync(&self, request: &SyncRequest) -> Result<SyncResponse, SyncError> {
+            self.requests.lock().unwrap().push(request.clone());
+            // Round-trip through the wire encoding
+            let request = SyncRequest::from_bytes(&request.to_bytes()?)?;
+            let response = SyncServer::new(&self.store)
+                .with_log(&self.log, &self.signer)
+                .with_max_limit(4)
+                .respond(&request)?;
+            Ok(SyncResponse::from_bytes(&response.to_bytes()?)?)
+        }
+    }
+
+    fn extend(gateway: &mut Gateway, robot: &Signer, count: u64) {
+        for _ in 0..count {
+            let builder = match gateway.store.0.values().last() {
+                Some(prev) => CheckpointBuilder::continuing_from(prev).unwrap(),
+                None => CheckpointBuilder::test_fixture(),
+            };
+            let sequence = gateway.store.0.len() as u64;
+            let checkpoint = builder
+                .monotonic_counter(sequence + 1)
+                .timestamp(
+                    chrono::DateTime::UNIX_EPOCH + chrono::Duration::minutes(sequence as i64 + 1),
+                )
+                .entries_root([sequence as u8 + 3; 32])
+                .build_and_sign(robot.signing_key())
+                .unwrap();
+            let mut verifier = ChainVerifier::new(robot.verifying_key());
+            if let Some(prev) = gateway.store.0.values().last() {
+                verifier = verifier.resume_from(ChainHead::from_checkpoint(prev).unwrap());
+            }
+            gateway
+                .log
+                .append_leaf(checkpoint_leaf_hash(&checkpoint).unwrap());
+            gateway.store.insert(verifier.accept(checkpoint).unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_fetches_only_missing_checkpoints() {
+        let robot = Signer::generate();
+        let mut gateway = Gateway {
+            store: MemoryStore::default(),
+            log: TransparencyLog::new(),
+            signer: Signer::generate(),
+            requests: Mutex::new(Vec::new()),
+        };
+        extend(&mut gateway, &robot, 3);
+
+        let mut local = MemoryStore::default();
+        let mut client = SyncClient::new(
+            RobotId("R-001".to_string()),
+            Box::new(robot.verifying_key()),
+        )
+        .with_log(Box::new(gateway.signer.verifying_key()));
+        let report = client.sync(&gateway, &mut local).await.unwrap();
+        assert_eq!(report.received, vec![0, 1, 2]);
+        assert_eq!(report.tree_head.unwrap().tree_head.tree_size, 3);
+
+        // Later: six more, fetched in pages of four from where the client stopped
+        extend(&mut gateway, &robot, 6);
+        let report = client.sync(&gateway, &mut local).await.unwrap();
+        assert_eq!(report.received, (3..9).collect::<Vec<_>>());
+        assert_eq!(report.round_trips, 2);
+        let requests = gateway.requests.lock().unwrap();
+        assert_eq!(requests[1].held.unwrap().sequence, 2);
+        assert_eq!(requests[1].tree_size, Some(3));
+        drop(requests);
+        assert_eq!(local.0.len(), 9);
+        assert_eq!(client.head().unwrap().sequence, 8);
+    }
+
+    #[test]
+    fn test_rewritten_history_rejected() {
+        let robot = Signer::generate();
+        let mut gateway = Gateway {
+            store: MemoryStore::default(),
+            log: TransparencyLog::new(),
+            signer: Signer::generate(),
+            requests: Mutex::new(Vec::new()),
+        };
+        extend(&mut gateway, &robot, 2);
+        let mut local = MemoryStore::default();
+        let mut client = SyncClient::new(
+            RobotId("R-001".to_string()),
+            Box::new(robot.verifying_key()),
+        )
+        .with_log(Box::new(gateway.signer.verifying_key()));
+        let response = SyncServer::new(&gateway.store)
+            .with_log(&gateway.log, &gateway.signer)
+            .respond(&client.request())
+            .unwrap();
+        client.apply(&response, &mut local).unwrap();
+
+        // The gateway replaces its log: the old tree head is not a prefix
+        let mut rewritten = TransparencyLog::new();
+        for leaf in [[9u8; 32], [8u8; 32], [7u8; 32]] {
+            rewritten.append_leaf(leaf);
+        }
+        let response = SyncServer::new(&gateway.store)
+            .with_log(&rewritten, &gateway.signer)
+            .respond(&client.request())
+            .unwrap();
+        let err = client.apply(&response, &mut local).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ConsistencyProofInvalid);
+
+        // A client holding a checkpoint the gateway never stored is refused
+        let mut request = client.request();
+        request.held.as_mut().unwrap().hash = [0u8; 32];
+        let err = SyncServer::new(&gateway.store)
+            .respond(&request)
+            .unwrap_err();
+        assert!(matches!(err, SyncError::HeadMismatch { sequence: 1 }));
+    }
+}
@@ -0,0 +1,213 @@
+//! Tenant scoping for a gateway shared by several customer fleets.
+//!
+//! A robot OEM runs one gateway for many customers, and no customer may see
+//! or write another's data. Each [`Tenant`] in the [`GatewayConfig`] lists
+//! its robots, and may bring its own signing keys and trust requirements.
+//! A request authenticated as a tenant goes through a [`TenantScope`], which
+//! refuses robots outside the tenant and hands out only the tenant's:
+//!
+//! - robots: [`TenantScope::check_robot`]
+//! - keys and policy: [`TenantScope::key_ring`] and [`TenantScope::trust`]
+//! - storage: [`TenantScope::checkpoints`], [`TenantScope::page`] and
+//!   [`TenantScope::insert`] over any [`CheckpointStore`], and a
+//!   [`storage prefix`](TenantScope::storage_prefix) for stores that keep
+//!   tenants apart by key
+//!
+//! A scope holds the config snapshot it was opened with, like any request
+//! (see [`crate::reload`]).
+
+use crate::backfill::{CheckpointPage, CheckpointQuery, CheckpointStore};
+use crate::chain::{TrustRequirements, VerifiedCheckpoint};
+use crate::checkpoint::Checkpoint;
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyRing;
+use crate::reload::{GatewayConfig, LiveConfig, ReloadError, Tenant};
+use crate::types::RobotId;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TenantError {
+    #[error("No tenant {0} is configured")]
+    Unknown(String),
+
+    #[error("Robot {robot} does not belong to tenant {tenant}")]
+    RobotOutsideTenant { tenant: String, robot: String },
+}
+
+impl ErrorCoded for TenantError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            TenantError::Unknown(_) => ErrorCode::TenantUnknown,
+            TenantError::RobotOutsideTenant { .. } => ErrorCode::TenantRobotOutside,
+        }
+    }
+}
+
+/// What one tenant's requests may reach.
+#[derive(Debug, Clone)]
+pub struct TenantScope {
+    config: Arc<GatewayConfig>,
+    tenant: Tenant,
+}
+
+impl TenantScope {
+    /// Scope to tenant `id` under the config in force.
+    pub fn open(live: &LiveConfig, id: &str) -> Result<Self, TenantError> {
+        let config = live.snapshot();
+        let tenant = config
+            .tenant(id)
+            .cloned()
+            .ok_or_else(|| TenantError::Unknown(id.to_string()))?;
+        Ok(Self { config, tenant })
+    }
+
+    pub fn tenant(&self) -> &Tenant {
+        &self.tenant
+    }
+
+    /// Refuse `robot_id` unless it belongs to this tenant.
+    pub fn check_robot(&self, robot_id: &RobotId) -> Result<(), TenantError> {
+        if self.tenant.robots.contains(robot_id) {
+            return Ok(());
+        }
+        Err(TenantError::RobotOutsideTenant {
+            tenant: self.tenant.id.clone(),
+            robot: robot_id.0.clone(),
+        })
+    }
+
+    /// The keys this tenant's checkpoints are verified against.
+    pub fn key_ring(&self) -> Result<KeyRing, ReloadError> {
+        self.config.tenant_key_ring(&self.tenant)
+    }
+
+    /// The trust requirements of this tenant's robots.
+    pub fn trust(&self) -> &TrustRequirements {
+        self.tenant.trust.as_ref().unwrap_or(&self.config.trust)
+    }
+
+    /// Key prefix under which a store keeps this tenant's data, e.g.
+    /// `tenants/acme/`.
+    pub fn storage_prefix(&self) -> String {
+        format!("tenants/{}/", self.tenant.id)
+    }
+
+    /// The stored checkpoints of one of this tenant's robots.
+    pub fn checkpoints(
+        &self,
+        store: &impl CheckpointStore,
+        robot_id: &RobotId,
+    ) -> Result<Vec<Checkpoint>, TenantError> {
+        self.check_robot(robot_id)?;
+        Ok(store.checkpoints(robot_id))
+    }
+
+    /// One page of the checkpoints `query` selects, for one of this tenant's robots.
+    pub fn page(
+        &self,
+        store: &impl CheckpointStore,
+        query: &CheckpointQuery,
+    ) -> Result<CheckpointPage, TenantError> {
+        self.check_robot(&query.robot_id)?;
+        Ok(store.page(query))
+    }
+
+    /// Store a verified checkpoint of one of this tenant's robots.
+    pub fn insert(
+        &self,
+        store: &mut impl CheckpointStore,
+        checkpoint: VerifiedCheckpoint,
+    ) -> Result<(), TenantError> {
+        self.check_robot(&checkpoint.robot_id)?;
+        store.insert(checkpoint);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::ChainVerifier;
+    use crate::checkpoint::CheckpointBuilder;
+    use crate::crypto::Signer;
+    use crate::keys::KeyResolver;
+    use crate::reload::{AuthConfig, TrustAnchors};
+    use crate::shard::ShardedStore;
+    use crate::types::TrustMode;
+    use chrono::Duration;
+
+    fn tenant(id: &str, robot: &str, signer: &Signer) -> Tenant {
+        Tenant {
+            id: id.to_string(),
+            robots: vec![RobotId(robot.to_string())],
+            signing_keys: vec![hex::encode(signer.verifying_key().as_bytes())],
+            rate_limit: None,
+            quota: None,
+            trust: None,
+        }
+    }
+
+    fn genesis(robot: &str, signer: &Signer) -> VerifiedCheckpoint {
+        let checkpoint = CheckpointBuilder::test_fixture()
+            .robot_id(RobotId(robot.to_string()))
+            .timestamp(chrono::DateTime::UNIX_EPOCH + Duration::minutes(1))
+            .build_and_sign(signer.signing_key())
+            .unwrap();
+        ChainVerifier::new(signer.verifying_key())
+            .accept(checkpoint)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_tenants_see_only_their_robots_keys_and_storage() {
+        let (acme_signer, globex_signer) = (Signer::generate(), Signer::generate());
+        let mut globex = tenant("globex", "R-GLX", &globex_signer);
+        globex.trust = Some(TrustRequirements {
+            default: Some(TrustMode::Trusted),
+            classes: Default::default(),
+        });
+        let live = LiveConfig::new(GatewayConfig {
+            revision: 1,
+            trust: TrustRequirements::default(),
+            trust_anchors: TrustAnchors::default(),
+            rate_limit: None,
+            quota: None,
+            tenants: vec![tenant("acme", "R-ACM", &acme_signer), globex],
+            auth: AuthConfig::default(),
+        })
+        .unwrap();
+        let acme = TenantScope::open(&live, "acme").unwrap();
+        let globex = TenantScope::open(&live, "globex").unwrap();
+        let err = TenantScope::open(&live, "initech").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TenantUnknown);
+
+        // Each tenant verifies against its own keys and requirements
+        assert!(acme
+            .key_ring()
+            .unwrap()
+            .resolve(&acme_signer.key_id())
+            .is_some());
+        assert!(acme
+            .key_ring()
+            .unwrap()
+            .resolve(&globex_signer.key_id())
+            .is_none());
+        assert_eq!(acme.trust().default, None);
+        assert_eq!(globex.trust().default, Some(TrustMode::Trusted));
+        assert_ne!(acme.storage_prefix(), globex.storage_prefix());
+
+        // Neither reads nor writes the other's robots
+        let mut store = ShardedStore::new(Duration::hours(1));
+        acme.insert(&mut store, genesis("R-ACM", &acme_signer))
+            .unwrap();
+        let err = globex
+            .insert(&mut store, genesis("R-ACM", &acme_signer))
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TenantRobotOutside);
+        let robot = RobotId("R-ACM".to_string());
+        assert_eq!(acme.checkpoints(&store, &robot).unwrap().len(), 1);
+        assert!(globex.checkpoints(&store, &robot).is_err());
+        assert!(globex.page(&store, &CheckpointQuery::new(robot)).is_err());
+    }
+}
@@ -0,0 +1,366 @@
+//! Tombstones: deleting entry payloads without touching committed roots.
+//!
+//! Data minimization rules may require deleting a log record's payload long
+//! before the evidence around it expires. The Merkle tree only commits to
+//! each entry's `data_hash`, so the payload can go as long as the leaf stays:
+//! a [`Tombstone`] keeps the entry (timestamp, nonce, data hash), records why
+//! its payload was deleted and is signed by whoever approved the deletion.
+//!
+//! [`verify_stored_entries`] checks a checkpoint's stored entries, a mix of
+//! live payloads and tombstones, and confirms they still reproduce the
+//! checkpoint's `entries_root`.
+//!
+//! Erasure goes one step further and drops the entry itself, leaving only
+//! its leaf hash: see [`crate::erasure`].
+
+use crate::crypto::{ct_eq, sha256, DigestAlgorithm, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::merkle::{compute_merkle_root, Entry};
+use crate::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, KeyId, SignatureBytes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Tombstone version (for schema evolution)
+pub const TOMBSTONE_VERSION: u8 = 1;
+
+/// Why a payload was deleted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionReason {
+    /// Payload is no longer needed for the evidence's purpose
+    DataMinimization,
+    /// Retention period for the payload expired
+    RetentionExpired,
+    /// Erasure request from a data subject (free-form reference)
+    SubjectRequest(String),
+    /// Court order or regulator instruction (free-form reference)
+    LegalOrder(String),
+}
+
+/// Signed record that an entry's payload was deleted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tombstone {
+    /// Schema version
+    pub version: u8,
+    /// The entry whose payload was deleted (its leaf is unchanged)
+    pub entry: Entry,
+    /// `entries_root` of the checkpoint that committed the entry
+    pub entries_root: Hash256,
+    pub reason: DeletionReason,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub deleted_at: DateTime<Utc>,
+    /// Fingerprint of the approver's signing key
+    pub approver_key_id: KeyId,
+    /// Ed25519 signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned tombstone (for signature computation)
+#[derive(Serialize)]
+struct UnsignedTombstone<'a> {
+    version: u8,
+    entry: &'a Entry,
+    entries_root: Hash256,
+    reason: &'a DeletionReason,
+    #[serde(with = "crate::serialization::timestamp")]
+    deleted_at: DateTime<Utc>,
+    approver_key_id: KeyId,
+}
+
+impl Tombstone {
+    /// Approve deletion of `entry`'s payload.
+    pub fn issue(
+        entry: Entry,
+        entries_root: Hash256,
+        reason: DeletionReason,
+        approver: &Signer,
+    ) -> Result<Self, TombstoneError> {
+        let mut tombstone = Self {
+            version: TOMBSTONE_VERSION,
+            entry,
+            entries_root,
+            reason,
+            deleted_at: Utc::now(),
+            approver_key_id: approver.key_id(),
+            signature: SignatureBytes([0u8; 64]),
+        };
+        let signature = approver.sign(&tombstone.signing_payload()?);
+        tombstone.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(tombstone)
+    }
+
+    /// Verify the approver's signature.
+    pub fn verify(&self, approvers: &dyn KeyResolver) -> Result<(), TombstoneError> {
+        use ed25519_dalek::Verifier;
+
+        let verifying_key = approvers
+            .resolve(&self.approver_key_id)
+            .ok_or(TombstoneError::UnknownApprover(self.approver_key_id))?;
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        verifying_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| TombstoneError::InvalidSignature)
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Deserialize from canonical CBOR bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        from_canonical_cbor(bytes)
+    }
+
+    /// SHA-256 of the canonical CBOR encoding, signature included.
+    pub fn compute_hash(&self) -> Result<Hash256, SerializationError> {
+        Ok(sha256(&self.to_bytes()?))
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedTombstone {
+            version: self.version,
+            entry: &self.entry,
+            entries_root: self.entries_root,
+            reason: &self.reason,
+            deleted_at: self.deleted_at,
+            approver_key_id: self.approver_key_id,
+        })
+    }
+}
+
+/// An entry as kept in storage.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StoredEntry {
+    /// Entry with its payload still present
+    Live { entry: Entry, payload: Vec<u8> },
+    /// Payload deleted under a tombstone
+    Tombstoned(Tombstone),
+    /// Entry erased; only its leaf hash is kept
+    Erased(ErasedEntry),
+}
+
+/// What remains of an erased entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErasedEntry {
+    /// Leaf hash of the entry, as committed under the entries root
+    pub leaf_hash: Hash256,
+    /// Hash of the tombstone that approved deleting its payload
+    pub tombstone_hash: Hash256,
+}
+
+impl StoredEntry {
+    /// The committed entry, unless it was erased.
+    pub fn entry(&self) -> Option<&Entry> {
+        match self {
+            StoredEntry::Live { entry, .. } => Some(entry),
+            StoredEntry::Tombstoned(tombstone) => Some(&tombstone.entry),
+            StoredEntry::Erased(_) => None,
+        }
+    }
+
+    /// The entry's leaf hash.
+    pub fn leaf_hash(&self) -> Hash256 {
+        match self {
+            StoredEntry::Erased(erased) => erased.leaf_hash,
+            StoredEntry::Live { entry, .. } => entry.hash(),
+            StoredEntry::Tombstoned(tombstone) => tombstone.entry.hash(),
+        }
+    }
+
+    /// Replace the payload with a tombstone approved by `approver`.
+    pub fn tombstone(
+        self,
+        entries_root: Hash256,
+        reason: DeletionReason,
+        approver: &Signer,
+    ) -> Result<Self, TombstoneError> {
+        match self {
+            StoredEntry::Live { entry, .. } => Ok(StoredEntry::Tombstoned(Tombstone::issue(
+                entry,
+                entries_root,
+                reason,
+                approver,
+            )?)),
+            deleted @ (StoredEntry::Tombstoned(_) | StoredEntry::Erased(_)) => Ok(deleted),
+        }
+    }
+}
+
+/// Check a checkpoint's stored entries against its `entries_root`.
+///
+/// Entries are stored in tree order. Live payloads must match their data
+/// hash, tombstones must be approved by a known key for this root, and
+/// together the entries, erased ones by their leaf hash, must reproduce
+/// `entries_root` exactly (none added, dropped, reordered or altered).
+pub fn verify_stored_entries(
+    entries: &[StoredEntry],
+    entries_root: &Hash256,
+    approvers: &dyn KeyResolver,
+) -> Result<(), TombstoneError> {
+    let mut previous: Option<(u64, u64)> = None;
+    for stored in entries {
+        match stored {
+            StoredEntry::Live { entry, payload } => {
+                if !ct_eq(&sha256(payload), &entry.data_hash) {
+                    return Err(TombstoneError::PayloadMismatch {
+                        timestamp_us: entry.timestamp_us,
+                        nonce: entry.nonce,
+                    });
+                }
+            }
+            StoredEntry::Tombstoned(tombstone) => {
+                if !ct_eq(&tombstone.entries_root, entries_root) {
+                    return Err(TombstoneError::RootAltered);
+                }
+                tombstone.verify(approvers)?;
+            }
+            StoredEntry::Erased(_) => {}
+        }
+        if let Some(entry) = stored.entry() {
+            let key = (entry.timestamp_us, entry.nonce);
+            if previous.is_some_and(|previous| previous >= key) {
+                return Err(TombstoneError::RootAltered);
+            }
+            previous = Some(key);
+        }
+    }
+
+    let leaves: Vec<Hash256> = entries.iter().map(StoredEntry::leaf_hash).collect();
+    let root = if leaves.is_empty() {
+        [0u8; 32]
+    } else {
+        compute_merkle_root(DigestAlgorithm::Sha256, &leaves)
+    };
+    if !ct_eq(&root, entries_root) {
+        return Err(TombstoneError::RootAltered);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Error)]
+pub enum TombstoneError {
+    #[error("Tombstone serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Tombstone approved by unknown key {0}")]
+    UnknownApprover(KeyId),
+
+    #[error("Invalid tombstone signature")]
+    InvalidSignature,
+
+    #[error("Payload of entry ({timestamp_us}, {nonce}) does not match its data hash")]
+    PayloadMismatch { timestamp_us: u64, nonce: u64 },
+
+    #[error("Stored entries do not reproduce the committed entries root")]
+    RootAltered,
+}
+
+impl ErrorCoded for TombstoneError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            TombstoneError::Serialization(e) => e.code(),
+            TombstoneError::UnknownApprover(_) => ErrorCode::TombstoneUnknownApprover,
+            TombstoneError::InvalidSignature => ErrorCode::TombstoneInvalidSignature,
+            TombstoneError::PayloadMismatch { .. } => ErrorCode::PayloadMismatch,
+            TombstoneError::RootAltered => ErrorCode::EntriesRootAltered,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::MerkleTree;
+
+    fn stored(n: u64) -> (Vec<StoredEntry>, Hash256) {
+        let mut tree = MerkleTree::new();
+        let entries: Vec<StoredEntry> = (0..n)
+            .map(|i| {
+                let payload = format!("record {i}").into_bytes();
+                let entry = Entry::new(1000 + i, i, &payload);
+                tree.insert(entry.clone());
+                StoredEntry::Live { entry, payload }
+            })
+            .collect();
+        (entries, tree.root())
+    }
+
+    #[test]
+    fn test_tombstoned_entries_keep_root() {
+        let approver = Signer::generate();
+        let (mut entries, root) = stored(5);
+        entries[2] = entries[2]
+            .clone()
+            .tombstone(
+                root,
+                DeletionReason::SubjectRequest("DSR-17".to_string()),
+                &approver,
+            )
+            .unwrap();
+        assert!(matches!(entries[2], StoredEntry::Tombstoned(_)));
+
+        verify_stored_entries(&entries, &root, &approver.verifying_key()).unwrap();
+
+        let StoredEntry::Tombstoned(tombstone) = &entries[2] else {
+            unreachable!()
+        };
+        let decoded = Tombstone::from_bytes(&tombstone.to_bytes().unwrap()).unwrap();
+        assert_eq!(&decoded, tombstone);
+    }
+
+    #[test]
+    fn test_rejects_altered_payload_or_dropped_entry() {
+        let approver = Signer::generate();
+        let (mut entries, root) = stored(4);
+
+        let mut dropped = entries.clone();
+        dropped.remove(1);
+        let err = verify_stored_entries(&dropped, &root, &approver.verifying_key()).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::EntriesRootAltered);
+
+        if let StoredEntry::Live { payload, .. } = &mut entries[0] {
+            payload.push(b'!');
+        }
+        let err = verify_stored_entries(&entries, &root, &approver.verifying_key()).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::PayloadMismatch);
+    }
+
+    #[test]
+    fn test_rejects_forged_tombstones() {
+        let approver = Signer::generate();
+        let (mut entries, root) = stored(3);
+
+        // A tombstone cannot swap in a different leaf...
+        let mut forged = Tombstone::issue(
+            Entry::new(1001, 1, b"something else"),
+            root,
+            DeletionReason::DataMinimization,
+            &approver,
+        )
+        .unwrap();
+        entries[1] = StoredEntry::Tombstoned(forged.clone());
+        let err = verify_stored_entries(&entries, &root, &approver.verifying_key()).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::EntriesRootAltered);
+
+        // ...or be edited after approval, or come from an unknown approver.
+        let (entries, root) = stored(3);
+        forged.entry = entries[1].entry().unwrap().clone();
+        let mut tampered = entries.clone();
+        tampered[1] = StoredEntry::Tombstoned(forged);
+        let err = verify_stored_entries(&tampered, &root, &approver.verifying_key()).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TombstoneInvalidSignature);
+
+        let mut unknown = entries;
+        unknown[0] = unknown[0]
+            .clone()
+            .tombstone(root, DeletionReason::RetentionExpired, &Signer::generate())
+            .unwrap();
+        let err = verify_stored_entries(&unknown, &root, &approver.verifying_key()).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::TombstoneUnknownApprover);
+    }
+}
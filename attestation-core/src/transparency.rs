@@ -0,0 +1,720 @@
+//! Append-only transparency log over accepted checkpoints.
+//!
+//! The gateway appends every checkpoint it accepts and returns, with each
+//! served checkpoint, an inclusion proof against a signed tree head. Clients
+//! that compare tree heads (and check consistency proofs between them) can
+//! detect a gateway presenting different histories to different parties.
+//!
+//! Building a proof can lag submission (the gateway batches appends), so on
+//! submission the log returns a [`CheckpointTimestamp`] instead: a signed
+//! promise, like a Certificate Transparency SCT, to include the checkpoint
+//! within a maximum merge delay. It verifies on its own right away and is
+//! later held against the [`LoggedCheckpoint`] to check the promise was kept.
+//!
+//! ## Hashing (RFC 9162)
+//! - Leaf: `SHA-256(0x00 || canonical CBOR of the signed checkpoint)`
+//! - Node: `SHA-256(0x01 || left || right)`
+//! - Empty tree: `SHA-256("")`
+
+use crate::checkpoint::Checkpoint;
+use crate::clock::{ClockError, SkewDecision};
+use crate::crypto::{ct_eq, sha256, Signer};
+use crate::error::{ErrorCode, ErrorCoded};
+use crate::keys::KeyResolver;
+use crate::serialization::{to_canonical_cbor, SerializationError};
+use crate::types::{Hash256, KeyId, SignatureBytes};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Size and root hash of the log at some point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeHead {
+    pub tree_size: u64,
+    pub root_hash: Hash256,
+}
+
+/// A tree head signed by the log operator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedTreeHead {
+    pub tree_head: TreeHead,
+    #[serde(with = "crate::serialization::timestamp")]
+    pub timestamp: DateTime<Utc>,
+    /// Fingerprint of the log's signing key
+    pub signer_key_id: KeyId,
+    /// Ed25519 signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned tree head (for signature computation)
+#[derive(Serialize)]
+struct UnsignedTreeHead {
+    tree_head: TreeHead,
+    #[serde(with = "crate::serialization::timestamp")]
+    timestamp: DateTime<Utc>,
+    signer_key_id: KeyId,
+}
+
+impl SignedTreeHead {
+    /// Sign `tree_head` with the log key.
+    pub fn sign(tree_head: TreeHead, signer: &Signer) -> Result<Self, LogError> {
+        let mut sth = Self::unsigned(tree_head, signer.key_id());
+        let signature = signer.sign(&sth.signing_payload()?);
+        sth.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(sth)
+    }
+
+    /// A tree head with every field but the signature filled in.
+    pub(crate) fn unsigned(tree_head: TreeHead, signer_key_id: KeyId) -> Self {
+        Self {
+            tree_head,
+            timestamp: Utc::now(),
+            signer_key_id,
+            signature: SignatureBytes([0u8; 64]),
+        }
+    }
+
+    /// Verify the signature using a key looked up by `signer_key_id`.
+    pub fn verify(&self, logs: &dyn KeyResolver) -> Result<(), LogError> {
+        use ed25519_dalek::Verifier;
+
+        let verifying_key = logs
+            .resolve(&self.signer_key_id)
+            .ok_or(LogError::UnknownSigner(self.signer_key_id))?;
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        verifying_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| LogError::InvalidSignature)
+    }
+
+    pub(crate) fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedTreeHead {
+            tree_head: self.tree_head,
+            timestamp: self.timestamp,
+            signer_key_id: self.signer_key_id,
+        })
+    }
+}
+
+/// Checkpoint timestamp version (for schema evolution)
+pub const TIMESTAMP_VERSION: u8 = 1;
+
+/// Signed promise by the log to include a checkpoint within a merge delay.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointTimestamp {
+    /// Schema version
+    pub version: u8,
+    /// Log leaf hash of the submitted checkpoint
+    pub leaf_hash: Hash256,
+    /// When the log accepted the checkpoint
+    #[serde(with = "crate::serialization::timestamp")]
+    pub timestamp: DateTime<Utc>,
+    /// Longest time after `timestamp` until a tree head includes the checkpoint
+    pub max_merge_delay_secs: u64,
+    /// Fingerprint of the log's signing key
+    pub signer_key_id: KeyId,
+    /// Ed25519 signature over canonical CBOR of all fields above
+    pub signature: SignatureBytes,
+}
+
+/// Unsigned checkpoint timestamp (for signature computation)
+#[derive(Serialize)]
+struct UnsignedTimestamp {
+    version: u8,
+    leaf_hash: Hash256,
+    #[serde(with = "crate::serialization::timestamp")]
+    timestamp: DateTime<Utc>,
+    max_merge_delay_secs: u64,
+    signer_key_id: KeyId,
+}
+
+impl CheckpointTimestamp {
+    /// Promise to include `checkpoint` within `max_merge_delay`, signed with the log key.
+    pub fn issue(
+        checkpoint: &Checkpoint,
+        max_merge_delay: Duration,
+        signer: &Signer,
+    ) -> Result<Self, LogError> {
+        let mut timestamp = Self {
+            version: TIMESTAMP_VERSION,
+            leaf_hash: checkpoint_leaf_hash(checkpoint)?,
+            timestamp: Utc::now(),
+            max_merge_delay_secs: max_merge_delay.num_seconds().max(0) as u64,
+            signer_key_id: signer.key_id(),
+            signature: SignatureBytes([0u8; 64]),
+        };
+        let signature = signer.sign(&timestamp.signing_payload()?);
+        timestamp.signature = SignatureBytes::from(signature.to_bytes());
+        Ok(timestamp)
+    }
+
+    /// Latest time a tree head may include the checkpoint.
+    pub fn merge_deadline(&self) -> DateTime<Utc> {
+        self.timestamp + Duration::seconds(self.max_merge_delay_secs as i64)
+    }
+
+    /// Check that the promise covers `checkpoint` and is signed by a known log.
+    pub fn verify(&self, checkpoint: &Checkpoint, logs: &dyn KeyResolver) -> Result<(), LogError> {
+        use ed25519_dalek::Verifier;
+
+        if !ct_eq(&checkpoint_leaf_hash(checkpoint)?, &self.leaf_hash) {
+            return Err(LogError::TimestampMismatch);
+        }
+        let verifying_key = logs
+            .resolve(&self.signer_key_id)
+            .ok_or(LogError::UnknownSigner(self.signer_key_id))?;
+        let signature = ed25519_dalek::Signature::from_bytes(self.signature.as_ref());
+        verifying_key
+            .verify(&self.signing_payload()?, &signature)
+            .map_err(|_| LogError::InvalidSignature)
+    }
+
+    /// Check that `logged` keeps the promise: it verifies, is the same
+    /// checkpoint from the same log, and its tree head is no later than
+    /// [`Self::merge_deadline`].
+    pub fn check_merged(
+        &self,
+        logged: &LoggedCheckpoint,
+        logs: &dyn KeyResolver,
+    ) -> Result<(), LogError> {
+        self.verify(&logged.checkpoint, logs)?;
+        logged.verify(logs)?;
+        if logged.tree_head.signer_key_id != self.signer_key_id {
+            return Err(LogError::TimestampMismatch);
+        }
+        if logged.tree_head.timestamp > self.merge_deadline() {
+            return Err(LogError::MergeDelayExceeded {
+                deadline: self.merge_deadline(),
+                merged: logged.tree_head.timestamp,
+            });
+        }
+        Ok(())
+    }
+
+    fn signing_payload(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(&UnsignedTimestamp {
+            version: self.version,
+            leaf_hash: self.leaf_hash,
+            timestamp: self.timestamp,
+            max_merge_delay_secs: self.max_merge_delay_secs,
+            signer_key_id: self.signer_key_id,
+        })
+    }
+}
+
+/// Proof that a leaf is included in a tree of a given size.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub leaf_index: u64,
+    pub tree_size: u64,
+    pub path: Vec<Hash256>,
+}
+
+impl InclusionProof {
+    /// Check that `leaf_hash` is at `leaf_index` in the tree described by `head`.
+    pub fn verify(&self, leaf_hash: &Hash256, head: &TreeHead) -> Result<(), LogError> {
+        if self.tree_size != head.tree_size || self.leaf_index >= self.tree_size {
+            return Err(LogError::InclusionProofFailed);
+        }
+
+        let (mut fn_, mut sn) = (self.leaf_index, self.tree_size - 1);
+        let mut r = *leaf_hash;
+        for p in &self.path {
+            if sn == 0 {
+                return Err(LogError::InclusionProofFailed);
+            }
+            if fn_ & 1 == 1 || fn_ == sn {
+                r = node_hash(p, &r);
+                while fn_ & 1 == 0 && fn_ != 0 {
+                    fn_ >>= 1;
+                    sn >>= 1;
+                }
+            } else {
+                r = node_hash(&r, p);
+            }
+            fn_ >>= 1;
+            sn >>= 1;
+        }
+
+        if sn == 0 && ct_eq(&r, &head.root_hash) {
+            Ok(())
+        } else {
+            Err(LogError::InclusionProofFailed)
+        }
+    }
+}
+
+/// Proof that a smaller tree is a prefix of a larger one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsistencyProof {
+    pub first_size: u64,
+    pub second_size: u64,
+    pub path: Vec<Hash256>,
+}
+
+impl ConsistencyProof {
+    /// Check that `first` is a prefix of `second`.
+    pub fn verify(&self, first: &TreeHead, second: &TreeHead) -> Result<(), LogError> {
+        let fail = Err(LogError::ConsistencyProofFailed);
+        if self.first_size != first.tree_size
+            || self.second_size != second.tree_size
+            || first.tree_size > second.tree_size
+        {
+            return fail;
+        }
+        if first.tree_size == second.tree_size {
+            return if self.path.is_empty() && ct_eq(&first.root_hash, &second.root_hash) {
+                Ok(())
+            } else {
+                fail
+            };
+        }
+        if first.tree_size == 0 {
+            // The empty tree is a prefix of every tree
+            return if self.path.is_empty() { Ok(()) } else { fail };
+        }
+
+        let mut path = self.path.clone();
+        if first.tree_size.is_power_of_two() {
+            path.insert(0, first.root_hash);
+        }
+        let Some((&seed, rest)) = path.split_first() else {
+            return fail;
+        };
+
+        let (mut fn_, mut sn) = (first.tree_size - 1, second.tree_size - 1);
+        while fn_ & 1 == 1 {
+            fn_ >>= 1;
+            sn >>= 1;
+        }
+        let (mut fr, mut sr) = (seed, seed);
+        for c in rest {
+            if sn == 0 {
+                return fail;
+            }
+            if fn_ & 1 == 1 || fn_ == sn {
+                fr = node_hash(c, &fr);
+                sr = node_hash(c, &sr);
+                while fn_ & 1 == 0 && fn_ != 0 {
+                    fn_ >>= 1;
+                    sn >>= 1;
+                }
+            } else {
+                sr = node_hash(&sr, c);
+            }
+            fn_ >>= 1;
+            sn >>= 1;
+        }
+
+        if sn == 0 && ct_eq(&fr, &first.root_hash) & ct_eq(&sr, &second.root_hash) {
+            Ok(())
+        } else {
+            fail
+        }
+    }
+}
+
+/// A checkpoint as served by the gateway, with proof of its place in the log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoggedCheckpoint {
+    pub checkpoint: Checkpoint,
+    pub inclusion: InclusionProof,
+    pub tree_head: SignedTreeHead,
+    /// The gateway's judgement of the checkpoint timestamp, if it applies a skew policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<SkewDecision>,
+}
+
+impl LoggedCheckpoint {
+    /// Verify the tree head signature and the checkpoint's inclusion under it,
+    /// plus the skew decision if one is attached.
+    pub fn verify(&self, logs: &dyn KeyResolver) -> Result<(), LogError> {
+        self.tree_head.verify(logs)?;
+        let leaf = checkpoint_leaf_hash(&self.checkpoint)?;
+        self.inclusion.verify(&leaf, &self.tree_head.tree_head)?;
+        if let Some(clock) = &self.clock {
+            clock.verify(&self.checkpoint, logs)?;
+        }
+        Ok(())
+    }
+}
+
+/// In-memory append-only Merkle log.
+///
+/// Stores leaf hashes only; subtree hashes are recomputed on demand.
+#[derive(Debug, Clone, Default)]
+pub struct TransparencyLog {
+    leaves: Vec<Hash256>,
+}
+
+impl TransparencyLog {
+    /// Create an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a leaf hash, returning its index.
+    pub fn append_leaf(&mut self, leaf_hash: Hash256) -> u64 {
+        self.leaves.push(leaf_hash);
+        self.leaves.len() as u64 - 1
+    }
+
+    /// Append a checkpoint, returning its index.
+    pub fn append_checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<u64, LogError> {
+        Ok(self.append_leaf(checkpoint_leaf_hash(checkpoint)?))
+    }
+
+    /// Number of leaves in the log.
+    pub fn len(&self) -> u64 {
+        self.leaves.len() as u64
+    }
+
+    /// Check if the log is empty.
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Leaf hash at `index`.
+    pub fn leaf(&self, index: u64) -> Option<Hash256> {
+        self.leaves.get(index as usize).copied()
+    }
+
+    /// Index of the first leaf equal to `leaf_hash`.
+    pub fn position(&self, leaf_hash: &Hash256) -> Option<u64> {
+        self.leaves
+            .iter()
+            .position(|l| l == leaf_hash)
+            .map(|i| i as u64)
+    }
+
+    /// Current tree head.
+    pub fn tree_head(&self) -> TreeHead {
+        self.tree_head_at(self.len())
+            .expect("current size is always valid")
+    }
+
+    /// Tree head of the log when it had `tree_size` leaves.
+    pub fn tree_head_at(&self, tree_size: u64) -> Result<TreeHead, LogError> {
+        let leaves = self.prefix(tree_size)?;
+        Ok(TreeHead {
+            tree_size,
+            root_hash: subtree_root(leaves),
+        })
+    }
+
+    /// Inclusion proof for the leaf at `index` in the tree of size `tree_size`.
+    pub fn inclusion_proof(&self, index: u64, tree_size: u64) -> Result<InclusionProof, LogError> {
+        let leaves = self.prefix(tree_size)?;
+        if index >= tree_size {
+            return Err(LogError::IndexOutOfRange {
+                index,
+                size: tree_size,
+            });
+        }
+        Ok(InclusionProof {
+            leaf_index: index,
+            tree_size,
+            path: inclusion_path(index as usize, leaves),
+        })
+    }
+
+    /// Consistency proof between the trees of size `first_size` and `second_size`.
+    pub fn consistency_proof(
+        &self,
+        first_size: u64,
+        second_size: u64,
+    ) -> Result<ConsistencyProof, LogError> {
+        let leaves = self.prefix(second_size)?;
+        if first_size > second_size {
+            return Err(LogError::IndexOutOfRange {
+                index: first_size,
+                size: second_size,
+            });
+        }
+        let path = if first_size == 0 || first_size == second_size {
+            Vec::new()
+        } else {
+            consistency_path(first_size as usize, leaves, true)
+        };
+        Ok(ConsistencyProof {
+            first_size,
+            second_size,
+            path,
+        })
+    }
+
+    /// Package the checkpoint at `index` with its inclusion proof under a fresh signed tree head.
+    pub fn serve(
+        &self,
+        index: u64,
+        checkpoint: Checkpoint,
+        signer: &Signer,
+    ) -> Result<LoggedCheckpoint, LogError> {
+        let tree_head = SignedTreeHead::sign(self.tree_head(), signer)?;
+        Ok(LoggedCheckpoint {
+            inclusion: self.inclusion_proof(index, tree_head.tree_head.tree_size)?,
+            checkpoint,
+            tree_head,
+            clock: None,
+        })
+    }
+
+    fn prefix(&self, tree_size: u64) -> Result<&[Hash256], LogError> {
+        self.leaves
+            .get(..tree_size as usize)
+            .ok_or(LogError::IndexOutOfRange {
+                index: tree_size,
+                size: self.len(),
+            })
+    }
+}
+
+/// Leaf hash of a checkpoint in the log.
+pub fn checkpoint_leaf_hash(checkpoint: &Checkpoint) -> Result<Hash256, SerializationError> {
+    Ok(leaf_hash(&checkpoint.to_bytes()?))
+}
+
+/// RFC 9162 leaf hash.
+pub fn leaf_hash(data: &[u8]) -> Hash256 {
+    let mut buf = Vec::with_capacity(1 + data.len());
+    buf.push(0x00);
+    buf.extend_from_slice(data);
+    sha256(&buf)
+}
+
+fn node_hash(left: &Hash256, right: &Hash256) -> Hash256 {
+    let mut buf = Vec::with_capacity(65);
+    buf.push(0x01);
+    buf.extend_from_slice(left);
+    buf.extend_from_slice(right);
+    sha256(&buf)
+}
+
+/// Largest power of two strictly less than `n` (n > 1).
+fn split_point(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+fn subtree_root(leaves: &[Hash256]) -> Hash256 {
+    match leaves.len() {
+        0 => sha256(&[]),
+        1 => leaves[0],
+        n => {
+            let k = split_point(n);
+            node_hash(&subtree_root(&leaves[..k]), &subtree_root(&leaves[k..]))
+        }
+    }
+}
+
+fn inclusion_path(index: usize, leaves: &[Hash256]) -> Vec<Hash256> {
+    let n = leaves.len();
+    if n <= 1 {
+        return Vec::new();
+    }
+    let k = split_point(n);
+    if index < k {
+        let mut path = inclusion_path(index, &leaves[..k]);
+        path.push(subtree_root(&leaves[k..]));
+        path
+    } else {
+        let mut path = inclusion_path(index - k, &leaves[k..]);
+        path.push(subtree_root(&leaves[..k]));
+        path
+    }
+}
+
+fn consistency_path(m: usize, leaves: &[Hash256], complete: bool) -> Vec<Hash256> {
+    let n = leaves.len();
+    if m == n {
+        return if complete {
+            Vec::new()
+        } else {
+            vec![subtree_root(leaves)]
+        };
+    }
+    let k = split_point(n);
+    if m <= k {
+        let mut path = consistency_path(m, &leaves[..k], complete);
+        path.push(subtree_root(&leaves[k..]));
+        path
+    } else {
+        let mut path = consistency_path(m - k, &leaves[k..], false);
+        path.push(subtree_root(&leaves[..k]));
+        path
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LogError {
+    #[error("Log serialization failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Tree head signed by unknown log key {0}")]
+    UnknownSigner(KeyId),
+
+    #[error("Invalid tree head signature")]
+    InvalidSignature,
+
+    #[error("Inclusion proof does not match the tree head")]
+    InclusionProofFailed,
+
+    #[error("Consistency proof does not link the tree heads")]
+    ConsistencyProofFailed,
+
+    #[error("Index {index} out of range for log of size {size}")]
+    IndexOutOfRange { index: u64, size: u64 },
+
+    #[error("Checkpoint timestamp rejected: {0}")]
+    Clock(#[from] ClockError),
+
+    #[error("Timestamp receipt covers a different checkpoint or log")]
+    TimestampMismatch,
+
+    #[error("Checkpoint merged at {merged}, after the promised deadline {deadline}")]
+    MergeDelayExceeded {
+        deadline: DateTime<Utc>,
+        merged: DateTime<Utc>,
+    },
+}
+
+impl ErrorCoded for LogError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            LogError::Serialization(e) => e.code(),
+            LogError::UnknownSigner(_) => ErrorCode::LogUnknownSigner,
+            LogError::InvalidSignature => ErrorCode::LogInvalidSignature,
+            LogError::InclusionProofFailed => ErrorCode::InclusionProofInvalid,
+            LogError::ConsistencyProofFailed => ErrorCode::ConsistencyProofInvalid,
+            LogError::IndexOutOfRange { .. } => ErrorCode::LogIndexOutOfRange,
+            LogError::Clock(e) => e.code(),
+            LogError::TimestampMismatch => ErrorCode::LogTimestampMismatch,
+            LogError::MergeDelayExceeded { .. } => ErrorCode::LogMergeDelayExceeded,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_of(n: u8) -> TransparencyLog {
+        let mut log = TransparencyLog::new();
+        for i in 0..n {
+            log.append_leaf(leaf_hash(&[i]));
+        }
+        log
+    }
+
+    #[test]
+    fn test_inclusion_proofs() {
+        let log = log_of(7);
+        for size in 1..=7 {
+            let head = log.tree_head_at(size).unwrap();
+            for index in 0..size {
+                let proof = log.inclusion_proof(index, size).unwrap();
+                proof.verify(&log.leaf(index).unwrap(), &head).unwrap();
+            }
+        }
+
+        let head = log.tree_head();
+        let proof = log.inclusion_proof(3, 7).unwrap();
+        assert!(proof.verify(&leaf_hash(b"other"), &head).is_err());
+    }
+
+    #[test]
+    fn test_consistency_proofs() {
+        let log = log_of(9);
+        for second in 0..=9 {
+            for first in 0..=second {
+                let proof = log.consistency_proof(first, second).unwrap();
+                let (a, b) = (
+                    log.tree_head_at(first).unwrap(),
+                    log.tree_head_at(second).unwrap(),
+                );
+                proof.verify(&a, &b).unwrap();
+            }
+        }
+
+        // A forked history is not consistent with the original
+        let mut forked = log_of(4);
+        forked.append_leaf(leaf_hash(b"fork"));
+        let proof = log.consistency_proof(4, 5).unwrap();
+        let err = proof
+            .verify(&log.tree_head_at(4).unwrap(), &forked.tree_head())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ConsistencyProofInvalid);
+    }
+
+    fn checkpoint(robot: &Signer) -> Checkpoint {
+        use crate::checkpoint::CheckpointBuilder;
+
+        CheckpointBuilder::test_fixture()
+            .sequence(1)
+            .build_and_sign(robot.signing_key())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_served_checkpoint() {
+        let robot = Signer::generate();
+        let checkpoint = checkpoint(&robot);
+
+        let gateway = Signer::generate();
+        let mut log = log_of(3);
+        let index = log.append_checkpoint(&checkpoint).unwrap();
+        let served = log.serve(index, checkpoint, &gateway).unwrap();
+        served.verify(&gateway.verifying_key()).unwrap();
+
+        let mut tampered = served.clone();
+        tampered.checkpoint.sequence = 2;
+        assert!(matches!(
+            tampered.verify(&gateway.verifying_key()),
+            Err(LogError::InclusionProofFailed)
+        ));
+        assert!(matches!(
+            served.verify(&robot.verifying_key()),
+            Err(LogError::UnknownSigner(_))
+        ));
+    }
+
+    #[test]
+    fn test_checkpoint_timestamp_promise() {
+        let robot = Signer::generate();
+        let gateway = Signer::generate();
+        let checkpoint = checkpoint(&robot);
+
+        // Verifiable on submission, before any proof exists
+        let promise =
+            CheckpointTimestamp::issue(&checkpoint, Duration::hours(1), &gateway).unwrap();
+        promise
+            .verify(&checkpoint, &gateway.verifying_key())
+            .unwrap();
+        let mut other = checkpoint.clone();
+        other.sequence = 2;
+        let err = promise
+            .verify(&other, &gateway.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::LogTimestampMismatch);
+
+        let mut log = log_of(3);
+        let index = log.append_checkpoint(&checkpoint).unwrap();
+        let served = log.serve(index, checkpoint, &gateway).unwrap();
+        promise
+            .check_merged(&served, &gateway.verifying_key())
+            .unwrap();
+
+        // A tree head past the deadline breaks the promise
+        let mut late = promise.clone();
+        late.timestamp -= Duration::hours(2);
+        late.signature =
+            SignatureBytes::from(gateway.sign(&late.signing_payload().unwrap()).to_bytes());
+        let err = late
+            .check_merged(&served, &gateway.verifying_key())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::LogMergeDelayExceeded);
+    }
+}
@@ -1,6 +1,7 @@
 //! Core types used across the attestation system.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
 
 /// SHA-256 hash (32 bytes)
@@ -32,11 +33,25 @@ mod serde_arrays {
 
     pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 64], D::Error> {
         let bytes: Vec<u8> = Vec::deserialize(deserializer)?;
-        bytes.try_into()
+        bytes
+            .try_into()
             .map_err(|_| serde::de::Error::custom("Invalid signature length"))
     }
 }
 
+/// Key identifier: SHA-256 fingerprint of an Ed25519 verifying key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct KeyId(pub Hash256);
+
+impl fmt::Display for KeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
 /// Robot identifier (unique per robot)
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct RobotId(pub String);
@@ -69,6 +84,21 @@ pub enum TrustMode {
     Untrusted,
 }
 
+impl TrustMode {
+    /// Whether this mode gives at least the assurance of `required`.
+    pub fn satisfies(self, required: TrustMode) -> bool {
+        self.rank() >= required.rank()
+    }
+
+    fn rank(self) -> u8 {
+        match self {
+            TrustMode::Trusted => 2,
+            TrustMode::SoftAttestation => 1,
+            TrustMode::Untrusted => 0,
+        }
+    }
+}
+
 impl fmt::Display for TrustMode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -121,14 +151,101 @@ pub struct AttestationResult {
     pub quote_verified: bool,
     /// Timestamp of verification
     pub verified_at: chrono::DateTime<chrono::Utc>,
-    /// Revocation check status
-    pub revoke_check: RevocationStatus,
+    /// Revocation check outcome
+    pub revoke_check: RevocationCheck,
     /// Raw attestation quote (vendor-specific format)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub raw_quote: Option<Vec<u8>>,
     /// PCK certificate chain (Intel SGX only)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pck_chain: Option<String>,
+    /// Vendor claims extracted from the evidence (PCRs, TCB components, RTMRs, advisory IDs)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub claims: BTreeMap<String, ClaimValue>,
+}
+
+impl AttestationResult {
+    /// Look up a claim by key (e.g., "sgx.mr_signer", "tpm.pcr.7").
+    pub fn claim(&self, key: &str) -> Option<&ClaimValue> {
+        self.claims.get(key)
+    }
+}
+
+/// Typed value of an attestation claim.
+///
+/// Keys are namespaced by vendor (e.g., "sgx.isv_svn", "tdx.rtmr.0") so policy
+/// evaluation can work across adapters without downcasting vendor types.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimValue {
+    /// Boolean flag (e.g., debug mode)
+    Bool(bool),
+    /// Unsigned integer (e.g., SVNs, product IDs)
+    Uint(u64),
+    /// Text value (e.g., TCB status, FMSPC)
+    Text(String),
+    /// Raw bytes (e.g., measurements, PCR values)
+    Bytes(Vec<u8>),
+    /// List of values (e.g., advisory IDs, TCB components)
+    List(Vec<ClaimValue>),
+}
+
+impl ClaimValue {
+    /// Get the value as a bool, if it is one.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            ClaimValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Get the value as an unsigned integer, if it is one.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            ClaimValue::Uint(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Get the value as text, if it is text.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ClaimValue::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Get the value as bytes, if it is a byte string.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            ClaimValue::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for ClaimValue {
+    fn from(value: bool) -> Self {
+        ClaimValue::Bool(value)
+    }
+}
+
+impl From<u64> for ClaimValue {
+    fn from(value: u64) -> Self {
+        ClaimValue::Uint(value)
+    }
+}
+
+impl From<String> for ClaimValue {
+    fn from(value: String) -> Self {
+        ClaimValue::Text(value)
+    }
+}
+
+impl From<Vec<u8>> for ClaimValue {
+    fn from(value: Vec<u8>) -> Self {
+        ClaimValue::Bytes(value)
+    }
 }
 
 /// Revocation status for attestation
@@ -143,6 +260,127 @@ pub enum RevocationStatus {
     Unknown,
 }
 
+/// Detailed outcome of a revocation check.
+///
+/// Carries enough context for policy to distinguish "never checked" from
+/// "checked against a stale CRL" from "actively revoked".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationCheck {
+    /// Overall revocation status
+    pub status: RevocationStatus,
+    /// Where the revocation decision came from
+    pub source: RevocationSource,
+    /// Reason codes (empty unless revoked)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reasons: Vec<RevocationReason>,
+    /// When the revocation took effect (if revoked and known)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Validity window of the CRL consulted (CRL source only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crl_freshness: Option<CrlFreshness>,
+}
+
+impl RevocationCheck {
+    /// Not revoked according to `source`.
+    pub fn ok(source: RevocationSource) -> Self {
+        Self {
+            status: RevocationStatus::Ok,
+            source,
+            reasons: Vec::new(),
+            revoked_at: None,
+            crl_freshness: None,
+        }
+    }
+
+    /// Revoked by `source` for the given reason.
+    pub fn revoked(
+        source: RevocationSource,
+        reason: RevocationReason,
+        revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Self {
+        Self {
+            status: RevocationStatus::Revoked,
+            source,
+            reasons: vec![reason],
+            revoked_at,
+            crl_freshness: None,
+        }
+    }
+
+    /// Revocation status could not be determined.
+    pub fn unknown(source: RevocationSource) -> Self {
+        Self {
+            status: RevocationStatus::Unknown,
+            source,
+            reasons: Vec::new(),
+            revoked_at: None,
+            crl_freshness: None,
+        }
+    }
+
+    /// Attach the validity window of the CRL that was consulted.
+    pub fn with_crl_freshness(mut self, freshness: CrlFreshness) -> Self {
+        self.crl_freshness = Some(freshness);
+        self
+    }
+
+    /// Whether the subject is actively revoked.
+    pub fn is_revoked(&self) -> bool {
+        self.status == RevocationStatus::Revoked
+    }
+
+    /// Whether the decision was based on a CRL past its next-update time.
+    pub fn is_stale(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.crl_freshness.as_ref().is_some_and(|f| f.is_stale(now))
+    }
+}
+
+/// Source of a revocation decision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationSource {
+    /// Vendor certificate revocation list
+    Crl,
+    /// Local or fleet revocation registry
+    Registry,
+    /// On-chain revocation (RobotAttestationRegistry)
+    OnChain,
+    /// No revocation source was consulted
+    NotChecked,
+}
+
+/// Reason code for a revocation (RFC 5280 reason codes plus fleet-specific ones).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationReason {
+    Unspecified,
+    KeyCompromise,
+    CaCompromise,
+    Superseded,
+    CessationOfOperation,
+    /// Enclave measurement revoked by governance
+    MeasurementRevoked,
+    /// Platform TCB is out of date
+    TcbOutOfDate,
+}
+
+/// Validity window of a CRL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrlFreshness {
+    /// CRL issue time
+    pub this_update: chrono::DateTime<chrono::Utc>,
+    /// Time by which a newer CRL should be published
+    pub next_update: chrono::DateTime<chrono::Utc>,
+}
+
+impl CrlFreshness {
+    /// Whether the CRL is past its next-update time.
+    pub fn is_stale(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        now > self.next_update
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -159,4 +397,73 @@ mod tests {
         let id = RobotId("R-001".to_string());
         assert_eq!(id.to_string(), "R-001");
     }
+
+    #[test]
+    fn test_claim_value_accessors() {
+        assert_eq!(ClaimValue::from(7u64).as_u64(), Some(7));
+        assert_eq!(ClaimValue::from(true).as_bool(), Some(true));
+        assert_eq!(
+            ClaimValue::from("UpToDate".to_string()).as_str(),
+            Some("UpToDate")
+        );
+        assert_eq!(
+            ClaimValue::from(vec![1u8, 2]).as_bytes(),
+            Some(&[1u8, 2][..])
+        );
+        assert_eq!(ClaimValue::Uint(1).as_str(), None);
+    }
+
+    #[test]
+    fn test_revocation_check_staleness() {
+        let now = chrono::Utc::now();
+        let fresh = CrlFreshness {
+            this_update: now - chrono::Duration::hours(1),
+            next_update: now + chrono::Duration::hours(1),
+        };
+        let stale = CrlFreshness {
+            this_update: now - chrono::Duration::days(2),
+            next_update: now - chrono::Duration::days(1),
+        };
+
+        let check = RevocationCheck::ok(RevocationSource::Crl).with_crl_freshness(fresh);
+        assert!(!check.is_stale(now));
+        assert!(!check.is_revoked());
+
+        let check = RevocationCheck::ok(RevocationSource::Crl).with_crl_freshness(stale);
+        assert!(check.is_stale(now));
+
+        let check = RevocationCheck::revoked(
+            RevocationSource::OnChain,
+            RevocationReason::KeyCompromise,
+            Some(now),
+        );
+        assert!(check.is_revoked());
+        assert_eq!(check.reasons, vec![RevocationReason::KeyCompromise]);
+    }
+
+    #[test]
+    fn test_claims_roundtrip() {
+        let mut claims = BTreeMap::new();
+        claims.insert("sgx.isv_svn".to_string(), ClaimValue::Uint(3));
+        claims.insert(
+            "sgx.advisory_ids".to_string(),
+            ClaimValue::List(vec![ClaimValue::Text("INTEL-SA-00615".to_string())]),
+        );
+
+        let result = AttestationResult {
+            vendor: "intel-sgx".to_string(),
+            enclave_measurement: vec![0u8; 32],
+            quote_verified: true,
+            verified_at: chrono::Utc::now(),
+            revoke_check: RevocationCheck::ok(RevocationSource::Crl),
+            raw_quote: None,
+            pck_chain: None,
+            claims,
+        };
+
+        let bytes = crate::serialization::to_canonical_cbor(&result).unwrap();
+        let decoded: AttestationResult = crate::serialization::from_canonical_cbor(&bytes).unwrap();
+        assert_eq!(decoded.claim("sgx.isv_svn"), Some(&ClaimValue::Uint(3)));
+        assert_eq!(decoded.claims, result.claims);
+    }
 }
@@ -0,0 +1,342 @@
+//! Programmable fake attestation adapter for development and tests.
+//!
+//! [`MockAdapter`] verifies [`MockQuote`]s instead of vendor evidence, so
+//! gateways, policy engines and fleet tooling can be exercised end to end
+//! without TEE hardware or network access to a vendor service. Its behaviour
+//! is driven through a [`MockControl`] handle, which stays usable after the
+//! adapter is boxed into an
+//! [`AttestationRegistry`](attestation_core::AttestationRegistry):
+//!
+//! - **Measurements**: accept any, or only those allowed
+//! - **Revocation**: revoke and reinstate measurements, or make the
+//!   revocation source unavailable
+//! - **Failures**: fail the next calls, or every call, with a chosen error
+//! - **Latency**: delay every verification
+//!
+//! Never register it in production: a mock quote proves nothing.
+
+pub mod quote;
+
+pub use quote::MockQuote;
+
+use async_trait::async_trait;
+use attestation_core::{
+    system_clock, AttestationAdapter, AttestationError, AttestationResult, ClaimValue, Clock,
+    RevocationCheck, RevocationReason, RevocationSource,
+};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Vendor name the adapter registers under by default.
+pub const VENDOR: &str = "mock";
+
+/// Error an injected failure produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// [`AttestationError::InvalidQuote`]
+    InvalidQuote,
+    /// [`AttestationError::VerificationFailed`]
+    VerificationFailed,
+    /// [`AttestationError::Network`], which retry policies treat as transient
+    Network,
+    /// [`AttestationError::Internal`]
+    Internal,
+}
+
+impl Fault {
+    fn error(self) -> AttestationError {
+        let message = "injected by mock adapter".to_string();
+        match self {
+            Fault::InvalidQuote => AttestationError::InvalidQuote(message),
+            Fault::VerificationFailed => AttestationError::VerificationFailed(message),
+            Fault::Network => AttestationError::Network(message),
+            Fault::Internal => AttestationError::Internal(message),
+        }
+    }
+}
+
+/// Adapter behaviour, shared between the adapter and its controls.
+#[derive(Debug, Default)]
+struct MockState {
+    allowed: Vec<[u8; 32]>,
+    revoked: BTreeMap<[u8; 32], RevocationReason>,
+    revocation_unavailable: bool,
+    next_faults: VecDeque<Fault>,
+    fault: Option<Fault>,
+    update_fault: Option<Fault>,
+    latency: Duration,
+    verify_calls: usize,
+}
+
+/// Handle for programming a [`MockAdapter`].
+#[derive(Debug, Clone, Default)]
+pub struct MockControl {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockControl {
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Accept `measurement`. Until one is allowed, every measurement is.
+    pub fn allow_measurement(&self, measurement: [u8; 32]) {
+        self.state().allowed.push(measurement);
+    }
+
+    /// Report `measurement` as revoked by the registry.
+    pub fn revoke(&self, measurement: [u8; 32], reason: RevocationReason) {
+        self.state().revoked.insert(measurement, reason);
+    }
+
+    /// Undo [`revoke`](Self::revoke).
+    pub fn reinstate(&self, measurement: &[u8; 32]) {
+        self.state().revoked.remove(measurement);
+    }
+
+    /// Report every revocation status as unknown, as if the source were down.
+    pub fn set_revocation_unavailable(&self, unavailable: bool) {
+        self.state().revocation_unavailable = unavailable;
+    }
+
+    /// Fail the next verification with `fault`. Queued faults are used up
+    /// in order, before any [`set_fault`](Self::set_fault).
+    pub fn fail_next(&self, fault: Fault) {
+        self.state().next_faults.push_back(fault);
+    }
+
+    /// Fail every verification with `fault`, or stop failing with `None`.
+    pub fn set_fault(&self, fault: Option<Fault>) {
+        self.state().fault = fault;
+    }
+
+    /// Fail every trust anchor update with `fault`, or stop with `None`.
+    pub fn set_update_fault(&self, fault: Option<Fault>) {
+        self.state().update_fault = fault;
+    }
+
+    /// Delay every verification by `latency`.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state().latency = latency;
+    }
+
+    /// Verifications attempted so far, including failed ones.
+    pub fn verify_calls(&self) -> usize {
+        self.state().verify_calls
+    }
+}
+
+/// Fake attestation adapter verifying [`MockQuote`]s.
+pub struct MockAdapter {
+    vendor: String,
+    control: MockControl,
+    /// Source of verification times
+    clock: Arc<dyn Clock>,
+}
+
+impl MockAdapter {
+    /// Adapter registering as [`VENDOR`] that accepts any measurement.
+    pub fn new() -> Self {
+        Self {
+            vendor: VENDOR.to_string(),
+            control: MockControl::default(),
+            clock: system_clock(),
+        }
+    }
+
+    /// Register under `vendor` instead, e.g. to stand in for "intel-sgx".
+    pub fn with_vendor(mut self, vendor: impl Into<String>) -> Self {
+        self.vendor = vendor.into();
+        self
+    }
+
+    /// Read verification times from `clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Handle for programming the adapter.
+    pub fn control(&self) -> MockControl {
+        self.control.clone()
+    }
+}
+
+impl Default for MockAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl AttestationAdapter for MockAdapter {
+    fn vendor_name(&self) -> &str {
+        &self.vendor
+    }
+
+    async fn verify_quote(
+        &self,
+        quote: &[u8],
+        nonce: Option<&[u8]>,
+    ) -> Result<AttestationResult, AttestationError> {
+        let (fault, latency) = {
+            let mut state = self.control.state();
+            state.verify_calls += 1;
+            let fault = state.next_faults.pop_front().or(state.fault);
+            (fault, state.latency)
+        };
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+        if let Some(fault) = fault {
+            tracing::debug!("Mock adapter failing verification with {fault:?}");
+            return Err(fault.error());
+        }
+
+        let parsed =
+            MockQuote::parse(quote).map_err(|e| AttestationError::InvalidQuote(e.to_string()))?;
+        if nonce.is_some_and(|nonce| nonce != parsed.nonce.as_slice()) {
+            return Err(AttestationError::VerificationFailed(
+                "Quote nonce does not match the challenge".to_string(),
+            ));
+        }
+        let allowed = {
+            let allowed = &self.control.state().allowed;
+            allowed.is_empty() || allowed.contains(&parsed.measurement)
+        };
+        if !allowed {
+            return Err(AttestationError::VerificationFailed(
+                "Measurement is not allowed".to_string(),
+            ));
+        }
+
+        let revoke_check = self.check_revocation(&parsed.measurement).await?;
+        let mut claims = parsed.claims;
+        claims.insert(
+            "mock.measurement".to_string(),
+            ClaimValue::Bytes(parsed.measurement.to_vec()),
+        );
+        Ok(AttestationResult {
+            vendor: self.vendor.clone(),
+            enclave_measurement: parsed.measurement.to_vec(),
+            quote_verified: true,
+            verified_at: self.clock.now(),
+            revoke_check,
+            raw_quote: Some(quote.to_vec()),
+            pck_chain: None,
+            claims,
+        })
+    }
+
+    async fn check_revocation(
+        &self,
+        measurement: &[u8],
+    ) -> Result<RevocationCheck, AttestationError> {
+        let state = self.control.state();
+        if state.revocation_unavailable {
+            return Ok(RevocationCheck::unknown(RevocationSource::Registry));
+        }
+        let revoked = <[u8; 32]>::try_from(measurement)
+            .ok()
+            .and_then(|measurement| state.revoked.get(&measurement).copied());
+        Ok(match revoked {
+            Some(reason) => RevocationCheck::revoked(RevocationSource::Registry, reason, None),
+            None => RevocationCheck::ok(RevocationSource::Registry),
+        })
+    }
+
+    async fn root_ca_certs(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    async fn update_trust_anchors(&self) -> Result<(), AttestationError> {
+        match self.control.state().update_fault {
+            Some(fault) => Err(fault.error()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use attestation_core::{AttestationRegistry, RevocationStatus};
+
+    #[tokio::test]
+    async fn test_programmed_measurements_and_revocation() {
+        let adapter = MockAdapter::new().with_vendor("intel-sgx");
+        let control = adapter.control();
+        let mut registry = AttestationRegistry::new();
+        registry.register(Box::new(adapter));
+
+        let quote = MockQuote::new([1u8; 32])
+            .with_nonce(b"challenge")
+            .with_claim("sgx.isv_svn", 3u64)
+            .to_bytes()
+            .unwrap();
+        let result = registry
+            .verify_quote("intel-sgx", &quote, Some(b"challenge"))
+            .await
+            .unwrap();
+        assert_eq!(result.enclave_measurement, vec![1u8; 32]);
+        assert_eq!(result.claim("sgx.isv_svn"), Some(&ClaimValue::Uint(3)));
+        assert_eq!(result.revoke_check.status, RevocationStatus::Ok);
+
+        control.revoke([1u8; 32], RevocationReason::KeyCompromise);
+        let result = registry
+            .verify_quote("intel-sgx", &quote, None)
+            .await
+            .unwrap();
+        assert!(result.revoke_check.is_revoked());
+        control.set_revocation_unavailable(true);
+        let result = registry
+            .verify_quote("intel-sgx", &quote, None)
+            .await
+            .unwrap();
+        assert_eq!(result.revoke_check.status, RevocationStatus::Unknown);
+
+        control.allow_measurement([2u8; 32]);
+        let err = registry
+            .verify_quote("intel-sgx", &quote, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AttestationError::VerificationFailed(_)));
+        let err = registry
+            .verify_quote("intel-sgx", b"not a quote", None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AttestationError::InvalidQuote(_)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failure_injection_and_latency() {
+        let adapter = MockAdapter::new();
+        let control = adapter.control();
+        let quote = MockQuote::new([1u8; 32]).to_bytes().unwrap();
+
+        control.fail_next(Fault::Network);
+        control.fail_next(Fault::InvalidQuote);
+        assert!(matches!(
+            adapter.verify_quote(&quote, None).await,
+            Err(AttestationError::Network(_))
+        ));
+        assert!(matches!(
+            adapter.verify_quote(&quote, None).await,
+            Err(AttestationError::InvalidQuote(_))
+        ));
+        assert!(adapter.verify_quote(&quote, None).await.is_ok());
+
+        control.set_fault(Some(Fault::Internal));
+        assert!(adapter.verify_quote(&quote, None).await.is_err());
+        control.set_fault(None);
+        control.set_update_fault(Some(Fault::Network));
+        assert!(adapter.update_trust_anchors().await.is_err());
+
+        control.set_latency(Duration::from_secs(2));
+        let started = tokio::time::Instant::now();
+        adapter.verify_quote(&quote, None).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_secs(2));
+        assert_eq!(control.verify_calls(), 5);
+    }
+}
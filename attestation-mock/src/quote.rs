@@ -0,0 +1,70 @@
+//! Mock quotes.
+//!
+//! A [`MockQuote`] stands in for vendor evidence: it states its measurement,
+//! nonce and claims outright, with nothing signed. Tests build one, send its
+//! canonical CBOR wherever a quote is expected, and program the adapter to
+//! accept or reject it.
+
+use attestation_core::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use attestation_core::ClaimValue;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Mock quote version (for schema evolution)
+pub const MOCK_QUOTE_VERSION: u8 = 1;
+
+/// Fake attestation evidence.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockQuote {
+    /// Schema version
+    pub version: u8,
+    /// Measurement the "enclave" claims
+    pub measurement: [u8; 32],
+    /// Nonce the quote is bound to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nonce: Vec<u8>,
+    /// Claims copied into the attestation result
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub claims: BTreeMap<String, ClaimValue>,
+}
+
+impl MockQuote {
+    /// Quote for `measurement`, with no nonce or claims.
+    pub fn new(measurement: [u8; 32]) -> Self {
+        Self {
+            version: MOCK_QUOTE_VERSION,
+            measurement,
+            nonce: Vec::new(),
+            claims: BTreeMap::new(),
+        }
+    }
+
+    /// Bind the quote to `nonce`.
+    pub fn with_nonce(mut self, nonce: &[u8]) -> Self {
+        self.nonce = nonce.to_vec();
+        self
+    }
+
+    /// Report `value` under `key` (e.g., "sgx.isv_svn" to exercise policy).
+    pub fn with_claim(mut self, key: impl Into<String>, value: impl Into<ClaimValue>) -> Self {
+        self.claims.insert(key.into(), value.into());
+        self
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+
+    /// Parse a mock quote from canonical CBOR.
+    pub fn parse(bytes: &[u8]) -> Result<Self, SerializationError> {
+        let quote: Self = from_canonical_cbor(bytes)?;
+        if quote.version != MOCK_QUOTE_VERSION {
+            return Err(SerializationError::UnsupportedVersion {
+                kind: "mock quote",
+                version: quote.version,
+            });
+        }
+        Ok(quote)
+    }
+}
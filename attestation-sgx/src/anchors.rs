@@ -0,0 +1,193 @@
+//! Trust anchor configuration (root CAs, intermediate and measurement pins).
+//!
+//! Anchors are loaded from a TOML file so operators can rotate roots or pin
+//! enclaves without rebuilding the verifier:
+//!
+//! ```toml
+//! root_cas = ["-----BEGIN CERTIFICATE-----\n..."]
+//! intermediate_pins = ["<sha256 of intermediate CA DER, hex>"]
+//!
+//! [measurements]
+//! mr_enclave = ["<hex>"]
+//! mr_signer = ["<hex>"]
+//! ```
+//!
+//! Every list is optional. An empty `root_cas` falls back to the built-in
+//! Intel SGX root; empty pin lists disable the corresponding check.
+
+use crate::pck::parse_pem_chain;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AnchorConfigError {
+    #[error("Failed to read trust anchor file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid trust anchor TOML: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("Root CA {index} is not a PEM certificate")]
+    InvalidRootCa { index: usize },
+
+    #[error("Invalid {kind} pin {value:?}: expected 32 hex-encoded bytes")]
+    InvalidPin { kind: &'static str, value: String },
+}
+
+/// Trust anchor file contents, as written by operators.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrustAnchorConfig {
+    /// PEM-encoded root CA certificates
+    #[serde(default)]
+    pub root_cas: Vec<String>,
+    /// Hex SHA-256 fingerprints of allowed intermediate CA certificates (DER)
+    #[serde(default)]
+    pub intermediate_pins: Vec<String>,
+    /// Allowed enclave measurements
+    #[serde(default)]
+    pub measurements: MeasurementPinConfig,
+}
+
+/// Measurement pins, as written by operators.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MeasurementPinConfig {
+    #[serde(default)]
+    pub mr_enclave: Vec<String>,
+    #[serde(default)]
+    pub mr_signer: Vec<String>,
+}
+
+/// Validated pins, decoded to raw bytes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pins {
+    pub intermediates: Vec<[u8; 32]>,
+    pub mr_enclave: Vec<[u8; 32]>,
+    pub mr_signer: Vec<[u8; 32]>,
+}
+
+impl Pins {
+    /// Check that `mr_enclave` and `mr_signer` are allowed by the measurement pins.
+    pub fn check_measurement(
+        &self,
+        mr_enclave: &[u8; 32],
+        mr_signer: &[u8; 32],
+    ) -> Result<(), String> {
+        if !self.mr_enclave.is_empty() && !self.mr_enclave.contains(mr_enclave) {
+            return Err(format!(
+                "MRENCLAVE {} is not pinned",
+                hex::encode(mr_enclave)
+            ));
+        }
+        if !self.mr_signer.is_empty() && !self.mr_signer.contains(mr_signer) {
+            return Err(format!("MRSIGNER {} is not pinned", hex::encode(mr_signer)));
+        }
+        Ok(())
+    }
+
+    /// Check that at least one intermediate in a DER chain is pinned.
+    pub fn check_intermediates(&self, intermediates: &[Vec<u8>]) -> bool {
+        self.intermediates.is_empty()
+            || intermediates
+                .iter()
+                .any(|der| self.intermediates.contains(&Sha256::digest(der).into()))
+    }
+}
+
+impl TrustAnchorConfig {
+    /// Parse a trust anchor file.
+    pub fn from_toml_str(s: &str) -> Result<Self, AnchorConfigError> {
+        Ok(toml::from_str(s)?)
+    }
+
+    /// Read and parse a trust anchor file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AnchorConfigError> {
+        Self::from_toml_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// Validate root certificates and decode pins.
+    pub fn validate(&self) -> Result<Pins, AnchorConfigError> {
+        for (index, root) in self.root_cas.iter().enumerate() {
+            match parse_pem_chain(root) {
+                Ok(certs) if certs.len() == 1 => {}
+                _ => return Err(AnchorConfigError::InvalidRootCa { index }),
+            }
+        }
+
+        Ok(Pins {
+            intermediates: decode_pins("intermediate", &self.intermediate_pins)?,
+            mr_enclave: decode_pins("mr_enclave", &self.measurements.mr_enclave)?,
+            mr_signer: decode_pins("mr_signer", &self.measurements.mr_signer)?,
+        })
+    }
+}
+
+fn decode_pins(kind: &'static str, values: &[String]) -> Result<Vec<[u8; 32]>, AnchorConfigError> {
+    values
+        .iter()
+        .map(|value| {
+            hex::decode(value)
+                .ok()
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .ok_or_else(|| AnchorConfigError::InvalidPin {
+                    kind,
+                    value: value.clone(),
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::INTEL_SGX_ROOT_CA;
+
+    #[test]
+    fn test_parse_and_validate() {
+        let toml = format!(
+            "root_cas = [{:?}]\nintermediate_pins = [\"{}\"]\n\n[measurements]\nmr_enclave = [\"{}\"]\n",
+            INTEL_SGX_ROOT_CA,
+            "ab".repeat(32),
+            "01".repeat(32),
+        );
+        let config = TrustAnchorConfig::from_toml_str(&toml).unwrap();
+        let pins = config.validate().unwrap();
+
+        assert_eq!(config.root_cas.len(), 1);
+        assert_eq!(pins.intermediates, vec![[0xab; 32]]);
+        assert!(pins.check_measurement(&[1u8; 32], &[9u8; 32]).is_ok());
+        assert!(pins.check_measurement(&[2u8; 32], &[9u8; 32]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_bad_pins_and_roots() {
+        let config = TrustAnchorConfig::from_toml_str("intermediate_pins = [\"abcd\"]").unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(AnchorConfigError::InvalidPin { .. })
+        ));
+
+        let config = TrustAnchorConfig::from_toml_str("root_cas = [\"not a cert\"]").unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(AnchorConfigError::InvalidRootCa { index: 0 })
+        ));
+
+        assert!(TrustAnchorConfig::from_toml_str("root_ca = []").is_err());
+    }
+
+    #[test]
+    fn test_intermediate_pins() {
+        let intermediate = vec![0x30, 0x82, 0x01];
+        let pins = Pins {
+            intermediates: vec![Sha256::digest(&intermediate).into()],
+            ..Pins::default()
+        };
+        assert!(pins.check_intermediates(&[intermediate]));
+        assert!(!pins.check_intermediates(&[vec![0x30]]));
+        assert!(Pins::default().check_intermediates(&[]));
+    }
+}
@@ -0,0 +1,323 @@
+//! Disk-backed cache of PCS collateral.
+//!
+//! Without a cache every process start refetches all collateral from Intel
+//! PCS, and a PCS outage fails every trust anchor refresh. A
+//! [`CollateralCache`] keeps each successful PCS response (PCK certificates,
+//! CRLs, TCB info, QE Identity) as one JSON file in a directory, with the
+//! time it was fetched and the time it expires. Wrapped around the PCS
+//! transport ([`CollateralCache::over`]), it:
+//!
+//! - serves a response younger than the cache's maximum age from disk,
+//!   without a request;
+//! - refetches older responses, and on a transport error or non-2xx status
+//!   falls back to the cached response as long as it has not expired.
+//!
+//! Expiry comes from the collateral itself: a CRL's next update, the
+//! `nextUpdate` of TCB info and enclave identities, or the earliest
+//! `notAfter` of a PEM certificate chain. Responses with none of these
+//! expire after the maximum age.
+
+use crate::dcap::{DcapError, HttpClient, HttpResponse};
+use crate::pck;
+use async_trait::async_trait;
+use attestation_core::{system_clock, Clock, ErrorCode, ErrorCoded};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error("Collateral cache I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Malformed collateral cache entry: {0}")]
+    Malformed(String),
+}
+
+impl ErrorCoded for CacheError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::CollateralUnavailable
+    }
+}
+
+/// A PCS response as kept on disk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub url: String,
+    pub fetched_at: DateTime<Utc>,
+    /// After this the response is never served
+    pub expires_at: DateTime<Utc>,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// Base64 body
+    pub body: String,
+}
+
+impl CachedResponse {
+    /// The response as the transport returned it.
+    pub fn response(&self) -> Result<HttpResponse, CacheError> {
+        Ok(HttpResponse {
+            status: self.status,
+            headers: self.headers.clone(),
+            body: STANDARD
+                .decode(&self.body)
+                .map_err(|e| CacheError::Malformed(e.to_string()))?,
+        })
+    }
+}
+
+/// Directory of cached PCS responses, one file per URL.
+#[derive(Clone)]
+pub struct CollateralCache {
+    dir: PathBuf,
+    max_age: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl CollateralCache {
+    /// Open (creating it if needed) the cache in `dir`, with a maximum age
+    /// of one hour.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, CacheError> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            max_age: Duration::hours(1),
+            clock: system_clock(),
+        })
+    }
+
+    /// Serve responses younger than `max_age` without refetching them.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Read fetch and expiry times from `clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The cached response for `url`, expired or not.
+    ///
+    /// Returns `Ok(None)` if nothing is cached for it.
+    pub fn get(&self, url: &str) -> Result<Option<CachedResponse>, CacheError> {
+        let bytes = match std::fs::read(self.path(url)) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let cached: CachedResponse =
+            serde_json::from_slice(&bytes).map_err(|e| CacheError::Malformed(e.to_string()))?;
+        // A hash collision or a misplaced file is not this URL's response
+        Ok(Some(cached).filter(|cached| cached.url == url))
+    }
+
+    /// Cache `response` to a GET of `url`, fetched now.
+    ///
+    /// The file is replaced atomically, so a crash leaves the previous entry.
+    pub fn put(&self, url: &str, response: &HttpResponse) -> Result<CachedResponse, CacheError> {
+        let fetched_at = self.clock.now();
+        let cached = CachedResponse {
+            url: url.to_string(),
+            fetched_at,
+            expires_at: expiry(&response.body).unwrap_or(fetched_at + self.max_age),
+            status: response.status,
+            headers: response.headers.clone(),
+            body: STANDARD.encode(&response.body),
+        };
+        let json =
+            serde_json::to_vec_pretty(&cached).map_err(|e| CacheError::Malformed(e.to_string()))?;
+        let path = self.path(url);
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, json)?;
+        std::fs::rename(&partial, &path)?;
+        Ok(cached)
+    }
+
+    /// `http`, with responses served from and saved to this cache.
+    pub fn over(self, http: Arc<dyn HttpClient>) -> CachingHttpClient {
+        CachingHttpClient { cache: self, http }
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!(
+            "{}.json",
+            hex::encode(Sha256::digest(url.as_bytes()))
+        ))
+    }
+}
+
+/// Time after which collateral in `body` must not be used, if it says.
+fn expiry(body: &[u8]) -> Option<DateTime<Utc>> {
+    if let Ok((_, crl)) = x509_parser::parse_x509_crl(body) {
+        return crl
+            .next_update()
+            .and_then(|t| DateTime::from_timestamp(t.timestamp(), 0));
+    }
+    if let Ok(json) = serde_json::from_slice::<serde_json::Value>(body) {
+        // {"tcbInfo": {.., "nextUpdate": ..}, ..} or {"enclaveIdentity": {..}, ..}
+        return json
+            .as_object()?
+            .values()
+            .find_map(|document| document.get("nextUpdate")?.as_str())
+            .and_then(|next_update| DateTime::parse_from_rfc3339(next_update).ok())
+            .map(|next_update| next_update.to_utc());
+    }
+    let certs = pck::parse_pem_chain(std::str::from_utf8(body).ok()?).ok()?;
+    certs
+        .iter()
+        .map(|der| {
+            let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+            DateTime::from_timestamp(cert.validity().not_after.timestamp(), 0)
+        })
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .min()
+}
+
+/// PCS transport backed by a [`CollateralCache`].
+pub struct CachingHttpClient {
+    cache: CollateralCache,
+    http: Arc<dyn HttpClient>,
+}
+
+#[async_trait]
+impl HttpClient for CachingHttpClient {
+    async fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<HttpResponse, DcapError> {
+        let now = self.cache.clock.now();
+        let cached = self.cache.get(url).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring collateral cache entry for {url}: {e}");
+            None
+        });
+        let usable = cached.filter(|cached| now < cached.expires_at);
+        if let Some(cached) = usable
+            .as_ref()
+            .filter(|cached| now - cached.fetched_at < self.cache.max_age)
+        {
+            if let Ok(response) = cached.response() {
+                tracing::debug!("Serving {url} from the collateral cache");
+                return Ok(response);
+            }
+        }
+
+        let fetched = self.http.get(url, headers).await;
+        if let Ok(response) = &fetched {
+            if (200..300).contains(&response.status) {
+                if let Err(e) = self.cache.put(url, response) {
+                    tracing::warn!("Could not cache collateral from {url}: {e}");
+                }
+                return fetched;
+            }
+        }
+        if let Some(cached) = usable {
+            if let Ok(response) = cached.response() {
+                tracing::warn!(
+                    "Fetching {url} failed; serving collateral cached at {}",
+                    cached.fetched_at
+                );
+                return Ok(response);
+            }
+        }
+        fetched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dcap::tests::FixedResponses;
+    use attestation_core::MockClock;
+    use std::sync::Mutex;
+
+    /// Transport that counts requests and can be taken down.
+    struct Flaky {
+        inner: FixedResponses,
+        requests: Mutex<u32>,
+        down: Mutex<bool>,
+    }
+
+    #[async_trait]
+    impl HttpClient for Flaky {
+        async fn get(
+            &self,
+            url: &str,
+            headers: &[(&str, &str)],
+        ) -> Result<HttpResponse, DcapError> {
+            *self.requests.lock().unwrap() += 1;
+            if *self.down.lock().unwrap() {
+                return Err(DcapError::Network("connection refused".to_string()));
+            }
+            self.inner.get(url, headers).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collateral_served_from_disk_until_it_expires() {
+        let dir = std::env::temp_dir().join(format!("veribot-collateral-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let pki = crate::pck::tests::Pki::new();
+        let url = "https://pcs.test/pckcrl?ca=processor&encoding=der";
+        let crl = pki.crl(0x07);
+        let flaky = Arc::new(Flaky {
+            inner: FixedResponses(vec![(
+                url.to_string(),
+                HttpResponse {
+                    status: 200,
+                    headers: Vec::new(),
+                    body: crl.clone(),
+                },
+            )]),
+            requests: Mutex::new(0),
+            down: Mutex::new(false),
+        });
+        // The test CRL is due for update on 2024-02-01
+        let clock = MockClock::new(
+            DateTime::parse_from_rfc3339("2024-01-15T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+        );
+        let open = || {
+            CollateralCache::open(&dir)
+                .unwrap()
+                .with_clock(Arc::new(clock.clone()))
+                .over(flaky.clone())
+        };
+
+        let body = open().get(url, &[]).await.unwrap().body;
+        assert_eq!(body, crl);
+        let cached = CollateralCache::open(&dir)
+            .unwrap()
+            .get(url)
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.expires_at.to_rfc3339(), "2024-02-01T00:00:00+00:00");
+
+        // A restarted process is served from disk
+        assert_eq!(open().get(url, &[]).await.unwrap().body, body);
+        assert_eq!(*flaky.requests.lock().unwrap(), 1);
+
+        // Past the maximum age it refetches, and rides out an outage on the cached CRL
+        clock.advance(Duration::hours(2));
+        *flaky.down.lock().unwrap() = true;
+        assert_eq!(open().get(url, &[]).await.unwrap().body, body);
+        assert_eq!(*flaky.requests.lock().unwrap(), 2);
+
+        // Never past the CRL's next update
+        clock.advance(Duration::days(30));
+        assert!(matches!(
+            open().get(url, &[]).await,
+            Err(DcapError::Network(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
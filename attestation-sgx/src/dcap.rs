@@ -1,16 +1,31 @@
 //! DCAP (Data Center Attestation Primitives) protocol implementation.
 //!
 //! This module handles communication with Intel PCS (Provisioning Certification Service)
-//! for fetching PCK certificates, CRLs, and TCB info.
+//! for fetching PCK certificates, CRLs, TCB info and the QE Identity.
+//!
+//! A local Provisioning Certificate Caching Service (PCCS) serves the same
+//! collateral: [`PcsClient::pccs`] talks to one, under its own URL scheme
+//! and API version (see [`CollateralSource`]). Both services take an API
+//! key, each in its own header ([`PcsClient::with_api_key`]).
+//!
+//! Requests go through an [`HttpClient`], so the PCS client runs under any
+//! async runtime. The `reqwest` feature (on by default) provides one for
+//! tokio; without it, implement [`HttpClient`] over the embedding runtime's
+//! HTTP stack and pass it to [`PcsClient::with_http_client`].
 
-use reqwest::Client;
+use crate::cache::CollateralCache;
+use async_trait::async_trait;
+use attestation_core::{ErrorCode, ErrorCoded};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum DcapError {
     #[error("Network error: {0}")]
-    Network(#[from] reqwest::Error),
+    Network(String),
 
     #[error("PCS API error: {0}")]
     PcsApi(String),
@@ -19,21 +34,174 @@ pub enum DcapError {
     InvalidResponse(String),
 }
 
-/// Intel PCS client for fetching attestation collateral.
+impl ErrorCoded for DcapError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            DcapError::Network(_) => ErrorCode::Network,
+            DcapError::PcsApi(_) | DcapError::InvalidResponse(_) => {
+                ErrorCode::CollateralUnavailable
+            }
+        }
+    }
+}
+
+/// Response to an HTTP GET.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+    /// Response headers; PCS returns collateral issuer chains in headers
+    pub headers: Vec<(String, String)>,
+}
+
+impl HttpResponse {
+    /// The value of header `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// HTTP transport used by [`PcsClient`].
+#[async_trait]
+pub trait HttpClient: Send + Sync {
+    /// GET `url`, sending the extra request `headers` (such as an API key).
+    /// Only transport failures are errors; any HTTP status is a response.
+    async fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<HttpResponse, DcapError>;
+}
+
+#[cfg(feature = "reqwest")]
+#[async_trait]
+impl HttpClient for reqwest::Client {
+    async fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<HttpResponse, DcapError> {
+        let network = |e: reqwest::Error| DcapError::Network(e.to_string());
+        let request = headers
+            .iter()
+            .fold(reqwest::Client::get(self, url), |request, (name, value)| {
+                request.header(*name, *value)
+            });
+        let response = request.send().await.map_err(network)?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = response.bytes().await.map_err(network)?;
+        Ok(HttpResponse {
+            status,
+            body: body.to_vec(),
+            headers,
+        })
+    }
+}
+
+/// Service attestation collateral is fetched from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum CollateralSource {
+    /// Intel PCS, at [`crate::SgxConfig::pcs_url`]
+    #[default]
+    IntelPcs,
+    /// A Provisioning Certificate Caching Service at `url` (scheme, host and
+    /// port, e.g. `https://pccs.internal:8081`)
+    Pccs {
+        url: String,
+        api_version: PccsApiVersion,
+    },
+}
+
+/// PCCS API version, the `vN` in `/sgx/certification/vN`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PccsApiVersion {
+    /// Serves PCK CRLs PEM-encoded only
+    V3,
+    #[default]
+    V4,
+}
+
+/// API a [`PcsClient`] talks to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CollateralApi {
+    IntelPcs,
+    Pccs(PccsApiVersion),
+}
+
+impl CollateralApi {
+    /// Header the service expects its API key in.
+    fn api_key_header(&self) -> &'static str {
+        match self {
+            CollateralApi::IntelPcs => "Ocp-Apim-Subscription-Key",
+            CollateralApi::Pccs(_) => "user-token",
+        }
+    }
+}
+
+/// Intel PCS (or PCCS) client for fetching attestation collateral.
 pub struct PcsClient {
-    client: Client,
+    http: Arc<dyn HttpClient>,
     base_url: String,
+    api: CollateralApi,
+    api_key: Option<String>,
 }
 
 impl PcsClient {
     /// Create a new PCS client.
+    #[cfg(feature = "reqwest")]
     pub fn new(base_url: String) -> Self {
+        Self::with_http_client(base_url, Arc::new(reqwest::Client::new()))
+    }
+
+    /// Create a PCS client that sends its requests through `http`.
+    pub fn with_http_client(base_url: String, http: Arc<dyn HttpClient>) -> Self {
         Self {
-            client: Client::new(),
+            http,
             base_url,
+            api: CollateralApi::IntelPcs,
+            api_key: None,
         }
     }
 
+    /// Create a client for the PCCS at `url` (scheme, host and port),
+    /// sending its requests through `http`.
+    pub fn pccs(url: &str, api_version: PccsApiVersion, http: Arc<dyn HttpClient>) -> Self {
+        let version = match api_version {
+            PccsApiVersion::V3 => 3,
+            PccsApiVersion::V4 => 4,
+        };
+        Self {
+            http,
+            base_url: format!("{}/sgx/certification/v{version}", url.trim_end_matches('/')),
+            api: CollateralApi::Pccs(api_version),
+            api_key: None,
+        }
+    }
+
+    /// Create a client for `source`, sending its requests through `http`.
+    ///
+    /// `pcs_url` is used for [`CollateralSource::IntelPcs`].
+    pub fn for_source(source: &CollateralSource, pcs_url: &str, http: Arc<dyn HttpClient>) -> Self {
+        match source {
+            CollateralSource::IntelPcs => Self::with_http_client(pcs_url.to_string(), http),
+            CollateralSource::Pccs { url, api_version } => Self::pccs(url, *api_version, http),
+        }
+    }
+
+    /// Send `key` with every request: as `Ocp-Apim-Subscription-Key` to
+    /// Intel PCS (which needs it for PCK certificates), as `user-token` to
+    /// a PCCS.
+    pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    /// Keep responses in `cache`, and serve them from it (see [`crate::cache`]).
+    pub fn with_cache(mut self, cache: CollateralCache) -> Self {
+        self.http = Arc::new(cache.over(self.http));
+        self
+    }
+
     /// Fetch PCK certificate for a given platform.
     ///
     /// # Arguments
@@ -44,45 +212,26 @@ impl PcsClient {
         fmspc: &str,
         pce_id: &str,
     ) -> Result<String, DcapError> {
-        let url = format!(
-            "{}/pckcert?fmspc={}&pceid={}",
-            self.base_url, fmspc, pce_id
-        );
+        let url = format!("{}/pckcert?fmspc={}&pceid={}", self.base_url, fmspc, pce_id);
 
-        let response = self.client.get(&url).send().await?;
-
-        if !response.status().is_success() {
-            return Err(DcapError::PcsApi(format!(
-                "HTTP {}",
-                response.status()
-            )));
-        }
-
-        let cert = response.text().await?;
-        Ok(cert)
+        let body = self.fetch(&url).await?;
+        String::from_utf8(body).map_err(|e| DcapError::InvalidResponse(e.to_string()))
     }
 
     /// Fetch PCK CRL (Certificate Revocation List).
     ///
     /// # Arguments
     /// * `ca` - CA type ("processor" or "platform")
+    ///
+    /// Always DER; a v3 PCCS serves PEM, which is decoded.
     pub async fn get_pck_crl(&self, ca: &str) -> Result<Vec<u8>, DcapError> {
-        let url = format!(
-            "{}/pckcrl?ca={}&encoding=der",
-            self.base_url, ca
-        );
-
-        let response = self.client.get(&url).send().await?;
-
-        if !response.status().is_success() {
-            return Err(DcapError::PcsApi(format!(
-                "HTTP {}",
-                response.status()
-            )));
+        if self.api == CollateralApi::Pccs(PccsApiVersion::V3) {
+            let url = format!("{}/pckcrl?ca={}", self.base_url, ca);
+            return pem_crl_to_der(&self.fetch(&url).await?);
         }
+        let url = format!("{}/pckcrl?ca={}&encoding=der", self.base_url, ca);
 
-        let crl = response.bytes().await?;
-        Ok(crl.to_vec())
+        self.fetch(&url).await
     }
 
     /// Fetch TCB (Trusted Computing Base) info for a platform.
@@ -90,19 +239,146 @@ impl PcsClient {
     /// # Arguments
     /// * `fmspc` - Platform family/model/stepping (6 bytes hex)
     pub async fn get_tcb_info(&self, fmspc: &str) -> Result<TcbInfo, DcapError> {
+        let body = self.get_tcb_info_raw(fmspc).await?;
+        serde_json::from_slice(&body).map_err(|e| DcapError::InvalidResponse(e.to_string()))
+    }
+
+    /// Fetch TCB info for a platform as served, for collateral bundles.
+    pub async fn get_tcb_info_raw(&self, fmspc: &str) -> Result<Vec<u8>, DcapError> {
         let url = format!("{}/tcb?fmspc={}", self.base_url, fmspc);
 
-        let response = self.client.get(&url).send().await?;
+        self.fetch(&url).await
+    }
 
-        if !response.status().is_success() {
-            return Err(DcapError::PcsApi(format!(
-                "HTTP {}",
-                response.status()
-            )));
+    /// Fetch the Quoting Enclave identity, with the chain that signed it.
+    ///
+    /// The response is returned as received: verify it with
+    /// [`crate::qe::verify_qe_identity`] before use.
+    pub async fn get_qe_identity(&self) -> Result<SignedEnclaveIdentity, DcapError> {
+        let url = format!("{}/qe/identity", self.base_url);
+
+        let response = self.fetch_response(&url).await?;
+        let issuer_chain = response
+            .header(ENCLAVE_IDENTITY_ISSUER_CHAIN)
+            .ok_or_else(|| {
+                DcapError::InvalidResponse(format!("no {ENCLAVE_IDENTITY_ISSUER_CHAIN} header"))
+            })?;
+        Ok(SignedEnclaveIdentity {
+            issuer_chain: percent_decode(issuer_chain)?,
+            body: response.body,
+        })
+    }
+
+    /// GET `url`, failing on a non-2xx status.
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>, DcapError> {
+        Ok(self.fetch_response(url).await?.body)
+    }
+
+    /// GET `url`, failing on a non-2xx status, keeping the headers.
+    async fn fetch_response(&self, url: &str) -> Result<HttpResponse, DcapError> {
+        let api_key = self
+            .api_key
+            .as_deref()
+            .map(|key| (self.api.api_key_header(), key));
+        let response = self.http.get(url, api_key.as_slice()).await?;
+
+        if !(200..300).contains(&response.status) {
+            return Err(DcapError::PcsApi(format!("HTTP {}", response.status)));
         }
 
-        let tcb_info: TcbInfo = response.json().await?;
-        Ok(tcb_info)
+        Ok(response)
+    }
+}
+
+/// Header carrying the PEM chain that signed an enclave identity
+const ENCLAVE_IDENTITY_ISSUER_CHAIN: &str = "SGX-Enclave-Identity-Issuer-Chain";
+
+/// Decode a PEM CRL as served by a v3 PCCS.
+fn pem_crl_to_der(body: &[u8]) -> Result<Vec<u8>, DcapError> {
+    let invalid = |reason: &str| DcapError::InvalidResponse(format!("PEM CRL: {reason}"));
+    let pem = std::str::from_utf8(body).map_err(|_| invalid("not UTF-8"))?;
+    let base64: String = pem
+        .trim()
+        .strip_prefix("-----BEGIN X509 CRL-----")
+        .and_then(|rest| rest.strip_suffix("-----END X509 CRL-----"))
+        .ok_or_else(|| invalid("no X509 CRL block"))?
+        .split_whitespace()
+        .collect();
+    STANDARD.decode(base64).map_err(|e| invalid(&e.to_string()))
+}
+
+/// Decode a URL-encoded header value (PCS percent-encodes PEM chains).
+fn percent_decode(value: &str) -> Result<String, DcapError> {
+    let invalid =
+        || DcapError::InvalidResponse("malformed percent-encoding in issuer chain".to_string());
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut input = value.bytes();
+    while let Some(byte) = input.next() {
+        if byte == b'%' {
+            let hex = [
+                input.next().ok_or_else(invalid)?,
+                input.next().ok_or_else(invalid)?,
+            ];
+            let hex = std::str::from_utf8(&hex).map_err(|_| invalid())?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+/// An enclave identity response from PCS, not yet verified.
+#[derive(Debug, Clone)]
+pub struct SignedEnclaveIdentity {
+    /// JSON body: `{"enclaveIdentity": {..}, "signature": "<hex r || s>"}`
+    pub body: Vec<u8>,
+    /// PEM chain of the TCB signing certificate, leaf first
+    pub issuer_chain: String,
+}
+
+/// Identity of an Intel architectural enclave (here the Quoting Enclave).
+///
+/// Hex fields are as PCS publishes them; `miscselect` and `attributes` are
+/// compared under their masks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclaveIdentity {
+    pub id: String,
+    pub version: u32,
+    pub issue_date: String,
+    pub next_update: String,
+    pub tcb_evaluation_data_number: u32,
+    pub miscselect: String,
+    pub miscselect_mask: String,
+    pub attributes: String,
+    pub attributes_mask: String,
+    pub mrsigner: String,
+    pub isvprodid: u16,
+    pub tcb_levels: Vec<EnclaveTcbLevel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclaveTcbLevel {
+    pub tcb: EnclaveTcb,
+    pub tcb_date: String,
+    pub tcb_status: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnclaveTcb {
+    pub isvsvn: u16,
+}
+
+impl EnclaveIdentity {
+    /// The TCB level an enclave at `isv_svn` falls in: the highest level
+    /// not above it.
+    pub fn tcb_level(&self, isv_svn: u16) -> Option<&EnclaveTcbLevel> {
+        self.tcb_levels
+            .iter()
+            .filter(|level| level.tcb.isvsvn <= isv_svn)
+            .max_by_key(|level| level.tcb.isvsvn)
     }
 }
 
@@ -151,7 +427,7 @@ pub struct TcbComponents {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
     #[test]
@@ -159,4 +435,131 @@ mod tests {
         let client = PcsClient::new("https://api.trustedservices.intel.com".to_string());
         assert_eq!(client.base_url, "https://api.trustedservices.intel.com");
     }
+
+    /// Serves fixed responses by URL, without any runtime-specific I/O.
+    pub(crate) struct FixedResponses(pub(crate) Vec<(String, HttpResponse)>);
+
+    #[async_trait]
+    impl HttpClient for FixedResponses {
+        async fn get(
+            &self,
+            url: &str,
+            _headers: &[(&str, &str)],
+        ) -> Result<HttpResponse, DcapError> {
+            self.0
+                .iter()
+                .find(|(u, _)| u == url)
+                .map(|(_, response)| response.clone())
+                .ok_or_else(|| DcapError::Network(format!("no route to {url}")))
+        }
+    }
+
+    #[test]
+    fn test_pcs_client_over_custom_transport() {
+        let response = |status, body: &[u8]| HttpResponse {
+            status,
+            body: body.to_vec(),
+            headers: Vec::new(),
+        };
+        let http = FixedResponses(vec![
+            (
+                "https://pcs/pckcrl?ca=processor&encoding=der".to_string(),
+                response(200, &[0x30, 0x00]),
+            ),
+            (
+                "https://pcs/pckcrl?ca=platform&encoding=der".to_string(),
+                response(404, b""),
+            ),
+            (
+                "https://pcs/tcb?fmspc=00906ED50000".to_string(),
+                response(200, b"{}"),
+            ),
+            (
+                "https://pcs/qe/identity".to_string(),
+                HttpResponse {
+                    headers: vec![(
+                        "sgx-enclave-identity-issuer-chain".to_string(),
+                        "-----BEGIN%20CERTIFICATE-----%0A".to_string(),
+                    )],
+                    ..response(200, b"{}")
+                },
+            ),
+        ]);
+        let client = PcsClient::with_http_client("https://pcs".to_string(), Arc::new(http));
+
+        // Any executor drives the client; no tokio runtime is running here
+        let crl = futures::executor::block_on(client.get_pck_crl("processor")).unwrap();
+        assert_eq!(crl, vec![0x30, 0x00]);
+        let err = futures::executor::block_on(client.get_pck_crl("platform")).unwrap_err();
+        assert!(matches!(err, DcapError::PcsApi(ref status) if status == "HTTP 404"));
+        let err = futures::executor::block_on(client.get_tcb_info("00906ED50000")).unwrap_err();
+        assert!(matches!(err, DcapError::InvalidResponse(_)));
+        let identity = futures::executor::block_on(client.get_qe_identity()).unwrap();
+        assert_eq!(identity.issuer_chain, "-----BEGIN CERTIFICATE-----\n");
+        let err = futures::executor::block_on(client.get_pck_certificate("00906ED50000", "0000"))
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Network);
+    }
+
+    /// Answers only requests carrying the PCCS user token "secret".
+    struct TokenGate(FixedResponses);
+
+    #[async_trait]
+    impl HttpClient for TokenGate {
+        async fn get(
+            &self,
+            url: &str,
+            headers: &[(&str, &str)],
+        ) -> Result<HttpResponse, DcapError> {
+            if !headers.contains(&("user-token", "secret")) {
+                return Ok(HttpResponse {
+                    status: 401,
+                    body: Vec::new(),
+                    headers: Vec::new(),
+                });
+            }
+            self.0.get(url, headers).await
+        }
+    }
+
+    #[test]
+    fn test_pccs_urls_versions_and_api_key() {
+        let der = vec![0x30, 0x03, 0x02, 0x01, 0x07];
+        let pem = format!(
+            "-----BEGIN X509 CRL-----\n{}\n-----END X509 CRL-----\n",
+            STANDARD.encode(&der)
+        );
+        let ok = |body: &[u8]| HttpResponse {
+            status: 200,
+            body: body.to_vec(),
+            headers: Vec::new(),
+        };
+        let http = Arc::new(TokenGate(FixedResponses(vec![
+            (
+                "https://pccs:8081/sgx/certification/v3/pckcrl?ca=processor".to_string(),
+                ok(pem.as_bytes()),
+            ),
+            (
+                "https://pccs:8081/sgx/certification/v4/pckcrl?ca=processor&encoding=der"
+                    .to_string(),
+                ok(&der),
+            ),
+        ])));
+        let source = |api_version| CollateralSource::Pccs {
+            url: "https://pccs:8081/".to_string(),
+            api_version,
+        };
+
+        for version in [PccsApiVersion::V3, PccsApiVersion::V4] {
+            let client = PcsClient::for_source(&source(version), "https://unused", http.clone());
+            let err = futures::executor::block_on(client.get_pck_crl("processor")).unwrap_err();
+            assert!(matches!(err, DcapError::PcsApi(ref status) if status == "HTTP 401"));
+
+            let client = client.with_api_key("secret");
+            assert_eq!(
+                futures::executor::block_on(client.get_pck_crl("processor")).unwrap(),
+                der
+            );
+        }
+    }
 }
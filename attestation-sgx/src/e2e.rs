@@ -0,0 +1,530 @@
+//! End-to-end pipeline harness.
+//!
+//! Runs the whole path of a checkpoint in one process, with every external
+//! party simulated by fixtures:
+//!
+//! - [`Enclave`]: a robot's enclave, holding the robot's signing key and
+//!   producing DCAP quotes whose PCK chain comes from a fixture PKI and whose
+//!   report data binds the key. The Quoting Enclave is a fixture key whose
+//!   QE report is signed by the fixture PCK key; no TCB info is evaluated
+//! - [`MockPcs`]: Intel PCS serving that PKI's CRLs, a signed QE Identity and
+//!   TCB info, through the adapter's own [`dcap::PcsClient`]
+//! - [`Gateway`]: verifies quote and chain, stores the checkpoint and its
+//!   entries in a [`ShardedStore`], records every decision in an
+//!   [`AuditLog`], and anchors stored roots with an [`AnchorTracker`]
+//! - [`AnchorStub`]: a chain that mines every transaction in the next block
+//!
+//! The tests drive submit → verify → anchor → audit flows through them.
+
+use crate::{dcap, pck, qe, quote, SgxConfig, SgxDcapAdapter};
+use attestation_core::backfill::CheckpointStore;
+use attestation_core::{
+    abi, AnchorChain, AnchorConfig, AnchorError, AnchorTracker, AttestationError,
+    AttestationRegistry, AuditError, AuditEvent, AuditExport, AuditLog, AuditSink, ChainVerifier,
+    Checkpoint, CheckpointBuilder, ClaimValue, Clock, Entry, ErrorCode, ErrorCoded, Hash256,
+    MerkleTree, MockClock, RobotId, ShardedStore, Signer, StoredEntry, TxInclusion, VerifyingKey,
+};
+use chrono::{DateTime, Duration, Utc};
+use ring::signature::EcdsaKeyPair;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Measurement of the robot firmware's enclave.
+const MR_ENCLAVE: [u8; 32] = [0x5A; 32];
+
+/// Start of the test clock; the fixture CRLs are due for update on 2024-02-01.
+fn start() -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339("2024-01-15T00:00:00Z")
+        .unwrap()
+        .to_utc()
+}
+
+/// A robot's enclave.
+struct Enclave {
+    robot_id: RobotId,
+    signer: Signer,
+    mr_enclave: [u8; 32],
+    /// The platform's Quoting Enclave
+    qe: quote::tests::QuoteSigner,
+    /// PEM chain of the platform's PCK certificate
+    pck_chain: String,
+    /// Key of the PCK certificate, which signs QE reports
+    pck_key: EcdsaKeyPair,
+    last: Option<Checkpoint>,
+}
+
+/// A checkpoint, its quote and the entries behind it.
+struct Submission {
+    checkpoint: Checkpoint,
+    quote: Vec<u8>,
+    entries: Vec<StoredEntry>,
+}
+
+impl Enclave {
+    fn new(robot_id: &str, mr_enclave: [u8; 32], pki: &pck::tests::Pki) -> Self {
+        Self {
+            robot_id: RobotId(robot_id.to_string()),
+            signer: Signer::generate(),
+            mr_enclave,
+            qe: quote::tests::QuoteSigner::new(),
+            pck_chain: pki.chain(),
+            pck_key: pki.pck_key(),
+            last: None,
+        }
+    }
+
+    /// DCAP quote of this enclave, binding its signing key in the report data.
+    fn quote(&self) -> Vec<u8> {
+        let signature_data =
+            quote::tests::intel_qe_signature_data(&self.qe.public_key(), b"qe auth data");
+        let mut quote = quote::tests::quote_with_certification_data(
+            &signature_data,
+            5,
+            self.pck_chain.as_bytes(),
+        );
+        let mut report_data = [0u8; 64];
+        report_data[..32].copy_from_slice(&key_binding(&self.signer.verifying_key()));
+        let body = quote::tests::ReportBody {
+            mr_enclave: self.mr_enclave,
+            mr_signer: [0u8; 32],
+            report_data,
+        };
+        body.write(&mut quote);
+        self.qe.sign(&mut quote, &self.pck_key);
+        quote
+    }
+
+    /// Log `payloads` and sign the next checkpoint over them at `at`.
+    fn checkpoint(&mut self, payloads: &[&[u8]], at: DateTime<Utc>) -> Submission {
+        let mut tree = MerkleTree::new();
+        for (i, payload) in payloads.iter().enumerate() {
+            tree.insert(Entry::new(
+                at.timestamp_micros() as u64 + i as u64,
+                0,
+                payload,
+            ));
+        }
+        let builder = match &self.last {
+            Some(prev) => CheckpointBuilder::continuing_from(prev).unwrap(),
+            None => CheckpointBuilder::test_fixture()
+                .robot_id(self.robot_id.clone())
+                .enclave_measurement(self.mr_enclave.to_vec()),
+        };
+        let counter = self
+            .last
+            .as_ref()
+            .map_or(1, |prev| prev.monotonic_counter + 1);
+        let checkpoint = builder
+            .monotonic_counter(counter)
+            .timestamp(at)
+            .entries_root(tree.root())
+            .build_and_sign(self.signer.signing_key())
+            .unwrap();
+        self.last = Some(checkpoint.clone());
+        let entries = tree
+            .entries()
+            .into_iter()
+            .zip(payloads)
+            .map(|(entry, payload)| StoredEntry::Live {
+                entry: entry.clone(),
+                payload: payload.to_vec(),
+            })
+            .collect();
+        Submission {
+            checkpoint,
+            quote: self.quote(),
+            entries,
+        }
+    }
+}
+
+/// What an enclave's report data carries: the SHA-256 of its signing key.
+fn key_binding(key: &VerifyingKey) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+/// Intel PCS for a fixture PKI.
+struct MockPcs;
+
+impl MockPcs {
+    /// PCS client whose processor CRL revokes the PCK certificate `revoked`
+    /// (the fixture PCK certificate is 0x42).
+    fn client(pki: &pck::tests::Pki, revoked: u8) -> dcap::PcsClient {
+        let identity = qe::tests::TcbSigner::new(&pki.root, &pki.root_key)
+            .sign(&serde_json::to_string(&qe::tests::identity()).unwrap());
+        let ok = |body: Vec<u8>, headers: Vec<(String, String)>| dcap::HttpResponse {
+            status: 200,
+            headers,
+            body,
+        };
+        dcap::PcsClient::with_http_client(
+            "https://pcs.test".to_string(),
+            Arc::new(dcap::tests::FixedResponses(vec![
+                (
+                    "https://pcs.test/pckcrl?ca=processor&encoding=der".to_string(),
+                    ok(pki.crl(revoked), Vec::new()),
+                ),
+                (
+                    "https://pcs.test/pckcrl?ca=platform&encoding=der".to_string(),
+                    ok(pki.crl(0x07), Vec::new()),
+                ),
+                (
+                    "https://pcs.test/qe/identity".to_string(),
+                    ok(
+                        identity.body,
+                        vec![(
+                            "SGX-Enclave-Identity-Issuer-Chain".to_string(),
+                            identity.issuer_chain,
+                        )],
+                    ),
+                ),
+                (
+                    "https://pcs.test/tcb?fmspc=00906EA10000".to_string(),
+                    ok(b"{}".to_vec(), Vec::new()),
+                ),
+            ])),
+        )
+    }
+}
+
+/// Chain that mines every submitted transaction, successfully, in the next block.
+#[derive(Default)]
+struct AnchorStub {
+    head: u64,
+    mined: HashMap<Hash256, TxInclusion>,
+    submitted: Vec<Vec<u8>>,
+}
+
+impl AnchorStub {
+    /// Produce `blocks` blocks.
+    fn advance(&mut self, blocks: u64) {
+        self.head += blocks;
+    }
+}
+
+impl AnchorChain for AnchorStub {
+    fn head(&self) -> Result<u64, AnchorError> {
+        Ok(self.head)
+    }
+
+    fn inclusion(&self, transaction_hash: &Hash256) -> Result<Option<TxInclusion>, AnchorError> {
+        Ok(self
+            .mined
+            .get(transaction_hash)
+            .filter(|tx| tx.block_number <= self.head)
+            .cloned())
+    }
+
+    fn block_hash(&self, number: u64) -> Result<Option<Hash256>, AnchorError> {
+        Ok((number <= self.head).then(|| Sha256::digest(number.to_be_bytes()).into()))
+    }
+
+    fn submit(&mut self, calldata: &[u8]) -> Result<Hash256, AnchorError> {
+        let transaction_hash: Hash256 = Sha256::digest(calldata).into();
+        self.submitted.push(calldata.to_vec());
+        let block_number = self.head + 1;
+        self.mined.insert(
+            transaction_hash,
+            TxInclusion {
+                block_number,
+                block_hash: Sha256::digest(block_number.to_be_bytes()).into(),
+                block_timestamp: 1_705_276_800 + 12 * block_number,
+                succeeded: true,
+                checkpoint_id: Some(transaction_hash),
+            },
+        );
+        Ok(transaction_hash)
+    }
+}
+
+/// Audit sink the test can read back.
+#[derive(Clone, Default)]
+struct SharedSink(Arc<Mutex<Vec<AuditEvent>>>);
+
+impl AuditSink for SharedSink {
+    fn append(&mut self, event: &AuditEvent) -> Result<(), AuditError> {
+        self.0.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+}
+
+/// Policy every gateway decision is recorded under.
+const POLICY: &str = "e2e-v1";
+
+/// Gateway with in-memory storage.
+struct Gateway {
+    registry: AttestationRegistry,
+    robots: HashMap<RobotId, VerifyingKey>,
+    chains: HashMap<RobotId, ChainVerifier>,
+    store: ShardedStore,
+    signer: Signer,
+    audit: AuditLog,
+    events: SharedSink,
+    anchors: AnchorTracker,
+    /// Checkpoints stored but not yet anchored
+    unanchored: Vec<Checkpoint>,
+}
+
+impl Gateway {
+    /// Gateway verifying SGX quotes of `MR_ENCLAVE` under the root of `pki`,
+    /// with collateral from a [`MockPcs`] revoking PCK certificate `revoked`.
+    async fn start(pki: &pck::tests::Pki, revoked: u8, clock: &MockClock) -> Self {
+        // Fetch collateral on every refresh, however recently anchors were loaded
+        let adapter = SgxDcapAdapter::with_config(SgxConfig {
+            cache_expiry_secs: 0,
+            require_qe_identity: true,
+            ..SgxConfig::default()
+        })
+        .with_clock(Arc::new(clock.clone()))
+        .with_pcs_client(MockPcs::client(pki, revoked));
+        adapter.update_anchors(|anchors| anchors.root_ca_certs = vec![pki.root.pem()]);
+        let mut registry = AttestationRegistry::new().with_clock(Arc::new(clock.clone()));
+        registry.register(Box::new(adapter));
+        for (vendor, refreshed) in registry.update_trust_anchors().await {
+            refreshed.unwrap_or_else(|e| panic!("{vendor}: {e}"));
+        }
+
+        let signer = Signer::generate();
+        let events = SharedSink::default();
+        let audit = AuditLog::new(
+            Signer::new(signer.signing_key().clone()),
+            "gateway-e2e",
+            Box::new(events.clone()),
+        )
+        .with_clock(Arc::new(clock.clone()));
+        Self {
+            registry,
+            robots: HashMap::new(),
+            chains: HashMap::new(),
+            store: ShardedStore::new(Duration::hours(1)),
+            signer,
+            audit,
+            events,
+            anchors: AnchorTracker::new(AnchorConfig {
+                confirmation_depth: 3,
+                ..AnchorConfig::new(31337, [0xAA; 20])
+            }),
+            unanchored: Vec::new(),
+        }
+    }
+
+    /// Enroll the robot of `enclave`.
+    fn enroll(&mut self, enclave: &Enclave) {
+        self.robots
+            .insert(enclave.robot_id.clone(), enclave.signer.verifying_key());
+    }
+
+    /// Verify and store a submission, and audit the decision either way.
+    async fn submit(&mut self, submission: Submission) -> Result<(), ErrorCode> {
+        let checkpoint_hash = submission.checkpoint.compute_hash().unwrap();
+        let outcome = self.accept(submission).await;
+        self.audit
+            .record(checkpoint_hash, POLICY, &outcome)
+            .unwrap();
+        outcome.map_err(|e| e.code())
+    }
+
+    async fn accept(&mut self, submission: Submission) -> Result<(), AttestationError> {
+        let Submission {
+            checkpoint,
+            quote,
+            entries,
+        } = submission;
+        let key = *self.robots.get(&checkpoint.robot_id).ok_or_else(|| {
+            AttestationError::VerificationFailed("robot not enrolled".to_string())
+        })?;
+
+        let result = self
+            .registry
+            .verify_quote("intel-sgx", &quote, None)
+            .await?;
+        if result.enclave_measurement != MR_ENCLAVE
+            || checkpoint.enclave_measurement != result.enclave_measurement
+        {
+            return Err(AttestationError::VerificationFailed(
+                "unexpected enclave measurement".to_string(),
+            ));
+        }
+        let bound = matches!(result.claim("sgx.report_data"), Some(ClaimValue::Bytes(data)) if data[..32] == key_binding(&key));
+        if !bound {
+            return Err(AttestationError::VerificationFailed(
+                "quote does not bind the robot key".to_string(),
+            ));
+        }
+
+        attestation_core::verify_stored_entries(&entries, &checkpoint.entries_root, &key)
+            .map_err(|e| AttestationError::VerificationFailed(e.to_string()))?;
+        let verified = self
+            .chains
+            .entry(checkpoint.robot_id.clone())
+            .or_insert_with(|| ChainVerifier::new(key))
+            .accept(checkpoint.clone())
+            .map_err(|e| AttestationError::VerificationFailed(e.to_string()))?;
+        self.store.insert(verified);
+        self.store
+            .insert_entries(&checkpoint, entries)
+            .expect("entries reproduce the root");
+        self.unanchored.push(checkpoint);
+        Ok(())
+    }
+
+    /// Anchor every stored root not anchored yet, and poll the chain.
+    fn anchor(&mut self, chain: &mut AnchorStub) {
+        for checkpoint in self.unanchored.drain(..) {
+            let signature = self.signer.sign(&checkpoint.compute_hash().unwrap());
+            let calldata =
+                abi::anchor_checkpoint_calldata(&checkpoint, "intel-sgx", &signature.to_bytes())
+                    .unwrap();
+            self.anchors
+                .submit(chain, checkpoint.entries_root, calldata)
+                .unwrap();
+        }
+        self.anchors.poll(chain).unwrap();
+    }
+
+    fn audit_events(&self) -> Vec<AuditEvent> {
+        self.events.0.lock().unwrap().clone()
+    }
+}
+
+/// What an auditor checks of an accepted checkpoint: the gateway still holds
+/// it under its published root-of-roots, its entries reproduce its root, and
+/// the root is anchored.
+fn audit_checkpoint(gateway: &Gateway, checkpoint: &Checkpoint) {
+    let root = gateway.store.root_of_roots().unwrap();
+    let proof = gateway.store.prove(checkpoint).unwrap().unwrap();
+    assert!(proof.verify(checkpoint, &root));
+    let entries = gateway.store.entries(checkpoint).unwrap();
+    attestation_core::verify_stored_entries(
+        entries,
+        &checkpoint.entries_root,
+        &gateway.signer.verifying_key(),
+    )
+    .unwrap();
+    let receipt = gateway
+        .anchors
+        .get(&checkpoint.entries_root)
+        .and_then(|r| r.receipt.as_ref())
+        .unwrap();
+    assert_eq!(receipt.merkle_root, checkpoint.entries_root);
+}
+
+#[tokio::test]
+async fn test_fleet_submissions_verified_stored_anchored_and_audited() {
+    let pki = pck::tests::Pki::new();
+    let clock = MockClock::new(start());
+    let mut gateway = Gateway::start(&pki, 0x07, &clock).await;
+    let mut chain = AnchorStub::default();
+    let mut enclaves = vec![
+        Enclave::new("R-001", MR_ENCLAVE, &pki),
+        Enclave::new("R-002", MR_ENCLAVE, &pki),
+    ];
+    enclaves.iter().for_each(|enclave| gateway.enroll(enclave));
+
+    let mut accepted = Vec::new();
+    for round in 0..3 {
+        clock.advance(Duration::minutes(40));
+        for enclave in &mut enclaves {
+            let state = format!("{} joint state {round}", enclave.robot_id.0);
+            let submission = enclave.checkpoint(&[state.as_bytes(), b"camera frame"], clock.now());
+            accepted.push(submission.checkpoint.clone());
+            gateway.submit(submission).await.unwrap();
+        }
+        gateway.anchor(&mut chain);
+        chain.advance(1);
+    }
+    // Spread over three hourly shards; the last roots still need confirmations
+    assert_eq!(gateway.store.summaries().unwrap().len(), 3);
+    assert_eq!(chain.submitted.len(), 6);
+    chain.advance(3);
+    gateway.anchor(&mut chain);
+
+    for checkpoint in &accepted {
+        audit_checkpoint(&gateway, checkpoint);
+    }
+    let events = gateway.audit_events();
+    assert_eq!(events.len(), 6);
+    let export = AuditExport::since(&events, 0);
+    AuditExport::from_bytes(&export.to_bytes().unwrap())
+        .unwrap()
+        .verify(&gateway.signer.verifying_key())
+        .unwrap();
+
+    // Archiving the oldest shard keeps its checkpoints provable from the archive
+    let first = gateway.store.window_start(accepted[0].local_timestamp_utc);
+    let archive = gateway.store.archive(first).unwrap();
+    let shard = gateway.store.verify_archive(&archive).unwrap();
+    assert_eq!(shard.checkpoints().len(), 2);
+    assert_eq!(gateway.store.checkpoints(&accepted[0].robot_id).len(), 2);
+}
+
+#[tokio::test]
+async fn test_untrusted_submissions_rejected_and_audited() {
+    let pki = pck::tests::Pki::new();
+    let clock = MockClock::new(start());
+    let mut gateway = Gateway::start(&pki, 0x07, &clock).await;
+    let mut chain = AnchorStub::default();
+    let mut honest = Enclave::new("R-001", MR_ENCLAVE, &pki);
+    let mut foreign = Enclave::new("R-002", [0x66; 32], &pki);
+    gateway.enroll(&honest);
+    gateway.enroll(&foreign);
+
+    // An enclave running other code
+    let submission = foreign.checkpoint(&[b"joint state"], clock.now());
+    assert_eq!(
+        gateway.submit(submission).await,
+        Err(ErrorCode::VerificationFailed)
+    );
+
+    // A quote from another robot's enclave, replayed under this robot's checkpoint
+    let mut submission = honest.checkpoint(&[b"joint state"], clock.now());
+    submission.quote = Enclave::new("R-003", MR_ENCLAVE, &pki).quote();
+    assert_eq!(
+        gateway.submit(submission).await,
+        Err(ErrorCode::VerificationFailed)
+    );
+
+    // A quote altered after the Quoting Enclave signed it, in a field the
+    // gateway does not check itself (ISVSVN)
+    let mut submission = honest.checkpoint(&[b"joint state"], clock.now());
+    submission.quote[48 + 258] ^= 1;
+    assert_eq!(
+        gateway.submit(submission).await,
+        Err(ErrorCode::VerificationFailed)
+    );
+
+    // Entries that do not match the signed root are not stored
+    let mut submission = honest.checkpoint(&[b"joint state"], clock.now());
+    submission.entries.pop();
+    assert_eq!(
+        gateway.submit(submission).await,
+        Err(ErrorCode::VerificationFailed)
+    );
+
+    // A platform whose PCK certificate Intel revoked
+    let revoking = Gateway::start(&pki, 0x42, &clock).await;
+    let mut revoking = Gateway {
+        robots: gateway.robots.clone(),
+        ..revoking
+    };
+    let submission = honest.checkpoint(&[b"joint state"], clock.now());
+    assert_eq!(
+        revoking.submit(submission).await,
+        Err(ErrorCode::MeasurementRevoked)
+    );
+
+    // Nothing was anchored, and every rejection is on the audit record
+    gateway.anchor(&mut chain);
+    assert!(chain.submitted.is_empty());
+    let events = gateway.audit_events();
+    assert_eq!(events.len(), 4);
+    assert!(events
+        .iter()
+        .all(|event| event.error_codes == [ErrorCode::VerificationFailed]));
+    AuditExport::since(&events, 0)
+        .verify(&gateway.signer.verifying_key())
+        .unwrap();
+    let revoked = revoking.audit_events();
+    assert_eq!(revoked[0].error_codes, [ErrorCode::MeasurementRevoked]);
+}
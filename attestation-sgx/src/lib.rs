@@ -1,30 +1,74 @@
 //! Intel SGX DCAP (Data Center Attestation Primitives) attestation adapter.
 //!
 //! This module implements remote attestation verification for Intel SGX enclaves
-//! using the DCAP protocol (PCK-based attestation without IAS).
+//! using the DCAP protocol (PCK-based attestation without IAS). The same
+//! adapter verifies Intel TDX quotes when configured with [`DcapTee::Tdx`]
+//! (see [`SgxDcapAdapter::tdx`]).
 //!
 //! ## Verification Flow
-//! 1. Parse SGX quote (ECDSA-p256)
-//! 2. Extract enclave measurement (MRENCLAVE) and attributes
+//! 1. Parse SGX quote v3 or, in [`DcapTee::Tdx`] mode, TDX quote v4 (ECDSA-p256)
+//! 2. Extract enclave measurement (MRENCLAVE) and attributes, and check that
+//!    the report data commits to the verifier's nonce, if one is given (see
+//!    [`nonce_binding`])
 //! 3. Verify PCK certificate chain
-//! 4. Check CRL for revoked certificates
-//! 5. Verify quote signature
-//! 6. Return attestation result
+//! 4. Check the PCK CRLs for revoked certificates; they are fetched on
+//!    trust anchor refresh once a [`dcap::PcsClient`] is configured, for
+//!    Intel PCS or a PCCS (see [`SgxConfig::collateral_client`])
+//! 5. Verify the PCK leaf key signed the QE report, the QE report binds the
+//!    attestation key, and the attestation key signed the quote
+//! 6. Check the QE report against Intel's QE Identity (see [`qe`]), when one
+//!    has been fetched with [`SgxDcapAdapter::with_pcs_client`]
+//! 7. Return attestation result
+//!
+//! TDX quotes go through steps 3 to 6 alike, with the PCK chain read from
+//! the QE report certification data (type 6) that wraps it.
+//!
+//! ## Offline verification
+//! Air-gapped verifiers cannot reach PCS. On a connected machine,
+//! [`SgxDcapAdapter::export_collateral`] fetches the root, PCK CRLs, QE
+//! Identity and TCB info into a [`CollateralBundle`], to be signed and
+//! carried over as CBOR; [`SgxDcapAdapter::load_collateral`] then verifies
+//! against it without any network access.
+//!
+//! ## Async runtimes
+//! The adapter does not depend on an executor: trust anchors sit behind a
+//! std lock that is never held across an `.await`, and collateral is fetched
+//! through [`dcap::HttpClient`]. Only the default `reqwest` feature, which
+//! provides that client, pulls in tokio.
 
+pub mod anchors;
+pub mod cache;
 pub mod dcap;
-pub mod quote;
 pub mod pck;
+pub mod qe;
+pub mod quote;
+
+#[cfg(test)]
+mod e2e;
 
-use attestation_core::{AttestationAdapter, AttestationError, AttestationResult, RevocationStatus};
+use anchors::{AnchorConfigError, Pins, TrustAnchorConfig};
 use async_trait::async_trait;
-use chrono::Utc;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use attestation_core::{
+    collateral::documents, ct_eq, system_clock, AttestationAdapter, AttestationError,
+    AttestationResult, ClaimValue, Clock, CollateralBundle, CollateralError, RevocationCheck,
+    RevocationSource,
+};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
 /// Intel SGX DCAP attestation adapter.
 pub struct SgxDcapAdapter {
     config: SgxConfig,
-    trust_anchors: Arc<RwLock<TrustAnchors>>,
+    /// Current trust anchors; verification runs against a snapshot
+    trust_anchors: RwLock<Arc<TrustAnchors>>,
+    /// Trust anchor file to re-read on refresh (see [`anchors`])
+    anchor_file: Option<PathBuf>,
+    /// Source of verification times and cache ages
+    clock: Arc<dyn Clock>,
+    /// Intel PCS, for collateral refreshed with the trust anchors
+    pcs: Option<dcap::PcsClient>,
 }
 
 /// Configuration for SGX DCAP verification.
@@ -32,43 +76,190 @@ pub struct SgxDcapAdapter {
 pub struct SgxConfig {
     /// URL for Intel PCS (Provisioning Certification Service)
     pub pcs_url: String,
+    /// Where collateral is fetched from: Intel PCS at `pcs_url`, or a PCCS
+    pub collateral_source: dcap::CollateralSource,
+    /// API key for the collateral source: the Intel PCS subscription key or
+    /// the PCCS user token
+    pub api_key: Option<String>,
     /// Cache expiry for CRLs and certificates (seconds)
     pub cache_expiry_secs: u64,
     /// Allow debug enclaves (should be false in production)
     pub allow_debug: bool,
+    /// Size limits enforced while parsing quotes
+    pub quote_limits: quote::QuoteLimits,
+    /// Which quotes the adapter verifies; also selects its vendor name
+    pub tee: DcapTee,
+    /// Reject quotes while no verified QE Identity is at hand
+    pub require_qe_identity: bool,
+    /// Accept quotes that embed no PCK certificate chain (certification
+    /// data type 5). Without the PCK key their QE report, and so the
+    /// attestation key it binds, cannot be authenticated: they are returned
+    /// with `quote_verified: false`. Leave this off outside development setups
+    pub allow_missing_pck_chain: bool,
+    /// Directory to cache PCS collateral in across restarts (see [`cache`]);
+    /// responses younger than `cache_expiry_secs` are served from it
+    pub collateral_cache_dir: Option<PathBuf>,
+}
+
+/// TEE whose DCAP quotes an adapter verifies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DcapTee {
+    /// SGX enclaves, quote v3 (vendor "intel-sgx")
+    #[default]
+    Sgx,
+    /// TDX trust domains, quote v4 (vendor "intel-tdx"); the measurement is MRTD
+    Tdx,
+}
+
+impl DcapTee {
+    /// Vendor name the adapter registers under.
+    pub fn vendor(&self) -> &'static str {
+        match self {
+            DcapTee::Sgx => "intel-sgx",
+            DcapTee::Tdx => "intel-tdx",
+        }
+    }
 }
 
 impl Default for SgxConfig {
     fn default() -> Self {
         Self {
             pcs_url: "https://api.trustedservices.intel.com/sgx/certification/v4".to_string(),
+            collateral_source: dcap::CollateralSource::IntelPcs,
+            api_key: None,
             cache_expiry_secs: 3600, // 1 hour
             allow_debug: false,
+            quote_limits: quote::QuoteLimits::default(),
+            tee: DcapTee::Sgx,
+            require_qe_identity: false,
+            allow_missing_pck_chain: false,
+            collateral_cache_dir: None,
         }
     }
 }
 
-/// Trust anchors (root CA, CRLs) for SGX attestation.
+impl SgxConfig {
+    /// Client for the configured collateral source, with the API key if
+    /// set, sending its requests through `http`.
+    pub fn collateral_client(&self, http: Arc<dyn dcap::HttpClient>) -> dcap::PcsClient {
+        let client = dcap::PcsClient::for_source(&self.collateral_source, &self.pcs_url, http);
+        match &self.api_key {
+            Some(key) => client.with_api_key(key.clone()),
+            None => client,
+        }
+    }
+
+    /// [`SgxConfig::collateral_client`] over reqwest.
+    #[cfg(feature = "reqwest")]
+    pub fn pcs_client(&self) -> dcap::PcsClient {
+        self.collateral_client(Arc::new(reqwest::Client::new()))
+    }
+}
+
+/// Trust anchors (root CAs, pins, CRLs) for SGX attestation.
 #[derive(Debug, Clone)]
+#[allow(dead_code)] // intermediates are not consulted: quotes embed the whole PCK chain
 struct TrustAnchors {
-    root_ca_cert: String,
+    root_ca_certs: Vec<String>,
     intermediate_certs: Vec<String>,
     crls: Vec<Vec<u8>>,
-    last_updated: chrono::DateTime<chrono::Utc>,
+    pins: Pins,
+    /// Verified QE Identity, once fetched
+    qe_identity: Option<dcap::EnclaveIdentity>,
+    /// When CRLs and QE Identity were last fetched; `None` until the first
+    /// refresh
+    last_updated: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl TrustAnchors {
+    /// Build trust anchors from a validated trust anchor file.
+    ///
+    /// Falls back to the built-in Intel SGX root when no roots are configured.
+    fn from_config(config: &TrustAnchorConfig) -> Result<Self, AnchorConfigError> {
+        let pins = config.validate()?;
+        let root_ca_certs = if config.root_cas.is_empty() {
+            vec![INTEL_SGX_ROOT_CA.to_string()]
+        } else {
+            config.root_cas.clone()
+        };
+
+        Ok(Self {
+            root_ca_certs,
+            pins,
+            ..Self::default()
+        })
+    }
+
+    /// Build trust anchors from an offline collateral bundle.
+    ///
+    /// The first certificate is taken as the root CA, the rest as intermediates.
+    /// Pins are operator policy rather than collateral, so they are carried over.
+    /// A bundled QE Identity is verified against the bundle's root at `now`.
+    fn from_collateral(
+        collateral: &CollateralBundle,
+        pins: Pins,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Self, AttestationError> {
+        let (root, intermediates) = collateral.certificates.split_first().ok_or_else(|| {
+            AttestationError::Config("Collateral bundle has no root certificate".to_string())
+        })?;
+
+        let mut anchors = Self {
+            root_ca_certs: vec![root.clone()],
+            intermediate_certs: intermediates.to_vec(),
+            crls: collateral.crls.clone(),
+            pins,
+            qe_identity: None,
+            last_updated: Some(collateral.fetched_at),
+        };
+        if let Some(body) = collateral.document(documents::QE_IDENTITY) {
+            let issuer_chain = collateral
+                .document(documents::QE_IDENTITY_ISSUER_CHAIN)
+                .and_then(|chain| String::from_utf8(chain.to_vec()).ok())
+                .ok_or_else(|| {
+                    AttestationError::Config(
+                        "Collateral QE Identity has no issuer chain".to_string(),
+                    )
+                })?;
+            let signed = dcap::SignedEnclaveIdentity {
+                body: body.to_vec(),
+                issuer_chain,
+            };
+            let identity = qe::verify_qe_identity(&signed, &anchors, now)
+                .map_err(|e| AttestationError::VerificationFailed(e.to_string()))?;
+            anchors.qe_identity = Some(identity);
+        }
+        Ok(anchors)
+    }
+}
+
+/// Lookups shared by the quotes of one batch.
+///
+/// A fleet's quotes come from a handful of platforms and enclave builds, so
+/// PCK chains and measurements repeat across a re-attestation sweep; each
+/// distinct one is checked once per batch.
+#[derive(Default)]
+struct BatchCache {
+    /// PCK chain verification outcome, by chain
+    pck_chains: HashMap<String, Result<pck::VerifiedPck, pck::PckError>>,
+    /// Revocation check, by measurement
+    revocations: HashMap<Vec<u8>, RevocationCheck>,
 }
 
 impl Default for TrustAnchors {
     fn default() -> Self {
         Self {
-            root_ca_cert: INTEL_SGX_ROOT_CA.to_string(),
+            root_ca_certs: vec![INTEL_SGX_ROOT_CA.to_string()],
             intermediate_certs: Vec::new(),
             crls: Vec::new(),
-            last_updated: Utc::now(),
+            pins: Pins::default(),
+            qe_identity: None,
+            last_updated: None,
         }
     }
 }
 
-/// Intel SGX Root CA certificate (PEM)
+/// Intel SGX Root CA certificate (PEM), used when no roots are configured
 const INTEL_SGX_ROOT_CA: &str = r#"-----BEGIN CERTIFICATE-----
 MIICjzCCAjSgAwIBAgIUImUM1lqdNInzg7SVUr9QGzknBqwwCgYIKoZIzj0EAwIw
 aDEaMBgGA1UEAwwRSW50ZWwgU0dYIFJvb3QgQ0ExGjAYBgNVBAoMEUludGVsIENv
@@ -86,34 +277,290 @@ KoZIzj0EAwIDSQAwRgIhAIpQ/KdMQPcbdLTq3ug17QPoGo3bILKQN8r0XiajmKOJ
 AiEA3e5tYNTqoTasDpKQJ4dWqkWVyCUsKgVLfHUELxOCLjY=
 -----END CERTIFICATE-----"#;
 
+/// The second half of the report data of a quote answering `nonce`.
+///
+/// An enclave challenged with a nonce puts SHA-256(nonce) in the last 32
+/// bytes of its report data; the first 32 stay free for its own binding,
+/// such as its signing key. A quote taken before the nonce was issued
+/// cannot commit to it, so an old quote is not accepted against a fresh
+/// nonce.
+pub fn nonce_binding(nonce: &[u8]) -> [u8; 32] {
+    Sha256::digest(nonce).into()
+}
+
+/// Refuse a quote whose report data does not commit to `nonce`, if given.
+fn check_nonce(report_data: &[u8; 64], nonce: Option<&[u8]>) -> Result<(), AttestationError> {
+    match nonce {
+        Some(nonce) if !ct_eq(&report_data[32..], &nonce_binding(nonce)) => {
+            Err(AttestationError::VerificationFailed(
+                "Quote report data does not commit to the nonce".to_string(),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
 impl SgxDcapAdapter {
     /// Create a new SGX DCAP adapter with default configuration.
     pub fn new() -> Self {
         Self::with_config(SgxConfig::default())
     }
 
+    /// Create an adapter for TDX quotes with default configuration.
+    pub fn tdx() -> Self {
+        Self::with_config(SgxConfig {
+            tee: DcapTee::Tdx,
+            ..SgxConfig::default()
+        })
+    }
+
     /// Create a new SGX DCAP adapter with custom configuration.
     pub fn with_config(config: SgxConfig) -> Self {
         Self {
             config,
-            trust_anchors: Arc::new(RwLock::new(TrustAnchors::default())),
+            trust_anchors: RwLock::new(Arc::new(TrustAnchors::default())),
+            anchor_file: None,
+            clock: system_clock(),
+            pcs: None,
+        }
+    }
+
+    /// Create an adapter whose root CAs and pins are loaded from a TOML file.
+    ///
+    /// The file is re-read on every [`SgxDcapAdapter::reload_trust_anchors`]
+    /// (and therefore on every `update_trust_anchors`).
+    pub fn with_trust_anchor_file(
+        config: SgxConfig,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, AnchorConfigError> {
+        let anchors = TrustAnchors::from_config(&TrustAnchorConfig::load(&path)?)?;
+        Ok(Self {
+            config,
+            trust_anchors: RwLock::new(Arc::new(anchors)),
+            anchor_file: Some(path.as_ref().to_path_buf()),
+            clock: system_clock(),
+            pcs: None,
+        })
+    }
+
+    /// Read verification times and the trust anchor cache age from `clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Fetch collateral from Intel PCS through `pcs` whenever the trust
+    /// anchors are refreshed: the PCK CRLs, which embedded PCK chains are
+    /// then checked against, and the QE Identity, which SGX quotes' QE
+    /// reports are then checked against.
+    ///
+    /// With [`SgxConfig::collateral_cache_dir`] set, collateral is cached
+    /// there, timed by the adapter's clock (so set it first). A cache that
+    /// cannot be opened is skipped with a warning.
+    pub fn with_pcs_client(mut self, pcs: dcap::PcsClient) -> Self {
+        let cache = self.config.collateral_cache_dir.as_ref().and_then(|dir| {
+            cache::CollateralCache::open(dir)
+                .inspect_err(|e| {
+                    tracing::warn!("Not caching SGX collateral in {}: {e}", dir.display())
+                })
+                .ok()
+        });
+        self.pcs = Some(match cache {
+            Some(cache) => pcs.with_cache(
+                cache
+                    .with_max_age(chrono::Duration::seconds(
+                        self.config.cache_expiry_secs as i64,
+                    ))
+                    .with_clock(self.clock.clone()),
+            ),
+            None => pcs,
+        });
+        self
+    }
+
+    /// Fetch the processor and platform PCK CRLs, and put them in the trust
+    /// anchors.
+    ///
+    /// Both must parse as CRLs; otherwise the previously fetched CRLs stay
+    /// in place.
+    pub async fn refresh_pck_crls(&self) -> Result<(), AttestationError> {
+        let Some(pcs) = &self.pcs else {
+            return Ok(());
+        };
+        let crls = fetch_pck_crls(pcs).await?;
+        tracing::info!("Fetched {} PCK CRLs", crls.len());
+        self.update_anchors(|anchors| {
+            anchors.crls = crls.into_iter().map(|(crl, _)| crl).collect()
+        });
+        Ok(())
+    }
+
+    /// Fetch collateral from PCS for verifying SGX quotes offline elsewhere.
+    ///
+    /// The bundle holds the first configured root CA, the processor and
+    /// platform PCK CRLs, the QE Identity with its issuer chain, and the TCB
+    /// info of each of `fmspcs`. The QE Identity is verified before export,
+    /// and the bundle is valid until the earliest next update among its
+    /// CRLs and QE Identity. Sign it with [`CollateralBundle::sign`] to
+    /// distribute it.
+    pub async fn export_collateral(
+        &self,
+        fmspcs: &[&str],
+    ) -> Result<CollateralBundle, AttestationError> {
+        let Some(pcs) = &self.pcs else {
+            return Err(AttestationError::Config(
+                "Exporting collateral needs a PCS client".to_string(),
+            ));
+        };
+        if self.config.tee != DcapTee::Sgx {
+            return Err(CollateralError::Unsupported(self.vendor_name().to_string()).into());
+        }
+        let network = |e: dcap::DcapError| AttestationError::Network(e.to_string());
+        let now = self.clock.now();
+
+        let mut bundle = CollateralBundle::new(self.vendor_name(), now, now);
+        bundle.certificates = self
+            .anchors()
+            .root_ca_certs
+            .iter()
+            .take(1)
+            .cloned()
+            .collect();
+        let mut valid_until = chrono::DateTime::<chrono::Utc>::MAX_UTC;
+        for (crl, next_update) in fetch_pck_crls(pcs).await? {
+            valid_until = valid_until.min(next_update.unwrap_or(valid_until));
+            bundle.crls.push(crl);
+        }
+        let signed = pcs.get_qe_identity().await.map_err(network)?;
+        bundle
+            .documents
+            .insert(documents::QE_IDENTITY.to_string(), signed.body);
+        bundle.documents.insert(
+            documents::QE_IDENTITY_ISSUER_CHAIN.to_string(),
+            signed.issuer_chain.into_bytes(),
+        );
+        for fmspc in fmspcs {
+            let tcb_info = pcs.get_tcb_info_raw(fmspc).await.map_err(network)?;
+            bundle
+                .documents
+                .insert(documents::tcb_info_for(fmspc), tcb_info);
         }
+
+        let anchors = TrustAnchors::from_collateral(&bundle, Pins::default(), now)?;
+        let identity = anchors
+            .qe_identity
+            .as_ref()
+            .expect("bundle carries a QE Identity");
+        let next_update =
+            chrono::DateTime::parse_from_rfc3339(&identity.next_update).map_err(|e| {
+                AttestationError::VerificationFailed(format!("QE Identity next update: {e}"))
+            })?;
+        bundle.valid_until = valid_until.min(next_update.to_utc());
+        tracing::info!("Exported SGX collateral valid until {}", bundle.valid_until);
+        Ok(bundle)
+    }
+
+    /// Verify against `collateral` from now on instead of fetched collateral.
+    ///
+    /// For air-gapped verifiers: the bundle's root, CRLs and QE Identity
+    /// replace the trust anchors, and pins stay. A bundle for another vendor,
+    /// outside its validity window or with a QE Identity that does not
+    /// verify is rejected, and the current anchors stay in effect.
+    pub fn load_collateral(&self, collateral: &CollateralBundle) -> Result<(), AttestationError> {
+        if collateral.vendor != self.vendor_name() {
+            return Err(CollateralError::VendorMismatch {
+                expected: self.vendor_name().to_string(),
+                actual: collateral.vendor.clone(),
+            }
+            .into());
+        }
+        let now = self.clock.now();
+        collateral.check_validity(now)?;
+        let loaded = TrustAnchors::from_collateral(collateral, self.anchors().pins.clone(), now)?;
+        self.update_anchors(|anchors| *anchors = loaded);
+        tracing::info!("Loaded SGX collateral fetched at {}", collateral.fetched_at);
+        Ok(())
+    }
+
+    /// Fetch and verify the QE Identity, and put it in the trust anchors.
+    ///
+    /// A failure leaves the previously verified identity in place.
+    pub async fn refresh_qe_identity(&self) -> Result<(), AttestationError> {
+        let Some(pcs) = &self.pcs else {
+            return Ok(());
+        };
+        let signed = pcs
+            .get_qe_identity()
+            .await
+            .map_err(|e| AttestationError::Network(e.to_string()))?;
+        let identity = qe::verify_qe_identity(&signed, &self.anchors(), self.clock.now())
+            .map_err(|e| AttestationError::VerificationFailed(e.to_string()))?;
+        tracing::info!(
+            "Verified QE Identity (TCB evaluation data number {})",
+            identity.tcb_evaluation_data_number
+        );
+        self.update_anchors(|anchors| anchors.qe_identity = Some(identity));
+        Ok(())
+    }
+
+    /// Re-read the trust anchor file, if one is configured.
+    ///
+    /// An invalid file is rejected and the current anchors stay in effect.
+    /// Fetched CRLs and intermediates are kept across reloads.
+    pub async fn reload_trust_anchors(&self) -> Result<(), AnchorConfigError> {
+        let Some(path) = &self.anchor_file else {
+            return Ok(());
+        };
+        let reloaded = TrustAnchors::from_config(&TrustAnchorConfig::load(path)?)?;
+
+        self.update_anchors(|anchors| {
+            anchors.root_ca_certs = reloaded.root_ca_certs;
+            anchors.pins = reloaded.pins;
+        });
+        tracing::info!("Reloaded SGX trust anchors from {}", path.display());
+        Ok(())
     }
 
-    /// Verify an SGX quote with DCAP.
+    /// The current trust anchors.
+    fn anchors(&self) -> Arc<TrustAnchors> {
+        self.trust_anchors
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Apply `update` to the trust anchors. Snapshots taken before keep the
+    /// anchors they were taken with.
+    fn update_anchors<T>(&self, update: impl FnOnce(&mut TrustAnchors) -> T) -> T {
+        let mut anchors = self
+            .trust_anchors
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        update(Arc::make_mut(&mut anchors))
+    }
+
+    /// Verify an SGX quote with DCAP against the given trust anchors.
     async fn verify_quote_internal(
         &self,
         quote_bytes: &[u8],
-        _nonce: Option<&[u8]>,
+        nonce: Option<&[u8]>,
+        trust_anchors: &TrustAnchors,
+        cache: &mut BatchCache,
     ) -> Result<AttestationResult, AttestationError> {
+        if self.config.tee == DcapTee::Tdx {
+            return self
+                .verify_tdx_quote(quote_bytes, nonce, trust_anchors, cache)
+                .await;
+        }
+
         // Parse the quote
-        let quote = quote::parse_sgx_quote_v3(quote_bytes)
+        let quote = quote::parse_sgx_quote_v3_with_limits(quote_bytes, &self.config.quote_limits)
             .map_err(|e| AttestationError::InvalidQuote(e.to_string()))?;
 
         tracing::debug!(
             "Parsed SGX quote: MRENCLAVE={}, MRSIGNER={}, Debug={}",
-            hex::encode(&quote.mr_enclave),
-            hex::encode(&quote.mr_signer),
+            hex::encode(quote.mr_enclave),
+            hex::encode(quote.mr_signer),
             quote.debug_mode
         );
 
@@ -124,30 +571,253 @@ impl SgxDcapAdapter {
             ));
         }
 
-        // Verify PCK certificate chain (if present)
-        if let Some(pck_chain_data) = &quote.certification_data {
-            pck::verify_pck_chain(pck_chain_data, &self.trust_anchors.read().await)
-                .await
-                .map_err(|e| AttestationError::VerificationFailed(e.to_string()))?;
-        }
+        check_nonce(&quote.report_data, nonce)?;
+
+        // Check measurement pins
+        trust_anchors
+            .pins
+            .check_measurement(&quote.mr_enclave, &quote.mr_signer)
+            .map_err(AttestationError::VerificationFailed)?;
+
+        // Verify the PCK certificate chain, and that its leaf signed this
+        // platform's QE report, which binds the attestation key
+        let certified = self
+            .certify_qe_report(
+                quote.certification_data.as_deref(),
+                trust_anchors,
+                cache,
+                |key| quote::verify_qe_report(&quote, key),
+            )
+            .await?;
+        let mut claims = quote.claims();
+        self.platform_claims(certified.as_ref(), trust_anchors, &mut claims)?;
 
-        // Verify quote signature (ECDSA-p256 over quote body)
+        // Verify the quote signature: the attestation key signed the header and report body
         quote::verify_quote_signature(&quote)
             .map_err(|e| AttestationError::VerificationFailed(e.to_string()))?;
 
-        // Check revocation
-        let revoke_status = self.check_revocation(&quote.mr_enclave).await?;
+        let revoke_status = self
+            .platform_revocation(&quote.mr_enclave, certified.as_ref(), cache)
+            .await?;
 
         Ok(AttestationResult {
             vendor: "intel-sgx".to_string(),
             enclave_measurement: quote.mr_enclave.to_vec(),
-            quote_verified: true,
-            verified_at: Utc::now(),
+            quote_verified: certified.is_some(),
+            verified_at: self.clock.now(),
             revoke_check: revoke_status,
             raw_quote: Some(quote_bytes.to_vec()),
             pck_chain: quote.certification_data.clone(),
+            claims,
         })
     }
+
+    /// Verify a TDX quote v4 with DCAP against the given trust anchors.
+    ///
+    /// MRENCLAVE and MRSIGNER pins have no TDX counterpart: an adapter with
+    /// such pins refuses TDX quotes rather than ignore them.
+    async fn verify_tdx_quote(
+        &self,
+        quote_bytes: &[u8],
+        nonce: Option<&[u8]>,
+        trust_anchors: &TrustAnchors,
+        cache: &mut BatchCache,
+    ) -> Result<AttestationResult, AttestationError> {
+        let quote = quote::parse_tdx_quote_v4_with_limits(quote_bytes, &self.config.quote_limits)
+            .map_err(|e| AttestationError::InvalidQuote(e.to_string()))?;
+
+        tracing::debug!(
+            "Parsed TDX quote: MRTD={}, Debug={}",
+            hex::encode(quote.mr_td),
+            quote.debug_mode
+        );
+
+        if quote.debug_mode && !self.config.allow_debug {
+            return Err(AttestationError::VerificationFailed(
+                "Debug trust domains are not allowed".to_string(),
+            ));
+        }
+
+        check_nonce(&quote.report_data, nonce)?;
+
+        let pins = &trust_anchors.pins;
+        if !pins.mr_enclave.is_empty() || !pins.mr_signer.is_empty() {
+            return Err(AttestationError::Config(
+                "MRENCLAVE/MRSIGNER pins do not apply to TDX quotes".to_string(),
+            ));
+        }
+
+        // The same platform checks as SGX quotes, on the PCK chain inside
+        // the QE report certification data
+        let certified = self
+            .certify_qe_report(
+                quote.certification_data.as_deref(),
+                trust_anchors,
+                cache,
+                |key| quote::verify_tdx_qe_report(&quote, key),
+            )
+            .await?;
+        let mut claims = quote.claims();
+        self.platform_claims(certified.as_ref(), trust_anchors, &mut claims)?;
+
+        quote::verify_tdx_quote_signature(&quote)
+            .map_err(|e| AttestationError::VerificationFailed(e.to_string()))?;
+
+        let revoke_status = self
+            .platform_revocation(&quote.mr_td, certified.as_ref(), cache)
+            .await?;
+
+        Ok(AttestationResult {
+            vendor: DcapTee::Tdx.vendor().to_string(),
+            enclave_measurement: quote.mr_td.to_vec(),
+            quote_verified: certified.is_some(),
+            verified_at: self.clock.now(),
+            revoke_check: revoke_status,
+            raw_quote: Some(quote_bytes.to_vec()),
+            pck_chain: quote.certification_data.clone(),
+            claims,
+        })
+    }
+
+    /// Verify the PCK certificate chain a quote embeds, once per batch, and
+    /// the QE report its leaf key signed, with `verify_qe_report`.
+    ///
+    /// Returns `None` for a quote without a chain if
+    /// [`SgxConfig::allow_missing_pck_chain`] is set, and refuses it otherwise.
+    async fn certify_qe_report(
+        &self,
+        pck_chain: Option<&str>,
+        trust_anchors: &TrustAnchors,
+        cache: &mut BatchCache,
+        verify_qe_report: impl FnOnce(&[u8]) -> Result<quote::QeReport, quote::QuoteError>,
+    ) -> Result<Option<(quote::QeReport, pck::VerifiedPck)>, AttestationError> {
+        let Some(pck_chain) = pck_chain else {
+            if !self.config.allow_missing_pck_chain {
+                return Err(AttestationError::VerificationFailed(
+                    "Quote embeds no PCK certificate chain".to_string(),
+                ));
+            }
+            tracing::warn!("Quote embeds no PCK certificate chain; its platform is not verified");
+            return Ok(None);
+        };
+        let verified = match cache.pck_chains.get(pck_chain) {
+            Some(verified) => verified.clone(),
+            None => {
+                let verified =
+                    pck::verify_pck_chain(pck_chain, trust_anchors, self.clock.now()).await;
+                cache
+                    .pck_chains
+                    .insert(pck_chain.to_string(), verified.clone());
+                verified
+            }
+        }?;
+        let report = verify_qe_report(&verified.public_key)
+            .map_err(|e| AttestationError::VerificationFailed(e.to_string()))?;
+        Ok(Some((report, verified)))
+    }
+
+    /// Add the claims of a certified platform: its PCK certificate
+    /// extensions, and the TCB status of its Quoting Enclave.
+    fn platform_claims(
+        &self,
+        certified: Option<&(quote::QeReport, pck::VerifiedPck)>,
+        trust_anchors: &TrustAnchors,
+        claims: &mut BTreeMap<String, ClaimValue>,
+    ) -> Result<(), AttestationError> {
+        if let Some((_, verified)) = certified {
+            claims.extend(verified.extensions.claims());
+        }
+        // Verify the QE itself is Intel's Quoting Enclave at an unrevoked TCB
+        if let Some(status) =
+            self.check_qe_identity(certified.map(|(report, _)| report), trust_anchors)?
+        {
+            claims.insert("sgx.qe_tcb_status".to_string(), ClaimValue::Text(status));
+        }
+        Ok(())
+    }
+
+    /// Check revocation of `measurement`; an unrevoked PCK chain counts as
+    /// checked against the CRLs that applied to it.
+    async fn platform_revocation(
+        &self,
+        measurement: &[u8],
+        certified: Option<&(quote::QeReport, pck::VerifiedPck)>,
+        cache: &mut BatchCache,
+    ) -> Result<RevocationCheck, AttestationError> {
+        let revoke_status = self.cached_revocation(measurement, cache).await?;
+        let freshness = certified.and_then(|(_, verified)| verified.crl_freshness);
+        match (&revoke_status.source, freshness) {
+            (RevocationSource::NotChecked, Some(freshness)) => {
+                Ok(RevocationCheck::ok(RevocationSource::Crl).with_crl_freshness(freshness))
+            }
+            _ => Ok(revoke_status),
+        }
+    }
+
+    /// Check a verified QE report against the QE Identity in the trust
+    /// anchors, at the adapter's clock, and return its TCB status.
+    ///
+    /// Returns `None` without a report (no PCK chain vouched for one) or
+    /// without an identity to check it against, unless
+    /// [`SgxConfig::require_qe_identity`] is set.
+    fn check_qe_identity(
+        &self,
+        report: Option<&quote::QeReport>,
+        trust_anchors: &TrustAnchors,
+    ) -> Result<Option<String>, AttestationError> {
+        let (Some(report), Some(identity)) = (report, &trust_anchors.qe_identity) else {
+            if self.config.require_qe_identity {
+                return Err(AttestationError::VerificationFailed(
+                    "No verified QE report and QE Identity to check the quoting enclave against"
+                        .to_string(),
+                ));
+            }
+            tracing::debug!("Quoting enclave identity not checked");
+            return Ok(None);
+        };
+        let status = qe::check_qe_report(identity, report, self.clock.now())
+            .map_err(|e| AttestationError::VerificationFailed(e.to_string()))?;
+        Ok(Some(status))
+    }
+
+    /// Check revocation of `measurement`, once per batch.
+    async fn cached_revocation(
+        &self,
+        measurement: &[u8],
+        cache: &mut BatchCache,
+    ) -> Result<RevocationCheck, AttestationError> {
+        if let Some(check) = cache.revocations.get(measurement) {
+            return Ok(check.clone());
+        }
+        let check = self.check_revocation(measurement).await?;
+        cache
+            .revocations
+            .insert(measurement.to_vec(), check.clone());
+        Ok(check)
+    }
+}
+
+/// Fetch the processor and platform PCK CRLs, with their next updates.
+///
+/// Both must parse as CRLs; their signatures are checked against each PCK
+/// chain they apply to.
+async fn fetch_pck_crls(
+    pcs: &dcap::PcsClient,
+) -> Result<Vec<(Vec<u8>, Option<chrono::DateTime<chrono::Utc>>)>, AttestationError> {
+    let mut crls = Vec::new();
+    for ca in ["processor", "platform"] {
+        let crl = pcs
+            .get_pck_crl(ca)
+            .await
+            .map_err(|e| AttestationError::Network(e.to_string()))?;
+        let (_, parsed) = x509_parser::parse_x509_crl(&crl)
+            .map_err(|e| AttestationError::VerificationFailed(format!("PCK {ca} CRL: {e}")))?;
+        let next_update = parsed
+            .next_update()
+            .and_then(|t| chrono::DateTime::from_timestamp(t.timestamp(), 0));
+        crls.push((crl, next_update));
+    }
+    Ok(crls)
 }
 
 impl Default for SgxDcapAdapter {
@@ -159,7 +829,7 @@ impl Default for SgxDcapAdapter {
 #[async_trait]
 impl AttestationAdapter for SgxDcapAdapter {
     fn vendor_name(&self) -> &str {
-        "intel-sgx"
+        self.config.tee.vendor()
     }
 
     async fn verify_quote(
@@ -167,44 +837,86 @@ impl AttestationAdapter for SgxDcapAdapter {
         quote: &[u8],
         nonce: Option<&[u8]>,
     ) -> Result<AttestationResult, AttestationError> {
-        self.verify_quote_internal(quote, nonce).await
+        let trust_anchors = self.anchors();
+        self.verify_quote_internal(quote, nonce, &trust_anchors, &mut BatchCache::default())
+            .await
     }
 
-    async fn check_revocation(&self, measurement: &[u8]) -> Result<RevocationStatus, AttestationError> {
+    async fn verify_quotes_batch(
+        &self,
+        quotes: &[(&[u8], Option<&[u8]>)],
+    ) -> Vec<Result<AttestationResult, AttestationError>> {
+        // One trust anchor snapshot and one set of lookups for the whole batch
+        let trust_anchors = self.anchors();
+        let mut cache = BatchCache::default();
+        let mut results = Vec::with_capacity(quotes.len());
+        for (quote, nonce) in quotes {
+            results.push(
+                self.verify_quote_internal(quote, *nonce, &trust_anchors, &mut cache)
+                    .await,
+            );
+        }
+        tracing::debug!(
+            "Verified batch of {} quotes ({} PCK chains, {} measurements)",
+            quotes.len(),
+            cache.pck_chains.len(),
+            cache.revocations.len()
+        );
+        results
+    }
+
+    async fn verify_with_collateral(
+        &self,
+        quote: &[u8],
+        nonce: Option<&[u8]>,
+        collateral: &CollateralBundle,
+    ) -> Result<AttestationResult, AttestationError> {
+        let pins = self.anchors().pins.clone();
+        let trust_anchors = TrustAnchors::from_collateral(collateral, pins, self.clock.now())?;
+        self.verify_quote_internal(quote, nonce, &trust_anchors, &mut BatchCache::default())
+            .await
+    }
+
+    async fn check_revocation(
+        &self,
+        measurement: &[u8],
+    ) -> Result<RevocationCheck, AttestationError> {
         // TODO: Check local revocation list (from smart contract or registry)
-        // For now, we only check CRLs for PCK certificates
+        // PCK certificates are checked against CRLs with their chain
 
-        tracing::debug!("Checking revocation for MRENCLAVE: {}", hex::encode(measurement));
+        tracing::debug!(
+            "Checking revocation for MRENCLAVE: {}",
+            hex::encode(measurement)
+        );
 
         // In production, query the smart contract for emergency revocations
-        // For now, return Ok if not in local blacklist
-        Ok(RevocationStatus::Ok)
+        // For now, no revocation source is consulted
+        Ok(RevocationCheck::unknown(RevocationSource::NotChecked))
     }
 
-    fn root_ca_certs(&self) -> &[String] {
-        // Return static root CA (in production, load from config)
-        static ROOT_CA: [String; 1] = [String::new()];
-        &ROOT_CA
+    async fn root_ca_certs(&self) -> Vec<String> {
+        self.anchors().root_ca_certs.clone()
     }
 
-    async fn update_trust_anchors(&mut self) -> Result<(), AttestationError> {
-        let mut anchors = self.trust_anchors.write().await;
+    async fn update_trust_anchors(&self) -> Result<(), AttestationError> {
+        self.reload_trust_anchors()
+            .await
+            .map_err(|e| AttestationError::Config(e.to_string()))?;
 
-        // Check if cache is still valid
-        let elapsed = Utc::now() - anchors.last_updated;
-        if elapsed.num_seconds() < self.config.cache_expiry_secs as i64 {
-            tracing::debug!("Trust anchors cache still valid");
-            return Ok(());
+        // Check if cache is still valid. Anchors never refreshed have no
+        // CRLs or QE Identity yet, so they are fetched right away.
+        let now = self.clock.now();
+        if let Some(last_updated) = self.anchors().last_updated {
+            if (now - last_updated).num_seconds() < self.config.cache_expiry_secs as i64 {
+                tracing::debug!("Trust anchors cache still valid");
+                return Ok(());
+            }
         }
 
         tracing::info!("Updating SGX trust anchors from Intel PCS");
-
-        // Fetch latest CRLs from Intel PCS
-        // In production: fetch from {pcs_url}/pckcrl?ca=processor&encoding=der
-        // For MVP, we skip this and rely on static root CA + manual CRL updates
-
-        anchors.last_updated = Utc::now();
-
+        self.refresh_pck_crls().await?;
+        self.refresh_qe_identity().await?;
+        self.update_anchors(|anchors| anchors.last_updated = Some(now));
         Ok(())
     }
 }
@@ -217,6 +929,7 @@ mod tests {
     async fn test_adapter_creation() {
         let adapter = SgxDcapAdapter::new();
         assert_eq!(adapter.vendor_name(), "intel-sgx");
+        assert_eq!(SgxDcapAdapter::tdx().vendor_name(), "intel-tdx");
     }
 
     #[tokio::test]
@@ -224,6 +937,445 @@ mod tests {
         let adapter = SgxDcapAdapter::new();
         let result = adapter.check_revocation(&[0u8; 32]).await;
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), RevocationStatus::Ok);
+        let check = result.unwrap();
+        assert_eq!(check.status, attestation_core::RevocationStatus::Unknown);
+        assert_eq!(check.source, RevocationSource::NotChecked);
+    }
+
+    #[tokio::test]
+    async fn test_trust_anchor_file_reload() {
+        let dir = std::env::temp_dir().join(format!("sgx-anchors-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("anchors.toml");
+
+        std::fs::write(
+            &path,
+            format!("[measurements]\nmr_enclave = [\"{}\"]\n", "01".repeat(32)),
+        )
+        .unwrap();
+        let adapter = SgxDcapAdapter::with_trust_anchor_file(SgxConfig::default(), &path).unwrap();
+        assert_eq!(
+            adapter.root_ca_certs().await,
+            vec![INTEL_SGX_ROOT_CA.to_string()]
+        );
+        assert_eq!(adapter.anchors().pins.mr_enclave, vec![[1u8; 32]]);
+
+        // Invalid edits are rejected and the previous anchors stay in effect
+        std::fs::write(&path, "[measurements]\nmr_enclave = [\"zz\"]\n").unwrap();
+        assert!(adapter.update_trust_anchors().await.is_err());
+        assert_eq!(adapter.anchors().pins.mr_enclave, vec![[1u8; 32]]);
+
+        std::fs::write(&path, "").unwrap();
+        adapter.reload_trust_anchors().await.unwrap();
+        assert!(adapter.anchors().pins.mr_enclave.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trust_anchor_cache_expiry_follows_clock() {
+        let start = chrono::DateTime::UNIX_EPOCH + chrono::Duration::days(20_000);
+        let clock = attestation_core::MockClock::new(start);
+        let adapter = SgxDcapAdapter::new().with_clock(Arc::new(clock.clone()));
+        assert_eq!(adapter.anchors().last_updated, None);
+
+        // The first refresh fetches whatever the clock says
+        adapter.update_trust_anchors().await.unwrap();
+        assert_eq!(adapter.anchors().last_updated, Some(start));
+
+        clock.advance(chrono::Duration::minutes(59));
+        adapter.update_trust_anchors().await.unwrap();
+        assert_eq!(
+            adapter.anchors().last_updated,
+            Some(start),
+            "cache still fresh"
+        );
+
+        clock.advance(chrono::Duration::minutes(2));
+        adapter.update_trust_anchors().await.unwrap();
+        assert_eq!(adapter.anchors().last_updated, Some(clock.now()));
+    }
+
+    #[tokio::test]
+    async fn test_batch_returns_per_quote_results_and_shares_lookups() {
+        let pki = pck::tests::Pki::new();
+        let valid = quote::tests::QuoteSigner::new().quote(&pki.chain(), &pki.pck_key());
+
+        let adapter = SgxDcapAdapter::new();
+        adapter.update_anchors(|anchors| anchors.root_ca_certs = vec![pki.root.pem()]);
+        let batch = [
+            (valid.as_slice(), None),
+            (&[0u8; 10][..], None),
+            (valid.as_slice(), None),
+        ];
+        let results = adapter.verify_quotes_batch(&batch).await;
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok() && results[2].is_ok());
+        assert!(matches!(results[1], Err(AttestationError::InvalidQuote(_))));
+
+        // The repeated measurement is checked for revocation once
+        let anchors = adapter.anchors();
+        let mut cache = BatchCache::default();
+        for _ in 0..2 {
+            adapter
+                .verify_quote_internal(&valid, None, &anchors, &mut cache)
+                .await
+                .unwrap();
+        }
+        assert_eq!(cache.revocations.len(), 1);
+        assert_eq!(cache.pck_chains.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_quote_without_pck_chain_rejected_unless_allowed() {
+        let signer = quote::tests::QuoteSigner::new();
+        let signature_data =
+            quote::tests::intel_qe_signature_data(&signer.public_key(), b"qe auth data");
+        let mut quote = quote::tests::quote_with_certification_data(&signature_data, 3, &[0u8; 16]);
+        signer.sign(&mut quote, &quote::tests::ecdsa_key());
+
+        let err = SgxDcapAdapter::new()
+            .verify_quote(&quote, None)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, AttestationError::VerificationFailed(ref reason) if reason.contains("PCK"))
+        );
+
+        // Signature data that ends before any certification data has no chain either
+        let mut bare = quote::tests::quote_with_certification_data(&signature_data, 3, &[]);
+        let signature_len = (bare.len() - 48 - 384 - 4 - 6) as u32;
+        bare[48 + 384..48 + 384 + 4].copy_from_slice(&signature_len.to_le_bytes());
+        bare.truncate(bare.len() - 6);
+        signer.sign(&mut bare, &quote::tests::ecdsa_key());
+        assert!(matches!(
+            SgxDcapAdapter::new().verify_quote(&bare, None).await,
+            Err(AttestationError::VerificationFailed(_))
+        ));
+
+        let adapter = SgxDcapAdapter::with_config(SgxConfig {
+            allow_missing_pck_chain: true,
+            ..SgxConfig::default()
+        });
+        let result = adapter.verify_quote(&quote, None).await.unwrap();
+        assert!(!result.quote_verified);
+        assert_eq!(result.pck_chain, None);
+    }
+
+    #[tokio::test]
+    async fn test_qe_report_checked_against_qe_identity() {
+        let pki = pck::tests::Pki::new();
+        let quote = quote::tests::QuoteSigner::new().quote(&pki.chain(), &pki.pck_key());
+
+        // The test QE Identity is due for update on 2025-02-01
+        let at = |day| chrono::DateTime::parse_from_rfc3339(day).unwrap().to_utc();
+        let clock = attestation_core::MockClock::new(at("2025-01-15T00:00:00Z"));
+        let adapter = SgxDcapAdapter::with_config(SgxConfig {
+            require_qe_identity: true,
+            ..SgxConfig::default()
+        })
+        .with_clock(Arc::new(clock.clone()));
+        adapter.update_anchors(|anchors| anchors.root_ca_certs = vec![pki.root.pem()]);
+        assert!(matches!(
+            adapter.verify_quote(&quote, None).await,
+            Err(AttestationError::VerificationFailed(_))
+        ));
+
+        // The test QE report is from ISVSVN 8, up to date
+        adapter.update_anchors(|anchors| anchors.qe_identity = Some(qe::tests::identity()));
+        let result = adapter.verify_quote(&quote, None).await.unwrap();
+        assert_eq!(
+            result.claim("sgx.qe_tcb_status"),
+            Some(&ClaimValue::Text("UpToDate".to_string()))
+        );
+
+        // A QE signed by anyone but Intel's QE key is refused
+        let mut other = qe::tests::identity();
+        other.mrsigner = "00".repeat(32);
+        adapter.update_anchors(|anchors| anchors.qe_identity = Some(other));
+        let err = adapter.verify_quote(&quote, None).await.unwrap_err();
+        assert!(
+            matches!(err, AttestationError::VerificationFailed(ref reason) if reason.contains("MRSIGNER"))
+        );
+
+        // Past its next update the identity no longer vouches for the QE,
+        // however recently the anchors holding it were loaded
+        adapter.update_anchors(|anchors| anchors.qe_identity = Some(qe::tests::identity()));
+        clock.advance(chrono::Duration::days(18));
+        let err = adapter.verify_quote(&quote, None).await.unwrap_err();
+        assert!(
+            matches!(err, AttestationError::VerificationFailed(ref reason) if reason.contains("expired"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_embedded_pck_chain_is_verified() {
+        let signature_data = quote::tests::bound_signature_data(&[7u8; 64], b"qe auth data");
+        let chain = "-----BEGIN CERTIFICATE-----\nAAEC\n-----END CERTIFICATE-----\n\
+                     -----BEGIN CERTIFICATE-----\nAwQF\n-----END CERTIFICATE-----\n";
+        let quote =
+            quote::tests::quote_with_certification_data(&signature_data, 5, chain.as_bytes());
+
+        // The embedded chain is checked, and is not a certificate chain
+        let err = SgxDcapAdapter::new()
+            .verify_quote(&quote, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AttestationError::VerificationFailed(_)));
+
+        // A genuine chain does not vouch for a QE report its key did not sign,
+        // whatever attestation key that report binds
+        let pki = pck::tests::Pki::new();
+        let adapter = SgxDcapAdapter::new();
+        adapter.update_anchors(|anchors| anchors.root_ca_certs = vec![pki.root.pem()]);
+        let forged =
+            quote::tests::QuoteSigner::new().quote(&pki.chain(), &quote::tests::ecdsa_key());
+        let err = adapter.verify_quote(&forged, None).await.unwrap_err();
+        assert!(
+            matches!(err, AttestationError::VerificationFailed(ref reason) if reason.contains("QE report"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tdx_quote_platform_verified() {
+        let pki = pck::tests::Pki::new();
+        let adapter = SgxDcapAdapter::tdx();
+        adapter.update_anchors(|anchors| anchors.root_ca_certs = vec![pki.root.pem()]);
+        let signer = quote::tests::QuoteSigner::new();
+        let quote = signer.tdx_quote(&pki.chain(), &pki.pck_key());
+        let result = adapter.verify_quote(&quote, None).await.unwrap();
+        assert!(result.quote_verified);
+        assert_eq!(result.pck_chain, Some(pki.chain()));
+
+        // A QE report the PCK key did not sign, a changed TD report, or no
+        // PCK chain at all are refused as for SGX quotes
+        let forged = signer.tdx_quote(&pki.chain(), &quote::tests::ecdsa_key());
+        let err = adapter.verify_quote(&forged, None).await.unwrap_err();
+        assert!(
+            matches!(err, AttestationError::VerificationFailed(ref reason) if reason.contains("QE report"))
+        );
+        let mut tampered = quote.clone();
+        tampered[48 + 136] ^= 1;
+        let err = adapter.verify_quote(&tampered, None).await.unwrap_err();
+        assert!(matches!(err, AttestationError::VerificationFailed(_)));
+        let signature_data =
+            quote::tests::intel_qe_signature_data(&signer.public_key(), b"qe auth data");
+        let unchained =
+            quote::tests::tdx_quote_with_certification_data(&signature_data, 3, &[0u8; 16]);
+        let err = adapter.verify_quote(&unchained, None).await.unwrap_err();
+        assert!(
+            matches!(err, AttestationError::VerificationFailed(ref reason) if reason.contains("PCK"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_old_quote_rejected_with_fresh_nonce() {
+        let pki = pck::tests::Pki::new();
+        let signer = quote::tests::QuoteSigner::new();
+        let nonces = Arc::new(attestation_core::NonceManager::new(
+            chrono::Duration::minutes(5),
+        ));
+        let mut registry =
+            attestation_core::AttestationRegistry::new().with_nonce_manager(nonces.clone());
+        for adapter in [SgxDcapAdapter::new(), SgxDcapAdapter::tdx()] {
+            adapter.update_anchors(|anchors| anchors.root_ca_certs = vec![pki.root.pem()]);
+            registry.register(Box::new(adapter));
+        }
+
+        // Report data ends in 64 bytes at the end of the report body
+        let answering = |nonce: &[u8], tdx: bool| {
+            let (mut quote, report_data_end) = if tdx {
+                (signer.tdx_quote(&pki.chain(), &pki.pck_key()), 48 + 584)
+            } else {
+                (signer.quote(&pki.chain(), &pki.pck_key()), 48 + 384)
+            };
+            quote[report_data_end - 32..report_data_end].copy_from_slice(&nonce_binding(nonce));
+            if tdx {
+                signer.sign_tdx(&mut quote, &pki.pck_key());
+            } else {
+                signer.sign(&mut quote, &pki.pck_key());
+            }
+            quote
+        };
+        for (vendor, tdx) in [("intel-sgx", false), ("intel-tdx", true)] {
+            let nonce = nonces.issue();
+            let quote = answering(&nonce, tdx);
+            registry
+                .verify_quote(vendor, &quote, Some(&nonce))
+                .await
+                .unwrap();
+
+            // The same quote does not answer a nonce issued after it
+            let fresh = nonces.issue();
+            let err = registry
+                .verify_quote(vendor, &quote, Some(&fresh))
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(err, AttestationError::VerificationFailed(ref reason) if reason.contains("nonce"))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pck_crls_fetched_and_enforced() {
+        let pki = pck::tests::Pki::new();
+        let quote = quote::tests::QuoteSigner::new().quote(&pki.chain(), &pki.pck_key());
+        // The test QE Identity is due for update on 2025-02-01
+        let clock = attestation_core::MockClock::new(
+            chrono::DateTime::parse_from_rfc3339("2025-01-15T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+        );
+        let adapter_revoking = |serial: u8| {
+            let ok = |body: Vec<u8>, headers: Vec<(String, String)>| dcap::HttpResponse {
+                status: 200,
+                headers,
+                body,
+            };
+            let signed = qe::tests::TcbSigner::new(&pki.root, &pki.root_key)
+                .sign(&serde_json::to_string(&qe::tests::identity()).unwrap());
+            let pcs = dcap::PcsClient::with_http_client(
+                "https://pcs.test".to_string(),
+                Arc::new(dcap::tests::FixedResponses(vec![
+                    (
+                        "https://pcs.test/pckcrl?ca=processor&encoding=der".to_string(),
+                        ok(pki.crl(serial), Vec::new()),
+                    ),
+                    (
+                        "https://pcs.test/pckcrl?ca=platform&encoding=der".to_string(),
+                        ok(pki.crl(0x07), Vec::new()),
+                    ),
+                    (
+                        "https://pcs.test/qe/identity".to_string(),
+                        ok(
+                            signed.body,
+                            vec![(
+                                "SGX-Enclave-Identity-Issuer-Chain".to_string(),
+                                signed.issuer_chain,
+                            )],
+                        ),
+                    ),
+                ])),
+            );
+            let adapter = SgxDcapAdapter::new()
+                .with_clock(Arc::new(clock.clone()))
+                .with_pcs_client(pcs);
+            adapter.update_anchors(|anchors| anchors.root_ca_certs = vec![pki.root.pem()]);
+            adapter
+        };
+
+        // Before the CRLs are fetched the chain is not checked for revocation
+        let adapter = adapter_revoking(0x42);
+        let result = adapter.verify_quote(&quote, None).await.unwrap();
+        assert_eq!(result.revoke_check.source, RevocationSource::NotChecked);
+
+        // A new adapter's first refresh fetches them, however recently it was built
+        adapter.update_trust_anchors().await.unwrap();
+        assert_eq!(adapter.anchors().crls.len(), 2);
+        assert!(adapter.anchors().qe_identity.is_some());
+        let err = adapter.verify_quote(&quote, None).await.unwrap_err();
+        assert!(matches!(err, AttestationError::MeasurementRevoked));
+
+        let adapter = adapter_revoking(0x07);
+        adapter.update_trust_anchors().await.unwrap();
+        let result = adapter.verify_quote(&quote, None).await.unwrap();
+        assert_eq!(result.revoke_check.source, RevocationSource::Crl);
+        assert!(result.revoke_check.crl_freshness.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_collateral_exported_and_loaded_offline() {
+        let pki = pck::tests::Pki::new();
+        let signed = qe::tests::TcbSigner::new(&pki.root, &pki.root_key)
+            .sign(&serde_json::to_string(&qe::tests::identity()).unwrap());
+        let ok = |body: Vec<u8>, headers: Vec<(String, String)>| dcap::HttpResponse {
+            status: 200,
+            headers,
+            body,
+        };
+        let pcs = dcap::PcsClient::with_http_client(
+            "https://pcs.test".to_string(),
+            Arc::new(dcap::tests::FixedResponses(vec![
+                (
+                    "https://pcs.test/pckcrl?ca=processor&encoding=der".to_string(),
+                    ok(pki.crl(0x42), Vec::new()),
+                ),
+                (
+                    "https://pcs.test/pckcrl?ca=platform&encoding=der".to_string(),
+                    ok(pki.crl(0x07), Vec::new()),
+                ),
+                (
+                    "https://pcs.test/qe/identity".to_string(),
+                    ok(
+                        signed.body,
+                        vec![(
+                            "SGX-Enclave-Identity-Issuer-Chain".to_string(),
+                            signed.issuer_chain,
+                        )],
+                    ),
+                ),
+                (
+                    "https://pcs.test/tcb?fmspc=00906EA10000".to_string(),
+                    ok(b"{}".to_vec(), Vec::new()),
+                ),
+            ])),
+        );
+        // The test CRLs are due for update on 2024-02-01
+        let at = |day| chrono::DateTime::parse_from_rfc3339(day).unwrap().to_utc();
+        let clock = attestation_core::MockClock::new(at("2024-01-15T00:00:00Z"));
+        let connected = SgxDcapAdapter::new()
+            .with_clock(Arc::new(clock.clone()))
+            .with_pcs_client(pcs);
+        connected.update_anchors(|anchors| anchors.root_ca_certs = vec![pki.root.pem()]);
+        let bundle = connected
+            .export_collateral(&["00906EA10000"])
+            .await
+            .unwrap();
+        assert_eq!(bundle.valid_until, at("2024-02-01T00:00:00Z"));
+        assert_eq!(bundle.crls.len(), 2);
+        assert!(bundle
+            .document(&documents::tcb_info_for("00906EA10000"))
+            .is_some());
+
+        // Carried to the air-gapped verifier as signed CBOR
+        let exporter = attestation_core::Signer::generate();
+        let bytes = bundle.sign(&exporter).unwrap().export().unwrap();
+        let bundle =
+            attestation_core::SignedCollateralBundle::import(&bytes, &exporter.verifying_key())
+                .unwrap();
+
+        let offline = SgxDcapAdapter::new().with_clock(Arc::new(clock.clone()));
+        offline.load_collateral(&bundle).unwrap();
+        assert!(offline.anchors().qe_identity.is_some());
+        let quote = quote::tests::QuoteSigner::new().quote(&pki.chain(), &pki.pck_key());
+        let err = offline.verify_quote(&quote, None).await.unwrap_err();
+        assert!(matches!(err, AttestationError::MeasurementRevoked));
+
+        // Bundles for another vendor or past their validity are refused
+        let tdx = SgxDcapAdapter::tdx().with_clock(Arc::new(clock.clone()));
+        assert!(matches!(
+            tdx.load_collateral(&bundle),
+            Err(AttestationError::Collateral(_))
+        ));
+        clock.advance(chrono::Duration::days(30));
+        assert!(matches!(
+            offline.load_collateral(&bundle),
+            Err(AttestationError::Collateral(_))
+        ));
+    }
+
+    #[test]
+    fn test_trust_anchors_from_collateral() {
+        let now = chrono::Utc::now();
+        let mut bundle = CollateralBundle::new("intel-sgx", now, now + chrono::Duration::days(1));
+        assert!(TrustAnchors::from_collateral(&bundle, Pins::default(), now).is_err());
+
+        bundle.certificates = vec![INTEL_SGX_ROOT_CA.to_string(), "intermediate".to_string()];
+        bundle.crls.push(vec![0x30]);
+        let anchors = TrustAnchors::from_collateral(&bundle, Pins::default(), now).unwrap();
+        assert_eq!(anchors.root_ca_certs, vec![INTEL_SGX_ROOT_CA.to_string()]);
+        assert_eq!(anchors.intermediate_certs.len(), 1);
+        assert_eq!(anchors.crls.len(), 1);
     }
 }
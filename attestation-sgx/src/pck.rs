@@ -1,9 +1,20 @@
 //! PCK (Provisioning Certification Key) certificate chain verification.
 
 use crate::TrustAnchors;
+use attestation_core::{AttestationError, ClaimValue, CrlFreshness, ErrorCode, ErrorCoded};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use der_parser::ber::BerObject;
+use std::collections::BTreeMap;
 use thiserror::Error;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::time::ASN1Time;
 
-#[derive(Debug, Error)]
+/// OID of the SGX extensions in a PCK certificate
+const SGX_EXTENSIONS_OID: &str = "1.2.840.113741.1.13.1";
+
+#[derive(Debug, Clone, Error)]
 pub enum PckError {
     #[error("Invalid certificate chain")]
     InvalidChain,
@@ -14,57 +25,293 @@ pub enum PckError {
     #[error("Certificate revoked")]
     Revoked,
 
+    #[error("Certificate chain does not end in a trusted root CA")]
+    UntrustedRoot,
+
+    #[error("No intermediate CA in the chain matches a configured pin")]
+    UnpinnedIntermediate,
+
+    #[error("CRL is not signed by the CA it names as issuer")]
+    InvalidCrl,
+
     #[error("Parse error: {0}")]
     ParseError(String),
 }
 
-/// Verify the PCK certificate chain against trust anchors.
+/// A revoked PCK chain revokes the platform's attestations; any other
+/// failure leaves the quote unverified.
+impl From<PckError> for AttestationError {
+    fn from(e: PckError) -> Self {
+        match e {
+            PckError::Revoked => AttestationError::MeasurementRevoked,
+            e => AttestationError::VerificationFailed(e.to_string()),
+        }
+    }
+}
+
+impl ErrorCoded for PckError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            PckError::Revoked => ErrorCode::CertificateRevoked,
+            PckError::InvalidChain
+            | PckError::Expired
+            | PckError::UntrustedRoot
+            | PckError::UnpinnedIntermediate
+            | PckError::InvalidCrl
+            | PckError::ParseError(_) => ErrorCode::VerificationFailed,
+        }
+    }
+}
+
+/// SGX extension fields of a verified PCK leaf certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PckExtensions {
+    /// Family-model-stepping-platform-custom SKU, the TCB info lookup key
+    pub fmspc: [u8; 6],
+    pub pce_id: [u8; 2],
+    /// SGX TCB component SVNs 1 to 16 the PCK certificate was issued for
+    pub tcb_components: [u8; 16],
+    /// PCE SVN the PCK certificate was issued for
+    pub pce_svn: u16,
+}
+
+impl PckExtensions {
+    /// The extension fields as vendor-namespaced claims for `AttestationResult`.
+    pub fn claims(&self) -> BTreeMap<String, ClaimValue> {
+        let mut claims = BTreeMap::new();
+        claims.insert(
+            "sgx.fmspc".to_string(),
+            ClaimValue::Bytes(self.fmspc.to_vec()),
+        );
+        claims.insert(
+            "sgx.pce_id".to_string(),
+            ClaimValue::Bytes(self.pce_id.to_vec()),
+        );
+        claims.insert(
+            "sgx.pck_tcb_components".to_string(),
+            ClaimValue::Bytes(self.tcb_components.to_vec()),
+        );
+        claims.insert(
+            "sgx.pck_pce_svn".to_string(),
+            ClaimValue::Uint(self.pce_svn as u64),
+        );
+        claims
+    }
+}
+
+/// A PCK certificate chain verified against the trust anchors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct VerifiedPck {
+    pub(crate) extensions: PckExtensions,
+    /// Public key of the PCK leaf certificate (an uncompressed P-256
+    /// point), which signs the platform's QE reports
+    pub(crate) public_key: Vec<u8>,
+    /// Validity window of the CRLs the chain was checked against, if any applied
+    pub(crate) crl_freshness: Option<CrlFreshness>,
+}
+
+/// Verify the PCK certificate chain against trust anchors at `now`.
 ///
 /// ## Verification Steps
-/// 1. Parse PCK leaf certificate
-/// 2. Verify chain: PCK -> Intermediate CA -> Root CA
-/// 3. Check certificate validity periods
-/// 4. Check CRLs for revoked certificates
-/// 5. Verify SGX extensions (FMSPC, TCB level, etc.)
-pub async fn verify_pck_chain(
+/// 1. Verify the chain as a whole (see [`verify_chain`])
+/// 2. Check an intermediate is pinned, if pins are configured
+/// 3. Parse the leaf's SGX extensions (OID 1.2.840.113741.1.13.1)
+///
+/// The chain only vouches for a quote once its leaf key has verified the
+/// quote's QE report (see [`crate::quote::verify_qe_report`]).
+pub(crate) async fn verify_pck_chain(
     pck_chain_pem: &str,
     trust_anchors: &TrustAnchors,
-) -> Result<(), PckError> {
+    now: DateTime<Utc>,
+) -> Result<VerifiedPck, PckError> {
     tracing::debug!("Verifying PCK certificate chain");
 
-    // Parse PEM certificates
-    let certs = parse_pem_chain(pck_chain_pem)?;
+    let ders = parse_pem_chain(pck_chain_pem)?;
+    let (certs, crl_freshness) = verify_chain(&ders, trust_anchors, now)?;
+    tracing::debug!("Verified {} certificates in PCK chain", certs.len());
 
-    if certs.is_empty() {
-        return Err(PckError::InvalidChain);
+    let intermediates = &ders[1..ders.len() - 1];
+    if !trust_anchors.pins.check_intermediates(intermediates) {
+        return Err(PckError::UnpinnedIntermediate);
     }
 
-    // For MVP: basic validation only
-    // In production:
-    // 1. Use x509-parser to parse each certificate
-    // 2. Verify signatures: cert[i].verify(cert[i+1].public_key)
-    // 3. Check validity: not_before <= now <= not_after
-    // 4. Check CRL: iterate trust_anchors.crls and check serial numbers
-    // 5. Verify SGX-specific extensions (OID 1.2.840.113741.1.13.1.*)
+    Ok(VerifiedPck {
+        extensions: sgx_extensions(&certs[0])?,
+        public_key: certs[0].public_key().subject_public_key.data.to_vec(),
+        crl_freshness,
+    })
+}
 
-    tracing::debug!("Parsed {} certificates in PCK chain", certs.len());
+/// Verify a DER certificate chain (leaf first, root last) at `now`, and
+/// return the parsed certificates with the validity window of the CRLs
+/// consulted (the latest issue and the earliest next update among them).
+///
+/// 1. Parse every certificate of the chain
+/// 2. Each certificate is issued and signed by the next, a CA
+/// 3. The root is a configured anchor
+/// 4. Every certificate is within its validity period
+/// 5. No certificate is revoked; a CRL applies to the certificates of the
+///    chain its issuer issued, and must carry that issuer's signature
+pub(crate) fn verify_chain<'a>(
+    ders: &'a [Vec<u8>],
+    trust_anchors: &TrustAnchors,
+    now: DateTime<Utc>,
+) -> Result<(Vec<X509Certificate<'a>>, Option<CrlFreshness>), PckError> {
+    let certs = ders
+        .iter()
+        .map(|der| {
+            x509_parser::parse_x509_certificate(der)
+                .map(|(_, cert)| cert)
+                .map_err(|e| PckError::ParseError(format!("X.509 certificate: {e}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
-    // Verify root CA matches
-    let root_cert_der = &certs[certs.len() - 1];
-    if !trust_anchors.root_ca_cert.contains("BEGIN CERTIFICATE") {
-        tracing::warn!("Trust anchor root CA is not in PEM format");
+    for pair in certs.windows(2) {
+        let (cert, issuer) = (&pair[0], &pair[1]);
+        if cert.issuer() != issuer.subject() || !issuer.is_ca() {
+            return Err(PckError::InvalidChain);
+        }
+        cert.verify_signature(Some(issuer.public_key()))
+            .map_err(|_| PckError::InvalidChain)?;
     }
 
-    // TODO: Implement proper X.509 chain verification
-    // For now, we assume the chain is valid if it can be parsed
+    let [_leaf, .., root] = ders else {
+        return Err(PckError::InvalidChain);
+    };
+    let mut trusted_roots = Vec::new();
+    for pem in &trust_anchors.root_ca_certs {
+        trusted_roots.extend(parse_pem_chain(pem)?);
+    }
+    if !trusted_roots.contains(root) {
+        return Err(PckError::UntrustedRoot);
+    }
 
-    tracing::warn!("PCK chain verification is incomplete (TODO: implement full X.509 validation)");
+    let at = ASN1Time::from_timestamp(now.timestamp())
+        .map_err(|e| PckError::ParseError(format!("verification time: {e}")))?;
+    if !certs.iter().all(|cert| cert.validity().is_valid_at(at)) {
+        return Err(PckError::Expired);
+    }
 
-    Ok(())
+    let mut crl_freshness: Option<CrlFreshness> = None;
+    for der in &trust_anchors.crls {
+        let (_, crl) = x509_parser::parse_x509_crl(der)
+            .map_err(|e| PckError::ParseError(format!("CRL: {e}")))?;
+        // CRLs of CAs outside this chain (e.g. the other PCK CA) do not apply
+        let Some(issuer) = certs.iter().find(|cert| cert.subject() == crl.issuer()) else {
+            continue;
+        };
+        crl.verify_signature(issuer.public_key())
+            .map_err(|_| PckError::InvalidCrl)?;
+        let revoked = certs
+            .iter()
+            .filter(|cert| cert.issuer() == crl.issuer())
+            .any(|cert| {
+                crl.iter_revoked_certificates()
+                    .any(|entry| entry.raw_serial() == cert.raw_serial())
+            });
+        if revoked {
+            return Err(PckError::Revoked);
+        }
+        // A CRL without a next update bounds nothing
+        if let Some(next_update) = crl.next_update() {
+            let time = |t: ASN1Time| DateTime::from_timestamp(t.timestamp(), 0).unwrap_or_default();
+            let window = CrlFreshness {
+                this_update: time(crl.last_update()),
+                next_update: time(next_update),
+            };
+            crl_freshness = Some(match crl_freshness {
+                Some(seen) => CrlFreshness {
+                    this_update: seen.this_update.max(window.this_update),
+                    next_update: seen.next_update.min(window.next_update),
+                },
+                None => window,
+            });
+        }
+    }
+
+    Ok((certs, crl_freshness))
+}
+
+/// Parse the SGX extensions of a PCK leaf certificate.
+///
+/// ```text
+/// SEQUENCE {
+///   SEQUENCE { OID .1, OCTET STRING ppid }
+///   SEQUENCE { OID .2, SEQUENCE { SEQUENCE { OID .2.1 .. .2.16, INTEGER svn }, .. (.2.17 pcesvn, .2.18 cpusvn) } }
+///   SEQUENCE { OID .3, OCTET STRING pce_id }
+///   SEQUENCE { OID .4, OCTET STRING fmspc }
+///   ..
+/// }
+/// ```
+fn sgx_extensions(leaf: &X509Certificate<'_>) -> Result<PckExtensions, PckError> {
+    let malformed = || PckError::ParseError("malformed SGX extensions".to_string());
+    let extension = leaf
+        .extensions()
+        .iter()
+        .find(|ext| ext.oid.to_id_string() == SGX_EXTENSIONS_OID)
+        .ok_or_else(|| PckError::ParseError("PCK certificate has no SGX extensions".to_string()))?;
+    let (_, fields) = der_parser::parse_der(extension.value).map_err(|_| malformed())?;
+
+    let (mut fmspc, mut pce_id, mut pce_svn) = (None, None, None);
+    let mut tcb_components = [0u8; 16];
+    for field in fields.as_sequence().map_err(|_| malformed())? {
+        let (arc, value) = sgx_field(field).ok_or_else(malformed)?;
+        match arc.as_str() {
+            ".2" => {
+                for component in value.as_sequence().map_err(|_| malformed())? {
+                    let (arc, svn) = sgx_field(component).ok_or_else(malformed)?;
+                    match arc
+                        .strip_prefix(".2.")
+                        .and_then(|n| n.parse::<usize>().ok())
+                    {
+                        Some(n @ 1..=16) => {
+                            tcb_components[n - 1] = svn
+                                .as_u32()
+                                .ok()
+                                .and_then(|svn| svn.try_into().ok())
+                                .ok_or_else(malformed)?
+                        }
+                        Some(17) => pce_svn = svn.as_u32().ok().and_then(|svn| svn.try_into().ok()),
+                        _ => {}
+                    }
+                }
+            }
+            ".3" => {
+                pce_id = value
+                    .as_slice()
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+            }
+            ".4" => {
+                fmspc = value
+                    .as_slice()
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+            }
+            _ => {}
+        }
+    }
+
+    Ok(PckExtensions {
+        fmspc: fmspc.ok_or_else(malformed)?,
+        pce_id: pce_id.ok_or_else(malformed)?,
+        tcb_components,
+        pce_svn: pce_svn.ok_or_else(malformed)?,
+    })
+}
+
+/// Split an SGX extension field, a SEQUENCE of its OID and value, into the
+/// OID's arc below [`SGX_EXTENSIONS_OID`] (e.g. ".4") and the value.
+fn sgx_field<'a, 'b>(field: &'b BerObject<'a>) -> Option<(String, &'b BerObject<'a>)> {
+    let [oid, value] = field.as_sequence().ok()?.as_slice() else {
+        return None;
+    };
+    let oid = oid.as_oid().ok()?.to_id_string();
+    Some((oid.strip_prefix(SGX_EXTENSIONS_OID)?.to_string(), value))
 }
 
 /// Parse a PEM-encoded certificate chain into DER bytes.
-fn parse_pem_chain(pem: &str) -> Result<Vec<Vec<u8>>, PckError> {
+pub(crate) fn parse_pem_chain(pem: &str) -> Result<Vec<Vec<u8>>, PckError> {
     let mut certs = Vec::new();
 
     for block in pem.split("-----END CERTIFICATE-----") {
@@ -72,7 +319,9 @@ fn parse_pem_chain(pem: &str) -> Result<Vec<Vec<u8>>, PckError> {
             continue;
         }
 
-        let cert_pem = block.split("-----BEGIN CERTIFICATE-----").nth(1)
+        let cert_pem = block
+            .split("-----BEGIN CERTIFICATE-----")
+            .nth(1)
             .ok_or_else(|| PckError::ParseError("Invalid PEM format".to_string()))?;
 
         // Decode base64
@@ -81,7 +330,8 @@ fn parse_pem_chain(pem: &str) -> Result<Vec<Vec<u8>>, PckError> {
             .filter(|c| !c.is_whitespace())
             .collect::<String>();
 
-        let decoded = base64::decode(&cert_der)
+        let decoded = STANDARD
+            .decode(&cert_der)
             .map_err(|e| PckError::ParseError(format!("Base64 decode error: {}", e)))?;
 
         certs.push(decoded);
@@ -90,12 +340,216 @@ fn parse_pem_chain(pem: &str) -> Result<Vec<Vec<u8>>, PckError> {
     Ok(certs)
 }
 
-// Add base64 dependency
-use base64;
-
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use rcgen::{
+        date_time_ymd, BasicConstraints, Certificate, CertificateParams,
+        CertificateRevocationListParams, CustomExtension, DnType, IsCa, KeyIdMethod, KeyPair,
+        RevokedCertParams, SerialNumber,
+    };
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair as _, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+    fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        match content.len() {
+            len @ 0..=127 => out.push(len as u8),
+            len => out.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]),
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    /// SEQUENCE { OID 1.2.840.113741.1.13.1.<arcs>, value }
+    fn sgx_field(arcs: &[u8], value: Vec<u8>) -> Vec<u8> {
+        let mut oid = vec![0x2A, 0x86, 0x48, 0x86, 0xF8, 0x4D, 0x01, 0x0D, 0x01];
+        oid.extend_from_slice(arcs);
+        tlv(0x30, &[tlv(0x06, &oid), value].concat())
+    }
+
+    fn sgx_extensions(fmspc: [u8; 6]) -> Vec<u8> {
+        let mut tcb: Vec<u8> = (1..=16u8)
+            .flat_map(|n| sgx_field(&[2, n], tlv(0x02, &[n])))
+            .collect();
+        tcb.extend(sgx_field(&[2, 17], tlv(0x02, &[11])));
+        tcb.extend(sgx_field(&[2, 18], tlv(0x04, &[0u8; 16])));
+        let fields = [
+            sgx_field(&[1], tlv(0x04, &[0xAB; 16])),
+            sgx_field(&[2], tlv(0x30, &tcb)),
+            sgx_field(&[3], tlv(0x04, &[0, 0])),
+            sgx_field(&[4], tlv(0x04, &fmspc)),
+        ];
+        tlv(0x30, &fields.concat())
+    }
+
+    fn params(name: &str, ca: bool) -> CertificateParams {
+        let mut params = CertificateParams::default();
+        params.distinguished_name.push(DnType::CommonName, name);
+        if ca {
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        }
+        params
+    }
+
+    /// Root, processor CA and PCK leaf (serial 0x42, valid until 2030).
+    pub(crate) struct Pki {
+        pub(crate) root: Certificate,
+        pub(crate) root_key: KeyPair,
+        ca: Certificate,
+        ca_key: KeyPair,
+        leaf: Certificate,
+        /// PKCS#8 of the leaf's key
+        pck_pkcs8: Vec<u8>,
+    }
+
+    impl Pki {
+        pub(crate) fn new() -> Self {
+            let root_key = KeyPair::generate().unwrap();
+            let root = params("Intel SGX Root CA", true)
+                .self_signed(&root_key)
+                .unwrap();
+            let ca_key = KeyPair::generate().unwrap();
+            let ca = params("Intel SGX PCK Processor CA", true)
+                .signed_by(&ca_key, &root, &root_key)
+                .unwrap();
+            let mut leaf = params("Intel SGX PCK Certificate", false);
+            leaf.serial_number = Some(SerialNumber::from(vec![0x42]));
+            leaf.not_after = date_time_ymd(2030, 1, 1);
+            leaf.custom_extensions
+                .push(CustomExtension::from_oid_content(
+                    &[1, 2, 840, 113741, 1, 13, 1],
+                    sgx_extensions([0x00, 0x90, 0x6E, 0xA1, 0x00, 0x00]),
+                ));
+            let pkcs8 = EcdsaKeyPair::generate_pkcs8(
+                &ECDSA_P256_SHA256_FIXED_SIGNING,
+                &SystemRandom::new(),
+            )
+            .unwrap();
+            let leaf = leaf
+                .signed_by(&KeyPair::try_from(pkcs8.as_ref()).unwrap(), &ca, &ca_key)
+                .unwrap();
+            Self {
+                root,
+                root_key,
+                ca,
+                ca_key,
+                leaf,
+                pck_pkcs8: pkcs8.as_ref().to_vec(),
+            }
+        }
+
+        /// The PCK leaf's key, which signs the platform's QE reports.
+        pub(crate) fn pck_key(&self) -> EcdsaKeyPair {
+            EcdsaKeyPair::from_pkcs8(
+                &ECDSA_P256_SHA256_FIXED_SIGNING,
+                &self.pck_pkcs8,
+                &SystemRandom::new(),
+            )
+            .unwrap()
+        }
+
+        pub(crate) fn chain(&self) -> String {
+            format!("{}{}{}", self.leaf.pem(), self.ca.pem(), self.root.pem())
+        }
+
+        /// CRL of the processor CA revoking `serial`, due for update in February 2024.
+        pub(crate) fn crl(&self, serial: u8) -> Vec<u8> {
+            CertificateRevocationListParams {
+                this_update: date_time_ymd(2024, 1, 1),
+                next_update: date_time_ymd(2024, 2, 1),
+                crl_number: SerialNumber::from(1),
+                issuing_distribution_point: None,
+                revoked_certs: vec![RevokedCertParams {
+                    serial_number: SerialNumber::from(vec![serial]),
+                    revocation_time: date_time_ymd(2024, 1, 1),
+                    reason_code: None,
+                    invalidity_date: None,
+                }],
+                key_identifier_method: KeyIdMethod::Sha256,
+            }
+            .signed_by(&self.ca, &self.ca_key)
+            .unwrap()
+            .der()
+            .to_vec()
+        }
+
+        fn anchors(&self) -> TrustAnchors {
+            TrustAnchors {
+                root_ca_certs: vec![self.root.pem()],
+                ..TrustAnchors::default()
+            }
+        }
+    }
+
+    fn at(year: i32) -> DateTime<Utc> {
+        DateTime::from_timestamp(date_time_ymd(year, 1, 1).unix_timestamp(), 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_chain_signatures_validity_and_extensions() {
+        let pki = Pki::new();
+        let verified = verify_pck_chain(&pki.chain(), &pki.anchors(), at(2025))
+            .await
+            .unwrap();
+        assert_eq!(verified.crl_freshness, None);
+        assert_eq!(verified.public_key, pki.pck_key().public_key().as_ref());
+        let extensions = verified.extensions;
+        assert_eq!(extensions.fmspc, [0x00, 0x90, 0x6E, 0xA1, 0x00, 0x00]);
+        assert_eq!(extensions.tcb_components[..3], [1, 2, 3]);
+        assert_eq!(extensions.pce_svn, 11);
+        assert_eq!(
+            extensions.claims().get("sgx.pck_pce_svn"),
+            Some(&ClaimValue::Uint(11))
+        );
+
+        let err = verify_pck_chain(&pki.chain(), &pki.anchors(), at(2031))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PckError::Expired));
+
+        // A CA with the right name but another key did not sign the leaf
+        let forged = Pki::new();
+        let chain = format!("{}{}{}", pki.leaf.pem(), forged.ca.pem(), forged.root.pem());
+        let err = verify_pck_chain(&chain, &forged.anchors(), at(2025))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PckError::InvalidChain));
+        let err = verify_pck_chain(&pki.chain(), &forged.anchors(), at(2025))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PckError::UntrustedRoot));
+    }
+
+    #[tokio::test]
+    async fn test_revoked_pck_certificate_rejected() {
+        let pki = Pki::new();
+        let mut anchors = pki.anchors();
+        anchors.crls.push(pki.crl(0x07));
+        let verified = verify_pck_chain(&pki.chain(), &anchors, at(2025))
+            .await
+            .unwrap();
+        assert_eq!(
+            verified.crl_freshness.unwrap().next_update,
+            at(2024) + chrono::Duration::days(31)
+        );
+
+        anchors.crls.push(pki.crl(0x42));
+        let err = verify_pck_chain(&pki.chain(), &anchors, at(2025))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PckError::Revoked));
+        assert_eq!(err.code(), ErrorCode::CertificateRevoked);
+
+        // A CRL naming the processor CA must carry its signature
+        let impostor = Pki::new();
+        let mut anchors = pki.anchors();
+        anchors.crls.push(impostor.crl(0x07));
+        let err = verify_pck_chain(&pki.chain(), &anchors, at(2025))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PckError::InvalidCrl));
+    }
 
     #[test]
     fn test_parse_pem_chain_empty() {
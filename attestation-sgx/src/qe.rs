@@ -0,0 +1,361 @@
+//! QE Identity verification.
+//!
+//! The QE report in a quote proves which enclave produced the attestation
+//! key, but only Intel's QE Identity says which enclave that should be:
+//! the Quoting Enclave's MRSIGNER, ISVPRODID, masked MISCSELECT and
+//! ATTRIBUTES, and the TCB status of each ISVSVN. PCS serves it signed by
+//! the TCB signing key, whose certificate chains to the SGX root.
+//!
+//! [`verify_qe_identity`] checks that signature when the identity is
+//! fetched; [`check_qe_report`] then holds each quote's verified QE report
+//! to it, for as long as the identity is not past its next update.
+
+use crate::dcap::{EnclaveIdentity, SignedEnclaveIdentity};
+use crate::pck::{parse_pem_chain, verify_chain, PckError};
+use crate::quote::QeReport;
+use crate::TrustAnchors;
+use attestation_core::{ErrorCode, ErrorCoded};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum QeIdentityError {
+    #[error("QE Identity signing chain: {0}")]
+    Chain(#[from] PckError),
+
+    #[error("QE Identity signature is invalid")]
+    InvalidSignature,
+
+    #[error("Malformed QE Identity: {0}")]
+    Malformed(String),
+
+    #[error("QE Identity expired at {0}")]
+    Expired(String),
+
+    #[error("QE report {0} does not match the QE Identity")]
+    Mismatch(&'static str),
+
+    #[error("QE ISVSVN {0} is below every TCB level of the QE Identity")]
+    UnknownTcb(u16),
+
+    #[error("QE ISVSVN {0} is at a revoked TCB level")]
+    Revoked(u16),
+}
+
+impl ErrorCoded for QeIdentityError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            QeIdentityError::Chain(e) => e.code(),
+            QeIdentityError::Malformed(_) => ErrorCode::CollateralUnavailable,
+            QeIdentityError::Revoked(_) => ErrorCode::CertificateRevoked,
+            QeIdentityError::InvalidSignature
+            | QeIdentityError::Expired(_)
+            | QeIdentityError::Mismatch(_)
+            | QeIdentityError::UnknownTcb(_) => ErrorCode::VerificationFailed,
+        }
+    }
+}
+
+/// PCS response body, keeping the identity JSON exactly as signed.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IdentityResponse<'a> {
+    #[serde(borrow)]
+    enclave_identity: &'a RawValue,
+    signature: String,
+}
+
+/// Verify a QE Identity fetched from PCS at `now`.
+///
+/// The issuer chain must verify against `trust_anchors` (and its CRLs),
+/// its leaf must have signed the identity JSON (ECDSA-p256, `r || s`), the
+/// identity must be the Quoting Enclave's, and it must not be past its
+/// next update.
+pub(crate) fn verify_qe_identity(
+    signed: &SignedEnclaveIdentity,
+    trust_anchors: &TrustAnchors,
+    now: DateTime<Utc>,
+) -> Result<EnclaveIdentity, QeIdentityError> {
+    let malformed = |e: &dyn std::fmt::Display| QeIdentityError::Malformed(e.to_string());
+    let ders = parse_pem_chain(&signed.issuer_chain)?;
+    let (certs, _) = verify_chain(&ders, trust_anchors, now)?;
+
+    let response: IdentityResponse<'_> =
+        serde_json::from_slice(&signed.body).map_err(|e| malformed(&e))?;
+    let signature = hex::decode(&response.signature).map_err(|e| malformed(&e))?;
+    let key = &certs[0].public_key().subject_public_key.data;
+    ring::signature::UnparsedPublicKey::new(&ring::signature::ECDSA_P256_SHA256_FIXED, key)
+        .verify(response.enclave_identity.get().as_bytes(), &signature)
+        .map_err(|_| QeIdentityError::InvalidSignature)?;
+
+    let identity: EnclaveIdentity =
+        serde_json::from_str(response.enclave_identity.get()).map_err(|e| malformed(&e))?;
+    if identity.id != "QE" {
+        return Err(QeIdentityError::Malformed(format!(
+            "identity is for {:?}, not the QE",
+            identity.id
+        )));
+    }
+    check_current(&identity, now)?;
+    Ok(identity)
+}
+
+/// Fail if `identity` is past its next update at `now`.
+fn check_current(identity: &EnclaveIdentity, now: DateTime<Utc>) -> Result<(), QeIdentityError> {
+    let next_update = DateTime::parse_from_rfc3339(&identity.next_update)
+        .map_err(|e| QeIdentityError::Malformed(e.to_string()))?;
+    if next_update < now {
+        return Err(QeIdentityError::Expired(identity.next_update.clone()));
+    }
+    Ok(())
+}
+
+/// Check a quote's verified QE report against a verified QE Identity at
+/// `now` and return the TCB status of its ISVSVN (e.g. "UpToDate",
+/// "OutOfDate").
+///
+/// The identity was current when fetched, but a verifier can outlive its
+/// next update between refreshes, so it is checked again here.
+pub fn check_qe_report(
+    identity: &EnclaveIdentity,
+    report: &QeReport,
+    now: DateTime<Utc>,
+) -> Result<String, QeIdentityError> {
+    check_current(identity, now)?;
+    if decode(&identity.mrsigner)? != report.mr_signer {
+        return Err(QeIdentityError::Mismatch("MRSIGNER"));
+    }
+    if identity.isvprodid != report.isv_prod_id {
+        return Err(QeIdentityError::Mismatch("ISVPRODID"));
+    }
+    if !masked_eq(
+        &identity.miscselect,
+        &identity.miscselect_mask,
+        &report.misc_select.to_le_bytes(),
+    )? {
+        return Err(QeIdentityError::Mismatch("MISCSELECT"));
+    }
+    if !masked_eq(
+        &identity.attributes,
+        &identity.attributes_mask,
+        &report.attributes,
+    )? {
+        return Err(QeIdentityError::Mismatch("ATTRIBUTES"));
+    }
+
+    let level = identity
+        .tcb_level(report.isv_svn)
+        .ok_or(QeIdentityError::UnknownTcb(report.isv_svn))?;
+    if level.tcb_status == "Revoked" {
+        return Err(QeIdentityError::Revoked(report.isv_svn));
+    }
+    Ok(level.tcb_status.clone())
+}
+
+fn decode(field: &str) -> Result<Vec<u8>, QeIdentityError> {
+    hex::decode(field).map_err(|e| QeIdentityError::Malformed(e.to_string()))
+}
+
+/// Whether `actual` under `mask` equals `expected` under `mask` (hex).
+fn masked_eq(expected: &str, mask: &str, actual: &[u8]) -> Result<bool, QeIdentityError> {
+    let (expected, mask) = (decode(expected)?, decode(mask)?);
+    if expected.len() != actual.len() || mask.len() != actual.len() {
+        return Err(QeIdentityError::Malformed(
+            "mask length differs from the report field".to_string(),
+        ));
+    }
+    Ok(expected
+        .iter()
+        .zip(&mask)
+        .zip(actual)
+        .all(|((e, m), a)| e & m == a & m))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::dcap::{EnclaveTcb, EnclaveTcbLevel};
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa, KeyPair};
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+    /// TCB signing key, certified by a test root.
+    pub(crate) struct TcbSigner {
+        rng: SystemRandom,
+        key: EcdsaKeyPair,
+        chain: String,
+    }
+
+    impl TcbSigner {
+        pub(crate) fn new(root: &Certificate, root_key: &KeyPair) -> Self {
+            let rng = SystemRandom::new();
+            let pkcs8 =
+                EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+            let key =
+                EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+                    .unwrap();
+            let mut leaf = CertificateParams::default();
+            leaf.distinguished_name
+                .push(DnType::CommonName, "Intel SGX TCB Signing");
+            let leaf = leaf
+                .signed_by(&KeyPair::try_from(pkcs8.as_ref()).unwrap(), root, root_key)
+                .unwrap();
+            let chain = format!("{}{}", leaf.pem(), root.pem());
+            Self { rng, key, chain }
+        }
+
+        /// A PCS response carrying `json` as the identity.
+        pub(crate) fn sign(&self, json: &str) -> SignedEnclaveIdentity {
+            let signature = self.key.sign(&self.rng, json.as_bytes()).unwrap();
+            let body = format!(
+                r#"{{"enclaveIdentity":{json},"signature":"{}"}}"#,
+                hex::encode(signature)
+            );
+            SignedEnclaveIdentity {
+                body: body.into_bytes(),
+                issuer_chain: self.chain.clone(),
+            }
+        }
+    }
+
+    /// QE Identity with next update on 2025-02-01.
+    pub(crate) fn identity() -> EnclaveIdentity {
+        let level = |isvsvn, status: &str| EnclaveTcbLevel {
+            tcb: EnclaveTcb { isvsvn },
+            tcb_date: "2024-01-01T00:00:00Z".to_string(),
+            tcb_status: status.to_string(),
+        };
+        EnclaveIdentity {
+            id: "QE".to_string(),
+            version: 2,
+            issue_date: "2025-01-01T00:00:00Z".to_string(),
+            next_update: "2025-02-01T00:00:00Z".to_string(),
+            tcb_evaluation_data_number: 17,
+            miscselect: "00000000".to_string(),
+            miscselect_mask: "FFFFFFFF".to_string(),
+            attributes: "11000000000000000000000000000000".to_string(),
+            attributes_mask: "FBFFFFFFFFFFFFFF0000000000000000".to_string(),
+            mrsigner: "8C4F5775D796503E96137F77C68A829A0056AC8DED70140B081B094490C57BFF"
+                .to_string(),
+            isvprodid: 1,
+            tcb_levels: vec![
+                level(8, "UpToDate"),
+                level(6, "OutOfDate"),
+                level(2, "Revoked"),
+            ],
+        }
+    }
+
+    fn report(isv_svn: u16) -> QeReport {
+        let mut attributes = [0u8; 16];
+        attributes[0] = 0x15; // debug bit (0x04) is masked out
+        QeReport {
+            misc_select: 0,
+            attributes,
+            mr_signer: hex::decode(identity().mrsigner)
+                .unwrap()
+                .try_into()
+                .unwrap(),
+            isv_prod_id: 1,
+            isv_svn,
+        }
+    }
+
+    #[test]
+    fn test_qe_report_checked_against_identity() {
+        let identity = identity();
+        let now = DateTime::parse_from_rfc3339("2025-01-15T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        let check = |report: &QeReport| check_qe_report(&identity, report, now);
+        assert_eq!(check(&report(9)).unwrap(), "UpToDate");
+        assert_eq!(check(&report(7)).unwrap(), "OutOfDate");
+        assert!(matches!(
+            check(&report(3)),
+            Err(QeIdentityError::Revoked(3))
+        ));
+        assert!(matches!(
+            check(&report(1)),
+            Err(QeIdentityError::UnknownTcb(1))
+        ));
+
+        let mut other = report(9);
+        other.mr_signer[0] ^= 1;
+        assert!(matches!(
+            check(&other),
+            Err(QeIdentityError::Mismatch("MRSIGNER"))
+        ));
+        let mut other = report(9);
+        other.isv_prod_id = 2;
+        assert!(matches!(
+            check(&other),
+            Err(QeIdentityError::Mismatch("ISVPRODID"))
+        ));
+        let mut other = report(9);
+        other.attributes[0] = 0x01;
+        assert!(matches!(
+            check(&other),
+            Err(QeIdentityError::Mismatch("ATTRIBUTES"))
+        ));
+
+        // Past its next update the identity no longer vouches for any report
+        let later = DateTime::parse_from_rfc3339("2025-02-02T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        let err = check_qe_report(&identity, &report(9), later).unwrap_err();
+        assert!(matches!(err, QeIdentityError::Expired(_)));
+        assert_eq!(err.code(), ErrorCode::VerificationFailed);
+    }
+
+    #[test]
+    fn test_identity_signature_verified_against_tcb_signing_chain() {
+        let root_key = KeyPair::generate().unwrap();
+        let mut root = CertificateParams::default();
+        root.distinguished_name
+            .push(DnType::CommonName, "Intel SGX Root CA");
+        root.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let root = root.self_signed(&root_key).unwrap();
+        let signer = TcbSigner::new(&root, &root_key);
+        let anchors = TrustAnchors {
+            root_ca_certs: vec![root.pem()],
+            ..TrustAnchors::default()
+        };
+
+        let json = serde_json::to_string(&identity()).unwrap();
+        let sign = |json: &str| signer.sign(json);
+        let now = DateTime::parse_from_rfc3339("2025-01-15T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        assert_eq!(
+            verify_qe_identity(&sign(&json), &anchors, now).unwrap(),
+            identity()
+        );
+
+        // Any change to the signed JSON breaks the signature
+        let mut tampered = sign(&json);
+        tampered.body = String::from_utf8(tampered.body)
+            .unwrap()
+            .replace("\"isvprodid\":1", "\"isvprodid\":2")
+            .into_bytes();
+        assert!(matches!(
+            verify_qe_identity(&tampered, &anchors, now),
+            Err(QeIdentityError::InvalidSignature)
+        ));
+
+        let later = DateTime::parse_from_rfc3339("2025-03-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        assert!(matches!(
+            verify_qe_identity(&sign(&json), &anchors, later),
+            Err(QeIdentityError::Expired(_))
+        ));
+        let other_root = TrustAnchors::default();
+        let err = verify_qe_identity(&sign(&json), &other_root, now).unwrap_err();
+        assert!(matches!(
+            err,
+            QeIdentityError::Chain(PckError::UntrustedRoot)
+        ));
+    }
+}
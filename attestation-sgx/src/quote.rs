@@ -1,5 +1,8 @@
 //! SGX quote parsing and signature verification.
 
+use attestation_core::{ClaimValue, ErrorCode, ErrorCoded};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -13,10 +16,114 @@ pub enum QuoteError {
     #[error("Invalid signature")]
     InvalidSignature,
 
+    #[error("QE report does not bind the attestation key")]
+    QeReportBinding,
+
+    #[error("QE report is not signed by the PCK certificate")]
+    QeReportSignature,
+
     #[error("Parse error: {0}")]
     ParseError(String),
+
+    #[error("Quote {field} of {actual} bytes exceeds the limit of {limit}")]
+    LimitExceeded {
+        field: &'static str,
+        limit: usize,
+        actual: usize,
+    },
+}
+
+impl ErrorCoded for QuoteError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            QuoteError::InvalidSignature
+            | QuoteError::QeReportBinding
+            | QuoteError::QeReportSignature => ErrorCode::VerificationFailed,
+            QuoteError::InvalidLength { .. }
+            | QuoteError::UnsupportedVersion(_)
+            | QuoteError::ParseError(_)
+            | QuoteError::LimitExceeded { .. } => ErrorCode::InvalidQuote,
+        }
+    }
+}
+
+/// Size limits applied to untrusted quotes before anything is copied out.
+///
+/// Length fields inside a quote are attacker-controlled; every one is
+/// checked against these limits (and the actual input length) first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuoteLimits {
+    /// Whole quote, header to end of signature data
+    pub max_quote_size: usize,
+    /// `signature_len` (signature data including certification data)
+    pub max_signature_len: usize,
+    /// Certification data (PCK certificate chain) declared in the signature data
+    pub max_certification_data_size: usize,
+}
+
+impl Default for QuoteLimits {
+    fn default() -> Self {
+        Self {
+            max_quote_size: 64 * 1024,
+            max_signature_len: 32 * 1024,
+            max_certification_data_size: 16 * 1024,
+        }
+    }
 }
 
+impl QuoteLimits {
+    fn check(&self, field: &'static str, limit: usize, actual: usize) -> Result<(), QuoteError> {
+        if actual > limit {
+            return Err(QuoteError::LimitExceeded {
+                field,
+                limit,
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Offset of the QE authentication data length within the signature data:
+/// ECDSA signature (64), attestation key (64), QE report (384), QE report signature (64)
+const QE_AUTH_DATA_OFFSET: usize = 64 + 64 + 384 + 64;
+
+/// Size of the quote header
+const HEADER_SIZE: usize = 48;
+
+/// Size of an SGX report body (REPORTBODY): the enclave's report in a v3
+/// quote, and the QE report in the signature data
+const REPORT_BODY_SIZE: usize = 384;
+
+/// Offset of `report_data` within a report body
+const REPORT_DATA_OFFSET: usize = REPORT_BODY_SIZE - 64;
+
+/// Offsets of the attestation key and QE report within the signature data
+const ATTESTATION_KEY_OFFSET: usize = 64;
+const QE_REPORT_OFFSET: usize = 64 + 64;
+const QE_REPORT_SIZE: usize = REPORT_BODY_SIZE;
+
+/// Certification data type of a PEM PCK certificate chain
+const CERT_DATA_PCK_CHAIN: u16 = 5;
+
+/// Certification data type of QE report certification data, which wraps
+/// the QE report, its signature and authentication data, and the PCK
+/// certification data
+const CERT_DATA_QE_REPORT: u16 = 6;
+
+/// TEE type of TDX quotes in the v4 header
+pub const TEE_TYPE_TDX: u32 = 0x81;
+
+/// Size of the TD report body (TDREPORT, TDX 1.0) in a v4 quote
+const TD_REPORT_SIZE: usize = 584;
+
+/// Offset of the QE report in v4 signature data: ECDSA signature (64),
+/// attestation key (64), certification data type (2) and size (4)
+const V4_QE_REPORT_OFFSET: usize = 64 + 64 + 2 + 4;
+
+/// Offset of the QE authentication data in v4 signature data
+const V4_QE_AUTH_DATA_OFFSET: usize = V4_QE_REPORT_OFFSET + QE_REPORT_SIZE + 64;
+
 /// SGX Quote v3 structure (ECDSA-p256 attestation).
 #[derive(Debug, Clone)]
 pub struct SgxQuoteV3 {
@@ -30,14 +137,62 @@ pub struct SgxQuoteV3 {
     pub isv_svn: u16,
     pub report_data: [u8; 64],
     pub debug_mode: bool,
+    /// Quote header and report body, the bytes the attestation key signs
+    pub signed_data: Vec<u8>,
     pub signature: Vec<u8>,
+    /// PEM PCK certificate chain (certification data type 5), if embedded
     pub certification_data: Option<String>,
 }
 
+impl SgxQuoteV3 {
+    /// Extract the quote fields as vendor-namespaced claims for `AttestationResult`.
+    pub fn claims(&self) -> BTreeMap<String, ClaimValue> {
+        let mut claims = BTreeMap::new();
+        claims.insert(
+            "sgx.quote_version".to_string(),
+            ClaimValue::Uint(self.version as u64),
+        );
+        claims.insert(
+            "sgx.attestation_key_type".to_string(),
+            ClaimValue::Uint(self.attestation_key_type as u64),
+        );
+        claims.insert(
+            "sgx.qe_svn".to_string(),
+            ClaimValue::Uint(self.qe_svn as u64),
+        );
+        claims.insert(
+            "sgx.pce_svn".to_string(),
+            ClaimValue::Uint(self.pce_svn as u64),
+        );
+        claims.insert(
+            "sgx.mr_enclave".to_string(),
+            ClaimValue::Bytes(self.mr_enclave.to_vec()),
+        );
+        claims.insert(
+            "sgx.mr_signer".to_string(),
+            ClaimValue::Bytes(self.mr_signer.to_vec()),
+        );
+        claims.insert(
+            "sgx.isv_prod_id".to_string(),
+            ClaimValue::Uint(self.isv_prod_id as u64),
+        );
+        claims.insert(
+            "sgx.isv_svn".to_string(),
+            ClaimValue::Uint(self.isv_svn as u64),
+        );
+        claims.insert(
+            "sgx.report_data".to_string(),
+            ClaimValue::Bytes(self.report_data.to_vec()),
+        );
+        claims.insert("sgx.debug".to_string(), ClaimValue::Bool(self.debug_mode));
+        claims
+    }
+}
+
 /// Parse an SGX quote v3 (ECDSA-p256).
 ///
 /// ## Quote Structure (simplified)
-/// ```
+/// ```text
 /// u16 version (= 3)
 /// u16 attestation_key_type (= 2 for ECDSA-p256)
 /// u32 tee_type (= 0 for SGX)
@@ -45,24 +200,45 @@ pub struct SgxQuoteV3 {
 /// u16 pce_svn
 /// [16] uuid
 /// [20] user_data
-/// [432] report_body
+/// [384] report_body
 ///   [16] cpu_svn
 ///   [4] misc_select
-///   [28] reserved
+///   [12] reserved
 ///   [16] isv_ext_prod_id
-///   [64] attributes
+///   [16] attributes (flags, then xfrm)
 ///   [32] mr_enclave
 ///   [32] reserved
 ///   [32] mr_signer
-///   [96] reserved
+///   [32] reserved
+///   [64] config_id
 ///   [2] isv_prod_id
 ///   [2] isv_svn
-///   [60] reserved
+///   [2] config_svn
+///   [42] reserved
+///   [16] isv_family_id
 ///   [64] report_data
 /// [4] signature_len
-/// [signature_len] signature + certification_data
+/// [signature_len] signature data
+///   [64] signature, [64] attestation_key,
+///   [384] QE report, [64] QE report signature,
+///   [2] auth_data_len, [auth_data_len] auth_data,
+///   [2] certification data type, [4] size, [size] certification data
 /// ```
+///
+/// Certification data of type 5 (the PEM PCK certificate chain, PCK leaf
+/// first) is returned in `certification_data`; other types carry no chain.
+///
+/// Applies the default [`QuoteLimits`].
 pub fn parse_sgx_quote_v3(quote: &[u8]) -> Result<SgxQuoteV3, QuoteError> {
+    parse_sgx_quote_v3_with_limits(quote, &QuoteLimits::default())
+}
+
+/// Parse an SGX quote v3, rejecting it if any size exceeds `limits`.
+pub fn parse_sgx_quote_v3_with_limits(
+    quote: &[u8],
+    limits: &QuoteLimits,
+) -> Result<SgxQuoteV3, QuoteError> {
+    limits.check("size", limits.max_quote_size, quote.len())?;
     if quote.len() < 48 {
         return Err(QuoteError::InvalidLength {
             expected: 48,
@@ -84,64 +260,30 @@ pub fn parse_sgx_quote_v3(quote: &[u8]) -> Result<SgxQuoteV3, QuoteError> {
     // Skip uuid (16 bytes) and user_data (20 bytes)
     // Report body starts at offset 48
 
-    if quote.len() < 48 + 432 {
+    if quote.len() < HEADER_SIZE + REPORT_BODY_SIZE {
         return Err(QuoteError::InvalidLength {
-            expected: 48 + 432,
+            expected: HEADER_SIZE + REPORT_BODY_SIZE,
             actual: quote.len(),
         });
     }
 
-    let report_body = &quote[48..48 + 432];
-
-    // Parse report_body
-    // cpu_svn: 0-15 (skip)
-    // misc_select: 16-19 (skip)
-    // reserved: 20-47 (skip)
-    // isv_ext_prod_id: 48-63 (skip)
-    // attributes at offset 48+64 = 112
-    let attributes_offset = 48 + 64;
-    let attributes = u64::from_le_bytes([
-        report_body[attributes_offset],
-        report_body[attributes_offset + 1],
-        report_body[attributes_offset + 2],
-        report_body[attributes_offset + 3],
-        report_body[attributes_offset + 4],
-        report_body[attributes_offset + 5],
-        report_body[attributes_offset + 6],
-        report_body[attributes_offset + 7],
-    ]);
-
-    // Debug mode = bit 1 of attributes
-    let debug_mode = (attributes & 0x02) != 0;
-
-    // mr_enclave at offset 48+64+64 = 176
-    let mr_enclave_offset = 48 + 64 + 64;
-    let mut mr_enclave = [0u8; 32];
-    mr_enclave.copy_from_slice(&report_body[mr_enclave_offset..mr_enclave_offset + 32]);
-
-    // mr_signer at offset 48+64+64+32+32 = 240
-    let mr_signer_offset = 48 + 64 + 64 + 32 + 32;
-    let mut mr_signer = [0u8; 32];
-    mr_signer.copy_from_slice(&report_body[mr_signer_offset..mr_signer_offset + 32]);
-
-    // isv_prod_id at offset 48+64+64+32+32+32+96 = 368
-    let isv_prod_id_offset = 48 + 64 + 64 + 32 + 32 + 32 + 96;
-    let isv_prod_id = u16::from_le_bytes([
-        report_body[isv_prod_id_offset],
-        report_body[isv_prod_id_offset + 1],
-    ]);
+    // The report body has the layout of the QE report (see `parse_qe_report`)
+    let report_body = &quote[HEADER_SIZE..HEADER_SIZE + REPORT_BODY_SIZE];
 
-    // isv_svn at offset 370
-    let isv_svn = u16::from_le_bytes([report_body[370], report_body[371]]);
+    // Debug mode = bit 1 of the attribute flags (attributes @48)
+    let debug_mode = (report_body[48] & 0x02) != 0;
 
-    // report_data at offset 48+64+64+32+32+32+96+2+2+60 = 432 - 64 = 368 (wait, recalculate)
-    // Actually: report_data is at the end of report_body (last 64 bytes)
-    let report_data_offset = 432 - 64;
+    let mut mr_enclave = [0u8; 32];
+    mr_enclave.copy_from_slice(&report_body[64..96]);
+    let mut mr_signer = [0u8; 32];
+    mr_signer.copy_from_slice(&report_body[128..160]);
+    let isv_prod_id = u16::from_le_bytes([report_body[256], report_body[257]]);
+    let isv_svn = u16::from_le_bytes([report_body[258], report_body[259]]);
     let mut report_data = [0u8; 64];
-    report_data.copy_from_slice(&report_body[report_data_offset..report_data_offset + 64]);
+    report_data.copy_from_slice(&report_body[REPORT_DATA_OFFSET..]);
 
     // Signature data starts after report_body
-    let sig_offset = 48 + 432;
+    let sig_offset = HEADER_SIZE + REPORT_BODY_SIZE;
     if quote.len() < sig_offset + 4 {
         return Err(QuoteError::InvalidLength {
             expected: sig_offset + 4,
@@ -155,19 +297,19 @@ pub fn parse_sgx_quote_v3(quote: &[u8]) -> Result<SgxQuoteV3, QuoteError> {
         quote[sig_offset + 2],
         quote[sig_offset + 3],
     ]) as usize;
+    limits.check("signature_len", limits.max_signature_len, signature_len)?;
 
-    if quote.len() < sig_offset + 4 + signature_len {
+    let sig_end = sig_offset + 4 + signature_len;
+    if quote.len() < sig_end {
         return Err(QuoteError::InvalidLength {
-            expected: sig_offset + 4 + signature_len,
+            expected: sig_end,
             actual: quote.len(),
         });
     }
+    let signature_data = &quote[sig_offset + 4..sig_end];
 
-    let signature = quote[sig_offset + 4..sig_offset + 4 + signature_len].to_vec();
-
-    // Certification data (PCK chain) is embedded in signature structure
-    // For simplicity, we store the entire signature blob
-    // In production, parse the QE Auth Data and extract PCK chain properly
+    let certification_data = parse_certification_data(signature_data, QE_AUTH_DATA_OFFSET, limits)?;
+    let signature = signature_data.to_vec();
 
     Ok(SgxQuoteV3 {
         version,
@@ -180,40 +322,553 @@ pub fn parse_sgx_quote_v3(quote: &[u8]) -> Result<SgxQuoteV3, QuoteError> {
         isv_svn,
         report_data,
         debug_mode,
+        signed_data: quote[..sig_offset].to_vec(),
         signature,
-        certification_data: None, // TODO: Parse PCK chain from signature data
+        certification_data,
     })
 }
 
-/// Verify the ECDSA-p256 signature on an SGX quote.
+/// Intel TDX quote v4 structure (ECDSA-p256 attestation).
+#[derive(Debug, Clone)]
+pub struct TdxQuoteV4 {
+    pub version: u16,
+    pub attestation_key_type: u16,
+    pub tee_tcb_svn: [u8; 16],
+    /// Measurement of the TDX module
+    pub mr_seam: [u8; 48],
+    /// Measurement of the initial TD contents
+    pub mr_td: [u8; 48],
+    pub mr_config_id: [u8; 48],
+    pub mr_owner: [u8; 48],
+    pub mr_owner_config: [u8; 48],
+    /// Runtime measurement registers 0 to 3
+    pub rtmrs: [[u8; 48]; 4],
+    pub td_attributes: u64,
+    pub xfam: u64,
+    pub report_data: [u8; 64],
+    pub debug_mode: bool,
+    /// Quote header and TD report body, the bytes the attestation key signs
+    pub signed_data: Vec<u8>,
+    pub signature: Vec<u8>,
+    /// PEM PCK certificate chain inside the QE report certification data
+    /// (type 6 wrapping type 5), if embedded
+    pub certification_data: Option<String>,
+}
+
+impl TdxQuoteV4 {
+    /// Extract the quote fields as vendor-namespaced claims for `AttestationResult`.
+    pub fn claims(&self) -> BTreeMap<String, ClaimValue> {
+        let mut claims = BTreeMap::new();
+        claims.insert(
+            "tdx.quote_version".to_string(),
+            ClaimValue::Uint(self.version as u64),
+        );
+        claims.insert(
+            "tdx.attestation_key_type".to_string(),
+            ClaimValue::Uint(self.attestation_key_type as u64),
+        );
+        claims.insert(
+            "tdx.tee_tcb_svn".to_string(),
+            ClaimValue::Bytes(self.tee_tcb_svn.to_vec()),
+        );
+        claims.insert(
+            "tdx.mr_seam".to_string(),
+            ClaimValue::Bytes(self.mr_seam.to_vec()),
+        );
+        claims.insert(
+            "tdx.mr_td".to_string(),
+            ClaimValue::Bytes(self.mr_td.to_vec()),
+        );
+        claims.insert(
+            "tdx.mr_config_id".to_string(),
+            ClaimValue::Bytes(self.mr_config_id.to_vec()),
+        );
+        claims.insert(
+            "tdx.mr_owner".to_string(),
+            ClaimValue::Bytes(self.mr_owner.to_vec()),
+        );
+        claims.insert(
+            "tdx.mr_owner_config".to_string(),
+            ClaimValue::Bytes(self.mr_owner_config.to_vec()),
+        );
+        for (index, rtmr) in self.rtmrs.iter().enumerate() {
+            claims.insert(
+                format!("tdx.rtmr.{index}"),
+                ClaimValue::Bytes(rtmr.to_vec()),
+            );
+        }
+        claims.insert(
+            "tdx.td_attributes".to_string(),
+            ClaimValue::Uint(self.td_attributes),
+        );
+        claims.insert("tdx.xfam".to_string(), ClaimValue::Uint(self.xfam));
+        claims.insert(
+            "tdx.report_data".to_string(),
+            ClaimValue::Bytes(self.report_data.to_vec()),
+        );
+        claims.insert("tdx.debug".to_string(), ClaimValue::Bool(self.debug_mode));
+        claims
+    }
+}
+
+/// Parse an Intel TDX quote v4 (ECDSA-p256).
 ///
-/// This is a simplified implementation. In production, use a proper ECDSA library
-/// and verify against the QE (Quoting Enclave) public key from the PCK chain.
-pub fn verify_quote_signature(quote: &SgxQuoteV3) -> Result<(), QuoteError> {
-    // TODO: Implement full ECDSA-p256 verification
-    // 1. Extract QE public key from PCK chain
-    // 2. Reconstruct signed data (quote header + report_body)
-    // 3. Verify ECDSA signature
+/// ## Quote Structure
+/// ```text
+/// u16 version (= 4)
+/// u16 attestation_key_type (= 2 for ECDSA-p256)
+/// u32 tee_type (= 0x81 for TDX)
+/// [4] reserved
+/// [16] qe_vendor_id
+/// [20] user_data
+/// [584] td_report_body
+///   [16] tee_tcb_svn
+///   [48] mr_seam
+///   [48] mr_signer_seam
+///   [8] seam_attributes
+///   [8] td_attributes (bit 0: debug)
+///   [8] xfam
+///   [48] mr_td
+///   [48] mr_config_id
+///   [48] mr_owner
+///   [48] mr_owner_config
+///   [4 x 48] rtmr0..rtmr3
+///   [64] report_data
+/// [4] signature_len
+/// [signature_len] signature data
+///   [64] signature, [64] attestation_key,
+///   [2] certification data type (= 6), [4] size,
+///   [384] QE report, [64] QE report signature,
+///   [2] auth_data_len, [auth_data_len] auth_data,
+///   [2] type, [4] size, [size] PCK certificate chain
+/// ```
+///
+/// A PCK certificate chain (type 5) inside certification data of type 6 is
+/// returned in `certification_data`; other types carry no chain.
+///
+/// Applies the default [`QuoteLimits`].
+pub fn parse_tdx_quote_v4(quote: &[u8]) -> Result<TdxQuoteV4, QuoteError> {
+    parse_tdx_quote_v4_with_limits(quote, &QuoteLimits::default())
+}
 
-    // For MVP: accept all quotes (verification happens at PCK chain level)
-    // In production, this MUST be implemented properly
+/// Parse a TDX quote v4, rejecting it if any size exceeds `limits`.
+pub fn parse_tdx_quote_v4_with_limits(
+    quote: &[u8],
+    limits: &QuoteLimits,
+) -> Result<TdxQuoteV4, QuoteError> {
+    limits.check("size", limits.max_quote_size, quote.len())?;
+    let sig_offset = 48 + TD_REPORT_SIZE;
+    if quote.len() < sig_offset + 4 {
+        return Err(QuoteError::InvalidLength {
+            expected: sig_offset + 4,
+            actual: quote.len(),
+        });
+    }
 
-    tracing::warn!(
-        "SGX quote signature verification is stubbed (TODO: implement ECDSA-p256 verification)"
-    );
+    let version = u16::from_le_bytes([quote[0], quote[1]]);
+    if version != 4 {
+        return Err(QuoteError::UnsupportedVersion(version));
+    }
+    let attestation_key_type = u16::from_le_bytes([quote[2], quote[3]]);
+    let tee_type = u32::from_le_bytes([quote[4], quote[5], quote[6], quote[7]]);
+    if tee_type != TEE_TYPE_TDX {
+        return Err(QuoteError::ParseError(format!(
+            "TEE type {tee_type:#x} is not TDX"
+        )));
+    }
+
+    let body = &quote[48..sig_offset];
+    let array = |offset: usize| -> [u8; 48] {
+        body[offset..offset + 48].try_into().expect("48-byte field")
+    };
+    let word = |offset: usize| {
+        u64::from_le_bytes(body[offset..offset + 8].try_into().expect("8-byte field"))
+    };
+    let td_attributes = word(120);
+    let mut report_data = [0u8; 64];
+    report_data.copy_from_slice(&body[520..584]);
+
+    let signature_len = u32::from_le_bytes(
+        quote[sig_offset..sig_offset + 4]
+            .try_into()
+            .expect("4-byte field"),
+    ) as usize;
+    limits.check("signature_len", limits.max_signature_len, signature_len)?;
+    let sig_end = sig_offset + 4 + signature_len;
+    if quote.len() < sig_end {
+        return Err(QuoteError::InvalidLength {
+            expected: sig_end,
+            actual: quote.len(),
+        });
+    }
+    let signature_data = &quote[sig_offset + 4..sig_end];
+
+    // Outer certification data: the QE report and the PCK certification data it wraps
+    let mut certification_data = None;
+    if let Some(header) = signature_data.get(V4_QE_REPORT_OFFSET - 6..V4_QE_REPORT_OFFSET) {
+        let certification_data_type = u16::from_le_bytes([header[0], header[1]]);
+        let certification_data_size =
+            u32::from_le_bytes([header[2], header[3], header[4], header[5]]) as usize;
+        limits.check(
+            "certification data",
+            limits.max_certification_data_size,
+            certification_data_size,
+        )?;
+        if certification_data_type == CERT_DATA_QE_REPORT {
+            certification_data =
+                parse_certification_data(signature_data, V4_QE_AUTH_DATA_OFFSET, limits)?;
+        } else {
+            tracing::debug!(
+                "TDX quote certification data type {certification_data_type} carries no QE report"
+            );
+        }
+    }
 
+    Ok(TdxQuoteV4 {
+        version,
+        attestation_key_type,
+        tee_tcb_svn: body[..16].try_into().expect("16-byte field"),
+        mr_seam: array(16),
+        mr_td: array(136),
+        mr_config_id: array(184),
+        mr_owner: array(232),
+        mr_owner_config: array(280),
+        rtmrs: [array(328), array(376), array(424), array(472)],
+        td_attributes,
+        xfam: word(128),
+        report_data,
+        debug_mode: td_attributes & 0x01 != 0,
+        signed_data: quote[..sig_offset].to_vec(),
+        signature: signature_data.to_vec(),
+        certification_data,
+    })
+}
+
+/// Read the certification data following the QE authentication data at
+/// `auth_data_offset`: `[2] type, [4] size, [size] data`.
+///
+/// Signature data that ends before the certification data header has none.
+/// The declared size is checked against `limits` before the data is read.
+fn parse_certification_data(
+    signature_data: &[u8],
+    auth_data_offset: usize,
+    limits: &QuoteLimits,
+) -> Result<Option<String>, QuoteError> {
+    let Some(auth_len) = signature_data.get(auth_data_offset..auth_data_offset + 2) else {
+        return Ok(None);
+    };
+    let type_offset =
+        auth_data_offset + 2 + u16::from_le_bytes([auth_len[0], auth_len[1]]) as usize;
+    let Some(header) = signature_data.get(type_offset..type_offset + 6) else {
+        return Ok(None);
+    };
+    let certification_data_type = u16::from_le_bytes([header[0], header[1]]);
+    let certification_data_size =
+        u32::from_le_bytes([header[2], header[3], header[4], header[5]]) as usize;
+    limits.check(
+        "certification data",
+        limits.max_certification_data_size,
+        certification_data_size,
+    )?;
+
+    let data_offset = type_offset + 6;
+    let data = signature_data
+        .get(data_offset..data_offset + certification_data_size)
+        .ok_or_else(|| QuoteError::ParseError("certification data is truncated".to_string()))?;
+    if certification_data_type != CERT_DATA_PCK_CHAIN {
+        tracing::debug!(
+            "Quote certification data type {certification_data_type} carries no PCK chain"
+        );
+        return Ok(None);
+    }
+    // The chain is PEM text, usually NUL-terminated
+    let pem = std::str::from_utf8(data)
+        .map_err(|_| QuoteError::ParseError("PCK certificate chain is not UTF-8".to_string()))?;
+    Ok(Some(pem.trim_end_matches('\0').to_string()))
+}
+
+/// Verify the QE report of an SGX quote: the PCK leaf certificate's key,
+/// `pck_public_key` (an uncompressed P-256 point), must have signed it, and
+/// it must bind the quote's attestation key.
+///
+/// The Quoting Enclave commits to the key it signs quotes with by setting
+/// its report's `report_data` to SHA-256(attestation key || QE
+/// authentication data), zero-padded to 64 bytes. That commitment only
+/// counts once the PCK signature shows the report is the QE's: anyone can
+/// write report data that hashes their own key.
+///
+/// Returns the verified report's identity fields, for checking against the
+/// QE Identity.
+pub fn verify_qe_report(quote: &SgxQuoteV3, pck_public_key: &[u8]) -> Result<QeReport, QuoteError> {
+    check_qe_report_signature(&quote.signature, QE_REPORT_OFFSET, pck_public_key)?;
+    check_qe_report_binding(&quote.signature, QE_REPORT_OFFSET)?;
+    parse_qe_report(&quote.signature, QE_REPORT_OFFSET)
+}
+
+/// Verify the QE report of a TDX quote, as [`verify_qe_report`] does for
+/// SGX quotes.
+pub fn verify_tdx_qe_report(
+    quote: &TdxQuoteV4,
+    pck_public_key: &[u8],
+) -> Result<QeReport, QuoteError> {
+    check_qe_report_signature(&quote.signature, V4_QE_REPORT_OFFSET, pck_public_key)?;
+    check_qe_report_binding(&quote.signature, V4_QE_REPORT_OFFSET)?;
+    parse_qe_report(&quote.signature, V4_QE_REPORT_OFFSET)
+}
+
+/// Check the QE report binding in signature data whose QE report starts at
+/// `qe_report_offset`, followed by its signature and the authentication data.
+fn check_qe_report_binding(
+    signature_data: &[u8],
+    qe_report_offset: usize,
+) -> Result<(), QuoteError> {
+    let auth_data_offset = qe_report_offset + QE_REPORT_SIZE + 64;
+
+    let attestation_key = signature_data
+        .get(ATTESTATION_KEY_OFFSET..ATTESTATION_KEY_OFFSET + 64)
+        .ok_or_else(|| missing("attestation key"))?;
+    let qe_report = signature_data
+        .get(qe_report_offset..qe_report_offset + QE_REPORT_SIZE)
+        .ok_or_else(|| missing("QE report"))?;
+    let auth_len = signature_data
+        .get(auth_data_offset..auth_data_offset + 2)
+        .ok_or_else(|| missing("QE authentication data"))?;
+    let auth_start = auth_data_offset + 2;
+    let auth_data = signature_data
+        .get(auth_start..auth_start + u16::from_le_bytes([auth_len[0], auth_len[1]]) as usize)
+        .ok_or_else(|| missing("QE authentication data"))?;
+
+    let mut expected = [0u8; 64];
+    expected[..32].copy_from_slice(
+        &Sha256::new()
+            .chain_update(attestation_key)
+            .chain_update(auth_data)
+            .finalize(),
+    );
+    if qe_report[REPORT_DATA_OFFSET..] != expected {
+        return Err(QuoteError::QeReportBinding);
+    }
     Ok(())
 }
 
+/// Identity fields of a verified Quoting Enclave report (see
+/// [`verify_qe_report`]), checked against the QE Identity Intel publishes
+/// (see [`crate::qe`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QeReport {
+    pub misc_select: u32,
+    pub attributes: [u8; 16],
+    pub mr_signer: [u8; 32],
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+}
+
+/// Read a QE report, a standard SGX report body:
+///
+/// ```text
+/// [16] cpu_svn, [4] misc_select @16, [28] reserved, [16] attributes @48,
+/// [32] mr_enclave @64, [32] reserved, [32] mr_signer @128, [96] reserved,
+/// [2] isv_prod_id @256, [2] isv_svn @258, [60] reserved, [64] report_data @320
+/// ```
+fn parse_qe_report(signature_data: &[u8], qe_report_offset: usize) -> Result<QeReport, QuoteError> {
+    let report = signature_data
+        .get(qe_report_offset..qe_report_offset + QE_REPORT_SIZE)
+        .ok_or_else(|| QuoteError::ParseError("signature data has no QE report".to_string()))?;
+    let word = |offset: usize| u16::from_le_bytes([report[offset], report[offset + 1]]);
+    let mut attributes = [0u8; 16];
+    attributes.copy_from_slice(&report[48..64]);
+    let mut mr_signer = [0u8; 32];
+    mr_signer.copy_from_slice(&report[128..160]);
+    Ok(QeReport {
+        misc_select: u32::from_le_bytes([report[16], report[17], report[18], report[19]]),
+        attributes,
+        mr_signer,
+        isv_prod_id: word(256),
+        isv_svn: word(258),
+    })
+}
+
+/// Verify the ECDSA-p256 quote signature: the attestation key carried in
+/// the signature data must have signed the quote header and report body.
+///
+/// The attestation key is only the Quoting Enclave's once
+/// [`verify_qe_report`] has passed.
+pub fn verify_quote_signature(quote: &SgxQuoteV3) -> Result<(), QuoteError> {
+    check_quote_signature(&quote.signed_data, &quote.signature)
+}
+
+/// Verify the ECDSA-p256 signature of a TDX quote over its header and TD
+/// report body (see [`verify_quote_signature`]).
+pub fn verify_tdx_quote_signature(quote: &TdxQuoteV4) -> Result<(), QuoteError> {
+    check_quote_signature(&quote.signed_data, &quote.signature)
+}
+
+fn missing(what: &str) -> QuoteError {
+    QuoteError::ParseError(format!("signature data has no {what}"))
+}
+
+/// Check the signature following the QE report at `qe_report_offset`.
+fn check_qe_report_signature(
+    signature_data: &[u8],
+    qe_report_offset: usize,
+    pck_public_key: &[u8],
+) -> Result<(), QuoteError> {
+    let signature_offset = qe_report_offset + QE_REPORT_SIZE;
+    let qe_report = signature_data
+        .get(qe_report_offset..signature_offset)
+        .ok_or_else(|| missing("QE report"))?;
+    let signature = signature_data
+        .get(signature_offset..signature_offset + 64)
+        .ok_or_else(|| missing("QE report signature"))?;
+    ecdsa_p256_verify(pck_public_key, qe_report, signature)
+        .map_err(|_| QuoteError::QeReportSignature)
+}
+
+/// Check the quote signature at the start of `signature_data` over `signed_data`.
+fn check_quote_signature(signed_data: &[u8], signature_data: &[u8]) -> Result<(), QuoteError> {
+    let signature = signature_data
+        .get(..64)
+        .ok_or_else(|| missing("quote signature"))?;
+    let attestation_key = signature_data
+        .get(ATTESTATION_KEY_OFFSET..ATTESTATION_KEY_OFFSET + 64)
+        .ok_or_else(|| missing("attestation key"))?;
+    // The attestation key is the bare point x || y
+    let mut public_key = Vec::with_capacity(65);
+    public_key.push(0x04);
+    public_key.extend_from_slice(attestation_key);
+    ecdsa_p256_verify(&public_key, signed_data, signature)
+}
+
+/// Verify an ECDSA-p256 signature (`r || s`) over SHA-256 of `message`.
+fn ecdsa_p256_verify(
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<(), QuoteError> {
+    ring::signature::UnparsedPublicKey::new(&ring::signature::ECDSA_P256_SHA256_FIXED, public_key)
+        .verify(message, signature)
+        .map_err(|_| QuoteError::InvalidSignature)
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+    pub(crate) fn ecdsa_key() -> EcdsaKeyPair {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap()
+    }
+
+    /// Attestation key of a test Quoting Enclave.
+    pub(crate) struct QuoteSigner {
+        rng: SystemRandom,
+        key: EcdsaKeyPair,
+    }
+
+    impl QuoteSigner {
+        pub(crate) fn new() -> Self {
+            Self {
+                rng: SystemRandom::new(),
+                key: ecdsa_key(),
+            }
+        }
+
+        /// The attestation key as signature data carries it: x || y.
+        pub(crate) fn public_key(&self) -> [u8; 64] {
+            self.key.public_key().as_ref()[1..].try_into().unwrap()
+        }
+
+        /// An SGX v3 quote from the test QE (see [`intel_qe_signature_data`])
+        /// carrying `pck_chain`, signed with `pck_key` as its PCK key.
+        pub(crate) fn quote(&self, pck_chain: &str, pck_key: &EcdsaKeyPair) -> Vec<u8> {
+            let signature_data = intel_qe_signature_data(&self.public_key(), b"qe auth data");
+            let mut quote = quote_with_certification_data(&signature_data, 5, pck_chain.as_bytes());
+            self.sign(&mut quote, pck_key);
+            quote
+        }
+
+        /// Sign an SGX v3 quote in place: its QE report with `pck_key`, its
+        /// header and report body with the attestation key.
+        pub(crate) fn sign(&self, quote: &mut [u8], pck_key: &EcdsaKeyPair) {
+            self.sign_at(
+                quote,
+                HEADER_SIZE + REPORT_BODY_SIZE,
+                QE_REPORT_OFFSET,
+                pck_key,
+            );
+        }
+
+        /// A TDX v4 quote from the test QE, as [`Self::quote`], with its
+        /// PCK chain wrapped in QE report certification data.
+        pub(crate) fn tdx_quote(&self, pck_chain: &str, pck_key: &EcdsaKeyPair) -> Vec<u8> {
+            let signature_data = intel_qe_signature_data(&self.public_key(), b"qe auth data");
+            let mut quote =
+                tdx_quote_with_certification_data(&signature_data, 5, pck_chain.as_bytes());
+            self.sign_tdx(&mut quote, pck_key);
+            quote
+        }
+
+        /// Sign a TDX v4 quote in place, as [`Self::sign`].
+        pub(crate) fn sign_tdx(&self, quote: &mut [u8], pck_key: &EcdsaKeyPair) {
+            self.sign_at(
+                quote,
+                HEADER_SIZE + TD_REPORT_SIZE,
+                V4_QE_REPORT_OFFSET,
+                pck_key,
+            );
+        }
+
+        fn sign_at(
+            &self,
+            quote: &mut [u8],
+            signed_len: usize,
+            qe_report_offset: usize,
+            pck_key: &EcdsaKeyPair,
+        ) {
+            let report = signed_len + 4 + qe_report_offset;
+            let signature = pck_key
+                .sign(&self.rng, &quote[report..report + QE_REPORT_SIZE])
+                .unwrap();
+            quote[report + QE_REPORT_SIZE..report + QE_REPORT_SIZE + 64]
+                .copy_from_slice(signature.as_ref());
+            let signature = self.key.sign(&self.rng, &quote[..signed_len]).unwrap();
+            quote[signed_len + 4..signed_len + 4 + 64].copy_from_slice(signature.as_ref());
+        }
+    }
 
     #[test]
     fn test_parse_invalid_quote_too_short() {
         let quote = vec![0u8; 10];
         let result = parse_sgx_quote_v3(&quote);
         assert!(matches!(result, Err(QuoteError::InvalidLength { .. })));
+        assert_eq!(result.unwrap_err().code(), ErrorCode::InvalidQuote);
+    }
+
+    #[test]
+    fn test_quote_claims() {
+        let mut quote = vec![0u8; 48 + 384 + 4];
+        quote[0] = 3;
+        quote[48 + 48] = 0x02; // debug attribute
+        quote[48 + 64..48 + 96].fill(0xE1); // mr_enclave
+        quote[48 + 128..48 + 160].fill(0x51); // mr_signer
+        quote[48 + 256] = 4; // isv_prod_id
+        quote[48 + 258] = 5; // isv_svn
+        quote[48 + 320] = 0xD0; // report_data
+
+        let parsed = parse_sgx_quote_v3(&quote).unwrap();
+        assert_eq!(parsed.mr_enclave, [0xE1; 32]);
+        assert_eq!(parsed.report_data[0], 0xD0);
+        let claims = parsed.claims();
+        assert_eq!(claims.get("sgx.isv_prod_id"), Some(&ClaimValue::Uint(4)));
+        assert_eq!(claims.get("sgx.isv_svn"), Some(&ClaimValue::Uint(5)));
+        assert_eq!(claims.get("sgx.debug"), Some(&ClaimValue::Bool(true)));
+        assert_eq!(
+            claims.get("sgx.mr_signer"),
+            Some(&ClaimValue::Bytes(vec![0x51; 32]))
+        );
     }
 
     #[test]
@@ -224,4 +879,298 @@ mod tests {
         let result = parse_sgx_quote_v3(&quote);
         assert!(matches!(result, Err(QuoteError::UnsupportedVersion(_))));
     }
+
+    /// Signature data whose QE report binds `attestation_key` with `auth_data`.
+    pub(crate) fn bound_signature_data(attestation_key: &[u8; 64], auth_data: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; QE_AUTH_DATA_OFFSET];
+        data[ATTESTATION_KEY_OFFSET..QE_REPORT_OFFSET].copy_from_slice(attestation_key);
+        let hash = Sha256::new()
+            .chain_update(attestation_key)
+            .chain_update(auth_data)
+            .finalize();
+        let report_data = QE_REPORT_OFFSET + REPORT_DATA_OFFSET;
+        data[report_data..report_data + 32].copy_from_slice(&hash);
+        data.extend_from_slice(&(auth_data.len() as u16).to_le_bytes());
+        data.extend_from_slice(auth_data);
+        data
+    }
+
+    /// Like [`bound_signature_data`], with a QE report from the quoting
+    /// enclave described by `qe::tests::identity` (ISVSVN 8, up to date).
+    pub(crate) fn intel_qe_signature_data(attestation_key: &[u8; 64], auth_data: &[u8]) -> Vec<u8> {
+        let mut data = bound_signature_data(attestation_key, auth_data);
+        let report = &mut data[QE_REPORT_OFFSET..QE_REPORT_OFFSET + QE_REPORT_SIZE];
+        report[48] = 0x11;
+        report[128..160].copy_from_slice(
+            &hex::decode("8C4F5775D796503E96137F77C68A829A0056AC8DED70140B081B094490C57BFF")
+                .unwrap(),
+        );
+        report[256..258].copy_from_slice(&1u16.to_le_bytes());
+        report[258..260].copy_from_slice(&8u16.to_le_bytes());
+        data
+    }
+
+    /// Fields of an enclave's report body, at their REPORTBODY offsets.
+    pub(crate) struct ReportBody {
+        pub(crate) mr_enclave: [u8; 32],
+        pub(crate) mr_signer: [u8; 32],
+        pub(crate) report_data: [u8; 64],
+    }
+
+    impl ReportBody {
+        /// Write these fields into the report body of an SGX v3 `quote`.
+        pub(crate) fn write(&self, quote: &mut [u8]) {
+            let body = &mut quote[HEADER_SIZE..HEADER_SIZE + REPORT_BODY_SIZE];
+            body[64..96].copy_from_slice(&self.mr_enclave);
+            body[128..160].copy_from_slice(&self.mr_signer);
+            body[REPORT_DATA_OFFSET..].copy_from_slice(&self.report_data);
+        }
+    }
+
+    /// SGX v3 quote with `signature_data` followed by certification data.
+    pub(crate) fn quote_with_certification_data(
+        signature_data: &[u8],
+        data_type: u16,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut signature_data = signature_data.to_vec();
+        signature_data.extend_from_slice(&data_type.to_le_bytes());
+        signature_data.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        signature_data.extend_from_slice(data);
+        let mut quote = vec![0u8; HEADER_SIZE + REPORT_BODY_SIZE];
+        quote[0] = 3;
+        quote.extend_from_slice(&(signature_data.len() as u32).to_le_bytes());
+        quote.extend_from_slice(&signature_data);
+        quote
+    }
+
+    /// TDX v4 quote whose v3-layout `signature_data` (see
+    /// [`bound_signature_data`]) is rearranged into v4 signature data: the
+    /// QE report and what follows it become certification data of type 6,
+    /// ending in certification data of `data_type`.
+    pub(crate) fn tdx_quote_with_certification_data(
+        signature_data: &[u8],
+        data_type: u16,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut qe_report_data = signature_data[QE_REPORT_OFFSET..].to_vec();
+        qe_report_data.extend_from_slice(&data_type.to_le_bytes());
+        qe_report_data.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        qe_report_data.extend_from_slice(data);
+        let mut signature_data = signature_data[..QE_REPORT_OFFSET].to_vec();
+        signature_data.extend_from_slice(&CERT_DATA_QE_REPORT.to_le_bytes());
+        signature_data.extend_from_slice(&(qe_report_data.len() as u32).to_le_bytes());
+        signature_data.extend_from_slice(&qe_report_data);
+        let mut quote = vec![0u8; HEADER_SIZE + TD_REPORT_SIZE];
+        quote[0] = 4;
+        quote[2] = 2;
+        quote[4] = 0x81;
+        quote.extend_from_slice(&(signature_data.len() as u32).to_le_bytes());
+        quote.extend_from_slice(&signature_data);
+        quote
+    }
+
+    #[test]
+    fn test_pck_chain_parsed_from_certification_data() {
+        let signature_data = bound_signature_data(&[7u8; 64], b"qe auth data");
+        let pem = "-----BEGIN CERTIFICATE-----\nAAEC\n-----END CERTIFICATE-----\n";
+        let quote =
+            quote_with_certification_data(&signature_data, 5, format!("{pem}\0").as_bytes());
+        let parsed = parse_sgx_quote_v3(&quote).unwrap();
+        assert_eq!(parsed.certification_data.as_deref(), Some(pem));
+
+        // Other certification data types carry no chain
+        let quote = quote_with_certification_data(&signature_data, 3, &[0u8; 16]);
+        assert_eq!(parse_sgx_quote_v3(&quote).unwrap().certification_data, None);
+
+        // A declared size past the end of the signature data is refused
+        let mut truncated = quote_with_certification_data(&signature_data, 5, pem.as_bytes());
+        truncated.truncate(truncated.len() - 1);
+        let signature_len = (truncated.len() - 48 - 384 - 4) as u32;
+        truncated[48 + 384..48 + 384 + 4].copy_from_slice(&signature_len.to_le_bytes());
+        assert!(matches!(
+            parse_sgx_quote_v3(&truncated),
+            Err(QuoteError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_qe_report_binding() {
+        let pck_key = ecdsa_key();
+        let pck_public_key = pck_key.public_key().as_ref();
+        let parsed = parse_sgx_quote_v3(&QuoteSigner::new().quote("", &pck_key)).unwrap();
+        let report = verify_qe_report(&parsed, pck_public_key).unwrap();
+        assert_eq!((report.isv_prod_id, report.isv_svn), (1, 8));
+
+        // A different attestation key is not the one the QE vouched for
+        let mut swapped = parsed.clone();
+        swapped.signature[ATTESTATION_KEY_OFFSET] ^= 1;
+        let err = verify_qe_report(&swapped, pck_public_key).unwrap_err();
+        assert!(matches!(err, QuoteError::QeReportBinding));
+        assert_eq!(err.code(), ErrorCode::VerificationFailed);
+
+        // So is the right key with altered authentication data
+        let mut altered = parsed.clone();
+        altered.signature[QE_AUTH_DATA_OFFSET + 2] ^= 1;
+        assert!(matches!(
+            verify_qe_report(&altered, pck_public_key),
+            Err(QuoteError::QeReportBinding)
+        ));
+
+        // A report binding the key proves nothing unless the PCK key signed it
+        let forged = parse_sgx_quote_v3(&QuoteSigner::new().quote("", &ecdsa_key())).unwrap();
+        assert!(matches!(
+            verify_qe_report(&forged, pck_public_key),
+            Err(QuoteError::QeReportSignature)
+        ));
+
+        let mut truncated = parsed;
+        truncated.signature.truncate(QE_REPORT_OFFSET + 10);
+        assert!(matches!(
+            verify_qe_report(&truncated, pck_public_key),
+            Err(QuoteError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_quote_and_qe_report_signatures() {
+        let signer = QuoteSigner::new();
+        let pck_key = ecdsa_key();
+        let pck_public_key = pck_key.public_key().as_ref();
+        let quote = signer.quote("", &pck_key);
+        let parsed = parse_sgx_quote_v3(&quote).unwrap();
+        verify_quote_signature(&parsed).unwrap();
+        verify_qe_report(&parsed, pck_public_key).unwrap();
+
+        // Any change to the report body breaks the quote signature
+        let mut tampered = quote.clone();
+        tampered[48 + 64] ^= 1;
+        let err = verify_quote_signature(&parse_sgx_quote_v3(&tampered).unwrap()).unwrap_err();
+        assert!(matches!(err, QuoteError::InvalidSignature));
+        assert_eq!(err.code(), ErrorCode::VerificationFailed);
+
+        // A QE report signed by another platform's PCK key is not certified
+        let other = ecdsa_key();
+        let err = verify_qe_report(&parsed, other.public_key().as_ref()).unwrap_err();
+        assert!(matches!(err, QuoteError::QeReportSignature));
+
+        // Nor is a QE report changed after signing
+        let mut tampered = quote.clone();
+        tampered[48 + 384 + 4 + QE_REPORT_OFFSET + 256] ^= 1;
+        let tampered = parse_sgx_quote_v3(&tampered).unwrap();
+        assert!(matches!(
+            verify_qe_report(&tampered, pck_public_key),
+            Err(QuoteError::QeReportSignature)
+        ));
+
+        // A quote signed by a key other than the attestation key it carries fails
+        let mut forged = quote.clone();
+        QuoteSigner::new().sign(&mut forged, &pck_key);
+        let forged = parse_sgx_quote_v3(&forged).unwrap();
+        assert!(matches!(
+            verify_quote_signature(&forged),
+            Err(QuoteError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn test_parse_tdx_quote_v4() {
+        let signer = QuoteSigner::new();
+        let pck_key = ecdsa_key();
+        let pem = "-----BEGIN CERTIFICATE-----\nAAEC\n-----END CERTIFICATE-----\n";
+        let mut quote = signer.tdx_quote(pem, &pck_key);
+        quote[48 + 120] = 0x01; // debug TD attribute
+        quote[48 + 136..48 + 184].fill(0xAA); // MRTD
+        quote[48 + 472..48 + 520].fill(0x33); // RTMR3
+        quote[48 + 520] = 0x5D; // report_data
+        signer.sign_tdx(&mut quote, &pck_key);
+
+        let parsed = parse_tdx_quote_v4(&quote).unwrap();
+        assert!(parsed.debug_mode);
+        assert_eq!(parsed.mr_td, [0xAA; 48]);
+        let claims = parsed.claims();
+        assert_eq!(
+            claims.get("tdx.rtmr.3"),
+            Some(&ClaimValue::Bytes(vec![0x33; 48]))
+        );
+        assert_eq!(
+            claims.get("tdx.rtmr.0"),
+            Some(&ClaimValue::Bytes(vec![0; 48]))
+        );
+        assert_eq!(parsed.report_data[0], 0x5D);
+
+        // The PCK chain is read from inside the QE report certification data
+        assert_eq!(parsed.certification_data.as_deref(), Some(pem));
+        let report = verify_tdx_qe_report(&parsed, pck_key.public_key().as_ref()).unwrap();
+        assert_eq!((report.isv_prod_id, report.isv_svn), (1, 8));
+        verify_tdx_quote_signature(&parsed).unwrap();
+
+        // The same checks as SGX quotes fail the same way
+        let other = ecdsa_key();
+        let err = verify_tdx_qe_report(&parsed, other.public_key().as_ref()).unwrap_err();
+        assert!(matches!(err, QuoteError::QeReportSignature));
+        let mut tampered = quote.clone();
+        tampered[48 + 136] ^= 1;
+        let err = verify_tdx_quote_signature(&parse_tdx_quote_v4(&tampered).unwrap()).unwrap_err();
+        assert!(matches!(err, QuoteError::InvalidSignature));
+
+        // Certification data of another type carries no chain
+        let signature_data = intel_qe_signature_data(&signer.public_key(), b"qe auth data");
+        let unchained = tdx_quote_with_certification_data(&signature_data, 3, &[0u8; 16]);
+        assert_eq!(
+            parse_tdx_quote_v4(&unchained).unwrap().certification_data,
+            None
+        );
+
+        // The v3 parser still refuses TDX quotes, and SGX v4 quotes are not TDX
+        assert!(matches!(
+            parse_sgx_quote_v3(&quote),
+            Err(QuoteError::UnsupportedVersion(4))
+        ));
+        quote[4] = 0;
+        assert!(matches!(
+            parse_tdx_quote_v4(&quote),
+            Err(QuoteError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_hostile_lengths_rejected() {
+        let mut quote = vec![0u8; 48 + 384 + 4];
+        quote[0] = 3;
+        quote[48 + 384..].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = parse_sgx_quote_v3(&quote).unwrap_err();
+        assert!(matches!(
+            err,
+            QuoteError::LimitExceeded {
+                field: "signature_len",
+                ..
+            }
+        ));
+        assert_eq!(err.code(), ErrorCode::InvalidQuote);
+
+        // Certification data size is checked even when the blob is truncated
+        let mut signature_data = vec![0u8; QE_AUTH_DATA_OFFSET + 2 + 2 + 4];
+        signature_data[QE_AUTH_DATA_OFFSET + 4..].copy_from_slice(&(1u32 << 30).to_le_bytes());
+        quote[48 + 384..].copy_from_slice(&(signature_data.len() as u32).to_le_bytes());
+        quote.extend_from_slice(&signature_data);
+        let err = parse_sgx_quote_v3(&quote).unwrap_err();
+        assert!(matches!(
+            err,
+            QuoteError::LimitExceeded {
+                field: "certification data",
+                ..
+            }
+        ));
+
+        let limits = QuoteLimits {
+            max_quote_size: 256,
+            ..QuoteLimits::default()
+        };
+        let err = parse_sgx_quote_v3_with_limits(&quote, &limits).unwrap_err();
+        assert!(matches!(
+            err,
+            QuoteError::LimitExceeded { field: "size", .. }
+        ));
+    }
 }
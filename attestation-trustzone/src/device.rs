@@ -0,0 +1,185 @@
+//! Device attestation key certificates.
+//!
+//! The attestation PTA signs with a per-device key whose certificate is
+//! issued, directly or through intermediates, by the device manufacturer's
+//! CA. [`verify_device_chain`] walks from the device certificate to a
+//! configured root, checking every signature and validity window, and
+//! returns the device key.
+
+use attestation_core::{ErrorCode, ErrorCoded};
+use chrono::{DateTime, Utc};
+use ring::signature::{
+    UnparsedPublicKey, VerificationAlgorithm, ECDSA_P256_SHA256_ASN1, RSA_PSS_2048_8192_SHA256,
+};
+use thiserror::Error;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::oid_registry::{OID_EC_P256, OID_KEY_TYPE_EC_PUBLIC_KEY, OID_PKCS1_RSAENCRYPTION};
+use x509_parser::prelude::{ASN1Time, FromDer};
+
+/// Verified device attestation key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceKey {
+    /// Subject of the device certificate
+    pub subject: String,
+    /// Signature scheme the key signs evidence with
+    pub scheme: DeviceKeyScheme,
+    /// Raw key (RSAPublicKey DER, or an uncompressed P-256 point)
+    pub public_key: Vec<u8>,
+}
+
+/// Signature schemes the attestation PTA may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKeyScheme {
+    /// RSASSA-PSS with SHA-256 (OP-TEE's default attestation key)
+    RsaPssSha256,
+    /// ECDSA P-256 with SHA-256, DER signature
+    EcdsaP256Sha256,
+}
+
+impl DeviceKeyScheme {
+    /// Name used in claims.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeviceKeyScheme::RsaPssSha256 => "rsa-pss-sha256",
+            DeviceKeyScheme::EcdsaP256Sha256 => "ecdsa-p256-sha256",
+        }
+    }
+
+    fn algorithm(&self) -> &'static dyn VerificationAlgorithm {
+        match self {
+            DeviceKeyScheme::RsaPssSha256 => &RSA_PSS_2048_8192_SHA256,
+            DeviceKeyScheme::EcdsaP256Sha256 => &ECDSA_P256_SHA256_ASN1,
+        }
+    }
+}
+
+impl DeviceKey {
+    /// Verify `signature` over `message`.
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), DeviceCertError> {
+        UnparsedPublicKey::new(self.scheme.algorithm(), &self.public_key)
+            .verify(message, signature)
+            .map_err(|_| DeviceCertError::InvalidEvidenceSignature)
+    }
+}
+
+/// Verify `device_cert` up to one of `roots` (all DER) at `now`.
+///
+/// `intermediates` may be in any order; each certificate on the path must be
+/// within its validity window and signed by the next.
+pub fn verify_device_chain(
+    device_cert: &[u8],
+    intermediates: &[Vec<u8>],
+    roots: &[Vec<u8>],
+    now: DateTime<Utc>,
+) -> Result<DeviceKey, DeviceCertError> {
+    let time = ASN1Time::from_timestamp(now.timestamp())
+        .map_err(|e| DeviceCertError::Parse(e.to_string()))?;
+    let device = parse(device_cert)?;
+    let intermediates = intermediates
+        .iter()
+        .map(|der| parse(der))
+        .collect::<Result<Vec<_>, _>>()?;
+    let roots = roots
+        .iter()
+        .map(|der| parse(der))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut current = &device;
+    // Each intermediate can appear on the path at most once
+    for _ in 0..=intermediates.len() {
+        check_validity(current, time)?;
+        if let Some(root) = roots.iter().find(|root| root.subject() == current.issuer()) {
+            verify_issued_by(current, root)?;
+            check_validity(root, time)?;
+            return device_key(&device);
+        }
+        let issuer = intermediates
+            .iter()
+            .find(|cert| cert.subject() == current.issuer() && cert.is_ca())
+            .ok_or(DeviceCertError::UntrustedRoot)?;
+        verify_issued_by(current, issuer)?;
+        current = issuer;
+    }
+    Err(DeviceCertError::UntrustedRoot)
+}
+
+fn parse(der: &[u8]) -> Result<X509Certificate<'_>, DeviceCertError> {
+    X509Certificate::from_der(der)
+        .map(|(_, cert)| cert)
+        .map_err(|e| DeviceCertError::Parse(e.to_string()))
+}
+
+fn check_validity(cert: &X509Certificate<'_>, time: ASN1Time) -> Result<(), DeviceCertError> {
+    if !cert.validity().is_valid_at(time) {
+        return Err(DeviceCertError::Expired(cert.subject().to_string()));
+    }
+    Ok(())
+}
+
+fn verify_issued_by(
+    cert: &X509Certificate<'_>,
+    issuer: &X509Certificate<'_>,
+) -> Result<(), DeviceCertError> {
+    cert.verify_signature(Some(issuer.public_key()))
+        .map_err(|_| DeviceCertError::InvalidCertSignature(cert.subject().to_string()))
+}
+
+fn device_key(cert: &X509Certificate<'_>) -> Result<DeviceKey, DeviceCertError> {
+    let spki = cert.public_key();
+    let scheme = if spki.algorithm.algorithm == OID_PKCS1_RSAENCRYPTION {
+        DeviceKeyScheme::RsaPssSha256
+    } else if spki.algorithm.algorithm == OID_KEY_TYPE_EC_PUBLIC_KEY
+        && spki
+            .algorithm
+            .parameters
+            .as_ref()
+            .and_then(|params| params.as_oid().ok())
+            .is_some_and(|curve| curve == OID_EC_P256)
+    {
+        DeviceKeyScheme::EcdsaP256Sha256
+    } else {
+        return Err(DeviceCertError::UnsupportedKey(
+            spki.algorithm.algorithm.to_id_string(),
+        ));
+    };
+    Ok(DeviceKey {
+        subject: cert.subject().to_string(),
+        scheme,
+        public_key: spki.subject_public_key.data.to_vec(),
+    })
+}
+
+#[derive(Debug, Error)]
+pub enum DeviceCertError {
+    #[error("Certificate parse error: {0}")]
+    Parse(String),
+
+    #[error("Certificate {0} is expired or not yet valid")]
+    Expired(String),
+
+    #[error("Certificate {0} is not signed by its issuer")]
+    InvalidCertSignature(String),
+
+    #[error("Device certificate does not chain to a trusted root")]
+    UntrustedRoot,
+
+    #[error("Unsupported device key algorithm {0}")]
+    UnsupportedKey(String),
+
+    #[error("Evidence signature does not verify under the device key")]
+    InvalidEvidenceSignature,
+}
+
+impl ErrorCoded for DeviceCertError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            DeviceCertError::Parse(_) | DeviceCertError::UnsupportedKey(_) => {
+                ErrorCode::InvalidQuote
+            }
+            DeviceCertError::Expired(_)
+            | DeviceCertError::InvalidCertSignature(_)
+            | DeviceCertError::UntrustedRoot
+            | DeviceCertError::InvalidEvidenceSignature => ErrorCode::VerificationFailed,
+        }
+    }
+}
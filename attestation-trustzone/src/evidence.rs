@@ -0,0 +1,172 @@
+//! OP-TEE TA attestation evidence.
+//!
+//! OP-TEE's attestation pseudo-TA (`PTA_ATTESTATION_HASH_TA_MEMORY`, or
+//! `PTA_ATTESTATION_GET_TA_SHDR_DIGEST` for the signed binary) returns a
+//! SHA-256 measurement of the calling TA and a signature over
+//! `nonce || measurement` by the device attestation key. The normal-world
+//! agent packs that output, the TA's UUID and the certificate chain of the
+//! device key into a [`TaEvidence`] and sends its canonical CBOR as the quote.
+
+use attestation_core::serialization::{from_canonical_cbor, to_canonical_cbor, SerializationError};
+use attestation_core::{ClaimValue, ErrorCode, ErrorCoded};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Evidence version (for schema evolution)
+pub const TA_EVIDENCE_VERSION: u8 = 1;
+
+/// Largest nonce the attestation PTA accepts.
+pub const MAX_NONCE_LEN: usize = 64;
+
+/// Attestation evidence for one OP-TEE trusted application.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaEvidence {
+    /// Schema version
+    pub version: u8,
+    /// UUID of the measured TA (RFC 4122 byte order)
+    pub ta_uuid: [u8; 16],
+    /// SHA-256 measurement reported by the attestation PTA
+    pub measurement: [u8; 32],
+    /// Nonce the verifier supplied
+    pub nonce: Vec<u8>,
+    /// Device key signature over `nonce || measurement` (RSASSA-PSS with
+    /// SHA-256 for RSA keys, DER-encoded ECDSA for P-256 keys)
+    pub signature: Vec<u8>,
+    /// DER certificate of the device attestation key
+    pub device_cert: Vec<u8>,
+    /// DER certificates between the device certificate and a trusted root
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub intermediates: Vec<Vec<u8>>,
+}
+
+impl TaEvidence {
+    /// Bytes covered by `signature`.
+    pub fn signed_message(&self) -> Vec<u8> {
+        [self.nonce.as_slice(), &self.measurement].concat()
+    }
+
+    /// TA UUID in its canonical text form.
+    pub fn ta_uuid_string(&self) -> String {
+        let hex = hex::encode(self.ta_uuid);
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        )
+    }
+
+    /// Claims extracted from the evidence (keys prefixed `optee.`).
+    pub fn claims(&self) -> BTreeMap<String, ClaimValue> {
+        let mut claims = BTreeMap::new();
+        claims.insert(
+            "optee.ta_uuid".to_string(),
+            ClaimValue::Text(self.ta_uuid_string()),
+        );
+        claims.insert(
+            "optee.measurement".to_string(),
+            ClaimValue::Bytes(self.measurement.to_vec()),
+        );
+        claims.insert(
+            "optee.nonce".to_string(),
+            ClaimValue::Bytes(self.nonce.clone()),
+        );
+        claims
+    }
+
+    /// Serialize to canonical CBOR bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        to_canonical_cbor(self)
+    }
+}
+
+/// Parse TA evidence from canonical CBOR.
+pub fn parse_ta_evidence(bytes: &[u8]) -> Result<TaEvidence, EvidenceError> {
+    let evidence: TaEvidence = from_canonical_cbor(bytes)?;
+    if evidence.version != TA_EVIDENCE_VERSION {
+        return Err(SerializationError::UnsupportedVersion {
+            kind: "OP-TEE TA evidence",
+            version: evidence.version,
+        }
+        .into());
+    }
+    if evidence.nonce.len() > MAX_NONCE_LEN {
+        return Err(EvidenceError::NonceTooLong(evidence.nonce.len()));
+    }
+    if evidence.device_cert.is_empty() {
+        return Err(EvidenceError::MissingDeviceCert);
+    }
+    Ok(evidence)
+}
+
+#[derive(Debug, Error)]
+pub enum EvidenceError {
+    #[error("Evidence decoding failed: {0}")]
+    Serialization(#[from] SerializationError),
+
+    #[error("Nonce of {0} bytes exceeds the attestation PTA limit")]
+    NonceTooLong(usize),
+
+    #[error("Evidence carries no device certificate")]
+    MissingDeviceCert,
+}
+
+impl ErrorCoded for EvidenceError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            EvidenceError::Serialization(e) => e.code(),
+            EvidenceError::NonceTooLong(_) | EvidenceError::MissingDeviceCert => {
+                ErrorCode::InvalidQuote
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evidence() -> TaEvidence {
+        TaEvidence {
+            version: TA_EVIDENCE_VERSION,
+            ta_uuid: [
+                0x8a, 0xaa, 0xf2, 0x00, 0x24, 0x50, 0x11, 0xe4, 0xab, 0xe2, 0x00, 0x02, 0xa5, 0xd5,
+                0xc5, 0x1b,
+            ],
+            measurement: [7u8; 32],
+            nonce: vec![1, 2, 3],
+            signature: vec![0u8; 64],
+            device_cert: vec![0x30],
+            intermediates: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_ta_evidence() {
+        let evidence = evidence();
+        assert_eq!(
+            parse_ta_evidence(&evidence.to_bytes().unwrap()).unwrap(),
+            evidence
+        );
+        assert_eq!(
+            evidence.ta_uuid_string(),
+            "8aaaf200-2450-11e4-abe2-0002a5d5c51b"
+        );
+        assert_eq!(&evidence.signed_message()[..3], [1, 2, 3]);
+
+        let mut old = evidence.clone();
+        old.version = 0;
+        let err = parse_ta_evidence(&old.to_bytes().unwrap()).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::UnsupportedVersion);
+
+        let mut long = evidence;
+        long.nonce = vec![0u8; MAX_NONCE_LEN + 1];
+        assert!(matches!(
+            parse_ta_evidence(&long.to_bytes().unwrap()),
+            Err(EvidenceError::NonceTooLong(65))
+        ));
+    }
+}
@@ -0,0 +1,345 @@
+//! OP-TEE (GlobalPlatform TEE on Arm TrustZone) attestation adapter.
+//!
+//! Verifies attestation evidence for trusted applications running under
+//! OP-TEE on embedded robots and plugs into
+//! [`AttestationRegistry`](attestation_core::AttestationRegistry) under the
+//! vendor name [`VENDOR`].
+//!
+//! ## Verification Flow
+//! 1. Decode the [`TaEvidence`] (see [`evidence`] for how it is produced)
+//! 2. Check the nonce and that the TA's UUID is allowed
+//! 3. Verify the device key certificate chain up to a configured
+//!    manufacturer root ([`device`])
+//! 4. Verify the device key's signature over `nonce || measurement`
+//! 5. Check the measurement against the allowed TA measurements
+//! 6. Return attestation result, with `optee.*` claims
+//!
+//! OP-TEE keeps no revocation service; revocation is left to the fleet
+//! registry and reported as not checked.
+
+pub mod device;
+pub mod evidence;
+
+use async_trait::async_trait;
+use attestation_core::{
+    system_clock, AttestationAdapter, AttestationError, AttestationResult, ClaimValue, Clock,
+    ErrorCode, ErrorCoded, RevocationCheck, RevocationSource,
+};
+use device::{verify_device_chain, DeviceCertError};
+use evidence::{parse_ta_evidence, TaEvidence};
+use std::sync::Arc;
+use x509_parser::pem::Pem;
+
+/// Vendor name the adapter registers under.
+pub const VENDOR: &str = "optee";
+
+/// Configuration for OP-TEE TA verification.
+#[derive(Debug, Clone, Default)]
+pub struct OpteeConfig {
+    /// Manufacturer root CAs for device attestation keys (PEM)
+    pub root_ca_certs: Vec<String>,
+    /// Accepted TA measurements (any, if empty)
+    pub allowed_measurements: Vec<[u8; 32]>,
+    /// Accepted TA UUIDs (any, if empty)
+    pub allowed_tas: Vec<[u8; 16]>,
+}
+
+/// OP-TEE trusted application attestation adapter.
+pub struct OpteeAdapter {
+    config: OpteeConfig,
+    /// Source of verification times and certificate validity checks
+    clock: Arc<dyn Clock>,
+}
+
+impl OpteeAdapter {
+    /// Create an adapter with the given configuration.
+    pub fn new(config: OpteeConfig) -> Self {
+        Self {
+            config,
+            clock: system_clock(),
+        }
+    }
+
+    /// Read verification times from `clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Configured roots, as DER.
+    fn roots(&self) -> Result<Vec<Vec<u8>>, AttestationError> {
+        let mut roots = Vec::new();
+        for pem in &self.config.root_ca_certs {
+            for block in Pem::iter_from_buffer(pem.as_bytes()) {
+                let block = block
+                    .map_err(|e| AttestationError::Config(format!("Invalid root CA PEM: {e}")))?;
+                roots.push(block.contents);
+            }
+        }
+        if roots.is_empty() {
+            return Err(AttestationError::Config(
+                "No OP-TEE root CA configured".to_string(),
+            ));
+        }
+        Ok(roots)
+    }
+
+    fn verify_evidence(
+        &self,
+        evidence: &TaEvidence,
+        nonce: Option<&[u8]>,
+    ) -> Result<AttestationResult, AttestationError> {
+        if nonce.is_some_and(|nonce| nonce != evidence.nonce.as_slice()) {
+            return Err(AttestationError::VerificationFailed(
+                "Evidence nonce does not match the challenge".to_string(),
+            ));
+        }
+
+        if !self.config.allowed_tas.is_empty()
+            && !self.config.allowed_tas.contains(&evidence.ta_uuid)
+        {
+            return Err(AttestationError::VerificationFailed(format!(
+                "TA {} is not allowed",
+                evidence.ta_uuid_string()
+            )));
+        }
+
+        let now = self.clock.now();
+        let key = verify_device_chain(
+            &evidence.device_cert,
+            &evidence.intermediates,
+            &self.roots()?,
+            now,
+        )
+        .and_then(|key| {
+            key.verify(&evidence.signed_message(), &evidence.signature)
+                .map(|_| key)
+        })
+        .map_err(device_error)?;
+
+        if !self.config.allowed_measurements.is_empty()
+            && !self
+                .config
+                .allowed_measurements
+                .contains(&evidence.measurement)
+        {
+            return Err(AttestationError::VerificationFailed(format!(
+                "TA measurement {} is not allowed",
+                hex::encode(evidence.measurement)
+            )));
+        }
+
+        let mut claims = evidence.claims();
+        claims.insert(
+            "optee.device_subject".to_string(),
+            ClaimValue::Text(key.subject),
+        );
+        claims.insert(
+            "optee.device_key_scheme".to_string(),
+            ClaimValue::Text(key.scheme.name().to_string()),
+        );
+
+        Ok(AttestationResult {
+            vendor: VENDOR.to_string(),
+            enclave_measurement: evidence.measurement.to_vec(),
+            quote_verified: true,
+            verified_at: now,
+            revoke_check: RevocationCheck::unknown(RevocationSource::NotChecked),
+            raw_quote: None,
+            pck_chain: None,
+            claims,
+        })
+    }
+}
+
+fn device_error(e: DeviceCertError) -> AttestationError {
+    match e.code() {
+        ErrorCode::InvalidQuote => AttestationError::InvalidQuote(e.to_string()),
+        _ => AttestationError::VerificationFailed(e.to_string()),
+    }
+}
+
+#[async_trait]
+impl AttestationAdapter for OpteeAdapter {
+    fn vendor_name(&self) -> &str {
+        VENDOR
+    }
+
+    async fn verify_quote(
+        &self,
+        quote: &[u8],
+        nonce: Option<&[u8]>,
+    ) -> Result<AttestationResult, AttestationError> {
+        let evidence =
+            parse_ta_evidence(quote).map_err(|e| AttestationError::InvalidQuote(e.to_string()))?;
+        tracing::debug!(
+            "Parsed OP-TEE evidence: TA={}, measurement={}",
+            evidence.ta_uuid_string(),
+            hex::encode(evidence.measurement)
+        );
+        let mut result = self.verify_evidence(&evidence, nonce)?;
+        result.raw_quote = Some(quote.to_vec());
+        Ok(result)
+    }
+
+    async fn check_revocation(
+        &self,
+        measurement: &[u8],
+    ) -> Result<RevocationCheck, AttestationError> {
+        tracing::debug!(
+            "No OP-TEE revocation source for {}",
+            hex::encode(measurement)
+        );
+        Ok(RevocationCheck::unknown(RevocationSource::NotChecked))
+    }
+
+    async fn root_ca_certs(&self) -> Vec<String> {
+        self.config.root_ca_certs.clone()
+    }
+
+    async fn update_trust_anchors(&self) -> Result<(), AttestationError> {
+        // Roots are static configuration; nothing to fetch
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use attestation_core::AttestationRegistry;
+    use evidence::TA_EVIDENCE_VERSION;
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+    struct Device {
+        root_pem: String,
+        device_cert: Vec<u8>,
+        signer: EcdsaKeyPair,
+    }
+
+    fn provision() -> Device {
+        let root_key = KeyPair::generate().unwrap();
+        let mut root_params = CertificateParams::new(Vec::new()).unwrap();
+        root_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        root_params
+            .distinguished_name
+            .push(DnType::CommonName, "Robot Vendor Root");
+        let root = root_params.self_signed(&root_key).unwrap();
+
+        let device_key = KeyPair::generate().unwrap();
+        let mut device_params = CertificateParams::new(Vec::new()).unwrap();
+        device_params
+            .distinguished_name
+            .push(DnType::CommonName, "robot-r-001");
+        let device_cert = device_params
+            .signed_by(&device_key, &root, &root_key)
+            .unwrap();
+
+        let signer = EcdsaKeyPair::from_pkcs8(
+            &ECDSA_P256_SHA256_ASN1_SIGNING,
+            &device_key.serialize_der(),
+            &SystemRandom::new(),
+        )
+        .unwrap();
+        Device {
+            root_pem: root.pem(),
+            device_cert: device_cert.der().to_vec(),
+            signer,
+        }
+    }
+
+    fn evidence(device: &Device, nonce: &[u8]) -> Vec<u8> {
+        let mut evidence = TaEvidence {
+            version: TA_EVIDENCE_VERSION,
+            ta_uuid: [0x11; 16],
+            measurement: [0xaa; 32],
+            nonce: nonce.to_vec(),
+            signature: Vec::new(),
+            device_cert: device.device_cert.clone(),
+            intermediates: Vec::new(),
+        };
+        let signature = device
+            .signer
+            .sign(&SystemRandom::new(), &evidence.signed_message())
+            .unwrap();
+        evidence.signature = signature.as_ref().to_vec();
+        evidence.to_bytes().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_verify_ta_evidence_through_registry() {
+        let device = provision();
+        let mut registry = AttestationRegistry::new();
+        registry.register(Box::new(OpteeAdapter::new(OpteeConfig {
+            root_ca_certs: vec![device.root_pem.clone()],
+            allowed_measurements: vec![[0xaa; 32]],
+            allowed_tas: vec![[0x11; 16]],
+        })));
+
+        let quote = evidence(&device, b"challenge");
+        let result = registry
+            .verify_quote(VENDOR, &quote, Some(b"challenge"))
+            .await
+            .unwrap();
+        assert!(result.quote_verified);
+        assert_eq!(result.enclave_measurement, vec![0xaa; 32]);
+        assert_eq!(
+            result.claim("optee.ta_uuid").and_then(|v| v.as_str()),
+            Some("11111111-1111-1111-1111-111111111111")
+        );
+        assert_eq!(
+            result
+                .claim("optee.device_key_scheme")
+                .and_then(|v| v.as_str()),
+            Some("ecdsa-p256-sha256")
+        );
+
+        // Replayed against another challenge
+        let err = registry
+            .verify_quote(VENDOR, &quote, Some(b"other"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::VerificationFailed);
+
+        // Signature no longer covers the measurement
+        let mut tampered = parse_ta_evidence(&quote).unwrap();
+        tampered.measurement = [0xbb; 32];
+        let err = registry
+            .verify_quote(VENDOR, &tampered.to_bytes().unwrap(), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Evidence signature"));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_untrusted_device_and_unlisted_measurement() {
+        let device = provision();
+        // Same issuer name, different key
+        let impostor = provision();
+        let adapter = OpteeAdapter::new(OpteeConfig {
+            root_ca_certs: vec![device.root_pem.clone()],
+            allowed_measurements: vec![[0xcc; 32]],
+            ..OpteeConfig::default()
+        });
+
+        let err = adapter
+            .verify_quote(&evidence(&impostor, b"n"), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("is not signed by its issuer"));
+
+        let err = adapter
+            .verify_quote(&evidence(&device, b"n"), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("measurement"));
+
+        let unconfigured = OpteeAdapter::new(OpteeConfig::default());
+        let err = unconfigured
+            .verify_quote(&evidence(&device, b"n"), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AttestationError::Config(_)));
+    }
+}
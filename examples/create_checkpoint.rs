@@ -66,11 +66,7 @@ fn main() {
         .unwrap();
 
     println!("   ✓ Checkpoint signed");
-    println!("   Robot ID: {}", checkpoint.robot_id);
-    println!("   Mission ID: {}", checkpoint.mission_id);
-    println!("   Sequence: {}", checkpoint.sequence);
-    println!("   Monotonic Counter: {}", checkpoint.monotonic_counter);
-    println!("   Trust Mode: {}\n", checkpoint.trust_mode);
+    println!("{}\n", checkpoint.describe());
 
     // Step 4: Serialize to canonical CBOR
     println!("4️⃣  Serializing to canonical CBOR...");
@@ -0,0 +1,255 @@
+//! # Veribot Mobile
+//!
+//! Checkpoint and proof verification for field-technician apps, exported to
+//! Kotlin and Swift with UniFFI. A technician reads a robot's latest
+//! checkpoint (canonical CBOR, as stored and transmitted everywhere else)
+//! over BLE or NFC and checks it on the spot against the robot's public key;
+//! no network or gateway is involved.
+//!
+//! Generate the bindings from a built library:
+//!
+//! ```text
+//! cargo build -p veribot-mobile --release
+//! cargo run -p veribot-mobile --features cli --bin uniffi-bindgen -- \
+//!     generate --library target/release/libveribot_mobile.so --language kotlin --out-dir out
+//! ```
+//!
+//! (`--language swift` for iOS.) Every failure carries the `VB-` error code
+//! of the check that failed, so apps can show the same codes as the CLI.
+
+use attestation_core::{
+    ChainHead, ChainVerifier, Checkpoint, ErrorCoded, MerkleProof, VerifyingKey,
+};
+
+uniffi::setup_scaffolding!();
+
+/// Why verification failed.
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum VerifyError {
+    /// The bytes are not a checkpoint or proof
+    #[error("{code}: {message}")]
+    Decode { code: String, message: String },
+
+    /// Not a 32-byte Ed25519 public key
+    #[error("Invalid public key")]
+    InvalidKey,
+
+    /// The checkpoint or proof failed a check
+    #[error("{code}: {message}")]
+    Rejected { code: String, message: String },
+}
+
+impl VerifyError {
+    fn decode(e: impl ErrorCoded) -> Self {
+        VerifyError::Decode {
+            code: e.code().as_str().to_string(),
+            message: e.to_string(),
+        }
+    }
+
+    fn rejected(e: impl ErrorCoded) -> Self {
+        VerifyError::Rejected {
+            code: e.code().as_str().to_string(),
+            message: e.to_string(),
+        }
+    }
+}
+
+/// What a verified checkpoint says, ready for display (hashes in hex).
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct CheckpointInfo {
+    pub robot_id: String,
+    pub mission_id: String,
+    pub sequence: u64,
+    pub monotonic_counter: u64,
+    /// Robot clock, milliseconds since the Unix epoch
+    pub timestamp_unix_ms: i64,
+    pub trust_mode: String,
+    pub model_name: String,
+    pub model_hash: String,
+    pub entries_root: String,
+    pub checkpoint_hash: String,
+    pub signer_key_id: String,
+}
+
+impl CheckpointInfo {
+    fn new(checkpoint: &Checkpoint) -> Result<Self, VerifyError> {
+        let hash = checkpoint.compute_hash().map_err(VerifyError::decode)?;
+        Ok(Self {
+            robot_id: checkpoint.robot_id.0.clone(),
+            mission_id: checkpoint.mission_id.0.clone(),
+            sequence: checkpoint.sequence,
+            monotonic_counter: checkpoint.monotonic_counter,
+            timestamp_unix_ms: checkpoint.local_timestamp_utc.timestamp_millis(),
+            trust_mode: format!("{:?}", checkpoint.trust_mode),
+            model_name: checkpoint.model_provenance.name.clone(),
+            model_hash: hex::encode(checkpoint.model_provenance.model_hash),
+            entries_root: hex::encode(checkpoint.entries_root),
+            checkpoint_hash: hex::encode(hash),
+            signer_key_id: checkpoint.signer_key_id.to_string(),
+        })
+    }
+}
+
+fn verifying_key(public_key: &[u8]) -> Result<VerifyingKey, VerifyError> {
+    let bytes: [u8; 32] = public_key.try_into().map_err(|_| VerifyError::InvalidKey)?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| VerifyError::InvalidKey)
+}
+
+fn signed_checkpoint(bytes: &[u8], key: &VerifyingKey) -> Result<Checkpoint, VerifyError> {
+    let checkpoint = Checkpoint::from_bytes(bytes).map_err(VerifyError::decode)?;
+    checkpoint
+        .verify_signature(key)
+        .map_err(VerifyError::rejected)?;
+    Ok(checkpoint)
+}
+
+/// Verify a checkpoint's signature against the robot's public key.
+#[uniffi::export]
+pub fn verify_checkpoint(
+    checkpoint: Vec<u8>,
+    public_key: Vec<u8>,
+) -> Result<CheckpointInfo, VerifyError> {
+    let key = verifying_key(&public_key)?;
+    CheckpointInfo::new(&signed_checkpoint(&checkpoint, &key)?)
+}
+
+/// Verify `latest` and that it directly follows `previous` (a checkpoint the
+/// app saw earlier): same robot, next sequence, higher counter, chained hash.
+#[uniffi::export]
+pub fn verify_successor(
+    previous: Vec<u8>,
+    latest: Vec<u8>,
+    public_key: Vec<u8>,
+) -> Result<CheckpointInfo, VerifyError> {
+    let key = verifying_key(&public_key)?;
+    let previous = signed_checkpoint(&previous, &key)?;
+    let latest = Checkpoint::from_bytes(&latest).map_err(VerifyError::decode)?;
+
+    let head = ChainHead::from_checkpoint(&previous).map_err(VerifyError::decode)?;
+    ChainVerifier::new(key)
+        .resume_from(head)
+        .verify_next(&latest)
+        .map_err(VerifyError::rejected)?;
+    CheckpointInfo::new(&latest)
+}
+
+/// Verify a checkpoint, then whether a Merkle proof (canonical CBOR) shows
+/// an entry under its entries root. Returns false for a well-formed proof
+/// that does not match.
+#[uniffi::export]
+pub fn verify_entry_proof(
+    checkpoint: Vec<u8>,
+    public_key: Vec<u8>,
+    proof: Vec<u8>,
+) -> Result<bool, VerifyError> {
+    let key = verifying_key(&public_key)?;
+    let checkpoint = signed_checkpoint(&checkpoint, &key)?;
+    let proof: MerkleProof = attestation_core::serialization::from_canonical_cbor(&proof)
+        .map_err(VerifyError::decode)?;
+    Ok(checkpoint.includes_entry(&proof))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use attestation_core::{
+        CheckpointBuilder, DeterminismConfig, Entry, ErrorCode, MerkleTree, ModelProvenance,
+        SigningKey, TrustMode,
+    };
+
+    fn chain(key: &SigningKey, tree: &MerkleTree) -> (Checkpoint, Checkpoint) {
+        let first = CheckpointBuilder::test_fixture()
+            .model_provenance(ModelProvenance {
+                name: "planner-v2".to_string(),
+                model_hash: [1u8; 32],
+                dataset_hash: None,
+                container_digest: None,
+                signature_bundle: None,
+            })
+            .firmware_hash([2u8; 32])
+            .enclave_measurement(vec![3u8; 32])
+            .entries_root([0u8; 32])
+            .inference_config(DeterminismConfig {
+                rng_seed: Some(7),
+                batch_size: 1,
+                flags: None,
+            })
+            .trust_mode(TrustMode::Trusted)
+            .build_and_sign(key)
+            .unwrap();
+        let second = CheckpointBuilder::continuing_from(&first)
+            .unwrap()
+            .monotonic_counter(2)
+            .entries_root(tree.root())
+            .build_and_sign(key)
+            .unwrap();
+        (first, second)
+    }
+
+    #[test]
+    fn test_verifies_latest_checkpoint_and_entry() {
+        let key = SigningKey::from_bytes(&[5u8; 32]);
+        let public = key.verifying_key().to_bytes().to_vec();
+        let mut tree = MerkleTree::new();
+        tree.insert(Entry::new(10, 0, b"arm extended"));
+        tree.insert(Entry::new(20, 0, b"arm retracted"));
+        let (first, second) = chain(&key, &tree);
+
+        let info = verify_successor(
+            first.to_bytes().unwrap(),
+            second.to_bytes().unwrap(),
+            public.clone(),
+        )
+        .unwrap();
+        assert_eq!(info.robot_id, "R-001");
+        assert_eq!(info.sequence, 1);
+        assert_eq!(
+            info.checkpoint_hash,
+            hex::encode(second.compute_hash().unwrap())
+        );
+
+        let proof = attestation_core::serialization::to_canonical_cbor(
+            &tree.generate_proof(20, 0).unwrap(),
+        )
+        .unwrap();
+        assert!(
+            verify_entry_proof(second.to_bytes().unwrap(), public.clone(), proof.clone()).unwrap()
+        );
+        assert!(!verify_entry_proof(first.to_bytes().unwrap(), public, proof).unwrap());
+    }
+
+    #[test]
+    fn test_failures_carry_error_codes() {
+        let key = SigningKey::from_bytes(&[5u8; 32]);
+        let (first, second) = chain(&key, &MerkleTree::new());
+        let other = SigningKey::from_bytes(&[6u8; 32])
+            .verifying_key()
+            .to_bytes()
+            .to_vec();
+
+        match verify_checkpoint(second.to_bytes().unwrap(), other) {
+            Err(VerifyError::Rejected { code, .. }) => {
+                assert_eq!(code, ErrorCode::KeyIdMismatch.as_str())
+            }
+            other => panic!("expected a rejection, got {other:?}"),
+        }
+        let public = key.verifying_key().to_bytes().to_vec();
+        assert!(matches!(
+            verify_successor(
+                second.to_bytes().unwrap(),
+                first.to_bytes().unwrap(),
+                public.clone()
+            ),
+            Err(VerifyError::Rejected { .. })
+        ));
+        assert!(matches!(
+            verify_checkpoint(vec![0xff], public),
+            Err(VerifyError::Decode { .. })
+        ));
+        assert!(matches!(
+            verify_checkpoint(first.to_bytes().unwrap(), vec![1, 2]),
+            Err(VerifyError::InvalidKey)
+        ));
+    }
+}
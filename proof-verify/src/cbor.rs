@@ -0,0 +1,115 @@
+//! Just enough canonical CBOR (RFC 8949) to read a checkpoint payload in
+//! place: definite-length items only, nesting bounded so a hostile payload
+//! cannot exhaust a small stack.
+
+use crate::Hash256;
+
+/// Deepest nesting [`Reader::skip`] follows
+const MAX_DEPTH: usize = 16;
+
+const UINT: u8 = 0;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    fn take(&mut self, len: u64) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(usize::try_from(len).ok()?)?;
+        let slice = self.bytes.get(self.pos..end)?;
+        self.pos = end;
+        Some(slice)
+    }
+
+    /// Major type and argument of the next item.
+    fn header(&mut self) -> Option<(u8, u64)> {
+        let initial = *self.take(1)?.first()?;
+        let argument = match initial & 0x1f {
+            info @ 0..=23 => u64::from(info),
+            24 => u64::from(self.take(1)?[0]),
+            25 => u64::from(u16::from_be_bytes(self.take(2)?.try_into().ok()?)),
+            26 => u64::from(u32::from_be_bytes(self.take(4)?.try_into().ok()?)),
+            27 => u64::from_be_bytes(self.take(8)?.try_into().ok()?),
+            // Reserved, or indefinite length (never canonical)
+            _ => return None,
+        };
+        Some((initial >> 5, argument))
+    }
+
+    fn expect(&mut self, major: u8) -> Option<u64> {
+        match self.header()? {
+            (found, argument) if found == major => Some(argument),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn map_len(&mut self) -> Option<u64> {
+        self.expect(MAP)
+    }
+
+    pub(crate) fn uint(&mut self) -> Option<u64> {
+        self.expect(UINT)
+    }
+
+    pub(crate) fn text(&mut self) -> Option<&'a str> {
+        let len = self.expect(TEXT)?;
+        core::str::from_utf8(self.take(len)?).ok()
+    }
+
+    /// A `[u8; 32]`, which serde writes as an array of 32 integers.
+    pub(crate) fn hash(&mut self) -> Option<Hash256> {
+        if self.expect(ARRAY)? != 32 {
+            return None;
+        }
+        let mut hash = [0u8; 32];
+        for byte in &mut hash {
+            *byte = u8::try_from(self.uint()?).ok()?;
+        }
+        Some(hash)
+    }
+
+    /// Step over the next item, whatever it is.
+    pub(crate) fn skip(&mut self) -> Option<()> {
+        self.skip_at(0)
+    }
+
+    fn skip_at(&mut self, depth: usize) -> Option<()> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let (major, argument) = self.header()?;
+        match major {
+            2 | TEXT => {
+                self.take(argument)?;
+            }
+            ARRAY => {
+                for _ in 0..argument {
+                    self.skip_at(depth + 1)?;
+                }
+            }
+            MAP => {
+                for _ in 0..argument {
+                    self.skip_at(depth + 1)?;
+                    self.skip_at(depth + 1)?;
+                }
+            }
+            TAG => self.skip_at(depth + 1)?,
+            // Integers and simple values carry nothing past the header
+            _ => {}
+        }
+        Some(())
+    }
+}
@@ -0,0 +1,237 @@
+//! Checkpoint signature verification.
+//!
+//! The input is the byte string the robot's key signed (canonical CBOR of
+//! every checkpoint field but the signature), so nothing is re-encoded: the
+//! signature is checked over the bytes as received, and the fields a
+//! cross-check needs are then read out of them.
+
+use crate::cbor::Reader;
+use crate::merkle::{verify_inclusion, LeafEntry};
+use crate::{ct_eq, DigestAlgorithm, Error, Hash256};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+/// Fields of a verified checkpoint, borrowed from its signed bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointClaims<'a> {
+    pub hash_alg: DigestAlgorithm,
+    pub robot_id: &'a str,
+    pub sequence: u64,
+    pub monotonic_counter: u64,
+    pub prev_root: Hash256,
+    pub entries_root: Hash256,
+    pub signer_key_id: Hash256,
+    /// Checkpoint hash (what the next checkpoint's `prev_root` must be)
+    pub hash: Hash256,
+}
+
+impl<'a> CheckpointClaims<'a> {
+    /// Read the claims out of signed bytes without checking the signature.
+    fn parse(signed_bytes: &'a [u8]) -> Result<Self, Error> {
+        let mut hash_alg = None;
+        let mut robot_id = None;
+        let mut sequence = None;
+        let mut monotonic_counter = None;
+        let mut prev_root = None;
+        let mut entries_root = None;
+        let mut signer_key_id = None;
+
+        let mut reader = Reader::new(signed_bytes);
+        let fields = reader.map_len().ok_or(Error::Malformed)?;
+        for _ in 0..fields {
+            let key = reader.text().ok_or(Error::Malformed)?;
+            let fresh = match key {
+                "hash_alg" => {
+                    let name = reader.text().ok_or(Error::Malformed)?;
+                    let alg = DigestAlgorithm::from_name(name).ok_or(Error::UnsupportedHashAlg)?;
+                    hash_alg.replace(alg).is_none()
+                }
+                "robot_id" => robot_id
+                    .replace(reader.text().ok_or(Error::Malformed)?)
+                    .is_none(),
+                "sequence" => sequence
+                    .replace(reader.uint().ok_or(Error::Malformed)?)
+                    .is_none(),
+                "monotonic_counter" => monotonic_counter
+                    .replace(reader.uint().ok_or(Error::Malformed)?)
+                    .is_none(),
+                "prev_root" => prev_root
+                    .replace(reader.hash().ok_or(Error::Malformed)?)
+                    .is_none(),
+                "entries_root" => entries_root
+                    .replace(reader.hash().ok_or(Error::Malformed)?)
+                    .is_none(),
+                "signer_key_id" => signer_key_id
+                    .replace(reader.hash().ok_or(Error::Malformed)?)
+                    .is_none(),
+                _ => reader.skip().is_some(),
+            };
+            if !fresh {
+                return Err(Error::Malformed);
+            }
+        }
+        if !reader.is_done() {
+            return Err(Error::Malformed);
+        }
+
+        // `hash_alg` is left out of the encoding when it is SHA-256
+        let hash_alg = hash_alg.unwrap_or_default();
+        Ok(Self {
+            hash_alg,
+            robot_id: robot_id.ok_or(Error::Malformed)?,
+            sequence: sequence.ok_or(Error::Malformed)?,
+            monotonic_counter: monotonic_counter.ok_or(Error::Malformed)?,
+            prev_root: prev_root.ok_or(Error::Malformed)?,
+            entries_root: entries_root.ok_or(Error::Malformed)?,
+            signer_key_id: signer_key_id.ok_or(Error::Malformed)?,
+            hash: hash_alg.digest(&[signed_bytes]),
+        })
+    }
+
+    /// Whether this checkpoint directly succeeds `prev` in the same chain.
+    pub fn follows(&self, prev: &CheckpointClaims<'_>) -> bool {
+        self.robot_id == prev.robot_id
+            && prev.sequence.checked_add(1) == Some(self.sequence)
+            && self.monotonic_counter > prev.monotonic_counter
+            && ct_eq(&self.prev_root, &prev.hash)
+    }
+
+    /// Whether `entry` at `index` is committed under this checkpoint's
+    /// entries root.
+    pub fn includes_entry(&self, entry: &LeafEntry, index: usize, siblings: &[Hash256]) -> bool {
+        verify_inclusion(self.hash_alg, entry, index, siblings, &self.entries_root)
+    }
+}
+
+/// Verify `signature` over a checkpoint's signed bytes with `public_key`,
+/// and that the checkpoint names that key as its signer.
+pub fn verify_checkpoint<'a>(
+    public_key: &[u8; 32],
+    signed_bytes: &'a [u8],
+    signature: &[u8; 64],
+) -> Result<CheckpointClaims<'a>, Error> {
+    let key = VerifyingKey::from_bytes(public_key).map_err(|_| Error::InvalidKey)?;
+    key.verify(signed_bytes, &Signature::from_bytes(signature))
+        .map_err(|_| Error::InvalidSignature)?;
+
+    let claims = CheckpointClaims::parse(signed_bytes)?;
+    if !ct_eq(
+        &claims.signer_key_id,
+        &DigestAlgorithm::Sha256.digest(&[public_key]),
+    ) {
+        return Err(Error::KeyIdMismatch);
+    }
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use attestation_core::{
+        Checkpoint, CheckpointBuilder, DeterminismConfig, Entry, MerkleTree, ModelProvenance,
+        Signer, SigningKey, TrustMode,
+    };
+
+    fn genesis(key: &SigningKey, tree: &MerkleTree) -> Checkpoint {
+        CheckpointBuilder::test_fixture()
+            .hash_alg(tree.hash_alg())
+            .model_provenance(ModelProvenance {
+                name: "planner-v2".to_string(),
+                model_hash: [1u8; 32],
+                dataset_hash: None,
+                container_digest: None,
+                signature_bundle: None,
+            })
+            .firmware_hash([2u8; 32])
+            .enclave_measurement(vec![3u8; 32])
+            .entries_root(tree.root())
+            .inference_config(DeterminismConfig {
+                rng_seed: Some(7),
+                batch_size: 1,
+                flags: None,
+            })
+            .trust_mode(TrustMode::Trusted)
+            .build_and_sign(key)
+            .unwrap()
+    }
+
+    fn verify(checkpoint: &Checkpoint, key: &SigningKey, signed: &[u8]) -> Result<(), Error> {
+        verify_checkpoint(
+            &key.verifying_key().to_bytes(),
+            signed,
+            checkpoint.signature.as_ref(),
+        )
+        .map(|_| ())
+    }
+
+    #[test]
+    fn test_core_checkpoints_verify_and_chain() {
+        let key = SigningKey::from_bytes(&[9u8; 32]);
+        let mut tree = MerkleTree::new().with_hash_alg(attestation_core::DigestAlgorithm::Sha3_256);
+        for i in 0..5u64 {
+            tree.insert(Entry::new(i, 0, b"telemetry"));
+        }
+        let first = genesis(&key, &tree);
+        let second = CheckpointBuilder::continuing_from(&first)
+            .unwrap()
+            .monotonic_counter(2)
+            .entries_root([4u8; 32])
+            .build_and_sign(&key)
+            .unwrap();
+
+        let first_bytes = first.signed_bytes().unwrap();
+        let second_bytes = second.signed_bytes().unwrap();
+        let public = key.verifying_key().to_bytes();
+        let a = verify_checkpoint(&public, &first_bytes, first.signature.as_ref()).unwrap();
+        let b = verify_checkpoint(&public, &second_bytes, second.signature.as_ref()).unwrap();
+
+        assert_eq!(a.hash_alg, DigestAlgorithm::Sha3_256);
+        assert_eq!(a.robot_id, "R-001");
+        assert_eq!(a.hash, first.compute_hash().unwrap());
+        assert!(b.follows(&a));
+        assert!(!a.follows(&b));
+
+        let proof = tree.generate_proof(3, 0).unwrap();
+        let entry = LeafEntry {
+            timestamp_us: proof.leaf.timestamp_us,
+            nonce: proof.leaf.nonce,
+            data_hash: proof.leaf.data_hash,
+        };
+        assert!(a.includes_entry(&entry, proof.leaf_index, &proof.siblings));
+        assert!(!b.includes_entry(&entry, proof.leaf_index, &proof.siblings));
+    }
+
+    #[test]
+    fn test_rejects_tampering_and_wrong_keys() {
+        let key = SigningKey::from_bytes(&[9u8; 32]);
+        let other = SigningKey::from_bytes(&[10u8; 32]);
+        let checkpoint = genesis(&key, &MerkleTree::new());
+        let signed = checkpoint.signed_bytes().unwrap();
+        assert_eq!(verify(&checkpoint, &key, &signed), Ok(()));
+
+        let mut tampered = signed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            verify(&checkpoint, &key, &tampered),
+            Err(Error::InvalidSignature)
+        );
+        assert_eq!(
+            verify(&checkpoint, &other, &signed),
+            Err(Error::InvalidSignature)
+        );
+
+        // Validly signed, but by a key the payload does not name
+        let sign =
+            |key: &SigningKey, message: &[u8]| Signer::new(key.clone()).sign(message).to_bytes();
+        let public = other.verifying_key().to_bytes();
+        assert_eq!(
+            verify_checkpoint(&public, &signed, &sign(&other, &signed)),
+            Err(Error::KeyIdMismatch)
+        );
+
+        let truncated = &signed[..signed.len() - 1];
+        assert_eq!(
+            verify_checkpoint(&public, truncated, &sign(&other, truncated)),
+            Err(Error::Malformed)
+        );
+    }
+}
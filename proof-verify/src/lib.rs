@@ -0,0 +1,119 @@
+//! # Veribot Proof Verify
+//!
+//! The two checks a safety controller needs to cross-check the main
+//! computer's claims, and nothing else:
+//!
+//! - [`verify_checkpoint`]: an Ed25519 signature over a checkpoint's signed
+//!   bytes (`Checkpoint::signed_bytes` in `attestation-core`), returning the
+//!   fields a cross-check needs as [`CheckpointClaims`]
+//! - [`verify_inclusion`]: Merkle inclusion of a log entry under a root
+//!
+//! The crate is `no_std` and never allocates: payloads are read in place,
+//! proofs are slices of sibling hashes. SHA-256 is always available;
+//! SHA3-256 and BLAKE3 (features `sha3`, `blake3`, on by default) can be
+//! dropped on targets whose checkpoints only use SHA-256.
+//!
+//! ```ignore
+//! let claims = verify_checkpoint(&robot_key, &signed_bytes, &signature)?;
+//! assert!(claims.follows(&previous));
+//! assert!(claims.includes_entry(&entry, leaf_index, &siblings));
+//! ```
+
+#![cfg_attr(not(test), no_std)]
+
+mod cbor;
+mod checkpoint;
+mod merkle;
+
+pub use checkpoint::{verify_checkpoint, CheckpointClaims};
+pub use merkle::{leaf_hash, reconstruct_root, verify_inclusion, LeafEntry};
+
+use core::fmt;
+
+/// 32-byte digest
+pub type Hash256 = [u8; 32];
+
+/// Compare two hashes in constant time.
+pub(crate) fn ct_eq(a: &Hash256, b: &Hash256) -> bool {
+    use subtle::ConstantTimeEq;
+    a.ct_eq(b).into()
+}
+
+/// Digest algorithm of a checkpoint and its entries tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    #[default]
+    Sha256,
+    #[cfg(feature = "sha3")]
+    Sha3_256,
+    #[cfg(feature = "blake3")]
+    Blake3,
+}
+
+impl DigestAlgorithm {
+    /// Look up an algorithm by its encoded name (`hash_alg` field).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sha256" => Some(DigestAlgorithm::Sha256),
+            #[cfg(feature = "sha3")]
+            "sha3_256" => Some(DigestAlgorithm::Sha3_256),
+            #[cfg(feature = "blake3")]
+            "blake3" => Some(DigestAlgorithm::Blake3),
+            _ => None,
+        }
+    }
+
+    /// Hash the concatenation of `parts`.
+    pub fn digest(&self, parts: &[&[u8]]) -> Hash256 {
+        match self {
+            DigestAlgorithm::Sha256 => {
+                use sha2::{Digest, Sha256};
+                let mut hasher = Sha256::new();
+                parts.iter().for_each(|part| hasher.update(part));
+                hasher.finalize().into()
+            }
+            #[cfg(feature = "sha3")]
+            DigestAlgorithm::Sha3_256 => {
+                use sha3::{Digest, Sha3_256};
+                let mut hasher = Sha3_256::new();
+                parts.iter().for_each(|part| hasher.update(part));
+                hasher.finalize().into()
+            }
+            #[cfg(feature = "blake3")]
+            DigestAlgorithm::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                parts.iter().for_each(|part| {
+                    hasher.update(part);
+                });
+                *hasher.finalize().as_bytes()
+            }
+        }
+    }
+}
+
+/// Why a checkpoint was not accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The public key is not a valid Ed25519 point
+    InvalidKey,
+    /// The signature does not verify over the signed bytes
+    InvalidSignature,
+    /// The signed bytes name a different signer key
+    KeyIdMismatch,
+    /// The signed bytes are not a checkpoint payload
+    Malformed,
+    /// The checkpoint's `hash_alg` is unknown or not compiled in
+    UnsupportedHashAlg,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Error::InvalidKey => "invalid Ed25519 public key",
+            Error::InvalidSignature => "invalid checkpoint signature",
+            Error::KeyIdMismatch => "checkpoint names a different signer key",
+            Error::Malformed => "malformed checkpoint payload",
+            Error::UnsupportedHashAlg => "unsupported checkpoint hash algorithm",
+        })
+    }
+}
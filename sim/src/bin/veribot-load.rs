@@ -0,0 +1,150 @@
+//! Load generator for checkpoint submission endpoints.
+//!
+//! Submits checkpoints at a fixed rate and prints a JSON [`LoadReport`] with
+//! latency percentiles, throughput and error rates. Checkpoints come from a
+//! synthetic fleet (`--robots`, `--checkpoints`, `--seed`) or are replayed
+//! from chain archive files (`--replay`), interleaved by timestamp.
+//!
+//! ```text
+//! veribot-load --target http://gateway:8080 --robots 1000 --rate 500
+//! veribot-load --robots 100 --rate 2000            # in-process dry run
+//! veribot-load --target http://gateway:8080 --replay archives/*.cbor
+//! ```
+//!
+//! Exits 1 when any submission failed outright.
+
+use anyhow::{bail, Context, Result};
+use attestation_core::serialization::from_canonical_cbor;
+use attestation_core::Checkpoint;
+use clap::Parser;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use veribot_sim::load::{run_load, HttpTarget, LoadConfig, LoadReport, LocalTarget, SubmitTarget};
+use veribot_sim::{Fleet, FleetConfig};
+
+#[derive(Parser)]
+#[command(
+    name = "veribot-load",
+    version,
+    about = "Load generator for checkpoint submission endpoints"
+)]
+struct Args {
+    /// Gateway base URL; without it, submissions are verified in process
+    #[arg(long)]
+    target: Option<String>,
+
+    /// Submissions per second
+    #[arg(long, default_value_t = 100.0)]
+    rate: f64,
+
+    /// Most requests in flight at once
+    #[arg(long, default_value_t = 64)]
+    concurrency: usize,
+
+    /// Per-request timeout in seconds
+    #[arg(long, default_value_t = 10)]
+    timeout_secs: u64,
+
+    /// Replay checkpoints from chain archive files instead of synthesizing
+    #[arg(long, num_args = 1.., conflicts_with_all = ["robots", "checkpoints", "seed"])]
+    replay: Vec<PathBuf>,
+
+    /// Robots in the synthetic fleet
+    #[arg(long, default_value_t = 100)]
+    robots: usize,
+
+    /// Checkpoints per synthetic robot
+    #[arg(long, default_value_t = 10)]
+    checkpoints: u64,
+
+    /// Seed of the synthetic fleet
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+/// The part of a chain archive the load generator needs.
+#[derive(Deserialize)]
+struct Archive {
+    checkpoints: Vec<Checkpoint>,
+}
+
+fn replay(paths: &[PathBuf]) -> Result<Vec<Checkpoint>> {
+    let mut checkpoints = Vec::new();
+    for path in paths {
+        let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        let archive: Archive = from_canonical_cbor(&bytes)
+            .with_context(|| format!("decoding archive {}", path.display()))?;
+        checkpoints.extend(archive.checkpoints);
+    }
+    // Stable, so each robot's chain keeps its order
+    checkpoints.sort_by_key(|c| c.local_timestamp_utc);
+    Ok(checkpoints)
+}
+
+async fn run(args: Args) -> Result<LoadReport> {
+    if args.rate.is_nan() || args.rate <= 0.0 {
+        bail!("--rate must be positive");
+    }
+    let config = LoadConfig {
+        rate_per_sec: args.rate,
+        concurrency: args.concurrency,
+    };
+
+    let (checkpoints, keys) = if args.replay.is_empty() {
+        let fleet = Fleet::generate(&FleetConfig {
+            robots: args.robots,
+            checkpoints_per_robot: args.checkpoints,
+            seed: args.seed,
+            ..Default::default()
+        })?;
+        let keys: HashMap<_, _> = fleet
+            .robots
+            .iter()
+            .map(|robot| (robot.robot_id.clone(), robot.signing_key.verifying_key()))
+            .collect();
+        let checkpoints = fleet
+            .submissions()
+            .into_iter()
+            .map(|(_, submission)| submission.checkpoint.clone())
+            .collect();
+        (checkpoints, Some(keys))
+    } else {
+        (replay(&args.replay)?, None)
+    };
+
+    let target: Arc<dyn SubmitTarget> = match (&args.target, keys) {
+        (Some(url), _) => Arc::new(HttpTarget::new(
+            url,
+            Duration::from_secs(args.timeout_secs),
+        )?),
+        (None, Some(keys)) => Arc::new(LocalTarget::new(keys)),
+        (None, None) => bail!("--replay needs --target (archives carry no robot keys)"),
+    };
+
+    Ok(run_load(target, checkpoints, config).await)
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Args::parse()).await {
+        Ok(report) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report).expect("report serializes")
+            );
+            if report.failed > 0 {
+                ExitCode::FAILURE
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
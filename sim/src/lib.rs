@@ -0,0 +1,476 @@
+//! # Veribot Sim
+//!
+//! Synthetic fleets for load-testing gateways and validating detection logic.
+//!
+//! [`Fleet::generate`] turns a [`FleetConfig`] into N robots, each with its
+//! own signing key and a chain of checkpoints over Merkle trees of synthetic
+//! entries. Entry rates, checkpoint cadence and hardware counter skips are
+//! configurable; everything is drawn from a seeded RNG, so the same config
+//! yields the same fleet (for a given `rand` version).
+//!
+//! ## Fault injection
+//! A [`Fault`] adds one malicious or broken submission to a robot's stream,
+//! right after an honest checkpoint. Each injected submission records the
+//! error code a gateway is expected to reject it with, and the honest chain
+//! carries on after it, so detection logic can be checked end to end:
+//!
+//! - [`Fault::Rollback`]: the robot's state is restored from an earlier
+//!   snapshot and it re-signs from there (`VB-CHK` sequence regression)
+//! - [`Fault::ClockJump`]: the robot's clock jumps by a fixed offset; a jump
+//!   backwards is a timestamp regression, a jump forwards is caught by the
+//!   gateway's clock skew policy
+//! - [`Fault::KeyCompromise`]: an attacker holding the robot's key signs a
+//!   second checkpoint for an already accepted sequence (equivocation)
+//!
+//! ## Load testing
+//! [`load`] replays submissions against a gateway at a fixed rate and reports
+//! latency percentiles and error rates; the `veribot-load` binary drives it
+//! from the command line.
+
+pub mod load;
+
+use attestation_core::{
+    Checkpoint, CheckpointBuilder, DeterminismConfig, Entry, ErrorCode, Hash256, MerkleTree,
+    MissionId, ModelProvenance, RobotId, TrustMode,
+};
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::SigningKey;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use thiserror::Error;
+
+/// Shape of a synthetic fleet.
+#[derive(Debug, Clone)]
+pub struct FleetConfig {
+    pub robots: usize,
+    /// Honest checkpoints per robot
+    pub checkpoints_per_robot: u64,
+    /// Time between a robot's checkpoints
+    pub checkpoint_interval: Duration,
+    /// Mean logged entries per second per robot
+    pub entries_per_second: f64,
+    /// Largest number of hardware counter increments skipped between
+    /// checkpoints (counters need not advance by exactly one)
+    pub max_counter_skip: u64,
+    /// Time of every robot's first checkpoint
+    pub start: DateTime<Utc>,
+    pub seed: u64,
+    pub faults: Vec<Fault>,
+}
+
+impl Default for FleetConfig {
+    fn default() -> Self {
+        Self {
+            robots: 10,
+            checkpoints_per_robot: 100,
+            checkpoint_interval: Duration::seconds(60),
+            entries_per_second: 5.0,
+            max_counter_skip: 2,
+            start: DateTime::UNIX_EPOCH + Duration::days(20_000),
+            seed: 0,
+            faults: Vec::new(),
+        }
+    }
+}
+
+/// A fault injected into one robot's submissions after its honest
+/// checkpoint `after_sequence`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Restore the robot's state from checkpoint `to_sequence` and sign the
+    /// checkpoint that would have followed it.
+    Rollback {
+        robot: usize,
+        after_sequence: u64,
+        to_sequence: u64,
+    },
+    /// Sign the next checkpoint with the clock moved by `by`.
+    ClockJump {
+        robot: usize,
+        after_sequence: u64,
+        by: Duration,
+    },
+    /// Sign a conflicting checkpoint for `after_sequence` (not genesis) with
+    /// the robot's key.
+    KeyCompromise { robot: usize, after_sequence: u64 },
+}
+
+impl Fault {
+    pub fn robot(&self) -> usize {
+        match *self {
+            Fault::Rollback { robot, .. }
+            | Fault::ClockJump { robot, .. }
+            | Fault::KeyCompromise { robot, .. } => robot,
+        }
+    }
+
+    pub fn after_sequence(&self) -> u64 {
+        match *self {
+            Fault::Rollback { after_sequence, .. }
+            | Fault::ClockJump { after_sequence, .. }
+            | Fault::KeyCompromise { after_sequence, .. } => after_sequence,
+        }
+    }
+
+    /// Code a gateway should reject the injected submission with.
+    pub fn expected_code(&self) -> ErrorCode {
+        match *self {
+            Fault::Rollback { .. } | Fault::KeyCompromise { .. } => ErrorCode::SequenceRegression,
+            Fault::ClockJump { by, .. } if by <= Duration::zero() => ErrorCode::TimestampRegression,
+            Fault::ClockJump { .. } => ErrorCode::ClockSkewExceeded,
+        }
+    }
+}
+
+/// One checkpoint as a robot would submit it.
+#[derive(Debug, Clone)]
+pub struct Submission {
+    pub checkpoint: Checkpoint,
+    /// Entries under `checkpoint.entries_root`
+    pub entries: Vec<Entry>,
+    /// When the gateway receives it (the robot's true time)
+    pub submitted_at: DateTime<Utc>,
+    /// The fault this submission injects (`None` for honest checkpoints)
+    pub fault: Option<Fault>,
+}
+
+/// A simulated robot and everything it submits, in order.
+#[derive(Debug, Clone)]
+pub struct SimulatedRobot {
+    pub robot_id: RobotId,
+    pub signing_key: SigningKey,
+    pub submissions: Vec<Submission>,
+}
+
+impl SimulatedRobot {
+    /// The honest checkpoints, without injected faults.
+    pub fn honest_chain(&self) -> Vec<Checkpoint> {
+        self.submissions
+            .iter()
+            .filter(|submission| submission.fault.is_none())
+            .map(|submission| submission.checkpoint.clone())
+            .collect()
+    }
+}
+
+/// A synthetic fleet.
+#[derive(Debug, Clone)]
+pub struct Fleet {
+    pub robots: Vec<SimulatedRobot>,
+}
+
+impl Fleet {
+    /// Generate the fleet described by `config`.
+    pub fn generate(config: &FleetConfig) -> Result<Self, SimError> {
+        if config.checkpoint_interval <= Duration::zero() {
+            return Err(SimError::InvalidConfig(
+                "checkpoint_interval must be positive",
+            ));
+        }
+        if !(config.entries_per_second >= 0.0 && config.entries_per_second.is_finite()) {
+            return Err(SimError::InvalidConfig(
+                "entries_per_second must be finite and non-negative",
+            ));
+        }
+        for fault in &config.faults {
+            check_fault(fault, config)?;
+        }
+
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let robots = (0..config.robots)
+            .map(|index| generate_robot(index, config, &mut rng))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { robots })
+    }
+
+    /// Every submission of the fleet in the order a gateway receives them.
+    pub fn submissions(&self) -> Vec<(usize, &Submission)> {
+        let mut all: Vec<(usize, &Submission)> = self
+            .robots
+            .iter()
+            .enumerate()
+            .flat_map(|(index, robot)| robot.submissions.iter().map(move |s| (index, s)))
+            .collect();
+        // Stable: a robot's own submissions keep their order on ties
+        all.sort_by_key(|(_, submission)| submission.submitted_at);
+        all
+    }
+}
+
+fn check_fault(fault: &Fault, config: &FleetConfig) -> Result<(), SimError> {
+    let in_range = fault.robot() < config.robots
+        && fault.after_sequence() < config.checkpoints_per_robot
+        && match *fault {
+            Fault::Rollback {
+                after_sequence,
+                to_sequence,
+                ..
+            } => to_sequence < after_sequence,
+            // Equivocating on genesis would need a second identity setup
+            Fault::KeyCompromise { after_sequence, .. } => after_sequence > 0,
+            Fault::ClockJump { .. } => true,
+        };
+    if in_range {
+        Ok(())
+    } else {
+        Err(SimError::FaultOutOfRange(*fault))
+    }
+}
+
+fn generate_robot(
+    index: usize,
+    config: &FleetConfig,
+    rng: &mut StdRng,
+) -> Result<SimulatedRobot, SimError> {
+    let signing_key = SigningKey::from_bytes(&rng.gen());
+    let robot_id = RobotId(format!("SIM-{index:05}"));
+    let interval_us = config
+        .checkpoint_interval
+        .num_microseconds()
+        .unwrap_or(i64::MAX);
+
+    let mut submissions: Vec<Submission> = Vec::new();
+    let mut honest: Vec<Checkpoint> = Vec::new();
+    let mut nonce = 0u64;
+    for sequence in 0..config.checkpoints_per_robot {
+        let window_start = config.start + config.checkpoint_interval * sequence as i32;
+        let window_end = window_start + config.checkpoint_interval;
+
+        // Entry count dithered so the long-run mean matches the rate
+        let expected = config.entries_per_second * interval_us as f64 / 1e6;
+        let count = (expected + rng.gen::<f64>()).floor() as u64;
+        let start_us = window_start.timestamp_micros() as u64;
+        let mut tree = MerkleTree::new();
+        for _ in 0..count {
+            nonce += 1;
+            tree.insert(Entry {
+                timestamp_us: start_us + rng.gen_range(0..interval_us.max(1) as u64),
+                nonce,
+                data_hash: rng.gen(),
+            });
+        }
+        let entries: Vec<Entry> = tree.entries().into_iter().cloned().collect();
+
+        let counter_step = 1 + rng.gen_range(0..=config.max_counter_skip);
+        let builder = match honest.last() {
+            Some(prev) => CheckpointBuilder::continuing_from(prev)?
+                .monotonic_counter(prev.monotonic_counter + counter_step),
+            None => genesis(&robot_id, index).monotonic_counter(counter_step),
+        };
+        let checkpoint = builder
+            .timestamp(window_end)
+            .entries_root(tree.root())
+            .build_and_sign(&signing_key)?;
+
+        submissions.push(Submission {
+            checkpoint: checkpoint.clone(),
+            entries,
+            submitted_at: window_end,
+            fault: None,
+        });
+        honest.push(checkpoint);
+
+        for fault in config
+            .faults
+            .iter()
+            .filter(|f| f.robot() == index && f.after_sequence() == sequence)
+        {
+            let injected = inject(fault, &honest, &signing_key, rng)?;
+            submissions.push(Submission {
+                checkpoint: injected,
+                entries: Vec::new(),
+                submitted_at: window_end,
+                fault: Some(*fault),
+            });
+        }
+    }
+
+    Ok(SimulatedRobot {
+        robot_id,
+        signing_key,
+        submissions,
+    })
+}
+
+fn genesis(robot_id: &RobotId, index: usize) -> CheckpointBuilder {
+    CheckpointBuilder::new()
+        .robot_id(robot_id.clone())
+        .mission_id(MissionId(format!("SIM-M-{index:05}")))
+        .sequence(0)
+        .model_provenance(ModelProvenance {
+            name: "sim-policy-v1".to_string(),
+            model_hash: [0x51; 32],
+            dataset_hash: None,
+            container_digest: None,
+            signature_bundle: None,
+        })
+        .firmware_hash([0x52; 32])
+        .enclave_measurement(vec![0x53; 32])
+        .prev_root([0u8; 32])
+        .inference_config(DeterminismConfig {
+            rng_seed: Some(index as u64),
+            batch_size: 1,
+            flags: None,
+        })
+        .trust_mode(TrustMode::Trusted)
+}
+
+/// Build the faulty checkpoint for `fault`; `honest` ends with the checkpoint
+/// it follows.
+fn inject(
+    fault: &Fault,
+    honest: &[Checkpoint],
+    signing_key: &SigningKey,
+    rng: &mut StdRng,
+) -> Result<Checkpoint, SimError> {
+    let head = honest.last().expect("faults follow an honest checkpoint");
+    let forged_root: Hash256 = rng.gen();
+    let checkpoint = match *fault {
+        Fault::Rollback { to_sequence, .. } => {
+            let snapshot = &honest[to_sequence as usize];
+            CheckpointBuilder::continuing_from(snapshot)?
+                .monotonic_counter(snapshot.monotonic_counter + 1)
+                .timestamp(head.local_timestamp_utc + Duration::seconds(1))
+                .entries_root(forged_root)
+                .build_and_sign(signing_key)?
+        }
+        Fault::ClockJump { by, .. } => CheckpointBuilder::continuing_from(head)?
+            .monotonic_counter(head.monotonic_counter + 1)
+            .timestamp(head.local_timestamp_utc + by)
+            .entries_root(forged_root)
+            .build_and_sign(signing_key)?,
+        Fault::KeyCompromise { .. } => {
+            let prev = &honest[honest.len() - 2];
+            CheckpointBuilder::continuing_from(prev)?
+                .monotonic_counter(head.monotonic_counter)
+                .timestamp(head.local_timestamp_utc)
+                .entries_root(forged_root)
+                .build_and_sign(signing_key)?
+        }
+    };
+    Ok(checkpoint)
+}
+
+#[derive(Debug, Error)]
+pub enum SimError {
+    #[error("Invalid fleet config: {0}")]
+    InvalidConfig(&'static str),
+
+    #[error("Fault {0:?} targets a robot or sequence outside the fleet")]
+    FaultOutOfRange(Fault),
+
+    #[error("Checkpoint construction failed: {0}")]
+    Build(#[from] attestation_core::checkpoint::BuildError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use attestation_core::{ChainVerifier, ClockSkewPolicy, ErrorCoded, Signer};
+
+    fn config(faults: Vec<Fault>) -> FleetConfig {
+        FleetConfig {
+            robots: 3,
+            checkpoints_per_robot: 12,
+            seed: 7,
+            faults,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_honest_fleet_verifies_and_is_reproducible() {
+        let fleet = Fleet::generate(&config(Vec::new())).unwrap();
+        assert_eq!(fleet.robots.len(), 3);
+        for robot in &fleet.robots {
+            let mut verifier = ChainVerifier::new(robot.signing_key.verifying_key());
+            verifier.verify_chain(&robot.honest_chain()).unwrap();
+            for submission in &robot.submissions {
+                let mut tree = MerkleTree::new();
+                submission.entries.iter().for_each(|entry| {
+                    tree.insert(entry.clone());
+                });
+                assert_eq!(tree.root(), submission.checkpoint.entries_root);
+            }
+        }
+        let entries: usize = fleet.robots[0]
+            .submissions
+            .iter()
+            .map(|s| s.entries.len())
+            .sum();
+        assert_eq!(entries, 5 * 60 * 12);
+
+        let again = Fleet::generate(&config(Vec::new())).unwrap();
+        assert_eq!(
+            again.robots[2].honest_chain(),
+            fleet.robots[2].honest_chain()
+        );
+        assert_eq!(fleet.submissions().len(), 36);
+    }
+
+    #[test]
+    fn test_injected_faults_are_detected() {
+        let faults = vec![
+            Fault::Rollback {
+                robot: 0,
+                after_sequence: 5,
+                to_sequence: 2,
+            },
+            Fault::ClockJump {
+                robot: 1,
+                after_sequence: 3,
+                by: Duration::seconds(-30),
+            },
+            Fault::ClockJump {
+                robot: 1,
+                after_sequence: 8,
+                by: Duration::hours(2),
+            },
+            Fault::KeyCompromise {
+                robot: 2,
+                after_sequence: 1,
+            },
+            Fault::KeyCompromise {
+                robot: 2,
+                after_sequence: 4,
+            },
+        ];
+        let fleet = Fleet::generate(&config(faults.clone())).unwrap();
+
+        let gateway = Signer::generate();
+        let mut detected = Vec::new();
+        for robot in &fleet.robots {
+            let mut verifier = ChainVerifier::new(robot.signing_key.verifying_key());
+            for submission in &robot.submissions {
+                let skew = ClockSkewPolicy::default()
+                    .decide(&submission.checkpoint, submission.submitted_at, &gateway)
+                    .unwrap();
+                let outcome = if skew.accepted {
+                    verifier
+                        .verify_next(&submission.checkpoint)
+                        .map_err(|e| e.code())
+                } else {
+                    Err(ErrorCode::ClockSkewExceeded)
+                };
+                match submission.fault {
+                    None => assert!(outcome.is_ok(), "honest chain continues after faults"),
+                    Some(fault) => detected.push((fault, outcome.unwrap_err())),
+                }
+            }
+        }
+        assert_eq!(detected.len(), faults.len());
+        for (fault, code) in detected {
+            assert_eq!(code, fault.expected_code(), "{fault:?}");
+        }
+
+        let bad = Fault::Rollback {
+            robot: 0,
+            after_sequence: 2,
+            to_sequence: 2,
+        };
+        assert!(matches!(
+            Fleet::generate(&config(vec![bad])),
+            Err(SimError::FaultOutOfRange(_))
+        ));
+    }
+}
@@ -0,0 +1,324 @@
+//! Open-loop load generation against a checkpoint submission endpoint.
+//!
+//! [`run_load`] sends submissions at a fixed rate regardless of how fast the
+//! target answers (so a slow gateway shows up as rising latency, not as a
+//! lower offered rate), caps in-flight requests at `concurrency`, and
+//! summarizes latency percentiles and outcomes in a [`LoadReport`].
+//!
+//! Targets implement [`SubmitTarget`]: [`HttpTarget`] posts canonical CBOR
+//! to a gateway, [`LocalTarget`] verifies in process with
+//! [`ChainVerifier`]s, for dry runs and for measuring verification cost alone.
+
+use async_trait::async_trait;
+use attestation_core::{ChainVerifier, Checkpoint, ErrorCoded, RobotId, VerifyingKey};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::time::Instant;
+
+/// How a target answered one submission.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Accepted,
+    /// The target understood and refused the checkpoint (reason: error code
+    /// or HTTP status)
+    Rejected(String),
+    /// The request itself failed (transport error, 5xx)
+    Failed(String),
+}
+
+/// Endpoint receiving checkpoint submissions.
+#[async_trait]
+pub trait SubmitTarget: Send + Sync {
+    async fn submit(&self, checkpoint: &Checkpoint) -> Outcome;
+}
+
+/// Gateway HTTP API: `POST {base_url}/v1/checkpoints` with the checkpoint
+/// as canonical CBOR (`application/cbor`).
+///
+/// 2xx is an acceptance, 4xx a rejection (reported with the `code` field of
+/// a JSON error body when present), anything else a failure.
+pub struct HttpTarget {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpTarget {
+    pub fn new(base_url: &str, timeout: Duration) -> reqwest::Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(timeout).build()?,
+            url: format!("{}/v1/checkpoints", base_url.trim_end_matches('/')),
+        })
+    }
+}
+
+#[async_trait]
+impl SubmitTarget for HttpTarget {
+    async fn submit(&self, checkpoint: &Checkpoint) -> Outcome {
+        let body = match checkpoint.to_bytes() {
+            Ok(body) => body,
+            Err(e) => return Outcome::Failed(e.to_string()),
+        };
+        let response = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/cbor")
+            .body(body)
+            .send()
+            .await;
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => return Outcome::Failed(e.to_string()),
+        };
+
+        let status = response.status();
+        if status.is_success() {
+            Outcome::Accepted
+        } else if status.is_client_error() {
+            let code = response
+                .json::<serde_json::Value>()
+                .await
+                .ok()
+                .and_then(|body| body.get("code")?.as_str().map(str::to_string));
+            Outcome::Rejected(code.unwrap_or_else(|| status.as_u16().to_string()))
+        } else {
+            Outcome::Failed(format!("HTTP {}", status.as_u16()))
+        }
+    }
+}
+
+/// In-process verification, one chain verifier per robot.
+pub struct LocalTarget {
+    chains: Mutex<HashMap<RobotId, ChainVerifier>>,
+    keys: HashMap<RobotId, VerifyingKey>,
+}
+
+impl LocalTarget {
+    /// Verify each robot's chain against its key.
+    pub fn new(keys: HashMap<RobotId, VerifyingKey>) -> Self {
+        Self {
+            chains: Mutex::new(HashMap::new()),
+            keys,
+        }
+    }
+}
+
+#[async_trait]
+impl SubmitTarget for LocalTarget {
+    async fn submit(&self, checkpoint: &Checkpoint) -> Outcome {
+        let Some(key) = self.keys.get(&checkpoint.robot_id) else {
+            return Outcome::Rejected("unknown robot".to_string());
+        };
+        let mut chains = self.chains.lock().unwrap_or_else(|e| e.into_inner());
+        let verifier = chains
+            .entry(checkpoint.robot_id.clone())
+            .or_insert_with(|| ChainVerifier::new(*key));
+        match verifier.verify_next(checkpoint) {
+            Ok(()) => Outcome::Accepted,
+            Err(e) => Outcome::Rejected(e.code().as_str().to_string()),
+        }
+    }
+}
+
+/// Pacing of a load run.
+#[derive(Debug, Clone, Copy)]
+pub struct LoadConfig {
+    /// Submissions started per second
+    pub rate_per_sec: f64,
+    /// Most requests in flight at once; a submission due while the cap is
+    /// reached waits (and its wait counts towards its latency)
+    pub concurrency: usize,
+}
+
+/// Latency distribution in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Latency {
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl Latency {
+    /// Nearest-rank percentiles of `samples`.
+    pub fn from_samples(samples: &mut [Duration]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_unstable();
+        let at = |p: f64| {
+            let rank = ((p / 100.0) * samples.len() as f64).ceil() as usize;
+            samples[rank.clamp(1, samples.len()) - 1].as_secs_f64() * 1e3
+        };
+        Self {
+            p50_ms: at(50.0),
+            p90_ms: at(90.0),
+            p99_ms: at(99.0),
+            max_ms: at(100.0),
+        }
+    }
+}
+
+/// Summary of a load run.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadReport {
+    pub sent: usize,
+    pub accepted: usize,
+    pub rejected: usize,
+    pub failed: usize,
+    /// Share of submissions that failed outright (rejections are answers)
+    pub error_rate: f64,
+    pub elapsed_secs: f64,
+    /// Submissions completed per second
+    pub throughput: f64,
+    pub latency: Latency,
+    /// Rejections by reason
+    pub rejections: BTreeMap<String, usize>,
+    /// Failures by reason
+    pub failures: BTreeMap<String, usize>,
+}
+
+/// Submit `checkpoints` in order to `target` at the configured rate.
+pub async fn run_load(
+    target: Arc<dyn SubmitTarget>,
+    checkpoints: Vec<Checkpoint>,
+    config: LoadConfig,
+) -> LoadReport {
+    let period = Duration::from_secs_f64(1.0 / config.rate_per_sec.max(1e-6));
+    let permits = Arc::new(Semaphore::new(config.concurrency.max(1)));
+    let mut ticker = tokio::time::interval(period);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Burst);
+
+    let started = Instant::now();
+    let mut tasks = Vec::with_capacity(checkpoints.len());
+    for checkpoint in checkpoints {
+        ticker.tick().await;
+        let due = Instant::now();
+        let target = target.clone();
+        let permits = permits.clone();
+        tasks.push(tokio::spawn(async move {
+            let _permit = permits
+                .acquire_owned()
+                .await
+                .expect("semaphore is never closed");
+            let outcome = target.submit(&checkpoint).await;
+            (due.elapsed(), outcome)
+        }));
+    }
+
+    let mut latencies = Vec::with_capacity(tasks.len());
+    let mut report = LoadReport {
+        sent: tasks.len(),
+        accepted: 0,
+        rejected: 0,
+        failed: 0,
+        error_rate: 0.0,
+        elapsed_secs: 0.0,
+        throughput: 0.0,
+        latency: Latency::default(),
+        rejections: BTreeMap::new(),
+        failures: BTreeMap::new(),
+    };
+    for task in tasks {
+        let (latency, outcome) = match task.await {
+            Ok(done) => done,
+            Err(e) => (
+                Duration::ZERO,
+                Outcome::Failed(format!("task panicked: {e}")),
+            ),
+        };
+        latencies.push(latency);
+        match outcome {
+            Outcome::Accepted => report.accepted += 1,
+            Outcome::Rejected(reason) => {
+                report.rejected += 1;
+                *report.rejections.entry(reason).or_default() += 1;
+            }
+            Outcome::Failed(reason) => {
+                report.failed += 1;
+                *report.failures.entry(reason).or_default() += 1;
+            }
+        }
+    }
+
+    let elapsed = started.elapsed().as_secs_f64();
+    report.elapsed_secs = elapsed;
+    if report.sent > 0 {
+        report.error_rate = report.failed as f64 / report.sent as f64;
+        report.throughput = report.sent as f64 / elapsed.max(f64::EPSILON);
+    }
+    report.latency = Latency::from_samples(&mut latencies);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Fault, Fleet, FleetConfig};
+    use attestation_core::ErrorCode;
+
+    #[test]
+    fn test_nearest_rank_percentiles() {
+        let mut samples: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        let latency = Latency::from_samples(&mut samples);
+        assert_eq!(
+            latency,
+            Latency {
+                p50_ms: 50.0,
+                p90_ms: 90.0,
+                p99_ms: 99.0,
+                max_ms: 100.0
+            }
+        );
+        assert_eq!(Latency::from_samples(&mut []), Latency::default());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_local_run_counts_outcomes() {
+        let config = FleetConfig {
+            robots: 4,
+            checkpoints_per_robot: 10,
+            entries_per_second: 0.0,
+            faults: vec![Fault::Rollback {
+                robot: 1,
+                after_sequence: 6,
+                to_sequence: 3,
+            }],
+            ..Default::default()
+        };
+        let fleet = Fleet::generate(&config).unwrap();
+        let keys = fleet
+            .robots
+            .iter()
+            .map(|robot| (robot.robot_id.clone(), robot.signing_key.verifying_key()))
+            .collect();
+        let checkpoints: Vec<Checkpoint> = fleet
+            .submissions()
+            .into_iter()
+            .map(|(_, submission)| submission.checkpoint.clone())
+            .collect();
+
+        let load = LoadConfig {
+            rate_per_sec: 100.0,
+            concurrency: 1,
+        };
+        let report = run_load(Arc::new(LocalTarget::new(keys)), checkpoints, load).await;
+        assert_eq!(report.sent, 41);
+        assert_eq!(report.accepted, 40);
+        assert_eq!(
+            report
+                .rejections
+                .get(ErrorCode::SequenceRegression.as_str()),
+            Some(&1)
+        );
+        assert_eq!(report.error_rate, 0.0);
+        // 41 submissions at 100/s under paused time: the first is immediate
+        assert!(
+            (report.elapsed_secs - 0.4).abs() < 1e-3,
+            "{}",
+            report.elapsed_secs
+        );
+    }
+}
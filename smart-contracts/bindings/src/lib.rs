@@ -0,0 +1,124 @@
+//! Typed ethers-rs bindings for the Veribot smart contracts.
+//!
+//! Generated by `abigen!` from the human-readable ABI of
+//! `contracts/RobotAttestationRegistry.sol`; keep the declarations below in
+//! sync with the contract. The tests decode calldata produced by
+//! `attestation_core::abi` with these bindings (and vice versa), so the
+//! off-chain encoders cannot drift from the contract interface.
+
+use ethers_contract::abigen;
+
+abigen!(
+    RobotAttestationRegistry,
+    r#"[
+        struct Entry { uint64 timestampUs; uint64 nonce; bytes32 dataHash; }
+        struct Checkpoint { bytes32 merkleRoot; bytes32 enclaveMeasurement; address gateway; uint256 timestamp; string vendor; bytes gatewaySignature; }
+        function registerModel(string name, bytes32 modelHash, bytes32 datasetHash, string containerDigest, bytes signatureBundle) external
+        function revokeModel(bytes32 modelHash) external
+        function anchorCheckpoint(bytes32 merkleRoot, bytes32 enclaveMeasurement, string vendor, bytes gatewaySignature) external returns (bytes32 checkpointId)
+        function verifyCheckpoint(bytes32 checkpointId) external view returns (bool valid)
+        function verifyEntry(bytes32 checkpointId, Entry entry, uint256 leafIndex, bytes32[] siblings) external view returns (bool valid)
+        function emergencyRevokeEnclave(bytes32 enclaveMeasurement, string reason) external
+        function reinstateEnclave(bytes32 enclaveMeasurement) external
+        function getCheckpoint(bytes32 checkpointId) external view returns (Checkpoint cp)
+        function isEnclaveRevoked(bytes32 enclaveMeasurement) external view returns (bool revoked)
+        function isModelRevoked(bytes32 modelHash) external view returns (bool revoked)
+        function addGateway(address gateway) external
+        function removeGateway(address gateway) external
+        event ModelRegistered(bytes32 indexed modelHash, string name, address indexed registeredBy, uint256 timestamp)
+        event ModelRevoked(bytes32 indexed modelHash, address indexed revokedBy, uint256 timestamp)
+        event CheckpointAnchored(bytes32 indexed checkpointId, bytes32 indexed merkleRoot, bytes32 indexed enclaveMeasurement, address gateway, string vendor, uint256 timestamp)
+        event EnclaveRevoked(bytes32 indexed enclaveMeasurement, address indexed revokedBy, string reason, uint256 timestamp)
+        event EnclaveReinstated(bytes32 indexed enclaveMeasurement, address indexed reinstatedBy, uint256 timestamp)
+    ]"#
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use attestation_core::abi;
+    use attestation_core::{CheckpointBuilder, Entry as LogEntry, MerkleTree, Signer};
+    use ethers_core::abi::{encode_packed, AbiDecode, AbiEncode, Token};
+    use ethers_core::types::{Address, U256};
+
+    /// Shared vector, also asserted in `test/RobotAttestationRegistry.t.sol`
+    fn vector_tree() -> MerkleTree {
+        let mut tree = MerkleTree::new();
+        for i in 0..3u64 {
+            tree.insert(LogEntry::new(1000 * (i + 1), i, &[i as u8]));
+        }
+        tree
+    }
+
+    #[test]
+    fn test_shared_vector() {
+        let tree = vector_tree();
+        let proof = tree.generate_proof(3000, 2).unwrap();
+        assert_eq!(
+            hex::encode(tree.root()),
+            "672b8e2f6b7b933ca2b9ff72e61579bda48c320aa14f32051f148b9683482010"
+        );
+        assert_eq!(
+            proof.siblings.iter().map(hex::encode).collect::<Vec<_>>(),
+            [
+                "5a696aa0e1b915594e420af8457530e6c4c06454d61df98598d258ce37b198cc",
+                "efecdce80ffffdce87322532cc1f03e97cf9055fd768c96f43fa3b8d0b673932",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_entry_roundtrip() {
+        let proof = vector_tree().generate_proof(3000, 2).unwrap();
+        let calldata = abi::verify_entry_calldata(&[7u8; 32], &proof);
+
+        let call = VerifyEntryCall::decode(&calldata).unwrap();
+        assert_eq!(call.checkpoint_id, [7u8; 32]);
+        assert_eq!(call.entry.timestamp_us, 3000);
+        assert_eq!(call.entry.nonce, 2);
+        assert_eq!(call.entry.data_hash, proof.leaf.data_hash);
+        assert_eq!(call.leaf_index, U256::from(2));
+        assert_eq!(call.siblings, proof.siblings);
+
+        assert_eq!(call.encode(), calldata);
+    }
+
+    #[test]
+    fn test_anchor_checkpoint_roundtrip() {
+        let checkpoint = CheckpointBuilder::test_fixture()
+            .entries_root(vector_tree().root())
+            .build_and_sign(Signer::generate().signing_key())
+            .unwrap();
+
+        let signature = vec![0xABu8; 65];
+        let calldata =
+            abi::anchor_checkpoint_calldata(&checkpoint, "intel-sgx", &signature).unwrap();
+
+        let call = AnchorCheckpointCall::decode(&calldata).unwrap();
+        assert_eq!(call.merkle_root, checkpoint.entries_root);
+        assert_eq!(call.enclave_measurement, [2u8; 32]);
+        assert_eq!(call.vendor, "intel-sgx");
+        assert_eq!(call.gateway_signature.to_vec(), signature);
+
+        assert_eq!(call.encode(), calldata);
+    }
+
+    #[test]
+    fn test_checkpoint_id_matches_encode_packed() {
+        let gateway = Address::repeat_byte(0x02);
+        // `encode_packed` trims untyped integers, so pass uint256 values as full words.
+        let packed = encode_packed(&[
+            Token::FixedBytes(vec![1u8; 32]),
+            Token::FixedBytes(vec![2u8; 32]),
+            Token::Address(gateway),
+            Token::FixedBytes(U256::from(1_700_000_000u64).encode()),
+            Token::FixedBytes(U256::from(5u64).encode()),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            abi::checkpoint_id(&[1u8; 32], &[2u8; 32], &gateway.0, 1_700_000_000, 5),
+            ethers_core::utils::keccak256(packed)
+        );
+    }
+}
@@ -3,6 +3,7 @@ pragma solidity ^0.8.24;
 
 import "@openzeppelin/contracts/access/AccessControl.sol";
 import "@openzeppelin/contracts/utils/ReentrancyGuard.sol";
+import "./VeribotMerkle.sol";
 
 /// @title Robot Attestation Registry
 /// @notice Manages model registry, checkpoint anchoring, and enclave revocation
@@ -229,6 +230,26 @@ contract RobotAttestationRegistry is AccessControl, ReentrancyGuard {
         return true;
     }
 
+    /// @notice Verify that a log entry is committed to by an anchored checkpoint
+    /// @param checkpointId Checkpoint ID returned by anchorCheckpoint
+    /// @param entry Disclosed log entry
+    /// @param leafIndex Position of the entry in the checkpoint's Merkle tree
+    /// @param siblings Sibling hashes from leaf to root
+    /// @return valid True if the checkpoint is valid and the proof reproduces its Merkle root
+    function verifyEntry(
+        bytes32 checkpointId,
+        VeribotMerkle.Entry calldata entry,
+        uint256 leafIndex,
+        bytes32[] calldata siblings
+    ) external view returns (bool valid) {
+        Checkpoint storage cp = checkpoints[checkpointId];
+        if (cp.timestamp == 0 || revokedEnclaves[cp.enclaveMeasurement]) {
+            return false;
+        }
+
+        return VeribotMerkle.verify(cp.merkleRoot, entry, leafIndex, siblings);
+    }
+
     // ============ Emergency Revocation ============
 
     /// @notice Emergency revoke an enclave measurement (compromised TEE)
@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: Apache-2.0
+pragma solidity ^0.8.24;
+
+/// @title Veribot Merkle proofs
+/// @notice On-chain mirror of attestation-core's log Merkle tree
+/// @dev Must stay byte-for-byte compatible with `attestation_core::merkle`:
+///      leaf = sha256(timestampUs ‖ nonce ‖ dataHash) with big-endian u64s,
+///      node = sha256(left ‖ right), odd nodes paired with themselves.
+library VeribotMerkle {
+    /// @notice A disclosed log entry
+    struct Entry {
+        uint64 timestampUs; // Microseconds since Unix epoch
+        uint64 nonce; // Tie-breaker for equal timestamps
+        bytes32 dataHash; // SHA-256 of the entry data
+    }
+
+    /// @notice Leaf hash of an entry
+    function leafHash(Entry memory entry) internal pure returns (bytes32) {
+        return sha256(abi.encodePacked(entry.timestampUs, entry.nonce, entry.dataHash));
+    }
+
+    /// @notice Recompute the root from a leaf and its sibling path
+    function computeRoot(bytes32 leaf, uint256 leafIndex, bytes32[] memory siblings) internal pure returns (bytes32) {
+        bytes32 node = leaf;
+        for (uint256 i = 0; i < siblings.length; i++) {
+            node = leafIndex % 2 == 0
+                ? sha256(abi.encodePacked(node, siblings[i]))
+                : sha256(abi.encodePacked(siblings[i], node));
+            leafIndex /= 2;
+        }
+        return node;
+    }
+
+    /// @notice Check that `entry` is committed to by `root`
+    function verify(
+        bytes32 root,
+        Entry memory entry,
+        uint256 leafIndex,
+        bytes32[] memory siblings
+    ) internal pure returns (bool) {
+        return computeRoot(leafHash(entry), leafIndex, siblings) == root;
+    }
+}
@@ -245,6 +245,46 @@ contract RobotAttestationRegistryTest is Test {
         assertFalse(registry.verifyCheckpoint(checkpointId));
     }
 
+    // ============ Entry Proof Tests ============
+
+    /// @dev Shared vector with veribot-bindings: entries (1000*(i+1), i, sha256([i])) for i in 0..3,
+    ///      proof for index 2 (odd leaf paired with itself)
+    bytes32 constant VECTOR_ROOT = hex"672b8e2f6b7b933ca2b9ff72e61579bda48c320aa14f32051f148b9683482010";
+
+    function _vectorProof() internal pure returns (VeribotMerkle.Entry memory entry, bytes32[] memory siblings) {
+        entry = VeribotMerkle.Entry({
+            timestampUs: 3000,
+            nonce: 2,
+            dataHash: hex"dbc1b4c900ffe48d575b5da5c638040125f65db0fe3e24494b76ea986457d986"
+        });
+        siblings = new bytes32[](2);
+        siblings[0] = hex"5a696aa0e1b915594e420af8457530e6c4c06454d61df98598d258ce37b198cc";
+        siblings[1] = hex"efecdce80ffffdce87322532cc1f03e97cf9055fd768c96f43fa3b8d0b673932";
+    }
+
+    function testVerifyEntry() public {
+        vm.prank(gateway);
+        bytes32 checkpointId = registry.anchorCheckpoint(VECTOR_ROOT, keccak256("enclave"), "intel-sgx", "");
+
+        (VeribotMerkle.Entry memory entry, bytes32[] memory siblings) = _vectorProof();
+        assertTrue(registry.verifyEntry(checkpointId, entry, 2, siblings));
+
+        entry.nonce = 3;
+        assertFalse(registry.verifyEntry(checkpointId, entry, 2, siblings));
+    }
+
+    function testVerifyEntryReturnsFalseForRevokedEnclave() public {
+        bytes32 enclaveMeasurement = keccak256("enclave");
+        vm.prank(gateway);
+        bytes32 checkpointId = registry.anchorCheckpoint(VECTOR_ROOT, enclaveMeasurement, "intel-sgx", "");
+
+        vm.prank(admin);
+        registry.emergencyRevokeEnclave(enclaveMeasurement, "compromised");
+
+        (VeribotMerkle.Entry memory entry, bytes32[] memory siblings) = _vectorProof();
+        assertFalse(registry.verifyEntry(checkpointId, entry, 2, siblings));
+    }
+
     // ============ Emergency Revocation Tests ============
 
     function testEmergencyRevokeEnclave() public {
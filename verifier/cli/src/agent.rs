@@ -0,0 +1,519 @@
+//! Minimal checkpointing agent behind `veribot watch`.
+//!
+//! The agent hashes each ingested log record into a Merkle tree and, on the
+//! configured cadence, cuts a signed checkpoint over the tree. Checkpoints are
+//! appended to `<out_dir>/chain.cbor` (a [`ChainArchive`], auditable with
+//! `veribot chain audit`) and each checkpoint's entries are kept in
+//! `<out_dir>/entries-<sequence>.cbor` so inclusion proofs can be produced
+//! later. Restarting the agent on the same directory continues the chain.
+//!
+//! Every cut is also recorded in `<out_dir>/history.cbor` (a [`RootHistory`]),
+//! so once checkpoints are anchored their entries files can be deleted with
+//! [`collect_garbage`] (`veribot gc`) while old roots stay provable.
+//!
+//! ```toml
+//! robot_id = "R-001"
+//! mission_id = "M-042"
+//! firmware_hash = "<hex>"
+//! enclave_measurement = "<hex>"   # optional
+//! hash_alg = "sha3_256"           # optional: sha256 (default), sha3_256, blake3
+//!
+//! [model]
+//! name = "planner-v3"
+//! hash = "<hex>"
+//!
+//! [cadence]
+//! entries = 1000        # cut after this many records...
+//! interval_secs = 60    # ...or this long after the previous cut
+//! ```
+//!
+//! `[[redaction]]` tables configure a [`RedactionPolicy`] applied to each
+//! record before it is hashed (see [`crate::redaction`]).
+
+use crate::archive::ChainArchive;
+use crate::redaction::{RedactionPolicy, RedactionRule};
+use anyhow::{bail, Context, Result};
+use attestation_core::serialization::{from_canonical_cbor, to_canonical_cbor};
+use attestation_core::{
+    Checkpoint, CheckpointBuilder, DeterminismConfig, DigestAlgorithm, Entry, Hash256, MerkleTree,
+    MissionId, ModelProvenance, RobotId, RootHistory, SigningKey, TrustMode,
+};
+use serde::{Deserialize, Deserializer};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Agent configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentConfig {
+    pub robot_id: String,
+    pub mission_id: String,
+    #[serde(deserialize_with = "hex_hash")]
+    pub firmware_hash: Hash256,
+    #[serde(default, deserialize_with = "hex_bytes")]
+    pub enclave_measurement: Vec<u8>,
+    /// Software-only agents default to `untrusted`
+    #[serde(default = "default_trust_mode")]
+    pub trust_mode: TrustMode,
+    /// Digest algorithm for checkpoint hashes and entry trees
+    #[serde(default)]
+    pub hash_alg: DigestAlgorithm,
+    pub model: ModelConfig,
+    #[serde(default)]
+    pub cadence: Cadence,
+    /// Fields to redact before hashing, applied in order
+    #[serde(default)]
+    pub redaction: Vec<RedactionRule>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelConfig {
+    pub name: String,
+    #[serde(deserialize_with = "hex_hash")]
+    pub hash: Hash256,
+}
+
+/// When to cut a checkpoint; whichever limit is reached first wins.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Cadence {
+    #[serde(default = "default_entries")]
+    pub entries: usize,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+}
+
+impl Default for Cadence {
+    fn default() -> Self {
+        Self {
+            entries: default_entries(),
+            interval_secs: default_interval_secs(),
+        }
+    }
+}
+
+fn default_trust_mode() -> TrustMode {
+    TrustMode::Untrusted
+}
+
+fn default_entries() -> usize {
+    1000
+}
+
+fn default_interval_secs() -> u64 {
+    60
+}
+
+fn hex_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    hex::decode(&value).map_err(serde::de::Error::custom)
+}
+
+fn hex_hash<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Hash256, D::Error> {
+    hex_bytes(deserializer)?
+        .try_into()
+        .map_err(|_| serde::de::Error::custom("expected 32 hex-encoded bytes"))
+}
+
+impl AgentConfig {
+    /// Read and parse a config file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let config: Self =
+            toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+        if config.cadence.entries == 0 || config.cadence.interval_secs == 0 {
+            bail!("cadence.entries and cadence.interval_secs must be at least 1");
+        }
+        RedactionPolicy::new(config.redaction.clone())?;
+        Ok(config)
+    }
+}
+
+/// A checkpoint cut by the agent.
+#[derive(Debug)]
+pub struct Cut {
+    pub checkpoint: Checkpoint,
+    pub entries: usize,
+    /// File holding the checkpoint's entries
+    pub entries_file: PathBuf,
+}
+
+pub struct Agent {
+    config: AgentConfig,
+    signing_key: SigningKey,
+    out_dir: PathBuf,
+    archive: ChainArchive,
+    /// Redaction policy and its hash, if any rules are configured
+    redaction: Option<(RedactionPolicy, Hash256)>,
+    /// Retained roots of every checkpoint cut into this directory
+    history: RootHistory,
+    tree: MerkleTree,
+    next_nonce: u64,
+    last_cut: Instant,
+}
+
+impl Agent {
+    /// Open (or resume) an agent writing to `out_dir`.
+    pub fn open(config: AgentConfig, signing_key: SigningKey, out_dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(out_dir)
+            .with_context(|| format!("creating {}", out_dir.display()))?;
+        let chain_file = out_dir.join("chain.cbor");
+        let archive = if chain_file.exists() {
+            ChainArchive::load(&chain_file)?
+        } else {
+            ChainArchive::default()
+        };
+        if let Some(last) = archive.checkpoints.last() {
+            if last.robot_id.0 != config.robot_id {
+                bail!(
+                    "{} continues robot {}, not {}",
+                    chain_file.display(),
+                    last.robot_id.0,
+                    config.robot_id
+                );
+            }
+        }
+
+        let redaction = if config.redaction.is_empty() {
+            None
+        } else {
+            let policy = RedactionPolicy::new(config.redaction.clone())?;
+            let hash = policy.hash()?;
+            let policy_file = out_dir.join(format!("redaction-{}.cbor", hex::encode(hash)));
+            std::fs::write(&policy_file, policy.to_bytes()?)
+                .with_context(|| format!("writing {}", policy_file.display()))?;
+            Some((policy, hash))
+        };
+
+        let history = load_history(out_dir, &archive)?;
+        let tree = MerkleTree::new().with_hash_alg(config.hash_alg);
+
+        Ok(Self {
+            config,
+            signing_key,
+            out_dir: out_dir.to_path_buf(),
+            archive,
+            redaction,
+            history,
+            tree,
+            next_nonce: 0,
+            last_cut: Instant::now(),
+        })
+    }
+
+    /// Checkpoints cut so far (including those from earlier runs).
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.archive.checkpoints
+    }
+
+    /// Add one log record, cutting a checkpoint if the entry cadence is reached.
+    ///
+    /// With a redaction policy the record is redacted first; records the
+    /// policy cannot be applied to are refused rather than logged as is.
+    pub fn ingest(&mut self, record: &[u8], timestamp_us: u64) -> Result<Option<Cut>> {
+        let entry = match &self.redaction {
+            Some((policy, _)) => Entry::new(timestamp_us, self.next_nonce, &policy.apply(record)?),
+            None => Entry::new(timestamp_us, self.next_nonce, record),
+        };
+        if self.tree.insert(entry).existed() {
+            bail!(
+                "entry ({timestamp_us}, {}) is already pending",
+                self.next_nonce
+            );
+        }
+        self.next_nonce += 1;
+        if self.tree.len() >= self.config.cadence.entries {
+            return self.cut();
+        }
+        Ok(None)
+    }
+
+    /// Cut a checkpoint if the interval has elapsed since the previous cut.
+    pub fn tick(&mut self) -> Result<Option<Cut>> {
+        if self.last_cut.elapsed() >= self.interval() {
+            return self.cut();
+        }
+        Ok(None)
+    }
+
+    /// Time until [`Agent::tick`] would next cut.
+    pub fn until_due(&self) -> Duration {
+        self.interval().saturating_sub(self.last_cut.elapsed())
+    }
+
+    /// Cut a checkpoint over all pending entries (none if nothing is pending).
+    pub fn cut(&mut self) -> Result<Option<Cut>> {
+        self.last_cut = Instant::now();
+        if self.tree.is_empty() {
+            return Ok(None);
+        }
+
+        let builder = match self.archive.checkpoints.last() {
+            Some(prev) => CheckpointBuilder::continuing_from(prev)?
+                .monotonic_counter(prev.monotonic_counter + 1),
+            None => self.genesis_builder(),
+        };
+        let builder = match &self.redaction {
+            Some((_, hash)) => builder.redaction_policy(*hash),
+            None => builder,
+        };
+        let checkpoint = builder
+            .hash_alg(self.config.hash_alg)
+            .entries_root(self.tree.root())
+            .build_and_sign(&self.signing_key)?;
+
+        let entries: Vec<&Entry> = self.tree.entries();
+        let entries_file = entries_file(&self.out_dir, checkpoint.sequence);
+        std::fs::write(&entries_file, to_canonical_cbor(&entries)?)
+            .with_context(|| format!("writing {}", entries_file.display()))?;
+        let cut = Cut {
+            entries: entries.len(),
+            checkpoint: checkpoint.clone(),
+            entries_file,
+        };
+
+        self.history.retire(&checkpoint, &mut self.tree)?;
+        save_history(&self.out_dir, &self.history)?;
+        self.archive.checkpoints.push(checkpoint);
+        self.archive.save(&self.out_dir.join("chain.cbor"))?;
+        self.next_nonce = 0;
+        Ok(Some(cut))
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.config.cadence.interval_secs)
+    }
+
+    fn genesis_builder(&self) -> CheckpointBuilder {
+        CheckpointBuilder::new()
+            .robot_id(RobotId(self.config.robot_id.clone()))
+            .mission_id(MissionId(self.config.mission_id.clone()))
+            .sequence(0)
+            .monotonic_counter(1)
+            .model_provenance(ModelProvenance {
+                name: self.config.model.name.clone(),
+                model_hash: self.config.model.hash,
+                dataset_hash: None,
+                container_digest: None,
+                signature_bundle: None,
+            })
+            .firmware_hash(self.config.firmware_hash)
+            .enclave_measurement(self.config.enclave_measurement.clone())
+            .prev_root([0u8; 32])
+            .inference_config(DeterminismConfig {
+                rng_seed: None,
+                batch_size: 1,
+                flags: None,
+            })
+            .trust_mode(self.config.trust_mode)
+    }
+}
+
+fn entries_file(out_dir: &Path, sequence: u64) -> PathBuf {
+    out_dir.join(format!("entries-{sequence:08}.cbor"))
+}
+
+fn save_history(out_dir: &Path, history: &RootHistory) -> Result<()> {
+    let path = out_dir.join("history.cbor");
+    std::fs::write(&path, history.to_bytes()?)
+        .with_context(|| format!("writing {}", path.display()))
+}
+
+/// Load `history.cbor`, first recording any archived checkpoints it lacks
+/// (directories written before the history existed) from their entries files.
+fn load_history(out_dir: &Path, archive: &ChainArchive) -> Result<RootHistory> {
+    let path = out_dir.join("history.cbor");
+    let mut history = if path.exists() {
+        let bytes = std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+        RootHistory::from_bytes(&bytes).with_context(|| format!("decoding {}", path.display()))?
+    } else {
+        RootHistory::new()
+    };
+
+    let last = history.roots().last().map(|root| root.sequence);
+    let mut changed = false;
+    for checkpoint in &archive.checkpoints {
+        if last.is_some_and(|last| checkpoint.sequence <= last) {
+            continue;
+        }
+        let file = entries_file(out_dir, checkpoint.sequence);
+        if !file.exists() {
+            continue;
+        }
+        let bytes = std::fs::read(&file).with_context(|| format!("reading {}", file.display()))?;
+        let entries: Vec<Entry> =
+            from_canonical_cbor(&bytes).with_context(|| format!("decoding {}", file.display()))?;
+        let mut tree = MerkleTree::new().with_hash_alg(checkpoint.hash_alg);
+        for entry in entries {
+            tree.insert(entry);
+        }
+        history.record(checkpoint, &tree)?;
+        changed = true;
+    }
+    if changed {
+        save_history(out_dir, &history)?;
+    }
+    Ok(history)
+}
+
+/// Delete the entries files of checkpoints up to `anchored_through` whose
+/// roots are retained in `history.cbor`; returns the deleted sequences.
+pub fn collect_garbage(out_dir: &Path, anchored_through: u64) -> Result<(RootHistory, Vec<u64>)> {
+    let archive = ChainArchive::load(&out_dir.join("chain.cbor"))?;
+    let history = load_history(out_dir, &archive)?;
+    let mut removed = Vec::new();
+    for root in history.roots() {
+        if root.sequence > anchored_through {
+            break;
+        }
+        let file = entries_file(out_dir, root.sequence);
+        if file.exists() {
+            std::fs::remove_file(&file).with_context(|| format!("removing {}", file.display()))?;
+            removed.push(root.sequence);
+        }
+    }
+    Ok((history, removed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::chain::{audit, AuditPolicy};
+    use attestation_core::Signer;
+
+    const CONFIG: &str = r#"
+        robot_id = "R-001"
+        mission_id = "M-001"
+        firmware_hash = "0101010101010101010101010101010101010101010101010101010101010101"
+
+        [model]
+        name = "model-v1"
+        hash = "0000000000000000000000000000000000000000000000000000000000000000"
+
+        [cadence]
+        entries = 3
+    "#;
+
+    fn config() -> AgentConfig {
+        toml::from_str(CONFIG).unwrap()
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config = config();
+        assert_eq!(config.trust_mode, TrustMode::Untrusted);
+        assert_eq!(config.cadence.interval_secs, 60);
+        assert!(config.enclave_measurement.is_empty());
+        assert!(toml::from_str::<AgentConfig>("robot_id = 1").is_err());
+    }
+
+    #[test]
+    fn test_cuts_on_entry_cadence_and_resumes() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::generate();
+
+        let mut agent = Agent::open(config(), signer.signing_key().clone(), dir.path()).unwrap();
+        assert!(agent.ingest(b"a", 1).unwrap().is_none());
+        assert!(agent.ingest(b"b", 2).unwrap().is_none());
+        let cut = agent.ingest(b"c", 3).unwrap().unwrap();
+        assert_eq!(cut.entries, 3);
+        assert!(cut.entries_file.exists());
+        agent.ingest(b"d", 4).unwrap();
+        drop(agent);
+
+        // Pending entries are lost on restart, but the chain continues.
+        let mut agent = Agent::open(config(), signer.signing_key().clone(), dir.path()).unwrap();
+        agent.ingest(b"e", 5).unwrap();
+        let cut = agent.cut().unwrap().unwrap();
+        assert_eq!(cut.checkpoint.sequence, 1);
+        assert!(agent.cut().unwrap().is_none());
+
+        let archive = ChainArchive::load(&dir.path().join("chain.cbor")).unwrap();
+        let report = audit(
+            &archive,
+            Box::new(signer.verifying_key()),
+            &AuditPolicy::default(),
+        );
+        assert!(report.passed);
+        assert_eq!(report.verified, 2);
+    }
+
+    #[test]
+    fn test_rejects_other_robots_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::generate();
+        let mut agent = Agent::open(config(), signer.signing_key().clone(), dir.path()).unwrap();
+        agent.ingest(b"a", 1).unwrap();
+        agent.cut().unwrap();
+
+        let mut other = config();
+        other.robot_id = "R-002".to_string();
+        assert!(Agent::open(other, signer.signing_key().clone(), dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_redacts_before_hashing() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::generate();
+        let mut config = config();
+        config.redaction = vec![RedactionRule::Remove {
+            field: "face".to_string(),
+        }];
+        let policy = RedactionPolicy::new(config.redaction.clone()).unwrap();
+
+        let mut agent = Agent::open(config, signer.signing_key().clone(), dir.path()).unwrap();
+        assert!(agent.ingest(b"not json", 1).is_err());
+        agent.ingest(br#"{"face":"...","x":1}"#, 1).unwrap();
+        let cut = agent.cut().unwrap().unwrap();
+
+        let hash = policy.hash().unwrap();
+        assert_eq!(cut.checkpoint.redaction_policy, Some(hash));
+        assert!(dir
+            .path()
+            .join(format!("redaction-{}.cbor", hex::encode(hash)))
+            .exists());
+        let mut expected = MerkleTree::new();
+        expected.insert(Entry::new(1, 0, br#"{"x":1}"#));
+        assert_eq!(cut.checkpoint.entries_root, expected.root());
+    }
+
+    #[test]
+    fn test_garbage_collection_keeps_roots_provable() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::generate();
+        let mut agent = Agent::open(config(), signer.signing_key().clone(), dir.path()).unwrap();
+        for i in 0..9 {
+            agent.ingest(format!("record-{i}").as_bytes(), i).unwrap();
+        }
+        let checkpoints = agent.checkpoints().to_vec();
+        assert_eq!(checkpoints.len(), 3);
+        drop(agent);
+
+        // Directories from before the history existed are backfilled
+        std::fs::remove_file(dir.path().join("history.cbor")).unwrap();
+        let (history, removed) = collect_garbage(dir.path(), 1).unwrap();
+        assert_eq!(removed, vec![0, 1]);
+        assert!(!entries_file(dir.path(), 1).exists());
+        assert!(entries_file(dir.path(), 2).exists());
+
+        let root = history.root().unwrap();
+        let proof = history.prove(0).unwrap().unwrap();
+        assert!(proof.verify(&root));
+        assert_eq!(proof.retained.entries_root, checkpoints[0].entries_root);
+
+        // Resuming after collection keeps extending the same history
+        let mut agent = Agent::open(config(), signer.signing_key().clone(), dir.path()).unwrap();
+        agent.ingest(b"late", 100).unwrap();
+        agent.cut().unwrap().unwrap();
+        let (history, removed) = collect_garbage(dir.path(), 1).unwrap();
+        assert!(removed.is_empty());
+        assert_eq!(history.roots().len(), 4);
+        assert!(history
+            .prove(0)
+            .unwrap()
+            .unwrap()
+            .verify(&history.root().unwrap()));
+    }
+}
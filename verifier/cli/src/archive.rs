@@ -0,0 +1,77 @@
+//! Chain archive files.
+//!
+//! A chain archive is a canonical CBOR document holding one robot's
+//! checkpoints in sequence order, plus any key rotation certificates needed
+//! to follow the chain across signer changes.
+//!
+//! A directory of archives, one per robot, doubles as a gateway checkpoint
+//! store for `veribot import` (see [`ChainArchive::store_path`]).
+
+use anyhow::{Context, Result};
+use attestation_core::serialization::{from_canonical_cbor, to_canonical_cbor};
+use attestation_core::{
+    Checkpoint, CheckpointPage, CheckpointQuery, CheckpointStore, KeyRotationCert, RobotId,
+    VerifiedCheckpoint,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChainArchive {
+    /// Checkpoints in sequence order
+    pub checkpoints: Vec<Checkpoint>,
+    /// Key rotation certificates, oldest first
+    #[serde(default)]
+    pub rotations: Vec<KeyRotationCert>,
+}
+
+impl ChainArchive {
+    /// Read an archive file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        from_canonical_cbor(&bytes).with_context(|| format!("decoding archive {}", path.display()))
+    }
+
+    /// Write an archive file, replacing it atomically.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("cbor.tmp");
+        std::fs::write(&tmp, to_canonical_cbor(self)?)
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("replacing {}", path.display()))
+    }
+
+    /// The archive file of `robot_id` in a store directory.
+    pub fn store_path(store: &Path, robot_id: &RobotId) -> PathBuf {
+        store.join(format!("{}.cbor", robot_id.0))
+    }
+}
+
+impl CheckpointStore for ChainArchive {
+    fn checkpoints(&self, robot_id: &RobotId) -> Vec<Checkpoint> {
+        self.checkpoints
+            .iter()
+            .filter(|c| &c.robot_id == robot_id)
+            .cloned()
+            .collect()
+    }
+
+    fn insert(&mut self, checkpoint: VerifiedCheckpoint) {
+        let at = self
+            .checkpoints
+            .partition_point(|c| c.sequence < checkpoint.sequence);
+        self.checkpoints.insert(at, checkpoint.into_inner());
+    }
+
+    fn page(&self, query: &CheckpointQuery) -> CheckpointPage {
+        // Checkpoints are kept in sequence order: skip straight to the page start
+        let start = self
+            .checkpoints
+            .partition_point(|c| c.sequence < query.start_sequence());
+        let matches = self.checkpoints[start..]
+            .iter()
+            .take_while(|c| query.to_sequence.is_none_or(|to| c.sequence <= to))
+            .filter(|c| query.matches(c))
+            .cloned();
+        CheckpointPage::from_matches(matches, query.limit)
+    }
+}
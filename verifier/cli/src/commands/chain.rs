@@ -0,0 +1,422 @@
+//! `veribot chain`: whole-chain audits.
+
+use crate::archive::ChainArchive;
+use crate::output::{emit, to_json, OutputFormat, Report};
+use crate::pubkey::load_verifying_key;
+use anyhow::{bail, Context, Result};
+use attestation_core::{
+    ApprovalLog, ArtifactKind, ChainVerifier, Checkpoint, ErrorCode, ErrorCoded, Hash256,
+    KeyResolver, KeyRing, PublishedApprovals, RobotId, TrustMode,
+};
+use clap::Subcommand;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(Debug, Subcommand)]
+pub enum ChainCommand {
+    /// Verify a full checkpoint chain and apply an allowlist policy
+    ///
+    /// Exits 0 when the chain verifies and satisfies the policy, 1 when it
+    /// does not, and 2 when the audit could not be run.
+    Audit {
+        /// Chain archive file (gateway URLs are not supported yet)
+        source: String,
+        /// Trusted signer key (PEM, hex or keystore); repeatable
+        #[arg(long = "pubkey", required = true)]
+        pubkeys: Vec<PathBuf>,
+        /// Allowed firmware hash (hex); repeatable, any if omitted
+        #[arg(long = "allow-firmware", value_parser = parse_hash)]
+        allowed_firmware: Vec<Hash256>,
+        /// Allowed enclave measurement (hex); repeatable, any if omitted
+        #[arg(long = "allow-measurement", value_parser = parse_hex)]
+        allowed_measurements: Vec<Vec<u8>>,
+        /// Reject checkpoints below this trust mode
+        /// (trusted, soft_attestation or untrusted)
+        #[arg(long = "min-trust-mode", value_parser = parse_trust_mode)]
+        min_trust_mode: Option<TrustMode>,
+        /// Published approval log (CBOR); checkpoints must run a model and
+        /// firmware it approves at their timestamp
+        #[arg(long, requires = "approvals_key")]
+        approvals: Option<PathBuf>,
+        /// Approval log signing key (PEM, hex or keystore)
+        #[arg(long = "approvals-key")]
+        approvals_key: Option<PathBuf>,
+        /// Also write the JSON report to this file
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+}
+
+pub fn run(command: ChainCommand, format: OutputFormat) -> Result<ExitCode> {
+    match command {
+        ChainCommand::Audit {
+            source,
+            pubkeys,
+            allowed_firmware,
+            allowed_measurements,
+            min_trust_mode,
+            approvals,
+            approvals_key,
+            out,
+        } => {
+            if source.starts_with("http://") || source.starts_with("https://") {
+                bail!("fetching chains from a gateway is not supported yet; audit an archive file");
+            }
+            let archive = ChainArchive::load(source.as_ref())?;
+
+            let mut keys = KeyRing::new();
+            for path in &pubkeys {
+                keys.insert(load_verifying_key(path)?);
+            }
+            let approvals = match (approvals, approvals_key) {
+                (Some(path), Some(key)) => Some(load_approvals(&path, &key)?),
+                _ => None,
+            };
+            let policy = AuditPolicy {
+                allowed_firmware,
+                allowed_measurements,
+                min_trust_mode,
+                approvals,
+            };
+
+            let report = audit(&archive, Box::new(keys), &policy);
+            if let Some(path) = out {
+                std::fs::write(&path, to_json(&report)? + "\n")
+                    .with_context(|| format!("writing {}", path.display()))?;
+            }
+            emit(format, &report)?;
+
+            Ok(if report.passed {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(1)
+            })
+        }
+    }
+}
+
+/// Per-checkpoint allowlists applied on top of chain verification.
+#[derive(Debug, Default)]
+pub struct AuditPolicy {
+    pub allowed_firmware: Vec<Hash256>,
+    pub allowed_measurements: Vec<Vec<u8>>,
+    /// Enforced by the chain verifier: a checkpoint below it fails the audit
+    pub min_trust_mode: Option<TrustMode>,
+    /// Approved models and firmware, with their effective dates
+    pub approvals: Option<ApprovalLog>,
+}
+
+impl AuditPolicy {
+    fn check(&self, checkpoint: &Checkpoint) -> Vec<PolicyViolation> {
+        let mut violations = Vec::new();
+        if !self.allowed_firmware.is_empty()
+            && !self.allowed_firmware.contains(&checkpoint.firmware_hash)
+        {
+            violations.push(PolicyViolation {
+                sequence: checkpoint.sequence,
+                rule: "firmware_allowlist",
+                detail: format!(
+                    "firmware {} not allowed",
+                    hex::encode(checkpoint.firmware_hash)
+                ),
+            });
+        }
+        if !self.allowed_measurements.is_empty()
+            && !self
+                .allowed_measurements
+                .contains(&checkpoint.enclave_measurement)
+        {
+            violations.push(PolicyViolation {
+                sequence: checkpoint.sequence,
+                rule: "measurement_allowlist",
+                detail: format!(
+                    "measurement {} not allowed",
+                    hex::encode(&checkpoint.enclave_measurement)
+                ),
+            });
+        }
+        if let Some(approvals) = &self.approvals {
+            let at = checkpoint.local_timestamp_utc;
+            let artifacts = [
+                (
+                    ArtifactKind::Model,
+                    checkpoint.model_provenance.model_hash,
+                    "model_approval",
+                ),
+                (
+                    ArtifactKind::Firmware,
+                    checkpoint.firmware_hash,
+                    "firmware_approval",
+                ),
+            ];
+            for (kind, hash, rule) in artifacts {
+                if let Err(e) = approvals.check(kind, &hash, at) {
+                    violations.push(PolicyViolation {
+                        sequence: checkpoint.sequence,
+                        rule,
+                        detail: e.to_string(),
+                    });
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// Read a published approval log and check it is signed by `key`.
+fn load_approvals(path: &Path, key: &Path) -> Result<ApprovalLog> {
+    let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let published = PublishedApprovals::from_bytes(&bytes)
+        .with_context(|| format!("decoding approval log {}", path.display()))?;
+    ApprovalLog::from_published(published, &load_verifying_key(key)?)
+        .with_context(|| format!("verifying approval log {}", path.display()))
+}
+
+/// Result of auditing one chain archive.
+#[derive(Debug, Serialize)]
+pub struct AuditReport {
+    pub passed: bool,
+    pub robot_id: Option<RobotId>,
+    pub checkpoints: usize,
+    pub verified: usize,
+    pub rotations: usize,
+    pub head: Option<AuditHead>,
+    pub failure: Option<AuditFailure>,
+    pub policy_violations: Vec<PolicyViolation>,
+}
+
+impl Report for AuditReport {
+    const SCHEMA: &'static str = "veribot.chain.audit/v1";
+
+    fn write_text(&self) {
+        let robot = self.robot_id.as_ref().map_or("-", |id| id.0.as_str());
+        println!("robot:        {robot}");
+        println!(
+            "checkpoints:  {} ({} verified)",
+            self.checkpoints, self.verified
+        );
+        println!("rotations:    {}", self.rotations);
+        if let Some(head) = &self.head {
+            println!("head:         #{} {}", head.sequence, head.hash);
+        }
+        if let Some(failure) = &self.failure {
+            let at = failure
+                .sequence
+                .map_or_else(|| "rotation".to_string(), |seq| format!("#{seq}"));
+            println!("failure:      {at} [{}] {}", failure.code, failure.message);
+        }
+        for violation in &self.policy_violations {
+            println!("violation:    #{} {}", violation.sequence, violation.detail);
+        }
+        println!(
+            "result:       {}",
+            if self.passed { "PASS" } else { "FAIL" }
+        );
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditHead {
+    pub sequence: u64,
+    pub monotonic_counter: u64,
+    pub hash: String,
+}
+
+/// First chain verification failure.
+#[derive(Debug, Serialize)]
+pub struct AuditFailure {
+    /// Sequence of the rejected checkpoint (absent for rotation failures)
+    pub sequence: Option<u64>,
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PolicyViolation {
+    pub sequence: u64,
+    pub rule: &'static str,
+    pub detail: String,
+}
+
+/// Verify `archive` and apply `policy`, stopping at the first chain failure.
+pub fn audit(
+    archive: &ChainArchive,
+    keys: Box<dyn KeyResolver>,
+    policy: &AuditPolicy,
+) -> AuditReport {
+    let mut report = AuditReport {
+        passed: false,
+        robot_id: archive.checkpoints.first().map(|c| c.robot_id.clone()),
+        checkpoints: archive.checkpoints.len(),
+        verified: 0,
+        rotations: archive.rotations.len(),
+        head: None,
+        failure: None,
+        policy_violations: Vec::new(),
+    };
+
+    let mut verifier = ChainVerifier::with_resolver(keys);
+    if let Some(minimum) = policy.min_trust_mode {
+        verifier = verifier.require_trust_mode(minimum);
+    }
+    for cert in &archive.rotations {
+        if let Err(e) = verifier.add_rotation(cert) {
+            report.failure = Some(AuditFailure {
+                sequence: None,
+                code: e.code(),
+                message: e.to_string(),
+            });
+            return report;
+        }
+    }
+
+    for checkpoint in &archive.checkpoints {
+        if let Err(e) = verifier.verify_next(checkpoint) {
+            report.failure = Some(AuditFailure {
+                sequence: Some(checkpoint.sequence),
+                code: e.code(),
+                message: e.to_string(),
+            });
+            break;
+        }
+        report.verified += 1;
+        report.policy_violations.extend(policy.check(checkpoint));
+    }
+
+    report.head = verifier.head().map(|head| AuditHead {
+        sequence: head.sequence,
+        monotonic_counter: head.monotonic_counter,
+        hash: hex::encode(head.hash),
+    });
+    report.passed = report.failure.is_none() && report.policy_violations.is_empty();
+    report
+}
+
+fn parse_trust_mode(value: &str) -> Result<TrustMode, String> {
+    match value {
+        "trusted" => Ok(TrustMode::Trusted),
+        "soft_attestation" => Ok(TrustMode::SoftAttestation),
+        "untrusted" => Ok(TrustMode::Untrusted),
+        _ => Err("expected trusted, soft_attestation or untrusted".to_string()),
+    }
+}
+
+fn parse_hex(value: &str) -> Result<Vec<u8>, hex::FromHexError> {
+    hex::decode(value)
+}
+
+fn parse_hash(value: &str) -> Result<Hash256, String> {
+    let bytes = hex::decode(value).map_err(|e| e.to_string())?;
+    bytes
+        .try_into()
+        .map_err(|_| "expected a 32-byte hex hash".to_string())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use attestation_core::{Approval, CheckpointBuilder};
+    use attestation_core::{Signer, SigningKey};
+
+    pub(crate) fn chain(key: &SigningKey, len: u64) -> Vec<Checkpoint> {
+        let mut checkpoints: Vec<Checkpoint> = Vec::new();
+        for sequence in 0..len {
+            let builder = match checkpoints.last() {
+                Some(prev) => CheckpointBuilder::continuing_from(prev)
+                    .unwrap()
+                    .monotonic_counter(prev.monotonic_counter + 1),
+                None => CheckpointBuilder::test_fixture(),
+            };
+            let checkpoint = builder
+                .entries_root([sequence as u8; 32])
+                .build_and_sign(key)
+                .unwrap();
+            checkpoints.push(checkpoint);
+        }
+        checkpoints
+    }
+
+    #[test]
+    fn test_audit_passes_valid_chain() {
+        let signer = Signer::generate();
+        let archive = ChainArchive {
+            checkpoints: chain(signer.signing_key(), 3),
+            rotations: Vec::new(),
+        };
+
+        let report = audit(
+            &archive,
+            Box::new(signer.verifying_key()),
+            &AuditPolicy::default(),
+        );
+        assert!(report.passed);
+        assert_eq!(report.verified, 3);
+        assert_eq!(report.head.unwrap().sequence, 2);
+    }
+
+    #[test]
+    fn test_audit_reports_first_failure() {
+        let signer = Signer::generate();
+        let mut checkpoints = chain(signer.signing_key(), 3);
+        checkpoints.remove(1);
+        let archive = ChainArchive {
+            checkpoints,
+            rotations: Vec::new(),
+        };
+
+        let report = audit(
+            &archive,
+            Box::new(signer.verifying_key()),
+            &AuditPolicy::default(),
+        );
+        assert!(!report.passed);
+        assert_eq!(report.verified, 1);
+        let failure = report.failure.unwrap();
+        assert_eq!(failure.sequence, Some(2));
+        assert_eq!(failure.code, ErrorCode::SequenceGap);
+    }
+
+    #[test]
+    fn test_audit_policy_violations() {
+        let signer = Signer::generate();
+        let archive = ChainArchive {
+            checkpoints: chain(signer.signing_key(), 2),
+            rotations: Vec::new(),
+        };
+        let policy = AuditPolicy {
+            allowed_firmware: vec![[9u8; 32]],
+            allowed_measurements: vec![vec![2u8; 32]],
+            ..Default::default()
+        };
+
+        let report = audit(&archive, Box::new(signer.verifying_key()), &policy);
+        assert!(report.failure.is_none());
+        assert!(!report.passed);
+        assert_eq!(report.policy_violations.len(), 2);
+        assert_eq!(report.policy_violations[0].rule, "firmware_allowlist");
+
+        // The firmware is approved, the model never was
+        let mut approvals = ApprovalLog::new();
+        let since = chrono::DateTime::UNIX_EPOCH;
+        approvals
+            .append(Approval::new(
+                ArtifactKind::Firmware,
+                [1u8; 32],
+                "fw",
+                since,
+            ))
+            .unwrap();
+        let policy = AuditPolicy {
+            approvals: Some(approvals),
+            ..Default::default()
+        };
+        let report = audit(&archive, Box::new(signer.verifying_key()), &policy);
+        assert!(!report.passed);
+        assert_eq!(report.policy_violations.len(), 2);
+        assert!(report
+            .policy_violations
+            .iter()
+            .all(|v| v.rule == "model_approval"));
+    }
+}
@@ -0,0 +1,122 @@
+//! `veribot conformance`: check externally produced CBOR against the
+//! canonical encoding before it reaches a production chain.
+
+use crate::output::{emit, OutputFormat, Report};
+use anyhow::{Context, Result};
+use attestation_core::conformance::{check, ConformanceKind, ConformanceReport};
+use clap::Args;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Debug, Args)]
+pub struct ConformanceArgs {
+    /// Files each holding one CBOR-encoded record
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Record type the files should hold
+    #[arg(long, value_enum, default_value_t = RecordKind::Checkpoint)]
+    kind: RecordKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum RecordKind {
+    Checkpoint,
+    Entry,
+}
+
+impl From<RecordKind> for ConformanceKind {
+    fn from(kind: RecordKind) -> Self {
+        match kind {
+            RecordKind::Checkpoint => ConformanceKind::Checkpoint,
+            RecordKind::Entry => ConformanceKind::Entry,
+        }
+    }
+}
+
+/// Report every canonicality and schema violation in each file, with byte
+/// offsets. Exits 1 if any file does not conform.
+pub fn run(args: ConformanceArgs, format: OutputFormat) -> Result<ExitCode> {
+    let mut files = Vec::with_capacity(args.files.len());
+    for path in &args.files {
+        let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        files.push(FileConformance {
+            path: path.display().to_string(),
+            report: check(args.kind.into(), &bytes),
+        });
+    }
+    let report = ConformanceSummary {
+        conformant: files.iter().all(|f| f.report.is_conformant()),
+        files,
+    };
+    emit(format, &report)?;
+    Ok(if report.conformant {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    })
+}
+
+/// Result of `conformance`.
+#[derive(Debug, Serialize)]
+pub struct ConformanceSummary {
+    pub conformant: bool,
+    pub files: Vec<FileConformance>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileConformance {
+    pub path: String,
+    #[serde(flatten)]
+    pub report: ConformanceReport,
+}
+
+impl Report for ConformanceSummary {
+    const SCHEMA: &'static str = "veribot.conformance/v1";
+
+    fn write_text(&self) {
+        for file in &self.files {
+            if file.report.is_conformant() {
+                println!("{}: ok ({} bytes)", file.path, file.report.length);
+                continue;
+            }
+            println!(
+                "{}: {} violation(s)",
+                file.path,
+                file.report.violations.len()
+            );
+            for violation in &file.report.violations {
+                println!(
+                    "  @{:<6} {:?}: {}",
+                    violation.offset, violation.rule, violation.message
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use attestation_core::serialization::to_canonical_cbor;
+    use attestation_core::Entry;
+
+    #[test]
+    fn test_nonconforming_file_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let good = dir.path().join("good.cbor");
+        let bad = dir.path().join("bad.cbor");
+        let bytes = to_canonical_cbor(&Entry::new(1_000, 0, b"event")).unwrap();
+        std::fs::write(&good, &bytes).unwrap();
+        std::fs::write(&bad, [&bytes[..], &[0x00]].concat()).unwrap();
+
+        let args = |files| ConformanceArgs {
+            files,
+            kind: RecordKind::Entry,
+        };
+        let code = run(args(vec![good.clone()]), OutputFormat::Json).unwrap();
+        assert_eq!(code, ExitCode::SUCCESS);
+        let code = run(args(vec![good, bad]), OutputFormat::Json).unwrap();
+        assert_eq!(code, ExitCode::from(1));
+    }
+}
@@ -0,0 +1,51 @@
+//! `veribot gc`: drop anchored entries from an agent directory.
+
+use crate::agent::collect_garbage;
+use crate::output::{emit, OutputFormat, Report};
+use anyhow::Result;
+use clap::Args;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Debug, Args)]
+pub struct GcArgs {
+    /// Agent output directory (as passed to `veribot watch --out-dir`)
+    #[arg(long)]
+    out_dir: PathBuf,
+    /// Last checkpoint sequence that is anchored; entries up to it are deleted
+    #[arg(long)]
+    anchored_through: u64,
+}
+
+/// Delete entries files of anchored checkpoints; their roots stay in
+/// `history.cbor`.
+pub fn run(args: GcArgs, format: OutputFormat) -> Result<ExitCode> {
+    let (history, removed) = collect_garbage(&args.out_dir, args.anchored_through)?;
+    let report = GcReport {
+        removed,
+        retained_roots: history.roots().len(),
+        history_root: hex::encode(history.root()?),
+    };
+    emit(format, &report)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Result of `gc`.
+#[derive(Debug, Serialize)]
+pub struct GcReport {
+    /// Sequences whose entries files were deleted
+    pub removed: Vec<u64>,
+    pub retained_roots: usize,
+    pub history_root: String,
+}
+
+impl Report for GcReport {
+    const SCHEMA: &'static str = "veribot.gc/v1";
+
+    fn write_text(&self) {
+        println!("removed entries of {} checkpoint(s)", self.removed.len());
+        println!("retained roots: {}", self.retained_roots);
+        println!("history root:   {}", self.history_root);
+    }
+}
@@ -0,0 +1,119 @@
+//! `veribot import`: backfill historical chain archives into a store.
+
+use crate::archive::ChainArchive;
+use crate::output::{emit, OutputFormat, Report};
+use crate::pubkey::load_verifying_key;
+use anyhow::{Context, Result};
+use attestation_core::{backfill, BackfillReport, KeyRing};
+use clap::Args;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// Chain archives exported by the previous evidence system
+    #[arg(required = true)]
+    archives: Vec<PathBuf>,
+    /// Store directory (one `<robot_id>.cbor` chain archive per robot)
+    #[arg(long)]
+    store: PathBuf,
+    /// Trusted signer key (PEM, hex or keystore); repeatable
+    #[arg(long = "pubkey", required = true)]
+    pubkeys: Vec<PathBuf>,
+}
+
+/// Import each archive, verifying its chain and reconciling it with the
+/// store. Exits 1 if any archive had conflicts or a rejected checkpoint.
+pub fn run(args: ImportArgs, format: OutputFormat) -> Result<ExitCode> {
+    let mut keys = KeyRing::new();
+    for path in &args.pubkeys {
+        keys.insert(load_verifying_key(path)?);
+    }
+    std::fs::create_dir_all(&args.store)
+        .with_context(|| format!("creating {}", args.store.display()))?;
+
+    let mut archives = Vec::new();
+    for path in &args.archives {
+        let incoming = ChainArchive::load(path)?;
+        let Some(first) = incoming.checkpoints.first() else {
+            continue;
+        };
+        let store_path = ChainArchive::store_path(&args.store, &first.robot_id);
+        let mut stored = if store_path.exists() {
+            ChainArchive::load(&store_path)?
+        } else {
+            ChainArchive::default()
+        };
+
+        let mut rotations = stored.rotations.clone();
+        for cert in &incoming.rotations {
+            if !rotations.contains(cert) {
+                rotations.push(cert.clone());
+            }
+        }
+        let report = backfill(
+            &mut stored,
+            &incoming.checkpoints,
+            Box::new(keys.clone()),
+            &rotations,
+        )
+        .with_context(|| format!("importing {}", path.display()))?;
+        if !report.imported.is_empty() {
+            stored.rotations = rotations;
+            stored.save(&store_path)?;
+        }
+        archives.push(ArchiveImport {
+            path: path.display().to_string(),
+            report,
+        });
+    }
+
+    let report = ImportReport { archives };
+    emit(format, &report)?;
+    Ok(if report.archives.iter().all(|a| a.report.is_clean()) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    })
+}
+
+/// Result of `import`.
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub archives: Vec<ArchiveImport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveImport {
+    pub path: String,
+    #[serde(flatten)]
+    pub report: BackfillReport,
+}
+
+impl Report for ImportReport {
+    const SCHEMA: &'static str = "veribot.import/v1";
+
+    fn write_text(&self) {
+        for archive in &self.archives {
+            let report = &archive.report;
+            println!("{} ({})", archive.path, report.robot_id.0);
+            println!("  imported:   {}", report.imported.len());
+            println!("  duplicates: {}", report.duplicates.len());
+            for conflict in &report.conflicts {
+                println!(
+                    "  CONFLICT #{}: stored {} != imported {}",
+                    conflict.sequence,
+                    hex::encode(conflict.stored_hash),
+                    hex::encode(conflict.imported_hash)
+                );
+            }
+            if let Some((sequence, detail)) = &report.rejected {
+                println!(
+                    "  REJECTED #{sequence} [{}] {}",
+                    detail.code, detail.message
+                );
+            }
+        }
+    }
+}
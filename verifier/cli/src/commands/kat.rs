@@ -0,0 +1,106 @@
+//! `veribot kat`: known-answer test vectors for interoperability testing.
+
+use crate::output::{emit, OutputFormat, Report};
+use anyhow::{Context, Result};
+use attestation_core::{ErrorCoded, ErrorDetail, KatError, KatSuite};
+use clap::Subcommand;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Debug, Subcommand)]
+pub enum KatCommand {
+    /// Write the reference test vectors as JSON
+    Generate {
+        /// Output file
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Check a test vector file against this implementation
+    ///
+    /// Recomputes every key, leaf hash, root, checkpoint encoding, hash and
+    /// signature from the vectors' inputs. Exits 1 on the first mismatch.
+    Verify {
+        /// Test vector file (JSON)
+        vectors: PathBuf,
+    },
+}
+
+pub fn run(command: KatCommand, format: OutputFormat) -> Result<ExitCode> {
+    match command {
+        KatCommand::Generate { out } => {
+            let suite = KatSuite::generate()?;
+            std::fs::write(&out, serde_json::to_string_pretty(&suite)? + "\n")
+                .with_context(|| format!("writing {}", out.display()))?;
+            emit(format, &KatReport::new(&out, &suite, Ok(())))?;
+            Ok(ExitCode::SUCCESS)
+        }
+        KatCommand::Verify { vectors } => {
+            let json = std::fs::read_to_string(&vectors)
+                .with_context(|| format!("reading {}", vectors.display()))?;
+            let suite: KatSuite = serde_json::from_str(&json)
+                .with_context(|| format!("decoding {}", vectors.display()))?;
+            let report = KatReport::new(&vectors, &suite, suite.validate());
+            emit(format, &report)?;
+            Ok(if report.passed {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(1)
+            })
+        }
+    }
+}
+
+/// Result of `kat generate` or `kat verify`.
+#[derive(Debug, Serialize)]
+pub struct KatReport {
+    pub path: String,
+    pub version: u8,
+    pub vectors: Vec<String>,
+    pub passed: bool,
+    pub failure: Option<ErrorDetail>,
+}
+
+impl KatReport {
+    fn new(path: &std::path::Path, suite: &KatSuite, outcome: Result<(), KatError>) -> Self {
+        Self {
+            path: path.display().to_string(),
+            version: suite.version,
+            vectors: suite.vectors.iter().map(|v| v.name.clone()).collect(),
+            passed: outcome.is_ok(),
+            failure: outcome.err().map(|e| e.detail()),
+        }
+    }
+}
+
+impl Report for KatReport {
+    const SCHEMA: &'static str = "veribot.kat/v1";
+
+    fn write_text(&self) {
+        println!("Test vectors {} (suite v{})", self.path, self.version);
+        for name in &self.vectors {
+            println!("  {name}");
+        }
+        match &self.failure {
+            Some(failure) => println!("FAILED [{}] {}", failure.code, failure.message),
+            None => println!("ok"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_committed_vectors_match() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../test-vectors/kat-v2.json"
+        );
+        let json = std::fs::read_to_string(path).unwrap();
+        let suite: KatSuite = serde_json::from_str(&json).unwrap();
+        suite.validate().unwrap();
+        assert_eq!(suite, KatSuite::generate().unwrap());
+    }
+}
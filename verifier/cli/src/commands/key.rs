@@ -0,0 +1,272 @@
+//! `veribot key`: keystore management.
+
+use crate::keystore::{fingerprint, KdfParams, KeyAlgorithm, Keystore, SecretKey};
+use crate::output::{emit, OutputFormat, Report};
+use anyhow::{bail, Context, Result};
+use attestation_core::{KeyRotationCert, Signer};
+use clap::{Args, Subcommand};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Debug, Subcommand)]
+pub enum KeyCommand {
+    /// Generate a new key into an encrypted keystore
+    Generate {
+        #[arg(long, value_enum, default_value_t = KeyAlgorithm::Ed25519)]
+        algorithm: KeyAlgorithm,
+        /// Keystore file to create
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long)]
+        force: bool,
+        #[command(flatten)]
+        passphrase: Passphrase,
+    },
+
+    /// Import a raw secret key (hex file) into an encrypted keystore
+    Import {
+        #[arg(long, value_enum, default_value_t = KeyAlgorithm::Ed25519)]
+        algorithm: KeyAlgorithm,
+        /// File containing the 32-byte secret key as hex
+        #[arg(long)]
+        secret_file: PathBuf,
+        /// Keystore file to create
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long)]
+        force: bool,
+        #[command(flatten)]
+        passphrase: Passphrase,
+    },
+
+    /// Export the public key, or the decrypted secret key with --secret
+    Export {
+        #[arg(long)]
+        keystore: PathBuf,
+        /// Export the secret key (hex) instead of the public key
+        #[arg(long)]
+        secret: bool,
+        /// Write to a file instead of stdout
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[command(flatten)]
+        passphrase: Passphrase,
+    },
+
+    /// Show algorithm, fingerprint and public key (no passphrase needed)
+    Show {
+        #[arg(long)]
+        keystore: PathBuf,
+    },
+
+    /// Rotate to a new Ed25519 key and issue a key rotation certificate
+    Rotate {
+        /// Keystore of the outgoing key
+        #[arg(long)]
+        keystore: PathBuf,
+        /// Keystore file for the incoming key
+        #[arg(long)]
+        out: PathBuf,
+        /// First checkpoint sequence the new key will sign
+        #[arg(long)]
+        effective_sequence: u64,
+        /// Where to write the rotation certificate (canonical CBOR)
+        #[arg(long)]
+        cert: PathBuf,
+        #[arg(long)]
+        force: bool,
+        #[command(flatten)]
+        passphrase: Passphrase,
+    },
+}
+
+/// Where to read the keystore passphrase from.
+#[derive(Debug, Args)]
+pub struct Passphrase {
+    /// Environment variable holding the keystore passphrase
+    #[arg(long, default_value = "VERIBOT_PASSPHRASE")]
+    passphrase_env: String,
+}
+
+impl Passphrase {
+    pub(crate) fn read(&self) -> Result<String> {
+        std::env::var(&self.passphrase_env)
+            .with_context(|| format!("passphrase not set (export {})", self.passphrase_env))
+    }
+}
+
+pub fn run(command: KeyCommand, format: OutputFormat) -> Result<ExitCode> {
+    match command {
+        KeyCommand::Generate {
+            algorithm,
+            out,
+            force,
+            passphrase,
+        } => {
+            let secret = SecretKey::generate(algorithm);
+            let keystore = Keystore::encrypt(&secret, &passphrase.read()?, KdfParams::default())?;
+            keystore.save(&out, force)?;
+            emit(format, &KeyInfo::from(&keystore))?;
+        }
+
+        KeyCommand::Import {
+            algorithm,
+            secret_file,
+            out,
+            force,
+            passphrase,
+        } => {
+            let hex_secret = std::fs::read_to_string(&secret_file)
+                .with_context(|| format!("reading {}", secret_file.display()))?;
+            let bytes = hex::decode(hex_secret.trim()).context("secret file is not hex")?;
+            let secret = SecretKey::from_bytes(algorithm, &bytes)?;
+            let keystore = Keystore::encrypt(&secret, &passphrase.read()?, KdfParams::default())?;
+            keystore.save(&out, force)?;
+            emit(format, &KeyInfo::from(&keystore))?;
+        }
+
+        KeyCommand::Export {
+            keystore,
+            secret,
+            out,
+            passphrase,
+        } => {
+            let keystore = Keystore::load(&keystore)?;
+            let export = KeyExport {
+                key_id: keystore.key_id.clone(),
+                secret_key: secret
+                    .then(|| keystore.decrypt(&passphrase.read()?))
+                    .transpose()?
+                    .map(|key| hex::encode(key.to_bytes())),
+                public_key: keystore.public_key,
+            };
+            match out {
+                Some(path) => std::fs::write(&path, format!("{}\n", export.exported()))
+                    .with_context(|| format!("writing {}", path.display()))?,
+                None => emit(format, &export)?,
+            }
+        }
+
+        KeyCommand::Show { keystore } => emit(format, &KeyInfo::from(&Keystore::load(&keystore)?))?,
+
+        KeyCommand::Rotate {
+            keystore,
+            out,
+            effective_sequence,
+            cert,
+            force,
+            passphrase,
+        } => {
+            let passphrase = passphrase.read()?;
+            let SecretKey::Ed25519(old) = Keystore::load(&keystore)?.decrypt(&passphrase)? else {
+                bail!("rotation certificates are only issued for ed25519 checkpoint keys");
+            };
+            if cert.exists() && !force {
+                bail!(
+                    "{} already exists (use --force to overwrite)",
+                    cert.display()
+                );
+            }
+
+            let new = SecretKey::generate(KeyAlgorithm::Ed25519);
+            let SecretKey::Ed25519(new_key) = &new else {
+                unreachable!()
+            };
+            let rotation = KeyRotationCert::issue(
+                &Signer::new(old),
+                &new_key.verifying_key(),
+                effective_sequence,
+            )?;
+
+            let new_keystore = Keystore::encrypt(&new, &passphrase, KdfParams::default())?;
+            new_keystore.save(&out, force)?;
+            std::fs::write(&cert, rotation.to_bytes()?)
+                .with_context(|| format!("writing {}", cert.display()))?;
+
+            let report = RotationReport {
+                old_key_id: rotation.old_key_id.to_string(),
+                new_key_id: fingerprint(&new.public_key()),
+                effective_sequence: rotation.effective_sequence,
+                keystore: out.display().to_string(),
+                certificate: cert.display().to_string(),
+            };
+            emit(format, &report)?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Public details of a keystore.
+#[derive(Debug, Serialize)]
+pub struct KeyInfo {
+    pub algorithm: KeyAlgorithm,
+    pub key_id: String,
+    pub public_key: String,
+}
+
+impl From<&Keystore> for KeyInfo {
+    fn from(keystore: &Keystore) -> Self {
+        Self {
+            algorithm: keystore.algorithm,
+            key_id: keystore.key_id.clone(),
+            public_key: keystore.public_key.clone(),
+        }
+    }
+}
+
+impl Report for KeyInfo {
+    const SCHEMA: &'static str = "veribot.key/v1";
+
+    fn write_text(&self) {
+        println!("algorithm:  {}", self.algorithm);
+        println!("key id:     {}", self.key_id);
+        println!("public key: {}", self.public_key);
+    }
+}
+
+/// An exported key; `secret_key` is only present with `--secret`.
+#[derive(Debug, Serialize)]
+pub struct KeyExport {
+    pub key_id: String,
+    pub public_key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret_key: Option<String>,
+}
+
+impl KeyExport {
+    /// The requested key as hex.
+    fn exported(&self) -> &str {
+        self.secret_key.as_deref().unwrap_or(&self.public_key)
+    }
+}
+
+impl Report for KeyExport {
+    const SCHEMA: &'static str = "veribot.key.export/v1";
+
+    fn write_text(&self) {
+        println!("{}", self.exported());
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RotationReport {
+    pub old_key_id: String,
+    pub new_key_id: String,
+    pub effective_sequence: u64,
+    /// Keystore written for the new key
+    pub keystore: String,
+    /// Rotation certificate path
+    pub certificate: String,
+}
+
+impl Report for RotationReport {
+    const SCHEMA: &'static str = "veribot.key.rotate/v1";
+
+    fn write_text(&self) {
+        println!("old key id:         {}", self.old_key_id);
+        println!("new key id:         {}", self.new_key_id);
+        println!("effective sequence: {}", self.effective_sequence);
+        println!("certificate:        {}", self.certificate);
+    }
+}
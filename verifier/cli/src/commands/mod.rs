@@ -0,0 +1,14 @@
+//! CLI subcommands.
+
+pub mod chain;
+pub mod conformance;
+pub mod export;
+pub mod gc;
+pub mod import;
+pub mod kat;
+pub mod key;
+pub mod notary;
+pub mod proof;
+pub mod quote;
+pub mod vectors;
+pub mod watch;
@@ -0,0 +1,301 @@
+//! `veribot notary`: long-term re-notarization of archived evidence.
+//!
+//! Each evidence file gets a sidecar `<file>.notary.cbor` holding its
+//! [`EvidenceRecord`]. Run `notary renew` from a scheduled job: it notarizes
+//! new files and adds a fresh layer to records older than `--max-age-days`.
+
+use crate::commands::key::Passphrase;
+use crate::keystore::{Keystore, SecretKey};
+use crate::output::{emit, OutputFormat, Report};
+use crate::pubkey::load_verifying_key;
+use anyhow::{bail, Context, Result};
+use attestation_core::{
+    AlgorithmPolicy, DigestAlgorithm, ErrorCode, ErrorCoded, EvidenceRecord, KeyRing,
+    SignatureAlgorithm, Signer,
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use clap::Subcommand;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(Debug, Subcommand)]
+pub enum NotaryCommand {
+    /// Notarize new evidence files and renew records that are due
+    Renew {
+        /// Evidence files
+        #[arg(required = true)]
+        evidence: Vec<PathBuf>,
+        /// Ed25519 keystore of the notary
+        #[arg(long)]
+        keystore: PathBuf,
+        /// Digest algorithm for new layers (sha256, sha3-256, blake3)
+        #[arg(long, value_parser = parse_digest, default_value = "sha3-256")]
+        algorithm: DigestAlgorithm,
+        /// Renew records whose latest layer is at least this old
+        #[arg(long, default_value_t = 365)]
+        max_age_days: i64,
+        /// Renew every record regardless of age
+        #[arg(long)]
+        force: bool,
+        #[command(flatten)]
+        passphrase: Passphrase,
+    },
+
+    /// Verify an evidence file against its notarization record
+    ///
+    /// Exits 0 when every layer verifies, 1 when one does not.
+    Verify {
+        evidence: PathBuf,
+        /// Record file (default: <evidence>.notary.cbor)
+        #[arg(long)]
+        record: Option<PathBuf>,
+        /// Trusted notary key (PEM, hex or keystore); repeatable
+        #[arg(long = "pubkey", required = true)]
+        pubkeys: Vec<PathBuf>,
+        /// Retired algorithm as ALGORITHM=DATE (e.g. sha256=2035-01-01); repeatable
+        #[arg(long = "retire", value_parser = parse_retirement)]
+        retirements: Vec<(String, DateTime<Utc>)>,
+    },
+}
+
+pub fn run(command: NotaryCommand, format: OutputFormat) -> Result<ExitCode> {
+    match command {
+        NotaryCommand::Renew {
+            evidence,
+            keystore,
+            algorithm,
+            max_age_days,
+            force,
+            passphrase,
+        } => {
+            let SecretKey::Ed25519(signing_key) =
+                Keystore::load(&keystore)?.decrypt(&passphrase.read()?)?
+            else {
+                bail!("notaries sign with ed25519; the keystore holds a different key type");
+            };
+            let notary = Signer::new(signing_key);
+            let max_age = Duration::days(max_age_days);
+
+            let mut report = RenewReport { files: Vec::new() };
+            for path in &evidence {
+                let data =
+                    std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+                let record_path = record_path(path);
+                let (record, action) = if record_path.exists() {
+                    let mut record = load_record(&record_path)?;
+                    if force || record.is_due(Utc::now(), max_age) {
+                        record.renew(&data, algorithm, &notary)?;
+                        (record, RenewAction::Renewed)
+                    } else {
+                        (record, RenewAction::NotDue)
+                    }
+                } else {
+                    (
+                        EvidenceRecord::notarize(&data, algorithm, &notary)?,
+                        RenewAction::Notarized,
+                    )
+                };
+                if action != RenewAction::NotDue {
+                    std::fs::write(&record_path, record.to_bytes()?)
+                        .with_context(|| format!("writing {}", record_path.display()))?;
+                }
+                report.files.push(RenewedFile {
+                    evidence: path.display().to_string(),
+                    action,
+                    layers: record.layers.len(),
+                });
+            }
+            emit(format, &report)?;
+            Ok(ExitCode::SUCCESS)
+        }
+
+        NotaryCommand::Verify {
+            evidence,
+            record,
+            pubkeys,
+            retirements,
+        } => {
+            let data = std::fs::read(&evidence)
+                .with_context(|| format!("reading {}", evidence.display()))?;
+            let record = load_record(&record.unwrap_or_else(|| record_path(&evidence)))?;
+
+            let mut notaries = KeyRing::new();
+            for path in &pubkeys {
+                notaries.insert(load_verifying_key(path)?);
+            }
+            let mut policy = AlgorithmPolicy::new();
+            for (algorithm, at) in retirements {
+                policy = match algorithm.as_str() {
+                    "ed25519" => policy.retire_signature(SignatureAlgorithm::Ed25519, at),
+                    name => policy.retire_digest(parse_digest(name)?, at),
+                };
+            }
+
+            let result = record.verify(&data, &notaries, &policy, Utc::now());
+            let report = VerifyReport {
+                passed: result.is_ok(),
+                evidence: evidence.display().to_string(),
+                layers: record
+                    .layers
+                    .iter()
+                    .map(|layer| LayerInfo {
+                        notarized_at: layer.notarized_at,
+                        digest_algorithm: layer.digest_algorithm.to_string(),
+                        signature_algorithm: layer.signature_algorithm.to_string(),
+                        notary_key_id: layer.notary_key_id.to_string(),
+                    })
+                    .collect(),
+                failure: result.err().map(|e| NotaryFailure {
+                    code: e.code(),
+                    message: e.to_string(),
+                }),
+            };
+            emit(format, &report)?;
+            Ok(if report.passed {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(1)
+            })
+        }
+    }
+}
+
+/// Sidecar record path for an evidence file.
+fn record_path(evidence: &Path) -> PathBuf {
+    let mut name = evidence.as_os_str().to_owned();
+    name.push(".notary.cbor");
+    PathBuf::from(name)
+}
+
+fn load_record(path: &Path) -> Result<EvidenceRecord> {
+    let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    EvidenceRecord::from_bytes(&bytes)
+        .with_context(|| format!("decoding notarization record {}", path.display()))
+}
+
+fn parse_digest(value: &str) -> Result<DigestAlgorithm> {
+    Ok(match value {
+        "sha256" => DigestAlgorithm::Sha256,
+        "sha3-256" => DigestAlgorithm::Sha3_256,
+        "blake3" => DigestAlgorithm::Blake3,
+        other => bail!("unknown digest algorithm {other:?} (sha256, sha3-256, blake3)"),
+    })
+}
+
+fn parse_retirement(value: &str) -> Result<(String, DateTime<Utc>)> {
+    let (algorithm, date) = value.split_once('=').context("expected ALGORITHM=DATE")?;
+    let at = match DateTime::parse_from_rfc3339(date) {
+        Ok(at) => at.with_timezone(&Utc),
+        Err(_) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .context("expected an RFC 3339 timestamp or YYYY-MM-DD date")?
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight is valid")
+            .and_utc(),
+    };
+    Ok((algorithm.to_string(), at))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenewAction {
+    /// First notarization
+    Notarized,
+    /// A new layer was added
+    Renewed,
+    /// Latest layer is recent enough
+    NotDue,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenewedFile {
+    pub evidence: String,
+    pub action: RenewAction,
+    pub layers: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenewReport {
+    pub files: Vec<RenewedFile>,
+}
+
+impl Report for RenewReport {
+    const SCHEMA: &'static str = "veribot.notary.renew/v1";
+
+    fn write_text(&self) {
+        for file in &self.files {
+            let action = match file.action {
+                RenewAction::Notarized => "notarized",
+                RenewAction::Renewed => "renewed",
+                RenewAction::NotDue => "not due",
+            };
+            println!("{:<10} {} ({} layers)", action, file.evidence, file.layers);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LayerInfo {
+    pub notarized_at: DateTime<Utc>,
+    pub digest_algorithm: String,
+    pub signature_algorithm: String,
+    pub notary_key_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotaryFailure {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyReport {
+    pub passed: bool,
+    pub evidence: String,
+    pub layers: Vec<LayerInfo>,
+    pub failure: Option<NotaryFailure>,
+}
+
+impl Report for VerifyReport {
+    const SCHEMA: &'static str = "veribot.notary.verify/v1";
+
+    fn write_text(&self) {
+        println!("evidence: {}", self.evidence);
+        for layer in &self.layers {
+            println!(
+                "layer:    {} {}/{} by {}",
+                layer.notarized_at.to_rfc3339(),
+                layer.digest_algorithm,
+                layer.signature_algorithm,
+                layer.notary_key_id
+            );
+        }
+        if let Some(failure) = &self.failure {
+            println!("failure:  [{}] {}", failure.code, failure.message);
+        }
+        println!("result:   {}", if self.passed { "PASS" } else { "FAIL" });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retirement() {
+        let (algorithm, at) = parse_retirement("sha256=2035-01-01").unwrap();
+        assert_eq!(algorithm, "sha256");
+        assert_eq!(at.to_rfc3339(), "2035-01-01T00:00:00+00:00");
+        assert!(parse_retirement("ed25519=2040-06-01T12:00:00Z").is_ok());
+        assert!(parse_retirement("sha256").is_err());
+        assert!(parse_digest("md5").is_err());
+    }
+
+    #[test]
+    fn test_record_path() {
+        assert_eq!(
+            record_path(Path::new("/archive/chain.cbor")),
+            PathBuf::from("/archive/chain.cbor.notary.cbor")
+        );
+    }
+}
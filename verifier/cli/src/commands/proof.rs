@@ -0,0 +1,239 @@
+//! `veribot proof`: one-off evidence checks.
+
+use crate::output::{emit, OutputFormat, Report};
+use crate::pubkey::load_verifying_key;
+use anyhow::{Context, Result};
+use attestation_core::crypto::sha256;
+use attestation_core::serialization::from_canonical_cbor;
+use attestation_core::{Checkpoint, MerkleProof, VerifyingKey};
+use chrono::{DateTime, Utc};
+use clap::Subcommand;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Debug, Subcommand)]
+pub enum ProofCommand {
+    /// Check that a disclosed entry is committed to by a signed checkpoint
+    ///
+    /// Verifies entry -> entries_root (Merkle proof) and the checkpoint
+    /// signature, then prints the disclosed entry. Exits 1 if any check fails.
+    Verify {
+        /// Merkle inclusion proof (canonical CBOR)
+        #[arg(long)]
+        proof: PathBuf,
+        /// Signed checkpoint (canonical CBOR)
+        #[arg(long)]
+        checkpoint: PathBuf,
+        /// Robot verifying key (PEM, hex or keystore)
+        #[arg(long)]
+        pubkey: PathBuf,
+        /// Original entry data, to check it against the disclosed hash
+        #[arg(long)]
+        data: Option<PathBuf>,
+    },
+}
+
+pub fn run(command: ProofCommand, format: OutputFormat) -> Result<ExitCode> {
+    match command {
+        ProofCommand::Verify {
+            proof,
+            checkpoint,
+            pubkey,
+            data,
+        } => {
+            let proof_bytes =
+                std::fs::read(&proof).with_context(|| format!("reading {}", proof.display()))?;
+            let proof: MerkleProof = from_canonical_cbor(&proof_bytes)
+                .with_context(|| format!("decoding proof {}", proof.display()))?;
+            let checkpoint_bytes = std::fs::read(&checkpoint)
+                .with_context(|| format!("reading {}", checkpoint.display()))?;
+            let checkpoint = Checkpoint::from_bytes(&checkpoint_bytes)
+                .with_context(|| format!("decoding checkpoint {}", checkpoint.display()))?;
+            let key = load_verifying_key(&pubkey)?;
+            let data = match data {
+                Some(path) => Some(
+                    std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?,
+                ),
+                None => None,
+            };
+
+            let checks = verify_proof(&proof, &checkpoint, &key, data.as_deref());
+            let report = ProofReport::new(&proof, &checkpoint, checks);
+            emit(format, &report)?;
+
+            Ok(if report.passed {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(1)
+            })
+        }
+    }
+}
+
+/// Outcome of one verification step.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub passed: bool,
+}
+
+/// Run every check (rather than stopping at the first failure) so the
+/// report shows exactly which link of entry -> root -> signature broke.
+pub fn verify_proof(
+    proof: &MerkleProof,
+    checkpoint: &Checkpoint,
+    key: &VerifyingKey,
+    data: Option<&[u8]>,
+) -> Vec<Check> {
+    let mut checks = Vec::new();
+    if let Some(data) = data {
+        checks.push(Check {
+            name: "entry data hash",
+            passed: sha256(data) == proof.leaf.data_hash,
+        });
+    }
+    checks.push(Check {
+        name: "entry in entries_root",
+        passed: checkpoint.includes_entry(proof),
+    });
+    checks.push(Check {
+        name: "checkpoint signature",
+        passed: checkpoint.verify_signature(key).is_ok(),
+    });
+    checks
+}
+
+/// Result of `proof verify`: the disclosed entry, the committing
+/// checkpoint and every check performed.
+#[derive(Debug, Serialize)]
+pub struct ProofReport {
+    pub passed: bool,
+    pub entry: DisclosedEntry,
+    pub checkpoint: CheckpointRef,
+    pub checks: Vec<Check>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DisclosedEntry {
+    pub timestamp_us: u64,
+    /// RFC 3339 rendering of `timestamp_us`, if representable
+    pub timestamp: Option<String>,
+    pub nonce: u64,
+    pub data_hash: String,
+    pub leaf_index: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckpointRef {
+    pub robot_id: String,
+    pub mission_id: String,
+    pub sequence: u64,
+    pub timestamp: String,
+    pub signer_key_id: String,
+}
+
+impl ProofReport {
+    fn new(proof: &MerkleProof, checkpoint: &Checkpoint, checks: Vec<Check>) -> Self {
+        let entry = &proof.leaf;
+        Self {
+            passed: checks.iter().all(|c| c.passed),
+            entry: DisclosedEntry {
+                timestamp_us: entry.timestamp_us,
+                timestamp: i64::try_from(entry.timestamp_us)
+                    .ok()
+                    .and_then(DateTime::<Utc>::from_timestamp_micros)
+                    .map(|ts| ts.to_rfc3339()),
+                nonce: entry.nonce,
+                data_hash: hex::encode(entry.data_hash),
+                leaf_index: proof.leaf_index,
+            },
+            checkpoint: CheckpointRef {
+                robot_id: checkpoint.robot_id.0.clone(),
+                mission_id: checkpoint.mission_id.0.clone(),
+                sequence: checkpoint.sequence,
+                timestamp: checkpoint.local_timestamp_utc.to_rfc3339(),
+                signer_key_id: checkpoint.signer_key_id.to_string(),
+            },
+            checks,
+        }
+    }
+}
+
+impl Report for ProofReport {
+    const SCHEMA: &'static str = "veribot.proof.verify/v1";
+
+    fn write_text(&self) {
+        let entry = &self.entry;
+        println!("Disclosed entry");
+        println!(
+            "  timestamp:   {} ({})",
+            entry.timestamp_us,
+            entry.timestamp.as_deref().unwrap_or("out of range")
+        );
+        println!("  nonce:       {}", entry.nonce);
+        println!("  data hash:   {}", entry.data_hash);
+        println!("  leaf index:  {}", entry.leaf_index);
+        println!("Checkpoint");
+        println!("  robot:       {}", self.checkpoint.robot_id);
+        println!("  mission:     {}", self.checkpoint.mission_id);
+        println!("  sequence:    {}", self.checkpoint.sequence);
+        println!("  timestamp:   {}", self.checkpoint.timestamp);
+        println!("  signer:      {}", self.checkpoint.signer_key_id);
+        println!();
+        for check in &self.checks {
+            let status = if check.passed { "ok" } else { "FAILED" };
+            println!("{:<24} {status}", check.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use attestation_core::{CheckpointBuilder, Entry, MerkleTree, Signer};
+
+    fn fixture(signer: &Signer) -> (MerkleProof, Checkpoint) {
+        let mut tree = MerkleTree::new();
+        for i in 0..5 {
+            tree.insert(Entry::new(
+                1_700_000_000_000_000 + i,
+                i,
+                format!("entry-{i}").as_bytes(),
+            ));
+        }
+        let proof = tree.generate_proof(1_700_000_000_000_002, 2).unwrap();
+
+        let checkpoint = CheckpointBuilder::test_fixture()
+            .entries_root(tree.root())
+            .build_and_sign(signer.signing_key())
+            .unwrap();
+        (proof, checkpoint)
+    }
+
+    #[test]
+    fn test_valid_proof_passes_all_checks() {
+        let signer = Signer::generate();
+        let (proof, checkpoint) = fixture(&signer);
+
+        let checks = verify_proof(
+            &proof,
+            &checkpoint,
+            &signer.verifying_key(),
+            Some(b"entry-2"),
+        );
+        assert_eq!(checks.len(), 3);
+        assert!(checks.iter().all(|c| c.passed));
+    }
+
+    #[test]
+    fn test_reports_each_broken_link() {
+        let signer = Signer::generate();
+        let (mut proof, checkpoint) = fixture(&signer);
+        proof.leaf.nonce = 99;
+
+        let other = Signer::generate().verifying_key();
+        let checks = verify_proof(&proof, &checkpoint, &other, Some(b"entry-3"));
+        assert!(checks.iter().all(|c| !c.passed));
+    }
+}
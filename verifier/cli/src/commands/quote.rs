@@ -0,0 +1,371 @@
+//! `veribot quote`: SGX quote inspection and offline collateral.
+
+use crate::commands::key::Passphrase;
+use crate::keystore::{Keystore, SecretKey};
+use crate::output::{emit, OutputFormat, Report};
+use crate::pubkey::load_verifying_key;
+use anyhow::{bail, Context, Result};
+use attestation_core::{
+    AttestationAdapter, AttestationError, AttestationResult, ErrorCode, ErrorCoded,
+    RevocationStatus, SignedCollateralBundle, Signer,
+};
+use attestation_sgx::dcap::{CollateralSource, PccsApiVersion};
+use attestation_sgx::quote::{parse_sgx_quote_v3, SgxQuoteV3};
+use attestation_sgx::{SgxConfig, SgxDcapAdapter};
+use chrono::{DateTime, Utc};
+use clap::Subcommand;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(Debug, Subcommand)]
+pub enum QuoteCommand {
+    /// Print the fields of a raw SGX DCAP quote (v3)
+    ///
+    /// With --verify, also runs full DCAP verification and exits 1 if it
+    /// fails.
+    Decode {
+        /// Raw quote file
+        file: PathBuf,
+        /// Run full verification after decoding
+        #[arg(long)]
+        verify: bool,
+        /// Trust anchor TOML (root CAs and pins) to verify against
+        #[arg(long, requires = "verify")]
+        trust_anchors: Option<PathBuf>,
+        /// Signed collateral bundle to verify against instead of fetching
+        #[arg(long, requires_all = ["verify", "collateral_key"])]
+        collateral: Option<PathBuf>,
+        /// Key of the collateral exporter (PEM, hex or keystore)
+        #[arg(long)]
+        collateral_key: Option<PathBuf>,
+        /// Accept debug enclaves
+        #[arg(long)]
+        allow_debug: bool,
+    },
+
+    /// Fetch SGX collateral from PCS into a signed bundle
+    ///
+    /// Run on a connected machine; air-gapped verifiers then pass the
+    /// bundle to `quote decode --verify --collateral`.
+    ExportCollateral {
+        /// Bundle file to write
+        #[arg(long)]
+        out: PathBuf,
+        /// Ed25519 keystore of the exporter
+        #[arg(long)]
+        keystore: PathBuf,
+        /// Platform FMSPC (hex) whose TCB info to include; repeatable
+        #[arg(long = "fmspc")]
+        fmspcs: Vec<String>,
+        /// Trust anchor TOML whose root CA the bundle carries
+        #[arg(long)]
+        trust_anchors: Option<PathBuf>,
+        /// PCS base URL (default: Intel PCS)
+        #[arg(long)]
+        pcs_url: Option<String>,
+        /// Fetch from the PCCS at this URL (scheme, host and port) instead of PCS
+        #[arg(long, conflicts_with = "pcs_url")]
+        pccs_url: Option<String>,
+        /// PCCS API version
+        #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u8).range(3..=4), requires = "pccs_url")]
+        pccs_api_version: u8,
+        /// Environment variable holding the PCS subscription key or PCCS user token, if any
+        #[arg(long, default_value = "VERIBOT_PCS_API_KEY")]
+        api_key_env: String,
+        #[command(flatten)]
+        passphrase: Passphrase,
+    },
+}
+
+pub fn run(command: QuoteCommand, format: OutputFormat) -> Result<ExitCode> {
+    match command {
+        QuoteCommand::Decode {
+            file,
+            verify,
+            trust_anchors,
+            collateral,
+            collateral_key,
+            allow_debug,
+        } => {
+            let bytes =
+                std::fs::read(&file).with_context(|| format!("reading {}", file.display()))?;
+            let quote = parse_sgx_quote_v3(&bytes).context("decoding SGX quote")?;
+            let mut report = QuoteReport::from(&quote);
+            if !verify {
+                emit(format, &report)?;
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let config = SgxConfig {
+                allow_debug,
+                ..SgxConfig::default()
+            };
+            let adapter = match &trust_anchors {
+                Some(path) => SgxDcapAdapter::with_trust_anchor_file(config, path)?,
+                None => SgxDcapAdapter::with_config(config),
+            };
+            let collateral = match (collateral, collateral_key) {
+                (Some(bundle), Some(key)) => Some((bundle, key)),
+                _ => None,
+            };
+
+            let runtime = tokio::runtime::Runtime::new()?;
+            let result = runtime.block_on(verify_quote(&adapter, &bytes, collateral))?;
+
+            let verification = QuoteVerification::from(result);
+            let passed = verification.passed;
+            report.verification = Some(verification);
+            emit(format, &report)?;
+
+            Ok(if passed {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(1)
+            })
+        }
+        QuoteCommand::ExportCollateral {
+            out,
+            keystore,
+            fmspcs,
+            trust_anchors,
+            pcs_url,
+            pccs_url,
+            pccs_api_version,
+            api_key_env,
+            passphrase,
+        } => {
+            let SecretKey::Ed25519(signing_key) =
+                Keystore::load(&keystore)?.decrypt(&passphrase.read()?)?
+            else {
+                bail!("collateral exporters sign with ed25519; the keystore holds a different key type");
+            };
+            let mut config = SgxConfig::default();
+            if let Some(url) = pcs_url {
+                config.pcs_url = url;
+            }
+            if let Some(url) = pccs_url {
+                let api_version = match pccs_api_version {
+                    3 => PccsApiVersion::V3,
+                    _ => PccsApiVersion::V4,
+                };
+                config.collateral_source = CollateralSource::Pccs { url, api_version };
+            }
+            config.api_key = std::env::var(&api_key_env).ok();
+            let pcs = config.pcs_client();
+            let adapter = match &trust_anchors {
+                Some(path) => SgxDcapAdapter::with_trust_anchor_file(config, path)?,
+                None => SgxDcapAdapter::with_config(config),
+            }
+            .with_pcs_client(pcs);
+
+            let fmspcs: Vec<&str> = fmspcs.iter().map(String::as_str).collect();
+            let runtime = tokio::runtime::Runtime::new()?;
+            let bundle = runtime
+                .block_on(adapter.export_collateral(&fmspcs))
+                .context("fetching collateral")?;
+            let report = CollateralReport {
+                path: out.display().to_string(),
+                fetched_at: bundle.fetched_at,
+                valid_until: bundle.valid_until,
+                crls: bundle.crls.len(),
+                documents: bundle.documents.keys().cloned().collect(),
+            };
+            let signed = bundle.sign(&Signer::new(signing_key))?;
+            std::fs::write(&out, signed.export()?)
+                .with_context(|| format!("writing {}", out.display()))?;
+            emit(format, &report)?;
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+/// Verify with a signed collateral bundle if given, otherwise with the
+/// adapter's own (refreshed) trust anchors.
+///
+/// The outer error means verification could not be attempted; the inner
+/// one is the verdict.
+async fn verify_quote(
+    adapter: &SgxDcapAdapter,
+    quote: &[u8],
+    collateral: Option<(PathBuf, PathBuf)>,
+) -> Result<Result<AttestationResult, AttestationError>> {
+    match collateral {
+        Some((bundle, key)) => {
+            let bundle = load_collateral(&bundle, &key)?;
+            Ok(adapter.verify_with_collateral(quote, None, &bundle).await)
+        }
+        None => {
+            adapter.update_trust_anchors().await?;
+            Ok(adapter.verify_quote(quote, None).await)
+        }
+    }
+}
+
+fn load_collateral(path: &Path, key: &Path) -> Result<attestation_core::CollateralBundle> {
+    let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let exporter = load_verifying_key(key)?;
+    SignedCollateralBundle::import(&bytes, &exporter)
+        .with_context(|| format!("importing collateral {}", path.display()))
+}
+
+/// Decoded quote fields, plus the verdict when `--verify` was given.
+#[derive(Debug, Serialize)]
+pub struct QuoteReport {
+    pub version: u16,
+    pub attestation_key_type: u16,
+    pub qe_svn: u16,
+    pub pce_svn: u16,
+    pub mr_enclave: String,
+    pub mr_signer: String,
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+    pub debug: bool,
+    pub report_data: String,
+    pub signature_len: usize,
+    pub cert_data_present: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<QuoteVerification>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuoteVerification {
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revocation: Option<RevocationStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl From<&SgxQuoteV3> for QuoteReport {
+    fn from(quote: &SgxQuoteV3) -> Self {
+        Self {
+            version: quote.version,
+            attestation_key_type: quote.attestation_key_type,
+            qe_svn: quote.qe_svn,
+            pce_svn: quote.pce_svn,
+            mr_enclave: hex::encode(quote.mr_enclave),
+            mr_signer: hex::encode(quote.mr_signer),
+            isv_prod_id: quote.isv_prod_id,
+            isv_svn: quote.isv_svn,
+            debug: quote.debug_mode,
+            report_data: hex::encode(quote.report_data),
+            signature_len: quote.signature.len(),
+            cert_data_present: quote.certification_data.is_some(),
+            verification: None,
+        }
+    }
+}
+
+impl From<Result<AttestationResult, AttestationError>> for QuoteVerification {
+    fn from(result: Result<AttestationResult, AttestationError>) -> Self {
+        match result {
+            Ok(result) => Self {
+                passed: true,
+                revocation: Some(result.revoke_check.status),
+                code: None,
+                message: None,
+            },
+            Err(e) => Self {
+                passed: false,
+                revocation: None,
+                code: Some(e.code()),
+                message: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+impl Report for QuoteReport {
+    const SCHEMA: &'static str = "veribot.quote.decode/v1";
+
+    fn write_text(&self) {
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        println!("version                {}", self.version);
+        println!("attestation key type   {}", self.attestation_key_type);
+        println!("qe svn                 {}", self.qe_svn);
+        println!("pce svn                {}", self.pce_svn);
+        println!("mrenclave              {}", self.mr_enclave);
+        println!("mrsigner               {}", self.mr_signer);
+        println!("isv prod id            {}", self.isv_prod_id);
+        println!("isv svn                {}", self.isv_svn);
+        println!("debug                  {}", yes_no(self.debug));
+        println!("report data            {}", self.report_data);
+        println!("signature length       {}", self.signature_len);
+        println!("cert data present      {}", yes_no(self.cert_data_present));
+
+        let Some(verification) = &self.verification else {
+            return;
+        };
+        println!();
+        if verification.passed {
+            println!("verification           ok");
+        } else {
+            println!(
+                "verification           FAILED [{}] {}",
+                verification.code.map_or("-", |c| c.as_str()),
+                verification.message.as_deref().unwrap_or_default()
+            );
+        }
+        if let Some(status) = verification.revocation {
+            println!("revocation             {status:?}");
+        }
+    }
+}
+
+/// An exported collateral bundle.
+#[derive(Debug, Serialize)]
+pub struct CollateralReport {
+    pub path: String,
+    pub fetched_at: DateTime<Utc>,
+    pub valid_until: DateTime<Utc>,
+    pub crls: usize,
+    pub documents: Vec<String>,
+}
+
+impl Report for CollateralReport {
+    const SCHEMA: &'static str = "veribot.quote.export-collateral/v1";
+
+    fn write_text(&self) {
+        println!("wrote {}", self.path);
+        println!("fetched at   {}", self.fetched_at);
+        println!("valid until  {}", self.valid_until);
+        println!("crls         {}", self.crls);
+        for document in &self.documents {
+            println!("document     {document}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_report() {
+        let mut bytes = vec![0u8; 48 + 384 + 4];
+        bytes[0] = 3;
+        bytes[48 + 48] = 0x02; // debug attribute
+        bytes[48 + 258] = 7; // isv_svn
+
+        let report = QuoteReport::from(&parse_sgx_quote_v3(&bytes).unwrap());
+        assert_eq!(report.version, 3);
+        assert_eq!(report.isv_svn, 7);
+        assert!(report.debug);
+        assert!(!report.cert_data_present);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert!(json.get("verification").is_none());
+    }
+
+    #[test]
+    fn test_verification_failure_carries_code() {
+        let verification = QuoteVerification::from(Err(AttestationError::VerificationFailed(
+            "Debug enclaves are not allowed".to_string(),
+        )));
+        assert!(!verification.passed);
+        assert_eq!(verification.code, Some(ErrorCode::VerificationFailed));
+    }
+}
@@ -0,0 +1,99 @@
+//! `veribot vectors`: cross-language interop vectors with expected verdicts.
+
+use crate::interop::{check, export, CaseResult};
+use crate::output::{emit, OutputFormat, Report};
+use anyhow::Result;
+use clap::Subcommand;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Debug, Subcommand)]
+pub enum VectorsCommand {
+    /// Write the reference vector directory (manifest.json plus input files)
+    Export {
+        /// Output directory
+        #[arg(long)]
+        dir: PathBuf,
+    },
+    /// Check every case of a vector directory against this implementation
+    ///
+    /// Exits 1 if any verdict differs from the manifest.
+    Check {
+        /// Vector directory (containing manifest.json)
+        #[arg(long)]
+        dir: PathBuf,
+    },
+}
+
+pub fn run(command: VectorsCommand, format: OutputFormat) -> Result<ExitCode> {
+    match command {
+        VectorsCommand::Export { dir } => {
+            let manifest = export(&dir)?;
+            let report = VectorsReport {
+                dir: dir.display().to_string(),
+                version: manifest.version,
+                cases: manifest.cases.len(),
+                failed: Vec::new(),
+            };
+            emit(format, &report)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        VectorsCommand::Check { dir } => {
+            let (manifest, results) = check(&dir)?;
+            let report = VectorsReport {
+                dir: dir.display().to_string(),
+                version: manifest.version,
+                cases: results.len(),
+                failed: results
+                    .into_iter()
+                    .filter(|result| !result.passed)
+                    .collect(),
+            };
+            emit(format, &report)?;
+            Ok(if report.failed.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(1)
+            })
+        }
+    }
+}
+
+/// Result of `vectors export` or `vectors check`.
+#[derive(Debug, Serialize)]
+pub struct VectorsReport {
+    pub dir: String,
+    pub version: u32,
+    pub cases: usize,
+    /// Cases whose verdict differs from the manifest
+    pub failed: Vec<CaseResult>,
+}
+
+impl Report for VectorsReport {
+    const SCHEMA: &'static str = "veribot.vectors/v1";
+
+    fn write_text(&self) {
+        println!("Interop vectors {} (v{})", self.dir, self.version);
+        println!("cases:  {}", self.cases);
+        for result in &self.failed {
+            println!(
+                "FAILED {}: expected {}, got {}",
+                result.name,
+                verdict_text(&result.expected),
+                verdict_text(&result.actual)
+            );
+        }
+        if self.failed.is_empty() {
+            println!("ok");
+        }
+    }
+}
+
+fn verdict_text(verdict: &crate::interop::Verdict) -> String {
+    match (verdict.valid, verdict.code) {
+        (true, _) => "valid".to_string(),
+        (false, Some(code)) => format!("rejected [{code}]"),
+        (false, None) => "rejected".to_string(),
+    }
+}
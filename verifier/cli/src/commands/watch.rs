@@ -0,0 +1,275 @@
+//! `veribot watch`: run the checkpointing agent over a log stream.
+
+use crate::agent::{Agent, AgentConfig, Cut};
+use crate::commands::key::Passphrase;
+use crate::keystore::{Keystore, SecretKey};
+use crate::output::{emit, OutputFormat, Report};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Args;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+/// How often a watched directory is rescanned
+const DIR_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Args)]
+pub struct WatchArgs {
+    /// Log source: a file or FIFO (one record per line, `-` for stdin), a
+    /// directory (one record per new file), or `mqtt://host[:port]/topic`
+    #[arg(long)]
+    input: String,
+    /// Agent configuration (robot, model and cadence)
+    #[arg(long)]
+    config: PathBuf,
+    /// Ed25519 keystore used to sign checkpoints
+    #[arg(long)]
+    keystore: PathBuf,
+    /// Directory for the chain archive and per-checkpoint entries
+    #[arg(long)]
+    out_dir: PathBuf,
+    #[command(flatten)]
+    passphrase: Passphrase,
+}
+
+/// A record from the input, or the end of input (EOF or Ctrl-C).
+enum Message {
+    Record(Vec<u8>),
+    End,
+}
+
+/// Ingest records until the input ends or the agent is interrupted; pending
+/// records are checkpointed before exiting.
+pub fn run(args: WatchArgs, format: OutputFormat) -> Result<ExitCode> {
+    let config = AgentConfig::load(&args.config)?;
+    let SecretKey::Ed25519(signing_key) =
+        Keystore::load(&args.keystore)?.decrypt(&args.passphrase.read()?)?
+    else {
+        bail!("checkpoints are signed with ed25519; the keystore holds a different key type");
+    };
+    let mut agent = Agent::open(config, signing_key, &args.out_dir)?;
+    if let Some(last) = agent.checkpoints().last() {
+        eprintln!("resuming chain after checkpoint #{}", last.sequence);
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let stop = tx.clone();
+    ctrlc::set_handler(move || {
+        let _ = stop.send(Message::End);
+    })?;
+    spawn_input(&args.input, tx)?;
+
+    loop {
+        let cut = match rx.recv_timeout(agent.until_due()) {
+            Ok(Message::Record(record)) => agent.ingest(&record, now_us())?,
+            Err(RecvTimeoutError::Timeout) => agent.tick()?,
+            Ok(Message::End) | Err(RecvTimeoutError::Disconnected) => {
+                if let Some(cut) = agent.cut()? {
+                    emit(format, &CutReport::from(&cut))?;
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+        };
+        if let Some(cut) = cut {
+            emit(format, &CutReport::from(&cut))?;
+        }
+    }
+}
+
+fn now_us() -> u64 {
+    chrono::Utc::now().timestamp_micros().max(0) as u64
+}
+
+/// Start a thread feeding records from `input` into `tx`.
+fn spawn_input(input: &str, tx: Sender<Message>) -> Result<()> {
+    if let Some(target) = input.strip_prefix("mqtt://") {
+        return spawn_mqtt(target, tx);
+    }
+    if input == "-" {
+        std::thread::spawn(move || read_lines(std::io::stdin().lock(), tx));
+        return Ok(());
+    }
+
+    let path = PathBuf::from(input);
+    if path.is_dir() {
+        std::thread::spawn(move || poll_dir(path, tx));
+    } else {
+        // Opening a FIFO blocks until a writer connects, so do it on the thread.
+        std::thread::spawn(move || match std::fs::File::open(&path) {
+            Ok(file) => read_lines(BufReader::new(file), tx),
+            Err(e) => {
+                eprintln!("error: opening {}: {e}", path.display());
+                let _ = tx.send(Message::End);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// One record per non-empty line, until end of input.
+fn read_lines(reader: impl BufRead, tx: Sender<Message>) {
+    for line in reader.split(b'\n') {
+        let mut line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("error: reading input: {e}");
+                break;
+            }
+        };
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        if !line.is_empty() && tx.send(Message::Record(line)).is_err() {
+            return;
+        }
+    }
+    // The Ctrl-C handler holds a sender too, so EOF must be signalled explicitly.
+    let _ = tx.send(Message::End);
+}
+
+/// One record per new file, in file name order. Hidden files are skipped
+/// so writers can stage files under a dot-name and rename them into place.
+fn poll_dir(dir: PathBuf, tx: Sender<Message>) {
+    let mut seen = HashSet::new();
+    loop {
+        let mut names: Vec<_> = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+                .map(|entry| entry.file_name())
+                .filter(|name| !name.to_string_lossy().starts_with('.') && !seen.contains(name))
+                .collect(),
+            Err(e) => {
+                eprintln!("error: reading {}: {e}", dir.display());
+                return;
+            }
+        };
+        names.sort();
+
+        for name in names {
+            let mut record = Vec::new();
+            let path = dir.join(&name);
+            match std::fs::File::open(&path).and_then(|mut f| f.read_to_end(&mut record)) {
+                Ok(_) => {
+                    if tx.send(Message::Record(record)).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => eprintln!("warning: skipping {}: {e}", path.display()),
+            }
+            seen.insert(name);
+        }
+        std::thread::sleep(DIR_POLL_INTERVAL);
+    }
+}
+
+/// Subscribe to `host[:port]/topic`; every publish is one record.
+fn spawn_mqtt(target: &str, tx: Sender<Message>) -> Result<()> {
+    use rumqttc::{Client, Event, MqttOptions, Packet, QoS};
+
+    let (authority, topic) = target
+        .split_once('/')
+        .filter(|(_, topic)| !topic.is_empty())
+        .ok_or_else(|| anyhow!("MQTT input must be mqtt://host[:port]/topic"))?;
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().context("invalid MQTT port")?),
+        None => (authority, 1883),
+    };
+
+    let mut options = MqttOptions::new(format!("veribot-watch-{}", std::process::id()), host, port);
+    options.set_keep_alive(Duration::from_secs(30));
+    let (client, mut connection) = Client::new(options, 64);
+    client.subscribe(topic, QoS::AtLeastOnce)?;
+
+    std::thread::spawn(move || {
+        // Keep the client alive for as long as the connection is polled.
+        let _client = client;
+        for event in connection.iter() {
+            match event {
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    if tx.send(Message::Record(publish.payload.to_vec())).is_err() {
+                        return;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("warning: MQTT connection error: {e}; retrying");
+                    std::thread::sleep(Duration::from_secs(1));
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// One checkpoint cut by the agent.
+#[derive(Debug, Serialize)]
+pub struct CutReport {
+    pub sequence: u64,
+    pub entries: usize,
+    pub entries_root: String,
+    pub hash: String,
+    pub entries_file: String,
+}
+
+impl From<&Cut> for CutReport {
+    fn from(cut: &Cut) -> Self {
+        Self {
+            sequence: cut.checkpoint.sequence,
+            entries: cut.entries,
+            entries_root: hex::encode(cut.checkpoint.entries_root),
+            hash: cut
+                .checkpoint
+                .compute_hash()
+                .map(hex::encode)
+                .unwrap_or_default(),
+            entries_file: cut.entries_file.display().to_string(),
+        }
+    }
+}
+
+impl Report for CutReport {
+    const SCHEMA: &'static str = "veribot.watch.checkpoint/v1";
+
+    fn write_text(&self) {
+        println!(
+            "checkpoint #{}: {} entries, root {}",
+            self.sequence, self.entries, self.entries_root
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_lines_skips_blank_and_strips_crlf() {
+        let (tx, rx) = mpsc::channel();
+        read_lines(&b"one\r\n\ntwo\nthree"[..], tx);
+
+        let mut records = Vec::new();
+        while let Ok(Message::Record(record)) = rx.recv() {
+            records.push(record);
+        }
+        assert!(matches!(
+            rx.try_recv(),
+            Err(mpsc::TryRecvError::Disconnected)
+        ));
+        assert_eq!(
+            records,
+            vec![b"one".to_vec(), b"two".to_vec(), b"three".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_rejects_mqtt_without_topic() {
+        let (tx, _rx) = mpsc::channel();
+        assert!(spawn_input("mqtt://localhost:1883", tx.clone()).is_err());
+        assert!(spawn_input("mqtt://localhost:1883/", tx).is_err());
+    }
+}
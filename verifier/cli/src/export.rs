@@ -0,0 +1,384 @@
+//! Flat analytics tables over checkpoint chains.
+//!
+//! Each checkpoint becomes one [`CheckpointRow`] carrying its provenance,
+//! trust mode, the gaps since the previous checkpoint and the outcome of
+//! verifying it in chain order, so fleet dashboards can be built in any BI
+//! tool that reads CSV or Parquet. Column names are identical in both
+//! formats.
+//!
+//! Anchor latency (time from a checkpoint's local timestamp to the block that
+//! anchored its `entries_root`) is filled from an optional anchors file: a
+//! JSON array of `CheckpointAnchored` events reduced to
+//! `{"merkle_root": "<hex>", "timestamp": <unix seconds>}`.
+
+use crate::archive::ChainArchive;
+use anyhow::{Context, Result};
+use arrow_array::builder::{
+    BooleanBuilder, Int64Builder, StringBuilder, TimestampMicrosecondBuilder, UInt64Builder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use attestation_core::{ChainVerifier, Checkpoint, ErrorCoded, Hash256, KeyResolver, TrustMode};
+use chrono::{DateTime, Utc};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Table file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TableFormat {
+    Csv,
+    /// Snappy-compressed Parquet
+    Parquet,
+}
+
+impl TableFormat {
+    /// Format implied by a file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "csv" => Some(TableFormat::Csv),
+            "parquet" => Some(TableFormat::Parquet),
+            _ => None,
+        }
+    }
+}
+
+/// One checkpoint, flattened.
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckpointRow {
+    pub robot_id: String,
+    pub mission_id: String,
+    pub sequence: u64,
+    pub monotonic_counter: u64,
+    pub timestamp: DateTime<Utc>,
+    pub trust_mode: &'static str,
+    pub model_name: String,
+    pub model_hash: String,
+    pub dataset_hash: Option<String>,
+    pub container_digest: Option<String>,
+    pub firmware_hash: String,
+    pub enclave_measurement: String,
+    pub signer_key_id: String,
+    pub checkpoint_hash: String,
+    /// Whether the checkpoint verified in chain order; every checkpoint
+    /// after the first failure is unverified
+    pub verified: bool,
+    /// Error code of the checkpoint that broke the chain
+    pub failure_code: Option<&'static str>,
+    /// Sequence numbers skipped since the previous checkpoint
+    pub sequence_gap: Option<u64>,
+    /// Counter values skipped since the previous checkpoint
+    pub counter_gap: Option<u64>,
+    /// Milliseconds since the previous checkpoint
+    pub interval_ms: Option<i64>,
+    /// Seconds from the local timestamp to the anchoring block
+    pub anchor_latency_secs: Option<i64>,
+}
+
+/// Anchoring block timestamps by Merkle root.
+#[derive(Debug, Default)]
+pub struct AnchorIndex(HashMap<Hash256, i64>);
+
+#[derive(Deserialize)]
+struct AnchorRecord {
+    merkle_root: String,
+    timestamp: i64,
+}
+
+impl AnchorIndex {
+    /// Read an anchors file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let json =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let records: Vec<AnchorRecord> =
+            serde_json::from_str(&json).with_context(|| format!("parsing {}", path.display()))?;
+
+        let mut index = HashMap::with_capacity(records.len());
+        for record in records {
+            let root: Hash256 = hex::decode(&record.merkle_root)
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .with_context(|| format!("invalid merkle_root {:?}", record.merkle_root))?;
+            // Re-anchoring the same root keeps the earliest block.
+            index
+                .entry(root)
+                .and_modify(|t: &mut i64| *t = (*t).min(record.timestamp))
+                .or_insert(record.timestamp);
+        }
+        Ok(Self(index))
+    }
+
+    fn latency(&self, checkpoint: &Checkpoint) -> Option<i64> {
+        self.0
+            .get(&checkpoint.entries_root)
+            .map(|anchored| anchored - checkpoint.local_timestamp_utc.timestamp())
+    }
+}
+
+/// Flatten one archive, verifying it in chain order against `keys`.
+pub fn rows(
+    archive: &ChainArchive,
+    keys: Box<dyn KeyResolver>,
+    anchors: &AnchorIndex,
+) -> Vec<CheckpointRow> {
+    let mut verifier = ChainVerifier::with_resolver(keys);
+    // A bad rotation certificate leaves the whole chain unverified.
+    let mut broken = archive
+        .rotations
+        .iter()
+        .any(|cert| verifier.add_rotation(cert).is_err());
+
+    let mut prev: Option<&Checkpoint> = None;
+    let mut rows = Vec::with_capacity(archive.checkpoints.len());
+    for checkpoint in &archive.checkpoints {
+        let mut failure_code = None;
+        if !broken {
+            if let Err(e) = verifier.verify_next(checkpoint) {
+                failure_code = Some(e.code().as_str());
+                broken = true;
+            }
+        }
+
+        let provenance = &checkpoint.model_provenance;
+        rows.push(CheckpointRow {
+            robot_id: checkpoint.robot_id.0.clone(),
+            mission_id: checkpoint.mission_id.0.clone(),
+            sequence: checkpoint.sequence,
+            monotonic_counter: checkpoint.monotonic_counter,
+            timestamp: checkpoint.local_timestamp_utc,
+            trust_mode: trust_mode_name(checkpoint.trust_mode),
+            model_name: provenance.name.clone(),
+            model_hash: hex::encode(provenance.model_hash),
+            dataset_hash: provenance.dataset_hash.map(hex::encode),
+            container_digest: provenance.container_digest.clone(),
+            firmware_hash: hex::encode(checkpoint.firmware_hash),
+            enclave_measurement: hex::encode(&checkpoint.enclave_measurement),
+            signer_key_id: checkpoint.signer_key_id.to_string(),
+            checkpoint_hash: checkpoint
+                .compute_hash()
+                .map(hex::encode)
+                .unwrap_or_default(),
+            verified: !broken,
+            failure_code,
+            sequence_gap: prev.map(|p| checkpoint.sequence.saturating_sub(p.sequence + 1)),
+            counter_gap: prev.map(|p| {
+                checkpoint
+                    .monotonic_counter
+                    .saturating_sub(p.monotonic_counter + 1)
+            }),
+            interval_ms: prev.map(|p| {
+                (checkpoint.local_timestamp_utc - p.local_timestamp_utc).num_milliseconds()
+            }),
+            anchor_latency_secs: anchors.latency(checkpoint),
+        });
+        prev = Some(checkpoint);
+    }
+    rows
+}
+
+fn trust_mode_name(mode: TrustMode) -> &'static str {
+    match mode {
+        TrustMode::Trusted => "trusted",
+        TrustMode::SoftAttestation => "soft_attestation",
+        TrustMode::Untrusted => "untrusted",
+    }
+}
+
+/// Write `rows` as CSV with a header line.
+pub fn write_csv<W: Write>(rows: &[CheckpointRow], writer: W) -> Result<()> {
+    let mut csv = csv::Writer::from_writer(writer);
+    for row in rows {
+        csv.serialize(row)?;
+    }
+    csv.flush()?;
+    Ok(())
+}
+
+/// Write `rows` as a single-row-group Parquet file.
+pub fn write_parquet<W: Write + Send>(rows: &[CheckpointRow], writer: W) -> Result<()> {
+    let batch = record_batch(rows)?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut parquet = ArrowWriter::try_new(writer, batch.schema(), Some(properties))?;
+    parquet.write(&batch)?;
+    parquet.close()?;
+    Ok(())
+}
+
+fn schema() -> Schema {
+    let utf8 = |name: &str, nullable: bool| Field::new(name, DataType::Utf8, nullable);
+    Schema::new(vec![
+        utf8("robot_id", false),
+        utf8("mission_id", false),
+        Field::new("sequence", DataType::UInt64, false),
+        Field::new("monotonic_counter", DataType::UInt64, false),
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            false,
+        ),
+        utf8("trust_mode", false),
+        utf8("model_name", false),
+        utf8("model_hash", false),
+        utf8("dataset_hash", true),
+        utf8("container_digest", true),
+        utf8("firmware_hash", false),
+        utf8("enclave_measurement", false),
+        utf8("signer_key_id", false),
+        utf8("checkpoint_hash", false),
+        Field::new("verified", DataType::Boolean, false),
+        utf8("failure_code", true),
+        Field::new("sequence_gap", DataType::UInt64, true),
+        Field::new("counter_gap", DataType::UInt64, true),
+        Field::new("interval_ms", DataType::Int64, true),
+        Field::new("anchor_latency_secs", DataType::Int64, true),
+    ])
+}
+
+fn record_batch(rows: &[CheckpointRow]) -> Result<RecordBatch> {
+    fn strings<'a>(values: impl Iterator<Item = Option<&'a str>>) -> ArrayRef {
+        let mut builder = StringBuilder::new();
+        values.for_each(|value| builder.append_option(value));
+        Arc::new(builder.finish())
+    }
+    fn u64s(values: impl Iterator<Item = Option<u64>>) -> ArrayRef {
+        let mut builder = UInt64Builder::new();
+        values.for_each(|value| builder.append_option(value));
+        Arc::new(builder.finish())
+    }
+    fn i64s(values: impl Iterator<Item = Option<i64>>) -> ArrayRef {
+        let mut builder = Int64Builder::new();
+        values.for_each(|value| builder.append_option(value));
+        Arc::new(builder.finish())
+    }
+
+    let mut timestamps = TimestampMicrosecondBuilder::new().with_timezone("UTC");
+    let mut verified = BooleanBuilder::new();
+    for row in rows {
+        timestamps.append_value(row.timestamp.timestamp_micros());
+        verified.append_value(row.verified);
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        strings(rows.iter().map(|r| Some(r.robot_id.as_str()))),
+        strings(rows.iter().map(|r| Some(r.mission_id.as_str()))),
+        u64s(rows.iter().map(|r| Some(r.sequence))),
+        u64s(rows.iter().map(|r| Some(r.monotonic_counter))),
+        Arc::new(timestamps.finish()),
+        strings(rows.iter().map(|r| Some(r.trust_mode))),
+        strings(rows.iter().map(|r| Some(r.model_name.as_str()))),
+        strings(rows.iter().map(|r| Some(r.model_hash.as_str()))),
+        strings(rows.iter().map(|r| r.dataset_hash.as_deref())),
+        strings(rows.iter().map(|r| r.container_digest.as_deref())),
+        strings(rows.iter().map(|r| Some(r.firmware_hash.as_str()))),
+        strings(rows.iter().map(|r| Some(r.enclave_measurement.as_str()))),
+        strings(rows.iter().map(|r| Some(r.signer_key_id.as_str()))),
+        strings(rows.iter().map(|r| Some(r.checkpoint_hash.as_str()))),
+        Arc::new(verified.finish()),
+        strings(rows.iter().map(|r| r.failure_code)),
+        u64s(rows.iter().map(|r| r.sequence_gap)),
+        u64s(rows.iter().map(|r| r.counter_gap)),
+        i64s(rows.iter().map(|r| r.interval_ms)),
+        i64s(rows.iter().map(|r| r.anchor_latency_secs)),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema()), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::chain::tests::chain;
+    use attestation_core::Signer;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn archive(signer: &Signer, len: u64) -> ChainArchive {
+        ChainArchive {
+            checkpoints: chain(signer.signing_key(), len),
+            rotations: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_rows_gaps_and_failures() {
+        let signer = Signer::generate();
+        let mut archive = archive(&signer, 4);
+        archive.checkpoints.remove(1);
+
+        let rows = rows(
+            &archive,
+            Box::new(signer.verifying_key()),
+            &AnchorIndex::default(),
+        );
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].verified);
+        assert_eq!(rows[0].sequence_gap, None);
+        assert_eq!(rows[1].sequence_gap, Some(1));
+        assert_eq!(rows[1].counter_gap, Some(1));
+        assert!(!rows[1].verified);
+        assert_eq!(rows[1].failure_code, Some("VB-CHK-005"));
+        // Later checkpoints are unverified but carry no failure of their own
+        assert!(!rows[2].verified);
+        assert_eq!(rows[2].failure_code, None);
+        assert_eq!(rows[2].trust_mode, "trusted");
+    }
+
+    #[test]
+    fn test_anchor_latency() {
+        let signer = Signer::generate();
+        let archive = archive(&signer, 2);
+        let first = &archive.checkpoints[0];
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("anchors.json");
+        let anchored = first.local_timestamp_utc.timestamp() + 42;
+        let anchors = serde_json::json!([
+            {"merkle_root": hex::encode(first.entries_root), "timestamp": anchored + 100},
+            {"merkle_root": hex::encode(first.entries_root), "timestamp": anchored},
+        ]);
+        std::fs::write(&path, anchors.to_string()).unwrap();
+
+        let index = AnchorIndex::load(&path).unwrap();
+        let rows = rows(&archive, Box::new(signer.verifying_key()), &index);
+        assert_eq!(rows[0].anchor_latency_secs, Some(42));
+        assert_eq!(rows[1].anchor_latency_secs, None);
+    }
+
+    #[test]
+    fn test_csv_and_parquet_share_columns() {
+        let signer = Signer::generate();
+        let rows = rows(
+            &archive(&signer, 3),
+            Box::new(signer.verifying_key()),
+            &AnchorIndex::default(),
+        );
+
+        let mut csv = Vec::new();
+        write_csv(&rows, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 4);
+        let header: Vec<&str> = csv.lines().next().unwrap().split(',').collect();
+
+        let file = tempfile::tempfile().unwrap();
+        write_parquet(&rows, file.try_clone().unwrap()).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+        assert_eq!(batches[0].num_rows(), 3);
+        let columns: Vec<&str> = batches[0]
+            .schema_ref()
+            .fields()
+            .iter()
+            .map(|f| f.name().as_str())
+            .collect();
+        assert_eq!(columns, header);
+    }
+}
@@ -0,0 +1,496 @@
+//! Cross-language interop vectors behind `veribot vectors`.
+//!
+//! Where the known-answer suite ([`attestation_core::KatSuite`]) pins byte
+//! encodings, interop vectors pin *verdicts*: each case is a set of raw input
+//! files (checkpoints, chains, Merkle proofs, SGX quotes) plus the decision a
+//! verifier must reach, including the error code when it rejects. The Go and
+//! TypeScript verifier ports load the same directory and compare their own
+//! verdicts with `manifest.json`.
+//!
+//! ```text
+//! <dir>/manifest.json        version, cases and expected verdicts
+//! <dir>/checkpoints/*.cbor   canonical CBOR of signed checkpoints
+//! <dir>/proofs/*.cbor        canonical CBOR of Merkle proofs
+//! <dir>/quotes/*.bin         raw SGX quotes
+//! ```
+//!
+//! Everything is generated from fixed keys and timestamps, so exporting twice
+//! yields identical files.
+
+use anyhow::{bail, Context, Result};
+use attestation_core::serialization::{from_canonical_cbor, to_canonical_cbor};
+use attestation_core::{
+    ChainVerifier, Checkpoint, CheckpointBuilder, DeterminismConfig, DigestAlgorithm, Entry,
+    ErrorCode, ErrorCoded, MerkleProof, MerkleTree, MissionId, ModelProvenance, RobotId,
+    SigningKey, VerifyingKey,
+};
+use attestation_sgx::quote::parse_sgx_quote_v3;
+use chrono::{Duration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Interop vector format version; bumped whenever a case's meaning changes.
+pub const INTEROP_VERSION: u32 = 2;
+
+/// `manifest.json`: every case with its inputs and expected verdict.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    /// Ed25519 public key (hex) that signed every checkpoint
+    pub public_key: String,
+    pub cases: Vec<Case>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Case {
+    pub name: String,
+    pub kind: CaseKind,
+    /// Input files relative to the vector directory
+    pub inputs: Vec<String>,
+    pub expected: Verdict,
+}
+
+/// How a case's inputs are interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseKind {
+    /// One checkpoint: decode, check the signature against `public_key`
+    Checkpoint,
+    /// Checkpoints in order: verify as a chain trusting `public_key`
+    Chain,
+    /// A Merkle proof and the checkpoint it is checked against
+    Proof,
+    /// An SGX quote: parse
+    Quote,
+}
+
+/// The decision a verifier must reach.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verdict {
+    pub valid: bool,
+    /// Error code of the rejection, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    /// Case output when valid: checkpoint hash, chain head hash, proven
+    /// leaf hash or quote MRENCLAVE (hex)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+impl Verdict {
+    fn accept(value: impl AsRef<[u8]>) -> Self {
+        Self {
+            valid: true,
+            code: None,
+            value: Some(hex::encode(value)),
+        }
+    }
+
+    fn reject(code: Option<ErrorCode>) -> Self {
+        Self {
+            valid: false,
+            code,
+            value: None,
+        }
+    }
+}
+
+/// One case checked against this implementation.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseResult {
+    pub name: String,
+    pub expected: Verdict,
+    pub actual: Verdict,
+    pub passed: bool,
+}
+
+/// Write the reference vectors to `dir` and return the manifest.
+pub fn export(dir: &Path) -> Result<Manifest> {
+    let key = SigningKey::from_bytes(&[0x42; 32]);
+    let mut writer = Writer {
+        dir,
+        cases: Vec::new(),
+    };
+
+    // Checkpoints
+    let chain = reference_chain(&key, 3)?;
+    let genesis = writer.file("checkpoints/genesis.cbor", &chain[0].0.to_bytes()?)?;
+    writer.case(
+        "checkpoint-valid",
+        CaseKind::Checkpoint,
+        vec![genesis.clone()],
+        Verdict::accept(chain[0].0.compute_hash()?),
+    );
+
+    let mut tampered = chain[0].0.clone();
+    tampered.sequence += 1;
+    let file = writer.file("checkpoints/tampered-sequence.cbor", &tampered.to_bytes()?)?;
+    writer.case(
+        "checkpoint-tampered",
+        CaseKind::Checkpoint,
+        vec![file],
+        Verdict::reject(Some(ErrorCode::InvalidSignature)),
+    );
+
+    let other = SigningKey::from_bytes(&[0x43; 32]);
+    let foreign = reference_chain(&other, 1)?;
+    let file = writer.file("checkpoints/foreign-signer.cbor", &foreign[0].0.to_bytes()?)?;
+    writer.case(
+        "checkpoint-foreign-signer",
+        CaseKind::Checkpoint,
+        vec![file],
+        Verdict::reject(Some(ErrorCode::KeyIdMismatch)),
+    );
+
+    let sha3 = chain_checkpoint(&key, Some(&chain[0].0), 2, DigestAlgorithm::Sha3_256)?;
+    let file = writer.file("checkpoints/sha3-256.cbor", &sha3.0.to_bytes()?)?;
+    writer.case(
+        "checkpoint-sha3-256",
+        CaseKind::Checkpoint,
+        vec![file],
+        Verdict::accept(sha3.0.compute_hash()?),
+    );
+
+    let mut truncated = chain[0].0.to_bytes()?;
+    truncated.truncate(truncated.len() / 2);
+    let file = writer.file("checkpoints/truncated.cbor", &truncated)?;
+    writer.case(
+        "checkpoint-truncated",
+        CaseKind::Checkpoint,
+        vec![file],
+        Verdict::reject(Some(ErrorCode::Decode)),
+    );
+
+    // Chains
+    let mut links = vec![genesis];
+    for (i, (checkpoint, _)) in chain.iter().enumerate().skip(1) {
+        links.push(writer.file(
+            &format!("checkpoints/chain-{i}.cbor"),
+            &checkpoint.to_bytes()?,
+        )?);
+    }
+    writer.case(
+        "chain-valid",
+        CaseKind::Chain,
+        links.clone(),
+        Verdict::accept(chain[2].0.compute_hash()?),
+    );
+    writer.case(
+        "chain-gap",
+        CaseKind::Chain,
+        vec![links[0].clone(), links[2].clone()],
+        Verdict::reject(Some(ErrorCode::SequenceGap)),
+    );
+    writer.case(
+        "chain-replay",
+        CaseKind::Chain,
+        vec![links[0].clone(), links[1].clone(), links[1].clone()],
+        Verdict::reject(Some(ErrorCode::SequenceRegression)),
+    );
+
+    // Proofs
+    let (_, tree) = &chain[1];
+    let proof = tree
+        .generate_proof(entry_time(1, 2), 2)
+        .context("entry missing from reference tree")?;
+    let proof_file = writer.file("proofs/entry.cbor", &to_canonical_cbor(&proof)?)?;
+    let anchor = links[1].clone();
+    writer.case(
+        "proof-valid",
+        CaseKind::Proof,
+        vec![proof_file.clone(), anchor.clone()],
+        Verdict::accept(proof.leaf.hash()),
+    );
+
+    let mut forged = proof.clone();
+    forged.leaf.data_hash[0] ^= 0xff;
+    let file = writer.file("proofs/forged-leaf.cbor", &to_canonical_cbor(&forged)?)?;
+    writer.case(
+        "proof-forged-leaf",
+        CaseKind::Proof,
+        vec![file, anchor],
+        Verdict::reject(None),
+    );
+    writer.case(
+        "proof-wrong-checkpoint",
+        CaseKind::Proof,
+        vec![proof_file, links[2].clone()],
+        Verdict::reject(None),
+    );
+
+    // Quotes
+    let mut quote = vec![0u8; 48 + 384 + 4];
+    quote[0] = 3;
+    quote[48 + 64..48 + 96].copy_from_slice(&[0xab; 32]);
+    let file = writer.file("quotes/v3-minimal.bin", &quote)?;
+    writer.case(
+        "quote-v3",
+        CaseKind::Quote,
+        vec![file],
+        Verdict::accept([0xab; 32]),
+    );
+
+    let mut v4 = quote.clone();
+    v4[0] = 4;
+    let file = writer.file("quotes/unsupported-version.bin", &v4)?;
+    writer.case(
+        "quote-unsupported-version",
+        CaseKind::Quote,
+        vec![file],
+        Verdict::reject(Some(ErrorCode::InvalidQuote)),
+    );
+
+    let file = writer.file("quotes/truncated.bin", &quote[..100])?;
+    writer.case(
+        "quote-truncated",
+        CaseKind::Quote,
+        vec![file],
+        Verdict::reject(Some(ErrorCode::InvalidQuote)),
+    );
+
+    let manifest = Manifest {
+        version: INTEROP_VERSION,
+        public_key: hex::encode(key.verifying_key().as_bytes()),
+        cases: std::mem::take(&mut writer.cases),
+    };
+    writer.file(
+        "manifest.json",
+        (serde_json::to_string_pretty(&manifest)? + "\n").as_bytes(),
+    )?;
+    Ok(manifest)
+}
+
+/// Load `dir`'s manifest and check every case against this implementation.
+pub fn check(dir: &Path) -> Result<(Manifest, Vec<CaseResult>)> {
+    let path = dir.join("manifest.json");
+    let json =
+        std::fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    let manifest: Manifest =
+        serde_json::from_str(&json).with_context(|| format!("decoding {}", path.display()))?;
+    if manifest.version != INTEROP_VERSION {
+        bail!("unsupported interop vector version {}", manifest.version);
+    }
+    let key_bytes: [u8; 32] = hex::decode(&manifest.public_key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .context("manifest public_key is not 32 hex-encoded bytes")?;
+    let key = VerifyingKey::from_bytes(&key_bytes)
+        .context("manifest public_key is not an Ed25519 key")?;
+
+    let mut results = Vec::new();
+    for case in &manifest.cases {
+        let inputs = case
+            .inputs
+            .iter()
+            .map(|input| {
+                let path = dir.join(input);
+                std::fs::read(&path).with_context(|| format!("reading {}", path.display()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let actual =
+            evaluate(case.kind, &inputs, &key).with_context(|| format!("case {:?}", case.name))?;
+        results.push(CaseResult {
+            name: case.name.clone(),
+            passed: actual == case.expected,
+            expected: case.expected.clone(),
+            actual,
+        });
+    }
+    Ok((manifest, results))
+}
+
+/// This implementation's verdict on one case.
+fn evaluate(kind: CaseKind, inputs: &[Vec<u8>], key: &VerifyingKey) -> Result<Verdict> {
+    let verdict = match kind {
+        CaseKind::Checkpoint => {
+            let [bytes] = inputs else {
+                bail!("expected one input")
+            };
+            match Checkpoint::from_bytes(bytes) {
+                Err(e) => Verdict::reject(Some(e.code())),
+                Ok(checkpoint) => match checkpoint.verify_signature(key) {
+                    Err(e) => Verdict::reject(Some(e.code())),
+                    Ok(()) => Verdict::accept(checkpoint.compute_hash()?),
+                },
+            }
+        }
+        CaseKind::Chain => {
+            let checkpoints = inputs
+                .iter()
+                .map(|bytes| Checkpoint::from_bytes(bytes))
+                .collect::<Result<Vec<_>, _>>()?;
+            let Some(head) = checkpoints.last() else {
+                bail!("expected at least one input")
+            };
+            match ChainVerifier::new(*key).verify_chain(&checkpoints) {
+                Err(e) => Verdict::reject(Some(e.code())),
+                Ok(()) => Verdict::accept(head.compute_hash()?),
+            }
+        }
+        CaseKind::Proof => {
+            let [proof, checkpoint] = inputs else {
+                bail!("expected a proof and a checkpoint")
+            };
+            let proof: MerkleProof = from_canonical_cbor(proof)?;
+            let checkpoint = Checkpoint::from_bytes(checkpoint)?;
+            if checkpoint.includes_entry(&proof) {
+                Verdict::accept(proof.leaf.hash_with(proof.hash_alg))
+            } else {
+                Verdict::reject(None)
+            }
+        }
+        CaseKind::Quote => {
+            let [bytes] = inputs else {
+                bail!("expected one input")
+            };
+            match parse_sgx_quote_v3(bytes) {
+                Err(e) => Verdict::reject(Some(e.code())),
+                Ok(quote) => Verdict::accept(quote.mr_enclave),
+            }
+        }
+    };
+    Ok(verdict)
+}
+
+struct Writer<'a> {
+    dir: &'a Path,
+    cases: Vec<Case>,
+}
+
+impl Writer<'_> {
+    /// Write `bytes` to `name` under the vector directory; returns `name`.
+    fn file(&self, name: &str, bytes: &[u8]) -> Result<String> {
+        let path = self.dir.join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating {}", parent.display()))?;
+        }
+        std::fs::write(&path, bytes).with_context(|| format!("writing {}", path.display()))?;
+        Ok(name.to_string())
+    }
+
+    fn case(&mut self, name: &str, kind: CaseKind, inputs: Vec<String>, expected: Verdict) {
+        self.cases.push(Case {
+            name: name.to_string(),
+            kind,
+            inputs,
+            expected,
+        });
+    }
+}
+
+/// Timestamp of entry `i` of checkpoint `sequence`.
+fn entry_time(sequence: u64, i: u64) -> u64 {
+    1_735_689_600_000_000 + sequence * 60_000_000 + i * 1000
+}
+
+/// `count` SHA-256 checkpoints of one chain, each with its entries tree.
+fn reference_chain(key: &SigningKey, count: u64) -> Result<Vec<(Checkpoint, MerkleTree)>> {
+    let mut chain: Vec<(Checkpoint, MerkleTree)> = Vec::new();
+    for sequence in 0..count {
+        let prev = chain.last().map(|(checkpoint, _)| checkpoint);
+        chain.push(chain_checkpoint(
+            key,
+            prev,
+            sequence,
+            DigestAlgorithm::Sha256,
+        )?);
+    }
+    Ok(chain)
+}
+
+fn chain_checkpoint(
+    key: &SigningKey,
+    prev: Option<&Checkpoint>,
+    sequence: u64,
+    hash_alg: DigestAlgorithm,
+) -> Result<(Checkpoint, MerkleTree)> {
+    let mut tree = MerkleTree::new().with_hash_alg(hash_alg);
+    for i in 0..4 {
+        tree.insert(Entry::new(
+            entry_time(sequence, i),
+            i,
+            format!("record {sequence}/{i}").as_bytes(),
+        ));
+    }
+    let builder = match prev {
+        Some(prev) => CheckpointBuilder::continuing_from(prev)?,
+        None => CheckpointBuilder::new()
+            .robot_id(RobotId("R-INTEROP".to_string()))
+            .mission_id(MissionId("M-INTEROP".to_string()))
+            .sequence(0)
+            .model_provenance(ModelProvenance {
+                name: "interop-model".to_string(),
+                model_hash: [0x11; 32],
+                dataset_hash: None,
+                container_digest: None,
+                signature_bundle: None,
+            })
+            .firmware_hash([0x22; 32])
+            .enclave_measurement(vec![0x33; 32])
+            .prev_root([0u8; 32])
+            .inference_config(DeterminismConfig {
+                rng_seed: Some(7),
+                batch_size: 1,
+                flags: None,
+            }),
+    };
+    let at = Utc.timestamp_opt(1_735_689_600, 0).unwrap() + Duration::minutes(sequence as i64 + 1);
+    let checkpoint = builder
+        .hash_alg(hash_alg)
+        .monotonic_counter(sequence + 1)
+        .timestamp(at)
+        .entries_root(tree.root())
+        .build_and_sign(key)?;
+    Ok((checkpoint, tree))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMITTED: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../test-vectors/interop-v2");
+
+    #[test]
+    fn test_committed_vectors_match() {
+        let (committed, results) = check(Path::new(COMMITTED)).unwrap();
+        for result in &results {
+            assert!(
+                result.passed,
+                "{}: expected {:?}, got {:?}",
+                result.name, result.expected, result.actual
+            );
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let fresh = export(dir.path()).unwrap();
+        assert_eq!(fresh, committed);
+        for case in &fresh.cases {
+            for input in &case.inputs {
+                let ours = std::fs::read(dir.path().join(input)).unwrap();
+                let theirs = std::fs::read(Path::new(COMMITTED).join(input)).unwrap();
+                assert_eq!(ours, theirs, "{input} differs from the committed vector");
+            }
+        }
+    }
+
+    #[test]
+    fn test_wrong_verdict_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manifest = export(dir.path()).unwrap();
+        manifest.cases[1].expected = Verdict::reject(Some(ErrorCode::KeyIdMismatch));
+        std::fs::write(
+            dir.path().join("manifest.json"),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+
+        let (_, results) = check(dir.path()).unwrap();
+        let failed: Vec<_> = results
+            .iter()
+            .filter(|r| !r.passed)
+            .map(|r| r.name.as_str())
+            .collect();
+        assert_eq!(failed, ["checkpoint-tampered"]);
+    }
+}
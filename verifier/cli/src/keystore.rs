@@ -0,0 +1,296 @@
+//! Passphrase-encrypted keystore files.
+//!
+//! A keystore is a JSON document holding one secret key, encrypted with
+//! ChaCha20-Poly1305 under a key derived from the passphrase with Argon2id.
+//! The algorithm and public key are stored in the clear (and bound to the
+//! ciphertext as associated data) so `veribot key show` works without the
+//! passphrase.
+
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use attestation_core::crypto::sha256;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// Keystore format version
+pub const KEYSTORE_VERSION: u8 = 1;
+
+/// Signature algorithm of a stored key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyAlgorithm {
+    /// Ed25519 (checkpoint signing)
+    Ed25519,
+    /// ECDSA P-256 (TEE-native keys)
+    P256,
+}
+
+impl fmt::Display for KeyAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyAlgorithm::Ed25519 => write!(f, "ed25519"),
+            KeyAlgorithm::P256 => write!(f, "p256"),
+        }
+    }
+}
+
+/// A decrypted secret key.
+pub enum SecretKey {
+    Ed25519(ed25519_dalek::SigningKey),
+    P256(p256::ecdsa::SigningKey),
+}
+
+impl SecretKey {
+    /// Generate a fresh random key.
+    pub fn generate(algorithm: KeyAlgorithm) -> Self {
+        match algorithm {
+            KeyAlgorithm::Ed25519 => {
+                SecretKey::Ed25519(ed25519_dalek::SigningKey::generate(&mut OsRng))
+            }
+            KeyAlgorithm::P256 => SecretKey::P256(p256::ecdsa::SigningKey::random(&mut OsRng)),
+        }
+    }
+
+    /// Load a key from its raw 32-byte secret scalar/seed.
+    pub fn from_bytes(algorithm: KeyAlgorithm, bytes: &[u8]) -> Result<Self> {
+        let seed: [u8; 32] = bytes.try_into().map_err(|_| {
+            anyhow!(
+                "{algorithm} secret key must be 32 bytes, got {}",
+                bytes.len()
+            )
+        })?;
+        Ok(match algorithm {
+            KeyAlgorithm::Ed25519 => {
+                SecretKey::Ed25519(ed25519_dalek::SigningKey::from_bytes(&seed))
+            }
+            KeyAlgorithm::P256 => SecretKey::P256(
+                p256::ecdsa::SigningKey::from_bytes(&seed.into())
+                    .map_err(|_| anyhow!("invalid P-256 secret scalar"))?,
+            ),
+        })
+    }
+
+    pub fn algorithm(&self) -> KeyAlgorithm {
+        match self {
+            SecretKey::Ed25519(_) => KeyAlgorithm::Ed25519,
+            SecretKey::P256(_) => KeyAlgorithm::P256,
+        }
+    }
+
+    /// Raw secret bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            SecretKey::Ed25519(key) => key.to_bytes().to_vec(),
+            SecretKey::P256(key) => key.to_bytes().to_vec(),
+        }
+    }
+
+    /// Public key bytes (Ed25519: 32 bytes; P-256: SEC1 compressed point).
+    pub fn public_key(&self) -> Vec<u8> {
+        match self {
+            SecretKey::Ed25519(key) => key.verifying_key().to_bytes().to_vec(),
+            SecretKey::P256(key) => key
+                .verifying_key()
+                .to_encoded_point(true)
+                .as_bytes()
+                .to_vec(),
+        }
+    }
+}
+
+/// Key fingerprint: SHA-256 of the public key bytes (matches `KeyId` for Ed25519).
+pub fn fingerprint(public_key: &[u8]) -> String {
+    hex::encode(sha256(public_key))
+}
+
+/// Argon2id cost parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory cost in KiB
+    pub m_cost: u32,
+    /// Iterations
+    pub t_cost: u32,
+    /// Parallelism
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// On-disk keystore.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u8,
+    pub algorithm: KeyAlgorithm,
+    /// Hex public key
+    pub public_key: String,
+    /// Hex SHA-256 fingerprint of the public key
+    pub key_id: String,
+    pub kdf: KdfParams,
+    /// Hex Argon2 salt
+    pub salt: String,
+    /// Hex ChaCha20-Poly1305 nonce
+    pub nonce: String,
+    /// Hex encrypted secret key
+    pub ciphertext: String,
+}
+
+impl Keystore {
+    /// Encrypt `secret` under `passphrase`.
+    pub fn encrypt(secret: &SecretKey, passphrase: &str, kdf: KdfParams) -> Result<Self> {
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let public_key = secret.public_key();
+        let mut keystore = Self {
+            version: KEYSTORE_VERSION,
+            algorithm: secret.algorithm(),
+            public_key: hex::encode(&public_key),
+            key_id: fingerprint(&public_key),
+            kdf,
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: String::new(),
+        };
+
+        let cipher = keystore.cipher(passphrase)?;
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &secret.to_bytes(),
+                    aad: keystore.associated_data().as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow!("keystore encryption failed"))?;
+        keystore.ciphertext = hex::encode(ciphertext);
+        Ok(keystore)
+    }
+
+    /// Decrypt the secret key.
+    pub fn decrypt(&self, passphrase: &str) -> Result<SecretKey> {
+        if self.version != KEYSTORE_VERSION {
+            bail!("unsupported keystore version {}", self.version);
+        }
+        let nonce = hex::decode(&self.nonce).context("invalid keystore nonce")?;
+        if nonce.len() != 12 {
+            bail!("invalid keystore nonce length");
+        }
+        let ciphertext = hex::decode(&self.ciphertext).context("invalid keystore ciphertext")?;
+
+        let plaintext = self
+            .cipher(passphrase)?
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: self.associated_data().as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow!("wrong passphrase or corrupted keystore"))?;
+
+        let secret = SecretKey::from_bytes(self.algorithm, &plaintext)?;
+        if hex::encode(secret.public_key()) != self.public_key {
+            bail!("keystore public key does not match its secret key");
+        }
+        Ok(secret)
+    }
+
+    /// Read a keystore file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let json =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("parsing keystore {}", path.display()))
+    }
+
+    /// Write a keystore file, refusing to overwrite unless `force` is set.
+    pub fn save(&self, path: &Path, force: bool) -> Result<()> {
+        if path.exists() && !force {
+            bail!(
+                "{} already exists (use --force to overwrite)",
+                path.display()
+            );
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n").with_context(|| format!("writing {}", path.display()))
+    }
+
+    fn cipher(&self, passphrase: &str) -> Result<ChaCha20Poly1305> {
+        let salt = hex::decode(&self.salt).context("invalid keystore salt")?;
+        let params = Params::new(self.kdf.m_cost, self.kdf.t_cost, self.kdf.p_cost, Some(32))
+            .map_err(|e| anyhow!("invalid KDF parameters: {e}"))?;
+
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| anyhow!("key derivation failed: {e}"))?;
+        Ok(ChaCha20Poly1305::new(&key.into()))
+    }
+
+    /// Metadata bound to the ciphertext.
+    fn associated_data(&self) -> String {
+        format!(
+            "veribot-keystore-v{}:{}:{}",
+            self.version, self.algorithm, self.public_key
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST_KDF: KdfParams = KdfParams {
+        m_cost: 256,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    #[test]
+    fn test_roundtrip_both_algorithms() {
+        for algorithm in [KeyAlgorithm::Ed25519, KeyAlgorithm::P256] {
+            let secret = SecretKey::generate(algorithm);
+            let keystore = Keystore::encrypt(&secret, "hunter2", FAST_KDF).unwrap();
+            assert_eq!(keystore.key_id, fingerprint(&secret.public_key()));
+
+            let json = serde_json::to_string(&keystore).unwrap();
+            let loaded: Keystore = serde_json::from_str(&json).unwrap();
+            let decrypted = loaded.decrypt("hunter2").unwrap();
+            assert_eq!(decrypted.to_bytes(), secret.to_bytes());
+        }
+    }
+
+    #[test]
+    fn test_wrong_passphrase_and_tampered_metadata() {
+        let secret = SecretKey::generate(KeyAlgorithm::Ed25519);
+        let mut keystore = Keystore::encrypt(&secret, "hunter2", FAST_KDF).unwrap();
+        assert!(keystore.decrypt("hunter3").is_err());
+
+        keystore.public_key = hex::encode(SecretKey::generate(KeyAlgorithm::Ed25519).public_key());
+        assert!(keystore.decrypt("hunter2").is_err());
+    }
+
+    #[test]
+    fn test_ed25519_fingerprint_matches_key_id() {
+        let secret = SecretKey::generate(KeyAlgorithm::Ed25519);
+        let SecretKey::Ed25519(key) = &secret else {
+            unreachable!()
+        };
+        let key_id = attestation_core::crypto::key_id(&key.verifying_key());
+        assert_eq!(fingerprint(&secret.public_key()), key_id.to_string());
+    }
+}
@@ -0,0 +1,132 @@
+//! `veribot`: command-line tools for robot attestation.
+//!
+//! ## Commands
+//! - `key`: keystore management (generate, import/export, rotate)
+//! - `chain audit`: full-chain verification with a JSON report
+//! - `proof verify`: entry -> root -> signature check for disclosed evidence
+//! - `quote decode`: SGX quote fields, optionally with full verification
+//! - `watch`: minimal agent that checkpoints a log stream with a local keystore
+//! - `gc`: delete anchored entries from an agent directory, keeping root history
+//! - `export`: one row per checkpoint as CSV or Parquet for fleet analytics
+//! - `import`: backfill historical chain archives into a checkpoint store
+//! - `notary`: periodic re-notarization of archived evidence
+//! - `kat`: known-answer test vectors for third-party implementations
+//! - `vectors`: interop vectors with expected verdicts for verifier ports
+//! - `conformance`: byte-level canonical encoding check of vendor-produced CBOR
+//!
+//! Every command accepts `--output text|json|cbor`; see [`output`] for the
+//! machine-readable envelope.
+//!
+//! ## Exit codes
+//! - `0`: success
+//! - `1`: verification failed
+//! - `2`: the command could not run (bad arguments, unreadable input)
+
+mod agent;
+mod archive;
+mod commands;
+mod export;
+mod interop;
+mod keystore;
+mod output;
+mod pubkey;
+mod redaction;
+
+use clap::{Parser, Subcommand};
+use output::OutputFormat;
+use std::process::ExitCode;
+
+#[derive(Debug, Parser)]
+#[command(name = "veribot", version, about = "Robot attestation tools")]
+struct Cli {
+    /// Output format
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Manage signing keys and keystores
+    #[command(subcommand)]
+    Key(commands::key::KeyCommand),
+
+    /// Audit checkpoint chains
+    #[command(subcommand)]
+    Chain(commands::chain::ChainCommand),
+
+    /// Verify selective-disclosure proofs
+    #[command(subcommand)]
+    Proof(commands::proof::ProofCommand),
+
+    /// Inspect attestation quotes
+    #[command(subcommand)]
+    Quote(commands::quote::QuoteCommand),
+
+    /// Checkpoint a log stream with a local keystore
+    Watch(commands::watch::WatchArgs),
+
+    /// Drop anchored entries, keeping a provable root history
+    Gc(commands::gc::GcArgs),
+
+    /// Export checkpoint chains as analytics tables
+    Export(commands::export::ExportArgs),
+
+    /// Backfill historical chain archives into a store
+    Import(commands::import::ImportArgs),
+
+    /// Re-notarize archived evidence
+    #[command(subcommand)]
+    Notary(commands::notary::NotaryCommand),
+
+    /// Generate or check known-answer test vectors
+    #[command(subcommand)]
+    Kat(commands::kat::KatCommand),
+
+    /// Export or check cross-language interop vectors
+    #[command(subcommand)]
+    Vectors(commands::vectors::VectorsCommand),
+
+    /// Check vendor-produced checkpoint or entry CBOR for canonical encoding
+    Conformance(commands::conformance::ConformanceArgs),
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Key(command) => commands::key::run(command, cli.output),
+        Command::Chain(command) => commands::chain::run(command, cli.output),
+        Command::Proof(command) => commands::proof::run(command, cli.output),
+        Command::Quote(command) => commands::quote::run(command, cli.output),
+        Command::Watch(args) => commands::watch::run(args, cli.output),
+        Command::Gc(args) => commands::gc::run(args, cli.output),
+        Command::Export(args) => commands::export::run(args, cli.output),
+        Command::Import(args) => commands::import::run(args, cli.output),
+        Command::Notary(command) => commands::notary::run(command, cli.output),
+        Command::Kat(command) => commands::kat::run(command, cli.output),
+        Command::Vectors(command) => commands::vectors::run(command, cli.output),
+        Command::Conformance(args) => commands::conformance::run(args, cli.output),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+}
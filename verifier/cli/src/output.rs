@@ -0,0 +1,96 @@
+//! Output formats shared by all commands.
+//!
+//! Every command produces a [`Report`]. In `text` mode it is rendered for
+//! humans; in `json` and `cbor` mode it is wrapped in an envelope naming its
+//! schema, e.g. `{"schema": "veribot.chain.audit/v1", "result": {...}}`.
+//! Fields are only ever added to a schema version; renaming or removing one
+//! bumps the version.
+
+use anyhow::Result;
+use attestation_core::serialization::to_canonical_cbor;
+use serde::Serialize;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// Pretty-printed JSON
+    Json,
+    /// Canonical CBOR (binary)
+    Cbor,
+}
+
+/// A command result with a stable machine-readable schema.
+pub trait Report: Serialize {
+    /// Schema identifier, `veribot.<command>/v<N>`
+    const SCHEMA: &'static str;
+
+    /// Print the human-readable form to stdout.
+    fn write_text(&self);
+}
+
+#[derive(Serialize)]
+struct Envelope<'a, R> {
+    schema: &'static str,
+    result: &'a R,
+}
+
+/// Print `report` to stdout in `format`.
+pub fn emit<R: Report>(format: OutputFormat, report: &R) -> Result<()> {
+    let envelope = Envelope {
+        schema: R::SCHEMA,
+        result: report,
+    };
+    match format {
+        OutputFormat::Text => report.write_text(),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&envelope)?),
+        OutputFormat::Cbor => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(&to_canonical_cbor(&envelope)?)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// JSON form of `report` in its envelope (for files written alongside stdout).
+pub fn to_json<R: Report>(report: &R) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&Envelope {
+        schema: R::SCHEMA,
+        result: report,
+    })?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use attestation_core::serialization::from_canonical_cbor;
+
+    #[derive(Serialize)]
+    struct Sample {
+        value: u32,
+    }
+
+    impl Report for Sample {
+        const SCHEMA: &'static str = "veribot.sample/v1";
+        fn write_text(&self) {}
+    }
+
+    #[test]
+    fn test_envelope_schema() {
+        let json: serde_json::Value =
+            serde_json::from_str(&to_json(&Sample { value: 7 }).unwrap()).unwrap();
+        assert_eq!(json["schema"], "veribot.sample/v1");
+        assert_eq!(json["result"]["value"], 7);
+
+        let cbor = to_canonical_cbor(&Envelope {
+            schema: Sample::SCHEMA,
+            result: &Sample { value: 7 },
+        })
+        .unwrap();
+        let decoded: serde_json::Value = from_canonical_cbor(&cbor).unwrap();
+        assert_eq!(decoded, json);
+    }
+}
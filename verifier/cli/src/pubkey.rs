@@ -0,0 +1,72 @@
+//! Loading Ed25519 verifying keys from files.
+//!
+//! Accepted formats: a PEM `PUBLIC KEY` (SubjectPublicKeyInfo), a hex-encoded
+//! 32-byte key (as printed by `veribot key export`), or a keystore file.
+
+use crate::keystore::{KeyAlgorithm, Keystore};
+use anyhow::{anyhow, bail, Context, Result};
+use ed25519_dalek::VerifyingKey;
+use std::path::Path;
+
+/// DER prefix of an Ed25519 SubjectPublicKeyInfo (RFC 8410).
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+/// Read an Ed25519 verifying key from `path`.
+pub fn load_verifying_key(path: &Path) -> Result<VerifyingKey> {
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    parse_verifying_key(&contents).with_context(|| format!("loading key {}", path.display()))
+}
+
+fn parse_verifying_key(contents: &str) -> Result<VerifyingKey> {
+    let contents = contents.trim();
+    let bytes = if contents.starts_with("-----BEGIN") {
+        let pem = pem::parse(contents)?;
+        if pem.tag() != "PUBLIC KEY" {
+            bail!("expected a PUBLIC KEY PEM block, found {}", pem.tag());
+        }
+        pem.contents()
+            .strip_prefix(&ED25519_SPKI_PREFIX[..])
+            .ok_or_else(|| anyhow!("not an Ed25519 public key"))?
+            .to_vec()
+    } else if contents.starts_with('{') {
+        let keystore: Keystore = serde_json::from_str(contents)?;
+        if keystore.algorithm != KeyAlgorithm::Ed25519 {
+            bail!(
+                "keystore holds a {} key, expected ed25519",
+                keystore.algorithm
+            );
+        }
+        hex::decode(&keystore.public_key)?
+    } else {
+        hex::decode(contents).context("expected PEM, hex or a keystore")?
+    };
+
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| anyhow!("Ed25519 public key must be 32 bytes"))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| anyhow!("invalid Ed25519 public key"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use attestation_core::Signer;
+
+    #[test]
+    fn test_parse_pem_and_hex() {
+        let key = Signer::generate().verifying_key();
+
+        let mut der = ED25519_SPKI_PREFIX.to_vec();
+        der.extend_from_slice(key.as_bytes());
+        let pem = pem::encode(&pem::Pem::new("PUBLIC KEY", der));
+        assert_eq!(parse_verifying_key(&pem).unwrap(), key);
+
+        let hex = format!("{}\n", hex::encode(key.as_bytes()));
+        assert_eq!(parse_verifying_key(&hex).unwrap(), key);
+
+        assert!(parse_verifying_key("abcd").is_err());
+    }
+}
@@ -0,0 +1,233 @@
+//! Pre-hash redaction of agent records.
+//!
+//! Some records must not be committed as captured: camera frames show
+//! bystanders, and full-precision GPS tracks locate people's homes. A
+//! redaction policy lists JSON fields to transform before a record is hashed
+//! into the Merkle tree, so the committed (and retained) payload is already
+//! redacted. The policy's hash is recorded in every checkpoint
+//! (`redaction_policy`), and the agent keeps the policy itself next to the
+//! chain as `redaction-<hash>.cbor`, so auditors know exactly which
+//! transformation was applied.
+//!
+//! ```toml
+//! [[redaction]]
+//! field = "camera.bystander_regions"
+//! action = "remove"
+//!
+//! [[redaction]]
+//! field = "gps.lat"
+//! action = "round"
+//! decimals = 3
+//!
+//! [[redaction]]
+//! field = "operator.badge"
+//! action = "hash"
+//!
+//! [[redaction]]
+//! field = "speed_mm_s"
+//! action = "bucket"
+//! width = 250
+//! ```
+//!
+//! With a policy in place every record must be a JSON object; redacted
+//! records are re-serialized as compact JSON with sorted keys.
+
+use anyhow::{bail, Context, Result};
+use attestation_core::crypto::sha256;
+use attestation_core::serialization::to_canonical_cbor;
+use attestation_core::Hash256;
+use serde::{Deserialize, Serialize};
+use serde_json::{Number, Value};
+
+/// One field transformation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+pub enum RedactionRule {
+    /// Drop the field
+    Remove { field: String },
+    /// Replace the field with the hex SHA-256 of its JSON encoding
+    Hash { field: String },
+    /// Round a number to `decimals` decimal places
+    Round { field: String, decimals: u32 },
+    /// Round a number down to a multiple of `width`
+    Bucket { field: String, width: u64 },
+}
+
+impl RedactionRule {
+    /// Dotted path of the field this rule applies to.
+    pub fn field(&self) -> &str {
+        match self {
+            RedactionRule::Remove { field }
+            | RedactionRule::Hash { field }
+            | RedactionRule::Round { field, .. }
+            | RedactionRule::Bucket { field, .. } => field,
+        }
+    }
+}
+
+/// An ordered list of rules applied to every record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactionPolicy {
+    pub rules: Vec<RedactionRule>,
+}
+
+impl RedactionPolicy {
+    pub fn new(rules: Vec<RedactionRule>) -> Result<Self> {
+        for rule in &rules {
+            if rule.field().split('.').any(str::is_empty) {
+                bail!("invalid redaction field {:?}", rule.field());
+            }
+            if let RedactionRule::Bucket { width: 0, field } = rule {
+                bail!("redaction bucket width for {field:?} must be at least 1");
+            }
+        }
+        Ok(Self { rules })
+    }
+
+    /// Canonical CBOR encoding (what the hash commits to).
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(to_canonical_cbor(self)?)
+    }
+
+    /// Hash recorded in each checkpoint's `redaction_policy`.
+    pub fn hash(&self) -> Result<Hash256> {
+        Ok(sha256(&self.to_bytes()?))
+    }
+
+    /// Redact one record.
+    pub fn apply(&self, record: &[u8]) -> Result<Vec<u8>> {
+        let mut value: Value =
+            serde_json::from_slice(record).context("redaction needs JSON records")?;
+        if !value.is_object() {
+            bail!("redaction needs JSON object records");
+        }
+        for rule in &self.rules {
+            apply_rule(&mut value, rule)?;
+        }
+        Ok(serde_json::to_vec(&value)?)
+    }
+}
+
+fn apply_rule(record: &mut Value, rule: &RedactionRule) -> Result<()> {
+    let (parents, name) = match rule.field().rsplit_once('.') {
+        Some((parents, name)) => (Some(parents), name),
+        None => (None, rule.field()),
+    };
+    let mut object = record;
+    for part in parents.into_iter().flat_map(|p| p.split('.')) {
+        match object.get_mut(part) {
+            Some(child) => object = child,
+            None => return Ok(()),
+        }
+    }
+    let Some(object) = object.as_object_mut() else {
+        return Ok(());
+    };
+    let Some(value) = object.get_mut(name) else {
+        return Ok(());
+    };
+
+    match rule {
+        RedactionRule::Remove { .. } => {
+            object.remove(name);
+        }
+        RedactionRule::Hash { .. } => {
+            *value = Value::String(hex::encode(sha256(&serde_json::to_vec(value)?)));
+        }
+        RedactionRule::Round { decimals, .. } => {
+            let scale = 10f64.powi(*decimals as i32);
+            let rounded = (number(value, rule)? * scale).round() / scale;
+            *value = Number::from_f64(rounded).map_or(Value::Null, Value::Number);
+        }
+        RedactionRule::Bucket { width, .. } => {
+            let width = *width as f64;
+            let bucketed = (number(value, rule)? / width).floor() * width;
+            *value = match Number::from_f64(bucketed) {
+                // Keep integers integral so "1000" does not become "1000.0"
+                Some(_) if bucketed.fract() == 0.0 && bucketed.abs() < i64::MAX as f64 => {
+                    Value::from(bucketed as i64)
+                }
+                Some(n) => Value::Number(n),
+                None => Value::Null,
+            };
+        }
+    }
+    Ok(())
+}
+
+fn number(value: &Value, rule: &RedactionRule) -> Result<f64> {
+    value
+        .as_f64()
+        .with_context(|| format!("redaction field {:?} is not a number", rule.field()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"
+        [[redaction]]
+        field = "camera.bystander_regions"
+        action = "remove"
+
+        [[redaction]]
+        field = "gps.lat"
+        action = "round"
+        decimals = 3
+
+        [[redaction]]
+        field = "operator"
+        action = "hash"
+
+        [[redaction]]
+        field = "speed_mm_s"
+        action = "bucket"
+        width = 250
+    "#;
+
+    fn policy() -> RedactionPolicy {
+        #[derive(Deserialize)]
+        struct Config {
+            redaction: Vec<RedactionRule>,
+        }
+        RedactionPolicy::new(toml::from_str::<Config>(POLICY).unwrap().redaction).unwrap()
+    }
+
+    #[test]
+    fn test_apply_policy() {
+        let record = br#"{"camera":{"frame":7,"bystander_regions":[[1,2,3,4]]},"gps":{"lat":52.520008,"lon":13.404954},"operator":"alice","speed_mm_s":1337}"#;
+        let redacted: Value = serde_json::from_slice(&policy().apply(record).unwrap()).unwrap();
+
+        assert_eq!(redacted["camera"], serde_json::json!({"frame": 7}));
+        assert_eq!(redacted["gps"]["lat"], serde_json::json!(52.52));
+        assert_eq!(redacted["gps"]["lon"], serde_json::json!(13.404954));
+        assert_eq!(redacted["speed_mm_s"], serde_json::json!(1250));
+        assert_eq!(
+            redacted["operator"],
+            Value::String(hex::encode(sha256(br#""alice""#)))
+        );
+
+        // Missing fields are left alone; non-JSON records are refused
+        assert_eq!(policy().apply(br#"{"x":1}"#).unwrap(), br#"{"x":1}"#);
+        assert!(policy().apply(b"raw bytes").is_err());
+        assert!(policy().apply(br#"{"speed_mm_s":"fast"}"#).is_err());
+    }
+
+    #[test]
+    fn test_policy_hash_tracks_rules() {
+        let mut other = policy();
+        assert_eq!(policy().hash().unwrap(), other.hash().unwrap());
+        other.rules.pop();
+        assert_ne!(policy().hash().unwrap(), other.hash().unwrap());
+
+        let bad = RedactionRule::Bucket {
+            field: "speed".to_string(),
+            width: 0,
+        };
+        assert!(RedactionPolicy::new(vec![bad]).is_err());
+        let bad = RedactionRule::Remove {
+            field: "gps..lat".to_string(),
+        };
+        assert!(RedactionPolicy::new(vec![bad]).is_err());
+    }
+}